@@ -1,24 +1,30 @@
 use crate::{
     cli::args::{
-        BundlerAndUoPoolArgs, BundlerArgs, CreateWalletArgs, MetricsArgs, RpcArgs, StorageType,
-        UoPoolArgs,
+        BundlerAndUoPoolArgs, BundlerArgs, CreateWalletArgs, MetricsArgs, NodeConfig, RpcArgs,
+        StorageType, UoPoolArgs,
     },
     utils::unwrap_path_or_home,
 };
 use alloy_chains::{Chain, NamedChain};
 use ethers::{providers::Middleware, types::Address};
 use parking_lot::RwLock;
-use silius_bundler::{ConditionalClient, EthereumClient, FastlaneClient, FlashbotsClient};
-use silius_contracts::EntryPoint;
+use silius_bundler::{
+    ConditionalClient, EthereumClient, FastlaneClient, FlashbotsClient, RelayClient,
+};
+use silius_contracts::{discover_entry_points, EntryPoint};
 use silius_grpc::{
     bundler_client::BundlerClient, bundler_service_run, uo_pool_client::UoPoolClient,
     uopool_service_run,
 };
 use silius_mempool::{
     init_env,
-    validate::validator::{new_canonical, new_canonical_unsafe},
-    CodeHashes, DatabaseTable, EntitiesReputation, Mempool, Reputation, UserOperations,
-    UserOperationsByEntity, UserOperationsBySender, WriteMap,
+    validate::{
+        sanity::call_data::SelectorFilter,
+        validator::{new_canonical, new_canonical_unsafe},
+    },
+    AgingFeeOrdering, AuditLog, Checkpoints, CodeHashes, DatabaseTable, EntitiesReputation,
+    Mempool, Reputation, UserOperationCodeHashAct, UserOperations, UserOperationsByEntity,
+    UserOperationsByEntryPoint, UserOperationsBySender, UserOperationsMetadata, WriteMap,
 };
 use silius_metrics::{launch_metrics_exporter, mempool::MetricsHandler};
 use silius_primitives::{
@@ -36,7 +42,7 @@ use silius_primitives::{
     provider::{create_http_provider, BlockStream},
     reputation::ReputationEntry,
     simulation::CodeHash,
-    UserOperationHash, UserOperationSigned, Wallet,
+    NonceGapConfig, UserOperationHash, UserOperationSigned, UserOperationSource, Wallet,
 };
 use silius_rpc::{
     debug_api::{DebugApiServer, DebugApiServerImpl},
@@ -69,6 +75,15 @@ where
     M: Middleware + Clone + 'static,
     N: Middleware + Clone + 'static,
 {
+    let config = NodeConfig {
+        bundler: Some(&bundler_args),
+        uopool: Some(&uopool_args),
+        common: Some(&common_args),
+        rpc: Some(&rpc_args),
+        metrics: Some(&metrics_args),
+    }
+    .to_json()?;
+
     launch_uopool(
         uopool_args.clone(),
         eth_client,
@@ -94,6 +109,7 @@ where
         format!("http://{:?}:{:?}", uopool_args.uopool_addr, uopool_args.uopool_port),
         format!("http://{:?}:{:?}", bundler_args.bundler_addr, bundler_args.bundler_port),
         metrics_args.clone(),
+        config,
     )
     .await?;
 
@@ -127,9 +143,12 @@ where
     let chain_conn = Chain::from(chain_id);
 
     let wallet: Wallet;
+    // relays authenticate the requests with the Flashbots signer
+    let flashbots_key =
+        matches!(args.bundle_strategy, BundleStrategy::Flashbots | BundleStrategy::Relay);
 
     if let Some(mnemonic_file) = args.mnemonic_file {
-        if args.bundle_strategy == BundleStrategy::Flashbots {
+        if flashbots_key {
             wallet = Wallet::from_file(mnemonic_file.into(), chain_id, true)
                 .map_err(|error| eyre::format_err!("Could not load mnemonic file: {}", error))?;
             info!("Wallet Signer {:?}", wallet.signer);
@@ -140,7 +159,7 @@ where
             info!("{:?}", wallet.signer);
         }
     } else if let Some(private_key) = args.private_key {
-        if args.bundle_strategy == BundleStrategy::Flashbots {
+        if flashbots_key {
             wallet = Wallet::from_private_key(
                 private_key.as_str(),
                 chain_id,
@@ -154,7 +173,7 @@ where
             info!("Flashbots Signer {:?}", wallet.flashbots_signer);
         } else {
             if args.flashbots_private_key.is_some() {
-                info!("Flashbots key is ignored since send bundle mode is not Flashbots");
+                info!("Flashbots key is ignored since send bundle mode is not Flashbots or relay");
             }
             wallet = Wallet::from_private_key(private_key.as_str(), chain_id, false, None)
                 .map_err(|error| eyre::format_err!("Could not load from private key: {}", error))?;
@@ -178,15 +197,24 @@ where
                 wallet,
                 entry_points,
                 chain_conn,
-                args.beneficiary,
+                args.beneficiary.clone(),
                 args.min_balance,
+                args.balance_warning_threshold,
                 bundle_interval,
                 eth_client,
                 client,
                 uopool_grpc_client,
                 metrics_args.enable_metrics,
                 args.enable_access_list,
-            );
+                args.max_bundle_rebuilds,
+                args.bundle_priority_fee_margin,
+                args.bundle_gas_limit_buffer,
+                args.max_bundle_submissions,
+                args.bundle_tip_escalation,
+                args.instance,
+                args.nonce_block_tag,
+            )
+            .await;
         }
         BundleStrategy::Conditional => {
             let client = Arc::new(ConditionalClient::new(eth_client.clone(), wallet.clone()));
@@ -195,15 +223,24 @@ where
                 wallet,
                 entry_points,
                 chain_conn,
-                args.beneficiary,
+                args.beneficiary.clone(),
                 args.min_balance,
+                args.balance_warning_threshold,
                 bundle_interval,
                 eth_client,
                 client,
                 uopool_grpc_client,
                 metrics_args.enable_metrics,
                 args.enable_access_list,
-            );
+                args.max_bundle_rebuilds,
+                args.bundle_priority_fee_margin,
+                args.bundle_gas_limit_buffer,
+                args.max_bundle_submissions,
+                args.bundle_tip_escalation,
+                args.instance,
+                args.nonce_block_tag,
+            )
+            .await;
         }
         BundleStrategy::Flashbots => {
             let relay_endpoints: Vec<String> = match chain_conn
@@ -229,15 +266,56 @@ where
                 wallet,
                 entry_points,
                 chain_conn,
-                args.beneficiary,
+                args.beneficiary.clone(),
                 args.min_balance,
+                args.balance_warning_threshold,
                 bundle_interval,
                 eth_client,
                 client,
                 uopool_grpc_client,
                 metrics_args.enable_metrics,
                 args.enable_access_list,
+                args.max_bundle_rebuilds,
+                args.bundle_priority_fee_margin,
+                args.bundle_gas_limit_buffer,
+                args.max_bundle_submissions,
+                args.bundle_tip_escalation,
+                args.instance,
+                args.nonce_block_tag,
+            )
+            .await;
+        }
+        BundleStrategy::Relay => {
+            let relay_endpoint = args.relay_endpoint.ok_or_else(|| {
+                eyre::format_err!("Relay endpoint is required by the relay strategy")
+            })?;
+            let client = Arc::new(
+                RelayClient::new(eth_client.clone(), &relay_endpoint, wallet.clone())?
+                    .with_signature_header(args.relay_signature_header),
             );
+            bundler_service_run(
+                SocketAddr::new(args.bundler_addr, args.bundler_port),
+                wallet,
+                entry_points,
+                chain_conn,
+                args.beneficiary.clone(),
+                args.min_balance,
+                args.balance_warning_threshold,
+                bundle_interval,
+                eth_client,
+                client,
+                uopool_grpc_client,
+                metrics_args.enable_metrics,
+                args.enable_access_list,
+                args.max_bundle_rebuilds,
+                args.bundle_priority_fee_margin,
+                args.bundle_gas_limit_buffer,
+                args.max_bundle_submissions,
+                args.bundle_tip_escalation,
+                args.instance,
+                args.nonce_block_tag,
+            )
+            .await;
         }
         BundleStrategy::Fastlane => {
             let relay_endpoint: String =
@@ -262,15 +340,24 @@ where
                 wallet,
                 entry_points,
                 chain_conn,
-                args.beneficiary,
+                args.beneficiary.clone(),
                 args.min_balance,
+                args.balance_warning_threshold,
                 bundle_interval,
                 eth_client,
                 client,
                 uopool_grpc_client,
                 metrics_args.enable_metrics,
                 args.enable_access_list,
-            );
+                args.max_bundle_rebuilds,
+                args.bundle_priority_fee_margin,
+                args.bundle_gas_limit_buffer,
+                args.max_bundle_submissions,
+                args.bundle_tip_escalation,
+                args.instance,
+                args.nonce_block_tag,
+            )
+            .await;
         }
     }
 
@@ -299,9 +386,10 @@ where
     );
 
     let chain = Chain::from(eth_client.get_chainid().await?.as_u64());
+    let database_args = args.to_database_arguments();
     let datadir = unwrap_path_or_home(args.datadir)?;
     let p2p_config = if args.p2p_opts.enable_p2p {
-        Some(args.p2p_opts.to_config(&chain, &datadir))
+        Some(args.p2p_opts.to_config(&chain, &datadir)?)
     } else {
         None
     };
@@ -311,20 +399,46 @@ where
         Address::from_str(entry_point::ADDRESS).expect("address should be valid"),
     );
 
+    let nonce_gap = args.max_nonce_gap.map(|max_gap| NonceGapConfig {
+        max_gap,
+        max_held: args.max_gapped_user_operations,
+        ttl: Duration::from_secs(args.gapped_user_operation_ttl),
+    });
+
     let (mempool, reputation) = match args.storage_type {
         StorageType::Database => {
             let env = Arc::new(
-                init_env::<WriteMap>(datadir.join(DATABASE_FOLDER_NAME)).expect("Init mdbx failed"),
+                init_env::<WriteMap>(datadir.join(DATABASE_FOLDER_NAME), database_args)
+                    .expect("Init mdbx failed"),
             );
             env.create_tables().expect("Create mdbx database tables failed");
+            let code_hashes: Box<dyn UserOperationCodeHashAct> =
+                match args.code_hashes_storage_type.unwrap_or(StorageType::Database) {
+                    StorageType::Database => {
+                        Box::new(DatabaseTable::<WriteMap, CodeHashes>::new(env.clone()))
+                    }
+                    StorageType::Memory => {
+                        Box::new(Arc::new(RwLock::new(
+                            HashMap::<UserOperationHash, Vec<CodeHash>>::default(),
+                        )))
+                    }
+                };
+            let uo_ops =
+                MetricsHandler::new(DatabaseTable::<WriteMap, UserOperations>::new(env.clone()));
             let mempool = Mempool::new(
-                Box::new(MetricsHandler::new(DatabaseTable::<WriteMap, UserOperations>::new(
-                    env.clone(),
-                ))),
+                Box::new(uo_ops.clone()),
                 Box::new(DatabaseTable::<WriteMap, UserOperationsBySender>::new(env.clone())),
                 Box::new(DatabaseTable::<WriteMap, UserOperationsByEntity>::new(env.clone())),
-                Box::new(DatabaseTable::<WriteMap, CodeHashes>::new(env.clone())),
-            );
+                code_hashes,
+            )
+            .with_metadata(Box::new(DatabaseTable::<WriteMap, UserOperationsMetadata>::new(
+                env.clone(),
+            )))
+            .with_entry_point_index(Box::new(
+                DatabaseTable::<WriteMap, UserOperationsByEntryPoint>::new(env.clone()),
+            ))
+            .with_checkpoints(Box::new(DatabaseTable::<WriteMap, Checkpoints>::new(env.clone())))
+            .with_batch_remover(Box::new(uo_ops));
             let mut reputation = Reputation::new(
                 MIN_INCLUSION_RATE_DENOMINATOR,
                 THROTTLING_SLACK,
@@ -336,13 +450,20 @@ where
                 Box::new(MetricsHandler::new(DatabaseTable::<WriteMap, EntitiesReputation>::new(
                     env.clone(),
                 ))),
-            );
+            )
+            .with_grace_period(args.reputation_grace_period)
+            .with_probation_period(args.reputation_probation_period);
             for whiteaddr in args.whitelist.iter() {
                 reputation.add_whitelist(whiteaddr);
             }
             (mempool, reputation)
         }
         StorageType::Memory => {
+            if let Some(StorageType::Database) = args.code_hashes_storage_type {
+                return Err(eyre::eyre!(
+                    "Code hashes can't be persisted in the database with memory storage"
+                ));
+            }
             let mempool = Mempool::new(
                 Box::new(Arc::new(RwLock::new(MetricsHandler::new(HashMap::<
                     UserOperationHash,
@@ -370,10 +491,32 @@ where
                     Address,
                     ReputationEntry,
                 >::default())))),
-            );
+            )
+            .with_grace_period(args.reputation_grace_period)
+            .with_probation_period(args.reputation_probation_period);
             (mempool, reputation)
         }
     };
+    let mempool = match args.p2p_max_uos_per_sender {
+        Some(max) => mempool.with_max_uos_per_sender(UserOperationSource::P2p, max),
+        None => mempool,
+    };
+    let mempool = match args.audit_log {
+        Some(path) => mempool.with_audit_log(AuditLog::open(path, args.audit_log_hmac_key)?),
+        None => mempool,
+    };
+    let mempool = match args.priority_aging_boost {
+        Some(boost_per_second) => mempool.with_ordering(AgingFeeOrdering {
+            boost_per_second,
+            max_boost: args.priority_aging_max_boost,
+        }),
+        None => mempool,
+    };
+    let mempool = mempool.with_subsidized(args.subsidized);
+    let mut trusted_factory_opcodes = HashMap::<Address, HashSet<String>>::new();
+    for (factory, opcode) in args.trusted_factory_opcodes.iter() {
+        trusted_factory_opcodes.entry(*factory).or_default().insert(opcode.clone());
+    }
     match args.uopool_mode {
         silius_primitives::UoPoolMode::Standard => {
             let validator = new_canonical(
@@ -381,21 +524,56 @@ where
                 chain,
                 args.max_verification_gas,
                 args.min_priority_fee_per_gas,
-            );
+            )
+            .with_downgraded_violations(args.downgrade_trace_violations.iter().copied().collect())
+            .with_signature_hash_verification(args.verify_signature_hash)
+            .with_strict_opcodes(args.strict_opcodes)
+            .with_trusted_factory_opcodes(trusted_factory_opcodes)
+            .with_acceptance_window(args.acceptance_window)
+            .with_prefund_policy(args.prefund_policy)
+            .with_subsidized(args.subsidized)
+            .with_priority_fee_cap(!args.allow_priority_fee_above_max_fee)
+            .with_ep_min_priority_fees(args.ep_min_priority_fee_per_gas.into_iter().collect())
+            .with_max_simulation_gas(args.max_simulation_gas)
+            .with_entry_point_simulations_code(args.entry_point_simulations_code.clone())
+            .with_selector_filter(SelectorFilter::new(
+                args.allowed_selectors,
+                args.denied_selectors,
+            ))
+            .with_staked_paymaster_required(args.require_staked_paymaster)
+            .with_staked_aggregator_required(args.require_staked_aggregator)
+            .with_eip7702_senders(args.eip7702_senders)
+            .with_replacement_fee_increase(args.replacement_fee_increase)
+            .with_min_call_gas(args.min_call_gas);
 
             uopool_service_run(
                 SocketAddr::new(args.uopool_addr, args.uopool_port),
                 args.uopool_mode,
                 entry_points,
+                args.entry_point_version,
                 eth_client,
                 block_streams,
                 chain,
                 args.max_verification_gas,
+                args.valid_after_policy,
+                args.max_delayed_user_operations,
+                args.replacement_policy,
+                args.replacement_fee_increase,
+                args.max_tracked_paymasters,
+                args.simulate_bundle,
+                args.max_user_operations,
+                nonce_gap,
+                args.verification_gas_buffer,
+                args.chain_split_pause,
+                args.max_dump_size,
+                args.simulation_concurrency,
+                args.acceptance_window.is_some(),
                 mempool,
                 reputation,
                 validator,
                 p2p_config,
                 metrics_args.enable_metrics,
+                args.scan_depth,
             )
             .await?;
             info!("Started uopool gRPC service at {:?}:{:?}", args.uopool_addr, args.uopool_port);
@@ -406,20 +584,51 @@ where
                 chain,
                 args.max_verification_gas,
                 args.min_priority_fee_per_gas,
-            );
+            )
+            .with_acceptance_window(args.acceptance_window)
+            .with_prefund_policy(args.prefund_policy)
+            .with_subsidized(args.subsidized)
+            .with_priority_fee_cap(!args.allow_priority_fee_above_max_fee)
+            .with_ep_min_priority_fees(args.ep_min_priority_fee_per_gas.into_iter().collect())
+            .with_max_simulation_gas(args.max_simulation_gas)
+            .with_entry_point_simulations_code(args.entry_point_simulations_code.clone())
+            .with_selector_filter(SelectorFilter::new(
+                args.allowed_selectors,
+                args.denied_selectors,
+            ))
+            .with_staked_paymaster_required(args.require_staked_paymaster)
+            .with_staked_aggregator_required(args.require_staked_aggregator)
+            .with_eip7702_senders(args.eip7702_senders)
+            .with_replacement_fee_increase(args.replacement_fee_increase)
+            .with_min_call_gas(args.min_call_gas);
             uopool_service_run(
                 SocketAddr::new(args.uopool_addr, args.uopool_port),
                 args.uopool_mode,
                 entry_points,
+                args.entry_point_version,
                 eth_client,
                 block_streams,
                 chain,
                 args.max_verification_gas,
+                args.valid_after_policy,
+                args.max_delayed_user_operations,
+                args.replacement_policy,
+                args.replacement_fee_increase,
+                args.max_tracked_paymasters,
+                args.simulate_bundle,
+                args.max_user_operations,
+                nonce_gap,
+                args.verification_gas_buffer,
+                args.chain_split_pause,
+                args.max_dump_size,
+                args.simulation_concurrency,
+                args.acceptance_window.is_some(),
                 mempool,
                 reputation,
                 validator,
                 p2p_config,
                 metrics_args.enable_metrics,
+                args.scan_depth,
             )
             .await?;
             info!("Started uopool gRPC service at {:?}:{:?}", args.uopool_addr, args.uopool_port);
@@ -434,6 +643,7 @@ pub async fn launch_rpc(
     uopool_grpc_listen_address: String,
     bundler_grpc_listen_address: String,
     metrics_args: MetricsArgs,
+    config: serde_json::Value,
 ) -> eyre::Result<()> {
     if !args.is_enabled() {
         return Err(eyre::eyre!("No RPC protocol is enabled"));
@@ -450,12 +660,28 @@ pub async fn launch_rpc(
         args.ws_port,
     )
     .with_cors(&args.http_corsdomain, JsonRpcServerType::Http)
-    .with_cors(&args.ws_origins, JsonRpcServerType::Ws);
+    .with_cors(&args.ws_origins, JsonRpcServerType::Ws)
+    .with_max_subscriptions_per_connection(args.ws_max_subscriptions);
 
     if let Some(eth_client_proxy_address) = args.eth_client_proxy_address.clone() {
         server = server.with_proxy(eth_client_proxy_address);
     }
 
+    if args.http_compression {
+        server = server.with_compression();
+    }
+
+    if !args.api_keys.is_empty() {
+        server = server.with_api_keys(
+            args.api_keys.iter().cloned().collect(),
+            Duration::from_secs(args.api_key_period),
+        );
+    }
+
+    if let Some(admin_token) = args.admin_token.clone() {
+        server = server.with_admin_token(admin_token);
+    }
+
     if metrics_args.enable_metrics {
         info!("Enabling json rpc server metrics.");
         server = server.with_metrics()
@@ -478,13 +704,21 @@ pub async fn launch_rpc(
     if args.is_api_method_enabled("eth") {
         if http_api.contains("eth") {
             server.add_methods(
-                EthApiServerImpl { uopool_grpc_client: uopool_grpc_client.clone() }.into_rpc(),
+                EthApiServerImpl {
+                    uopool_grpc_client: uopool_grpc_client.clone(),
+                    deny_unknown_fields: args.deny_unknown_fields,
+                }
+                .into_rpc(),
                 JsonRpcServerType::Http,
             )?;
         }
         if ws_api.contains("eth") {
             server.add_methods(
-                EthApiServerImpl { uopool_grpc_client: uopool_grpc_client.clone() }.into_rpc(),
+                EthApiServerImpl {
+                    uopool_grpc_client: uopool_grpc_client.clone(),
+                    deny_unknown_fields: args.deny_unknown_fields,
+                }
+                .into_rpc(),
                 JsonRpcServerType::Ws,
             )?;
         }
@@ -500,6 +734,7 @@ pub async fn launch_rpc(
                 DebugApiServerImpl {
                     uopool_grpc_client: uopool_grpc_client.clone(),
                     bundler_grpc_client: bundler_grpc_client.clone(),
+                    config: Some(config.clone()),
                 }
                 .into_rpc(),
                 JsonRpcServerType::Http,
@@ -508,7 +743,12 @@ pub async fn launch_rpc(
 
         if ws_api.contains("debug") {
             server.add_methods(
-                DebugApiServerImpl { uopool_grpc_client, bundler_grpc_client }.into_rpc(),
+                DebugApiServerImpl {
+                    uopool_grpc_client,
+                    bundler_grpc_client,
+                    config: Some(config),
+                }
+                .into_rpc(),
                 JsonRpcServerType::Ws,
             )?;
         }
@@ -544,6 +784,45 @@ pub fn create_wallet(args: CreateWalletArgs) -> eyre::Result<()> {
     Ok(())
 }
 
+/// Returns the configured entry points, with the entry points discovered in the recent blocks if
+/// their adoption is enabled (otherwise the discovered entry points are only suggested). Only the
+/// discovered contracts which pass the entry point probes are adopted.
+pub async fn resolve_entry_points<M>(
+    eth_client: Arc<M>,
+    args: &BundlerAndUoPoolArgs,
+) -> eyre::Result<Vec<Address>>
+where
+    M: Middleware + Clone + 'static,
+{
+    let mut entry_points = args.entry_points.clone();
+    let Some(blocks) = args.entry_point_discovery_blocks else {
+        return Ok(entry_points);
+    };
+
+    info!("Scanning the last {blocks} blocks for entry points...");
+    for (ep, events) in discover_entry_points(eth_client.clone(), blocks).await? {
+        if entry_points.contains(&ep) {
+            continue;
+        }
+
+        if args.adopt_discovered_entry_points {
+            match EntryPoint::new(eth_client.clone(), ep).verify().await {
+                Ok(version) => {
+                    info!("Adopting discovered entry point {ep:?} {version:?} ({events} user operation events)");
+                    entry_points.push(ep);
+                }
+                Err(err) => warn!(
+                    "Not adopting discovered emitter {ep:?} ({events} user operation events), it doesn't behave like an entry point: {err:?}"
+                ),
+            }
+        } else {
+            info!("Discovered entry point {ep:?} ({events} user operation events), add it with --entry-points or --adopt-discovered-entry-points");
+        }
+    }
+
+    Ok(entry_points)
+}
+
 async fn check_connected_chain<M>(
     eth_client: Arc<M>,
     chain: Option<NamedChain>,
@@ -572,3 +851,76 @@ where
 
     Ok(eth_client.client_version().await?)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use ethers::{
+        abi::AbiEncode,
+        providers::{JsonRpcError, MockResponse, Provider},
+        types::{Bytes, Log, U256, U64},
+    };
+    use silius_contracts::UserOperationEventFilter;
+
+    #[tokio::test]
+    async fn resolve_discovered_entry_points() {
+        let (provider, mock) = Provider::mocked();
+        let eth_client = Arc::new(provider);
+        let configured = Address::random();
+        let entry_point = Address::random();
+        let fake = Address::random();
+        let logs = [(configured, 1), (entry_point, 3), (fake, 2)]
+            .into_iter()
+            .flat_map(|(address, events)| {
+                vec![
+                    Log {
+                        address,
+                        topics: vec![UserOperationEventFilter::signature()],
+                        ..Default::default()
+                    };
+                    events
+                ]
+            })
+            .collect::<Vec<_>>();
+        let args = |adopt: bool| {
+            let configured = format!("{configured:?}");
+            let mut args = vec![
+                "bundleranduopoolargs",
+                "--entry-points",
+                &configured,
+                "--entry-point-discovery-blocks",
+                "100",
+            ];
+            if adopt {
+                args.push("--adopt-discovered-entry-points");
+            }
+            BundlerAndUoPoolArgs::try_parse_from(args).unwrap()
+        };
+
+        // the discovered entry points are only suggested by default (responses are popped in the
+        // reverse order)
+        mock.push(logs.clone()).unwrap();
+        mock.push(U64::from(1_000)).unwrap();
+        assert_eq!(
+            resolve_entry_points(eth_client.clone(), &args(false)).await.unwrap(),
+            vec![configured]
+        );
+
+        // once adopted, only the emitters which behave like an entry point are added (the fake one
+        // reverts on `getNonce`)
+        mock.push_response(MockResponse::Error(JsonRpcError {
+            code: 3,
+            message: "execution reverted".into(),
+            data: None,
+        }));
+        mock.push(Bytes::from(U256::one().encode())).unwrap();
+        mock.push(Bytes::from(U256::zero().encode())).unwrap();
+        mock.push(logs).unwrap();
+        mock.push(U64::from(1_000)).unwrap();
+        assert_eq!(
+            resolve_entry_points(eth_client, &args(true)).await.unwrap(),
+            vec![configured, entry_point]
+        );
+    }
+}
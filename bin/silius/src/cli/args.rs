@@ -1,27 +1,50 @@
 use crate::utils::{
-    parse_address, parse_bundle_strategy, parse_duration, parse_enr, parse_label_value, parse_u256,
-    parse_uopool_mode,
+    parse_address, parse_api_key, parse_bundle_strategy, parse_bundler_instance, parse_bytes,
+    parse_duration, parse_enr, parse_entry_point_fee, parse_entry_point_version,
+    parse_factory_opcode, parse_label_value, parse_nonce_block_tag, parse_prefund_policy,
+    parse_replacement_policy, parse_selector, parse_trace_violation, parse_u256, parse_uopool_mode,
+    parse_valid_after_policy, serialize_api_keys, serialize_datadir, serialize_display_opt,
+    serialize_display_seq, serialize_redacted, serialize_redacted_required, serialize_selectors,
 };
 use alloy_chains::{Chain, NamedChain};
 use clap::{ArgGroup, Parser, ValueEnum};
 use discv5::Enr;
-use ethers::types::{Address, U256};
+use ethers::{
+    abi::Selector,
+    types::{Address, BlockNumber, Bytes, U256},
+};
 use expanded_pathbuf::ExpandedPathBuf;
+use serde::Serialize;
+use silius_contracts::EntryPointVersion;
+use silius_mempool::{validate::TraceViolation, DatabaseArguments};
 use silius_metrics::label::LabelValue;
 use silius_p2p::{
-    config::{gossipsub_config, Config, ConfigBuilder},
+    config::{gossipsub_config_with_mesh_params, Config, ConfigBuilder, GossipsubMeshParams},
     listen_addr::{ListenAddr, ListenAddress},
 };
 use silius_primitives::{
-    bundler::BundleStrategy,
+    bundler::{BundleStrategy, BundlerInstance},
     chain::ChainSpec,
     constants::{
-        bundler::BUNDLE_INTERVAL,
+        bundler::{
+            BUNDLE_GAS_LIMIT_BUFFER, BUNDLE_INTERVAL, BUNDLE_TIP_ESCALATION, MAX_BUNDLE_REBUILDS,
+            MAX_BUNDLE_SUBMISSIONS, RELAY_SIGNATURE_HEADER,
+        },
         grpc::{BUNDLER_PORT, MEMPOOL_PORT},
-        p2p::{NODE_ENR_FILE_NAME, NODE_KEY_FILE_NAME},
-        rpc::{HTTP_PORT, WS_PORT},
+        mempool::{
+            GAPPED_USER_OPERATION_TTL, GAS_INCREASE_PERC, LATEST_SCAN_DEPTH,
+            MAX_DELAYED_USER_OPERATIONS, MAX_GAPPED_USER_OPERATIONS, MAX_TRACKED_PAYMASTERS,
+            PRIORITY_AGING_MAX_BOOST, SIMULATION_CONCURRENCY, VERIFICATION_GAS_BUFFER_PERC,
+        },
+        p2p::{
+            GOSSIP_LAZY, GOSSIP_MESH_N, GOSSIP_MESH_N_HIGH, GOSSIP_MESH_N_LOW, NODE_ENR_FILE_NAME,
+            NODE_KEY_FILE_NAME, SEEN_CACHE_SIZE,
+        },
+        rpc::{API_KEY_RATE_PERIOD, HTTP_PORT, MAX_SUBSCRIPTIONS_PER_CONNECTION, WS_PORT},
+        storage::{DATABASE_GROWTH_STEP, DATABASE_INITIAL_SIZE, DATABASE_MAX_SIZE},
+        validation::MIN_CALL_GAS_LIMIT,
     },
-    UoPoolMode,
+    PrefundPolicy, ReplacementPolicy, UoPoolMode, ValidAfterPolicy,
 };
 use std::{
     net::{IpAddr, Ipv4Addr, SocketAddr},
@@ -29,14 +52,15 @@ use std::{
     time::Duration,
 };
 
-#[derive(ValueEnum, Debug, Clone)]
+#[derive(ValueEnum, Debug, Clone, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum StorageType {
     Database,
     Memory,
 }
 
 /// Bundler CLI args
-#[derive(Debug, Clone, Parser, PartialEq)]
+#[derive(Debug, Clone, Parser, PartialEq, Serialize)]
 #[clap(group(ArgGroup::new("account").required(true).args(&["mnemonic_file", "private_key"])))]
 pub struct BundlerArgs {
     /// Bundler gRPC address to listen on.
@@ -53,22 +77,31 @@ pub struct BundlerArgs {
 
     /// Private key for the wallet
     #[clap(long, group = "account")]
+    #[serde(serialize_with = "serialize_redacted")]
     pub private_key: Option<String>,
 
     /// Flashbots private key
     #[clap(long, conflicts_with = "mnemonic_file")]
+    #[serde(serialize_with = "serialize_redacted")]
     pub flashbots_private_key: Option<String>,
 
-    /// The bundler beneficiary address.
-    #[clap(long, value_parser=parse_address)]
-    pub beneficiary: Address,
+    /// The bundler beneficiary addresses (comma-separated), paid in round-robin order.
+    #[clap(long, required = true, value_delimiter=',', value_parser=parse_address)]
+    pub beneficiary: Vec<Address>,
 
-    /// The minimum balance required for the beneficiary address.
+    /// The minimum balance of the bundler account (bundling is paused below it).
     ///
     /// By default, this option is set to `100000000000000000`.
     #[clap(long, default_value = "100000000000000000", value_parser=parse_u256)]
     pub min_balance: U256,
 
+    /// The balance of the bundler account below which a warning is logged (and the gas is
+    /// refunded to the bundler instead of the beneficiary).
+    ///
+    /// By default, this option is set to the minimum balance.
+    #[clap(long, value_parser=parse_u256)]
+    pub balance_warning_threshold: Option<U256>,
+
     /// Whether the bundler should send bundles manually.
     ///
     /// By default, this option is set to false.
@@ -93,15 +126,77 @@ pub struct BundlerArgs {
     ///
     /// By default, this will be the same as `eth-client-address`
     #[clap(long)]
+    #[serde(serialize_with = "serialize_redacted")]
     pub eth_client_bundle_address: Option<String>,
 
+    /// The relay endpoint accepting `eth_sendBundle` requests (used by the `relay` bundle
+    /// strategy).
+    #[clap(long, required_if_eq("bundle_strategy", "relay"))]
+    #[serde(serialize_with = "serialize_redacted")]
+    pub relay_endpoint: Option<String>,
+
+    /// The header carrying the signature of the requests sent to the relay.
+    ///
+    /// By default, this option is set to `X-Flashbots-Signature`.
+    #[clap(long, default_value = RELAY_SIGNATURE_HEADER)]
+    pub relay_signature_header: String,
+
     /// Indicates whether the access list is enabled.
     #[clap(long)]
     pub enable_access_list: bool,
+
+    /// Maximum number of times a bundle is rebuilt after dropping user operations with invalid
+    /// nonce (e.g., already included by a competing bundler).
+    ///
+    /// By default, this option is set to 3. Set to 0 to disable the recovery.
+    #[clap(long, default_value_t = MAX_BUNDLE_REBUILDS)]
+    pub max_bundle_rebuilds: usize,
+
+    /// Percentage added to the lowest priority fee of the user operations in a bundle, which is
+    /// used as the priority fee of the bundle transaction.
+    ///
+    /// By default, this option is set to 0 (the bundler keeps the whole spread).
+    #[clap(long, default_value = "0")]
+    pub bundle_priority_fee_margin: u64,
+
+    /// Percentage added to the gas of the bundle transaction estimated by the provider, which is
+    /// used as the gas limit of the bundle transaction.
+    ///
+    /// By default, this option is set to 0.
+    #[clap(long, default_value_t = BUNDLE_GAS_LIMIT_BUFFER)]
+    pub bundle_gas_limit_buffer: u64,
+
+    /// Maximum number of submissions of a bundle which isn't included in the target block (e.g.,
+    /// by the Flashbots relay). Each submission targets the next block.
+    ///
+    /// By default, this option is set to 3. Set to 1 to disable the resubmission.
+    #[clap(long, default_value_t = MAX_BUNDLE_SUBMISSIONS)]
+    pub max_bundle_submissions: usize,
+
+    /// Percentage by which the priority fee of a resubmitted bundle is increased (never above the
+    /// max fee of the bundle).
+    ///
+    /// By default, this option is set to 20.
+    #[clap(long, default_value_t = BUNDLE_TIP_ESCALATION)]
+    pub bundle_tip_escalation: u64,
+
+    /// Position of the bundler among the instances sharing a mempool (e.g., behind a load
+    /// balancer), in the format `<index>/<count>`. User operations are assigned to the instances
+    /// by their sender, so they aren't bundled by multiple instances.
+    #[clap(long, value_parser=parse_bundler_instance)]
+    pub instance: Option<BundlerInstance>,
+
+    /// Block tag used to fetch the nonce of the bundler account (`latest` or `pending`).
+    /// Providers differ on whether the pending block includes the transactions in their mempool,
+    /// so the nonces of the bundles sent before are also tracked locally.
+    ///
+    /// By default, this option is set to `latest`.
+    #[clap(long, default_value = "latest", value_parser=parse_nonce_block_tag)]
+    pub nonce_block_tag: BlockNumber,
 }
 
 /// UoPool CLI args
-#[derive(Debug, Clone, Parser)]
+#[derive(Debug, Clone, Parser, Serialize)]
 pub struct UoPoolArgs {
     /// UoPool gRPC address to listen on.
     #[clap(long = "uopool.addr", default_value_t = IpAddr::V4(Ipv4Addr::LOCALHOST))]
@@ -113,6 +208,7 @@ pub struct UoPoolArgs {
 
     /// Data directory (primarily for database).
     #[clap(long)]
+    #[serde(serialize_with = "serialize_datadir")]
     pub datadir: Option<ExpandedPathBuf>,
 
     /// The storage type which is used for mempool and repution
@@ -120,6 +216,24 @@ pub struct UoPoolArgs {
     #[clap(value_enum, default_value_t = StorageType::Database)]
     pub storage_type: StorageType,
 
+    /// The storage type which is used for the code hashes of user operations (defaults to the
+    /// storage type of the mempool). Code hashes can be kept in memory while the user operations
+    /// are persisted in the database (they are lost on restart).
+    #[clap(long, value_enum)]
+    pub code_hashes_storage_type: Option<StorageType>,
+
+    /// Initial size of the database (in bytes).
+    #[clap(long = "db.initial-size", default_value_t = DATABASE_INITIAL_SIZE)]
+    pub db_initial_size: usize,
+
+    /// Maximum size of the database (in bytes).
+    #[clap(long = "db.max-size", default_value_t = DATABASE_MAX_SIZE)]
+    pub db_max_size: usize,
+
+    /// Step by which the database grows when it's full (in bytes).
+    #[clap(long = "db.growth-step", default_value_t = DATABASE_GROWTH_STEP)]
+    pub db_growth_step: usize,
+
     /// Max allowed verification gas.
     #[clap(long, default_value="5000000", value_parser=parse_u256)]
     pub max_verification_gas: U256,
@@ -132,6 +246,11 @@ pub struct UoPoolArgs {
     #[clap(long, value_parser=parse_u256, default_value = "0")]
     pub min_priority_fee_per_gas: U256,
 
+    /// Minimum priority fee per gas of specific entry points (overrides
+    /// `min-priority-fee-per-gas`), in the format `<entry point address>=<min priority fee>`.
+    #[clap(long, value_delimiter=',', value_parser=parse_entry_point_fee)]
+    pub ep_min_priority_fee_per_gas: Vec<(Address, U256)>,
+
     /// Addresses of whitelisted entities.
     #[clap(long, value_delimiter=',', value_parser = parse_address)]
     pub whitelist: Vec<Address>,
@@ -140,26 +259,252 @@ pub struct UoPoolArgs {
     #[clap(long, default_value = "standard", value_parser=parse_uopool_mode)]
     pub uopool_mode: UoPoolMode,
 
+    /// Handling of user operations which aren't valid yet (`validAfter` in the future): `queue`
+    /// holds them until `validAfter` passes, `reject` rejects them.
+    #[clap(long, default_value = "queue", value_parser=parse_valid_after_policy)]
+    pub valid_after_policy: ValidAfterPolicy,
+
+    /// Maximum number of user operations held until their `validAfter` passes.
+    #[clap(long, default_value_t = MAX_DELAYED_USER_OPERATIONS)]
+    pub max_delayed_user_operations: usize,
+
+    /// Precedence between the sources of user operations replacing each other (same sender and
+    /// nonce): `prefer-local` requires the ones received over the p2p network to bump the fees
+    /// twice as much to replace the ones submitted via the RPC API, `equal` treats them equally.
+    #[clap(long, default_value = "prefer-local", value_parser=parse_replacement_policy)]
+    pub replacement_policy: ReplacementPolicy,
+
+    /// Minimum increase (percentage) of both the max fee per gas and the max priority fee per gas
+    /// required to replace a user operation with the same sender and nonce.
+    #[clap(long, default_value_t = GAS_INCREASE_PERC)]
+    pub replacement_fee_increase: u64,
+
+    /// Version of the entry points (`0.6` or `0.7`) used if it can't be detected by probing them
+    /// (otherwise determined from their addresses).
+    #[clap(long, value_parser=parse_entry_point_version)]
+    #[serde(serialize_with = "serialize_display_opt")]
+    pub entry_point_version: Option<EntryPointVersion>,
+
+    /// Hex encoded deployed code of `EntryPointSimulations`, which the user operations of the
+    /// entry points v0.7 are simulated with (they can't be validated if not set).
+    #[clap(long, value_parser=parse_bytes)]
+    pub entry_point_simulations_code: Option<Bytes>,
+
+    /// Maximum number of paymaster deposits tracked while building a bundle (least recently used
+    /// deposits are evicted and re-fetched).
+    #[clap(long, default_value_t = MAX_TRACKED_PAYMASTERS)]
+    pub max_tracked_paymasters: usize,
+
+    /// Simulates the whole bundle (`handleOps` call) as the final step of bundling and drops the
+    /// user operations which revert because of the preceding ones in the bundle.
+    #[clap(long)]
+    pub simulate_bundle: bool,
+
+    /// Maximum number of user operations in the mempool of each entry point. Once it's full, the
+    /// user operation with the lowest max priority fee per gas is evicted to make room for a user
+    /// operation paying more.
+    #[clap(long)]
+    pub max_user_operations: Option<usize>,
+
+    /// Maximum distance of the nonce of a user operation from the next nonce of the sender. If
+    /// set, user operations with a nonce gap are held (instead of rejected) until the user
+    /// operations filling the gap are added.
+    #[clap(long)]
+    pub max_nonce_gap: Option<u64>,
+
+    /// Maximum number of user operations held until their nonce gap is filled.
+    #[clap(long, default_value_t = MAX_GAPPED_USER_OPERATIONS)]
+    pub max_gapped_user_operations: usize,
+
+    /// Time (in seconds) after which a user operation held because of a nonce gap is dropped.
+    #[clap(long, default_value_t = GAPPED_USER_OPERATION_TTL)]
+    pub gapped_user_operation_ttl: u64,
+
+    /// Percentage added to the estimated verification gas limit, so user operations don't revert
+    /// if the state changes slightly between the estimation and the submission (disabled by
+    /// default).
+    #[clap(long, default_value_t = VERIFICATION_GAS_BUFFER_PERC)]
+    pub verification_gas_buffer: u64,
+
+    /// Number of blocks without a chain split (a block at an already seen height with a
+    /// different hash) before bundling resumes after one. Bundling isn't paused if not set.
+    #[clap(long)]
+    pub chain_split_pause: Option<u64>,
+
+    /// Maximum number of user operations returned by a single mempool dump (larger mempools are
+    /// paged through). The whole mempool is dumped if not set.
+    #[clap(long)]
+    pub max_dump_size: Option<usize>,
+
+    /// Number of user operations simulated concurrently while building a bundle.
+    #[clap(long, default_value_t = SIMULATION_CONCURRENCY)]
+    pub simulation_concurrency: usize,
+
+    /// Simulation trace rule violations which are downgraded to warnings (for private mempools).
+    /// Storage access and entity association violations can't be downgraded.
+    /// Possible values: `opcode`, `out-of-gas`, `call-stack`, `code-hashes`.
+    #[clap(long, value_delimiter=',', value_parser=parse_trace_violation)]
+    pub downgrade_trace_violations: Vec<TraceViolation>,
+
+    /// Verifies that the account's `validateUserOp` is called with the full user operation hash
+    /// (guards against accounts accepting signatures over a partial hash).
+    #[clap(long)]
+    pub verify_signature_hash: bool,
+
+    /// Rejects user operations using the opcodes forbidden only by the latest ERC-7562 rules
+    /// (e.g., `BLOBHASH`, `BLOBBASEFEE`), on top of the canonical ones.
+    #[clap(long)]
+    pub strict_opcodes: bool,
+
+    /// Otherwise banned opcodes allowed in the frames of trusted factories (for private
+    /// mempools), in the format `<factory address>=<opcode>` (e.g., `0x...=GAS`).
+    #[clap(long, value_delimiter=',', value_parser=parse_factory_opcode)]
+    pub trusted_factory_opcodes: Vec<(Address, String)>,
+
+    /// Funds of the sender required to cover the prefund of user operations without a paymaster,
+    /// checked before the simulation.
+    /// Possible values: `disabled`, `deposit` (entry point deposit), `deposit-and-balance`.
+    #[clap(long, default_value = "disabled", value_parser=parse_prefund_policy)]
+    pub prefund_policy: PrefundPolicy,
+
+    /// Max number of seconds after the latest block within which user operations are accepted
+    /// (rejects user operations which would be validated against stale state). User operations in
+    /// the mempool are then re-validated on every new block.
+    #[clap(long)]
+    pub acceptance_window: Option<u64>,
+
+    /// Accepts user operations with zero priority fee (or lower than `min-priority-fee-per-gas`)
+    /// and max fee below the base fee (the bundler pays the difference), for bundlers
+    /// intentionally running at a loss on subsidized chains.
+    #[clap(long)]
+    pub subsidized: bool,
+
+    /// Accepts user operations whose max priority fee per gas exceeds the max fee per gas (invalid
+    /// per EIP-1559, rejected by default).
+    #[clap(long)]
+    pub allow_priority_fee_above_max_fee: bool,
+
+    /// Max gas of the validation simulation. User operations whose gas fields add up to more are
+    /// rejected before being simulated.
+    #[clap(long, value_parser=parse_u256)]
+    pub max_simulation_gas: Option<U256>,
+
+    /// Function selectors which user operations are allowed to call (inner calls of the account's
+    /// `execute`/`executeBatch` are inspected). If set, user operations calling other selectors
+    /// are rejected.
+    #[clap(long, value_delimiter=',', value_parser=parse_selector)]
+    #[serde(serialize_with = "serialize_selectors")]
+    pub allowed_selectors: Vec<Selector>,
+
+    /// Function selectors which user operations are not allowed to call.
+    #[clap(long, value_delimiter=',', value_parser=parse_selector)]
+    #[serde(serialize_with = "serialize_selectors")]
+    pub denied_selectors: Vec<Selector>,
+
+    /// Rejects user operations whose paymaster is neither staked nor whitelisted (stricter than
+    /// the reputation throttling of unstaked paymasters).
+    #[clap(long)]
+    pub require_staked_paymaster: bool,
+
+    /// Rejects aggregated user operations whose aggregator is neither staked nor whitelisted.
+    #[clap(long)]
+    pub require_staked_aggregator: bool,
+
+    /// Accepts EOAs delegated to a contract (EIP-7702) as deployed senders even if the initCode
+    /// carries the authorization. The authorization is dropped before the user operation is hashed
+    /// (the entry point can't apply it, so the delegation has to be in place already).
+    #[clap(long)]
+    pub eip7702_senders: bool,
+
+    /// Min call gas limit of user operations (user operations with a lower call gas limit would
+    /// revert in execution). Defaults to the cost of a CALL with non-zero value.
+    #[clap(long, value_parser=parse_u256, default_value_t = U256::from(MIN_CALL_GAS_LIMIT))]
+    pub min_call_gas: U256,
+
+    /// Number of blocks scanned at once for past entry point events. On startup, the blocks
+    /// produced since the last processed block (persisted in the database) are scanned, or the
+    /// latest ones if it's unknown (e.g., with the memory storage).
+    #[clap(long, default_value_t = LATEST_SCAN_DEPTH)]
+    pub scan_depth: u64,
+
+    /// Number of seconds since a new entity was first seen during which it isn't throttled or
+    /// banned (regardless of its inclusion ratio).
+    #[clap(long, default_value = "0")]
+    pub reputation_grace_period: u64,
+
+    /// Number of seconds an entity recovering from throttling (or a ban) is on probation. Entities
+    /// on probation have a smaller mempool and bundle allowance than OK entities, but aren't
+    /// throttled (disabled if 0).
+    #[clap(long, default_value = "0")]
+    pub reputation_probation_period: u64,
+
+    /// Maximum number of user operations per sender received over the p2p network
+    /// (user operations submitted via RPC are not affected).
+    #[clap(long)]
+    pub p2p_max_uos_per_sender: Option<usize>,
+
+    /// Path of the append-only audit log of admitted user operations (disabled if not set).
+    #[clap(long)]
+    pub audit_log: Option<PathBuf>,
+
+    /// Hex encoded key used to sign the audit log records with HMAC-SHA256.
+    #[clap(long = "audit-log.hmac-key", requires = "audit_log", value_parser=parse_bytes)]
+    #[serde(serialize_with = "serialize_redacted")]
+    pub audit_log_hmac_key: Option<Bytes>,
+
+    /// Priority fee boost (in wei per second of waiting) of the user operations in the mempool,
+    /// so low-fee user operations aren't starved by higher-fee ones (disabled if not set).
+    #[clap(long, value_parser=parse_u256)]
+    pub priority_aging_boost: Option<U256>,
+
+    /// Maximum priority fee boost (in wei) of the user operations waiting in the mempool, so fees
+    /// still take precedence over the waiting time.
+    #[clap(long, value_parser=parse_u256, default_value_t = U256::from(PRIORITY_AGING_MAX_BOOST))]
+    pub priority_aging_max_boost: U256,
+
     /// P2P configuration
     #[clap(flatten)]
     pub p2p_opts: P2PArgs,
 }
 
+impl UoPoolArgs {
+    /// Convert the database related args to [DatabaseArguments]
+    pub fn to_database_arguments(&self) -> DatabaseArguments {
+        DatabaseArguments {
+            initial_size: self.db_initial_size,
+            max_size: self.db_max_size,
+            growth_step: self.db_growth_step,
+        }
+    }
+}
+
 /// Common CLI args for bundler and uopool
-#[derive(Debug, Clone, Parser, PartialEq)]
+#[derive(Debug, Clone, Parser, PartialEq, Serialize)]
 pub struct BundlerAndUoPoolArgs {
     /// Ethereum execution client RPC endpoint.
     #[clap(long, default_value = "http://127.0.0.1:8545")]
+    #[serde(serialize_with = "serialize_redacted_required")]
     pub eth_client_address: String,
 
     /// Chain information.
     #[clap(long)]
+    #[serde(serialize_with = "serialize_display_opt")]
     pub chain: Option<NamedChain>,
 
     /// Entry point addresses.
     #[clap(long, value_delimiter=',', value_parser=parse_address)]
     pub entry_points: Vec<Address>,
 
+    /// Number of recent blocks scanned at startup for the emitters of `UserOperationEvent` logs,
+    /// which are suggested as entry points (not scanned if not set).
+    #[clap(long)]
+    pub entry_point_discovery_blocks: Option<u64>,
+
+    /// Adds the entry points discovered by the startup scan to the configured entry points
+    /// (otherwise they are only suggested).
+    #[clap(long, requires = "entry_point_discovery_blocks")]
+    pub adopt_discovered_entry_points: bool,
+
     /// Poll interval event filters and pending transactions in milliseconds.
     #[clap(long, default_value = "500", value_parser= parse_duration)]
     pub poll_interval: Duration,
@@ -169,7 +514,7 @@ pub struct BundlerAndUoPoolArgs {
 }
 
 /// RPC CLI args
-#[derive(Debug, Clone, Parser, PartialEq)]
+#[derive(Debug, Clone, Parser, PartialEq, Serialize)]
 pub struct RpcArgs {
     /// Enables or disables the HTTP RPC.
     ///
@@ -201,6 +546,12 @@ pub struct RpcArgs {
     #[clap(long = "http.corsdomain", value_delimiter = ',', default_value = "*")]
     pub http_corsdomain: Vec<String>,
 
+    /// Enables gzip compression of HTTP RPC responses (if requested by the client).
+    ///
+    /// By default, this option is set to false.
+    #[clap(long = "http.compression")]
+    pub http_compression: bool,
+
     /// Enables or disables the WebSocket RPC.
     ///
     /// By default, this option is set to false.
@@ -231,9 +582,42 @@ pub struct RpcArgs {
     #[clap(long = "ws.origins", value_delimiter = ',', default_value = "*")]
     pub ws_origins: Vec<String>,
 
+    /// Sets the maximum number of subscriptions per WS connection.
+    ///
+    /// By default, this option is set to `1024`.
+    #[clap(long = "ws.max-subscriptions", default_value_t = MAX_SUBSCRIPTIONS_PER_CONNECTION)]
+    pub ws_max_subscriptions: u32,
+
     /// Ethereum execution client proxy HTTP RPC endpoint
     #[clap(long)]
+    #[serde(serialize_with = "serialize_redacted")]
     pub eth_client_proxy_address: Option<String>,
+
+    /// Rejects user operations carrying unknown fields in `eth_sendUserOperation` instead of
+    /// ignoring them.
+    ///
+    /// By default, this option is set to false.
+    #[clap(long = "rpc.deny-unknown-fields")]
+    pub deny_unknown_fields: bool,
+
+    /// API keys (in the form of key=limit) required on `eth_sendUserOperation` requests sent over
+    /// HTTP (in the `x-api-key` header). Each API key can send at most `limit` user operations
+    /// per rate limit period. If set, user operations can't be sent over WS. If not set, no API
+    /// key is required.
+    #[clap(long = "rpc.api-keys", value_delimiter = ',', value_parser = parse_api_key)]
+    #[serde(serialize_with = "serialize_api_keys")]
+    pub api_keys: Vec<(String, u64)>,
+
+    /// Period (in seconds) of the API key rate limits.
+    #[clap(long = "rpc.api-key-period", default_value_t = API_KEY_RATE_PERIOD)]
+    pub api_key_period: u64,
+
+    /// Token required on the admin methods (e.g., `debug_bundler_setPinnedUserOperations`) in the
+    /// `Authorization: Bearer` header. The admin methods are only served over HTTP and are
+    /// rejected if no token is set.
+    #[clap(long = "rpc.admin-token")]
+    #[serde(serialize_with = "serialize_redacted")]
+    pub admin_token: Option<String>,
 }
 
 impl RpcArgs {
@@ -257,6 +641,29 @@ impl RpcArgs {
     }
 }
 
+/// Effective configuration of the node (CLI args merged with the defaults) exported over the
+/// `debug_bundler_getConfig` RPC method. Secrets are redacted.
+#[derive(Debug, Default, Serialize)]
+pub struct NodeConfig<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bundler: Option<&'a BundlerArgs>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uopool: Option<&'a UoPoolArgs>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub common: Option<&'a BundlerAndUoPoolArgs>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rpc: Option<&'a RpcArgs>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<&'a MetricsArgs>,
+}
+
+impl NodeConfig<'_> {
+    /// Serializes the configuration to JSON
+    pub fn to_json(&self) -> eyre::Result<serde_json::Value> {
+        Ok(serde_json::to_value(self)?)
+    }
+}
+
 /// Create wallet CLI args
 #[derive(Debug, Clone, Parser)]
 pub struct CreateWalletArgs {
@@ -273,7 +680,7 @@ pub struct CreateWalletArgs {
     pub flashbots_key: bool,
 }
 
-#[derive(Clone, Debug, Parser, PartialEq)]
+#[derive(Clone, Debug, Parser, PartialEq, Serialize)]
 pub struct P2PArgs {
     /// enable p2p
     #[clap(long)]
@@ -297,6 +704,7 @@ pub struct P2PArgs {
 
     /// The initial bootnodes to connect to for the p2p network
     #[clap(long, value_delimiter = ',', value_parser=parse_enr)]
+    #[serde(serialize_with = "serialize_display_seq")]
     pub bootnodes: Vec<Enr>,
 
     /// The path to the file where the p2p private key is stored.
@@ -310,17 +718,53 @@ pub struct P2PArgs {
     /// List of whitelisted ENRs (for permissioned mempools).
     /// If empty, all ENRs are allowed.
     #[clap(long = "p2p.whitelist-enrs", value_delimiter = ',', value_parser=parse_enr)]
+    #[serde(serialize_with = "serialize_display_seq")]
     pub peers_whitelist: Vec<Enr>,
 
     /// List of whitelisted IPs (for permissioned mempools).
     /// If empty, all IPs are allowed.
     #[clap(long = "p2p.whitelist-ips", value_delimiter = ',')]
     pub ips_whitelist: Vec<IpAddr>,
+
+    /// Minimum number of connected peers before user operations received over p2p are pooled.
+    #[clap(long = "p2p.min-peers", default_value = "0")]
+    pub min_peers: usize,
+
+    /// Number of recently seen user operation hashes remembered to drop duplicate gossip
+    /// (0 disables the cache).
+    #[clap(long = "p2p.seen-cache-size", default_value_t = SEEN_CACHE_SIZE)]
+    pub seen_cache_size: usize,
+
+    /// Canonical mempool (id) used to validate user operations which weren't validated against a
+    /// specific canonical mempool. If not set, the first canonical mempool of the chain is used.
+    #[clap(long = "p2p.default-mempool")]
+    pub default_mempool: Option<String>,
+
+    /// Time (in seconds) without useful gossip after which a peer is disconnected, freeing its
+    /// connection slot (whitelisted peers are kept). If not set, idle peers aren't pruned.
+    #[clap(long = "p2p.idle-peer-timeout")]
+    pub idle_peer_timeout: Option<u64>,
+
+    /// Target number of peers in the gossipsub mesh (D).
+    #[clap(long = "p2p.mesh-n", default_value_t = GOSSIP_MESH_N)]
+    pub mesh_n: usize,
+
+    /// Minimum number of peers in the gossipsub mesh (D_low).
+    #[clap(long = "p2p.mesh-n-low", default_value_t = GOSSIP_MESH_N_LOW)]
+    pub mesh_n_low: usize,
+
+    /// Maximum number of peers in the gossipsub mesh (D_high).
+    #[clap(long = "p2p.mesh-n-high", default_value_t = GOSSIP_MESH_N_HIGH)]
+    pub mesh_n_high: usize,
+
+    /// Number of peers outside the gossipsub mesh to emit gossip to (D_lazy).
+    #[clap(long = "p2p.gossip-lazy", default_value_t = GOSSIP_LAZY)]
+    pub gossip_lazy: usize,
 }
 
 impl P2PArgs {
     /// Convert the P2PArgs to [silius_p2p::config::Config]
-    pub fn to_config(&self, chain: &Chain, datadir: &Path) -> Config {
+    pub fn to_config(&self, chain: &Chain, datadir: &Path) -> eyre::Result<Config> {
         let listen_addr = ListenAddress::V4(ListenAddr {
             addr: self.p2p_listen_address,
             udp_port: self.udp4_port,
@@ -346,14 +790,26 @@ impl P2PArgs {
             .bootnodes(self.bootnodes.clone())
             .peers_whitelist(self.peers_whitelist.clone())
             .ips_whitelist(self.ips_whitelist.clone())
-            .gs_config(gossipsub_config())
+            .min_peers(self.min_peers)
+            .seen_cache_size(self.seen_cache_size)
+            .default_mempool(self.default_mempool.clone())
+            .idle_peer_timeout(self.idle_peer_timeout.map(Duration::from_secs))
+            .gs_config(
+                gossipsub_config_with_mesh_params(&GossipsubMeshParams {
+                    mesh_n: self.mesh_n,
+                    mesh_n_low: self.mesh_n_low,
+                    mesh_n_high: self.mesh_n_high,
+                    gossip_lazy: self.gossip_lazy,
+                })
+                .map_err(|e| eyre::eyre!("Invalid gossipsub mesh params: {e}"))?,
+            )
             .discv5_config(discv5::ConfigBuilder::new(listen_addr.to_listen_config()).build());
 
-        config_builder.build()
+        Ok(config_builder.build())
     }
 }
 
-#[derive(Clone, Debug, Parser, PartialEq)]
+#[derive(Clone, Debug, Parser, PartialEq, Serialize)]
 pub struct MetricsArgs {
     #[clap(long)]
     pub enable_metrics: bool,
@@ -404,16 +860,27 @@ mod tests {
                 )),
                 private_key: None,
                 flashbots_private_key: None,
-                beneficiary: Address::from_str("0x690B9A9E9aa1C9dB991C7721a92d351Db4FaC990")
-                    .unwrap(),
+                beneficiary: vec![
+                    Address::from_str("0x690B9A9E9aa1C9dB991C7721a92d351Db4FaC990").unwrap()
+                ],
                 min_balance: U256::from(100000000000000000_u64),
+                balance_warning_threshold: None,
                 manual_bundle_mode: false,
                 bundle_interval: 10,
                 bundle_strategy: BundleStrategy::EthereumClient,
                 eth_client_bundle_address: None,
+                relay_endpoint: None,
+                relay_signature_header: RELAY_SIGNATURE_HEADER.into(),
                 bundler_addr: IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
                 bundler_port: 3002,
                 enable_access_list: false,
+                max_bundle_rebuilds: 3,
+                bundle_priority_fee_margin: 0,
+                bundle_gas_limit_buffer: 0,
+                max_bundle_submissions: 3,
+                bundle_tip_escalation: 20,
+                instance: None,
+                nonce_block_tag: BlockNumber::Latest,
             },
             BundlerArgs::try_parse_from(args).unwrap()
         );
@@ -446,16 +913,27 @@ mod tests {
                     .unwrap()
                 ),
                 flashbots_private_key: None,
-                beneficiary: Address::from_str("0x690B9A9E9aa1C9dB991C7721a92d351Db4FaC990")
-                    .unwrap(),
+                beneficiary: vec![
+                    Address::from_str("0x690B9A9E9aa1C9dB991C7721a92d351Db4FaC990").unwrap()
+                ],
                 min_balance: U256::from(100000000000000000_u64),
+                balance_warning_threshold: None,
                 manual_bundle_mode: false,
                 bundle_interval: 10,
                 bundle_strategy: BundleStrategy::EthereumClient,
                 eth_client_bundle_address: None,
+                relay_endpoint: None,
+                relay_signature_header: RELAY_SIGNATURE_HEADER.into(),
                 bundler_addr: IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
                 bundler_port: 3002,
                 enable_access_list: false,
+                max_bundle_rebuilds: 3,
+                bundle_priority_fee_margin: 0,
+                bundle_gas_limit_buffer: 0,
+                max_bundle_submissions: 3,
+                bundle_tip_escalation: 20,
+                instance: None,
+                nonce_block_tag: BlockNumber::Latest,
             },
             BundlerArgs::try_parse_from(args).unwrap()
         );
@@ -496,21 +974,80 @@ mod tests {
                     )
                     .unwrap()
                 ),
-                beneficiary: Address::from_str("0x690B9A9E9aa1C9dB991C7721a92d351Db4FaC990")
-                    .unwrap(),
+                beneficiary: vec![
+                    Address::from_str("0x690B9A9E9aa1C9dB991C7721a92d351Db4FaC990").unwrap()
+                ],
                 min_balance: U256::from(100000000000000000_u64),
+                balance_warning_threshold: None,
                 manual_bundle_mode: true,
                 bundle_interval: 10,
                 bundle_strategy: BundleStrategy::EthereumClient,
                 eth_client_bundle_address: Some(String::from("http://127.0.0.1:8545")),
+                relay_endpoint: None,
+                relay_signature_header: RELAY_SIGNATURE_HEADER.into(),
                 bundler_addr: IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
                 bundler_port: 3002,
                 enable_access_list: false,
+                max_bundle_rebuilds: 3,
+                bundle_priority_fee_margin: 0,
+                bundle_gas_limit_buffer: 0,
+                max_bundle_submissions: 3,
+                bundle_tip_escalation: 20,
+                instance: None,
+                nonce_block_tag: BlockNumber::Latest,
             },
             BundlerArgs::try_parse_from(args).unwrap()
         );
     }
 
+    #[test]
+    fn bundler_args_relay() {
+        let args = vec![
+            "bundlerargs",
+            "--private-key",
+            "4c5e5d3076c425e8d8affe9c2a0da32b779820ef008fdda02d5c7b783674d8c4",
+            "--flashbots-private-key",
+            "df218be02efd744fc91f93d7f3c49676fb99b296e99c1410fccd65be79d608a7",
+            "--beneficiary",
+            "0x690B9A9E9aa1C9dB991C7721a92d351Db4FaC990",
+            "--bundle-strategy",
+            "relay",
+            "--relay-endpoint",
+            "https://relay.example.org",
+            "--relay-signature-header",
+            "X-Relay-Signature",
+        ];
+        let bundler_args = BundlerArgs::try_parse_from(args.clone()).unwrap();
+        assert_eq!(bundler_args.bundle_strategy, BundleStrategy::Relay);
+        assert_eq!(bundler_args.relay_endpoint, Some(String::from("https://relay.example.org")));
+        assert_eq!(bundler_args.relay_signature_header, String::from("X-Relay-Signature"));
+
+        // relay endpoint is required by the relay strategy
+        assert!(BundlerArgs::try_parse_from(&args[..args.len() - 4]).is_err());
+    }
+
+    #[test]
+    fn bundler_args_instance() {
+        let args = vec![
+            "bundlerargs",
+            "--private-key",
+            "4c5e5d3076c425e8d8affe9c2a0da32b779820ef008fdda02d5c7b783674d8c4",
+            "--beneficiary",
+            "0x690B9A9E9aa1C9dB991C7721a92d351Db4FaC990",
+            "--instance",
+            "1/3",
+        ];
+        assert_eq!(
+            BundlerArgs::try_parse_from(args.clone()).unwrap().instance,
+            Some(BundlerInstance { index: 1, count: 3 })
+        );
+
+        // index must be lower than the number of instances
+        let mut args = args;
+        args[6] = "3/3";
+        assert!(BundlerArgs::try_parse_from(args).is_err());
+    }
+
     #[test]
     fn bundler_and_uopool_args() {
         let args = vec![
@@ -523,6 +1060,9 @@ mod tests {
             "0x690B9A9E9aa1C9dB991C7721a92d351Db4FaC990",
             "--poll-interval",
             "5000",
+            "--entry-point-discovery-blocks",
+            "1000",
+            "--adopt-discovered-entry-points",
         ];
         assert_eq!(
             BundlerAndUoPoolArgs {
@@ -531,6 +1071,8 @@ mod tests {
                 entry_points: vec![
                     Address::from_str("0x690B9A9E9aa1C9dB991C7721a92d351Db4FaC990").unwrap()
                 ],
+                entry_point_discovery_blocks: Some(1000),
+                adopt_discovered_entry_points: true,
                 poll_interval: Duration::from_millis(5000),
                 metrics: MetricsArgs {
                     enable_metrics: false,
@@ -573,17 +1115,34 @@ mod tests {
                 http_port: 3000,
                 http_api: vec![String::from("eth"), String::from("debug"), String::from("web3")],
                 http_corsdomain: vec![String::from("127.0.0.1:4321")],
+                http_compression: false,
                 ws: true,
                 ws_addr: IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
                 ws_port: 3001,
                 ws_api: vec![String::from("eth"), String::from("debug"), String::from("web3")],
                 ws_origins: vec![String::from("127.0.0.1:4321")],
+                ws_max_subscriptions: MAX_SUBSCRIPTIONS_PER_CONNECTION,
                 eth_client_proxy_address: None,
+                deny_unknown_fields: false,
+                api_keys: vec![],
+                api_key_period: API_KEY_RATE_PERIOD,
+                admin_token: None,
             },
             RpcArgs::try_parse_from(args).unwrap()
         );
     }
 
+    #[test]
+    fn rpc_args_api_keys() {
+        let args = vec!["rpcargs", "--http", "--rpc.api-keys", "key1=10,key2=5"];
+        assert_eq!(
+            RpcArgs::try_parse_from(args).unwrap().api_keys,
+            vec![(String::from("key1"), 10), (String::from("key2"), 5)]
+        );
+
+        assert!(RpcArgs::try_parse_from(vec!["rpcargs", "--rpc.api-keys", "key1"]).is_err());
+    }
+
     #[test]
     fn rpc_args_when_http_is_true_ws_is_false() {
         let args = vec![
@@ -605,12 +1164,18 @@ mod tests {
                 http_port: 3000,
                 http_api: vec![String::from("eth"), String::from("debug"), String::from("web3")],
                 http_corsdomain: vec![String::from("127.0.0.1:4321")],
+                http_compression: false,
                 ws: false,
                 ws_addr: IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
                 ws_port: 3001,
                 ws_api: vec![String::from("eth"),],
                 ws_origins: vec![String::from("*")],
+                ws_max_subscriptions: MAX_SUBSCRIPTIONS_PER_CONNECTION,
                 eth_client_proxy_address: None,
+                deny_unknown_fields: false,
+                api_keys: vec![],
+                api_key_period: API_KEY_RATE_PERIOD,
+                admin_token: None,
             },
             RpcArgs::try_parse_from(args).unwrap()
         );
@@ -637,12 +1202,18 @@ mod tests {
                 http_port: 3000,
                 http_api: vec![String::from("eth"),],
                 http_corsdomain: vec![String::from("*")],
+                http_compression: false,
                 ws: true,
                 ws_addr: IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
                 ws_port: 3001,
                 ws_api: vec![String::from("eth"), String::from("debug"), String::from("web3")],
                 ws_origins: vec![String::from("127.0.0.1:4321")],
+                ws_max_subscriptions: MAX_SUBSCRIPTIONS_PER_CONNECTION,
                 eth_client_proxy_address: None,
+                deny_unknown_fields: false,
+                api_keys: vec![],
+                api_key_period: API_KEY_RATE_PERIOD,
+                admin_token: None,
             },
             RpcArgs::try_parse_from(args).unwrap()
         );
@@ -668,12 +1239,18 @@ mod tests {
                 http_port: 3000,
                 http_api: vec![String::from("eth"), String::from("debug"), String::from("web3")],
                 http_corsdomain: vec![String::from("127.0.0.1:4321")],
+                http_compression: false,
                 ws: false,
                 ws_addr: IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
                 ws_port: 3001,
                 ws_api: vec![String::from("eth"),],
                 ws_origins: vec![String::from("*")],
+                ws_max_subscriptions: MAX_SUBSCRIPTIONS_PER_CONNECTION,
                 eth_client_proxy_address: None,
+                deny_unknown_fields: false,
+                api_keys: vec![],
+                api_key_period: API_KEY_RATE_PERIOD,
+                admin_token: None,
             },
             RpcArgs::try_parse_from(args).unwrap()
         );
@@ -688,12 +1265,18 @@ mod tests {
                 http_port: 3000,
                 http_api: vec![String::from("eth"), String::from("debug"), String::from("web3")],
                 http_corsdomain: vec![String::from("127.0.0.1:4321")],
+                http_compression: false,
                 ws: false,
                 ws_addr: IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
                 ws_port: 3001,
                 ws_api: vec![String::from("eth"),],
                 ws_origins: vec![String::from("*")],
+                ws_max_subscriptions: MAX_SUBSCRIPTIONS_PER_CONNECTION,
                 eth_client_proxy_address: None,
+                deny_unknown_fields: false,
+                api_keys: vec![],
+                api_key_period: API_KEY_RATE_PERIOD,
+                admin_token: None,
             }
             .is_enabled(),
             true
@@ -709,12 +1292,18 @@ mod tests {
                 http_port: 3000,
                 http_api: vec![String::from("eth"),],
                 http_corsdomain: vec![String::from("*")],
+                http_compression: false,
                 ws: true,
                 ws_addr: IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
                 ws_port: 3001,
                 ws_api: vec![String::from("eth"), String::from("debug"), String::from("web3")],
                 ws_origins: vec![String::from("127.0.0.1:4321")],
+                ws_max_subscriptions: MAX_SUBSCRIPTIONS_PER_CONNECTION,
                 eth_client_proxy_address: None,
+                deny_unknown_fields: false,
+                api_keys: vec![],
+                api_key_period: API_KEY_RATE_PERIOD,
+                admin_token: None,
             }
             .is_enabled(),
             true
@@ -730,12 +1319,18 @@ mod tests {
                 http_port: 3000,
                 http_api: vec![String::from("eth"), String::from("debug"), String::from("web3")],
                 http_corsdomain: vec![String::from("127.0.0.1:4321")],
+                http_compression: false,
                 ws: true,
                 ws_addr: IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
                 ws_port: 3001,
                 ws_api: vec![String::from("eth"), String::from("debug"), String::from("web3")],
                 ws_origins: vec![String::from("127.0.0.1:4321")],
+                ws_max_subscriptions: MAX_SUBSCRIPTIONS_PER_CONNECTION,
                 eth_client_proxy_address: None,
+                deny_unknown_fields: false,
+                api_keys: vec![],
+                api_key_period: API_KEY_RATE_PERIOD,
+                admin_token: None,
             }
             .is_enabled(),
             true
@@ -751,12 +1346,18 @@ mod tests {
                 http_port: 3000,
                 http_api: vec![String::from("eth"), String::from("debug"), String::from("web3")],
                 http_corsdomain: vec![String::from("127.0.0.1:4321")],
+                http_compression: false,
                 ws: false,
                 ws_addr: IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
                 ws_port: 3001,
                 ws_api: vec![String::from("eth"),],
                 ws_origins: vec![String::from("*")],
+                ws_max_subscriptions: MAX_SUBSCRIPTIONS_PER_CONNECTION,
                 eth_client_proxy_address: None,
+                deny_unknown_fields: false,
+                api_keys: vec![],
+                api_key_period: API_KEY_RATE_PERIOD,
+                admin_token: None,
             }
             .is_enabled(),
             false
@@ -792,6 +1393,18 @@ mod tests {
             "~/.silius/p2p/node-enr",
             "--p2p.whitelist-enrs",
             &binding,
+            "--p2p.min-peers",
+            "2",
+            "--p2p.seen-cache-size",
+            "256",
+            "--p2p.idle-peer-timeout",
+            "600",
+            "--p2p.mesh-n",
+            "4",
+            "--p2p.mesh-n-low",
+            "2",
+            "--p2p.mesh-n-high",
+            "8",
         ];
         assert_eq!(
             P2PArgs {
@@ -805,6 +1418,14 @@ mod tests {
                 node_enr: Some(PathBuf::from("~/.silius/p2p/node-enr")),
                 peers_whitelist: vec![enr],
                 ips_whitelist: vec![],
+                min_peers: 2,
+                seen_cache_size: 256,
+                default_mempool: None,
+                idle_peer_timeout: Some(600),
+                mesh_n: 4,
+                mesh_n_low: 2,
+                mesh_n_high: 8,
+                gossip_lazy: GOSSIP_LAZY,
             },
             P2PArgs::try_parse_from(args).unwrap()
         )
@@ -835,4 +1456,66 @@ mod tests {
             MetricsArgs::try_parse_from(args).unwrap()
         )
     }
+
+    #[test]
+    fn node_config() {
+        let private_key = "4c5e5d3076c425e8d8affe9c2a0da32b779820ef008fdda02d5c7b783674d8c4";
+        let bundler_args = BundlerArgs::try_parse_from(vec![
+            "bundlerargs",
+            "--private-key",
+            private_key,
+            "--beneficiary",
+            "0x690B9A9E9aa1C9dB991C7721a92d351Db4FaC990",
+            "--min-balance",
+            "200000000000000000",
+            "--eth-client-bundle-address",
+            "https://bundle.example.org/v1/api-key",
+        ])
+        .unwrap();
+        let common_args = BundlerAndUoPoolArgs::try_parse_from(vec![
+            "bundleranduopoolargs",
+            "--eth-client-address",
+            "https://eth.example.org/v1/api-key",
+        ])
+        .unwrap();
+        let rpc_args = RpcArgs::try_parse_from(vec![
+            "rpcargs",
+            "--http",
+            "--eth-client-proxy-address",
+            "https://proxy.example.org/v1/api-key",
+        ])
+        .unwrap();
+
+        let config = NodeConfig {
+            bundler: Some(&bundler_args),
+            common: Some(&common_args),
+            rpc: Some(&rpc_args),
+            ..Default::default()
+        }
+        .to_json()
+        .unwrap();
+
+        // overrides
+        assert_eq!(
+            config["bundler"]["min_balance"],
+            serde_json::to_value(U256::from(200000000000000000_u64)).unwrap()
+        );
+        assert_eq!(config["rpc"]["http"], true);
+
+        // defaults
+        assert_eq!(config["bundler"]["bundle_interval"], BUNDLE_INTERVAL);
+        assert_eq!(config["bundler"]["bundle_strategy"], "ethereum-client");
+        assert_eq!(config["rpc"]["http_port"], HTTP_PORT);
+
+        // secrets
+        assert_eq!(config["bundler"]["private_key"], "<redacted>");
+        assert_eq!(config["bundler"]["flashbots_private_key"], serde_json::Value::Null);
+        assert!(!config.to_string().contains(private_key));
+        assert_eq!(config["bundler"]["eth_client_bundle_address"], "<redacted>");
+        assert_eq!(config["bundler"]["relay_endpoint"], serde_json::Value::Null);
+        assert_eq!(config["common"]["eth_client_address"], "<redacted>");
+        assert_eq!(config["rpc"]["eth_client_proxy_address"], "<redacted>");
+        assert!(!config.to_string().contains("api-key"));
+        assert!(config.get("uopool").is_none());
+    }
 }
@@ -1,12 +1,16 @@
 use super::args::{
-    BundlerAndUoPoolArgs, BundlerArgs, CreateWalletArgs, MetricsArgs, RpcArgs, UoPoolArgs,
+    BundlerAndUoPoolArgs, BundlerArgs, CreateWalletArgs, MetricsArgs, NodeConfig, RpcArgs,
+    UoPoolArgs,
+};
+use crate::bundler::{
+    create_wallet, launch_bundler, launch_bundling, launch_rpc, launch_uopool, resolve_entry_points,
 };
-use crate::bundler::{create_wallet, launch_bundler, launch_bundling, launch_rpc, launch_uopool};
 use clap::{Parser, Subcommand};
 use ethers::types::Address;
 use silius_mempool::{
-    init_env, DatabaseTable, UserOperationAddrOp, UserOperationOp, UserOperations,
-    UserOperationsByEntity, UserOperationsBySender, WriteMap,
+    init_env, CodeHashes, DatabaseArguments, DatabaseTable, Mempool, UserOperationAddrOp,
+    UserOperationOp, UserOperations, UserOperationsByEntity, UserOperationsByEntryPoint,
+    UserOperationsBySender, UserOperationsMetadata, WriteMap,
 };
 use silius_metrics::ethers::MetricsMiddleware;
 use silius_primitives::provider::{
@@ -36,7 +40,7 @@ pub struct NodeCommand {
 
 impl NodeCommand {
     /// Execute the command
-    pub async fn execute(self) -> eyre::Result<()> {
+    pub async fn execute(mut self) -> eyre::Result<()> {
         if self.common.eth_client_address.clone().starts_with("http") {
             let http_client =
                 create_http_provider(&self.common.eth_client_address, self.common.poll_interval)
@@ -54,6 +58,8 @@ impl NodeCommand {
                 eth_client.clone()
             };
 
+            self.common.entry_points =
+                resolve_entry_points(eth_client.clone(), &self.common).await?;
             let block_streams =
                 create_http_block_streams(eth_client.clone(), self.common.entry_points.len()).await;
 
@@ -72,6 +78,8 @@ impl NodeCommand {
             let ws_client = create_ws_provider(&self.common.eth_client_address).await?;
             let eth_client = Arc::new(MetricsMiddleware::new(ws_client));
 
+            self.common.entry_points =
+                resolve_entry_points(eth_client.clone(), &self.common).await?;
             let block_streams =
                 create_ws_block_streams(eth_client.clone(), self.common.entry_points.len()).await;
 
@@ -130,7 +138,7 @@ pub struct BundlerCommand {
 
 impl BundlerCommand {
     /// Execute the command
-    pub async fn execute(self) -> eyre::Result<()> {
+    pub async fn execute(mut self) -> eyre::Result<()> {
         let eth_client_address = if let Some(eth_client_bundle_address) =
             self.bundler.eth_client_bundle_address.clone()
         {
@@ -143,6 +151,8 @@ impl BundlerCommand {
             let eth_client = Arc::new(
                 create_http_provider(&eth_client_address, self.common.poll_interval).await?,
             );
+            self.common.entry_points =
+                resolve_entry_points(eth_client.clone(), &self.common).await?;
             launch_bundling(
                 self.bundler,
                 eth_client,
@@ -154,6 +164,8 @@ impl BundlerCommand {
             .await?;
         } else {
             let eth_client = Arc::new(create_ws_provider(&eth_client_address).await?);
+            self.common.entry_points =
+                resolve_entry_points(eth_client.clone(), &self.common).await?;
             launch_bundling(
                 self.bundler,
                 eth_client,
@@ -183,12 +195,14 @@ pub struct UoPoolCommand {
 
 impl UoPoolCommand {
     /// Execute the command
-    pub async fn execute(self) -> eyre::Result<()> {
+    pub async fn execute(mut self) -> eyre::Result<()> {
         if self.common.eth_client_address.clone().starts_with("http") {
             let eth_client = Arc::new(
                 create_http_provider(&self.common.eth_client_address, self.common.poll_interval)
                     .await?,
             );
+            self.common.entry_points =
+                resolve_entry_points(eth_client.clone(), &self.common).await?;
             let block_streams =
                 create_http_block_streams(eth_client.clone(), self.common.entry_points.len()).await;
             launch_uopool(
@@ -202,6 +216,8 @@ impl UoPoolCommand {
             .await?;
         } else {
             let eth_client = Arc::new(create_ws_provider(&self.common.eth_client_address).await?);
+            self.common.entry_points =
+                resolve_entry_points(eth_client.clone(), &self.common).await?;
             let block_streams =
                 create_ws_block_streams(eth_client.clone(), self.common.entry_points.len()).await;
             launch_uopool(
@@ -242,11 +258,16 @@ pub struct RpcCommand {
 impl RpcCommand {
     /// Execute the command
     pub async fn execute(self) -> eyre::Result<()> {
+        let config =
+            NodeConfig { rpc: Some(&self.rpc), metrics: Some(&self.metrics), ..Default::default() }
+                .to_json()?;
+
         launch_rpc(
             self.rpc,
             self.uopool_grpc_listen_address,
             self.bundler_grpc_listen_address,
             self.metrics,
+            config,
         )
         .await?;
         pending().await
@@ -277,6 +298,9 @@ pub enum DebugCommand {
 
     #[command(name = "dump-uo-by-sender")]
     DumpUoBySender(DumpUserOperationsBySender),
+
+    #[command(name = "check-mempool")]
+    CheckMempool(CheckMempool),
 }
 
 impl DebugCommand {
@@ -285,6 +309,7 @@ impl DebugCommand {
         match self {
             DebugCommand::DumpUserops(command) => command.execute(),
             DebugCommand::DumpUoBySender(command) => command.execute(),
+            DebugCommand::CheckMempool(command) => command.execute(),
         }
     }
 }
@@ -297,7 +322,10 @@ pub struct DumpUserOperations {
 
 impl DumpUserOperations {
     pub fn execute(self) -> eyre::Result<()> {
-        let env = Arc::new(init_env::<WriteMap>(self.data_dir).expect("Init mdbx failed"));
+        let env = Arc::new(
+            init_env::<WriteMap>(self.data_dir, DatabaseArguments::default())
+                .expect("Init mdbx failed"),
+        );
         let table = DatabaseTable::<WriteMap, UserOperations>::new(env.clone());
         let uo = table.get_all()?;
         serde_json::to_writer(std::io::stdout(), &uo)?;
@@ -316,7 +344,10 @@ pub struct DumpUserOperationsBySender {
 }
 impl DumpUserOperationsBySender {
     pub fn execute(self) -> eyre::Result<()> {
-        let env = Arc::new(init_env::<WriteMap>(self.data_dir).expect("Init mdbx failed"));
+        let env = Arc::new(
+            init_env::<WriteMap>(self.data_dir, DatabaseArguments::default())
+                .expect("Init mdbx failed"),
+        );
         let table = DatabaseTable::<WriteMap, UserOperationsBySender>::new(env.clone());
         let mut uo = table.get_all_by_address(&self.address);
 
@@ -327,3 +358,41 @@ impl DumpUserOperationsBySender {
         Ok(())
     }
 }
+
+/// Checks that the mempool indexes (by sender, by entity and by entry point) are consistent with
+/// the user operations and rebuilds them if not
+#[derive(Debug, Parser)]
+pub struct CheckMempool {
+    /// The directory of the database.
+    #[clap(long, short)]
+    data_dir: PathBuf,
+
+    /// Only report the discrepancies, without rebuilding the indexes.
+    #[clap(long)]
+    dry_run: bool,
+}
+
+impl CheckMempool {
+    pub fn execute(self) -> eyre::Result<()> {
+        let env = Arc::new(
+            init_env::<WriteMap>(self.data_dir, DatabaseArguments::default())
+                .expect("Init mdbx failed"),
+        );
+        let mut mempool = Mempool::new(
+            Box::new(DatabaseTable::<WriteMap, UserOperations>::new(env.clone())),
+            Box::new(DatabaseTable::<WriteMap, UserOperationsBySender>::new(env.clone())),
+            Box::new(DatabaseTable::<WriteMap, UserOperationsByEntity>::new(env.clone())),
+            Box::new(DatabaseTable::<WriteMap, CodeHashes>::new(env.clone())),
+        )
+        // the sources of the removed user operations are removed too
+        .with_metadata(Box::new(DatabaseTable::<WriteMap, UserOperationsMetadata>::new(
+            env.clone(),
+        )))
+        .with_entry_point_index(Box::new(
+            DatabaseTable::<WriteMap, UserOperationsByEntryPoint>::new(env.clone()),
+        ));
+        let report = mempool.check_consistency(!self.dry_run)?;
+        serde_json::to_writer(std::io::stdout(), &report)?;
+        Ok(())
+    }
+}
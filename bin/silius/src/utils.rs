@@ -1,11 +1,20 @@
 use dirs::home_dir;
 use discv5::Enr;
-use ethers::types::{Address, U256};
+use ethers::{
+    abi::Selector,
+    types::{Address, BlockNumber, Bytes, U256},
+};
 use expanded_pathbuf::ExpandedPathBuf;
 use pin_utils::pin_mut;
+use serde::{Serialize, Serializer};
+use silius_contracts::EntryPointVersion;
+use silius_mempool::validate::TraceViolation;
 use silius_metrics::label::LabelValue;
-use silius_primitives::{bundler::BundleStrategy, UoPoolMode};
-use std::{future::Future, str::FromStr, time::Duration};
+use silius_primitives::{
+    bundler::{BundleStrategy, BundlerInstance},
+    PrefundPolicy, ReplacementPolicy, UoPoolMode, ValidAfterPolicy,
+};
+use std::{fmt::Display, future::Future, str::FromStr, time::Duration};
 use tracing::info;
 
 /// Unwrap path or returns home directory
@@ -25,21 +34,98 @@ pub fn parse_address(s: &str) -> Result<Address, String> {
     Address::from_str(s).map_err(|_| format!("String {s} is not a valid address"))
 }
 
+/// Parses hex encoded bytes from string
+pub fn parse_bytes(s: &str) -> Result<Bytes, String> {
+    Bytes::from_str(s).map_err(|_| format!("String {s} is not a valid hex string"))
+}
+
+/// Parses function selector (4 hex encoded bytes) from string
+pub fn parse_selector(s: &str) -> Result<Selector, String> {
+    Bytes::from_str(s)
+        .ok()
+        .and_then(|b| Selector::try_from(b.as_ref()).ok())
+        .ok_or_else(|| format!("String {s} is not a valid function selector"))
+}
+
 /// Parses U256 from string
 pub fn parse_u256(s: &str) -> Result<U256, String> {
     U256::from_str_radix(s, 10).map_err(|_| format!("String {s} is not a valid U256"))
 }
 
+/// Parses a fee of an entry point from string in the format `<entry point address>=<fee>`
+pub fn parse_entry_point_fee(s: &str) -> Result<(Address, U256), String> {
+    let (ep, fee) =
+        s.split_once('=').ok_or_else(|| format!("String {s} is not a valid address=fee"))?;
+    Ok((parse_address(ep)?, parse_u256(fee)?))
+}
+
+/// Parses an opcode allowed for a trusted factory from string in the format
+/// `<factory address>=<opcode>`
+pub fn parse_factory_opcode(s: &str) -> Result<(Address, String), String> {
+    let (factory, opcode) =
+        s.split_once('=').ok_or_else(|| format!("String {s} is not a valid factory=opcode"))?;
+    Ok((parse_address(factory)?, opcode.to_uppercase()))
+}
+
+/// Parses API key and its rate limit from string
+pub fn parse_api_key(s: &str) -> Result<(String, u64), String> {
+    let (key, limit) =
+        s.split_once('=').ok_or_else(|| format!("String {s} is not a valid key=limit"))?;
+    let limit = limit.parse().map_err(|_| format!("String {limit} is not a valid limit"))?;
+    Ok((key.to_string(), limit))
+}
+
 /// Parses BundleStrategy from string
 pub fn parse_bundle_strategy(s: &str) -> Result<BundleStrategy, String> {
     BundleStrategy::from_str(s).map_err(|_| format!("String {s} is not a valid BundleStrategy"))
 }
 
+/// Parses BundlerInstance from string
+pub fn parse_bundler_instance(s: &str) -> Result<BundlerInstance, String> {
+    BundlerInstance::from_str(s)
+}
+
 /// Parses UoPoolMode from string
 pub fn parse_uopool_mode(s: &str) -> Result<UoPoolMode, String> {
     UoPoolMode::from_str(s).map_err(|_| format!("String {s} is not a valid UoPoolMode"))
 }
 
+/// Parses ValidAfterPolicy from string
+pub fn parse_valid_after_policy(s: &str) -> Result<ValidAfterPolicy, String> {
+    ValidAfterPolicy::from_str(s).map_err(|_| format!("String {s} is not a valid ValidAfterPolicy"))
+}
+
+/// Parses PrefundPolicy from string
+pub fn parse_prefund_policy(s: &str) -> Result<PrefundPolicy, String> {
+    PrefundPolicy::from_str(s).map_err(|_| format!("String {s} is not a valid PrefundPolicy"))
+}
+
+/// Parses ReplacementPolicy from string
+pub fn parse_replacement_policy(s: &str) -> Result<ReplacementPolicy, String> {
+    ReplacementPolicy::from_str(s)
+        .map_err(|_| format!("String {s} is not a valid ReplacementPolicy"))
+}
+
+/// Parses EntryPointVersion from string
+pub fn parse_entry_point_version(s: &str) -> Result<EntryPointVersion, String> {
+    EntryPointVersion::from_str(s)
+        .map_err(|_| format!("String {s} is not a valid EntryPointVersion"))
+}
+
+/// Parses the block tag used to fetch the nonce of the bundler account from string
+pub fn parse_nonce_block_tag(s: &str) -> Result<BlockNumber, String> {
+    match s {
+        "latest" => Ok(BlockNumber::Latest),
+        "pending" => Ok(BlockNumber::Pending),
+        _ => Err(format!("String {s} is not a valid block tag (latest or pending)")),
+    }
+}
+
+/// Parses TraceViolation from string
+pub fn parse_trace_violation(s: &str) -> Result<TraceViolation, String> {
+    TraceViolation::from_str(s)
+}
+
 /// Parses ENR record
 pub fn parse_enr(enr: &str) -> Result<Enr, String> {
     Enr::from_str(enr).map_err(|_| format!("Enr {enr} is not a valid enr."))
@@ -61,6 +147,68 @@ pub fn parse_label_value(label_value: &str) -> Result<LabelValue, String> {
     Ok(LabelValue::new(label.to_string(), value.to_string()))
 }
 
+/// Serializes a secret (e.g., a private key) as a placeholder, so it isn't exposed in the exported
+/// configuration
+pub fn serialize_redacted<T, S: Serializer>(
+    value: &Option<T>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    value.as_ref().map(|_| "<redacted>").serialize(serializer)
+}
+
+/// Serializes a required secret (e.g., an RPC endpoint, whose URL usually contains an API key) as
+/// a placeholder, so it isn't exposed in the exported configuration
+pub fn serialize_redacted_required<T, S: Serializer>(
+    _value: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    "<redacted>".serialize(serializer)
+}
+
+/// Serializes API keys with their rate limits, redacting the keys
+pub fn serialize_api_keys<S: Serializer>(
+    api_keys: &[(String, u64)],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    api_keys
+        .iter()
+        .map(|(_, limit)| format!("<redacted>={limit}"))
+        .collect::<Vec<_>>()
+        .serialize(serializer)
+}
+
+/// Serializes an optional value as a string
+pub fn serialize_display_opt<T: Display, S: Serializer>(
+    value: &Option<T>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    value.as_ref().map(ToString::to_string).serialize(serializer)
+}
+
+/// Serializes the values as strings
+pub fn serialize_display_seq<T: Display, S: Serializer>(
+    values: &[T],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(values.iter().map(ToString::to_string))
+}
+
+/// Serializes function selectors as hex strings
+pub fn serialize_selectors<S: Serializer>(
+    selectors: &[Selector],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(selectors.iter().map(|selector| Bytes::from(selector.to_vec())))
+}
+
+/// Serializes the (expanded) data directory
+pub fn serialize_datadir<S: Serializer>(
+    datadir: &Option<ExpandedPathBuf>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    datadir.as_ref().map(|datadir| &datadir.0).serialize(serializer)
+}
+
 /// Runs the future to completion or until:
 /// - `ctrl-c` is received.
 /// - `SIGTERM` is received (unix only).
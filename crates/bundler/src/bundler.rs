@@ -3,13 +3,178 @@ use ethers::{
     providers::Middleware,
     signers::Signer,
     types::{
-        transaction::eip2718::TypedTransaction, Address, Eip1559TransactionRequest, H256, U256, U64,
+        transaction::eip2718::TypedTransaction, Address, BlockNumber, Eip1559TransactionRequest,
+        H256, U256, U64,
     },
 };
-use silius_contracts::entry_point::EntryPointAPI;
-use silius_primitives::{simulation::StorageMap, UserOperation, UserOperationHash, Wallet};
-use std::sync::Arc;
-use tracing::{info, trace};
+use parking_lot::RwLock;
+use silius_contracts::entry_point::{EntryPointAPIErrors, EntryPointError, EntryPointVersion};
+use silius_primitives::{
+    bundler::{BundleStrategy, BundlerInstance},
+    constants::{
+        bundler::{
+            BUNDLE_GAS_LIMIT_BUFFER, BUNDLE_TIP_ESCALATION, MAX_BUNDLE_REBUILDS,
+            MAX_BUNDLE_SUBMISSIONS,
+        },
+        validation::INVALID_NONCE_REASON,
+    },
+    simulation::StorageMap,
+    UserOperation, UserOperationHash, Wallet,
+};
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+use tracing::{info, trace, warn};
+
+/// Error returned when `handleOps` reverts because of an invalid sender nonce (e.g., the user
+/// operation was already included by a competing bundler)
+#[derive(Debug)]
+struct InvalidNonceError {
+    /// Index of the user operation in the bundle
+    index: usize,
+    /// Revert reason
+    reason: String,
+}
+
+impl fmt::Display for InvalidNonceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "user operation at index {} failed: {}", self.index, self.reason)
+    }
+}
+
+impl std::error::Error for InvalidNonceError {}
+
+/// Error returned by a [SendBundleOp](SendBundleOp) when the bundle wasn't included in the
+/// target block (e.g., by the Flashbots relay), so it can be resubmitted
+#[derive(Debug)]
+pub struct BundleNotIncludedError {
+    /// Block the bundle targeted
+    pub block: Option<U64>,
+}
+
+impl fmt::Display for BundleNotIncludedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "bundle not included in the target block {:?}", self.block)
+    }
+}
+
+impl std::error::Error for BundleNotIncludedError {}
+
+/// Computes the EIP-1559 fees of the bundle transaction from the included user operations. The
+/// priority fee is the lowest priority fee of the user operations, so each of them pays at least
+/// as much per gas as the bundler and the bundler keeps the spread. It's increased by the margin
+/// (in percent) to keep the bundle competitive, but never above the lowest max fee.
+///
+/// # Arguments
+/// * `uos` - Slice of [UserOperations](UserOperation) included in the bundle
+/// * `priority_fee_margin` - Percentage added to the lowest priority fee
+///
+/// # Returns
+/// * `(U256, U256)` - The max fee per gas and the max priority fee per gas
+fn bundle_fees(uos: &[UserOperation], priority_fee_margin: u64) -> (U256, U256) {
+    let max_fee_per_gas = uos.iter().map(|uo| uo.max_fee_per_gas).min().unwrap_or_default();
+    let max_priority_fee_per_gas =
+        uos.iter().map(|uo| uo.max_priority_fee_per_gas).min().unwrap_or_default();
+
+    let max_priority_fee_per_gas = (max_priority_fee_per_gas
+        .saturating_mul(U256::from(100).saturating_add(priority_fee_margin.into())) /
+        U256::from(100))
+    .min(max_fee_per_gas);
+
+    (max_fee_per_gas, max_priority_fee_per_gas)
+}
+
+/// Increases the priority fee of a bundle resubmitted after it wasn't included in the target block
+/// by the escalation (in percent), but never above the max fee.
+///
+/// # Arguments
+/// * `bundle` - The bundle transaction
+/// * `tip_escalation` - Percentage added to the priority fee
+fn escalate_tip(bundle: &mut TypedTransaction, tip_escalation: u64) {
+    if let TypedTransaction::Eip1559(tx) = bundle {
+        if let (Some(max_fee_per_gas), Some(max_priority_fee_per_gas)) =
+            (tx.max_fee_per_gas, tx.max_priority_fee_per_gas)
+        {
+            tx.max_priority_fee_per_gas = Some(
+                (max_priority_fee_per_gas * (100 + tip_escalation) / 100).min(max_fee_per_gas),
+            );
+        }
+    }
+}
+
+/// Tracks the nonce of the next bundle sent from the bundler's wallet, so rapid bundles don't reuse
+/// a nonce the provider isn't aware of yet (shared by the bundlers using the same wallet)
+#[derive(Clone, Debug, Default)]
+pub struct NonceTracker(Arc<RwLock<Option<U256>>>);
+
+impl NonceTracker {
+    /// Reserves the nonce of the next bundle: the nonce returned by the provider, unless the
+    /// bundles sent (or being created) before have already used it
+    pub fn next(&self, provider_nonce: U256) -> U256 {
+        let mut next = self.0.write();
+        let nonce = next.map_or(provider_nonce, |nonce| nonce.max(provider_nonce));
+        *next = Some(nonce + 1);
+        nonce
+    }
+
+    /// Records the nonce of a sent bundle
+    pub fn sent(&self, nonce: U256) {
+        let mut next = self.0.write();
+        *next = Some(next.map_or(nonce + 1, |next| next.max(nonce + 1)));
+    }
+
+    /// Releases the nonce of a bundle that couldn't be created, so the next bundle reuses it (only
+    /// if no nonce was reserved after it)
+    pub fn release(&self, nonce: U256) {
+        let mut next = self.0.write();
+        if *next == Some(nonce + 1) {
+            *next = Some(nonce);
+        }
+    }
+
+    /// Forgets the locally tracked nonce (e.g., the bundle might not have been sent), so the nonce
+    /// returned by the provider is used
+    pub fn reset(&self) {
+        *self.0.write() = None;
+    }
+}
+
+/// Result of sending a bundle of user operations
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SendBundleResult {
+    /// Transaction hash (or bundle hash if the bundle was sent to a relay)
+    pub hash: H256,
+    /// Where the bundle was submitted
+    pub target: BundleStrategy,
+    /// Block the bundle was included in (`None` if the inclusion isn't known yet)
+    pub block: Option<U64>,
+    /// Block the bundle was submitted for (only set if the bundle was sent to a relay)
+    pub target_block: Option<U64>,
+    /// Hashes of the user operations included in the bundle
+    pub uo_hashes: Vec<UserOperationHash>,
+}
+
+impl SendBundleResult {
+    pub fn new(hash: H256, target: BundleStrategy, uo_hashes: Vec<UserOperationHash>) -> Self {
+        Self { hash, target, block: None, target_block: None, uo_hashes }
+    }
+
+    /// Sets the block the bundle was included in
+    pub fn with_block(mut self, block: Option<U64>) -> Self {
+        self.block = block;
+        self
+    }
+
+    /// Sets the block the bundle was submitted for
+    pub fn with_target_block(mut self, target_block: U64) -> Self {
+        self.target_block = Some(target_block);
+        self
+    }
+}
 
 /// A trait for sending the bundler of user operations
 #[async_trait::async_trait]
@@ -19,14 +184,16 @@ pub trait SendBundleOp: Send + Sync + 'static {
     /// # Arguments
     /// * `bundle` - Bundle of user operations as [TypedTransaction](TypedTransaction).
     /// * 'storage_map' - Storage map
+    /// * `uo_hashes` - Hashes of the user operations included in the bundle
     ///
     /// # Returns
-    /// * `H256` - The hash
+    /// * `SendBundleResult` - The hash, submission target, blocks and user operations of the bundle
     async fn send_bundle(
         &self,
         bundle: TypedTransaction,
         storage_map: StorageMap,
-    ) -> eyre::Result<H256>;
+        uo_hashes: Vec<UserOperationHash>,
+    ) -> eyre::Result<SendBundleResult>;
 }
 
 /// The `Bundler` struct is used to represent a bundler with necessary properties
@@ -38,20 +205,50 @@ where
 {
     /// Bundler's wallet
     pub wallet: Wallet,
-    /// Beneficiary address where the gas is refunded after execution
-    pub beneficiary: Address,
+    /// Beneficiary addresses where the gas is refunded after execution, paid in round-robin order
+    /// (can be changed at runtime)
+    pub beneficiaries: Arc<RwLock<Vec<Address>>>,
+    /// Index of the beneficiary of the next bundle (shared between the clones)
+    pub beneficiary_index: Arc<AtomicUsize>,
     /// Entry point contract address
     pub entry_point: Address,
+    /// Version of the entry point (determines the ABI of the `handleOps` call)
+    pub entry_point_version: EntryPointVersion,
     /// Chain the bundler is running on
     pub chain: Chain,
-    /// Minimum balance required
+    /// Minimum balance required to send bundles (bundling is paused below it)
     pub min_balance: U256,
+    /// Balance below which a warning is logged and the bundler refunds itself (defaults to the
+    /// minimum balance)
+    pub balance_warning_threshold: U256,
     /// Ethereum execution client
     pub eth_client: Arc<M>,
     /// Client that sends the bundle to some network
     pub client: Arc<S>,
     /// Whether add access list into tx
     pub enable_access_list: bool,
+    /// Maximum number of times the bundle is rebuilt after dropping user operations with invalid
+    /// nonce (e.g., already included by a competing bundler)
+    pub max_rebuilds: usize,
+    /// Percentage added to the lowest priority fee of the included user operations
+    pub priority_fee_margin: u64,
+    /// Position among the bundler instances sharing a mempool (None if the bundler runs alone)
+    pub instance: Option<BundlerInstance>,
+    /// Block tag used to fetch the nonce of the bundler's wallet (providers differ on whether
+    /// the pending block includes the transactions in their mempool)
+    pub nonce_block: BlockNumber,
+    /// Nonce of the next bundle tracked locally
+    pub nonce_tracker: NonceTracker,
+    /// Percentage added to the estimated gas of the bundle transaction (used as its gas limit)
+    pub gas_limit_buffer: u64,
+    /// Maximum number of submissions of a bundle not included in the target block (each one
+    /// targets the next block)
+    pub max_submissions: usize,
+    /// Percentage by which the priority fee of a resubmitted bundle is increased
+    pub tip_escalation: u64,
+    /// User operations dropped from bundles for an invalid nonce (e.g., already included by a
+    /// competing bundler), to be removed from the mempool
+    pub dropped: Arc<RwLock<Vec<UserOperation>>>,
 }
 
 impl<M, S> Bundler<M, S>
@@ -61,12 +258,16 @@ where
 {
     /// Create a new Bundler thats bundles multiple user operations and sends them as bundle
     ///
+    /// # Arguments
+    /// * `beneficiaries` - Beneficiary addresses the bundles are paid to in round-robin order (the
+    ///   gas is refunded to the bundler if empty)
+    ///
     /// # Returns
     /// * `Self` - A new `Bundler` instance
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         wallet: Wallet,
-        beneficiary: Address,
+        beneficiaries: Vec<Address>,
         entry_point: Address,
         chain: Chain,
         min_balance: U256,
@@ -74,70 +275,236 @@ where
         client: Arc<S>,
         enable_access_list: bool,
     ) -> Self {
+        let beneficiaries =
+            if beneficiaries.is_empty() { vec![wallet.signer.address()] } else { beneficiaries };
         Self {
             wallet,
-            beneficiary,
+            beneficiaries: Arc::new(RwLock::new(beneficiaries)),
+            beneficiary_index: Arc::new(AtomicUsize::new(0)),
             entry_point,
+            entry_point_version: EntryPointVersion::from_address(&entry_point),
             chain,
             min_balance,
+            balance_warning_threshold: min_balance,
             eth_client,
             client,
             enable_access_list,
+            max_rebuilds: MAX_BUNDLE_REBUILDS,
+            priority_fee_margin: 0,
+            instance: None,
+            nonce_block: BlockNumber::Latest,
+            nonce_tracker: NonceTracker::default(),
+            gas_limit_buffer: BUNDLE_GAS_LIMIT_BUFFER,
+            max_submissions: MAX_BUNDLE_SUBMISSIONS,
+            tip_escalation: BUNDLE_TIP_ESCALATION,
+            dropped: Default::default(),
         }
     }
 
+    /// Takes the user operations dropped from bundles for an invalid nonce since the last call
+    pub fn take_dropped(&self) -> Vec<UserOperation> {
+        std::mem::take(&mut *self.dropped.write())
+    }
+
+    /// Sets the maximum number of bundle rebuilds after dropping user operations with invalid
+    /// nonce (0 disables the recovery)
+    pub fn with_max_rebuilds(mut self, max_rebuilds: usize) -> Self {
+        self.max_rebuilds = max_rebuilds;
+        self
+    }
+
+    /// Sets the percentage added to the lowest priority fee of the included user operations (to
+    /// keep the bundle competitive at the cost of a part of the spread)
+    pub fn with_priority_fee_margin(mut self, priority_fee_margin: u64) -> Self {
+        self.priority_fee_margin = priority_fee_margin;
+        self
+    }
+
+    /// Sets the position among the bundler instances sharing a mempool, so the bundler only gets
+    /// the user operations assigned to it from the mempool
+    pub fn with_instance(mut self, instance: Option<BundlerInstance>) -> Self {
+        self.instance = instance;
+        self
+    }
+
+    /// Sets the block tag used to fetch the nonce of the bundler's wallet
+    pub fn with_nonce_block(mut self, nonce_block: BlockNumber) -> Self {
+        self.nonce_block = nonce_block;
+        self
+    }
+
+    /// Sets the nonce tracker (shared by the bundlers using the same wallet)
+    pub fn with_nonce_tracker(mut self, nonce_tracker: NonceTracker) -> Self {
+        self.nonce_tracker = nonce_tracker;
+        self
+    }
+
+    /// Sets the version of the entry point (e.g., the one detected by probing the entry point)
+    pub fn with_entry_point_version(mut self, entry_point_version: EntryPointVersion) -> Self {
+        self.entry_point_version = entry_point_version;
+        self
+    }
+
+    /// Sets the percentage added to the estimated gas of the bundle transaction
+    pub fn with_gas_limit_buffer(mut self, gas_limit_buffer: u64) -> Self {
+        self.gas_limit_buffer = gas_limit_buffer;
+        self
+    }
+
+    /// Sets the maximum number of submissions of a bundle not included in the target block (1
+    /// disables the resubmission)
+    pub fn with_max_submissions(mut self, max_submissions: usize) -> Self {
+        self.max_submissions = max_submissions;
+        self
+    }
+
+    /// Sets the percentage by which the priority fee of a resubmitted bundle is increased
+    pub fn with_tip_escalation(mut self, tip_escalation: u64) -> Self {
+        self.tip_escalation = tip_escalation;
+        self
+    }
+
+    /// Sets the balance below which a warning is logged and the gas is refunded to the bundler
+    pub fn with_balance_warning_threshold(mut self, balance_warning_threshold: U256) -> Self {
+        self.balance_warning_threshold = balance_warning_threshold;
+        self
+    }
+
+    /// Returns the beneficiary address of the next bundle
+    ///
+    /// # Returns
+    /// * `Address` - The beneficiary address
+    pub fn beneficiary(&self) -> Address {
+        let beneficiaries = self.beneficiaries.read();
+        beneficiaries[self.beneficiary_index.load(Ordering::Relaxed) % beneficiaries.len()]
+    }
+
+    /// Returns the beneficiary addresses the bundles are paid to in round-robin order
+    pub fn beneficiaries(&self) -> Vec<Address> {
+        self.beneficiaries.read().clone()
+    }
+
+    /// Sets the beneficiary address used for the subsequent bundles (replacing the list of
+    /// beneficiaries)
+    ///
+    /// # Arguments
+    /// * `beneficiary` - The new beneficiary address (must be non-zero)
+    ///
+    /// # Returns
+    /// * `()` - Error if the beneficiary address is zero
+    pub fn set_beneficiary(&self, beneficiary: Address) -> eyre::Result<()> {
+        if beneficiary.is_zero() {
+            return Err(eyre::eyre!("Beneficiary address can't be zero"));
+        }
+
+        *self.beneficiaries.write() = vec![beneficiary];
+        self.beneficiary_index.store(0, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Fetches the balance of the bundler's wallet and logs a warning if it's below the warning
+    /// threshold
+    ///
+    /// # Returns
+    /// * `U256` - The balance of the bundler's wallet
+    pub async fn check_balance(&self) -> eyre::Result<U256> {
+        let balance = self.eth_client.get_balance(self.wallet.signer.address(), None).await?;
+
+        if balance < self.balance_warning_threshold || balance < self.min_balance {
+            warn!(
+                "Balance of the bundler account {:?} is low: {balance} (warning threshold: {}, minimum balance: {})",
+                self.wallet.signer.address(),
+                self.balance_warning_threshold,
+                self.min_balance
+            );
+        }
+
+        Ok(balance)
+    }
+
     /// Functions that generates a bundle of user operations (i.e.,
-    /// [TypedTransaction](TypedTransaction)).
+    /// [TypedTransaction](TypedTransaction)). The nonce of the bundle is reserved with the
+    /// [NonceTracker](NonceTracker) and released if the bundle can't be created.
     ///
     /// # Arguments
     /// * `uos` - Slice of [UserOperations](UserOperation)
+    /// * `beneficiary` - Beneficiary address of the bundle (the bundler itself is refunded instead
+    ///   if its balance is below the warning threshold)
     ///
     /// # Returns
     /// * `TypedTransaction` - A [TypedTransaction](TypedTransaction)
-    async fn create_bundle(&self, uos: &[UserOperation]) -> eyre::Result<TypedTransaction> {
-        let ep = EntryPointAPI::new(self.entry_point, self.eth_client.clone());
+    async fn create_bundle(
+        &self,
+        uos: &[UserOperation],
+        beneficiary: Address,
+    ) -> eyre::Result<TypedTransaction> {
+        let nonce = self.nonce_tracker.next(
+            self.eth_client
+                .get_transaction_count(self.wallet.signer.address(), Some(self.nonce_block.into()))
+                .await?,
+        );
 
-        let nonce =
-            self.eth_client.get_transaction_count(self.wallet.signer.address(), None).await?;
-        let balance = self.eth_client.get_balance(self.wallet.signer.address(), None).await?;
-        let beneficiary = if balance < self.min_balance {
+        let bundle = self.create_bundle_with_nonce(uos, beneficiary, nonce).await;
+        if bundle.is_err() {
+            self.nonce_tracker.release(nonce);
+        }
+        bundle
+    }
+
+    /// Creates a bundle transaction with the reserved nonce
+    async fn create_bundle_with_nonce(
+        &self,
+        uos: &[UserOperation],
+        beneficiary: Address,
+        nonce: U256,
+    ) -> eyre::Result<TypedTransaction> {
+        let balance = self.check_balance().await?;
+        let beneficiary = if balance < self.min_balance || balance < self.balance_warning_threshold
+        {
             self.wallet.signer.address()
         } else {
-            self.beneficiary
+            beneficiary
         };
 
-        let mut tx: TypedTransaction = ep
-            .handle_ops(
-                uos.iter().cloned().map(|uo| uo.user_operation.into()).collect(),
-                beneficiary,
-            )
-            .tx;
+        let data = self.entry_point_version.handle_ops_call_data(
+            uos.iter().map(|uo| uo.user_operation.clone()).collect(),
+            beneficiary,
+        )?;
+        let mut tx: TypedTransaction = Eip1559TransactionRequest::new()
+            .to(self.entry_point)
+            .from(self.wallet.signer.address())
+            .data(data)
+            .into();
 
         let accesslist = if self.enable_access_list {
-            let accesslist = self.eth_client.create_access_list(&tx, None).await?.access_list;
+            let accesslist = self
+                .eth_client
+                .create_access_list(&tx, None)
+                .await
+                .map_err(Self::handle_ops_error)?
+                .access_list;
             tx.set_access_list(accesslist.clone());
             accesslist
         } else {
             Default::default()
         };
-        let estimated_gas = self.eth_client.estimate_gas(&tx, None).await?;
-
-        let mut max_fee_per_gas: U256 = U256::zero();
-        let mut max_priority_fee_per_gas: U256 = U256::zero();
+        // the estimation can fall short for large bundles (e.g., the gas refunds and the 63/64
+        // rule of the inner calls), so the gas limit is increased by the buffer
+        let estimated_gas =
+            self.eth_client.estimate_gas(&tx, None).await.map_err(Self::handle_ops_error)?;
+        let gas_limit = estimated_gas * (100 + self.gas_limit_buffer) / 100;
 
-        for uo in uos {
-            max_fee_per_gas += uo.max_fee_per_gas;
-            max_priority_fee_per_gas += uo.max_priority_fee_per_gas;
-        }
+        let (max_fee_per_gas, max_priority_fee_per_gas) =
+            bundle_fees(uos, self.priority_fee_margin);
 
         tx = TypedTransaction::Eip1559(Eip1559TransactionRequest {
             to: tx.to().cloned(),
             from: Some(self.wallet.signer.address()),
             data: tx.data().cloned(),
             chain_id: Some(U64::from(self.chain.id())),
-            max_priority_fee_per_gas: Some(max_priority_fee_per_gas / uos.len()),
-            max_fee_per_gas: Some(max_fee_per_gas / uos.len()),
-            gas: Some(estimated_gas),
+            max_priority_fee_per_gas: Some(max_priority_fee_per_gas),
+            max_fee_per_gas: Some(max_fee_per_gas),
+            gas: Some(gas_limit),
             nonce: Some(nonce),
             value: None,
             access_list: accesslist,
@@ -146,6 +513,19 @@ where
         Ok(tx)
     }
 
+    /// Converts the error of a `handleOps` call. Reverts caused by an invalid sender nonce are
+    /// returned as [InvalidNonceError](InvalidNonceError), so that the bundle can be rebuilt.
+    fn handle_ops_error(err: M::Error) -> eyre::Report {
+        match err.as_error_response().map(EntryPointError::from_json_rpc_error) {
+            Some(Ok(EntryPointAPIErrors::FailedOp(op)))
+                if op.reason.starts_with(INVALID_NONCE_REASON) =>
+            {
+                InvalidNonceError { index: op.op_index.as_usize(), reason: op.reason }.into()
+            }
+            _ => err.into(),
+        }
+    }
+
     /// Send a bundle of [UserOperations](UserOperation)
     ///
     /// # Arguments
@@ -153,17 +533,28 @@ where
     /// * `storage_map` - Storage map
     ///
     /// # Returns
-    /// * `H256` - The hash
+    /// * `SendBundleResult` - The hash, submission target, block and included user operations of
+    ///   the bundle
     pub async fn send_bundle(
         &self,
         uos: &Vec<UserOperation>,
         storage_map: StorageMap,
-    ) -> eyre::Result<Option<H256>> {
+    ) -> eyre::Result<Option<SendBundleResult>> {
         if uos.is_empty() {
             info!("Skipping creating a new bundle, no user operations");
             return Ok(None);
         };
 
+        let balance = self.check_balance().await?;
+        if balance < self.min_balance {
+            warn!(
+                "Skipping creating a new bundle, balance of the bundler account {:?} is below the minimum balance: {balance} < {}",
+                self.wallet.signer.address(),
+                self.min_balance
+            );
+            return Ok(None);
+        }
+
         info!(
             "Creating a new bundle with {} user operations: {:?}",
             uos.len(),
@@ -171,17 +562,598 @@ where
         );
         trace!("Bundle content: {uos:?}");
 
-        let bundle = self.create_bundle(uos).await?;
-        let hash = self.client.send_bundle(bundle, storage_map).await?;
+        // the beneficiary is kept when the bundle is rebuilt, the rotation only advances once the
+        // bundle is sent
+        let beneficiary = self.beneficiary();
+        let mut uos = uos.clone();
+        let mut rebuilds = 0;
+        let mut bundle = loop {
+            let err = match self.create_bundle(&uos, beneficiary).await {
+                Ok(bundle) => break bundle,
+                Err(err) => err,
+            };
+
+            // user operation was probably included by another bundler, drop it and rebuild
+            match err.downcast_ref::<InvalidNonceError>() {
+                Some(InvalidNonceError { index, reason })
+                    if rebuilds < self.max_rebuilds && *index < uos.len() =>
+                {
+                    let uo = uos.remove(*index);
+                    warn!(
+                        "Dropping user operation {:?} from the bundle ({reason}), rebuilding bundle",
+                        uo.hash
+                    );
+                    self.dropped.write().push(uo);
+                    rebuilds += 1;
+                }
+                _ => return Err(err),
+            }
+
+            if uos.is_empty() {
+                info!("Skipping sending the bundle, no user operations left");
+                return Ok(None);
+            }
+        };
+        let nonce = bundle.nonce().cloned();
+        let uo_hashes: Vec<UserOperationHash> = uos.iter().map(|uo| uo.hash).collect();
+        let mut submissions = 1;
+        let res = loop {
+            let err = match self
+                .client
+                .send_bundle(bundle.clone(), storage_map.clone(), uo_hashes.clone())
+                .await
+            {
+                Ok(res) => break res,
+                Err(err) => err,
+            };
+
+            // bundle wasn't included in the target block, resubmit it with a higher tip
+            match err.downcast_ref::<BundleNotIncludedError>() {
+                Some(BundleNotIncludedError { block }) if submissions < self.max_submissions => {
+                    escalate_tip(&mut bundle, self.tip_escalation);
+                    submissions += 1;
+                    warn!(
+                        "Bundle not included in the target block {block:?}, resubmitting with priority fee {:?} ({submissions}/{})",
+                        bundle.as_eip1559_ref().and_then(|tx| tx.max_priority_fee_per_gas),
+                        self.max_submissions
+                    );
+                }
+                _ => {
+                    self.nonce_tracker.reset();
+                    return Err(err);
+                }
+            }
+        };
+        if let Some(nonce) = nonce {
+            self.nonce_tracker.sent(nonce);
+        }
+        self.beneficiary_index.fetch_add(1, Ordering::Relaxed);
 
         info!(
-            "Bundle successfully sent, hash: {:?}, account: {:?}, entry point: {:?}, beneficiary: {:?}",
-            hash,
+            "Bundle successfully sent, hash: {:?}, target: {:?}, block: {:?}, target block: {:?}, account: {:?}, entry point: {:?}, beneficiary: {:?}",
+            res.hash,
+            res.target,
+            res.block,
+            res.target_block,
             self.wallet.signer.address(),
             self.entry_point,
-            self.beneficiary
+            beneficiary
+        );
+
+        Ok(Some(res))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::{
+        abi::AbiEncode,
+        contract::EthError,
+        providers::{JsonRpcError, MockProvider, MockResponse, Provider},
+        types::Bytes,
+    };
+    use parking_lot::Mutex;
+    use silius_contracts::{entry_point::EntryPointAPI, FailedOp};
+    use silius_primitives::UserOperationSigned;
+
+    #[derive(Default)]
+    struct MockClient(Mutex<Vec<TypedTransaction>>);
+
+    #[async_trait::async_trait]
+    impl SendBundleOp for MockClient {
+        async fn send_bundle(
+            &self,
+            bundle: TypedTransaction,
+            _storage_map: StorageMap,
+            uo_hashes: Vec<UserOperationHash>,
+        ) -> eyre::Result<SendBundleResult> {
+            self.0.lock().push(bundle);
+            Ok(SendBundleResult::new(H256::random(), BundleStrategy::EthereumClient, uo_hashes))
+        }
+    }
+
+    /// Relay including the bundle only on the given submission
+    struct MockRelay {
+        bundles: Mutex<Vec<TypedTransaction>>,
+        included_at: usize,
+    }
+
+    #[async_trait::async_trait]
+    impl SendBundleOp for MockRelay {
+        async fn send_bundle(
+            &self,
+            bundle: TypedTransaction,
+            _storage_map: StorageMap,
+            uo_hashes: Vec<UserOperationHash>,
+        ) -> eyre::Result<SendBundleResult> {
+            let mut bundles = self.bundles.lock();
+            bundles.push(bundle);
+            if bundles.len() < self.included_at {
+                return Err(BundleNotIncludedError { block: Some(U64::from(bundles.len())) }.into());
+            }
+            let target_block = U64::from(bundles.len());
+            Ok(SendBundleResult::new(H256::random(), BundleStrategy::Flashbots, uo_hashes)
+                .with_block(Some(target_block))
+                .with_target_block(target_block))
+        }
+    }
+
+    fn test_bundler<S: SendBundleOp>(
+        eth_client: Arc<Provider<MockProvider>>,
+        beneficiaries: Vec<Address>,
+        min_balance: U256,
+        client: Arc<S>,
+    ) -> Bundler<Provider<MockProvider>, S> {
+        let wallet = Wallet::from_private_key(
+            "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318",
+            1,
+            false,
+            None,
+        )
+        .unwrap();
+        Bundler::new(
+            wallet,
+            beneficiaries,
+            Address::random(),
+            Chain::from(1),
+            min_balance,
+            eth_client,
+            client,
+            false,
+        )
+    }
+
+    fn invalid_nonce_response(index: usize) -> MockResponse {
+        let data = [
+            FailedOp::selector().as_slice(),
+            &(U256::from(index), "AA25 invalid account nonce".to_string()).encode(),
+        ]
+        .concat();
+        MockResponse::Error(JsonRpcError {
+            code: -32000,
+            message: "execution reverted".into(),
+            data: Some(serde_json::Value::String(Bytes::from(data).to_string())),
+        })
+    }
+
+    #[tokio::test]
+    async fn set_beneficiary() {
+        let (provider, mock) = Provider::mocked();
+        let eth_client = Arc::new(provider);
+        let bundler =
+            test_bundler(eth_client.clone(), vec![], U256::zero(), Arc::new(MockClient::default()));
+
+        assert!(bundler.set_beneficiary(Address::zero()).is_err());
+        assert_eq!(bundler.beneficiary(), bundler.wallet.signer.address());
+
+        let beneficiary = Address::random();
+        bundler.set_beneficiary(beneficiary).unwrap();
+        assert_eq!(bundler.beneficiary(), beneficiary);
+
+        // mocked responses are returned in reverse order (gas estimate, balance, nonce)
+        mock.push(U256::from(1_000_000)).unwrap();
+        mock.push(U256::from(1_000_000_000)).unwrap();
+        mock.push(U256::zero()).unwrap();
+
+        let uo = UserOperation::from_user_operation_signed(
+            Default::default(),
+            UserOperationSigned::default(),
         );
+        let bundle = bundler.create_bundle(&[uo], bundler.beneficiary()).await.unwrap();
+
+        // beneficiary is the second argument of `handleOps`
+        let data = bundle.data().expect("bundle should have call data");
+        assert_eq!(&data[48..68], beneficiary.as_bytes());
+    }
+
+    #[tokio::test]
+    async fn rebuild_bundle_after_competing_inclusion() {
+        let (provider, mock) = Provider::mocked();
+        let eth_client = Arc::new(provider);
+        let client = Arc::new(MockClient::default());
+        let bundler = test_bundler(eth_client.clone(), vec![], U256::zero(), client.clone());
+
+        let uos: Vec<UserOperation> = (0..3)
+            .map(|i| {
+                UserOperation::from_user_operation_signed(
+                    UserOperationHash(H256::from_low_u64_be(i + 1)),
+                    UserOperationSigned {
+                        sender: Address::random(),
+                        max_fee_per_gas: U256::from(1),
+                        ..Default::default()
+                    },
+                )
+            })
+            .collect();
+
+        // mocked responses are returned in reverse order: the first attempt fails as the second
+        // user operation was already included by another bundler, the second attempt succeeds
+        mock.push(U256::from(1_000_000)).unwrap();
+        mock.push(U256::from(1_000_000_000)).unwrap();
+        mock.push(U256::zero()).unwrap();
+        mock.push_response(invalid_nonce_response(1));
+        mock.push(U256::from(1_000_000_000)).unwrap();
+        mock.push(U256::zero()).unwrap();
+        mock.push(U256::from(1_000_000_000)).unwrap();
+
+        let res = bundler.send_bundle(&uos, StorageMap::default()).await.unwrap().unwrap();
+        assert_eq!(res.uo_hashes, vec![uos[0].hash, uos[2].hash]);
+
+        let bundles = client.0.lock().clone();
+        assert_eq!(bundles.len(), 1);
+        let expected = EntryPointAPI::new(bundler.entry_point, eth_client.clone())
+            .handle_ops(
+                vec![uos[0].user_operation.clone().into(), uos[2].user_operation.clone().into()],
+                bundler.beneficiary(),
+            )
+            .tx;
+        assert_eq!(bundles[0].data(), expected.data());
+        assert_eq!(bundles[0].gas(), Some(&U256::from(1_000_000)));
+        // the nonce of the failed attempt is reused
+        assert_eq!(bundles[0].nonce(), Some(&U256::zero()));
+
+        // the dropped user operation is handed over for removal from the mempool
+        let dropped: Vec<_> = bundler.take_dropped().into_iter().map(|uo| uo.hash).collect();
+        assert_eq!(dropped, vec![uos[1].hash]);
+        assert!(bundler.take_dropped().is_empty());
+
+        // without recovery the error is returned
+        let bundler = bundler.with_max_rebuilds(0);
+        mock.push_response(invalid_nonce_response(0));
+        mock.push(U256::from(1_000_000_000)).unwrap();
+        mock.push(U256::zero()).unwrap();
+        mock.push(U256::from(1_000_000_000)).unwrap();
+
+        assert!(bundler.send_bundle(&uos, StorageMap::default()).await.is_err());
+        assert_eq!(client.0.lock().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn pause_bundling_below_min_balance() {
+        let (provider, mock) = Provider::mocked();
+        let eth_client = Arc::new(provider);
+        let client = Arc::new(MockClient::default());
+        let beneficiary = Address::random();
+        let bundler = test_bundler(
+            eth_client.clone(),
+            vec![beneficiary],
+            U256::from(1_000_000_000),
+            client.clone(),
+        )
+        .with_balance_warning_threshold(U256::from(2_000_000_000));
+
+        let uos = vec![UserOperation::from_user_operation_signed(
+            UserOperationHash(H256::from_low_u64_be(1)),
+            UserOperationSigned { max_fee_per_gas: U256::from(1), ..Default::default() },
+        )];
+
+        // below the minimum balance, no bundle is sent
+        mock.push(U256::from(999_999_999)).unwrap();
+        assert!(bundler.send_bundle(&uos, StorageMap::default()).await.unwrap().is_none());
+        assert!(client.0.lock().is_empty());
+
+        // below the warning threshold, the bundles refund the bundler itself (so the balance can
+        // recover), the beneficiary is paid otherwise
+        for balance in [1_500_000_000_u64, 2_000_000_000] {
+            // mocked responses are returned in reverse order (gas estimate, balance, nonce,
+            // balance)
+            mock.push(U256::from(1_000_000)).unwrap();
+            mock.push(U256::from(balance)).unwrap();
+            mock.push(U256::zero()).unwrap();
+            mock.push(U256::from(balance)).unwrap();
+            assert!(bundler.send_bundle(&uos, StorageMap::default()).await.unwrap().is_some());
+        }
+
+        // beneficiary is the second argument of `handleOps`
+        let paid: Vec<Address> = client
+            .0
+            .lock()
+            .iter()
+            .map(|bundle| Address::from_slice(&bundle.data().unwrap()[48..68]))
+            .collect();
+        assert_eq!(paid, vec![bundler.wallet.signer.address(), beneficiary]);
+    }
+
+    #[tokio::test]
+    async fn bundle_fees_from_included_user_operations() {
+        let (provider, mock) = Provider::mocked();
+        let eth_client = Arc::new(provider);
+        let bundler = test_bundler(
+            eth_client.clone(),
+            vec![Address::random()],
+            U256::zero(),
+            Arc::new(MockClient::default()),
+        )
+        .with_priority_fee_margin(10);
+
+        let uos: Vec<UserOperation> = [(100, 10), (80, 20), (120, 30)]
+            .into_iter()
+            .map(|(max_fee, max_priority_fee)| {
+                UserOperation::from_user_operation_signed(
+                    Default::default(),
+                    UserOperationSigned {
+                        sender: Address::random(),
+                        max_fee_per_gas: U256::from(max_fee),
+                        max_priority_fee_per_gas: U256::from(max_priority_fee),
+                        ..Default::default()
+                    },
+                )
+            })
+            .collect();
+
+        // mocked responses are returned in reverse order (gas estimate, balance, nonce)
+        mock.push(U256::from(1_000_000)).unwrap();
+        mock.push(U256::from(1_000_000_000)).unwrap();
+        mock.push(U256::zero()).unwrap();
+
+        let bundle = match bundler.create_bundle(&uos, bundler.beneficiary()).await.unwrap() {
+            TypedTransaction::Eip1559(tx) => tx,
+            _ => panic!("bundle should be an EIP-1559 transaction"),
+        };
+
+        // the lowest priority fee plus 10% margin, the lowest max fee
+        assert_eq!(bundle.max_priority_fee_per_gas, Some(U256::from(11)));
+        assert_eq!(bundle.max_fee_per_gas, Some(U256::from(80)));
+
+        // the priority fee never exceeds the max fee
+        assert_eq!(bundle_fees(&uos, 1000), (U256::from(80), U256::from(80)));
+        assert_eq!(bundle_fees(&uos, 0), (U256::from(80), U256::from(10)));
+    }
+
+    #[tokio::test]
+    async fn resubmit_bundle_with_escalating_tip() {
+        let (provider, mock) = Provider::mocked();
+        let eth_client = Arc::new(provider);
+        let client = Arc::new(MockRelay { bundles: Mutex::new(vec![]), included_at: 3 });
+        let bundler = test_bundler(eth_client.clone(), vec![], U256::zero(), client.clone())
+            .with_max_submissions(3)
+            .with_tip_escalation(20);
+
+        let uos = vec![UserOperation::from_user_operation_signed(
+            UserOperationHash(H256::from_low_u64_be(1)),
+            UserOperationSigned {
+                max_fee_per_gas: U256::from(100),
+                max_priority_fee_per_gas: U256::from(10),
+                ..Default::default()
+            },
+        )];
+
+        // mocked responses are returned in reverse order (gas estimate, balance, nonce, balance)
+        mock.push(U256::from(1_000_000)).unwrap();
+        mock.push(U256::from(1_000_000_000)).unwrap();
+        mock.push(U256::zero()).unwrap();
+        mock.push(U256::from(1_000_000_000)).unwrap();
+
+        // the relay includes the bundle on the third submission
+        let res = bundler.send_bundle(&uos, StorageMap::default()).await.unwrap().unwrap();
+        assert_eq!(res.uo_hashes, vec![uos[0].hash]);
+        assert_eq!(res.block, Some(U64::from(3)));
+        assert_eq!(res.target_block, Some(U64::from(3)));
+
+        // the tip is escalated by 20% each round, the rest of the bundle is unchanged
+        let bundles = client.bundles.lock().clone();
+        let tips = bundles
+            .iter()
+            .map(|bundle| bundle.as_eip1559_ref().and_then(|tx| tx.max_priority_fee_per_gas))
+            .collect::<Vec<_>>();
+        assert_eq!(tips, vec![Some(U256::from(10)), Some(U256::from(12)), Some(U256::from(14))]);
+        assert!(bundles.iter().all(
+            |bundle| bundle.nonce() == bundles[0].nonce() && bundle.data() == bundles[0].data()
+        ));
+
+        // the bundler gives up after the last submission
+        let client = Arc::new(MockRelay { bundles: Mutex::new(vec![]), included_at: 4 });
+        let bundler =
+            test_bundler(eth_client, vec![], U256::zero(), client.clone()).with_max_submissions(3);
+        mock.push(U256::from(1_000_000)).unwrap();
+        mock.push(U256::from(1_000_000_000)).unwrap();
+        mock.push(U256::zero()).unwrap();
+        mock.push(U256::from(1_000_000_000)).unwrap();
+
+        let err = bundler.send_bundle(&uos, StorageMap::default()).await.unwrap_err();
+        assert!(err.downcast_ref::<BundleNotIncludedError>().is_some());
+        assert_eq!(client.bundles.lock().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn bundle_gas_limit_buffer() {
+        let (provider, mock) = Provider::mocked();
+        let eth_client = Arc::new(provider);
+        let bundler = test_bundler(
+            eth_client.clone(),
+            vec![Address::random()],
+            U256::zero(),
+            Arc::new(MockClient::default()),
+        )
+        .with_gas_limit_buffer(25);
+
+        let uos = vec![UserOperation::from_user_operation_signed(
+            Default::default(),
+            UserOperationSigned { max_fee_per_gas: U256::from(1), ..Default::default() },
+        )];
+
+        // mocked responses are returned in reverse order (gas estimate, balance, nonce)
+        mock.push(U256::from(1_000_000)).unwrap();
+        mock.push(U256::from(1_000_000_000)).unwrap();
+        mock.push(U256::zero()).unwrap();
+        let bundle = bundler.create_bundle(&uos, bundler.beneficiary()).await.unwrap();
+        assert_eq!(bundle.gas(), Some(&U256::from(1_250_000)));
+
+        // without the buffer, the gas limit is the estimated gas
+        let bundler = bundler.with_gas_limit_buffer(0);
+        mock.push(U256::from(1_000_000)).unwrap();
+        mock.push(U256::from(1_000_000_000)).unwrap();
+        mock.push(U256::zero()).unwrap();
+        let bundle = bundler.create_bundle(&uos, bundler.beneficiary()).await.unwrap();
+        assert_eq!(bundle.gas(), Some(&U256::from(1_000_000)));
+    }
+
+    #[tokio::test]
+    async fn bundle_entry_point_version() {
+        let (provider, mock) = Provider::mocked();
+        let eth_client = Arc::new(provider);
+        let bundler = test_bundler(
+            eth_client.clone(),
+            vec![Address::random()],
+            U256::zero(),
+            Arc::new(MockClient::default()),
+        )
+        .with_entry_point_version(EntryPointVersion::V0_7);
+
+        let uos = vec![UserOperation::from_user_operation_signed(
+            Default::default(),
+            UserOperationSigned { max_fee_per_gas: U256::from(1), ..Default::default() },
+        )];
+
+        // mocked responses are returned in reverse order (gas estimate, balance, nonce)
+        mock.push(U256::from(1_000_000)).unwrap();
+        mock.push(U256::from(1_000_000_000)).unwrap();
+        mock.push(U256::zero()).unwrap();
+        let bundle = bundler.create_bundle(&uos, bundler.beneficiary()).await.unwrap();
+        let expected = EntryPointVersion::V0_7
+            .handle_ops_call_data(vec![uos[0].user_operation.clone()], bundler.beneficiary())
+            .unwrap();
+        assert_eq!(bundle.data(), Some(&expected));
+
+        // user operations that can't be packed for the entry point v0.7 fail the bundle (and the
+        // reserved nonce is released)
+        let uos = vec![UserOperation::from_user_operation_signed(
+            Default::default(),
+            UserOperationSigned { max_fee_per_gas: U256::MAX, ..Default::default() },
+        )];
+        mock.push(U256::from(1_000_000_000)).unwrap();
+        mock.push(U256::from(1)).unwrap();
+        assert!(bundler.create_bundle(&uos, bundler.beneficiary()).await.is_err());
+        assert_eq!(bundler.nonce_tracker.next(1.into()), 1.into());
+    }
+
+    #[test]
+    fn nonce_reservation() {
+        let tracker = NonceTracker::default();
+        assert_eq!(tracker.next(3.into()), 3.into());
+
+        // the nonce is reserved before the bundle is sent (e.g., the bundlers of other entry
+        // points sharing the wallet)
+        assert_eq!(tracker.next(3.into()), 4.into());
+
+        // a bundle sent with an earlier nonce doesn't move the tracked nonce back
+        tracker.sent(3.into());
+        assert_eq!(tracker.next(3.into()), 5.into());
+
+        // only the last reservation is released
+        tracker.release(4.into());
+        assert_eq!(tracker.next(3.into()), 6.into());
+        tracker.release(6.into());
+        assert_eq!(tracker.next(3.into()), 6.into());
+
+        // the provider is ahead
+        assert_eq!(tracker.next(10.into()), 10.into());
+    }
+
+    #[tokio::test]
+    async fn nonce_sequencing_across_rapid_bundles() {
+        let (provider, mock) = Provider::mocked();
+        let eth_client = Arc::new(provider);
+        let client = Arc::new(MockClient::default());
+        let bundler = test_bundler(eth_client.clone(), vec![], U256::zero(), client.clone())
+            .with_nonce_block(BlockNumber::Pending);
+
+        let uos = vec![UserOperation::from_user_operation_signed(
+            UserOperationHash(H256::from_low_u64_be(1)),
+            UserOperationSigned {
+                sender: Address::random(),
+                max_fee_per_gas: U256::from(1),
+                ..Default::default()
+            },
+        )];
+
+        // the provider isn't aware of the bundles sent before (returns the same nonce), and then
+        // catches up with a higher nonce (e.g., transactions sent from the wallet by others)
+        for provider_nonce in [3, 3, 3, 7] {
+            // mocked responses are returned in reverse order (gas estimate, balance, nonce,
+            // balance)
+            mock.push(U256::from(1_000_000)).unwrap();
+            mock.push(U256::from(1_000_000_000)).unwrap();
+            mock.push(U256::from(provider_nonce)).unwrap();
+            mock.push(U256::from(1_000_000_000)).unwrap();
+
+            assert!(bundler.send_bundle(&uos, StorageMap::default()).await.unwrap().is_some());
+        }
+
+        let nonces: Vec<U256> =
+            client.0.lock().iter().map(|bundle| *bundle.nonce().unwrap()).collect();
+        assert_eq!(nonces, vec![3.into(), 4.into(), 5.into(), 7.into()]);
+    }
+
+    #[tokio::test]
+    async fn round_robin_beneficiaries() {
+        let (provider, mock) = Provider::mocked();
+        let eth_client = Arc::new(provider);
+        let client = Arc::new(MockClient::default());
+        let beneficiaries = vec![Address::random(), Address::random()];
+        let bundler =
+            test_bundler(eth_client.clone(), beneficiaries.clone(), U256::zero(), client.clone());
+        assert_eq!(bundler.beneficiaries(), beneficiaries);
+
+        let uos = vec![UserOperation::from_user_operation_signed(
+            UserOperationHash(H256::from_low_u64_be(1)),
+            UserOperationSigned {
+                sender: Address::random(),
+                max_fee_per_gas: U256::from(1),
+                ..Default::default()
+            },
+        )];
+
+        // a bundle which can't be created doesn't advance the rotation
+        mock.push_response(MockResponse::Error(JsonRpcError {
+            code: -32000,
+            message: "nonce unavailable".into(),
+            data: None,
+        }));
+        mock.push(U256::from(1_000_000_000)).unwrap();
+        assert!(bundler.send_bundle(&uos, StorageMap::default()).await.is_err());
+        assert_eq!(bundler.beneficiary(), beneficiaries[0]);
+
+        // the rotation is shared with the clones of the bundler
+        for bundler in [bundler.clone(), bundler.clone(), bundler] {
+            // mocked responses are returned in reverse order (gas estimate, balance, nonce,
+            // balance)
+            mock.push(U256::from(1_000_000)).unwrap();
+            mock.push(U256::from(1_000_000_000)).unwrap();
+            mock.push(U256::zero()).unwrap();
+            mock.push(U256::from(1_000_000_000)).unwrap();
+
+            assert!(bundler.send_bundle(&uos, StorageMap::default()).await.unwrap().is_some());
+        }
 
-        Ok(Some(hash))
+        // beneficiary is the second argument of `handleOps`
+        let paid: Vec<Address> = client
+            .0
+            .lock()
+            .iter()
+            .map(|bundle| Address::from_slice(&bundle.data().unwrap()[48..68]))
+            .collect();
+        assert_eq!(paid, vec![beneficiaries[0], beneficiaries[1], beneficiaries[0]]);
     }
 }
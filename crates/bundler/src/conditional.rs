@@ -1,24 +1,38 @@
-use crate::bundler::SendBundleOp;
+use crate::bundler::{SendBundleOp, SendBundleResult};
 use alloy_chains::{Chain, NamedChain};
 use ethers::{
     middleware::SignerMiddleware,
-    providers::Middleware,
+    providers::{Middleware, MiddlewareError},
     signers::LocalWallet,
     types::{
         transaction::{
             conditional::{AccountStorage, ConditionalOptions},
             eip2718::TypedTransaction,
         },
-        Address, H256,
+        Address,
     },
 };
-use silius_primitives::{simulation::StorageMap, Wallet};
-use std::{collections::HashMap, sync::Arc, time::Duration};
-use tracing::trace;
+use silius_primitives::{
+    bundler::BundleStrategy, simulation::StorageMap, UserOperationHash, Wallet,
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+use tracing::{trace, warn};
+
+/// JSON-RPC error code returned when the method isn't supported by the endpoint
+const METHOD_NOT_FOUND_CODE: i64 = -32601;
 
 /// A type alias for the Ethereum Conditional Signer client
 #[derive(Clone)]
-pub struct ConditionalClient<M>(pub SignerMiddleware<Arc<M>, LocalWallet>);
+pub struct ConditionalClient<M>(
+    pub SignerMiddleware<Arc<M>, LocalWallet>,
+    /// Whether the endpoint supports `eth_sendRawTransactionConditional` (unknown until the
+    /// first bundle is sent)
+    Arc<OnceLock<bool>>,
+);
 
 #[async_trait::async_trait]
 impl<M> SendBundleOp for ConditionalClient<M>
@@ -26,19 +40,22 @@ where
     M: Middleware + 'static,
 {
     /// Send a bundle of user operations to the Ethereum execution client
-    /// over conditional RPC method.
+    /// over conditional RPC method. If the endpoint doesn't support the conditional RPC method,
+    /// the bundle (and all the following ones) is sent as a regular transaction.
     ///
     /// # Arguments
     /// * `bundle` - Bundle of user operations as [TypedTransaction](TypedTransaction).
     /// * 'storage_map' - Storage map
+    /// * `uo_hashes` - Hashes of the user operations included in the bundle
     ///
     /// # Returns
-    /// * `H256` - The transaction hash
+    /// * `SendBundleResult` - The transaction hash and the block the transaction was included in
     async fn send_bundle(
         &self,
         bundle: TypedTransaction,
         storage_map: StorageMap,
-    ) -> eyre::Result<H256> {
+        uo_hashes: Vec<UserOperationHash>,
+    ) -> eyre::Result<SendBundleResult> {
         trace!("Sending transaction to the conditional endpoint: {bundle:?}");
 
         let mut known_accounts: HashMap<Address, AccountStorage> = HashMap::default();
@@ -53,31 +70,53 @@ where
 
         let signed_tx = self.0.sign_transaction(bundle).await?;
 
-        let prefix: Option<String> = if self.0.get_chainid().await? ==
-            Chain::from_named(NamedChain::Polygon).id().into() ||
-            self.0.get_chainid().await? == Chain::from_named(NamedChain::PolygonAmoy).id().into()
-        {
-            Some("bor".to_string())
+        let (tx, target) = if self.1.get() == Some(&false) {
+            (self.0.send_raw_transaction(signed_tx).await?, BundleStrategy::EthereumClient)
         } else {
-            None
-        };
+            let prefix: Option<String> = if self.0.get_chainid().await? ==
+                Chain::from_named(NamedChain::Polygon).id().into() ||
+                self.0.get_chainid().await? ==
+                    Chain::from_named(NamedChain::PolygonAmoy).id().into()
+            {
+                Some("bor".to_string())
+            } else {
+                None
+            };
 
-        let tx = self
-            .0
-            .send_raw_transaction_conditional(
-                signed_tx,
-                prefix,
-                ConditionalOptions { known_accounts, ..Default::default() },
-            )
-            .await?
-            .interval(Duration::from_millis(75));
+            match self
+                .0
+                .send_raw_transaction_conditional(
+                    signed_tx.clone(),
+                    prefix,
+                    ConditionalOptions { known_accounts, ..Default::default() },
+                )
+                .await
+            {
+                Ok(tx) => {
+                    self.1.get_or_init(|| true);
+                    (tx, BundleStrategy::Conditional)
+                }
+                Err(err)
+                    if self.1.get().is_none() &&
+                        err.as_error_response()
+                            .is_some_and(|err| err.code == METHOD_NOT_FOUND_CODE) =>
+                {
+                    warn!("The endpoint doesn't support eth_sendRawTransactionConditional, sending bundles as regular transactions");
+                    self.1.get_or_init(|| false);
+                    (self.0.send_raw_transaction(signed_tx).await?, BundleStrategy::EthereumClient)
+                }
+                Err(err) => return Err(err.into()),
+            }
+        };
+        let tx = tx.interval(Duration::from_millis(75));
         let tx_hash = tx.tx_hash();
 
         let tx_receipt = tx.await?;
 
         trace!("Transaction receipt: {tx_receipt:?}");
 
-        Ok(tx_hash)
+        Ok(SendBundleResult::new(tx_hash, target, uo_hashes)
+            .with_block(tx_receipt.and_then(|receipt| receipt.block_number)))
     }
 }
 
@@ -95,6 +134,127 @@ where
     /// * `ConditionalClient` - A [Ethereum Signer Middleware](ConditionalClient)
     pub fn new(eth_client: Arc<M>, wallet: Wallet) -> Self {
         let signer = SignerMiddleware::new(eth_client, wallet.signer);
-        Self(signer)
+        Self(signer, Arc::new(OnceLock::new()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::{
+        providers::{JsonRpcError, MockResponse, Provider},
+        types::{Transaction, TransactionReceipt, TransactionRequest, H256, U256, U64},
+    };
+
+    #[tokio::test]
+    async fn send_bundle_to_conditional_endpoint() {
+        let (provider, mock) = Provider::mocked();
+        let wallet = Wallet::from_private_key(
+            "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318",
+            1,
+            false,
+            None,
+        )
+        .unwrap();
+        let client = ConditionalClient::new(Arc::new(provider), wallet);
+
+        // mocked responses are returned in reverse order (chain id twice, transaction hash,
+        // transaction, receipt)
+        let tx_hash = H256::random();
+        mock.push(TransactionReceipt {
+            transaction_hash: tx_hash,
+            block_number: Some(U64::from(100)),
+            ..Default::default()
+        })
+        .unwrap();
+        mock.push(Transaction {
+            hash: tx_hash,
+            block_number: Some(U64::from(100)),
+            ..Default::default()
+        })
+        .unwrap();
+        mock.push(tx_hash).unwrap();
+        mock.push(U256::one()).unwrap();
+        mock.push(U256::one()).unwrap();
+
+        let uo_hashes = vec![UserOperationHash(H256::random())];
+        let tx: TypedTransaction = TransactionRequest::new()
+            .to(Address::random())
+            .nonce(1)
+            .gas(21000)
+            .gas_price(1)
+            .chain_id(1)
+            .into();
+        assert_eq!(
+            client.send_bundle(tx, StorageMap::default(), uo_hashes.clone()).await.unwrap(),
+            SendBundleResult::new(tx_hash, BundleStrategy::Conditional, uo_hashes)
+                .with_block(Some(U64::from(100)))
+        );
+        assert_eq!(client.1.get(), Some(&true));
+    }
+
+    #[tokio::test]
+    async fn fallback_to_regular_transaction() {
+        let (provider, mock) = Provider::mocked();
+        let wallet = Wallet::from_private_key(
+            "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318",
+            1,
+            false,
+            None,
+        )
+        .unwrap();
+        let client = ConditionalClient::new(Arc::new(provider), wallet);
+        let tx: TypedTransaction = TransactionRequest::new()
+            .to(Address::random())
+            .nonce(1)
+            .gas(21000)
+            .gas_price(1)
+            .chain_id(1)
+            .into();
+        let uo_hashes = vec![UserOperationHash(H256::random())];
+        let push_sent_tx = |tx_hash: H256| {
+            mock.push(TransactionReceipt {
+                transaction_hash: tx_hash,
+                block_number: Some(U64::from(100)),
+                ..Default::default()
+            })
+            .unwrap();
+            mock.push(Transaction {
+                hash: tx_hash,
+                block_number: Some(U64::from(100)),
+                ..Default::default()
+            })
+            .unwrap();
+            mock.push(tx_hash).unwrap();
+        };
+
+        // mocked responses are returned in reverse order (chain id twice, method not found,
+        // transaction hash, transaction, receipt)
+        let tx_hash = H256::random();
+        push_sent_tx(tx_hash);
+        mock.push_response(MockResponse::Error(JsonRpcError {
+            code: METHOD_NOT_FOUND_CODE,
+            message: "the method eth_sendRawTransactionConditional does not exist".into(),
+            data: None,
+        }));
+        mock.push(U256::one()).unwrap();
+        mock.push(U256::one()).unwrap();
+
+        assert_eq!(
+            client.send_bundle(tx.clone(), StorageMap::default(), uo_hashes.clone()).await.unwrap(),
+            SendBundleResult::new(tx_hash, BundleStrategy::EthereumClient, uo_hashes.clone())
+                .with_block(Some(U64::from(100)))
+        );
+        assert_eq!(client.1.get(), Some(&false));
+
+        // the choice is cached, so the conditional method isn't probed again (no chain id
+        // requests either)
+        let tx_hash = H256::random();
+        push_sent_tx(tx_hash);
+        assert_eq!(
+            client.send_bundle(tx, StorageMap::default(), uo_hashes.clone()).await.unwrap(),
+            SendBundleResult::new(tx_hash, BundleStrategy::EthereumClient, uo_hashes)
+                .with_block(Some(U64::from(100)))
+        );
     }
 }
@@ -1,11 +1,11 @@
-use crate::bundler::SendBundleOp;
+use crate::bundler::{SendBundleOp, SendBundleResult};
 use ethers::{
-    middleware::SignerMiddleware,
-    providers::Middleware,
-    signers::LocalWallet,
-    types::{transaction::eip2718::TypedTransaction, H256},
+    middleware::SignerMiddleware, providers::Middleware, signers::LocalWallet,
+    types::transaction::eip2718::TypedTransaction,
+};
+use silius_primitives::{
+    bundler::BundleStrategy, simulation::StorageMap, UserOperationHash, Wallet,
 };
-use silius_primitives::{simulation::StorageMap, Wallet};
 use std::{sync::Arc, time::Duration};
 use tracing::trace;
 
@@ -23,14 +23,16 @@ where
     /// # Arguments
     /// * `bundle` - Bundle of user operations as [TypedTransaction](TypedTransaction).
     /// * 'storage_map' - Storage map
+    /// * `uo_hashes` - Hashes of the user operations included in the bundle
     ///
     /// # Returns
-    /// * `H256` - The transaction hash
+    /// * `SendBundleResult` - The transaction hash and the block the transaction was included in
     async fn send_bundle(
         &self,
         bundle: TypedTransaction,
         _storage_map: StorageMap,
-    ) -> eyre::Result<H256> {
+        uo_hashes: Vec<UserOperationHash>,
+    ) -> eyre::Result<SendBundleResult> {
         trace!("Sending transaction to the execution client: {bundle:?}");
 
         let tx = self.0.send_transaction(bundle, None).await?.interval(Duration::from_millis(75));
@@ -40,7 +42,8 @@ where
 
         trace!("Transaction receipt: {tx_receipt:?}");
 
-        Ok(tx_hash)
+        Ok(SendBundleResult::new(tx_hash, BundleStrategy::EthereumClient, uo_hashes)
+            .with_block(tx_receipt.and_then(|receipt| receipt.block_number)))
     }
 }
 
@@ -61,3 +64,55 @@ where
         Self(signer)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::{
+        providers::Provider,
+        types::{Address, Transaction, TransactionReceipt, TransactionRequest, H256, U64},
+    };
+
+    #[tokio::test]
+    async fn send_bundle_to_execution_client() {
+        let (provider, mock) = Provider::mocked();
+        let wallet = Wallet::from_private_key(
+            "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318",
+            1,
+            false,
+            None,
+        )
+        .unwrap();
+        let client = EthereumClient::new(Arc::new(provider), wallet);
+
+        // mocked responses are returned in reverse order (transaction hash, transaction, receipt)
+        let tx_hash = H256::random();
+        mock.push(TransactionReceipt {
+            transaction_hash: tx_hash,
+            block_number: Some(U64::from(100)),
+            ..Default::default()
+        })
+        .unwrap();
+        mock.push(Transaction {
+            hash: tx_hash,
+            block_number: Some(U64::from(100)),
+            ..Default::default()
+        })
+        .unwrap();
+        mock.push(tx_hash).unwrap();
+
+        let uo_hashes = vec![UserOperationHash(H256::random())];
+        let tx: TypedTransaction = TransactionRequest::new()
+            .to(Address::random())
+            .nonce(1)
+            .gas(21000)
+            .gas_price(1)
+            .chain_id(1)
+            .into();
+        assert_eq!(
+            client.send_bundle(tx, StorageMap::default(), uo_hashes.clone()).await.unwrap(),
+            SendBundleResult::new(tx_hash, BundleStrategy::EthereumClient, uo_hashes)
+                .with_block(Some(U64::from(100)))
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use crate::bundler::SendBundleOp;
+use crate::bundler::{SendBundleOp, SendBundleResult};
 use ethers::{
     middleware::SignerMiddleware,
     providers::{Http, Middleware, Provider},
@@ -8,12 +8,13 @@ use ethers::{
             conditional::{AccountStorage, ConditionalOptions},
             eip2718::TypedTransaction,
         },
-        Address, BlockNumber, H256,
+        Address, BlockNumber,
     },
 };
 use serde::Deserialize;
 use silius_primitives::{
-    constants::fastlane_relay_endpoints::FASTLANE_VALIDATORS, simulation::StorageMap, Wallet,
+    bundler::BundleStrategy, constants::fastlane_relay_endpoints::FASTLANE_VALIDATORS,
+    simulation::StorageMap, UserOperationHash, Wallet,
 };
 use std::{collections::HashMap, sync::Arc};
 use tracing::trace;
@@ -24,6 +25,8 @@ pub struct FastlaneClient<M> {
     pub client: SignerMiddleware<Arc<M>, LocalWallet>,
     pub polygon_client: Provider<Http>,
     pub relay_client: Provider<Http>,
+    /// Endpoint returning the validators participating in the Fastlane protocol
+    pub validators_endpoint: String,
 }
 
 /// Validators participating in the Fastlane relay network
@@ -43,14 +46,16 @@ where
     /// # Arguments
     /// * `bundle` - Bundle of user operations as [TypedTransaction](TypedTransaction).
     /// * 'storage_map' - Storage map
+    /// * `uo_hashes` - Hashes of the user operations included in the bundle
     ///
     /// # Returns
-    /// * `H256` - The transaction hash
+    /// * `SendBundleResult` - The transaction hash and the block the transaction was included in
     async fn send_bundle(
         &self,
         bundle: TypedTransaction,
         storage_map: StorageMap,
-    ) -> eyre::Result<H256> {
+        uo_hashes: Vec<UserOperationHash>,
+    ) -> eyre::Result<SendBundleResult> {
         trace!("Sending transaction to the conditional endpoint: {bundle:?}");
 
         let mut known_accounts: HashMap<Address, AccountStorage> = HashMap::default();
@@ -77,7 +82,7 @@ where
 
         // check if the current validator is participating in the Fastlane protocol
         let fastlane_validators =
-            reqwest::get(FASTLANE_VALIDATORS).await?.json::<FastlaneValidators>().await?;
+            reqwest::get(&self.validators_endpoint).await?.json::<FastlaneValidators>().await?;
         let current_validator: Address =
             self.polygon_client.request("bor_getCurrentProposer", ()).await?;
 
@@ -96,7 +101,8 @@ where
 
         trace!("Transaction receipt: {tx_receipt:?}");
 
-        Ok(tx_hash)
+        Ok(SendBundleResult::new(tx_hash, BundleStrategy::Fastlane, uo_hashes)
+            .with_block(tx_receipt.and_then(|receipt| receipt.block_number)))
     }
 }
 
@@ -121,6 +127,180 @@ where
         wallet: Wallet,
     ) -> Self {
         let signer = SignerMiddleware::new(eth_client, wallet.clone().signer);
-        Self { client: signer, polygon_client, relay_client }
+        Self {
+            client: signer,
+            polygon_client,
+            relay_client,
+            validators_endpoint: FASTLANE_VALIDATORS.into(),
+        }
+    }
+
+    /// Sets the endpoint returning the validators participating in the Fastlane protocol
+    pub fn with_validators_endpoint(mut self, validators_endpoint: String) -> Self {
+        self.validators_endpoint = validators_endpoint;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::{
+        providers::MockProvider,
+        types::{Block, Transaction, TransactionReceipt, TransactionRequest, H256, U256, U64},
+    };
+    use parking_lot::Mutex;
+    use std::time::Duration;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    /// Serves the validators endpoint, the Polygon node and the Fastlane relay until aborted.
+    /// The JSON-RPC methods called are recorded in `methods`.
+    async fn mock_fastlane(
+        listener: TcpListener,
+        validators: Vec<Address>,
+        proposer: Address,
+        tx_hash: H256,
+        methods: Arc<Mutex<Vec<String>>>,
+    ) {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+
+            let mut buf = Vec::new();
+            let (request_line, content_length, body_start) = loop {
+                let mut chunk = [0u8; 1024];
+                let n = stream.read(&mut chunk).await.unwrap();
+                buf.extend_from_slice(&chunk[..n]);
+                if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                    let head = String::from_utf8_lossy(&buf[..pos]).to_string();
+                    let content_length = head
+                        .lines()
+                        .filter_map(|line| line.split_once(':'))
+                        .find(|(k, _)| k.trim().eq_ignore_ascii_case("content-length"))
+                        .map_or(0, |(_, v)| v.trim().parse().unwrap());
+                    break (head.lines().next().unwrap().to_string(), content_length, pos + 4);
+                }
+            };
+            while buf.len() < body_start + content_length {
+                let mut chunk = [0u8; 1024];
+                let n = stream.read(&mut chunk).await.unwrap();
+                buf.extend_from_slice(&chunk[..n]);
+            }
+
+            let response = if request_line.starts_with("GET") {
+                serde_json::json!({ "validators": validators })
+            } else {
+                let request: serde_json::Value =
+                    serde_json::from_slice(&buf[body_start..]).unwrap();
+                let method = request["method"].as_str().unwrap().to_string();
+                let result = match method.as_str() {
+                    "bor_getCurrentProposer" => serde_json::to_value(proposer).unwrap(),
+                    "eth_getTransactionByHash" => serde_json::to_value(Transaction {
+                        hash: tx_hash,
+                        block_number: Some(U64::from(100)),
+                        ..Default::default()
+                    })
+                    .unwrap(),
+                    "eth_getTransactionReceipt" => serde_json::to_value(TransactionReceipt {
+                        transaction_hash: tx_hash,
+                        block_number: Some(U64::from(100)),
+                        ..Default::default()
+                    })
+                    .unwrap(),
+                    _ => serde_json::to_value(tx_hash).unwrap(),
+                };
+                methods.lock().push(method);
+                serde_json::json!({ "jsonrpc": "2.0", "id": request["id"], "result": result })
+            }
+            .to_string();
+
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{response}",
+                response.len()
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+        }
+    }
+
+    /// Creates a Fastlane client connected to the mock and the mocked execution client
+    async fn fastlane_client(
+        validators: Vec<Address>,
+        proposer: Address,
+        tx_hash: H256,
+    ) -> (FastlaneClient<Provider<MockProvider>>, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let methods = Arc::new(Mutex::new(vec![]));
+        tokio::spawn(mock_fastlane(listener, validators, proposer, tx_hash, methods.clone()));
+
+        let (provider, mock) = Provider::mocked();
+        mock.push(Block::<H256> { timestamp: U256::from(1_000), ..Default::default() }).unwrap();
+        let wallet = Wallet::from_private_key(
+            "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318",
+            1,
+            false,
+            None,
+        )
+        .unwrap();
+        let client = FastlaneClient::new(
+            Arc::new(provider),
+            Provider::<Http>::try_from(endpoint.as_str()).unwrap(),
+            Provider::<Http>::try_from(endpoint.as_str())
+                .unwrap()
+                .interval(Duration::from_millis(10)),
+            wallet,
+        )
+        .with_validators_endpoint(endpoint);
+
+        (client, methods)
+    }
+
+    #[tokio::test]
+    async fn send_bundle_to_fastlane() {
+        let validator = Address::random();
+        let tx_hash = H256::random();
+        let (client, methods) = fastlane_client(vec![validator], validator, tx_hash).await;
+
+        let uo_hashes = vec![UserOperationHash(H256::random())];
+        let tx: TypedTransaction = TransactionRequest::new()
+            .to(Address::random())
+            .nonce(1)
+            .gas(21000)
+            .gas_price(1)
+            .chain_id(1)
+            .into();
+        assert_eq!(
+            client.send_bundle(tx, StorageMap::default(), uo_hashes.clone()).await.unwrap(),
+            SendBundleResult::new(tx_hash, BundleStrategy::Fastlane, uo_hashes)
+                .with_block(Some(U64::from(100)))
+        );
+
+        // the bundle is sent to the relay with the Fastlane prefix
+        let methods = methods.lock();
+        assert_eq!(methods[0], "bor_getCurrentProposer");
+        assert!(methods[1].starts_with("pfl") && methods[1].ends_with("RawTransactionConditional"));
+    }
+
+    #[tokio::test]
+    async fn skip_validator_not_participating() {
+        let (client, methods) =
+            fastlane_client(vec![Address::random()], Address::random(), H256::random()).await;
+
+        let tx: TypedTransaction = TransactionRequest::new()
+            .to(Address::random())
+            .nonce(1)
+            .gas(21000)
+            .gas_price(1)
+            .chain_id(1)
+            .into();
+        assert!(client
+            .send_bundle(tx, StorageMap::default(), vec![UserOperationHash(H256::random())])
+            .await
+            .is_err());
+
+        // the bundle isn't sent to the relay
+        assert_eq!(*methods.lock(), vec!["bor_getCurrentProposer".to_string()]);
     }
 }
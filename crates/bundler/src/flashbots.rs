@@ -1,4 +1,4 @@
-use crate::bundler::SendBundleOp;
+use crate::bundler::{BundleNotIncludedError, SendBundleOp, SendBundleResult};
 use ethers::{
     middleware::SignerMiddleware,
     providers::Middleware,
@@ -6,7 +6,9 @@ use ethers::{
     types::{transaction::eip2718::TypedTransaction, H256},
 };
 use ethers_flashbots::{BundleRequest, FlashbotsMiddleware, PendingBundleError, SimulatedBundle};
-use silius_primitives::{simulation::StorageMap, Wallet};
+use silius_primitives::{
+    bundler::BundleStrategy, simulation::StorageMap, UserOperationHash, Wallet,
+};
 use std::sync::Arc;
 use tracing::{info, trace};
 use url::Url;
@@ -23,20 +25,28 @@ where
     M: Middleware + 'static,
 {
     // TODO: add more relay endpoints support
-    /// Send a bundle of user operations to the Flashbots relay.
+    /// Send a bundle of user operations to the Flashbots relay. If the bundle isn't included in
+    /// the target block, a [BundleNotIncludedError](BundleNotIncludedError) is returned, so the
+    /// bundler can resubmit it.
     ///
     /// # Arguments
     /// * `bundle` - Bundle of user operations as [TypedTransaction](TypedTransaction).
     /// * 'storage_map' - Storage map
+    /// * `uo_hashes` - Hashes of the user operations included in the bundle
     ///
     /// # Returns
-    /// * `H256` - The transaction hash of the bundle
+    /// * `SendBundleResult` - The hash, the target block and the block the bundle was included in
+    ///   (always the target block)
     async fn send_bundle(
         &self,
         bundle: TypedTransaction,
         _storage_map: StorageMap,
-    ) -> eyre::Result<H256> {
+        uo_hashes: Vec<UserOperationHash>,
+    ) -> eyre::Result<SendBundleResult> {
         let bundle_req = self.generate_bundle_req(vec![bundle], false).await?;
+        let target_block = bundle_req
+            .block()
+            .ok_or_else(|| eyre::eyre!("Flashbots bundle request has no target block"))?;
 
         match self.simulate_flashbots_bundle(&bundle_req).await {
             Ok(_) => {}
@@ -45,7 +55,10 @@ where
 
         let bundle_hash = self.send_flashbots_bundle(bundle_req.clone()).await?;
 
-        Ok(bundle_hash)
+        // the bundle is only reported as sent once it's included in the target block
+        Ok(SendBundleResult::new(bundle_hash, BundleStrategy::Flashbots, uo_hashes)
+            .with_block(Some(target_block))
+            .with_target_block(target_block))
     }
 }
 
@@ -148,12 +161,13 @@ where
         };
 
         info!("Bundle received at block: {:?}", pending_bundle.block);
+        let block = pending_bundle.block;
 
         match pending_bundle.await {
             Ok(bundle_hash) => Ok(bundle_hash),
             Err(err) => match err {
                 PendingBundleError::BundleNotIncluded => {
-                    Err(eyre::eyre!("Bundle not included in the target block"))
+                    Err(BundleNotIncludedError { block: Some(block) }.into())
                 }
                 _ => Err(eyre::eyre!("Bundle rejected: {:?}", err)),
             },
@@ -188,3 +202,64 @@ where
         Ok(simulated_bundle)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::{
+        providers::{MockProvider, Provider},
+        types::{Address, TransactionRequest, U64},
+    };
+
+    fn flashbots_client() -> (FlashbotsClient<Provider<MockProvider>>, MockProvider) {
+        let (provider, mock) = Provider::mocked();
+        let wallet = Wallet::from_private_key(
+            "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318",
+            1,
+            true,
+            Some("0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d"),
+        )
+        .unwrap();
+        // nothing listens on the relay endpoint
+        let client = FlashbotsClient::new(
+            Arc::new(provider),
+            Some(vec!["http://127.0.0.1:1".into()]),
+            wallet,
+        )
+        .unwrap();
+        (client, mock)
+    }
+
+    fn bundle() -> TypedTransaction {
+        TransactionRequest::new()
+            .to(Address::random())
+            .nonce(1)
+            .gas(21000)
+            .gas_price(1)
+            .chain_id(1)
+            .into()
+    }
+
+    #[tokio::test]
+    async fn bundle_request_targets_next_block() {
+        let (client, mock) = flashbots_client();
+        mock.push(U64::from(100)).unwrap();
+
+        let bundle_req = client.generate_bundle_req(vec![bundle()], false).await.unwrap();
+        assert_eq!(bundle_req.block(), Some(U64::from(101)));
+        assert_eq!(bundle_req.simulation_block(), Some(U64::from(100)));
+        assert_eq!(bundle_req.transactions().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn bundle_failing_simulation_not_sent() {
+        let (client, mock) = flashbots_client();
+        mock.push(U64::from(100)).unwrap();
+
+        let err = client
+            .send_bundle(bundle(), StorageMap::default(), vec![UserOperationHash(H256::random())])
+            .await
+            .unwrap_err();
+        assert!(err.to_string().starts_with("Bundle simulation failed"));
+    }
+}
@@ -6,9 +6,11 @@ mod conditional;
 mod ethereum;
 mod fastlane;
 mod flashbots;
+mod relay;
 
-pub use bundler::{Bundler, SendBundleOp};
+pub use bundler::{BundleNotIncludedError, Bundler, NonceTracker, SendBundleOp, SendBundleResult};
 pub use conditional::ConditionalClient;
 pub use ethereum::EthereumClient;
 pub use fastlane::FastlaneClient;
 pub use flashbots::FlashbotsClient;
+pub use relay::RelayClient;
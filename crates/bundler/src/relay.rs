@@ -0,0 +1,281 @@
+use crate::bundler::{SendBundleOp, SendBundleResult};
+use ethers::{
+    providers::Middleware,
+    signers::{LocalWallet, Signer},
+    types::{transaction::eip2718::TypedTransaction, Bytes, H256, U64},
+    utils::keccak256,
+};
+use serde::{Deserialize, Serialize};
+use silius_primitives::{
+    bundler::BundleStrategy, constants::bundler::RELAY_SIGNATURE_HEADER, simulation::StorageMap,
+    UserOperationHash, Wallet,
+};
+use std::sync::Arc;
+use tracing::trace;
+use url::Url;
+
+/// Parameters of the `eth_sendBundle` JSON-RPC method
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SendBundleParams {
+    txs: Vec<Bytes>,
+    block_number: U64,
+}
+
+/// JSON-RPC request sent to the relay
+#[derive(Debug, Serialize)]
+struct RelayRequest<'a> {
+    jsonrpc: &'a str,
+    id: u64,
+    method: &'a str,
+    params: [SendBundleParams; 1],
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RelayBundleResult {
+    bundle_hash: H256,
+}
+
+#[derive(Debug, Deserialize)]
+struct RelayError {
+    code: i64,
+    message: String,
+}
+
+/// JSON-RPC response of the relay
+#[derive(Debug, Deserialize)]
+struct RelayResponse {
+    result: Option<RelayBundleResult>,
+    error: Option<RelayError>,
+}
+
+/// A client for generic MEV relays supporting the standard `eth_sendBundle` JSON-RPC method
+#[derive(Clone)]
+pub struct RelayClient<M> {
+    pub eth_client: Arc<M>,
+    pub relay_url: Url,
+    pub signer: LocalWallet,
+    pub relay_signer: LocalWallet,
+    pub signature_header: String,
+    http_client: reqwest::Client,
+}
+
+#[async_trait::async_trait]
+impl<M> SendBundleOp for RelayClient<M>
+where
+    M: Middleware + 'static,
+{
+    /// Send a bundle of user operations to the relay over `eth_sendBundle` JSON-RPC method.
+    ///
+    /// # Arguments
+    /// * `bundle` - Bundle of user operations as [TypedTransaction](TypedTransaction).
+    /// * 'storage_map' - Storage map
+    /// * `uo_hashes` - Hashes of the user operations included in the bundle
+    ///
+    /// # Returns
+    /// * `SendBundleResult` - The hash and the target block of the bundle
+    async fn send_bundle(
+        &self,
+        bundle: TypedTransaction,
+        _storage_map: StorageMap,
+        uo_hashes: Vec<UserOperationHash>,
+    ) -> eyre::Result<SendBundleResult> {
+        let signature = self.signer.sign_transaction(&bundle).await?;
+        let block_number = self.eth_client.get_block_number().await.map_err(|err| {
+            eyre::eyre!("Failed to get the block number for the relay bundle: {err:?}")
+        })? + 1;
+
+        let body = serde_json::to_string(&RelayRequest {
+            jsonrpc: "2.0",
+            id: 1,
+            method: "eth_sendBundle",
+            params: [SendBundleParams { txs: vec![bundle.rlp_signed(&signature)], block_number }],
+        })?;
+
+        trace!("Sending bundle to the relay {}: {body}", self.relay_url);
+
+        let response = self
+            .http_client
+            .post(self.relay_url.clone())
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(self.signature_header.as_str(), self.sign_body(&body).await?)
+            .body(body)
+            .send()
+            .await?
+            .json::<RelayResponse>()
+            .await?;
+
+        match response {
+            RelayResponse { result: Some(result), .. } => {
+                Ok(SendBundleResult::new(result.bundle_hash, BundleStrategy::Relay, uo_hashes)
+                    .with_target_block(block_number))
+            }
+            RelayResponse { error: Some(err), .. } => {
+                Err(eyre::eyre!("Relay rejected the bundle ({}): {}", err.code, err.message))
+            }
+            _ => Err(eyre::eyre!("Relay returned an empty response")),
+        }
+    }
+}
+
+impl<M> RelayClient<M>
+where
+    M: Middleware + 'static,
+{
+    /// Create a new relay client
+    ///
+    /// # Arguments
+    /// * `eth_client` - Connection to the Ethereum execution client
+    /// * `relay_endpoint` - The relay endpoint accepting `eth_sendBundle` requests
+    /// * `wallet` - A [Wallet](Wallet) instance (the Flashbots signer is used to sign requests)
+    ///
+    /// # Returns
+    /// * `RelayClient` - A [Relay client](RelayClient)
+    pub fn new(eth_client: Arc<M>, relay_endpoint: &str, wallet: Wallet) -> eyre::Result<Self> {
+        let relay_signer = match wallet.flashbots_signer {
+            Some(signer) => signer,
+            None => return Err(eyre::eyre!("No relay signer provided")),
+        };
+
+        Ok(Self {
+            eth_client,
+            relay_url: Url::parse(relay_endpoint)?,
+            signer: wallet.signer,
+            relay_signer,
+            signature_header: RELAY_SIGNATURE_HEADER.into(),
+            http_client: reqwest::Client::new(),
+        })
+    }
+
+    /// Sets the name of the header carrying the signature of the request
+    pub fn with_signature_header(mut self, signature_header: String) -> Self {
+        self.signature_header = signature_header;
+        self
+    }
+
+    /// Signs the request body (Flashbots-style): `<address>:<signature of keccak256(body)>`
+    async fn sign_body(&self, body: &str) -> eyre::Result<String> {
+        let signature = self
+            .relay_signer
+            .sign_message(format!("0x{:x}", H256::from(keccak256(body.as_bytes()))))
+            .await?;
+        Ok(format!("{:?}:0x{}", self.relay_signer.address(), signature))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::{
+        providers::Provider,
+        types::{Address, Signature, TransactionRequest},
+    };
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    /// Accepts a single HTTP request and answers it with the given JSON body.
+    ///
+    /// # Returns
+    /// * The lowercased request headers and the request body
+    async fn mock_relay(listener: TcpListener, response: &str) -> (Vec<(String, String)>, String) {
+        let (mut stream, _) = listener.accept().await.unwrap();
+
+        let mut buf = Vec::new();
+        let (headers, body_start) = loop {
+            let mut chunk = [0u8; 1024];
+            let n = stream.read(&mut chunk).await.unwrap();
+            buf.extend_from_slice(&chunk[..n]);
+            if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                let headers = String::from_utf8_lossy(&buf[..pos])
+                    .lines()
+                    .skip(1)
+                    .filter_map(|line| line.split_once(':'))
+                    .map(|(k, v)| (k.trim().to_lowercase(), v.trim().to_string()))
+                    .collect::<Vec<_>>();
+                break (headers, pos + 4);
+            }
+        };
+
+        let content_length: usize = headers
+            .iter()
+            .find(|(k, _)| k == "content-length")
+            .map(|(_, v)| v.parse().unwrap())
+            .unwrap();
+        while buf.len() < body_start + content_length {
+            let mut chunk = [0u8; 1024];
+            let n = stream.read(&mut chunk).await.unwrap();
+            buf.extend_from_slice(&chunk[..n]);
+        }
+        let body = String::from_utf8(buf[body_start..].to_vec()).unwrap();
+
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{response}",
+            response.len()
+        );
+        stream.write_all(response.as_bytes()).await.unwrap();
+
+        (headers, body)
+    }
+
+    #[tokio::test]
+    async fn send_bundle_to_relay() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let relay_endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let bundle_hash = H256::random();
+        let relay = tokio::spawn(async move {
+            mock_relay(
+                listener,
+                &format!(
+                    r#"{{"jsonrpc":"2.0","id":1,"result":{{"bundleHash":"{bundle_hash:?}"}}}}"#
+                ),
+            )
+            .await
+        });
+
+        let (provider, mock) = Provider::mocked();
+        mock.push(U64::from(100)).unwrap();
+        let wallet = Wallet::from_private_key(
+            "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318",
+            1,
+            true,
+            Some("0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d"),
+        )
+        .unwrap();
+        let relay_signer = wallet.flashbots_signer.clone().unwrap().address();
+        let client = RelayClient::new(Arc::new(provider), &relay_endpoint, wallet.clone())
+            .unwrap()
+            .with_signature_header("X-Relay-Signature".into());
+
+        let uo_hashes = vec![UserOperationHash(H256::random())];
+        let tx: TypedTransaction =
+            TransactionRequest::new().to(Address::random()).nonce(1).gas(21000).chain_id(1).into();
+
+        // the bundle isn't known to be included yet, only the block it was submitted for
+        assert_eq!(
+            client.send_bundle(tx.clone(), StorageMap::default(), uo_hashes.clone()).await.unwrap(),
+            SendBundleResult::new(bundle_hash, BundleStrategy::Relay, uo_hashes)
+                .with_target_block(U64::from(101))
+        );
+
+        let (headers, body) = relay.await.unwrap();
+
+        // request
+        let request: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(request["method"], "eth_sendBundle");
+        assert_eq!(request["params"][0]["blockNumber"], "0x65");
+        let signature = wallet.signer.sign_transaction(&tx).await.unwrap();
+        assert_eq!(request["params"][0]["txs"], serde_json::json!([tx.rlp_signed(&signature)]));
+
+        // signature header
+        let header = headers.iter().find(|(k, _)| k == "x-relay-signature").unwrap();
+        let (address, signature) = header.1.split_once(':').unwrap();
+        assert_eq!(address.parse::<Address>().unwrap(), relay_signer);
+        let signature: Signature = signature.parse().unwrap();
+        signature
+            .verify(format!("0x{:x}", H256::from(keccak256(body.as_bytes()))), relay_signer)
+            .unwrap();
+    }
+}
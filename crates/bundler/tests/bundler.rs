@@ -87,7 +87,7 @@ async fn setup() -> eyre::Result<TestContext<Provider<Ws>, FlashbotsClient<Provi
     // Create a bundler and connect to the Anvil
     let bundler = Bundler::new(
         wallet.clone(),
-        wallet.signer.address(),
+        vec![wallet.signer.address()],
         ep_address,
         Chain::from(1),
         U256::from(100000000000000000u64),
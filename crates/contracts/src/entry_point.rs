@@ -1,52 +1,360 @@
 pub use super::{
     error::EntryPointError,
     gen::{
+        account_api::ValidateUserOpCall,
+        entry_point_api::{EntryPointAPIErrors, ValidationResult, ValidationResultWithAggregation},
         EntryPointAPI, EntryPointAPIEvents, StakeManagerAPI, UserOperationEventFilter,
-        ValidatePaymasterUserOpReturn, SELECTORS_INDICES, SELECTORS_NAMES,
+        ValidatePaymasterUserOpReturn, ValidateUserOpV07Call, SELECTORS_INDICES, SELECTORS_NAMES,
     },
 };
 use super::{
     gen::{
-        entry_point_api::{
-            EntryPointAPIErrors, SenderAddressResult, UserOperation, ValidationResult,
-            ValidationResultWithAggregation,
-        },
+        decode_simulation_return,
+        entry_point_api::{HandleOpsCall, SenderAddressResult, UserOperation},
         stake_manager_api::DepositInfo,
+        ExecutionResultV07, HandleOpsV07Call, SimulateHandleOpV07Call, SimulateValidationV07Call,
+        ValidationResultV07,
     },
     tracer::JS_TRACER,
 };
 use crate::{error::decode_revert_error, executor_tracer::EXECUTOR_TRACER, gen::ExecutionResult};
 use ethers::{
-    prelude::{ContractError, Event},
-    providers::Middleware,
+    abi::{AbiDecode, AbiEncode},
+    contract::EthEvent,
+    prelude::{ContractCall, ContractError, Event},
+    providers::{Middleware, MiddlewareError, RawCall},
     types::{
-        spoof, transaction::eip2718::TypedTransaction, Address, Bytes, GethDebugTracerType,
+        spoof, transaction::eip2718::TypedTransaction, Address, Bytes, Filter, GethDebugTracerType,
         GethDebugTracingCallOptions, GethDebugTracingOptions, GethTrace, TransactionRequest, U256,
     },
 };
-use std::sync::Arc;
+use serde_json::{json, Map, Value};
+use silius_primitives::{
+    constants::entry_point::ADDRESS_V07, PackedUserOperation, PackingError, UserOperationHash,
+    UserOperationSigned,
+};
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc,
+    },
+};
 
 const UINT96_MAX: u128 = 5192296858534827628530496329220095;
 
+// support of `debug_traceCall` by the provider (probed on first use)
+const DEBUG_TRACE_UNKNOWN: u8 = 0;
+const DEBUG_TRACE_SUPPORTED: u8 = 1;
+const DEBUG_TRACE_UNSUPPORTED: u8 = 2;
+
+// JSON-RPC error code of calls to unknown methods
+const METHOD_NOT_FOUND_CODE: i64 = -32601;
+
+// `supportsInterface(bytes4)` of ERC-165 (also the interface ID of ERC-165 itself)
+const ERC165_SUPPORTS_INTERFACE: [u8; 4] = [0x01, 0xff, 0xc9, 0xa7];
+
+// `validUntil` of validation data which doesn't expire (max uint48)
+const VALID_UNTIL_MAX: u64 = 0xffff_ffff_ffff;
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum SimulateValidationResult {
     ValidationResult(ValidationResult),
     ValidationResultWithAggregation(ValidationResultWithAggregation),
 }
 
+/// Parses the `validationData` of the account or the paymaster: the aggregator (1 if the signature
+/// is invalid) in the low 160 bits, followed by `validUntil` (0 if it doesn't expire) and
+/// `validAfter`
+fn parse_validation_data(data: U256) -> (Address, u64, u64) {
+    let mut bytes = [0u8; 32];
+    data.to_big_endian(&mut bytes);
+    let valid_after = U256::from_big_endian(&bytes[..6]).as_u64();
+    let valid_until = match U256::from_big_endian(&bytes[6..12]).as_u64() {
+        0 => VALID_UNTIL_MAX,
+        valid_until => valid_until,
+    };
+    (Address::from_slice(&bytes[12..]), valid_after, valid_until)
+}
+
+/// Intersects the validation data of the account and the paymaster (as the entry point v0.6 does
+/// in its results): whether any signature is invalid, the latest `validAfter` and the earliest
+/// `validUntil`
+fn intersect_validation_data(account: U256, paymaster: U256) -> (bool, u64, u64) {
+    let (account_aggregator, account_after, account_until) = parse_validation_data(account);
+    let (paymaster_aggregator, paymaster_after, paymaster_until) = parse_validation_data(paymaster);
+    let sig_failed =
+        [account_aggregator, paymaster_aggregator].contains(&Address::from_low_u64_be(1));
+    (sig_failed, account_after.max(paymaster_after), account_until.min(paymaster_until))
+}
+
+impl From<ValidationResultV07> for SimulateValidationResult {
+    fn from(res: ValidationResultV07) -> Self {
+        let info = res.return_info;
+        let (sig_failed, valid_after, valid_until) =
+            intersect_validation_data(info.account_validation_data, info.paymaster_validation_data);
+        let return_info = (
+            info.pre_op_gas,
+            info.prefund,
+            sig_failed,
+            valid_after,
+            valid_until,
+            info.paymaster_context,
+        );
+
+        if res.aggregator_info.0.is_zero() {
+            Self::ValidationResult(ValidationResult {
+                return_info,
+                sender_info: res.sender_info,
+                factory_info: res.factory_info,
+                paymaster_info: res.paymaster_info,
+            })
+        } else {
+            Self::ValidationResultWithAggregation(ValidationResultWithAggregation {
+                return_info,
+                sender_info: res.sender_info,
+                factory_info: res.factory_info,
+                paymaster_info: res.paymaster_info,
+                aggregator_info: res.aggregator_info,
+            })
+        }
+    }
+}
+
+impl From<ExecutionResultV07> for ExecutionResult {
+    fn from(res: ExecutionResultV07) -> Self {
+        let (_, valid_after, valid_until) =
+            intersect_validation_data(res.account_validation_data, res.paymaster_validation_data);
+        Self {
+            pre_op_gas: res.pre_op_gas,
+            paid: res.paid,
+            valid_after,
+            valid_until,
+            target_success: res.target_success,
+            target_result: res.target_result,
+        }
+    }
+}
+
+/// Packs the user operation for the entry point v0.7
+fn pack_user_operation(uo: UserOperation) -> Result<PackedUserOperation, EntryPointError> {
+    PackedUserOperation::try_from(UserOperationSigned::from(uo))
+        .map_err(|err| EntryPointError::Other { inner: err.to_string() })
+}
+
+/// Version of the entry point (determines the ABI of `handleOps`)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EntryPointVersion {
+    /// User operations are passed as is
+    #[default]
+    V0_6,
+    /// User operations are passed as
+    /// [PackedUserOperations](silius_primitives::PackedUserOperation)
+    V0_7,
+}
+
+impl EntryPointVersion {
+    /// Determines the version from the canonical address of the entry point (v0.6 for any other
+    /// address)
+    pub fn from_address(address: &Address) -> Self {
+        if ADDRESS_V07.parse::<Address>().is_ok_and(|addr| addr == *address) {
+            Self::V0_7
+        } else {
+            Self::V0_6
+        }
+    }
+
+    /// Calculates the hash of the user operation (as `getUserOpHash` of the version). Fails if the
+    /// user operation can't be packed for the entry point v0.7.
+    pub fn user_operation_hash(
+        &self,
+        uo: &UserOperationSigned,
+        entry_point: &Address,
+        chain_id: u64,
+    ) -> Result<UserOperationHash, PackingError> {
+        match self {
+            Self::V0_6 => Ok(uo.hash(entry_point, chain_id)),
+            Self::V0_7 => {
+                Ok(PackedUserOperation::try_from(uo.clone())?.hash(entry_point, chain_id))
+            }
+        }
+    }
+
+    /// Packs the user operation in the layout of the version (as it is passed to `handleOps`).
+    /// Fails if the user operation can't be packed for the entry point v0.7.
+    pub fn pack_user_operation(&self, uo: &UserOperationSigned) -> Result<Bytes, PackingError> {
+        match self {
+            Self::V0_6 => Ok(uo.pack()),
+            Self::V0_7 => Ok(PackedUserOperation::try_from(uo.clone())?.pack()),
+        }
+    }
+
+    /// Decodes the user operations from the `handleOps` call data in the ABI of the version (None
+    /// if the call data isn't a `handleOps` call of the version)
+    pub fn decode_handle_ops(&self, data: &[u8]) -> Option<Vec<UserOperationSigned>> {
+        match self {
+            Self::V0_6 => HandleOpsCall::decode(data)
+                .ok()
+                .map(|call| call.ops.into_iter().map(|uo| uo.into()).collect()),
+            Self::V0_7 => HandleOpsV07Call::decode(data)
+                .ok()
+                .map(|call| call.ops.into_iter().map(|uo| uo.into()).collect()),
+        }
+    }
+
+    /// Encodes the `handleOps` call data in the ABI of the version. Fails if any of the user
+    /// operations can't be packed for the entry point v0.7.
+    pub fn handle_ops_call_data(
+        &self,
+        uos: Vec<UserOperationSigned>,
+        beneficiary: Address,
+    ) -> Result<Bytes, PackingError> {
+        match self {
+            Self::V0_6 => Ok(HandleOpsCall {
+                ops: uos.into_iter().map(|uo| uo.into()).collect(),
+                beneficiary,
+            }
+            .encode()
+            .into()),
+            Self::V0_7 => Ok(HandleOpsV07Call {
+                ops: uos
+                    .into_iter()
+                    .map(PackedUserOperation::try_from)
+                    .collect::<Result<_, _>>()?,
+                beneficiary,
+            }
+            .encode()
+            .into()),
+        }
+    }
+
+    /// Decodes the result of `simulateHandleOp` of the version: the data the entry point v0.6
+    /// reverts with, or the data the entry point simulations (v0.7) return
+    pub fn decode_execution_result(&self, data: &[u8]) -> Option<ExecutionResult> {
+        match self {
+            Self::V0_6 => ExecutionResult::decode(data).ok(),
+            Self::V0_7 => decode_simulation_return::<ExecutionResultV07>(data).map(Into::into),
+        }
+    }
+}
+
+impl Display for EntryPointVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::V0_6 => write!(f, "0.6"),
+            Self::V0_7 => write!(f, "0.7"),
+        }
+    }
+}
+
+impl FromStr for EntryPointVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim_start_matches('v') {
+            "0.6" | "0.6.0" => Ok(Self::V0_6),
+            "0.7" | "0.7.0" => Ok(Self::V0_7),
+            _ => Err(format!("unsupported entry point version {s}")),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct EntryPoint<M: Middleware + 'static> {
     eth_client: Arc<M>,
     address: Address,
+    version: EntryPointVersion,
     entry_point_api: EntryPointAPI<M>,
     stake_manager_api: StakeManagerAPI<M>,
+    debug_trace_support: Arc<AtomicU8>,
+    // gas limit of the validation simulation calls (provider default if not set)
+    simulation_gas: Option<U256>,
+    // deployed code of `EntryPointSimulations`, which replaces the code of the entry point (v0.7)
+    // in its simulations
+    simulations_code: Option<Bytes>,
 }
 
 impl<M: Middleware + 'static> EntryPoint<M> {
     pub fn new(eth_client: Arc<M>, address: Address) -> Self {
         let entry_point_api = EntryPointAPI::new(address, eth_client.clone());
         let stake_manager_api = StakeManagerAPI::new(address, eth_client.clone());
-        Self { eth_client, address, entry_point_api, stake_manager_api }
+        Self {
+            eth_client,
+            address,
+            version: EntryPointVersion::from_address(&address),
+            entry_point_api,
+            stake_manager_api,
+            debug_trace_support: Arc::new(AtomicU8::new(DEBUG_TRACE_UNKNOWN)),
+            simulation_gas: None,
+            simulations_code: None,
+        }
+    }
+
+    /// Sets the gas limit of the `simulateValidation` calls (`eth_call`, `debug_traceCall` and
+    /// `eth_createAccessList`), bounding the cost of simulating expensive user operations.
+    pub fn with_simulation_gas(mut self, simulation_gas: Option<U256>) -> Self {
+        self.simulation_gas = simulation_gas;
+        self
+    }
+
+    pub fn simulation_gas(&self) -> Option<U256> {
+        self.simulation_gas
+    }
+
+    /// Sets the deployed code of `EntryPointSimulations`: the entry point v0.7 doesn't implement
+    /// the simulations, so they are executed with its code overridden by this one (the
+    /// simulations of the entry point v0.7 fail if not set)
+    pub fn with_simulations_code(mut self, simulations_code: Option<Bytes>) -> Self {
+        self.simulations_code = simulations_code;
+        self
+    }
+
+    pub fn simulations_code(&self) -> Option<Bytes> {
+        self.simulations_code.clone()
+    }
+
+    /// Overrides the version of the entry point (otherwise determined from its address)
+    pub fn with_version(mut self, version: EntryPointVersion) -> Self {
+        self.version = version;
+        self
+    }
+
+    pub fn version(&self) -> EntryPointVersion {
+        self.version
+    }
+
+    /// Probes the deployed entry point for its version: the entry point v0.7 implements ERC-165,
+    /// whereas the entry point v0.6 (without a fallback function) reverts on `supportsInterface`.
+    pub async fn detect_version(&self) -> Result<EntryPointVersion, EntryPointError> {
+        let data =
+            [ERC165_SUPPORTS_INTERFACE.to_vec(), ERC165_SUPPORTS_INTERFACE.to_vec(), vec![0; 28]]
+                .concat();
+        let tx: TypedTransaction = TransactionRequest::new().to(self.address).data(data).into();
+
+        match self.eth_client.call(&tx, None).await {
+            Ok(res) if res.len() == 32 && U256::from_big_endian(&res) == U256::one() => {
+                Ok(EntryPointVersion::V0_7)
+            }
+            Ok(res) => Err(EntryPointError::Other {
+                inner: format!("unexpected supportsInterface result {res:?}"),
+            }),
+            Err(err)
+                if err.as_error_response().is_some_and(|err| err.message.contains("revert")) =>
+            {
+                Ok(EntryPointVersion::V0_6)
+            }
+            Err(err) => Err(EntryPointError::Provider { inner: err.to_string() }),
+        }
+    }
+
+    /// Verifies that the contract behaves like an entry point (e.g., before adopting an emitter of
+    /// `UserOperationEvent` logs, which any contract can emit): it has to answer `getNonce` and
+    /// its version has to be detected.
+    pub async fn verify(&self) -> Result<EntryPointVersion, EntryPointError> {
+        self.get_nonce(&Address::zero(), U256::zero()).await?;
+        self.detect_version().await
     }
 
     pub fn entry_point_api(&self) -> &EntryPointAPI<M> {
@@ -80,11 +388,85 @@ impl<M: Middleware + 'static> EntryPoint<M> {
         }
     }
 
+    fn simulate_validation_call(&self, uo: UserOperation) -> ContractCall<M, ()> {
+        let call = self.entry_point_api.simulate_validation(uo);
+        match self.simulation_gas {
+            Some(gas) => call.gas(gas),
+            None => call,
+        }
+    }
+
+    /// Builds the call of the entry point simulations (v0.7) from the given state, overriding
+    /// the code of the entry point with the code of the simulations
+    fn simulations_call(
+        &self,
+        data: Vec<u8>,
+        mut state: spoof::State,
+    ) -> Result<(TypedTransaction, spoof::State), EntryPointError> {
+        let code = self.simulations_code.clone().ok_or_else(|| EntryPointError::Other {
+            inner: format!(
+                "simulations of the entry point {:?} (v0.7) require the code of \
+                 EntryPointSimulations",
+                self.address
+            ),
+        })?;
+        state.account(self.address).code(code);
+
+        Ok((TransactionRequest::new().to(self.address).data(data).into(), state))
+    }
+
+    /// Builds the `simulateValidation` transaction of the version (with the state it's simulated
+    /// against, if overridden)
+    fn simulate_validation_tx(
+        &self,
+        uo: UserOperation,
+    ) -> Result<(TypedTransaction, Option<spoof::State>), EntryPointError> {
+        let (mut tx, state) = match self.version {
+            EntryPointVersion::V0_6 => (self.entry_point_api.simulate_validation(uo).tx, None),
+            EntryPointVersion::V0_7 => {
+                let data = SimulateValidationV07Call { user_op: pack_user_operation(uo)? }.encode();
+                let (tx, state) = self.simulations_call(data, spoof::State::default())?;
+                (tx, Some(state))
+            }
+        };
+        if let Some(gas) = self.simulation_gas {
+            tx.set_gas(gas);
+        }
+        Ok((tx, state))
+    }
+
+    /// Calls the entry point simulations (v0.7) against the overridden state
+    async fn call_simulations(
+        &self,
+        tx: &TypedTransaction,
+        state: &spoof::State,
+        function: &str,
+    ) -> Result<Bytes, EntryPointError> {
+        self.eth_client.provider().call_raw(tx).state(state).await.or_else(|err| {
+            EntryPointError::from_provider_error(&err).and_then(|op| match op {
+                EntryPointAPIErrors::FailedOp(err) => Err(EntryPointError::FailedOp(err)),
+                _ => Err(EntryPointError::Other { inner: format!("{function} error: {op:?}") }),
+            })
+        })
+    }
+
     pub async fn simulate_validation<U: Into<UserOperation>>(
         &self,
         uo: U,
     ) -> Result<SimulateValidationResult, EntryPointError> {
-        let res = self.entry_point_api.simulate_validation(uo.into()).await;
+        if self.version == EntryPointVersion::V0_7 {
+            let (tx, state) = self.simulate_validation_tx(uo.into())?;
+            let res = self
+                .call_simulations(&tx, &state.unwrap_or_default(), "simulate validation")
+                .await?;
+            return decode_simulation_return::<ValidationResultV07>(&res)
+                .map(Into::into)
+                .ok_or_else(|| EntryPointError::Decode {
+                    inner: format!("simulate validation result can't be decoded: {res:?}"),
+                });
+        }
+
+        let res = self.simulate_validation_call(uo.into()).call().await;
 
         match res {
             Ok(_) => Err(EntryPointError::NoRevert { function: "simulate_validation".into() }),
@@ -103,16 +485,62 @@ impl<M: Middleware + 'static> EntryPoint<M> {
         }
     }
 
+    /// Probes whether the provider supports `debug_traceCall`. Only definitive results are cached:
+    /// a successful probe or a "method not found" response (other errors, e.g. transport ones,
+    /// are considered transient).
+    pub async fn supports_debug_trace_call(&self) -> bool {
+        match self.debug_trace_support.load(Ordering::Relaxed) {
+            DEBUG_TRACE_SUPPORTED => true,
+            DEBUG_TRACE_UNSUPPORTED => false,
+            _ => {
+                let res = self
+                    .eth_client
+                    .debug_trace_call(
+                        TransactionRequest::default(),
+                        None,
+                        GethDebugTracingCallOptions::default(),
+                    )
+                    .await;
+
+                match res {
+                    Ok(_) => {
+                        self.debug_trace_support.store(DEBUG_TRACE_SUPPORTED, Ordering::Relaxed);
+                        true
+                    }
+                    Err(err)
+                        if err
+                            .as_error_response()
+                            .is_some_and(|err| err.code == METHOD_NOT_FOUND_CODE) =>
+                    {
+                        self.debug_trace_support.store(DEBUG_TRACE_UNSUPPORTED, Ordering::Relaxed);
+                        false
+                    }
+                    Err(_) => true,
+                }
+            }
+        }
+    }
+
     pub async fn simulate_validation_trace<U: Into<UserOperation>>(
         &self,
         uo: U,
     ) -> Result<GethTrace, EntryPointError> {
-        let call = self.entry_point_api.simulate_validation(uo.into());
+        // the trace rules can't be enforced without the trace, so the validation is rejected
+        // instead of falling back to the (storage access only) access list
+        if !self.supports_debug_trace_call().await {
+            return Err(EntryPointError::Provider {
+                inner: "debug_traceCall is not supported by the provider, so the validation can't \
+                        be traced"
+                    .into(),
+            });
+        }
+
+        let (tx, state) = self.simulate_validation_tx(uo.into())?;
 
         let res = self
             .eth_client
             .debug_trace_call(
-                call.tx,
+                tx,
                 None,
                 GethDebugTracingCallOptions {
                     tracing_options: GethDebugTracingOptions {
@@ -124,7 +552,7 @@ impl<M: Middleware + 'static> EntryPoint<M> {
                         tracer_config: None,
                         timeout: None,
                     },
-                    state_overrides: None,
+                    state_overrides: state,
                     block_overrides: None,
                 },
             )
@@ -136,14 +564,81 @@ impl<M: Middleware + 'static> EntryPoint<M> {
         Ok(res)
     }
 
+    /// Alternative for providers without `debug_traceCall`: collects the storage accessed during
+    /// the validation via `eth_createAccessList` and returns it in the format of the JavaScript
+    /// tracer. The returned trace contains only the storage access info (no opcodes, calls or
+    /// top-level entity frames), so the trace rules can't be enforced on it: it's only suitable
+    /// for unsafe mode (e.g., to collect the storage map) and is never used by
+    /// [simulate_validation_trace](EntryPoint::simulate_validation_trace). Not supported for the
+    /// entry point v0.7, as `eth_createAccessList` doesn't take state overrides.
+    pub async fn simulate_validation_access_list<U: Into<UserOperation>>(
+        &self,
+        uo: U,
+    ) -> Result<GethTrace, EntryPointError> {
+        let (tx, state) = self.simulate_validation_tx(uo.into())?;
+        if state.is_some() {
+            return Err(EntryPointError::Other {
+                inner: "access list of the validation isn't supported for the entry point v0.7"
+                    .into(),
+            });
+        }
+
+        let res = self.eth_client.create_access_list(&tx, None).await.map_err(|e| {
+            EntryPointError::Provider { inner: format!("create access list error: {e:?}") }
+        })?;
+
+        let mut access = Map::new();
+        for item in res.access_list.0 {
+            let mut reads = Map::new();
+            for slot in item.storage_keys {
+                let value =
+                    self.eth_client.get_storage_at(item.address, slot, None).await.map_err(
+                        |e| EntryPointError::Provider {
+                            inner: format!("get storage error: {e:?}"),
+                        },
+                    )?;
+                reads.insert(format!("{slot:?}"), Value::String(format!("{value:?}")));
+            }
+            access.insert(format!("{:?}", item.address), json!({ "reads": reads, "writes": {} }));
+        }
+
+        Ok(GethTrace::Unknown(json!({
+            "callsFromEntryPoint": [{
+                "topLevelMethodSig": "0x",
+                "topLevelTargetAddress": "0x",
+                "access": access,
+                "opcodes": {},
+                "contractSize": {},
+                "extCodeAccessInfo": {},
+                "oog": false,
+            }],
+            "keccak": [],
+            "logs": [],
+            "calls": [],
+            "debug": [],
+        })))
+    }
+
     pub async fn simulate_handle_op_trace<U: Into<UserOperation>>(
         &self,
         uo: U,
+        state_override: Option<&spoof::State>,
     ) -> Result<GethTrace, EntryPointError> {
         let uo = uo.into();
+        let mut state = state_override.cloned().unwrap_or_default();
+        state.account(Address::zero()).balance(UINT96_MAX.into());
         let max_fee_per_gas = uo.max_fee_per_gas;
-        let call = self.entry_point_api.simulate_handle_op(uo, Address::zero(), Bytes::default());
-        let mut tx: TypedTransaction = call.tx;
+        let (mut tx, state) = match self.version {
+            EntryPointVersion::V0_6 => (
+                self.entry_point_api.simulate_handle_op(uo, Address::zero(), Bytes::default()).tx,
+                state,
+            ),
+            EntryPointVersion::V0_7 => self.simulations_call(
+                SimulateHandleOpV07Call { op: pack_user_operation(uo)?, ..Default::default() }
+                    .encode(),
+                state,
+            )?,
+        };
         tx.set_from(Address::zero());
         tx.set_gas_price(max_fee_per_gas);
         tx.set_gas(u64::MAX);
@@ -162,7 +657,7 @@ impl<M: Middleware + 'static> EntryPoint<M> {
                         tracer_config: None,
                         timeout: None,
                     },
-                    state_overrides: Some(spoof::balance(Address::zero(), UINT96_MAX.into())),
+                    state_overrides: Some(state),
                     block_overrides: None,
                 },
             )
@@ -174,21 +669,23 @@ impl<M: Middleware + 'static> EntryPoint<M> {
         Ok(res)
     }
 
-    pub async fn handle_ops<U: Into<UserOperation>>(
+    pub async fn handle_ops<U: Into<UserOperationSigned>>(
         &self,
         uos: Vec<U>,
         beneficiary: Address,
     ) -> Result<(), EntryPointError> {
-        self.entry_point_api
-            .handle_ops(uos.into_iter().map(|u| u.into()).collect(), beneficiary)
-            .call()
-            .await
-            .or_else(|e| {
-                Self::deserialize_error_msg(e).and_then(|op| match op {
-                    EntryPointAPIErrors::FailedOp(err) => Err(EntryPointError::FailedOp(err)),
-                    _ => Err(EntryPointError::Other { inner: format!("handle ops error: {op:?}") }),
-                })
+        let data = self
+            .version
+            .handle_ops_call_data(uos.into_iter().map(|u| u.into()).collect(), beneficiary)
+            .map_err(|err| EntryPointError::Other { inner: format!("handle ops error: {err}") })?;
+        let tx: TypedTransaction = TransactionRequest::new().to(self.address).data(data).into();
+
+        self.eth_client.call(&tx, None).await.map(|_| ()).or_else(|e| {
+            EntryPointError::from_middleware_error::<M>(e).and_then(|op| match op {
+                EntryPointAPIErrors::FailedOp(err) => Err(EntryPointError::FailedOp(err)),
+                _ => Err(EntryPointError::Other { inner: format!("handle ops error: {op:?}") }),
             })
+        })
     }
 
     pub async fn get_deposit_info(&self, addr: &Address) -> Result<DepositInfo, EntryPointError> {
@@ -257,14 +754,36 @@ impl<M: Middleware + 'static> EntryPoint<M> {
             .map_err(|err| EntryPointError::Provider { inner: err.to_string() })
     }
 
+    /// Simulates the user operation with `simulateHandleOp`, optionally against an overridden
+    /// state (e.g., a pre-funded sender).
     pub async fn simulate_handle_op<U: Into<UserOperation>>(
         &self,
         uo: U,
+        state_override: Option<&spoof::State>,
     ) -> Result<ExecutionResult, EntryPointError> {
-        let res = self
-            .entry_point_api
-            .simulate_handle_op(uo.into(), Address::zero(), Bytes::default())
-            .await;
+        if self.version == EntryPointVersion::V0_7 {
+            let (tx, state) = self.simulations_call(
+                SimulateHandleOpV07Call {
+                    op: pack_user_operation(uo.into())?,
+                    ..Default::default()
+                }
+                .encode(),
+                state_override.cloned().unwrap_or_default(),
+            )?;
+            let res = self.call_simulations(&tx, &state, "simulate handle op").await?;
+            return self.version.decode_execution_result(&res).ok_or_else(|| {
+                EntryPointError::Decode {
+                    inner: format!("simulate handle op result can't be decoded: {res:?}"),
+                }
+            });
+        }
+
+        let call =
+            self.entry_point_api.simulate_handle_op(uo.into(), Address::zero(), Bytes::default());
+        let res = match state_override {
+            Some(state) => call.call_raw().state(state).await,
+            None => call.call().await,
+        };
 
         match res {
             Ok(_) => Err(EntryPointError::NoRevert { function: "simulate_handle_op".into() }),
@@ -287,10 +806,275 @@ impl<M: Middleware + 'static> EntryPoint<M> {
     }
 }
 
+/// Scans the recent blocks for the emitters of `UserOperationEvent` logs, to find the entry points
+/// active on the chain. Any contract can emit such logs, so the emitters should be verified (see
+/// [EntryPoint::verify]) before they are used as entry points.
+///
+/// # Arguments
+/// * `eth_client` - The client of the execution client
+/// * `blocks` - The number of recent blocks to scan
+///
+/// # Returns
+/// * The emitters with their number of `UserOperationEvent` logs, the most active first
+pub async fn discover_entry_points<M: Middleware + 'static>(
+    eth_client: Arc<M>,
+    blocks: u64,
+) -> Result<Vec<(Address, usize)>, EntryPointError> {
+    let latest = eth_client
+        .get_block_number()
+        .await
+        .map_err(|err| EntryPointError::Provider { inner: err.to_string() })?
+        .as_u64();
+
+    let event = UserOperationEventFilter::signature();
+    let filter = Filter::new()
+        .topic0(event)
+        .from_block(latest.saturating_sub(blocks.saturating_sub(1)))
+        .to_block(latest);
+    let logs = eth_client
+        .get_logs(&filter)
+        .await
+        .map_err(|err| EntryPointError::Provider { inner: err.to_string() })?;
+
+    let mut events = HashMap::<Address, usize>::new();
+    for log in logs.into_iter().filter(|log| log.topics.first() == Some(&event)) {
+        *events.entry(log.address).or_default() += 1;
+    }
+
+    let mut emitters: Vec<(Address, usize)> = events.into_iter().collect();
+    emitters.sort_by(|(a, a_events), (b, b_events)| b_events.cmp(a_events).then(a.cmp(b)));
+    Ok(emitters)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use ethers::providers::{Http, Provider};
+    use crate::{gen::ReturnInfoV07, tracer::JsTracerFrame};
+    use ethers::{
+        abi::Tokenizable,
+        providers::{Http, JsonRpcError, MockResponse, Provider},
+        types::{
+            transaction::eip2930::{AccessList, AccessListItem, AccessListWithGasUsed},
+            Log, H256, U64,
+        },
+    };
+
+    #[tokio::test]
+    async fn simulate_validation_trace_unsupported() {
+        let (provider, mock) = Provider::mocked();
+        let ep = EntryPoint::new(Arc::new(provider), Address::random());
+
+        let (addr, slot, value) = (Address::random(), H256::random(), H256::random());
+
+        // mocked responses are returned in reverse order
+        mock.push(value).unwrap();
+        mock.push(AccessListWithGasUsed {
+            access_list: AccessList(vec![AccessListItem {
+                address: addr,
+                storage_keys: vec![slot],
+            }]),
+            gas_used: U256::from(100_000),
+        })
+        .unwrap();
+        mock.push_response(MockResponse::Error(JsonRpcError {
+            code: -32601,
+            message: "the method debug_traceCall does not exist/is not available".into(),
+            data: None,
+        }));
+
+        // the validation isn't traced without debug_traceCall
+        assert!(matches!(
+            ep.simulate_validation_trace(UserOperation::default()).await,
+            Err(EntryPointError::Provider { .. })
+        ));
+        assert!(!ep.supports_debug_trace_call().await);
+
+        let trace = ep.simulate_validation_access_list(UserOperation::default()).await.unwrap();
+        let js_trace = JsTracerFrame::try_from(trace).unwrap();
+        assert_eq!(
+            js_trace.calls_from_entry_point[0].access[&addr].reads[&format!("{slot:?}")],
+            format!("{value:?}")
+        );
+    }
+
+    #[tokio::test]
+    async fn debug_trace_support_cached_if_definitive() {
+        let (provider, mock) = Provider::mocked();
+        let ep = EntryPoint::new(Arc::new(provider), Address::random());
+
+        // mocked responses are returned in reverse order
+        mock.push_response(MockResponse::Error(JsonRpcError {
+            code: -32601,
+            message: "the method debug_traceCall does not exist/is not available".into(),
+            data: None,
+        }));
+        mock.push_response(MockResponse::Error(JsonRpcError {
+            code: -32000,
+            message: "request timed out".into(),
+            data: None,
+        }));
+
+        // transient errors aren't cached
+        assert!(ep.supports_debug_trace_call().await);
+        assert!(!ep.supports_debug_trace_call().await);
+        // no more responses are mocked, so the result is cached
+        assert!(!ep.supports_debug_trace_call().await);
+    }
+
+    #[test]
+    fn entry_point_version() {
+        let v07: Address = ADDRESS_V07.parse().unwrap();
+        assert_eq!(EntryPointVersion::from_address(&v07), EntryPointVersion::V0_7);
+        assert_eq!(
+            EntryPointVersion::from_address(
+                &"0x5FF137D4b0FDCD49DcA30c7CF57E578a026d2789".parse().unwrap()
+            ),
+            EntryPointVersion::V0_6
+        );
+
+        let (provider, _) = Provider::mocked();
+        let ep = EntryPoint::new(Arc::new(provider), v07);
+        assert_eq!(ep.version(), EntryPointVersion::V0_7);
+        assert_eq!(ep.with_version(EntryPointVersion::V0_6).version(), EntryPointVersion::V0_6);
+
+        assert_eq!("0.7".parse::<EntryPointVersion>().unwrap(), EntryPointVersion::V0_7);
+        assert_eq!("v0.6.0".parse::<EntryPointVersion>().unwrap(), EntryPointVersion::V0_6);
+        assert!("0.8".parse::<EntryPointVersion>().is_err());
+        assert_eq!(EntryPointVersion::V0_7.to_string(), "0.7");
+
+        let uos = vec![UserOperationSigned::default()];
+        let beneficiary = Address::random();
+        assert_eq!(
+            EntryPointVersion::V0_6.handle_ops_call_data(uos.clone(), beneficiary).unwrap()[..4],
+            [0x1f, 0xad, 0x94, 0x8c]
+        );
+        assert_eq!(
+            EntryPointVersion::V0_7.handle_ops_call_data(uos, beneficiary).unwrap()[..4],
+            [0x76, 0x5e, 0x82, 0x7f]
+        );
+
+        // gas values above 128 bits can't be packed for the entry point v0.7
+        let uo = UserOperationSigned::default().call_gas_limit(U256::MAX);
+        assert!(EntryPointVersion::V0_6
+            .handle_ops_call_data(vec![uo.clone()], beneficiary)
+            .is_ok());
+        assert!(EntryPointVersion::V0_7
+            .handle_ops_call_data(vec![uo.clone()], beneficiary)
+            .is_err());
+        assert!(EntryPointVersion::V0_7.user_operation_hash(&uo, &v07, 1).is_err());
+    }
+
+    #[tokio::test]
+    async fn discover_entry_point_from_logs() {
+        let (provider, mock) = Provider::mocked();
+        let (ep, other) = (Address::random(), Address::random());
+
+        let log = |address: Address, topic: H256| Log {
+            address,
+            topics: vec![topic, H256::random()],
+            ..Default::default()
+        };
+        let event = UserOperationEventFilter::signature();
+
+        // responses are popped in the reverse order (the block number is queried first)
+        mock.push(vec![
+            log(other, event),
+            log(ep, event),
+            log(ep, event),
+            log(ep, event),
+            log(Address::random(), H256::random()),
+        ])
+        .unwrap();
+        mock.push(U64::from(100)).unwrap();
+
+        let emitters = discover_entry_points(Arc::new(provider), 10).await.unwrap();
+        assert_eq!(emitters, vec![(ep, 3), (other, 1)]);
+    }
+
+    #[tokio::test]
+    async fn detect_entry_point_version() {
+        let (provider, mock) = Provider::mocked();
+        let ep = EntryPoint::new(Arc::new(provider), Address::random());
+        assert_eq!(ep.version(), EntryPointVersion::V0_6);
+
+        // the entry point v0.7 supports ERC-165
+        mock.push(Bytes::from(U256::one().encode())).unwrap();
+        let version = ep.detect_version().await.unwrap();
+        assert_eq!(version, EntryPointVersion::V0_7);
+
+        // hashing and packing follow the v0.7 code path
+        let ep = ep.with_version(version);
+        let uo = UserOperationSigned::default().sender(Address::random());
+        assert_eq!(
+            ep.version().user_operation_hash(&uo, &ep.address(), 1).unwrap(),
+            PackedUserOperation::try_from(uo.clone()).unwrap().hash(&ep.address(), 1)
+        );
+        assert_ne!(
+            ep.version().user_operation_hash(&uo, &ep.address(), 1).unwrap(),
+            uo.hash(&ep.address(), 1)
+        );
+        let data = ep.version().handle_ops_call_data(vec![uo.clone()], Address::random()).unwrap();
+        assert_eq!(ep.version().decode_handle_ops(&data), Some(vec![uo.clone()]));
+        assert_eq!(EntryPointVersion::V0_6.decode_handle_ops(&data), None);
+
+        // the entry point v0.6 reverts
+        mock.push_response(MockResponse::Error(JsonRpcError {
+            code: 3,
+            message: "execution reverted".into(),
+            data: None,
+        }));
+        assert_eq!(ep.detect_version().await.unwrap(), EntryPointVersion::V0_6);
+
+        // the detection fails if the entry point can't be probed
+        mock.push_response(MockResponse::Error(JsonRpcError {
+            code: -32005,
+            message: "limit exceeded".into(),
+            data: None,
+        }));
+        assert!(ep.detect_version().await.is_err());
+        mock.push(Bytes::default()).unwrap();
+        assert!(ep.detect_version().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn simulate_validation_v07() {
+        let (provider, mock) = Provider::mocked();
+        let ep = EntryPoint::new(Arc::new(provider), ADDRESS_V07.parse().unwrap());
+        let uo = UserOperationSigned::default().sender(Address::random());
+
+        // the simulations can't be executed without the code of EntryPointSimulations
+        assert!(matches!(
+            ep.simulate_validation(uo.clone()).await,
+            Err(EntryPointError::Other { .. })
+        ));
+
+        // the account is valid after 10 (without an expiry), the paymaster's signature is invalid
+        // and its validity ends at 100
+        let res = ValidationResultV07 {
+            return_info: ReturnInfoV07 {
+                pre_op_gas: U256::from(50_000),
+                prefund: U256::from(1_000),
+                account_validation_data: U256::from(10) << 208,
+                paymaster_validation_data: (U256::from(100) << 160) | U256::one(),
+                paymaster_context: Bytes::default(),
+            },
+            sender_info: (U256::from(1), U256::from(2)),
+            ..Default::default()
+        };
+        let ep = ep.with_simulations_code(Some(Bytes::from(vec![0x60, 0x00])));
+        mock.push(Bytes::from(ethers::abi::encode(&[res.into_token()]))).unwrap();
+
+        let SimulateValidationResult::ValidationResult(res) =
+            ep.simulate_validation(uo).await.unwrap()
+        else {
+            panic!("user operation without an aggregator");
+        };
+        assert_eq!(
+            res.return_info,
+            (U256::from(50_000), U256::from(1_000), true, 10, 100, Bytes::default())
+        );
+        assert_eq!(res.sender_info, (U256::from(1), U256::from(2)));
+    }
 
     #[tokio::test]
     #[ignore]
@@ -138,6 +138,9 @@ impl EntryPointError {
 // case revert with `require` error would ends up with error event signature `0x08c379a0`
 // we need to handle it manually
 pub fn decode_revert_string(data: Bytes) -> Option<String> {
+    if data.len() < 4 {
+        return None;
+    }
     let (error_sig, reason) = data.split_at(4);
     if error_sig == [0x08, 0xc3, 0x79, 0xa0] {
         <String as AbiDecode>::decode(reason).ok()
@@ -0,0 +1,98 @@
+//! OP Stack `GasPriceOracle` predeploy used to price the L1 data posting cost of bundles
+
+use crate::{entry_point::EntryPoint, error::EntryPointError};
+use ethers::{
+    contract::abigen,
+    providers::Middleware,
+    types::{Address, Bytes, U256},
+};
+use silius_primitives::UserOperationSigned;
+use std::sync::Arc;
+
+abigen!(
+    GasPriceOracleAPI,
+    r#"[
+        function getL1Fee(bytes memory _data) external view returns (uint256)
+    ]"#
+);
+
+/// Address of the `GasPriceOracle` predeploy on OP Stack chains
+pub const GAS_PRICE_ORACLE_ADDRESS: &str = "0x420000000000000000000000000000000000000F";
+
+#[derive(Clone)]
+pub struct GasPriceOracle<M: Middleware + 'static> {
+    address: Address,
+    gas_price_oracle_api: GasPriceOracleAPI<M>,
+}
+
+impl<M: Middleware + 'static> GasPriceOracle<M> {
+    /// Creates the oracle object pointing to the OP Stack predeploy
+    pub fn new(eth_client: Arc<M>) -> Self {
+        let address = GAS_PRICE_ORACLE_ADDRESS.parse().expect("Predeploy address is valid");
+        Self { address, gas_price_oracle_api: GasPriceOracleAPI::new(address, eth_client) }
+    }
+
+    pub fn address(&self) -> Address {
+        self.address
+    }
+
+    /// Queries the L1 fee (in wei) for posting the given transaction data to L1
+    pub async fn get_l1_fee(&self, data: Bytes) -> Result<U256, EntryPointError> {
+        self.gas_price_oracle_api
+            .get_l1_fee(data)
+            .call()
+            .await
+            .map_err(|err| EntryPointError::Other { inner: format!("get l1 fee error: {err:?}") })
+    }
+
+    /// Queries the L1 fee (in wei) for the serialized `handleOps` calldata of the given user
+    /// operations (in the ABI of the version of the entry point)
+    pub async fn get_handle_ops_l1_fee(
+        &self,
+        entry_point: &EntryPoint<M>,
+        uos: Vec<UserOperationSigned>,
+        beneficiary: Address,
+    ) -> Result<U256, EntryPointError> {
+        let data = entry_point.version().handle_ops_call_data(uos, beneficiary).map_err(|err| {
+            EntryPointError::ABI { inner: format!("failed to encode handle ops: {err}") }
+        })?;
+
+        self.get_l1_fee(data).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entry_point::EntryPointVersion;
+    use ethers::{
+        abi::{encode, Token},
+        providers::Provider,
+    };
+
+    #[tokio::test]
+    async fn get_handle_ops_l1_fee() {
+        let (provider, mock) = Provider::mocked();
+        let eth_client = Arc::new(provider);
+        let ep = EntryPoint::new(eth_client.clone(), Address::random());
+        let oracle = GasPriceOracle::new(eth_client);
+
+        let l1_fee = U256::from(123_456_789_u64);
+        mock.push(Bytes::from(encode(&[Token::Uint(l1_fee)]))).unwrap();
+
+        let fee = oracle
+            .get_handle_ops_l1_fee(&ep, vec![UserOperationSigned::default()], Address::random())
+            .await
+            .unwrap();
+        assert_eq!(fee, l1_fee);
+
+        // the calldata is encoded for the version of the entry point (user operations that can't
+        // be packed for the entry point v0.7 fail before querying the oracle)
+        let ep = ep.with_version(EntryPointVersion::V0_7);
+        let uo = UserOperationSigned { max_fee_per_gas: U256::MAX, ..Default::default() };
+        assert!(matches!(
+            oracle.get_handle_ops_l1_fee(&ep, vec![uo], Address::random()).await,
+            Err(EntryPointError::ABI { .. })
+        ));
+    }
+}
@@ -1,8 +1,10 @@
 use ethers::{
-    contract::{abigen, EthCall},
-    types::Selector,
+    abi::{AbiType, Tokenizable},
+    contract::{abigen, EthAbiType, EthCall, EthEvent},
+    types::{Address, Bytes, Selector, U256},
 };
 use lazy_static::lazy_static;
+use silius_primitives::PackedUserOperation;
 use std::collections::HashMap;
 
 abigen!(AccountAPI, "$OUT_DIR/IAccount.sol/IAccount.json");
@@ -11,6 +13,116 @@ abigen!(PaymasterAPI, "$OUT_DIR/IPaymaster.sol/IPaymaster.json");
 abigen!(SenderCreatorAPI, "$OUT_DIR/SenderCreator.sol/SenderCreator.json");
 abigen!(StakeManagerAPI, "$OUT_DIR/IStakeManager.sol/IStakeManager.json");
 
+/// Event emitted by the entry point (v0.7) if the paymaster's `postOp` reverts
+#[derive(Clone, Debug, Default, Eq, PartialEq, EthEvent)]
+#[ethevent(name = "PostOpRevertReason", abi = "PostOpRevertReason(bytes32,address,uint256,bytes)")]
+pub struct PostOpRevertReasonFilter {
+    #[ethevent(indexed)]
+    pub user_op_hash: [u8; 32],
+    #[ethevent(indexed)]
+    pub sender: Address,
+    pub nonce: U256,
+    pub revert_reason: Bytes,
+}
+
+/// `handleOps` of the entry point (v0.7), which takes packed user operations
+#[derive(Clone, Debug, Default, Eq, PartialEq, EthCall)]
+#[ethcall(
+    name = "handleOps",
+    abi = "handleOps((address,uint256,bytes,bytes,bytes32,uint256,bytes32,bytes,bytes)[],address)"
+)]
+pub struct HandleOpsV07Call {
+    pub ops: Vec<PackedUserOperation>,
+    pub beneficiary: Address,
+}
+
+/// `simulateValidation` of the entry point simulations (v0.7)
+#[derive(Clone, Debug, Default, Eq, PartialEq, EthCall)]
+#[ethcall(
+    name = "simulateValidation",
+    abi = "simulateValidation((address,uint256,bytes,bytes,bytes32,uint256,bytes32,bytes,bytes))"
+)]
+pub struct SimulateValidationV07Call {
+    pub user_op: PackedUserOperation,
+}
+
+/// `simulateHandleOp` of the entry point simulations (v0.7)
+#[derive(Clone, Debug, Default, Eq, PartialEq, EthCall)]
+#[ethcall(
+    name = "simulateHandleOp",
+    abi = "simulateHandleOp((address,uint256,bytes,bytes,bytes32,uint256,bytes32,bytes,bytes),address,bytes)"
+)]
+pub struct SimulateHandleOpV07Call {
+    pub op: PackedUserOperation,
+    pub target: Address,
+    pub target_call_data: Bytes,
+}
+
+/// `validateUserOp` of the account (v0.7), which takes the packed user operation
+#[derive(Clone, Debug, Default, Eq, PartialEq, EthCall)]
+#[ethcall(
+    name = "validateUserOp",
+    abi = "validateUserOp((address,uint256,bytes,bytes,bytes32,uint256,bytes32,bytes,bytes),bytes32,uint256)"
+)]
+pub struct ValidateUserOpV07Call {
+    pub user_op: PackedUserOperation,
+    pub user_op_hash: [u8; 32],
+    pub missing_account_funds: U256,
+}
+
+/// `validatePaymasterUserOp` of the paymaster (v0.7), which takes the packed user operation
+#[derive(Clone, Debug, Default, Eq, PartialEq, EthCall)]
+#[ethcall(
+    name = "validatePaymasterUserOp",
+    abi = "validatePaymasterUserOp((address,uint256,bytes,bytes,bytes32,uint256,bytes32,bytes,bytes),bytes32,uint256)"
+)]
+pub struct ValidatePaymasterUserOpV07Call {
+    pub user_op: PackedUserOperation,
+    pub user_op_hash: [u8; 32],
+    pub max_cost: U256,
+}
+
+/// `ReturnInfo` of the validation simulation of the entry point (v0.7), with the validation data
+/// of the account and the paymaster as returned by them
+#[derive(Clone, Debug, Default, Eq, PartialEq, EthAbiType)]
+pub struct ReturnInfoV07 {
+    pub pre_op_gas: U256,
+    pub prefund: U256,
+    pub account_validation_data: U256,
+    pub paymaster_validation_data: U256,
+    pub paymaster_context: Bytes,
+}
+
+/// Result of `simulateValidation` of the entry point simulations (v0.7), which is returned
+/// (instead of reverted with, as by the entry point v0.6)
+#[derive(Clone, Debug, Default, Eq, PartialEq, EthAbiType)]
+pub struct ValidationResultV07 {
+    pub return_info: ReturnInfoV07,
+    pub sender_info: (U256, U256),
+    pub factory_info: (U256, U256),
+    pub paymaster_info: (U256, U256),
+    pub aggregator_info: (Address, (U256, U256)),
+}
+
+/// Result of `simulateHandleOp` of the entry point simulations (v0.7)
+#[derive(Clone, Debug, Default, Eq, PartialEq, EthAbiType)]
+pub struct ExecutionResultV07 {
+    pub pre_op_gas: U256,
+    pub paid: U256,
+    pub account_validation_data: U256,
+    pub paymaster_validation_data: U256,
+    pub target_success: bool,
+    pub target_result: Bytes,
+}
+
+/// Decodes the return data of the entry point simulations (v0.7), which return a single struct
+pub fn decode_simulation_return<T: AbiType + Tokenizable>(data: &[u8]) -> Option<T> {
+    ethers::abi::decode(&[T::param_type()], data)
+        .ok()
+        .and_then(|mut tokens| tokens.pop())
+        .and_then(|token| T::from_token(token).ok())
+}
+
 lazy_static! {
     pub static ref SELECTORS_NAMES: HashMap<Selector, String> = {
         let mut map = HashMap::new();
@@ -28,12 +140,18 @@ lazy_static! {
         map.insert(entry_point_api::UnlockStakeCall::selector(), entry_point_api::UnlockStakeCall::function_name().into());
         map.insert(entry_point_api::WithdrawStakeCall::selector(), entry_point_api::WithdrawStakeCall::function_name().into());
         map.insert(entry_point_api::WithdrawToCall::selector(), entry_point_api::WithdrawToCall::function_name().into());
+        // entry point (v0.7)
+        map.insert(HandleOpsV07Call::selector(), HandleOpsV07Call::function_name().into());
+        map.insert(SimulateHandleOpV07Call::selector(), SimulateHandleOpV07Call::function_name().into());
+        map.insert(SimulateValidationV07Call::selector(), SimulateValidationV07Call::function_name().into());
         // sender creator
         map.insert(sender_creator_api::CreateSenderCall::selector(), sender_creator_api::CreateSenderCall::function_name().into());
         // account
         map.insert(account_api::ValidateUserOpCall::selector(), account_api::ValidateUserOpCall::function_name().into());
+        map.insert(ValidateUserOpV07Call::selector(), ValidateUserOpV07Call::function_name().into());
         // paymaster
         map.insert(paymaster_api::ValidatePaymasterUserOpCall::selector(), paymaster_api::ValidatePaymasterUserOpCall::function_name().into());
+        map.insert(ValidatePaymasterUserOpV07Call::selector(), ValidatePaymasterUserOpV07Call::function_name().into());
         map
     };
     pub static ref SELECTORS_INDICES: HashMap<Selector, usize> = {
@@ -42,8 +160,10 @@ lazy_static! {
         map.insert(sender_creator_api::CreateSenderCall::selector(), 0);
         // sender/account
         map.insert(account_api::ValidateUserOpCall::selector(), 1);
+        map.insert(ValidateUserOpV07Call::selector(), 1);
         // paymaster
         map.insert(paymaster_api::ValidatePaymasterUserOpCall::selector(), 2);
+        map.insert(ValidatePaymasterUserOpV07Call::selector(), 2);
         map
     };
 }
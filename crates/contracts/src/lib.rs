@@ -3,12 +3,15 @@
 pub mod entry_point;
 mod error;
 pub mod executor_tracer;
+pub mod gas_price_oracle;
 mod gen;
 pub mod tracer;
 pub mod utils;
 
-pub use entry_point::EntryPoint;
+pub use entry_point::{discover_entry_points, EntryPoint, EntryPointVersion};
 pub use error::{decode_revert_string, EntryPointError};
+pub use gas_price_oracle::GasPriceOracle;
 pub use gen::{
-    ExecutionResult, FailedOp, UserOperationEventFilter, UserOperationRevertReasonFilter,
+    BeforeExecutionFilter, ExecutionResult, FailedOp, PostOpRevertReasonFilter,
+    UserOperationEventFilter, UserOperationRevertReasonFilter,
 };
@@ -1,10 +1,10 @@
 use ethers::types::{Address, Bytes, GethTrace, U256};
 use eyre::format_err;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 /// Object (frame) return the JavaScript tracer when simulating validation of user operation
-#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct JsTracerFrame {
     #[serde(rename = "callsFromEntryPoint")]
     pub calls_from_entry_point: Vec<TopLevelCallInfo>,
@@ -25,7 +25,7 @@ impl TryFrom<GethTrace> for JsTracerFrame {
     }
 }
 
-#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TopLevelCallInfo {
     #[serde(rename = "topLevelMethodSig")]
     pub top_level_method_sig: Bytes,
@@ -33,6 +33,8 @@ pub struct TopLevelCallInfo {
     pub top_level_target_address: Bytes,
     pub access: HashMap<Address, ReadsAndWrites>,
     pub opcodes: HashMap<String, u64>,
+    #[serde(rename = "contractOpcodes", default)]
+    pub contract_opcodes: HashMap<Address, HashMap<String, u64>>,
     #[serde(rename = "contractSize")]
     pub contract_size: HashMap<Address, ContractSizeInfo>,
     #[serde(rename = "extCodeAccessInfo")]
@@ -40,26 +42,26 @@ pub struct TopLevelCallInfo {
     pub oog: Option<bool>,
 }
 
-#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ReadsAndWrites {
     pub reads: HashMap<String, String>,
     pub writes: HashMap<String, u64>,
 }
 
-#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ContractSizeInfo {
     pub opcode: String,
     #[serde(rename = "contractSize")]
     pub contract_size: u64,
 }
 
-#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Log {
     pub topics: Vec<String>,
     pub data: Bytes,
 }
 
-#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Call {
     #[serde(rename = "type")]
     pub typ: String,
@@ -73,7 +75,7 @@ pub struct Call {
     pub value: Option<U256>,
 }
 
-#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct CallEntry {
     pub typ: String,
     pub from: Option<Address>,
@@ -119,11 +121,16 @@ pub const JS_TRACER: &str = r#"
             return;
         }
         // this.debug.push('enter gas=', frame.getGas(), ' type=', frame.getType(), ' to=', toHex(frame.getTo()), ' in=', toHex(frame.getInput()).slice(0, 500))
+        const input = toHex(frame.getInput());
+        const method = input.slice(0, 10);
         this.calls.push({
             type: frame.getType(),
             from: toHex(frame.getFrom()),
             to: toHex(frame.getTo()),
-            method: toHex(frame.getInput()).slice(0, 10),
+            method: method,
+            // keep the user operation hash argument of validateUserOp (selector + 2 words) of
+            // both entry point versions
+            data: method === '0x3a871cdd' || method === '0x19822f7c' ? input.slice(0, 138) : undefined,
             gas: frame.getGas(),
             value: frame.getValue()
         });
@@ -146,6 +153,15 @@ pub const JS_TRACER: &str = r#"
         list[key] = ((_a = list[key]) !== null && _a !== void 0 ? _a : 0) + 1;
     },
 
+    // count the opcode in the current level and in the frames of the executing contract
+    countOpcode: function(log, opcode) {
+        var _a;
+        this.countSlot(this.currentLevel.opcodes, opcode);
+        const addrHex = toHex(log.contract.getAddress());
+        const contractOpcodes = (_a = this.currentLevel.contractOpcodes[addrHex]) !== null && _a !== void 0 ? _a : (this.currentLevel.contractOpcodes[addrHex] = {});
+        this.countSlot(contractOpcodes, opcode);
+    },
+
     step: function(log, db) {
         var _a;
         if (this.stopCollecting) {
@@ -202,6 +218,7 @@ pub const JS_TRACER: &str = r#"
                     topLevelTargetAddress: topLevelTargetAddress,
                     access: {},
                     opcodes: {},
+                    contractOpcodes: {},
                     extCodeAccessInfo: {},
                     contractSize: {}
                 };
@@ -257,12 +274,12 @@ pub const JS_TRACER: &str = r#"
         // [OP-012] - GAS opcode is allowed, but only if followed immediately by *CALL instructions
         if (this.lastOp === 'GAS' && !opcode.includes('CALL')) {
             // count "GAS" opcode only if not followed by "CALL"
-            this.countSlot(this.currentLevel.opcodes, 'GAS');
+            this.countOpcode(log, 'GAS');
         }
         if (opcode !== 'GAS') {
             // ignore "unimportant" opcodes:
             if (opcode.match(/^(DUP\d+|PUSH\d+|SWAP\d+|POP|ADD|SUB|MUL|DIV|EQ|LTE?|S?GTE?|SLT|SH[LR]|AND|OR|NOT|ISZERO)$/) == null) {
-                this.countSlot(this.currentLevel.opcodes, opcode);
+                this.countOpcode(log, opcode);
             }
         }
         this.lastOp = opcode;
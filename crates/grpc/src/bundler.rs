@@ -1,20 +1,25 @@
 use crate::{
+    health::{reflection_service, report_serving_when_connected},
     proto::{
         bundler::*,
         uopool::{GetSortedRequest, RemoveRequest},
     },
     uo_pool_client::UoPoolClient,
+    utils::{detect_entry_point_version, parse_addr},
 };
 use alloy_chains::Chain;
 use async_trait::async_trait;
 use ethers::{
     providers::Middleware,
-    types::{Address, H256, U256},
+    types::{Address, BlockNumber, H256, U256},
 };
 use parking_lot::Mutex;
-use silius_bundler::{Bundler, SendBundleOp};
-use silius_metrics::grpc::MetricsLayer;
-use silius_primitives::{simulation::StorageMap, UserOperation, Wallet};
+use silius_bundler::{Bundler, NonceTracker, SendBundleOp};
+use silius_metrics::{bundler::record_bundler_balance, grpc::MetricsLayer};
+use silius_primitives::{
+    bundler::BundlerInstance, constants::bundler::BALANCE_CHECK_INTERVAL, simulation::StorageMap,
+    UserOperation, Wallet,
+};
 use std::{net::SocketAddr, sync::Arc, time::Duration};
 use tonic::{Request, Response, Status};
 use tracing::{error, info};
@@ -48,9 +53,12 @@ where
 
     async fn get_user_operations(
         uopool_grpc_client: &UoPoolClient<tonic::transport::Channel>,
-        ep: &Address,
+        bundler: &Bundler<M, S>,
     ) -> eyre::Result<(Vec<UserOperation>, StorageMap)> {
-        let req = Request::new(GetSortedRequest { ep: Some((*ep).into()) });
+        let req = Request::new(GetSortedRequest {
+            ep: Some(bundler.entry_point.into()),
+            instance: bundler.instance.map(Into::into),
+        });
         let res = uopool_grpc_client.clone().get_sorted_user_operations(req).await?;
 
         let res = res.into_inner();
@@ -64,14 +72,34 @@ where
         Ok((uos, map))
     }
 
+    /// Removes the user operations dropped from bundles for an invalid nonce from the mempool
+    async fn remove_dropped(
+        uopool_grpc_client: &UoPoolClient<tonic::transport::Channel>,
+        bundler: &Bundler<M, S>,
+    ) -> eyre::Result<()> {
+        let uos = bundler.take_dropped();
+        if uos.is_empty() {
+            return Ok(());
+        }
+
+        let req = Request::new(RemoveRequest {
+            uos: uos.into_iter().map(|uo| uo.into()).collect(),
+            ep: Some(bundler.entry_point.into()),
+        });
+        uopool_grpc_client.clone().remove(req).await?;
+
+        Ok(())
+    }
+
     pub async fn send_bundles(&self) -> eyre::Result<(Vec<UserOperation>, Option<H256>)> {
         let mut tx_hashes: Vec<Option<H256>> = vec![];
         let mut user_operations: Vec<Vec<UserOperation>> = vec![];
 
         for bundler in self.bundlers.iter() {
-            let (uos, map) =
-                Self::get_user_operations(&self.uopool_grpc_client, &bundler.entry_point).await?;
-            let tx_hash = bundler.send_bundle(&uos, map).await?;
+            let (uos, map) = Self::get_user_operations(&self.uopool_grpc_client, bundler).await?;
+            let res = bundler.send_bundle(&uos, map).await;
+            Self::remove_dropped(&self.uopool_grpc_client, bundler).await?;
+            let tx_hash = res?.map(|res| res.hash);
 
             tx_hashes.push(tx_hash);
             user_operations.push(uos);
@@ -95,6 +123,32 @@ where
         is_running(self.running.clone())
     }
 
+    /// Periodically checks the balance of the bundler's wallet (shared by all bundlers), records
+    /// it as a metric and logs a warning if it's below the warning threshold
+    pub fn start_balance_monitor(&self, int: u64) {
+        let Some(bundler) = self.bundlers.first().cloned() else {
+            return;
+        };
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(int));
+            loop {
+                interval.tick().await;
+
+                match bundler.check_balance().await {
+                    Ok(balance) => record_bundler_balance(
+                        bundler.wallet.signer.address(),
+                        balance,
+                        bundler.balance_warning_threshold,
+                    ),
+                    Err(e) => {
+                        error!("Error while checking the balance of the bundler: {e:?}");
+                    }
+                }
+            }
+        });
+    }
+
     pub fn start_bundling(&self, int: u64) {
         if !self.is_running() {
             info!("Starting auto bundling");
@@ -118,16 +172,16 @@ where
                             break;
                         }
 
-                        match Self::get_user_operations(
-                            &uopool_grpc_client,
-                            &bundler_own.entry_point,
-                        )
-                        .await
-                        {
+                        match Self::get_user_operations(&uopool_grpc_client, &bundler_own).await {
                             Ok((bundle, map)) => {
                                 if let Err(e) = bundler_own.send_bundle(&bundle, map).await {
                                     error!("Error while sending bundle: {e:?}");
                                 }
+                                if let Err(e) =
+                                    Self::remove_dropped(&uopool_grpc_client, &bundler_own).await
+                                {
+                                    error!("Error while removing dropped user operations: {e:?}");
+                                }
                             }
                             Err(e) => {
                                 error!("Error while creating bundle: {e:?}");
@@ -208,32 +262,75 @@ where
 
         Ok(Response::new(SendBundleNowResponse { res: Some(tx_hash.unwrap_or_default().into()) }))
     }
+
+    async fn get_beneficiary(
+        &self,
+        _req: Request<()>,
+    ) -> Result<Response<GetBeneficiaryResponse>, Status> {
+        let beneficiaries =
+            self.bundlers.first().expect("Must have at least one bundler").beneficiaries();
+
+        Ok(Response::new(GetBeneficiaryResponse {
+            beneficiaries: beneficiaries.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn set_beneficiary(
+        &self,
+        req: Request<SetBeneficiaryRequest>,
+    ) -> Result<Response<()>, Status> {
+        let req = req.into_inner();
+
+        let beneficiary = parse_addr(req.beneficiary)?;
+        for bundler in self.bundlers.iter() {
+            bundler.set_beneficiary(beneficiary).map_err(|e| {
+                tonic::Status::invalid_argument(format!("Set beneficiary with error: {e:?}"))
+            })?;
+        }
+        info!("Beneficiary set to {beneficiary:?}");
+
+        Ok(Response::new(()))
+    }
 }
 
 #[allow(clippy::too_many_arguments)]
-pub fn bundler_service_run<M, S>(
+pub async fn bundler_service_run<M, S>(
     addr: SocketAddr,
     wallet: Wallet,
     eps: Vec<Address>,
     chain: Chain,
-    beneficiary: Address,
+    beneficiaries: Vec<Address>,
     min_balance: U256,
+    balance_warning_threshold: Option<U256>,
     bundle_interval: Option<u64>,
     eth_client: Arc<M>,
     client: Arc<S>,
     uopool_grpc_client: UoPoolClient<tonic::transport::Channel>,
     enable_metrics: bool,
     enable_access_list: bool,
+    max_bundle_rebuilds: usize,
+    priority_fee_margin: u64,
+    gas_limit_buffer: u64,
+    max_bundle_submissions: usize,
+    tip_escalation: u64,
+    instance: Option<BundlerInstance>,
+    nonce_block: BlockNumber,
 ) where
     M: Middleware + Clone + 'static,
     S: SendBundleOp + Clone + 'static,
 {
-    let bundlers: Vec<Bundler<M, S>> = eps
-        .into_iter()
-        .map(|ep| {
+    // the bundlers of all entry points send bundles from the same wallet
+    let nonce_tracker = NonceTracker::default();
+    let mut bundlers: Vec<Bundler<M, S>> = Vec::with_capacity(eps.len());
+    for ep in eps {
+        // bundles are encoded in the handleOps ABI of the detected version of the entry point
+        let version = detect_entry_point_version(eth_client.clone(), ep, None).await;
+
+        // the gas is refunded to the bundler if no beneficiary is set
+        bundlers.push(
             Bundler::new(
                 wallet.clone(),
-                beneficiary,
+                beneficiaries.clone(),
                 ep,
                 chain,
                 min_balance,
@@ -241,21 +338,50 @@ pub fn bundler_service_run<M, S>(
                 client.clone(),
                 enable_access_list,
             )
-        })
-        .collect();
+            .with_max_rebuilds(max_bundle_rebuilds)
+            .with_priority_fee_margin(priority_fee_margin)
+            .with_gas_limit_buffer(gas_limit_buffer)
+            .with_max_submissions(max_bundle_submissions)
+            .with_tip_escalation(tip_escalation)
+            .with_instance(instance)
+            .with_nonce_block(nonce_block)
+            .with_nonce_tracker(nonce_tracker.clone())
+            .with_balance_warning_threshold(balance_warning_threshold.unwrap_or(min_balance))
+            .with_entry_point_version(version),
+        );
+    }
 
     let bundler_service = BundlerService::new(bundlers, uopool_grpc_client);
+    bundler_service.start_balance_monitor(BALANCE_CHECK_INTERVAL);
     if let Some(bundle_interval) = bundle_interval {
         bundler_service.start_bundling(bundle_interval);
     }
 
+    // the service is reported as serving once the execution client is reachable
+    let (health_reporter, health_svc) = tonic_health::server::health_reporter();
+    tokio::spawn(report_serving_when_connected::<
+        _,
+        bundler_server::BundlerServer<BundlerService<M, S>>,
+    >(eth_client, health_reporter));
+
     tokio::spawn(async move {
         let mut builder = tonic::transport::Server::builder();
         let svc = bundler_server::BundlerServer::new(bundler_service);
         if enable_metrics {
-            builder.layer(MetricsLayer).add_service(svc).serve(addr).await
+            builder
+                .layer(MetricsLayer)
+                .add_service(health_svc)
+                .add_service(reflection_service())
+                .add_service(svc)
+                .serve(addr)
+                .await
         } else {
-            builder.add_service(svc).serve(addr).await
+            builder
+                .add_service(health_svc)
+                .add_service(reflection_service())
+                .add_service(svc)
+                .serve(addr)
+                .await
         }
     });
 }
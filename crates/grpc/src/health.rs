@@ -0,0 +1,85 @@
+//! Standard gRPC health checking and reflection of the uopool and bundler servers.
+
+use crate::proto::FILE_DESCRIPTOR_SET;
+use ethers::providers::Middleware;
+use silius_primitives::constants::grpc::HEALTH_PROBE_INTERVAL;
+use std::{sync::Arc, time::Duration};
+use tonic::server::NamedService;
+use tonic_health::server::HealthReporter;
+use tonic_reflection::server::{ServerReflection, ServerReflectionServer};
+use tracing::{info, warn};
+
+/// Reports the service as not serving until the execution client is reachable (probed every
+/// [HEALTH_PROBE_INTERVAL] seconds) and as serving afterward
+///
+/// # Arguments
+/// * `eth_client` - The client of the execution client
+/// * `reporter` - The [HealthReporter] of the server the service is added to
+pub async fn report_serving_when_connected<M, S>(eth_client: Arc<M>, mut reporter: HealthReporter)
+where
+    M: Middleware + 'static,
+    S: NamedService,
+{
+    reporter.set_not_serving::<S>().await;
+
+    while let Err(err) = eth_client.get_block_number().await {
+        warn!("{} waiting for the execution client: {err:?}", S::NAME);
+        tokio::time::sleep(Duration::from_secs(HEALTH_PROBE_INTERVAL)).await;
+    }
+
+    info!("{} connected to the execution client", S::NAME);
+    reporter.set_serving::<S>().await;
+}
+
+/// Builds the reflection service describing the uopool and bundler services
+pub fn reflection_service() -> ServerReflectionServer<impl ServerReflection> {
+    tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(FILE_DESCRIPTOR_SET)
+        .register_encoded_file_descriptor_set(tonic_health::pb::FILE_DESCRIPTOR_SET)
+        .build()
+        .expect("file descriptor sets of the protos should be valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::{providers::Provider, types::U64};
+    use std::net::TcpListener;
+    use tonic_health::pb::{
+        health_check_response::ServingStatus, health_client::HealthClient, HealthCheckRequest,
+    };
+
+    struct TestService;
+
+    impl NamedService for TestService {
+        const NAME: &'static str = "test.TestService";
+    }
+
+    #[tokio::test]
+    async fn serving_once_connected() {
+        let (provider, mock) = Provider::mocked();
+        let (reporter, health_service) = tonic_health::server::health_reporter();
+        let addr = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(health_service)
+                .add_service(reflection_service())
+                .serve(addr),
+        );
+        tokio::spawn(report_serving_when_connected::<_, TestService>(Arc::new(provider), reporter));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let mut client = HealthClient::connect(format!("http://{addr}")).await.unwrap();
+        let mut statuses = client
+            .watch(HealthCheckRequest { service: TestService::NAME.into() })
+            .await
+            .unwrap()
+            .into_inner();
+
+        // the execution client isn't reachable yet
+        assert_eq!(statuses.message().await.unwrap().unwrap().status(), ServingStatus::NotServing);
+
+        mock.push(U64::from(1)).unwrap();
+        assert_eq!(statuses.message().await.unwrap().unwrap().status(), ServingStatus::Serving);
+    }
+}
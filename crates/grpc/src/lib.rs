@@ -1,10 +1,12 @@
 #![allow(dead_code)]
 
 mod bundler;
+mod health;
 mod proto;
 mod uopool;
 mod utils;
 
 pub use bundler::{bundler_service_run, BundlerService};
+pub use health::{reflection_service, report_serving_when_connected};
 pub use proto::{bundler::*, types::*, uopool::*};
 pub use uopool::{uopool_service_run, UoPoolService};
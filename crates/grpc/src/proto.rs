@@ -1,4 +1,8 @@
 // Code adapted from: https://github.com/ledgerwatch/interfaces/blob/master/src/lib.rs#L1
+
+/// Encoded file descriptor set of the protos (served by the reflection service)
+pub const FILE_DESCRIPTOR_SET: &[u8] = tonic::include_file_descriptor_set!("descriptor");
+
 pub mod types {
     use arrayref::array_ref;
     use ethers::types::{Address, Bloom, U256};
@@ -230,6 +234,7 @@ pub mod types {
                 uo_included: reputation_entry.uo_included,
                 stat: match silius_primitives::reputation::Status::from(reputation_entry.status) {
                     silius_primitives::reputation::Status::OK => ReputationStatus::Ok,
+                    silius_primitives::reputation::Status::PROBATION => ReputationStatus::Probation,
                     silius_primitives::reputation::Status::THROTTLED => ReputationStatus::Throttled,
                     silius_primitives::reputation::Status::BANNED => ReputationStatus::Banned,
                 } as i32,
@@ -253,6 +258,9 @@ pub mod types {
                     _ if reputation_entry.stat == ReputationStatus::Ok as i32 => {
                         silius_primitives::reputation::Status::OK.into()
                     }
+                    _ if reputation_entry.stat == ReputationStatus::Probation as i32 => {
+                        silius_primitives::reputation::Status::PROBATION.into()
+                    }
                     _ if reputation_entry.stat == ReputationStatus::Throttled as i32 => {
                         silius_primitives::reputation::Status::THROTTLED.into()
                     }
@@ -425,7 +433,72 @@ pub mod types {
 }
 
 pub mod uopool {
+    use silius_mempool::{validate::UserOperationValidationOutcome, NewUserOperation};
+    use silius_primitives::SimulationOutcome;
+
     tonic::include_proto!("uopool");
+
+    impl From<&UserOperationValidationOutcome> for ValidationOutcome {
+        fn from(outcome: &UserOperationValidationOutcome) -> Self {
+            Self {
+                pre_fund: Some(outcome.pre_fund.into()),
+                verification_gas_limit: Some(outcome.verification_gas_limit.into()),
+                call_gas_limit: Some(outcome.call_gas_limit.into()),
+                pre_verification_gas: Some(outcome.pre_verification_gas.into()),
+                valid_after: outcome.valid_after.map(|valid_after| valid_after.into()),
+                verified_block: Some(outcome.verified_block.into()),
+                prev_hash: outcome.prev_hash.map(|prev_hash| prev_hash.into()),
+            }
+        }
+    }
+
+    impl From<NewUserOperation> for NewUserOperationResponse {
+        fn from(new_uo: NewUserOperation) -> Self {
+            Self {
+                uo: Some(new_uo.user_operation.into()),
+                ep: Some(new_uo.entry_point.into()),
+                cancels: new_uo.cancels.map(|cancels| cancels.into()),
+            }
+        }
+    }
+
+    impl From<silius_primitives::UserOperationSource> for UserOperationSource {
+        fn from(value: silius_primitives::UserOperationSource) -> Self {
+            match value {
+                silius_primitives::UserOperationSource::Rpc => Self::Rpc,
+                silius_primitives::UserOperationSource::P2p => Self::P2p,
+            }
+        }
+    }
+
+    impl From<UserOperationSource> for silius_primitives::UserOperationSource {
+        fn from(value: UserOperationSource) -> Self {
+            match value {
+                UserOperationSource::Rpc => Self::Rpc,
+                UserOperationSource::P2p => Self::P2p,
+            }
+        }
+    }
+
+    impl From<silius_primitives::bundler::BundlerInstance> for BundlerInstance {
+        fn from(instance: silius_primitives::bundler::BundlerInstance) -> Self {
+            Self { index: instance.index, count: instance.count }
+        }
+    }
+
+    impl From<ValidationOutcome> for SimulationOutcome {
+        fn from(outcome: ValidationOutcome) -> Self {
+            Self {
+                pre_fund: outcome.pre_fund.unwrap_or_default().into(),
+                verification_gas_limit: outcome.verification_gas_limit.unwrap_or_default().into(),
+                call_gas_limit: outcome.call_gas_limit.unwrap_or_default().into(),
+                pre_verification_gas: outcome.pre_verification_gas.unwrap_or_default().into(),
+                valid_after: outcome.valid_after.map(|valid_after| valid_after.into()),
+                verified_block: outcome.verified_block.unwrap_or_default().into(),
+                prev_hash: outcome.prev_hash.map(|prev_hash| prev_hash.into()),
+            }
+        }
+    }
 }
 
 pub mod bundler {
@@ -451,3 +524,54 @@ pub mod bundler {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::uopool::{AddResponse, AddResult, ValidationOutcome};
+    use ethers::types::{H256, U256};
+    use prost::Message;
+    use silius_mempool::validate::UserOperationValidationOutcome;
+    use silius_primitives::UserOperationHash;
+
+    #[test]
+    fn validation_outcome_round_trip() {
+        let prev_hash: UserOperationHash = H256::random().into();
+        let outcome = UserOperationValidationOutcome {
+            prev_hash: Some(prev_hash),
+            pre_fund: U256::from(1_000_000_000_000_u64),
+            verification_gas_limit: U256::from(150_000),
+            call_gas_limit: U256::from(200_000),
+            pre_verification_gas: U256::from(50_000),
+            valid_after: Some(U256::from(1_700_000_000)),
+            verified_block: U256::from(42),
+            ..Default::default()
+        };
+
+        let res = AddResponse {
+            res: AddResult::Added as i32,
+            data: String::new(),
+            outcome: Some(ValidationOutcome::from(&outcome)),
+        };
+        let res = AddResponse::decode(res.encode_to_vec().as_slice()).unwrap();
+
+        let decoded = res.outcome.unwrap();
+        assert_eq!(U256::from(decoded.pre_fund.unwrap()), outcome.pre_fund);
+        assert_eq!(
+            U256::from(decoded.verification_gas_limit.unwrap()),
+            outcome.verification_gas_limit
+        );
+        assert_eq!(U256::from(decoded.call_gas_limit.unwrap()), outcome.call_gas_limit);
+        assert_eq!(U256::from(decoded.pre_verification_gas.unwrap()), outcome.pre_verification_gas);
+        assert_eq!(decoded.valid_after.map(U256::from), outcome.valid_after);
+        assert_eq!(U256::from(decoded.verified_block.unwrap()), outcome.verified_block);
+        assert_eq!(decoded.prev_hash.map(UserOperationHash::from), Some(prev_hash));
+
+        // outcomes without the optional fields
+        let outcome = UserOperationValidationOutcome::default();
+        let decoded =
+            ValidationOutcome::decode(ValidationOutcome::from(&outcome).encode_to_vec().as_slice())
+                .unwrap();
+        assert!(decoded.valid_after.is_none());
+        assert!(decoded.prev_hash.is_none());
+    }
+}
@@ -1,9 +1,10 @@
 use crate::{
+    health::{reflection_service, report_serving_when_connected},
     proto::{
         types::{GetChainIdResponse, GetSupportedEntryPointsResponse},
         uopool::*,
     },
-    utils::{parse_addr, parse_hash, parse_uo},
+    utils::{detect_entry_point_version, parse_addr, parse_hash, parse_uo},
 };
 use alloy_chains::Chain;
 use async_trait::async_trait;
@@ -12,22 +13,36 @@ use ethers::{
     types::{Address, U256},
 };
 use eyre::Result;
-use futures::{channel::mpsc::unbounded, StreamExt};
+use futures::{
+    channel::mpsc::{unbounded, UnboundedSender},
+    Future, Stream, StreamExt,
+};
 use parking_lot::RwLock;
+use silius_contracts::EntryPointVersion;
 use silius_mempool::{
-    mempool_id, validate::validator::StandardUserOperationValidator, Mempool, MempoolErrorKind,
-    MempoolId, Reputation, SanityCheck, SimulationCheck, SimulationTraceCheck,
+    mempool_id, validate::validator::StandardUserOperationValidator, Mempool, MempoolError,
+    MempoolErrorKind, MempoolId, Reputation, SanityCheck, SimulationCheck, SimulationTraceCheck,
     UoPool as UserOperationPool, UoPoolBuilder,
 };
 use silius_metrics::grpc::MetricsLayer;
 use silius_p2p::{
     config::Config,
     service::{MempoolChannel, Network},
+    types::globals::NetworkGlobals,
+};
+use silius_primitives::{
+    bundler::BundlerInstance, constants::p2p::VALIDATION_WORKERS, p2p::NetworkMessage,
+    provider::BlockStream, simulation::ValidationConfig, NonceGapConfig, ReplacementPolicy,
+    UoPoolMode, UserOperation, UserOperationSource, ValidAfterPolicy,
+};
+use std::{collections::HashMap, net::SocketAddr, pin::Pin, sync::Arc, time::Duration};
+use tokio::sync::{
+    broadcast::error::RecvError,
+    oneshot::{self, error::TryRecvError},
+    Semaphore,
 };
-use silius_primitives::{p2p::NetworkMessage, provider::BlockStream, UoPoolMode};
-use std::{collections::HashMap, net::SocketAddr, sync::Arc, time::Duration};
 use tonic::{Code, Request, Response, Status};
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 type StandardUserPool<M, SanCk, SimCk, SimTrCk> =
     UserOperationPool<M, StandardUserOperationValidator<M, SanCk, SimCk, SimTrCk>>;
@@ -44,6 +59,7 @@ where
 {
     pub uopools: UoPoolMaps<M, SanCk, SimCk, SimTrCk>,
     pub chain: Chain,
+    pub network_globals: Option<Arc<NetworkGlobals>>,
 }
 
 impl<M, SanCk, SimCk, SimTrCk> UoPoolService<M, SanCk, SimCk, SimTrCk>
@@ -53,8 +69,12 @@ where
     SimCk: SimulationCheck + Clone + 'static,
     SimTrCk: SimulationTraceCheck<M> + Clone + 'static,
 {
-    pub fn new(uopools: UoPoolMaps<M, SanCk, SimCk, SimTrCk>, chain: Chain) -> Self {
-        Self { uopools, chain }
+    pub fn new(
+        uopools: UoPoolMaps<M, SanCk, SimCk, SimTrCk>,
+        chain: Chain,
+        network_globals: Option<Arc<NetworkGlobals>>,
+    ) -> Self {
+        Self { uopools, chain, network_globals }
     }
 
     #[allow(clippy::type_complexity)]
@@ -85,31 +105,99 @@ where
         let uo = parse_uo(req.uo)?;
         let ep = parse_addr(req.ep)?;
 
-        let res = {
-            let uopool = self.get_uopool(&ep)?;
-            uopool.validate_user_operation(&uo, None).await
+        let uopool = self.get_uopool(&ep)?;
+        let uo = uopool.normalize_user_operation(uo);
+
+        // user operations already in the mempool aren't validated again
+        let (added, outcome) = match uopool.check_duplicate(&uo) {
+            Ok(true) => (Ok(uo.hash), None),
+            Err(err) => (Err(err), None),
+            Ok(false) => {
+                let res = uopool.validate_user_operation(&uo, None).await;
+                let outcome = res.as_ref().ok().map(ValidationOutcome::from);
+                let mut uopool = self.get_uopool(&ep)?;
+                (uopool.add_user_operation(uo, res, UserOperationSource::Rpc).await, outcome)
+            }
         };
 
-        let mut uopool = self.get_uopool(&ep)?;
-
-        match uopool.add_user_operation(uo, res).await {
+        match added {
             Ok(uo_hash) => Ok(Response::new(AddResponse {
                 res: AddResult::Added as i32,
                 data: serde_json::to_string(&uo_hash)
                     .map_err(|err| Status::internal(format!("Failed to serialize hash: {err}")))?,
+                outcome,
             })),
             Err(err) => match err.kind {
-                MempoolErrorKind::InvalidUserOperation(_) => Ok(Response::new(AddResponse {
+                MempoolErrorKind::InvalidUserOperation(_) |
+                MempoolErrorKind::MempoolFull { .. } |
+                MempoolErrorKind::DelayedQueueFull { .. } |
+                MempoolErrorKind::ReplacementUnderpriced { .. } |
+                MempoolErrorKind::HashCollision { .. } |
+                MempoolErrorKind::NonceGap { .. } => Ok(Response::new(AddResponse {
                     res: AddResult::NotAdded as i32,
                     data: serde_json::to_string(&err).map_err(|err| {
                         Status::internal(format!("Failed to serialize error: {err}"))
                     })?,
+                    outcome: None,
                 })),
                 _ => Err(Status::internal(format!("Internal error: {err}"))),
             },
         }
     }
 
+    async fn simulate(
+        &self,
+        req: Request<AddRequest>,
+    ) -> Result<Response<SimulateResponse>, Status> {
+        let req = req.into_inner();
+
+        let uo = parse_uo(req.uo)?;
+        let ep = parse_addr(req.ep)?;
+
+        let uopool = self.get_uopool(&ep)?;
+        let uo = uopool.normalize_user_operation(uo);
+
+        match uopool.simulate_user_operation(&uo).await {
+            Ok(outcome) => Ok(Response::new(SimulateResponse {
+                outcome: Some(ValidationOutcome::from(&outcome)),
+                data: String::new(),
+            })),
+            Err(err) => Ok(Response::new(SimulateResponse {
+                outcome: None,
+                data: serde_json::to_string(&MempoolError { hash: uo.hash, kind: err.into() })
+                    .map_err(|err| Status::internal(format!("Failed to serialize error: {err}")))?,
+            })),
+        }
+    }
+
+    async fn trace_validation(
+        &self,
+        req: Request<AddRequest>,
+    ) -> Result<Response<TraceValidationResponse>, Status> {
+        let req = req.into_inner();
+
+        let uo = parse_uo(req.uo)?;
+        let ep = parse_addr(req.ep)?;
+
+        let uopool = self.get_uopool(&ep)?;
+        let uo = uopool.normalize_user_operation(uo);
+
+        // traced with the validation config of the user operations added via RPC
+        match uopool.trace_user_operation_validation(&uo, None).await {
+            Ok(trace) => Ok(Response::new(TraceValidationResponse {
+                trace: serde_json::to_string(&trace).map_err(|err| {
+                    Status::internal(format!("Failed to serialize validation trace: {err}"))
+                })?,
+                data: String::new(),
+            })),
+            Err(err) => Ok(Response::new(TraceValidationResponse {
+                trace: String::new(),
+                data: serde_json::to_string(&MempoolError { hash: uo.hash, kind: err.into() })
+                    .map_err(|err| Status::internal(format!("Failed to serialize error: {err}")))?,
+            })),
+        }
+    }
+
     async fn remove(&self, req: Request<RemoveRequest>) -> Result<Response<()>, Status> {
         let req = req.into_inner();
 
@@ -150,10 +238,18 @@ where
 
         let uo = parse_uo(req.uo)?;
         let ep = parse_addr(req.ep)?;
+        let state_override = if req.state_override.is_empty() {
+            None
+        } else {
+            Some(serde_json::from_str(&req.state_override).map_err(|err| {
+                Status::invalid_argument(format!("Failed to parse state override: {err}"))
+            })?)
+        };
 
         let uopool = self.get_uopool(&ep)?;
+        let uo = uopool.normalize_user_operation(uo);
 
-        Ok(Response::new(match uopool.estimate_user_operation_gas(&uo).await {
+        Ok(Response::new(match uopool.estimate_user_operation_gas(&uo, state_override).await {
             Ok(gas) => EstimateUserOperationGasResponse {
                 res: EstimateUserOperationGasResult::Estimated as i32,
                 data: serde_json::to_string(&gas)
@@ -174,14 +270,25 @@ where
         let req = req.into_inner();
 
         let ep = parse_addr(req.ep)?;
+        let instance = req
+            .instance
+            .map(|instance| BundlerInstance::new(instance.index, instance.count))
+            .transpose()
+            .map_err(Status::invalid_argument)?;
 
-        let uos = {
+        let mut uos = {
             let uopool = self.get_uopool(&ep)?;
-            uopool.get_sorted_user_operations().map_err(|e| {
+            uopool.get_sorted_user_operations().await.map_err(|e| {
                 tonic::Status::internal(format!("Get sorted uos internal error: {e:?}"))
             })?
         };
 
+        // user operations assigned to other bundler instances are bundled by them (filtered
+        // before the bundle is selected, so it's filled with the assigned ones)
+        if let Some(instance) = instance {
+            uos.retain(|uo| instance.is_assigned(&uo.sender));
+        }
+
         let (uos_valid, storage_map) = {
             let mut uopool = self.get_uopool(&ep)?;
             uopool
@@ -266,14 +373,66 @@ where
 
         let ep = parse_addr(req.ep)?;
         let uopool = self.get_uopool(&ep)?;
-        match uopool.get_all() {
-            Ok(uos) => {
-                Ok(Response::new(GetAllResponse { uos: uos.into_iter().map(Into::into).collect() }))
+        // a zero limit returns the maximum page size (the whole mempool if not set)
+        let limit = (req.limit > 0).then_some(req.limit as usize);
+        let after = req
+            .after_sender
+            .map(|sender| (sender.into(), req.after_nonce.map(Into::into).unwrap_or_default()));
+        match uopool.get_page(after, limit) {
+            Ok((uos, next, total)) => Ok(Response::new(GetAllResponse {
+                sources: uos
+                    .iter()
+                    .map(|uo| {
+                        crate::proto::uopool::UserOperationSource::from(uopool.get_source(&uo.hash))
+                            as i32
+                    })
+                    .collect(),
+                uos: uos.into_iter().map(Into::into).collect(),
+                total: total as u64,
+                next_sender: next.map(|(sender, _)| sender.into()),
+                next_nonce: next.map(|(_, nonce)| nonce.into()),
+            })),
+            Err(err) => Err(Status::unknown(format!("Internal error: {err:?}"))),
+        }
+    }
+
+    async fn get_mempool_stats(
+        &self,
+        req: Request<GetAllRequest>,
+    ) -> Result<Response<GetMempoolStatsResponse>, Status> {
+        let req = req.into_inner();
+
+        let ep = parse_addr(req.ep)?;
+        let uopool = self.get_uopool(&ep)?;
+        match uopool.get_stats() {
+            Ok(stats) => {
+                Ok(Response::new(GetMempoolStatsResponse { rpc: stats.rpc, p2p: stats.p2p }))
             }
             Err(err) => Err(Status::unknown(format!("Internal error: {err:?}"))),
         }
     }
 
+    async fn get_chain_context(
+        &self,
+        req: Request<GetAllRequest>,
+    ) -> Result<Response<GetChainContextResponse>, Status> {
+        let req = req.into_inner();
+
+        let ep = parse_addr(req.ep)?;
+        let uopool = self.get_uopool(&ep)?;
+        let ctx = uopool.get_chain_context().await.map_err(|e| {
+            tonic::Status::internal(format!("Get chain context internal error: {e}"))
+        })?;
+        Ok(Response::new(GetChainContextResponse {
+            chain_id: ctx.chain_id,
+            block_number: ctx.block_number,
+            block_hash: Some(ctx.block_hash.into()),
+            base_fee_per_gas: Some(ctx.base_fee_per_gas.into()),
+            min_priority_fee_per_gas: Some(ctx.min_priority_fee_per_gas.into()),
+            max_verification_gas: Some(ctx.max_verification_gas.into()),
+        }))
+    }
+
     async fn clear_mempool(&self, _req: Request<()>) -> Result<Response<()>, Status> {
         self.uopools.read().values().for_each(|uopool| {
             uopool.uopool().clear_mempool();
@@ -288,6 +447,17 @@ where
         Ok(Response::new(()))
     }
 
+    async fn update_reputation(&self, _req: Request<()>) -> Result<Response<()>, Status> {
+        // the reputation is shared by the mempools of the entry points, so it's updated once
+        if let Some(uopool) = self.uopools.read().values().next() {
+            uopool
+                .uopool()
+                .update_reputation()
+                .map_err(|err| Status::internal(format!("Failed to update reputation: {err:?}")))?;
+        }
+        Ok(Response::new(()))
+    }
+
     async fn clear(&self, _req: Request<()>) -> Result<Response<()>, Status> {
         self.uopools.read().values().for_each(|uopool| {
             uopool.uopool().clear();
@@ -319,7 +489,9 @@ where
         let mut uopool = self.get_uopool(&ep)?;
 
         let res = Response::new(SetReputationResponse {
-            res: match uopool.set_reputation(req.rep.iter().map(|re| re.clone().into()).collect()) {
+            res: match uopool
+                .set_reputation(req.rep.iter().map(|re| re.clone().into()).collect(), req.overwrite)
+            {
                 Ok(_) => SetReputationResult::Set as i32,
                 Err(_) => SetReputationResult::NotSet as i32,
             },
@@ -337,11 +509,10 @@ where
         let ep = parse_addr(req.ep)?;
         let mut uopool = self.get_uopool(&ep)?;
 
+        let uos =
+            req.uos.into_iter().map(|uo| uopool.normalize_user_operation(uo.into())).collect();
         let res = Response::new(AddMempoolResponse {
-            res: match uopool
-                .add_user_operations(req.uos.into_iter().map(|uo| uo.into()).collect(), None)
-                .await
-            {
+            res: match uopool.add_user_operations(uos, None).await {
                 Ok(_) => AddMempoolResult::AddedMempool as i32,
                 Err(_) => AddMempoolResult::NotAddedMempool as i32,
             },
@@ -350,6 +521,36 @@ where
         Ok(res)
     }
 
+    async fn set_pinned(&self, req: Request<SetPinnedRequest>) -> Result<Response<()>, Status> {
+        let req = req.into_inner();
+
+        let ep = parse_addr(req.ep)?;
+        let mut uopool = self.get_uopool(&ep)?;
+
+        uopool.set_pinned_user_operations(req.hashes.into_iter().map(Into::into).collect());
+
+        Ok(Response::new(()))
+    }
+
+    async fn get_peers(&self, _req: Request<()>) -> Result<Response<GetPeersResponse>, Status> {
+        let peers = match self.network_globals {
+            Some(ref network_globals) => network_globals
+                .connected_peers_details()
+                .into_iter()
+                .map(|peer| Peer {
+                    peer_id: peer.peer_id,
+                    multiaddr: peer.multiaddr.unwrap_or_default(),
+                    supported_mempools: peer.supported_mempools,
+                    score: peer.score,
+                    direction: peer.direction.unwrap_or_default(),
+                })
+                .collect(),
+            None => vec![],
+        };
+
+        Ok(Response::new(GetPeersResponse { peers }))
+    }
+
     async fn get_stake_info(
         &self,
         req: Request<GetStakeInfoRequest>,
@@ -369,6 +570,87 @@ where
             is_staked: res.is_staked,
         }))
     }
+
+    type SubscribeUserOperationsStream =
+        Pin<Box<dyn Stream<Item = Result<NewUserOperationResponse, Status>> + Send>>;
+
+    async fn subscribe_user_operations(
+        &self,
+        _req: Request<()>,
+    ) -> Result<Response<Self::SubscribeUserOperationsStream>, Status> {
+        // the mempool is shared by all user operation pools
+        let new_uos =
+            self.uopools
+                .read()
+                .values()
+                .next()
+                .map(|b| b.uopool().mempool.subscribe())
+                .ok_or(Status::new(Code::Unavailable, "User operation pool is not available"))?;
+
+        // the receiver is dropped (and unsubscribed) together with the stream once the client
+        // disconnects
+        let stream = futures::stream::unfold(new_uos, |mut new_uos| async move {
+            loop {
+                match new_uos.recv().await {
+                    Ok(new_uo) => return Some((Ok(new_uo.into()), new_uos)),
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("Subscriber lagged behind and missed {skipped} user operations")
+                    }
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        });
+
+        Ok(Response::new(Box::pin(stream)))
+    }
+}
+
+/// Dispatches the validations of user operations received from the p2p network to a bounded pool
+/// of workers, so a burst of gossiped user operations doesn't pile up behind a single (slow)
+/// simulation. Validations of user operations with the same sender are chained: they start once
+/// the previous one finished, so their results are forwarded in the order they were received
+/// (otherwise a higher nonce could reach the mempool before the lower one).
+async fn dispatch_validations<S, V, Fut, R>(
+    mut receiver: S,
+    workers: usize,
+    validate: V,
+    results: UnboundedSender<(UserOperation, R)>,
+) where
+    S: Stream<Item = NetworkMessage> + Unpin,
+    V: Fn(UserOperation, ValidationConfig) -> Fut,
+    Fut: Future<Output = R> + Send + 'static,
+    R: Send + 'static,
+{
+    let workers = Arc::new(Semaphore::new(workers));
+    // completion of the last dispatched validation of each sender (signaled by dropping the sender
+    // half of the channel)
+    let mut pending = HashMap::<Address, oneshot::Receiver<()>>::new();
+
+    while let Some(msg) = receiver.next().await {
+        if let NetworkMessage::Validate { user_operation, validation_config } = msg {
+            pending.retain(|_, done| matches!(done.try_recv(), Err(TryRecvError::Empty)));
+
+            let (done_sender, done) = oneshot::channel::<()>();
+            let previous = pending.insert(user_operation.sender, done);
+            let validation = validate(user_operation.clone(), validation_config);
+            let workers = workers.clone();
+            let results = results.clone();
+
+            tokio::spawn(async move {
+                if let Some(previous) = previous {
+                    let _ = previous.await;
+                }
+                let permit = match workers.acquire_owned().await {
+                    Ok(permit) => permit,
+                    Err(_) => return,
+                };
+                let res = validation.await;
+                drop(permit);
+                let _ = results.unbounded_send((user_operation, res));
+                drop(done_sender);
+            });
+        }
+    }
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -376,15 +658,30 @@ pub async fn uopool_service_run<M, SanCk, SimCk, SimTrCk>(
     addr: SocketAddr,
     mode: UoPoolMode,
     eps: Vec<Address>,
+    entry_point_version: Option<EntryPointVersion>,
     eth_client: Arc<M>,
     block_streams: Vec<BlockStream>,
     chain: Chain,
     max_verification_gas: U256,
+    valid_after_policy: ValidAfterPolicy,
+    max_delayed: usize,
+    replacement_policy: ReplacementPolicy,
+    replacement_fee_increase: u64,
+    max_tracked_paymasters: usize,
+    simulate_bundle: bool,
+    max_user_operations: Option<usize>,
+    nonce_gap: Option<NonceGapConfig>,
+    verification_gas_buffer: u64,
+    chain_split_pause: Option<u64>,
+    max_dump_size: Option<usize>,
+    simulation_concurrency: usize,
+    revalidate: bool,
     mempool: Mempool,
     reputation: Reputation,
     validator: StandardUserOperationValidator<M, SanCk, SimCk, SimTrCk>,
     p2p_config: Option<Config>,
     enable_metrics: bool,
+    scan_depth: u64,
 ) -> Result<()>
 where
     M: Middleware + Clone + 'static,
@@ -398,11 +695,13 @@ where
         let mut m_map = HashMap::<MempoolId, UoPoolBuilder<M, SanCk, SimCk, SimTrCk>>::new();
 
         // setup p2p
-        if let Some(config) = p2p_config {
+        let network_globals = if let Some(config) = p2p_config {
             let mut mempool_channels: Vec<MempoolChannel> = Vec::new();
 
             for (ep, block_stream) in eps.into_iter().zip(block_streams.into_iter()) {
                 let id = mempool_id(&ep, chain.id());
+                let version =
+                    detect_entry_point_version(eth_client.clone(), ep, entry_point_version).await;
 
                 let (mempool_sender, mempool_receiver) = unbounded::<NetworkMessage>();
 
@@ -416,26 +715,87 @@ where
                     reputation.clone(),
                     validator.clone(),
                     Some(mempool_sender),
-                );
+                )
+                .with_entry_point_version(version)
+                .with_valid_after_policy(valid_after_policy)
+                .with_max_delayed(max_delayed)
+                .with_replacement_policy(replacement_policy)
+                .with_replacement_fee_increase(replacement_fee_increase)
+                .with_max_tracked_paymasters(max_tracked_paymasters)
+                .with_bundle_simulation(simulate_bundle)
+                .with_max_user_operations(max_user_operations)
+                .with_nonce_gap(nonce_gap)
+                .with_verification_gas_buffer(verification_gas_buffer)
+                .with_chain_split_pause(chain_split_pause)
+                .with_max_dump_size(max_dump_size)
+                .with_simulation_concurrency(simulation_concurrency)
+                .with_revalidation(revalidate)
+                .with_scan_depth(scan_depth);
                 uo_builder.register_block_updates(block_stream);
                 uo_builder.register_reputation_updates();
 
-                let (network_sender, mut network_receiver) = unbounded::<NetworkMessage>();
+                let (network_sender, network_receiver) = unbounded::<NetworkMessage>();
+                let (result_sender, mut result_receiver) = unbounded();
+                let uo_pool_val = uo_builder.uopool();
                 let mut uo_pool = uo_builder.uopool();
 
-                // spawn a task which would consume user operations received from p2p network
+                // spawn a task which would dispatch user operations received from p2p network to a
+                // bounded pool of validation workers
                 tokio::spawn(async move {
-                    while let Some(msg) = network_receiver.next().await {
-                        if let NetworkMessage::Validate { user_operation, validation_config } = msg
-                        {
-                            let res = uo_pool
-                                .validate_user_operation(&user_operation, Some(validation_config))
-                                .await;
-                            match uo_pool.add_user_operation(user_operation, res).await {
-                                Ok(_) => {}
-                                Err(e) => {
-                                    error!("Failed to add user operation: {:?} from p2p", e)
+                    let uo_pool_val = Arc::new(uo_pool_val);
+
+                    // user operations are hashed for the entry point before they're dispatched
+                    let normalizer = uo_pool_val.clone();
+                    let network_receiver = network_receiver.map(move |msg| match msg {
+                        NetworkMessage::Validate { user_operation, validation_config } => {
+                            NetworkMessage::Validate {
+                                user_operation: normalizer.normalize_user_operation(user_operation),
+                                validation_config,
+                            }
+                        }
+                        msg => msg,
+                    });
+
+                    dispatch_validations(
+                        network_receiver,
+                        VALIDATION_WORKERS,
+                        |user_operation, validation_config| {
+                            let uo_pool_val = uo_pool_val.clone();
+                            async move {
+                                // user operations already in the mempool aren't validated again
+                                if let Ok(true) = uo_pool_val.check_duplicate(&user_operation) {
+                                    return None;
                                 }
+
+                                Some(
+                                    uo_pool_val
+                                        .validate_user_operation(
+                                            &user_operation,
+                                            Some(validation_config),
+                                        )
+                                        .await,
+                                )
+                            }
+                        },
+                        result_sender,
+                    )
+                    .await;
+                });
+
+                // spawn a task which would add validated user operations into the mempool
+                tokio::spawn(async move {
+                    while let Some((user_operation, res)) = result_receiver.next().await {
+                        let Some(res) = res else {
+                            continue;
+                        };
+
+                        match uo_pool
+                            .add_user_operation(user_operation, res, UserOperationSource::P2p)
+                            .await
+                        {
+                            Ok(_) => {}
+                            Err(e) => {
+                                error!("Failed to add user operation: {:?} from p2p", e)
                             }
                         }
                     }
@@ -467,15 +827,20 @@ where
             )
             .await
             .expect("p2p network init failed");
+            let network_globals = p2p_network.network_globals();
 
             tokio::spawn(async move {
                 loop {
                     p2p_network.next_event().await;
                 }
             });
+
+            Some(network_globals)
         } else {
             for (ep, block_stream) in eps.into_iter().zip(block_streams.into_iter()) {
                 let id = mempool_id(&ep, chain.id());
+                let version =
+                    detect_entry_point_version(eth_client.clone(), ep, entry_point_version).await;
                 let uo_builder = UoPoolBuilder::new(
                     mode,
                     eth_client.clone(),
@@ -486,22 +851,57 @@ where
                     reputation.clone(),
                     validator.clone(),
                     None,
-                );
+                )
+                .with_entry_point_version(version)
+                .with_valid_after_policy(valid_after_policy)
+                .with_max_delayed(max_delayed)
+                .with_replacement_policy(replacement_policy)
+                .with_replacement_fee_increase(replacement_fee_increase)
+                .with_max_tracked_paymasters(max_tracked_paymasters)
+                .with_bundle_simulation(simulate_bundle)
+                .with_max_user_operations(max_user_operations)
+                .with_nonce_gap(nonce_gap)
+                .with_verification_gas_buffer(verification_gas_buffer)
+                .with_chain_split_pause(chain_split_pause)
+                .with_max_dump_size(max_dump_size)
+                .with_simulation_concurrency(simulation_concurrency)
+                .with_revalidation(revalidate)
+                .with_scan_depth(scan_depth);
                 uo_builder.register_block_updates(block_stream);
                 uo_builder.register_reputation_updates();
                 m_map.insert(id, uo_builder);
             }
+
+            None
         };
 
         let uopool_map = Arc::new(RwLock::new(m_map));
         let svc = uo_pool_server::UoPoolServer::new(
-            UoPoolService::<M, SanCk, SimCk, SimTrCk>::new(uopool_map, chain),
+            UoPoolService::<M, SanCk, SimCk, SimTrCk>::new(uopool_map, chain, network_globals),
         );
 
+        // the service is reported as serving once the execution client is reachable
+        let (health_reporter, health_svc) = tonic_health::server::health_reporter();
+        tokio::spawn(report_serving_when_connected::<
+            _,
+            uo_pool_server::UoPoolServer<UoPoolService<M, SanCk, SimCk, SimTrCk>>,
+        >(eth_client, health_reporter));
+
         if enable_metrics {
-            builder.layer(MetricsLayer).add_service(svc).serve(addr).await
+            builder
+                .layer(MetricsLayer)
+                .add_service(health_svc)
+                .add_service(reflection_service())
+                .add_service(svc)
+                .serve(addr)
+                .await
         } else {
-            builder.add_service(svc).serve(addr).await
+            builder
+                .add_service(health_svc)
+                .add_service(reflection_service())
+                .add_service(svc)
+                .serve(addr)
+                .await
         }
     });
 
@@ -509,3 +909,56 @@ where
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use silius_primitives::UserOperationSigned;
+
+    fn validate_message(sender: Address, nonce: u64) -> NetworkMessage {
+        NetworkMessage::Validate {
+            user_operation: UserOperation::from_user_operation_signed(
+                Default::default(),
+                UserOperationSigned::default().sender(sender).nonce(nonce.into()),
+            ),
+            validation_config: ValidationConfig::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn validations_of_sender_stay_ordered() {
+        let (sender_a, sender_b) = (Address::random(), Address::random());
+        let (network_sender, network_receiver) = unbounded::<NetworkMessage>();
+        let (result_sender, result_receiver) = unbounded();
+
+        for (sender, nonce) in [(sender_a, 0), (sender_b, 0), (sender_a, 1), (sender_a, 2)] {
+            network_sender.unbounded_send(validate_message(sender, nonce)).unwrap();
+        }
+        drop(network_sender);
+
+        // the first validation of each sender is the slowest one
+        dispatch_validations(
+            network_receiver,
+            VALIDATION_WORKERS,
+            |user_operation, _| async move {
+                if user_operation.nonce.is_zero() {
+                    for _ in 0..100 {
+                        tokio::task::yield_now().await;
+                    }
+                }
+            },
+            result_sender,
+        )
+        .await;
+
+        let results = result_receiver
+            .map(|(user_operation, _)| (user_operation.sender, user_operation.nonce.as_u64()))
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(results.len(), 4);
+        assert_eq!(
+            results.iter().filter(|(sender, _)| *sender == sender_a).collect::<Vec<_>>(),
+            vec![&(sender_a, 0), &(sender_a, 1), &(sender_a, 2)]
+        );
+    }
+}
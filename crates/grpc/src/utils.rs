@@ -1,6 +1,12 @@
-use ethers::types::{Address, H256};
+use ethers::{
+    providers::Middleware,
+    types::{Address, H256},
+};
+use silius_contracts::{EntryPoint, EntryPointVersion};
 use silius_primitives::UserOperation;
+use std::sync::Arc;
 use tonic::{Code, Status};
+use tracing::{info, warn};
 
 pub fn parse_addr(h: Option<crate::H160>) -> Result<Address, Status> {
     match h {
@@ -22,3 +28,27 @@ pub fn parse_uo(uo: Option<crate::UserOperation>) -> Result<UserOperation, Statu
         None => Err(Status::new(Code::InvalidArgument, "User operation is not valid")),
     }
 }
+
+/// Detects the version of the entry point by probing it, falling back to the configured version
+/// (or the one determined from the address of the entry point) if the detection fails
+pub async fn detect_entry_point_version<M: Middleware + 'static>(
+    eth_client: Arc<M>,
+    ep: Address,
+    fallback: Option<EntryPointVersion>,
+) -> EntryPointVersion {
+    let entry_point = EntryPoint::new(eth_client, ep);
+
+    match entry_point.detect_version().await {
+        Ok(version) => {
+            info!("Detected entry point {ep:?} version {version:?}");
+            version
+        }
+        Err(err) => {
+            let version = fallback.unwrap_or(entry_point.version());
+            warn!(
+                "Failed to detect entry point {ep:?} version ({err}), falling back to {version:?}"
+            );
+            version
+        }
+    }
+}
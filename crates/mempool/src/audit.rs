@@ -0,0 +1,187 @@
+//! Append-only audit log of the user operations admitted to the mempool. Each record can be
+//! signed with HMAC-SHA256, so that tampering with the log is detectable.
+
+use ethers::types::{Address, Bytes};
+use hmac::{Hmac, Mac};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use silius_primitives::{UserOperationHash, UserOperationSource};
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Record of a user operation admitted to the mempool
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditRecord {
+    /// Sequence number of the record (gaps indicate removed records)
+    pub index: u64,
+    /// Hash of the user operation
+    pub user_operation_hash: UserOperationHash,
+    /// Sender of the user operation
+    pub sender: Address,
+    /// Unix timestamp (in seconds) when the user operation was admitted
+    pub timestamp: u64,
+    /// Source from which the user operation was submitted
+    pub source: UserOperationSource,
+    /// HMAC-SHA256 of the record (without this field), if signing is enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hmac: Option<Bytes>,
+}
+
+impl AuditRecord {
+    /// Data covered by the HMAC (JSON serialization of the record without the HMAC)
+    fn signing_payload(&self) -> eyre::Result<Vec<u8>> {
+        Ok(serde_json::to_vec(&Self { hmac: None, ..self.clone() })?)
+    }
+
+    /// Signs the record with the given key
+    pub fn sign(&mut self, key: &[u8]) -> eyre::Result<()> {
+        let mut mac =
+            HmacSha256::new_from_slice(key).map_err(|e| eyre::eyre!("Invalid HMAC key: {e}"))?;
+        mac.update(&self.signing_payload()?);
+        self.hmac = Some(mac.finalize().into_bytes().to_vec().into());
+        Ok(())
+    }
+
+    /// Verifies the HMAC of the record with the given key (unsigned records don't verify)
+    pub fn verify(&self, key: &[u8]) -> bool {
+        let (Some(hmac), Ok(payload)) = (&self.hmac, self.signing_payload()) else {
+            return false;
+        };
+        let Ok(mut mac) = HmacSha256::new_from_slice(key) else {
+            return false;
+        };
+        mac.update(&payload);
+        mac.verify_slice(hmac).is_ok()
+    }
+}
+
+#[derive(Debug)]
+struct AuditLogFile {
+    file: File,
+    next_index: u64,
+}
+
+/// File sink of the audit log (one JSON record per line)
+#[derive(Clone, Debug)]
+pub struct AuditLog {
+    path: PathBuf,
+    inner: Arc<Mutex<AuditLogFile>>,
+    hmac_key: Option<Bytes>,
+}
+
+impl AuditLog {
+    /// Opens (or creates) the audit log file. New records are appended after the existing ones.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the audit log file
+    /// * `hmac_key` - The key used to sign the records (records are not signed if None)
+    pub fn open(path: PathBuf, hmac_key: Option<Bytes>) -> eyre::Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let next_index = match File::open(&path) {
+            Ok(file) => BufReader::new(file).lines().count() as u64,
+            Err(_) => 0,
+        };
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+
+        Ok(Self { path, inner: Arc::new(Mutex::new(AuditLogFile { file, next_index })), hmac_key })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends the record of an admitted user operation to the log
+    ///
+    /// # Returns
+    /// The written [AuditRecord](AuditRecord)
+    pub fn record(
+        &self,
+        uo_hash: &UserOperationHash,
+        sender: &Address,
+        source: UserOperationSource,
+    ) -> eyre::Result<AuditRecord> {
+        let mut inner = self.inner.lock();
+
+        let mut record = AuditRecord {
+            index: inner.next_index,
+            user_operation_hash: *uo_hash,
+            sender: *sender,
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+            source,
+            hmac: None,
+        };
+        if let Some(key) = &self.hmac_key {
+            record.sign(key)?;
+        }
+
+        writeln!(inner.file, "{}", serde_json::to_string(&record)?)?;
+        inner.file.flush()?;
+        inner.next_index += 1;
+
+        Ok(record)
+    }
+
+    /// Reads all records from the audit log file
+    pub fn read_records(path: &Path) -> eyre::Result<Vec<AuditRecord>> {
+        BufReader::new(File::open(path)?)
+            .lines()
+            .map(|line| Ok(serde_json::from_str(&line?)?))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::H256;
+    use tempfile::TempDir;
+
+    #[test]
+    fn audit_log_records() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("audit").join("uos.log");
+        let key = Bytes::from(b"secret".to_vec());
+
+        let log = AuditLog::open(path.clone(), Some(key.clone())).unwrap();
+        let uo_hash = UserOperationHash(H256::random());
+        let sender = Address::random();
+        log.record(&uo_hash, &sender, UserOperationSource::Rpc).unwrap();
+        log.record(&UserOperationHash(H256::random()), &sender, UserOperationSource::P2p).unwrap();
+
+        // reopened log continues the sequence
+        let log = AuditLog::open(path.clone(), Some(key.clone())).unwrap();
+        log.record(&UserOperationHash(H256::random()), &sender, UserOperationSource::Rpc).unwrap();
+
+        let records = AuditLog::read_records(&path).unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].user_operation_hash, uo_hash);
+        assert_eq!(records[0].sender, sender);
+        assert_eq!(records[1].source, UserOperationSource::P2p);
+        assert_eq!(records.iter().map(|r| r.index).collect::<Vec<_>>(), vec![0, 1, 2]);
+        assert!(records.iter().all(|r| r.verify(&key)));
+
+        // tampered records and wrong keys don't verify
+        let tampered = AuditRecord { sender: Address::random(), ..records[0].clone() };
+        assert!(!tampered.verify(&key));
+        assert!(!records[0].verify(b"other"));
+
+        // records are not signed without a key
+        let path = dir.path().join("unsigned.log");
+        let log = AuditLog::open(path.clone(), None).unwrap();
+        let record = log.record(&uo_hash, &sender, UserOperationSource::Rpc).unwrap();
+        assert!(record.hmac.is_none());
+        assert!(!record.verify(&key));
+    }
+}
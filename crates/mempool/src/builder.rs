@@ -3,7 +3,7 @@ use crate::{
         validator::StandardUserOperationValidator, SanityCheck, SimulationCheck,
         SimulationTraceCheck,
     },
-    Mempool, Reputation, UoPool,
+    HeadTracker, Mempool, OverheadConfig, Reputation, UoPool,
 };
 use alloy_chains::Chain;
 use ethers::{
@@ -12,12 +12,18 @@ use ethers::{
 };
 use futures::channel::mpsc::UnboundedSender;
 use futures_util::StreamExt;
-use silius_contracts::EntryPoint;
+use silius_contracts::{EntryPoint, EntryPointVersion};
 use silius_primitives::{
-    p2p::NetworkMessage, provider::BlockStream, UoPoolMode, UserOperation, UserOperationSigned,
+    constants::mempool::{
+        GAS_INCREASE_PERC, LATEST_SCAN_DEPTH, MAX_DELAYED_USER_OPERATIONS, MAX_TRACKED_PAYMASTERS,
+        SIMULATION_CONCURRENCY, VERIFICATION_GAS_BUFFER_PERC,
+    },
+    p2p::NetworkMessage,
+    provider::BlockStream,
+    NonceGapConfig, ReplacementPolicy, UoPoolMode, UserOperation, ValidAfterPolicy,
 };
 use std::{sync::Arc, time::Duration};
-use tracing::warn;
+use tracing::{info, warn};
 
 type StandardUoPool<M, SanCk, SimCk, SimTrCk> =
     UoPool<M, StandardUserOperationValidator<M, SanCk, SimCk, SimTrCk>>;
@@ -32,6 +38,8 @@ where
     mode: UoPoolMode,
     eth_client: Arc<M>,
     entrypoint: Address,
+    // Version of the entry point (hashing, packing and decoding of user operations)
+    entry_point_version: EntryPointVersion,
     chain: Chain,
     max_verification_gas: U256,
     mempool: Mempool,
@@ -39,6 +47,36 @@ where
     validator: StandardUserOperationValidator<M, SanCk, SimCk, SimTrCk>,
     // Channel to publish to p2p network (None if not enabled)
     network: Option<UnboundedSender<NetworkMessage>>,
+    // Handling of user operations which aren't valid yet
+    valid_after_policy: ValidAfterPolicy,
+    // Maximum number of user operations held until their validAfter passes
+    max_delayed: usize,
+    // Maximum number of paymaster deposits tracked while building a bundle
+    max_tracked_paymasters: usize,
+    // Whether the bundle is simulated as a whole before it's returned
+    simulate_bundle: bool,
+    // Maximum number of user operations in the mempool (unbounded if None)
+    max_user_operations: Option<usize>,
+    // Minimum fee increase (percentage) of a replacement user operation
+    replacement_fee_increase: u64,
+    // Precedence between the sources of replacement user operations
+    replacement_policy: ReplacementPolicy,
+    // Holding of user operations with a nonce gap (None if not enabled)
+    nonce_gap: Option<NonceGapConfig>,
+    // Percentage added to the estimated verification gas limit
+    verification_gas_buffer: u64,
+    // Configuration of the pre-verification gas calculation
+    overhead: OverheadConfig<M>,
+    // Number of blocks scanned at once for past entry point events
+    scan_depth: u64,
+    // Tracker of the recent chain heads (shared by the uopools built)
+    head_tracker: HeadTracker,
+    // Maximum number of user operations returned by a single dump (unlimited if None)
+    max_dump_size: Option<usize>,
+    // Number of user operations simulated concurrently while building a bundle
+    simulation_concurrency: usize,
+    // Whether the user operations in the mempool are re-validated on every new block
+    revalidate: bool,
 }
 
 impl<M, SanCk, SimCk, SimTrCk> UoPoolBuilder<M, SanCk, SimCk, SimTrCk>
@@ -64,36 +102,204 @@ where
             mode,
             eth_client,
             entrypoint,
+            entry_point_version: EntryPointVersion::from_address(&entrypoint),
             chain,
             max_verification_gas,
             mempool,
             reputation,
             validator,
             network,
+            valid_after_policy: ValidAfterPolicy::default(),
+            max_delayed: MAX_DELAYED_USER_OPERATIONS,
+            max_tracked_paymasters: MAX_TRACKED_PAYMASTERS,
+            simulate_bundle: false,
+            max_user_operations: None,
+            replacement_fee_increase: GAS_INCREASE_PERC,
+            replacement_policy: ReplacementPolicy::default(),
+            nonce_gap: None,
+            verification_gas_buffer: VERIFICATION_GAS_BUFFER_PERC,
+            overhead: OverheadConfig::for_chain(&chain),
+            scan_depth: LATEST_SCAN_DEPTH,
+            head_tracker: HeadTracker::default(),
+            max_dump_size: None,
+            simulation_concurrency: SIMULATION_CONCURRENCY,
+            revalidate: false,
         }
     }
 
+    /// Sets the version of the entry point (otherwise determined from its address)
+    pub fn with_entry_point_version(mut self, entry_point_version: EntryPointVersion) -> Self {
+        self.entry_point_version = entry_point_version;
+        self
+    }
+
+    /// Sets the handling of user operations which aren't valid yet (`validAfter` in the future)
+    pub fn with_valid_after_policy(mut self, valid_after_policy: ValidAfterPolicy) -> Self {
+        self.valid_after_policy = valid_after_policy;
+        self
+    }
+
+    /// Sets the maximum number of user operations held until their `validAfter` passes
+    pub fn with_max_delayed(mut self, max_delayed: usize) -> Self {
+        self.max_delayed = max_delayed;
+        self
+    }
+
+    /// Sets the maximum number of paymaster deposits tracked while building a bundle
+    pub fn with_max_tracked_paymasters(mut self, max_tracked_paymasters: usize) -> Self {
+        self.max_tracked_paymasters = max_tracked_paymasters;
+        self
+    }
+
+    /// Enables the simulation of the whole bundle (`handleOps` call) as the final step of
+    /// bundling
+    pub fn with_bundle_simulation(mut self, simulate_bundle: bool) -> Self {
+        self.simulate_bundle = simulate_bundle;
+        self
+    }
+
+    /// Sets the maximum number of user operations in the mempool (the ones with the lowest
+    /// `max_priority_fee_per_gas` are evicted once it's full)
+    pub fn with_max_user_operations(mut self, max_user_operations: Option<usize>) -> Self {
+        self.max_user_operations = max_user_operations;
+        self
+    }
+
+    /// Sets the minimum increase (percentage) of both `max_fee_per_gas` and
+    /// `max_priority_fee_per_gas` required to replace a user operation with the same sender and
+    /// nonce
+    pub fn with_replacement_fee_increase(mut self, replacement_fee_increase: u64) -> Self {
+        self.replacement_fee_increase = replacement_fee_increase;
+        self
+    }
+
+    /// Sets the precedence between the sources of user operations replacing each other
+    pub fn with_replacement_policy(mut self, replacement_policy: ReplacementPolicy) -> Self {
+        self.replacement_policy = replacement_policy;
+        self
+    }
+
+    /// Enables holding of user operations whose nonce is ahead of the next one expected for the
+    /// sender until the nonce gap is filled
+    pub fn with_nonce_gap(mut self, nonce_gap: Option<NonceGapConfig>) -> Self {
+        self.nonce_gap = nonce_gap;
+        self
+    }
+
+    /// Sets the percentage added to the estimated verification gas limit of user operations
+    pub fn with_verification_gas_buffer(mut self, verification_gas_buffer: u64) -> Self {
+        self.verification_gas_buffer = verification_gas_buffer;
+        self
+    }
+
+    /// Sets the configuration of the pre-verification gas calculation, used both by the gas
+    /// estimation and the validation of user operations (mainnet constants, plus the L1 data
+    /// posting cost on OP Stack chains, if not set)
+    pub fn with_overhead(mut self, overhead: OverheadConfig<M>) -> Self {
+        self.validator = self.validator.with_overhead(overhead.clone());
+        self.overhead = overhead;
+        self
+    }
+
+    /// Sets the number of blocks scanned at once for past entry point events
+    pub fn with_scan_depth(mut self, scan_depth: u64) -> Self {
+        self.scan_depth = scan_depth;
+        self
+    }
+
+    /// Sets the number of blocks without a chain split (same height, different hash) before
+    /// bundling resumes after one. Bundling isn't paused if not set.
+    pub fn with_chain_split_pause(mut self, pause: Option<u64>) -> Self {
+        self.head_tracker = HeadTracker::new(pause);
+        self
+    }
+
+    /// Sets the maximum number of user operations returned by a single mempool dump (unlimited if
+    /// None)
+    pub fn with_max_dump_size(mut self, max_dump_size: Option<usize>) -> Self {
+        self.max_dump_size = max_dump_size;
+        self
+    }
+
+    /// Sets the number of user operations simulated concurrently while building a bundle
+    pub fn with_simulation_concurrency(mut self, simulation_concurrency: usize) -> Self {
+        self.simulation_concurrency = simulation_concurrency;
+        self
+    }
+
+    /// Sets whether the user operations in the mempool are re-validated on every new block
+    pub fn with_revalidation(mut self, revalidate: bool) -> Self {
+        self.revalidate = revalidate;
+        self
+    }
+
+    fn read_checkpoint(uopool: &StandardUoPool<M, SanCk, SimCk, SimTrCk>) -> Option<u64> {
+        uopool.mempool.get_checkpoint(&uopool.entry_point.address()).unwrap_or_else(|e| {
+            warn!("Failed to read the last processed block: {:?}", e);
+            None
+        })
+    }
+
+    fn write_checkpoint(uopool: &mut StandardUoPool<M, SanCk, SimCk, SimTrCk>, block: u64) {
+        let entry_point = uopool.entry_point.address();
+        let _ = uopool
+            .mempool
+            .set_checkpoint(&entry_point, block)
+            .map_err(|e| warn!("Failed to persist the last processed block: {:?}", e));
+    }
+
     async fn handle_block_update(
         hash: H256,
         uopool: &mut StandardUoPool<M, SanCk, SimCk, SimTrCk>,
-    ) -> eyre::Result<()> {
-        let txs =
-            uopool.entry_point.eth_client().get_block_with_txs(hash).await?.map(|b| b.transactions);
+    ) -> eyre::Result<Option<u64>> {
+        let block = uopool.entry_point.eth_client().get_block_with_txs(hash).await?;
+        let block_number = block.as_ref().and_then(|b| b.number).map(|n| n.as_u64());
+
+        if let (Some(number), Some(block)) = (block_number, &block) {
+            // user operations included in reorged blocks are re-admitted (before the new head is
+            // recorded, as it might replace one of them)
+            match uopool.handle_reorg(number, hash, block.parent_hash).await {
+                Ok(0) => {}
+                Ok(readmitted) => {
+                    info!("Re-admitted {readmitted} user operations after a chain reorg")
+                }
+                Err(e) => warn!("Failed to handle chain reorg: {:?}", e),
+            }
 
-        if let Some(txs) = txs {
+            // a different block at an already seen height pauses bundling (if enabled)
+            uopool.head_tracker.observe(number, hash);
+        }
+
+        // user operations which became valid in this block can be bundled from now on
+        if let Some(ref block) = block {
+            uopool.promote_delayed_user_operations(block.timestamp).await;
+        }
+
+        // user operations whose nonce gap wasn't filled in time are dropped
+        uopool.remove_expired_gapped_user_operations();
+
+        if let Some(txs) = block.map(|b| b.transactions) {
             for tx in txs {
                 if tx.to == Some(uopool.entry_point.address()) {
-                    let dec: Result<(Vec<UserOperationSigned>, Address), _> =
-                        uopool.entry_point.entry_point_api().decode("handleOps", tx.input);
-
-                    if let Ok((uos, _)) = dec {
-                        uopool.remove_user_operations(
-                            uos.iter()
-                                .map(|uo| {
-                                    UserOperation::from_user_operation_signed(
-                                        uo.hash(&uopool.entry_point.address(), uopool.chain.id()),
-                                        uo.clone(),
-                                    )
+                    let version = uopool.entry_point.version();
+
+                    if let Some(uos) = version.decode_handle_ops(&tx.input) {
+                        uopool.remove_included_user_operations(
+                            block_number.unwrap_or_default(),
+                            // the decoded user operations were packed by the call, so they can
+                            // always be hashed
+                            uos.into_iter()
+                                .filter_map(|uo| {
+                                    version
+                                        .user_operation_hash(
+                                            &uo,
+                                            &uopool.entry_point.address(),
+                                            uopool.chain.id(),
+                                        )
+                                        .ok()
+                                        .map(|hash| {
+                                            UserOperation::from_user_operation_signed(hash, uo)
+                                        })
                                 })
                                 .collect(),
                         );
@@ -102,19 +308,43 @@ where
             }
         }
 
-        Ok(())
+        Ok(block_number)
     }
 
     pub fn register_block_updates(&self, mut block_stream: BlockStream) {
         let mut uopool = self.uopool();
         let network = self.network.clone();
+        let scan_depth = self.scan_depth;
+        let revalidate = self.revalidate;
         tokio::spawn(async move {
+            // catch up with the blocks produced since the last processed block
+            let last_block = Self::read_checkpoint(&uopool);
+            match uopool.handle_past_events(last_block, scan_depth).await {
+                Ok(block) => Self::write_checkpoint(&mut uopool, block),
+                Err(e) => warn!("Failed to handle past events: {:?}", e),
+            }
+
             while let Some(hash) = block_stream.next().await {
                 if let Ok(hash) = hash {
                     let h: H256 = hash;
-                    let _ = Self::handle_block_update(h, &mut uopool)
-                        .await
-                        .map_err(|e| warn!("Failed to handle block update: {:?}", e));
+                    match Self::handle_block_update(h, &mut uopool).await {
+                        Ok(Some(block)) => Self::write_checkpoint(&mut uopool, block),
+                        Ok(None) => {}
+                        Err(e) => warn!("Failed to handle block update: {:?}", e),
+                    }
+
+                    // user operations accepted against an older state are checked against the new
+                    // one
+                    if revalidate {
+                        match uopool.revalidate_user_operations().await {
+                            Ok(removed) if !removed.is_empty() => info!(
+                                "Removed {} user operations after re-validation",
+                                removed.len()
+                            ),
+                            Ok(_) => {}
+                            Err(e) => warn!("Failed to re-validate user operations: {:?}", e),
+                        }
+                    }
 
                     // update p2p latest block info
                     if let Some(ref network) = network {
@@ -142,8 +372,7 @@ where
         tokio::spawn(async move {
             loop {
                 let _ = uopool
-                    .reputation
-                    .update_hourly()
+                    .update_reputation()
                     .map_err(|e| warn!("Failed to update hourly reputation: {:?}", e));
                 tokio::time::sleep(Duration::from_secs(60 * 60)).await;
             }
@@ -151,17 +380,35 @@ where
     }
 
     pub fn uopool(&self) -> StandardUoPool<M, SanCk, SimCk, SimTrCk> {
-        let entry_point = EntryPoint::<M>::new(self.eth_client.clone(), self.entrypoint);
+        // user operations are simulated against the entry point of the mempool (in its version)
+        let validator = self.validator.clone().with_entry_point(
+            EntryPoint::<M>::new(self.eth_client.clone(), self.entrypoint)
+                .with_version(self.entry_point_version),
+        );
+        let entry_point = validator.entry_point().clone();
 
         UoPool::<M, StandardUserOperationValidator<M, SanCk, SimCk, SimTrCk>>::new(
             self.mode,
             entry_point,
-            self.validator.clone(),
+            validator,
             self.mempool.clone(),
             self.reputation.clone(),
             self.max_verification_gas,
             self.chain,
             self.network.as_ref().cloned(),
         )
+        .with_valid_after_policy(self.valid_after_policy)
+        .with_max_delayed(self.max_delayed)
+        .with_max_tracked_paymasters(self.max_tracked_paymasters)
+        .with_bundle_simulation(self.simulate_bundle)
+        .with_max_user_operations(self.max_user_operations)
+        .with_replacement_fee_increase(self.replacement_fee_increase)
+        .with_replacement_policy(self.replacement_policy)
+        .with_nonce_gap(self.nonce_gap)
+        .with_verification_gas_buffer(self.verification_gas_buffer)
+        .with_overhead(self.overhead.clone())
+        .with_head_tracker(self.head_tracker.clone())
+        .with_max_dump_size(self.max_dump_size)
+        .with_simulation_concurrency(self.simulation_concurrency)
     }
 }
@@ -8,8 +8,12 @@ use reth_db::{
     },
     Error as RethDatabaseError, TableType,
 };
+use reth_libmdbx::Error as MdbxError;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use std::{fs, path::PathBuf};
+use silius_primitives::constants::storage::{
+    DATABASE_GROWTH_STEP, DATABASE_INITIAL_SIZE, DATABASE_MAX_SIZE,
+};
+use std::{fs, ops::Range, path::PathBuf};
 use thiserror::Error;
 
 // Code adapted from: https://github.com/paradigmxyz/reth/blob/main/crates/storage/db/src/implementation/mdbx/mod.rs
@@ -38,6 +42,64 @@ impl<E: EnvironmentKind> Database for Env<E> {
     }
 }
 
+/// Database geometry arguments
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DatabaseArguments {
+    /// Initial (minimum) size of the database (in bytes)
+    pub initial_size: usize,
+    /// Maximum size of the database (in bytes)
+    pub max_size: usize,
+    /// Step by which the database grows when it's full (in bytes)
+    pub growth_step: usize,
+}
+
+impl Default for DatabaseArguments {
+    fn default() -> Self {
+        Self {
+            initial_size: DATABASE_INITIAL_SIZE,
+            max_size: DATABASE_MAX_SIZE,
+            growth_step: DATABASE_GROWTH_STEP,
+        }
+    }
+}
+
+impl DatabaseArguments {
+    /// Validates the database geometry
+    pub fn validate(&self) -> eyre::Result<()> {
+        if self.max_size == 0 {
+            eyre::bail!("Database max size must be greater than 0");
+        }
+        if self.initial_size > self.max_size {
+            eyre::bail!(
+                "Database initial size {} is greater than max size {}",
+                self.initial_size,
+                self.max_size
+            );
+        }
+        if self.growth_step == 0 || self.growth_step > self.max_size {
+            eyre::bail!(
+                "Database growth step {} must be greater than 0 and at most max size {}",
+                self.growth_step,
+                self.max_size
+            );
+        }
+        if isize::try_from(self.growth_step).is_err() {
+            eyre::bail!("Database growth step {} is too large", self.growth_step);
+        }
+        Ok(())
+    }
+
+    /// Returns the mdbx geometry
+    pub fn geometry(&self) -> Geometry<Range<usize>> {
+        Geometry {
+            size: Some(self.initial_size..self.max_size),
+            growth_step: Some(self.growth_step as isize),
+            shrink_threshold: None,
+            page_size: Some(PageSize::Set(default_page_size())),
+        }
+    }
+}
+
 /// Database error
 #[derive(Debug, Error)]
 pub enum DatabaseError {
@@ -47,11 +109,21 @@ pub enum DatabaseError {
     /// Databse not found
     #[error("Database not found")]
     NotFound,
+    /// Database reached its maximum size
+    #[error("Database map is full (increase the maximum database size and restart)")]
+    MapFull,
 }
 
 impl From<RethDatabaseError> for DatabaseError {
     fn from(value: RethDatabaseError) -> Self {
-        DatabaseError::Internal(value)
+        match value {
+            RethDatabaseError::Write(code) | RethDatabaseError::Commit(code)
+                if MdbxError::from_err_code(code) == MdbxError::MapFull =>
+            {
+                DatabaseError::MapFull
+            }
+            _ => DatabaseError::Internal(value),
+        }
     }
 }
 
@@ -83,17 +155,21 @@ fn default_page_size() -> usize {
 
 impl<E: EnvironmentKind> Env<E> {
     /// Sets up the database environment
-    pub fn open(path: PathBuf) -> eyre::Result<Self> {
+    pub fn open(path: PathBuf, args: DatabaseArguments) -> eyre::Result<Self> {
+        args.validate()?;
+        Self::open_with_geometry(path, args.geometry())
+    }
+
+    /// Sets up the database environment with the given geometry
+    pub fn open_with_geometry(
+        path: PathBuf,
+        geometry: Geometry<Range<usize>>,
+    ) -> eyre::Result<Self> {
         fs::create_dir_all(&path)?;
 
         let env = Environment::new()
             .set_max_dbs(TABLES.len())
-            .set_geometry(Geometry {
-                size: Some(0..(1024 * 1024 * 1024 * 1024 * 4)), // TODO: reevaluate (4 tb)
-                growth_step: Some(1024 * 1024 * 256),           // TODO: reevaluate (256 mb)
-                shrink_threshold: None,
-                page_size: Some(PageSize::Set(default_page_size())),
-            })
+            .set_geometry(geometry)
             .set_flags(EnvironmentFlags {
                 mode: Mode::ReadWrite { sync_mode: SyncMode::Durable },
                 no_rdahead: true, // TODO: reevaluate
@@ -1,16 +1,20 @@
 use super::{
     env::DatabaseError,
-    tables::{CodeHashes, UserOperations, UserOperationsByEntity, UserOperationsBySender},
+    tables::{
+        Checkpoints, CodeHashes, UserOperations, UserOperationsByEntity,
+        UserOperationsByEntryPoint, UserOperationsBySender, UserOperationsMetadata,
+    },
     utils::{
-        WrapAddress, WrapCodeHash, WrapCodeHashVec, WrapUserOpSet, WrapUserOperationHash,
-        WrapUserOperationSigned,
+        WrapAddress, WrapBlockNumber, WrapCodeHash, WrapCodeHashVec, WrapUserOpSet,
+        WrapUserOperationHash, WrapUserOperationMetadata, WrapUserOperationSigned,
     },
     DatabaseTable,
 };
 use crate::{
     mempool::{
-        AddRemoveUserOp, AddRemoveUserOpHash, ClearOp, UserOperationAddrOp,
-        UserOperationCodeHashOp, UserOperationOp,
+        AddRemoveUserOp, AddRemoveUserOpHash, CheckpointOp, ClearOp, RemoveBatchOp,
+        UserOperationAddrOp, UserOperationCodeHashOp, UserOperationMetadata,
+        UserOperationMetadataOp, UserOperationOp,
     },
     MempoolErrorKind,
 };
@@ -42,6 +46,38 @@ impl<E: EnvironmentKind> AddRemoveUserOp for DatabaseTable<E, UserOperations> {
         tx.commit()?;
         Ok(original_value.is_some())
     }
+
+    fn remove_by_uo_hashes(
+        &mut self,
+        uo_hashes: &[UserOperationHash],
+    ) -> Result<usize, MempoolErrorKind> {
+        let tx = self.env.tx_mut()?;
+        let mut removed = 0;
+        for uo_hash in uo_hashes {
+            let uo_hash_wrap: WrapUserOperationHash = (*uo_hash).into();
+            if tx.delete::<UserOperations>(uo_hash_wrap, None)? {
+                removed += 1;
+            }
+        }
+        tx.commit()?;
+        Ok(removed)
+    }
+}
+
+// Removes the (address, user operation hash) entries from the table within the transaction
+macro_rules! remove_uo_hashes {
+    ($tx: ident, $table: ident, $entries: ident) => {
+        for (address, uo_hash) in $entries {
+            if let Some(mut uo_hash_set) = $tx.get::<$table>(address.clone().into())? {
+                uo_hash_set.remove(&uo_hash.clone().into());
+                if uo_hash_set.is_empty() {
+                    $tx.delete::<$table>(address.clone().into(), None)?;
+                } else {
+                    $tx.put::<$table>(address.clone().into(), uo_hash_set)?;
+                }
+            }
+        }
+    };
 }
 
 macro_rules! impl_add_remove_user_op_hash {
@@ -72,14 +108,12 @@ macro_rules! impl_add_remove_user_op_hash {
                 uo_hash: &UserOperationHash,
             ) -> Result<bool, MempoolErrorKind> {
                 let tx = self.env.tx_mut()?;
-                if let Some(mut uo_hash_set) =
-                    tx.get::<UserOperationsBySender>(address.clone().into())?
-                {
+                if let Some(mut uo_hash_set) = tx.get::<$table>(address.clone().into())? {
                     uo_hash_set.remove(&uo_hash.clone().into());
                     if uo_hash_set.is_empty() {
-                        tx.delete::<UserOperationsBySender>(address.clone().into(), None)?;
+                        tx.delete::<$table>(address.clone().into(), None)?;
                     } else {
-                        tx.put::<UserOperationsBySender>(address.clone().into(), uo_hash_set)?;
+                        tx.put::<$table>(address.clone().into(), uo_hash_set)?;
                     }
                     tx.commit()?;
                     Ok(true)
@@ -87,12 +121,47 @@ macro_rules! impl_add_remove_user_op_hash {
                     Ok(false)
                 }
             }
+
+            fn remove_uo_hashes(
+                &mut self,
+                entries: &[(Address, UserOperationHash)],
+            ) -> Result<(), MempoolErrorKind> {
+                let tx = self.env.tx_mut()?;
+                remove_uo_hashes!(tx, $table, entries);
+                tx.commit()?;
+                Ok(())
+            }
         }
     };
 }
 
 impl_add_remove_user_op_hash!(UserOperationsBySender);
 impl_add_remove_user_op_hash!(UserOperationsByEntity);
+impl_add_remove_user_op_hash!(UserOperationsByEntryPoint);
+
+/// The user operations table removes the related entries of the other tables in the same write
+/// transaction
+impl<E: EnvironmentKind> RemoveBatchOp for DatabaseTable<E, UserOperations> {
+    fn remove_batch(
+        &mut self,
+        uo_hashes: &[UserOperationHash],
+        by_sender: &[(Address, UserOperationHash)],
+        by_entity: &[(Address, UserOperationHash)],
+        by_entry_point: &[(Address, UserOperationHash)],
+    ) -> Result<(), MempoolErrorKind> {
+        let tx = self.env.tx_mut()?;
+        for uo_hash in uo_hashes {
+            let uo_hash_wrap: WrapUserOperationHash = (*uo_hash).into();
+            tx.delete::<UserOperations>(uo_hash_wrap.clone(), None)?;
+            tx.delete::<UserOperationsMetadata>(uo_hash_wrap, None)?;
+        }
+        remove_uo_hashes!(tx, UserOperationsBySender, by_sender);
+        remove_uo_hashes!(tx, UserOperationsByEntity, by_entity);
+        remove_uo_hashes!(tx, UserOperationsByEntryPoint, by_entry_point);
+        tx.commit()?;
+        Ok(())
+    }
+}
 
 impl<E: EnvironmentKind> UserOperationOp for DatabaseTable<E, UserOperations> {
     fn get_by_uo_hash(
@@ -160,11 +229,25 @@ macro_rules! impl_user_op_addr_op {
                     })
                     .unwrap_or_else(|_| vec![])
             }
+
+            fn get_all_entries(
+                &self,
+            ) -> Result<Vec<(Address, Vec<UserOperationHash>)>, MempoolErrorKind> {
+                let tx = self.env.tx()?;
+                let mut c = tx.cursor_read::<$table>()?;
+                let res = c
+                    .walk(Some(WrapAddress::default()))?
+                    .map(|a| a.map(|(addr, uo_hash_set)| (addr.into(), uo_hash_set.to_vec())))
+                    .collect::<Result<Vec<_>, _>>()?;
+                tx.commit()?;
+                Ok(res)
+            }
         }
     };
 }
 impl_user_op_addr_op!(UserOperationsBySender);
 impl_user_op_addr_op!(UserOperationsByEntity);
+impl_user_op_addr_op!(UserOperationsByEntryPoint);
 
 impl<E: EnvironmentKind> UserOperationCodeHashOp for DatabaseTable<E, CodeHashes> {
     fn has_code_hashes(&self, uo_hash: &UserOperationHash) -> Result<bool, MempoolErrorKind> {
@@ -218,6 +301,96 @@ impl<E: EnvironmentKind> UserOperationCodeHashOp for DatabaseTable<E, CodeHashes
             Ok(false)
         }
     }
+
+    fn remove_code_hashes_batch(
+        &mut self,
+        uo_hashes: &[UserOperationHash],
+    ) -> Result<(), MempoolErrorKind> {
+        let tx = self.env.tx_mut()?;
+        for uo_hash in uo_hashes {
+            let uo_hash_wrap: WrapUserOperationHash = (*uo_hash).into();
+            tx.delete::<CodeHashes>(uo_hash_wrap, None)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+impl<E: EnvironmentKind> UserOperationMetadataOp for DatabaseTable<E, UserOperationsMetadata> {
+    fn get_metadata(
+        &self,
+        uo_hash: &UserOperationHash,
+    ) -> Result<Option<UserOperationMetadata>, MempoolErrorKind> {
+        let uo_hash_wrap: WrapUserOperationHash = (*uo_hash).into();
+
+        let tx = self.env.tx()?;
+        let res = tx.get::<UserOperationsMetadata>(uo_hash_wrap)?;
+        tx.commit()?;
+
+        Ok(res.map(Into::into))
+    }
+
+    fn set_metadata(
+        &mut self,
+        uo_hash: &UserOperationHash,
+        metadata: UserOperationMetadata,
+    ) -> Result<(), MempoolErrorKind> {
+        let uo_hash_wrap: WrapUserOperationHash = (*uo_hash).into();
+        let metadata_wrap: WrapUserOperationMetadata = metadata.into();
+
+        let tx = self.env.tx_mut()?;
+        tx.put::<UserOperationsMetadata>(uo_hash_wrap, metadata_wrap)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn remove_metadata(&mut self, uo_hash: &UserOperationHash) -> Result<bool, MempoolErrorKind> {
+        let uo_hash_wrap: WrapUserOperationHash = (*uo_hash).into();
+
+        let tx = self.env.tx_mut()?;
+        let removed = tx.delete::<UserOperationsMetadata>(uo_hash_wrap, None)?;
+        tx.commit()?;
+        Ok(removed)
+    }
+
+    fn remove_metadata_batch(
+        &mut self,
+        uo_hashes: &[UserOperationHash],
+    ) -> Result<(), MempoolErrorKind> {
+        let tx = self.env.tx_mut()?;
+        for uo_hash in uo_hashes {
+            let uo_hash_wrap: WrapUserOperationHash = (*uo_hash).into();
+            tx.delete::<UserOperationsMetadata>(uo_hash_wrap, None)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+impl<E: EnvironmentKind> CheckpointOp for DatabaseTable<E, Checkpoints> {
+    fn get_checkpoint(&self, entry_point: &Address) -> Result<Option<u64>, MempoolErrorKind> {
+        let entry_point_wrap: WrapAddress = (*entry_point).into();
+
+        let tx = self.env.tx()?;
+        let res = tx.get::<Checkpoints>(entry_point_wrap)?;
+        tx.commit()?;
+
+        Ok(res.map(Into::into))
+    }
+
+    fn set_checkpoint(
+        &mut self,
+        entry_point: &Address,
+        block: u64,
+    ) -> Result<(), MempoolErrorKind> {
+        let entry_point_wrap: WrapAddress = (*entry_point).into();
+        let block_wrap: WrapBlockNumber = block.into();
+
+        let tx = self.env.tx_mut()?;
+        tx.put::<Checkpoints>(entry_point_wrap, block_wrap)?;
+        tx.commit()?;
+        Ok(())
+    }
 }
 
 macro_rules! impl_clear {
@@ -238,21 +411,42 @@ macro_rules! impl_clear {
 impl_clear!(UserOperations);
 impl_clear!(UserOperationsBySender);
 impl_clear!(UserOperationsByEntity);
+impl_clear!(UserOperationsByEntryPoint);
 impl_clear!(CodeHashes);
+impl_clear!(UserOperationsMetadata);
 
 #[cfg(test)]
 mod tests {
     use crate::{
         database::{
             init_env,
-            tables::{CodeHashes, UserOperations, UserOperationsByEntity, UserOperationsBySender},
-            DatabaseTable,
+            tables::{
+                Checkpoints, CodeHashes, UserOperations, UserOperationsByEntity,
+                UserOperationsByEntryPoint, UserOperationsBySender, UserOperationsMetadata,
+            },
+            DatabaseArguments, DatabaseTable,
+        },
+        mempool::{
+            AddRemoveUserOp, AddRemoveUserOpHash, UserOperationAddrOp, UserOperationCodeHashOp,
+            UserOperationMetadataOp,
         },
-        utils::tests::mempool_test_case,
-        Mempool,
+        test_utils::UoPoolTestBuilder,
+        utils::tests::{mempool_eviction_test_case, mempool_test_case},
+        validate::UserOperationValidationOutcome,
+        DatabaseError, Mempool, MempoolErrorKind,
+    };
+    use ethers::{
+        providers::Provider,
+        types::{Address, H256},
     };
+    use parking_lot::RwLock;
     use reth_libmdbx::WriteMap;
-    use std::sync::Arc;
+    use silius_contracts::EntryPoint;
+    use silius_primitives::{
+        simulation::CodeHash, MempoolStats, UserOperation, UserOperationHash, UserOperationSigned,
+        UserOperationSource,
+    };
+    use std::{collections::HashMap, sync::Arc};
     use tempfile::TempDir;
 
     #[allow(clippy::unit_cmp)]
@@ -260,7 +454,35 @@ mod tests {
     async fn database_mempool() {
         let dir = TempDir::new().unwrap();
 
-        let env = init_env::<WriteMap>(dir.into_path()).unwrap();
+        let env = init_env::<WriteMap>(dir.into_path(), DatabaseArguments::default()).unwrap();
+        env.create_tables().expect("Create mdbx database tables failed");
+        let env = Arc::new(env);
+        let uo_ops: DatabaseTable<WriteMap, UserOperations> = DatabaseTable::new(env.clone());
+        let uo_ops_sender: DatabaseTable<WriteMap, UserOperationsBySender> =
+            DatabaseTable::new(env.clone());
+        let uo_ops_entity: DatabaseTable<WriteMap, UserOperationsByEntity> =
+            DatabaseTable::new(env.clone());
+        let uo_ops_codehashes: DatabaseTable<WriteMap, CodeHashes> =
+            DatabaseTable::new(env.clone());
+        let mempool = Mempool::new(
+            Box::new(uo_ops.clone()),
+            Box::new(uo_ops_sender),
+            Box::new(uo_ops_entity),
+            Box::new(uo_ops_codehashes),
+        )
+        .with_metadata(Box::new(DatabaseTable::<WriteMap, UserOperationsMetadata>::new(
+            env.clone(),
+        )))
+        .with_batch_remover(Box::new(uo_ops));
+
+        mempool_test_case(mempool);
+    }
+
+    #[tokio::test]
+    async fn database_mempool_eviction() {
+        let dir = TempDir::new().unwrap();
+
+        let env = init_env::<WriteMap>(dir.into_path(), DatabaseArguments::default()).unwrap();
         env.create_tables().expect("Create mdbx database tables failed");
         let env = Arc::new(env);
         let uo_ops: DatabaseTable<WriteMap, UserOperations> = DatabaseTable::new(env.clone());
@@ -277,6 +499,311 @@ mod tests {
             Box::new(uo_ops_codehashes),
         );
 
-        mempool_test_case(mempool);
+        mempool_eviction_test_case(mempool).await;
+    }
+
+    #[tokio::test]
+    async fn database_mempool_memory_code_hashes() {
+        let dir = TempDir::new().unwrap();
+
+        let env = init_env::<WriteMap>(dir.into_path(), DatabaseArguments::default()).unwrap();
+        env.create_tables().expect("Create mdbx database tables failed");
+        let env = Arc::new(env);
+        let mempool = Mempool::new(
+            Box::new(DatabaseTable::<WriteMap, UserOperations>::new(env.clone())),
+            Box::new(DatabaseTable::<WriteMap, UserOperationsBySender>::new(env.clone())),
+            Box::new(DatabaseTable::<WriteMap, UserOperationsByEntity>::new(env.clone())),
+            Box::new(Arc::new(RwLock::new(HashMap::<UserOperationHash, Vec<CodeHash>>::default()))),
+        );
+
+        mempool_test_case(mempool.clone());
+
+        // code hashes aren't written to the database
+        let uo = UserOperation::from_user_operation_signed(
+            UserOperationHash(H256::random()),
+            UserOperationSigned::random(),
+        );
+        let mut mempool = mempool;
+        let uo_hash = mempool.add(uo).unwrap();
+        let code_hashes = vec![CodeHash { address: Address::random(), hash: H256::random() }];
+        mempool.set_code_hashes(&uo_hash, code_hashes.clone()).unwrap();
+        assert_eq!(mempool.get_code_hashes(&uo_hash).unwrap(), code_hashes);
+        assert!(!DatabaseTable::<WriteMap, CodeHashes>::new(env)
+            .has_code_hashes(&uo_hash)
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn database_mempool_consistency() {
+        let dir = TempDir::new().unwrap();
+
+        let env = Arc::new(init_env::<WriteMap>(dir.into_path(), Default::default()).unwrap());
+        let mut uo_ops_sender: DatabaseTable<WriteMap, UserOperationsBySender> =
+            DatabaseTable::new(env.clone());
+        let mut uo_ops_entity: DatabaseTable<WriteMap, UserOperationsByEntity> =
+            DatabaseTable::new(env.clone());
+        let mut mempool = Mempool::new(
+            Box::new(DatabaseTable::<WriteMap, UserOperations>::new(env.clone())),
+            Box::new(uo_ops_sender.clone()),
+            Box::new(uo_ops_entity.clone()),
+            Box::new(DatabaseTable::<WriteMap, CodeHashes>::new(env.clone())),
+        );
+
+        let paymaster = Address::random();
+        let mut uos = vec![];
+        for _ in 0..3 {
+            let uo = UserOperationSigned {
+                paymaster_and_data: paymaster.as_bytes().to_vec().into(),
+                ..UserOperationSigned::random()
+            };
+            let uo =
+                UserOperation::from_user_operation_signed(UserOperationHash(H256::random()), uo);
+            mempool.add(uo.clone()).unwrap();
+            uos.push(uo);
+        }
+        assert!(mempool.check_consistency(false).unwrap().is_consistent());
+
+        // corrupt the indexes (as after a crash between the writes of the tables)
+        let stale = (Address::random(), UserOperationHash(H256::random()));
+        uo_ops_sender.remove_uo_hash(&uos[0].sender, &uos[0].hash).unwrap();
+        uo_ops_entity.add(&stale.0, stale.1).unwrap();
+
+        let report = mempool.check_consistency(false).unwrap();
+        assert_eq!(report.user_operations, 3);
+        assert_eq!(report.missing_by_sender, vec![(uos[0].sender, uos[0].hash)]);
+        assert_eq!(report.stale_by_entity, vec![stale]);
+        assert!(!report.is_consistent() && !report.repaired);
+        assert!(mempool.get_all_by_sender(&uos[0].sender).is_empty());
+
+        let report = mempool.check_consistency(true).unwrap();
+        assert!(!report.is_consistent() && report.repaired);
+
+        let report = mempool.check_consistency(false).unwrap();
+        assert!(report.is_consistent());
+        assert_eq!(mempool.get_all_by_sender(&uos[0].sender)[0].hash, uos[0].hash);
+        assert_eq!(mempool.get_number_by_entity(&paymaster), 3);
+        assert_eq!(mempool.get_number_by_entity(&stale.0), 0);
+    }
+
+    #[tokio::test]
+    async fn database_mempool_metadata() {
+        let dir = TempDir::new().unwrap();
+
+        let env = init_env::<WriteMap>(dir.into_path(), DatabaseArguments::default()).unwrap();
+        env.create_tables().expect("Create mdbx database tables failed");
+        let env = Arc::new(env);
+        let new_mempool = || {
+            Mempool::new(
+                Box::new(DatabaseTable::<WriteMap, UserOperations>::new(env.clone())),
+                Box::new(DatabaseTable::<WriteMap, UserOperationsBySender>::new(env.clone())),
+                Box::new(DatabaseTable::<WriteMap, UserOperationsByEntity>::new(env.clone())),
+                Box::new(DatabaseTable::<WriteMap, CodeHashes>::new(env.clone())),
+            )
+            .with_metadata(Box::new(DatabaseTable::<WriteMap, UserOperationsMetadata>::new(
+                env.clone(),
+            )))
+            .with_checkpoints(Box::new(DatabaseTable::<WriteMap, Checkpoints>::new(env.clone())))
+        };
+
+        let mut mempool = new_mempool();
+        let entry_point = Address::random();
+        assert_eq!(mempool.get_checkpoint(&entry_point).unwrap(), None);
+        mempool.set_checkpoint(&entry_point, 100).unwrap();
+        let uo = UserOperation::from_user_operation_signed(
+            UserOperationHash(H256::random()),
+            UserOperationSigned::random(),
+        );
+        let uo_hash = mempool.add_with_source(uo, UserOperationSource::P2p).unwrap();
+        let metadata = DatabaseTable::<WriteMap, UserOperationsMetadata>::new(env.clone())
+            .get_metadata(&uo_hash)
+            .unwrap()
+            .unwrap();
+        assert!(metadata.added_at > 0);
+        drop(mempool);
+
+        // the source, the time the user operation was added and the last processed block are kept
+        // across restarts
+        let mut mempool = new_mempool();
+        assert_eq!(mempool.get_source(&uo_hash), UserOperationSource::P2p);
+        assert_eq!(
+            DatabaseTable::<WriteMap, UserOperationsMetadata>::new(env.clone())
+                .get_metadata(&uo_hash)
+                .unwrap(),
+            Some(metadata)
+        );
+        assert_eq!(mempool.get_checkpoint(&entry_point).unwrap(), Some(100));
+        assert_eq!(mempool.get_stats().unwrap(), MempoolStats { rpc: 0, p2p: 1 });
+
+        assert!(mempool.remove(&uo_hash).unwrap());
+        assert!(DatabaseTable::<WriteMap, UserOperationsMetadata>::new(env)
+            .get_metadata(&uo_hash)
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn database_mempool_entry_points() {
+        let dir = TempDir::new().unwrap();
+
+        let env = init_env::<WriteMap>(dir.into_path(), DatabaseArguments::default()).unwrap();
+        env.create_tables().expect("Create mdbx database tables failed");
+        let env = Arc::new(env);
+        let new_mempool = |entry_point: Address| {
+            let uo_ops = DatabaseTable::<WriteMap, UserOperations>::new(env.clone());
+            Mempool::new(
+                Box::new(uo_ops.clone()),
+                Box::new(DatabaseTable::<WriteMap, UserOperationsBySender>::new(env.clone())),
+                Box::new(DatabaseTable::<WriteMap, UserOperationsByEntity>::new(env.clone())),
+                Box::new(DatabaseTable::<WriteMap, CodeHashes>::new(env.clone())),
+            )
+            .with_metadata(Box::new(DatabaseTable::<WriteMap, UserOperationsMetadata>::new(
+                env.clone(),
+            )))
+            .with_entry_point_index(Box::new(
+                DatabaseTable::<WriteMap, UserOperationsByEntryPoint>::new(env.clone()),
+            ))
+            .with_batch_remover(Box::new(uo_ops))
+            .with_entry_point(entry_point)
+        };
+
+        // the storage is shared by the mempools of the entry points
+        let (entry_point_a, entry_point_b) = (Address::random(), Address::random());
+        let uo = UserOperationSigned::random();
+        let uo_a =
+            UserOperation::from_user_operation_signed(uo.hash(&entry_point_a, 1), uo.clone());
+        let uo_b = UserOperation::from_user_operation_signed(uo.hash(&entry_point_b, 1), uo);
+        new_mempool(entry_point_a).add_with_source(uo_a.clone(), UserOperationSource::Rpc).unwrap();
+        new_mempool(entry_point_b).add_with_source(uo_b.clone(), UserOperationSource::P2p).unwrap();
+
+        // after a restart, each user operation is still only visible to its entry point
+        let hashes = |uos: Vec<UserOperation>| uos.iter().map(|uo| uo.hash).collect::<Vec<_>>();
+        let mut mempool_a = new_mempool(entry_point_a);
+        let mempool_b = new_mempool(entry_point_b);
+        assert_eq!(mempool_a.get_entry_point(&uo_a.hash), Some(entry_point_a));
+        assert_eq!(mempool_b.get_entry_point(&uo_b.hash), Some(entry_point_b));
+        assert_eq!(hashes(mempool_a.get_all().unwrap()), vec![uo_a.hash]);
+        assert_eq!(hashes(mempool_b.get_sorted(None).unwrap()), vec![uo_b.hash]);
+        assert_eq!(hashes(mempool_a.get_all_by_sender(&uo_a.sender)), vec![uo_a.hash]);
+        let (page, total) = mempool_b.get_page(0, None).unwrap();
+        assert_eq!((hashes(page), total), (vec![uo_b.hash], 1));
+        assert!(mempool_a.check_consistency(false).unwrap().is_consistent());
+
+        // the index entry is removed along with the user operation
+        assert_eq!(mempool_a.remove_included_batch(&[uo_a.hash]).unwrap(), 1);
+        assert!(DatabaseTable::<WriteMap, UserOperationsByEntryPoint>::new(env)
+            .get_all_by_address(&entry_point_a)
+            .is_empty());
+        assert!(mempool_a.get_all().unwrap().is_empty());
+        assert_eq!(hashes(mempool_b.get_all().unwrap()), vec![uo_b.hash]);
+    }
+
+    #[tokio::test]
+    async fn database_map_full() {
+        let dir = TempDir::new().unwrap();
+
+        let args =
+            DatabaseArguments { initial_size: 0, max_size: 1024 * 1024, growth_step: 1024 * 64 };
+        let env = init_env::<WriteMap>(dir.into_path(), args).unwrap();
+        let mut uo_ops: DatabaseTable<WriteMap, UserOperations> = DatabaseTable::new(Arc::new(env));
+
+        // writing past the maximum size returns an error instead of panicking
+        let err = (0..100_000)
+            .find_map(|_| add_random_user_operation(&mut uo_ops).err())
+            .expect("Database should be full");
+        assert!(matches!(err, MempoolErrorKind::Database(DatabaseError::MapFull)));
+    }
+
+    #[tokio::test]
+    async fn database_custom_geometry() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.into_path();
+
+        let args = DatabaseArguments {
+            initial_size: 1024 * 1024,
+            max_size: 1024 * 1024 * 64,
+            growth_step: 1024 * 1024,
+        };
+        let env = init_env::<WriteMap>(path.clone(), args).unwrap();
+        let mut uo_ops: DatabaseTable<WriteMap, UserOperations> = DatabaseTable::new(Arc::new(env));
+
+        // writing past the initial size grows the database
+        for _ in 0..5_000 {
+            add_random_user_operation(&mut uo_ops).unwrap();
+        }
+        let size = std::fs::metadata(path.join("mdbx.dat")).unwrap().len();
+        assert!(size > args.initial_size as u64);
+
+        // invalid geometry is rejected
+        let args = DatabaseArguments { initial_size: 2, max_size: 1, growth_step: 1 };
+        assert!(init_env::<WriteMap>(TempDir::new().unwrap().into_path(), args).is_err());
+        let args = DatabaseArguments { growth_step: 0, ..Default::default() };
+        assert!(init_env::<WriteMap>(TempDir::new().unwrap().into_path(), args).is_err());
+    }
+
+    fn add_random_user_operation(
+        uo_ops: &mut DatabaseTable<WriteMap, UserOperations>,
+    ) -> Result<UserOperationHash, MempoolErrorKind> {
+        let uo =
+            UserOperationSigned { call_data: vec![1; 512].into(), ..UserOperationSigned::random() };
+        uo_ops.add(UserOperation::from_user_operation_signed(UserOperationHash(H256::random()), uo))
+    }
+
+    #[tokio::test]
+    async fn database_mempool_replacement_after_restart() {
+        let dir = TempDir::new().unwrap();
+
+        let env = init_env::<WriteMap>(dir.into_path(), DatabaseArguments::default()).unwrap();
+        env.create_tables().expect("Create mdbx database tables failed");
+        let env = Arc::new(env);
+        let new_mempool = || {
+            Mempool::new(
+                Box::new(DatabaseTable::<WriteMap, UserOperations>::new(env.clone())),
+                Box::new(DatabaseTable::<WriteMap, UserOperationsBySender>::new(env.clone())),
+                Box::new(DatabaseTable::<WriteMap, UserOperationsByEntity>::new(env.clone())),
+                Box::new(DatabaseTable::<WriteMap, CodeHashes>::new(env.clone())),
+            )
+            .with_metadata(Box::new(
+                DatabaseTable::<WriteMap, UserOperationsMetadata>::new(env.clone()),
+            ))
+        };
+        let (provider, _) = Provider::mocked();
+        let entry_point = EntryPoint::new(Arc::new(provider), Address::random());
+
+        let sender = Address::random();
+        let new_uo = |max_fee_per_gas: u64, max_priority_fee_per_gas: u64| {
+            UserOperation::from_user_operation_signed(
+                H256::random().into(),
+                UserOperationSigned::default()
+                    .sender(sender)
+                    .max_fee_per_gas(max_fee_per_gas.into())
+                    .max_priority_fee_per_gas(max_priority_fee_per_gas.into()),
+            )
+        };
+
+        let mut uopool =
+            UoPoolTestBuilder::new(entry_point.clone()).with_mempool(new_mempool()).build();
+        let p2p_hash = uopool
+            .add_user_operation(new_uo(100, 10), Ok(Default::default()), UserOperationSource::P2p)
+            .await
+            .unwrap();
+        drop(uopool);
+
+        // after a restart, the p2p user operation isn't treated as a local one (which would
+        // require twice the increase to be replaced by a p2p user operation)
+        let mut uopool = UoPoolTestBuilder::new(entry_point).with_mempool(new_mempool()).build();
+        let hash = uopool
+            .add_user_operation(
+                new_uo(110, 11),
+                Ok(UserOperationValidationOutcome {
+                    prev_hash: Some(p2p_hash),
+                    ..Default::default()
+                }),
+                UserOperationSource::P2p,
+            )
+            .await
+            .unwrap();
+        let uos = uopool.get_all().unwrap();
+        assert_eq!(uos.len(), 1);
+        assert_eq!(uos[0].hash, hash);
     }
 }
@@ -1,8 +1,8 @@
 //! The database implementation of the [Mempool](crate::mempool::Mempool) trait. Primarily used for
 //! storing mempool information in a local database.
 
-pub use self::env::DatabaseError;
 use self::env::Env;
+pub use self::env::{DatabaseArguments, DatabaseError};
 use reth_libmdbx::EnvironmentKind;
 pub use reth_libmdbx::WriteMap;
 use std::{path::PathBuf, sync::Arc};
@@ -13,8 +13,11 @@ pub mod reputation;
 pub mod tables;
 mod utils;
 
-pub fn init_env<E: EnvironmentKind>(path: PathBuf) -> eyre::Result<Env<E>> {
-    let env = Env::open(path)?;
+pub fn init_env<E: EnvironmentKind>(
+    path: PathBuf,
+    args: DatabaseArguments,
+) -> eyre::Result<Env<E>> {
+    let env = Env::open(path, args)?;
     env.create_tables()?;
     Ok(env)
 }
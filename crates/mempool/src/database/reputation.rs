@@ -61,7 +61,7 @@ impl<E: EnvironmentKind> ReputationEntryOp for DatabaseTable<E, EntitiesReputati
 #[cfg(test)]
 mod tests {
     use crate::{
-        database::{init_env, tables::EntitiesReputation, DatabaseTable},
+        database::{init_env, tables::EntitiesReputation, DatabaseArguments, DatabaseTable},
         utils::tests::reputation_test_case,
         Reputation,
     };
@@ -78,7 +78,7 @@ mod tests {
     async fn database_reputation() {
         let dir = TempDir::new().unwrap();
 
-        let env = init_env::<WriteMap>(dir.into_path()).unwrap();
+        let env = init_env::<WriteMap>(dir.into_path(), DatabaseArguments::default()).unwrap();
         env.create_tables().expect("Create mdbx database tables failed");
         let env = Arc::new(env);
         let entry: Box<DatabaseTable<WriteMap, EntitiesReputation>> =
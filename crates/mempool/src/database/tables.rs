@@ -1,6 +1,6 @@
 use super::utils::{
-    WrapAddress, WrapCodeHashVec, WrapReputationEntry, WrapUserOpSet, WrapUserOperationHash,
-    WrapUserOperationSigned,
+    WrapAddress, WrapBlockNumber, WrapCodeHashVec, WrapReputationEntry, WrapUserOpSet,
+    WrapUserOperationHash, WrapUserOperationMetadata, WrapUserOperationSigned,
 };
 use reth_db::{table, TableType};
 
@@ -20,21 +20,39 @@ table!(
     ( UserOperationsByEntity ) WrapAddress | WrapUserOpSet
 );
 
+table!(
+    /// Stores the hashes of user operations by the entry point they were added for
+    ( UserOperationsByEntryPoint ) WrapAddress | WrapUserOpSet
+);
+
 table!(
     /// Stores the code hashes (needed during simulation)
     ( CodeHashes ) WrapUserOperationHash | WrapCodeHashVec
 );
 
+table!(
+    /// Stores the metadata of user operations (e.g., their sources)
+    ( UserOperationsMetadata ) WrapUserOperationHash | WrapUserOperationMetadata
+);
+
 table!(
     /// Stores the reputation of entities
     ( EntitiesReputation ) WrapAddress | WrapReputationEntry
 );
 
+table!(
+    /// Stores the last processed blocks of the entry points
+    ( Checkpoints ) WrapAddress | WrapBlockNumber
+);
+
 /// Tables that should be present inside database
-pub const TABLES: [(TableType, &str); 5] = [
+pub const TABLES: [(TableType, &str); 8] = [
     (TableType::Table, UserOperations::const_name()),
     (TableType::Table, UserOperationsBySender::const_name()),
     (TableType::Table, UserOperationsByEntity::const_name()),
+    (TableType::Table, UserOperationsByEntryPoint::const_name()),
     (TableType::Table, CodeHashes::const_name()),
+    (TableType::Table, UserOperationsMetadata::const_name()),
     (TableType::Table, EntitiesReputation::const_name()),
+    (TableType::Table, Checkpoints::const_name()),
 ];
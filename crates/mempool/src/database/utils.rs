@@ -1,3 +1,4 @@
+use crate::mempool::UserOperationMetadata;
 use bin_layout::{Decoder, Encoder};
 use ethers::{
     abi::{AbiDecode, AbiEncode},
@@ -8,6 +9,7 @@ use reth_db::table::{Compress, Decode, Decompress, Encode};
 use serde::{Deserialize, Serialize};
 use silius_primitives::{
     reputation::ReputationEntry, simulation::CodeHash, UserOperationHash, UserOperationSigned,
+    UserOperationSource,
 };
 use std::{collections::HashSet, fmt::Debug};
 
@@ -213,3 +215,100 @@ impl Decompress for WrapCodeHashVec {
         Ok(decoded.into())
     }
 }
+
+#[derive(Default, Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct WrapBlockNumber(u64);
+
+impl From<u64> for WrapBlockNumber {
+    fn from(value: u64) -> Self {
+        Self(value)
+    }
+}
+
+impl From<WrapBlockNumber> for u64 {
+    fn from(value: WrapBlockNumber) -> Self {
+        value.0
+    }
+}
+
+impl Compress for WrapBlockNumber {
+    type Compressed = Vec<u8>;
+    fn compress(self) -> Self::Compressed {
+        self.0.to_be_bytes().to_vec()
+    }
+}
+
+impl Decompress for WrapBlockNumber {
+    fn decompress<B: Into<prost::bytes::Bytes>>(value: B) -> Result<Self, reth_db::Error> {
+        let value = value.into();
+        let bytes: [u8; 8] = value.as_ref().try_into().map_err(|_| reth_db::Error::DecodeError)?;
+        Ok(Self(u64::from_be_bytes(bytes)))
+    }
+}
+
+#[derive(Default, Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct WrapUserOperationMetadata(UserOperationMetadata);
+
+impl From<UserOperationMetadata> for WrapUserOperationMetadata {
+    fn from(value: UserOperationMetadata) -> Self {
+        Self(value)
+    }
+}
+
+impl From<WrapUserOperationMetadata> for UserOperationMetadata {
+    fn from(value: WrapUserOperationMetadata) -> Self {
+        value.0
+    }
+}
+
+impl<'de> Decoder<'de> for WrapUserOperationMetadata {
+    fn decoder(data: &mut &'de [u8]) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let source = match u8::decoder(data)? {
+            0 => UserOperationSource::Rpc,
+            1 => UserOperationSource::P2p,
+            source => return Err(format!("Unknown user operation source {source}").into()),
+        };
+        let added_at = u64::decoder(data)?;
+        // the entry point follows the time it was added (missing if the user operation wasn't
+        // added for one)
+        let bytes: &'de [u8] = *data;
+        let entry_point = match bytes.len() {
+            0 => None,
+            len if len >= Address::len_bytes() => {
+                let (entry_point, rest) = bytes.split_at(Address::len_bytes());
+                *data = rest;
+                Some(Address::from_slice(entry_point))
+            }
+            len => return Err(format!("Invalid entry point of {len} bytes").into()),
+        };
+        Ok(WrapUserOperationMetadata(UserOperationMetadata { source, entry_point, added_at }))
+    }
+}
+
+impl Encoder for WrapUserOperationMetadata {
+    fn encoder(&self, write: &mut impl std::io::prelude::Write) -> std::io::Result<()> {
+        let source: u8 = match self.0.source {
+            UserOperationSource::Rpc => 0,
+            UserOperationSource::P2p => 1,
+        };
+        source.encoder(write)?;
+        self.0.added_at.encoder(write)?;
+        if let Some(entry_point) = self.0.entry_point {
+            write.write_all(entry_point.as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+impl Compress for WrapUserOperationMetadata {
+    type Compressed = Vec<u8>;
+    fn compress(self) -> Self::Compressed {
+        self.encode()
+    }
+}
+
+impl Decompress for WrapUserOperationMetadata {
+    fn decompress<B: Into<prost::bytes::Bytes>>(value: B) -> Result<Self, reth_db::Error> {
+        Self::decode(value.into().as_ref()).map_err(|_| reth_db::Error::DecodeError)
+    }
+}
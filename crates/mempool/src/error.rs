@@ -2,8 +2,8 @@
 use crate::DatabaseError;
 use ethers::types::{Address, U256};
 use serde::{Deserialize, Serialize};
-use silius_contracts::EntryPointError;
-use silius_primitives::UserOperationHash;
+use silius_contracts::{EntryPointError, FailedOp};
+use silius_primitives::{constants::validation::INVALID_NONCE_REASON, UserOperationHash};
 use thiserror::Error;
 
 pub type MempoolResult<T> = Result<T, MempoolError>;
@@ -46,6 +46,40 @@ pub enum MempoolErrorKind {
     #[cfg(feature = "mdbx")]
     #[error(transparent)]
     Database(DatabaseError),
+    /// Mempool is full and the user operation doesn't pay more than any of the user operations
+    /// which could be evicted
+    #[error("mempool is full, maxPriorityFeePerGas {max_priority_fee_per_gas} too low to evict")]
+    MempoolFull {
+        /// The max priority fee per gas of the user operation
+        max_priority_fee_per_gas: U256,
+    },
+    /// Nonce of the user operation is too far ahead of the next one expected for the sender (or
+    /// there is no room to hold it until the gap is filled)
+    #[error("nonce gap: {inner}")]
+    NonceGap {
+        /// The inner error message
+        inner: String,
+    },
+    /// No room to hold the user operation until its validAfter passes
+    #[error("no room to hold the user operation until {valid_after}")]
+    DelayedQueueFull {
+        /// The validAfter of the user operation
+        valid_after: U256,
+    },
+    /// Replacement user operation (same sender and nonce) doesn't bump the fees enough
+    #[error("replacement user operation underpriced, requires maxFeePerGas {max_fee_per_gas} and maxPriorityFeePerGas {max_priority_fee_per_gas}")]
+    ReplacementUnderpriced {
+        /// The minimum max fee per gas of the replacement
+        max_fee_per_gas: U256,
+        /// The minimum max priority fee per gas of the replacement
+        max_priority_fee_per_gas: U256,
+    },
+    /// User operation with the same hash is already in the mempool for another entry point
+    #[error("user operation with the same hash already added for entry point {entry_point:?}")]
+    HashCollision {
+        /// The entry point the user operation in the mempool was added for
+        entry_point: Address,
+    },
     /// Any other error
     #[error("other error: {inner}")]
     Other {
@@ -159,9 +193,24 @@ pub enum SanityError {
     /// Paymaster validation failed
     #[error("{inner}")]
     Paymaster { inner: String },
+    /// Call data validation failed
+    #[error("{inner}")]
+    CallData { inner: String },
     /// Sender validation failed
     #[error("{inner}")]
     Sender { inner: String },
+    /// Byte fields of the user operation are malformed
+    #[error("{inner}")]
+    MalformedFields { inner: String },
+    /// Gas required by the user operation exceeds the gas cap of the simulation
+    #[error("simulation gas too high: expected at most {max_simulation_gas}")]
+    SimulationGasTooHigh { simulation_gas: U256, max_simulation_gas: U256 },
+    /// Funds of the sender (without a paymaster) don't cover the required prefund
+    #[error("sender funds too low: expected at least {required_prefund}")]
+    InsufficientPrefund { funds: U256, required_prefund: U256 },
+    /// Latest block is older than the acceptance window
+    #[error("latest block is {age} seconds old: expected at most {acceptance_window} seconds")]
+    StaleBlock { age: u64, acceptance_window: u64 },
     /// Entity role validation
     #[error("A {entity} at {address:?} in this user operation is used as a {entity_other} entity in another useroperation currently in mempool")]
     EntityRoles { entity: String, address: Address, entity_other: String },
@@ -213,15 +262,36 @@ pub enum SimulationError {
     /// Error during user operation validation
     #[error("{inner}")]
     Validation { inner: String },
+    /// Invalid nonce of the user operation (e.g., ahead of the next nonce of the sender)
+    #[error("{inner}")]
+    Nonce { inner: String },
     /// Error during user operation execution
     #[error("{inner}")]
     Execution { inner: String },
     /// Opcode error
-    #[error("{entity} uses banned opcode: {opcode}")]
-    Opcode { entity: String, opcode: String },
+    #[error("[{rule}] {entity} at {address:?} uses banned opcode: {opcode}")]
+    Opcode {
+        /// The violated rule (e.g. OP-011)
+        rule: String,
+        /// The entity (factory, account, paymaster) which used the opcode
+        entity: String,
+        /// The address of the entity
+        address: Address,
+        /// The banned opcode
+        opcode: String,
+    },
     /// Storage access error
-    #[error("Storage access validation failed for slot: {slot}")]
-    StorageAccess { slot: String },
+    #[error("[{rule}] {entity} at {address:?} failed storage access validation for slot {slot}")]
+    StorageAccess {
+        /// The violated rule (e.g. STO-033)
+        rule: String,
+        /// The entity (factory, account, paymaster) which accessed the storage
+        entity: String,
+        /// The address of the contract whose storage was accessed
+        address: Address,
+        /// The accessed slot
+        slot: String,
+    },
     /// Unstaked entity did something it shouldn't
     #[error("A unstaked {entity} at {address:?}: {inner}")]
     Unstaked { entity: String, address: Address, inner: String },
@@ -261,6 +331,16 @@ impl From<ReputationError> for SimulationError {
     }
 }
 
+impl From<FailedOp> for SimulationError {
+    fn from(op: FailedOp) -> Self {
+        if op.reason.starts_with(INVALID_NONCE_REASON) {
+            SimulationError::Nonce { inner: op.reason }
+        } else {
+            SimulationError::Validation { inner: op.reason }
+        }
+    }
+}
+
 impl From<EntryPointError> for SimulationError {
     fn from(err: EntryPointError) -> Self {
         match err {
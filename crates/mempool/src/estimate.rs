@@ -4,13 +4,13 @@ use ethers::{
     abi::{Hash, RawLog},
     contract::EthLogDecode,
     providers::Middleware,
-    types::{Bytes, U256},
+    types::{spoof, Bytes, U256},
 };
 use silius_contracts::{
     decode_revert_string,
     executor_tracer::{ExecutorTracerResult, LogInfo},
-    EntryPoint, EntryPointError, ExecutionResult, FailedOp, UserOperationEventFilter,
-    UserOperationRevertReasonFilter,
+    EntryPoint, EntryPointError, EntryPointVersion, ExecutionResult, FailedOp,
+    UserOperationEventFilter, UserOperationRevertReasonFilter,
 };
 use silius_primitives::UserOperationSigned;
 use std::str::FromStr;
@@ -58,13 +58,14 @@ struct TraceOutput {
     user_op_revert_event: Option<UserOperationRevertReasonFilter>,
 }
 
-fn parse_simulate_handle_op_output(output: &str) -> Result<ExecutionResult, EntryPointError> {
+fn parse_simulate_handle_op_output(
+    output: &str,
+    version: EntryPointVersion,
+) -> Result<ExecutionResult, EntryPointError> {
     let output_b = Bytes::from_str(output).map_err(|e| EntryPointError::Other {
         inner: format!("parse simulate handle op output failed: {e:?}"),
     })?;
-    if let Ok(decoded) =
-        <ExecutionResult as ::ethers::core::abi::AbiDecode>::decode(output_b.as_ref())
-    {
+    if let Some(decoded) = version.decode_execution_result(output_b.as_ref()) {
         return Ok(decoded);
     };
 
@@ -109,15 +110,17 @@ fn parse_user_op_event<T: Debug + EthLogDecode>(event: &LogInfo) -> Result<T, En
 async fn trace_simulate_handle_op<M: Middleware>(
     user_op: &UserOperationSigned,
     entry_point: &EntryPoint<M>,
+    state_override: Option<&spoof::State>,
 ) -> Result<TraceOutput, EntryPointError> {
-    let geth_trace = entry_point.simulate_handle_op_trace(user_op.clone()).await?;
+    let geth_trace = entry_point.simulate_handle_op_trace(user_op.clone(), state_override).await?;
 
     let tracer_result: ExecutorTracerResult =
         ExecutorTracerResult::try_from(geth_trace).map_err(|e| EntryPointError::Other {
             inner: format!("Estimate trace simulate handle op decode error {e:?}"),
         })?;
 
-    let execution_result = parse_simulate_handle_op_output(tracer_result.output.as_str())?;
+    let execution_result =
+        parse_simulate_handle_op_output(tracer_result.output.as_str(), entry_point.version())?;
 
     let user_op_event = tracer_result.user_op_event.as_ref().ok_or(EntryPointError::Other {
         inner: "Estimate trace simulate handle op user op event not found".to_string(),
@@ -152,6 +155,7 @@ async fn trace_simulate_handle_op<M: Middleware>(
 pub async fn estimate_user_op_gas<M: Middleware>(
     user_operation_original: &UserOperationSigned,
     entry_point: &EntryPoint<M>,
+    state_override: Option<&spoof::State>,
 ) -> Result<(U256, U256), EntryPointError> {
     let mut iter: u64 = 0;
 
@@ -172,7 +176,7 @@ pub async fn estimate_user_op_gas<M: Middleware>(
     while r - l >= FALL_BACK_BINARY_SEARCH_CUT_OFF {
         let m = (l + r) / 2;
         user_operation.verification_gas_limit = m.into();
-        match entry_point.simulate_handle_op(user_operation.clone()).await {
+        match entry_point.simulate_handle_op(user_operation.clone(), state_override).await {
             // VGL too high
             Ok(_) => {
                 r = m - 1;
@@ -210,7 +214,7 @@ pub async fn estimate_user_op_gas<M: Middleware>(
         user_operation.max_fee_per_gas = 0u64.into();
         user_operation.max_priority_fee_per_gas = 0u64.into();
         user_operation.call_gas_limit = MAX_CALL_GAS_LIMIT.into(); // max block gas limit, better set as a config parameter
-        match trace_simulate_handle_op(&user_operation, entry_point).await {
+        match trace_simulate_handle_op(&user_operation, entry_point, state_override).await {
             Ok(o) => {
                 out = o;
                 break;
@@ -236,7 +240,7 @@ pub async fn estimate_user_op_gas<M: Middleware>(
     user_operation.call_gas_limit = call_gas_limit.into();
 
     loop {
-        match trace_simulate_handle_op(&user_operation, entry_point).await {
+        match trace_simulate_handle_op(&user_operation, entry_point, state_override).await {
             Ok(_) => break,
             Err(e) => {
                 if is_execution_oog(&e) || is_execution_revert(&e) {
@@ -246,7 +250,9 @@ pub async fn estimate_user_op_gas<M: Middleware>(
                     while r - l >= FALL_BACK_BINARY_SEARCH_CUT_OFF {
                         let m = (l + r) / 2;
                         user_operation.call_gas_limit = m.into();
-                        let res = trace_simulate_handle_op(&user_operation, entry_point).await;
+                        let res =
+                            trace_simulate_handle_op(&user_operation, entry_point, state_override)
+                                .await;
                         match res {
                             Ok(_) => {
                                 r = m - 1;
@@ -312,7 +318,7 @@ mod tests {
             signature: "0xcbe8b7855dc1481374c37579f953876b778a4ee16f5408b18894d2306977651498b79128e5fedab6855d6b16f8466e8247e4ba601989d1c5fd24194b01b5e8514d".parse().unwrap(),
         };
 
-        let res = estimate_user_op_gas(&uo, &ep).await;
+        let res = estimate_user_op_gas(&uo, &ep, None).await;
         assert!(res.is_err());
     }
 }
@@ -0,0 +1,126 @@
+//! Tracking of the chain heads received from the provider, to detect chain splits (a block at an
+//! already seen height with a different hash) and pause bundling until the head stabilizes, and
+//! of the user operations included in these heads, to re-admit them if the blocks are reorged.
+
+use crate::metrics::record_chain_split;
+use ethers::types::H256;
+use parking_lot::RwLock;
+use silius_primitives::{
+    constants::mempool::CHAIN_HEAD_HISTORY, UserOperation, UserOperationSource,
+};
+use std::{collections::BTreeMap, sync::Arc};
+use tracing::warn;
+
+#[derive(Debug, Default)]
+struct Heads {
+    // Hashes of the recent heads by block number
+    hashes: BTreeMap<u64, H256>,
+    // User operations (and their sources) removed from the mempool as included in the recent
+    // heads by block number
+    included: BTreeMap<u64, Vec<(UserOperation, UserOperationSource)>>,
+    latest: u64,
+    // Block number from which bundling resumes (None if not paused)
+    paused_until: Option<u64>,
+}
+
+/// Tracker of the recent chain heads (shared between the clones)
+#[derive(Clone, Debug, Default)]
+pub struct HeadTracker {
+    // Number of blocks without a chain split before bundling resumes (never paused if None)
+    pause: Option<u64>,
+    heads: Arc<RwLock<Heads>>,
+}
+
+impl HeadTracker {
+    pub fn new(pause: Option<u64>) -> Self {
+        Self { pause, heads: Default::default() }
+    }
+
+    /// Records a new chain head
+    ///
+    /// # Arguments
+    /// * `number` - The number of the block
+    /// * `hash` - The hash of the block
+    ///
+    /// # Returns
+    /// * `true` if a different block was already seen at this height (chain split)
+    pub fn observe(&self, number: u64, hash: H256) -> bool {
+        let mut heads = self.heads.write();
+
+        let split = heads.hashes.insert(number, hash).is_some_and(|prev| prev != hash);
+
+        heads.latest = heads.latest.max(number);
+        let oldest = heads.latest.saturating_sub(CHAIN_HEAD_HISTORY);
+        heads.hashes = heads.hashes.split_off(&oldest);
+        heads.included = heads.included.split_off(&oldest);
+
+        if split {
+            warn!("Chain split detected at block {number} (new head {hash:?})");
+            self.pause(&mut heads);
+        }
+
+        split
+    }
+
+    /// Returns the hash of the head seen at the given height (if still tracked)
+    pub fn hash(&self, number: u64) -> Option<H256> {
+        self.heads.read().hashes.get(&number).copied()
+    }
+
+    /// Records the user operations removed from the mempool as included in a block
+    ///
+    /// # Arguments
+    /// * `number` - The number of the block
+    /// * `uos` - The included user operations and their sources
+    pub fn record_included(&self, number: u64, uos: Vec<(UserOperation, UserOperationSource)>) {
+        if uos.is_empty() {
+            return;
+        }
+
+        let mut heads = self.heads.write();
+        if number >= heads.latest.saturating_sub(CHAIN_HEAD_HISTORY) {
+            heads.included.entry(number).or_default().extend(uos);
+        }
+    }
+
+    /// Forgets the heads from the given height on (they were reorged) and records the new head,
+    /// which pauses bundling like a chain split. As the new head is recorded, observing it
+    /// afterwards doesn't count the chain split again.
+    ///
+    /// # Arguments
+    /// * `from` - The number of the first reorged block
+    /// * `number` - The number of the new head
+    /// * `hash` - The hash of the new head
+    ///
+    /// # Returns
+    /// * The user operations (and their sources) which were included in the reorged blocks
+    pub fn roll_back(
+        &self,
+        from: u64,
+        number: u64,
+        hash: H256,
+    ) -> Vec<(UserOperation, UserOperationSource)> {
+        warn!("Chain reorg detected from block {from} (new head {hash:?})");
+
+        let mut heads = self.heads.write();
+        heads.hashes.retain(|number, _| *number < from);
+        heads.hashes.insert(number, hash);
+        heads.latest = heads.latest.max(number);
+        self.pause(&mut heads);
+        heads.included.split_off(&from).into_values().flatten().collect()
+    }
+
+    fn pause(&self, heads: &mut Heads) {
+        record_chain_split();
+
+        if let Some(pause) = self.pause {
+            heads.paused_until = Some(heads.latest + pause);
+        }
+    }
+
+    /// Whether bundling is paused because of a recent chain split
+    pub fn is_paused(&self) -> bool {
+        let heads = self.heads.read();
+        heads.paused_until.is_some_and(|until| heads.latest < until)
+    }
+}
@@ -1,39 +1,48 @@
 //! The UserOperation alternative mempool implementation according to the [ERC-4337 specifications](https://eips.ethereum.org/EIPS/eip-4337#Alternative%20Mempools).
 #![allow(dead_code)]
 
+mod audit;
 mod builder;
 #[cfg(feature = "mdbx")]
 mod database;
 pub mod error;
 mod estimate;
+mod head;
 mod memory;
 mod mempool;
 pub mod metrics;
 mod reputation;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
 mod uopool;
 mod utils;
 pub mod validate;
 
+pub use audit::{AuditLog, AuditRecord};
 pub use builder::UoPoolBuilder;
 #[cfg(feature = "mdbx")]
 pub use database::{
     init_env,
     tables::{
-        CodeHashes, EntitiesReputation, UserOperations, UserOperationsByEntity,
-        UserOperationsBySender,
+        Checkpoints, CodeHashes, EntitiesReputation, UserOperations, UserOperationsByEntity,
+        UserOperationsByEntryPoint, UserOperationsBySender, UserOperationsMetadata,
     },
-    DatabaseError, DatabaseTable, WriteMap,
+    DatabaseArguments, DatabaseError, DatabaseTable, WriteMap,
 };
 pub use error::{
     InvalidMempoolUserOperationError, MempoolError, MempoolErrorKind, ReputationError, SanityError,
     SimulationError,
 };
+pub use head::HeadTracker;
 pub use mempool::{
-    mempool_id, AddRemoveUserOp, AddRemoveUserOpHash, ClearOp, Mempool, MempoolId,
-    UserOperationAct, UserOperationAddrAct, UserOperationAddrOp, UserOperationCodeHashAct,
-    UserOperationCodeHashOp, UserOperationOp,
+    mempool_id, AddRemoveUserOp, AddRemoveUserOpHash, AgingFeeOrdering, BundleOrdering,
+    CheckpointAct, CheckpointOp, ClearOp, DelayedUserOperation, FeeOrdering, GappedUserOperation,
+    Mempool, MempoolConsistencyReport, MempoolId, NewUserOperation, OrderingContext,
+    RemoveBatchAct, RemoveBatchOp, UserOperationAct, UserOperationAddrAct, UserOperationAddrOp,
+    UserOperationCodeHashAct, UserOperationCodeHashOp, UserOperationMetadata,
+    UserOperationMetadataAct, UserOperationMetadataOp, UserOperationOp,
 };
 pub use reputation::{HashSetOp, Reputation, ReputationEntryOp};
 pub use uopool::UoPool;
-pub use utils::Overhead;
+pub use utils::{L1GasOracle, OpStackL1GasOracle, Overhead, OverheadConfig};
 pub use validate::{SanityCheck, SimulationCheck, SimulationTraceCheck};
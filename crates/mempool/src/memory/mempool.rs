@@ -1,7 +1,7 @@
 use crate::{
     mempool::{
-        AddRemoveUserOp, AddRemoveUserOpHash, ClearOp, UserOperationAddrOp,
-        UserOperationCodeHashOp, UserOperationOp,
+        AddRemoveUserOp, AddRemoveUserOpHash, CheckpointOp, ClearOp, UserOperationAddrOp,
+        UserOperationCodeHashOp, UserOperationMetadata, UserOperationMetadataOp, UserOperationOp,
     },
     MempoolErrorKind,
 };
@@ -71,6 +71,13 @@ impl UserOperationAddrOp for HashMap<Address, HashSet<UserOperationHash>> {
             vec![]
         };
     }
+
+    fn get_all_entries(&self) -> Result<Vec<(Address, Vec<UserOperationHash>)>, MempoolErrorKind> {
+        Ok(self
+            .iter()
+            .map(|(addr, uo_hashes)| (*addr, uo_hashes.iter().cloned().collect()))
+            .collect())
+    }
 }
 
 impl AddRemoveUserOpHash for HashMap<Address, HashSet<UserOperationHash>> {
@@ -135,6 +142,49 @@ impl UserOperationCodeHashOp for HashMap<UserOperationHash, Vec<CodeHash>> {
     }
 }
 
+impl UserOperationMetadataOp for HashMap<UserOperationHash, UserOperationMetadata> {
+    fn get_metadata(
+        &self,
+        uo_hash: &UserOperationHash,
+    ) -> Result<Option<UserOperationMetadata>, MempoolErrorKind> {
+        Ok(self.get(uo_hash).cloned())
+    }
+
+    fn set_metadata(
+        &mut self,
+        uo_hash: &UserOperationHash,
+        metadata: UserOperationMetadata,
+    ) -> Result<(), MempoolErrorKind> {
+        self.insert(*uo_hash, metadata);
+        Ok(())
+    }
+
+    fn remove_metadata(&mut self, uo_hash: &UserOperationHash) -> Result<bool, MempoolErrorKind> {
+        Ok(self.remove(uo_hash).is_some())
+    }
+}
+
+impl CheckpointOp for HashMap<Address, u64> {
+    fn get_checkpoint(&self, entry_point: &Address) -> Result<Option<u64>, MempoolErrorKind> {
+        Ok(self.get(entry_point).copied())
+    }
+
+    fn set_checkpoint(
+        &mut self,
+        entry_point: &Address,
+        block: u64,
+    ) -> Result<(), MempoolErrorKind> {
+        self.insert(*entry_point, block);
+        Ok(())
+    }
+}
+
+impl ClearOp for HashMap<UserOperationHash, UserOperationMetadata> {
+    fn clear(&mut self) {
+        self.clear()
+    }
+}
+
 impl ClearOp for HashMap<UserOperationHash, Vec<CodeHash>> {
     fn clear(&mut self) {
         self.clear()
@@ -156,7 +206,10 @@ impl ClearOp for HashMap<Address, HashSet<UserOperationHash>> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{utils::tests::mempool_test_case, Mempool};
+    use crate::{
+        utils::tests::{mempool_eviction_test_case, mempool_test_case},
+        Mempool,
+    };
 
     #[allow(clippy::unit_cmp)]
     #[tokio::test]
@@ -169,4 +222,15 @@ mod tests {
         );
         mempool_test_case(mempool);
     }
+
+    #[tokio::test]
+    async fn memory_mempool_eviction() {
+        let mempool = Mempool::new(
+            Box::new(HashMap::<UserOperationHash, UserOperationSigned>::default()),
+            Box::new(HashMap::<Address, HashSet<UserOperationHash>>::default()),
+            Box::new(HashMap::<Address, HashSet<UserOperationHash>>::default()),
+            Box::new(HashMap::<UserOperationHash, Vec<CodeHash>>::default()),
+        );
+        mempool_eviction_test_case(mempool).await;
+    }
 }
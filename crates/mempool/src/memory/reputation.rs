@@ -49,7 +49,14 @@ impl ReputationEntryOp for HashMap<Address, ReputationEntry> {
 }
 #[cfg(test)]
 mod tests {
-    use crate::{utils::tests::reputation_test_case, Reputation};
+    use crate::{
+        test_utils,
+        utils::tests::{
+            reputation_export_import_test_case, reputation_grace_period_test_case,
+            reputation_probation_test_case, reputation_test_case,
+        },
+        Reputation,
+    };
     use ethers::types::{Address, U256};
     use parking_lot::RwLock;
     use silius_primitives::{
@@ -78,4 +85,22 @@ mod tests {
         );
         reputation_test_case(reputation);
     }
+
+    #[tokio::test]
+    async fn memory_reputation_grace_period() {
+        reputation_grace_period_test_case(test_utils::memory_reputation());
+    }
+
+    #[tokio::test]
+    async fn memory_reputation_probation() {
+        reputation_probation_test_case(test_utils::memory_reputation());
+    }
+
+    #[tokio::test]
+    async fn memory_reputation_export_import() {
+        reputation_export_import_test_case(
+            test_utils::memory_reputation(),
+            test_utils::memory_reputation(),
+        );
+    }
 }
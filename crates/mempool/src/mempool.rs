@@ -1,16 +1,36 @@
-use crate::MempoolErrorKind;
+use crate::{audit::AuditLog, MempoolErrorKind, SanityError};
 use dyn_clone::DynClone;
 use ethers::{
     abi::AbiEncode,
     types::{Address, H256, U256},
     utils::{keccak256, to_checksum},
 };
-use parking_lot::RwLock;
-use silius_primitives::{simulation::CodeHash, UserOperation, UserOperationHash};
-use std::sync::Arc;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use silius_primitives::{
+    constants::mempool::NEW_USER_OPERATIONS_CAPACITY, simulation::CodeHash, MempoolStats,
+    UserOperation, UserOperationHash, UserOperationSource,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::broadcast;
+use tracing::error;
 
 pub type MempoolId = H256;
 
+/// Computes the deterministic id of the mempool for the given entry point and chain.
+/// The id is `keccak256(abi.encode(checksum(entry_point)) ++ abi.encode(uint256(chain_id)))`, so
+/// it doesn't depend on the letter case in which the entry point address was provided.
+///
+/// # Arguments
+/// * `ep` - The address of the entry point
+/// * `chain_id` - The chain id
+///
+/// # Returns
+/// * `MempoolId` - The id of the mempool
 pub fn mempool_id(ep: &Address, chain_id: u64) -> MempoolId {
     H256::from_slice(
         keccak256([to_checksum(ep, None).encode(), U256::from(chain_id).encode()].concat())
@@ -39,6 +59,26 @@ pub trait AddRemoveUserOp {
     ///   not found
     /// * `Err(MempoolErrorKind)` - If there are some  internal errors
     fn remove_by_uo_hash(&mut self, uo_hash: &UserOperationHash) -> Result<bool, MempoolErrorKind>;
+    /// Removes multiple [UserOperations](UserOperation) by their hashes
+    ///
+    /// # Arguments
+    /// * `uo_hashes` - The hashes of the [UserOperations](UserOperation) to remove
+    ///
+    /// # Returns
+    /// * `Ok(usize)` - The number of [UserOperations](UserOperation) that were removed
+    /// * `Err(MempoolErrorKind)` - If there are some internal errors
+    fn remove_by_uo_hashes(
+        &mut self,
+        uo_hashes: &[UserOperationHash],
+    ) -> Result<usize, MempoolErrorKind> {
+        let mut removed = 0;
+        for uo_hash in uo_hashes {
+            if self.remove_by_uo_hash(uo_hash)? {
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
 }
 
 impl<T: AddRemoveUserOp> AddRemoveUserOp for Arc<RwLock<T>> {
@@ -49,6 +89,13 @@ impl<T: AddRemoveUserOp> AddRemoveUserOp for Arc<RwLock<T>> {
     fn remove_by_uo_hash(&mut self, uo_hash: &UserOperationHash) -> Result<bool, MempoolErrorKind> {
         self.write().remove_by_uo_hash(uo_hash)
     }
+
+    fn remove_by_uo_hashes(
+        &mut self,
+        uo_hashes: &[UserOperationHash],
+    ) -> Result<usize, MempoolErrorKind> {
+        self.write().remove_by_uo_hashes(uo_hashes)
+    }
 }
 
 /// AddRemoveUserOpHash describe the ability to add and remove user operation hash set
@@ -93,6 +140,26 @@ pub trait AddRemoveUserOpHash {
         address: &Address,
         uo_hash: &UserOperationHash,
     ) -> Result<bool, MempoolErrorKind>;
+
+    /// Removes multiple user operation hashes from their associated addresses.
+    ///
+    /// # Arguments
+    ///
+    /// * `entries` - The pairs of address and user operation hash to be removed.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the removal was successful, otherwise returns an error of type
+    /// `MempoolErrorKind`.
+    fn remove_uo_hashes(
+        &mut self,
+        entries: &[(Address, UserOperationHash)],
+    ) -> Result<(), MempoolErrorKind> {
+        for (address, uo_hash) in entries {
+            self.remove_uo_hash(address, uo_hash)?;
+        }
+        Ok(())
+    }
 }
 
 impl<T: AddRemoveUserOpHash> AddRemoveUserOpHash for Arc<RwLock<T>> {
@@ -111,6 +178,13 @@ impl<T: AddRemoveUserOpHash> AddRemoveUserOpHash for Arc<RwLock<T>> {
     ) -> Result<bool, MempoolErrorKind> {
         self.write().remove_uo_hash(address, uo_hash)
     }
+
+    fn remove_uo_hashes(
+        &mut self,
+        entries: &[(Address, UserOperationHash)],
+    ) -> Result<(), MempoolErrorKind> {
+        self.write().remove_uo_hashes(entries)
+    }
 }
 
 /// Trait representing operations on user operations.
@@ -190,12 +264,24 @@ pub trait UserOperationAddrOp {
     fn get_number_by_address(&self, addr: &Address) -> usize {
         self.get_all_by_address(addr).len()
     }
+
+    /// Retrieves all addresses with the associated user operation hashes.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(Vec<(Address, Vec<UserOperationHash>)>)` containing all addresses and their
+    /// associated user operation hashes, or an `Err(MempoolErrorKind)` if an error occurs.
+    fn get_all_entries(&self) -> Result<Vec<(Address, Vec<UserOperationHash>)>, MempoolErrorKind>;
 }
 
 impl<T: UserOperationAddrOp> UserOperationAddrOp for Arc<RwLock<T>> {
     fn get_all_by_address(&self, addr: &Address) -> Vec<UserOperationHash> {
         self.read().get_all_by_address(addr)
     }
+
+    fn get_all_entries(&self) -> Result<Vec<(Address, Vec<UserOperationHash>)>, MempoolErrorKind> {
+        self.read().get_all_entries()
+    }
 }
 
 /// Trait for managing user operation code hashes in a memory pool.
@@ -272,6 +358,27 @@ pub trait UserOperationCodeHashOp {
     ///   `MempoolErrorKind`.
     fn remove_code_hashes(&mut self, uo_hash: &UserOperationHash)
         -> Result<bool, MempoolErrorKind>;
+
+    /// Removes the code hashes associated with multiple user operation hashes from the memory
+    /// pool.
+    ///
+    /// # Arguments
+    ///
+    /// * `uo_hashes` - The user operation hashes to remove the code hashes for.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the removal was successful, otherwise an `Err` variant is returned with
+    /// a `MempoolErrorKind`.
+    fn remove_code_hashes_batch(
+        &mut self,
+        uo_hashes: &[UserOperationHash],
+    ) -> Result<(), MempoolErrorKind> {
+        for uo_hash in uo_hashes {
+            self.remove_code_hashes(uo_hash)?;
+        }
+        Ok(())
+    }
 }
 
 impl<T: UserOperationCodeHashOp> UserOperationCodeHashOp for Arc<RwLock<T>> {
@@ -300,6 +407,187 @@ impl<T: UserOperationCodeHashOp> UserOperationCodeHashOp for Arc<RwLock<T>> {
     ) -> Result<bool, MempoolErrorKind> {
         self.write().remove_code_hashes(uo_hash)
     }
+
+    fn remove_code_hashes_batch(
+        &mut self,
+        uo_hashes: &[UserOperationHash],
+    ) -> Result<(), MempoolErrorKind> {
+        self.write().remove_code_hashes_batch(uo_hashes)
+    }
+}
+
+/// Metadata of a user operation in the mempool (stored along with it)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserOperationMetadata {
+    /// Source the user operation was submitted from
+    pub source: UserOperationSource,
+    /// Entry point the user operation was added for (None if the mempool isn't scoped to an
+    /// entry point)
+    pub entry_point: Option<Address>,
+    /// Time the user operation was added (seconds since the Unix epoch), so its waiting time is
+    /// kept across restarts
+    pub added_at: u64,
+}
+
+/// Trait for managing the metadata of user operations in a memory pool.
+pub trait UserOperationMetadataOp {
+    /// Retrieves the metadata of the given user operation hash.
+    ///
+    /// # Arguments
+    ///
+    /// * `uo_hash` - The user operation hash to retrieve the metadata for.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(Some(UserOperationMetadata))` if the metadata is found, `Ok(None)` if it's not
+    /// found, or an `Err(MempoolErrorKind)` if an error occurs.
+    fn get_metadata(
+        &self,
+        uo_hash: &UserOperationHash,
+    ) -> Result<Option<UserOperationMetadata>, MempoolErrorKind>;
+
+    /// Sets the metadata of the given user operation hash.
+    ///
+    /// # Arguments
+    ///
+    /// * `uo_hash` - The user operation hash to set the metadata for.
+    /// * `metadata` - The metadata of the user operation.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the metadata was set, otherwise an `Err(MempoolErrorKind)`.
+    fn set_metadata(
+        &mut self,
+        uo_hash: &UserOperationHash,
+        metadata: UserOperationMetadata,
+    ) -> Result<(), MempoolErrorKind>;
+
+    /// Removes the metadata of the given user operation hash.
+    ///
+    /// # Arguments
+    ///
+    /// * `uo_hash` - The user operation hash to remove the metadata for.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(true)` if the metadata was removed, `Ok(false)` if it wasn't found, or an
+    /// `Err(MempoolErrorKind)` if an error occurs.
+    fn remove_metadata(&mut self, uo_hash: &UserOperationHash) -> Result<bool, MempoolErrorKind>;
+
+    /// Removes the metadata of multiple user operation hashes.
+    ///
+    /// # Arguments
+    ///
+    /// * `uo_hashes` - The user operation hashes to remove the metadata for.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the removal was successful, otherwise an `Err(MempoolErrorKind)`.
+    fn remove_metadata_batch(
+        &mut self,
+        uo_hashes: &[UserOperationHash],
+    ) -> Result<(), MempoolErrorKind> {
+        for uo_hash in uo_hashes {
+            self.remove_metadata(uo_hash)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: UserOperationMetadataOp> UserOperationMetadataOp for Arc<RwLock<T>> {
+    fn get_metadata(
+        &self,
+        uo_hash: &UserOperationHash,
+    ) -> Result<Option<UserOperationMetadata>, MempoolErrorKind> {
+        self.read().get_metadata(uo_hash)
+    }
+
+    fn set_metadata(
+        &mut self,
+        uo_hash: &UserOperationHash,
+        metadata: UserOperationMetadata,
+    ) -> Result<(), MempoolErrorKind> {
+        self.write().set_metadata(uo_hash, metadata)
+    }
+
+    fn remove_metadata(&mut self, uo_hash: &UserOperationHash) -> Result<bool, MempoolErrorKind> {
+        self.write().remove_metadata(uo_hash)
+    }
+
+    fn remove_metadata_batch(
+        &mut self,
+        uo_hashes: &[UserOperationHash],
+    ) -> Result<(), MempoolErrorKind> {
+        self.write().remove_metadata_batch(uo_hashes)
+    }
+}
+
+/// Trait for managing the last processed blocks of the entry points (the blocks produced since
+/// then are scanned for entry point events on startup).
+pub trait CheckpointOp {
+    /// Retrieves the last processed block of the given entry point.
+    ///
+    /// # Arguments
+    ///
+    /// * `entry_point` - The entry point to retrieve the last processed block for.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(Some(u64))` if the last processed block is found, `Ok(None)` if it's not
+    /// found, or an `Err(MempoolErrorKind)` if an error occurs.
+    fn get_checkpoint(&self, entry_point: &Address) -> Result<Option<u64>, MempoolErrorKind>;
+
+    /// Sets the last processed block of the given entry point.
+    ///
+    /// # Arguments
+    ///
+    /// * `entry_point` - The entry point to set the last processed block for.
+    /// * `block` - The last processed block.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the last processed block was set, otherwise an `Err(MempoolErrorKind)`.
+    fn set_checkpoint(&mut self, entry_point: &Address, block: u64)
+        -> Result<(), MempoolErrorKind>;
+}
+
+impl<T: CheckpointOp> CheckpointOp for Arc<RwLock<T>> {
+    fn get_checkpoint(&self, entry_point: &Address) -> Result<Option<u64>, MempoolErrorKind> {
+        self.read().get_checkpoint(entry_point)
+    }
+
+    fn set_checkpoint(
+        &mut self,
+        entry_point: &Address,
+        block: u64,
+    ) -> Result<(), MempoolErrorKind> {
+        self.write().set_checkpoint(entry_point, block)
+    }
+}
+
+/// Trait for removing user operations from the tables of a memory pool at once.
+pub trait RemoveBatchOp {
+    /// Removes the user operations along with their entries by sender, entity and entry point and
+    /// their metadata, e.g. in a single database transaction so a crash can't leave the tables
+    /// inconsistent.
+    ///
+    /// # Arguments
+    ///
+    /// * `uo_hashes` - The user operation hashes to remove.
+    /// * `by_sender` - The (sender, user operation hash) entries to remove.
+    /// * `by_entity` - The (entity, user operation hash) entries to remove.
+    /// * `by_entry_point` - The (entry point, user operation hash) entries to remove.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the removal was successful, otherwise an `Err(MempoolErrorKind)`.
+    fn remove_batch(
+        &mut self,
+        uo_hashes: &[UserOperationHash],
+        by_sender: &[(Address, UserOperationHash)],
+        by_entity: &[(Address, UserOperationHash)],
+        by_entry_point: &[(Address, UserOperationHash)],
+    ) -> Result<(), MempoolErrorKind>;
 }
 
 /// A trait for clearing operation.
@@ -347,12 +635,244 @@ impl<T> UserOperationCodeHashAct for T where
 {
 }
 
+pub trait UserOperationMetadataAct:
+    UserOperationMetadataOp + ClearOp + Send + Sync + DynClone
+{
+}
+
+dyn_clone::clone_trait_object!(UserOperationMetadataAct);
+impl<T> UserOperationMetadataAct for T where
+    T: UserOperationMetadataOp + ClearOp + Send + Sync + Clone
+{
+}
+
+pub trait CheckpointAct: CheckpointOp + Send + Sync + DynClone {}
+
+dyn_clone::clone_trait_object!(CheckpointAct);
+impl<T> CheckpointAct for T where T: CheckpointOp + Send + Sync + Clone {}
+
+pub trait RemoveBatchAct: RemoveBatchOp + Send + Sync + DynClone {}
+
+dyn_clone::clone_trait_object!(RemoveBatchAct);
+impl<T> RemoveBatchAct for T where T: RemoveBatchOp + Send + Sync + Clone {}
+
+/// Ordering of the user operations in bundles, used by [Mempool::get_sorted](Mempool::get_sorted)
+/// (e.g., fee-based, MEV-aware or fairness-based ordering)
+pub trait BundleOrdering: Send + Sync {
+    /// Sorts the user operations in the order in which they should be included in the bundle
+    ///
+    /// # Arguments
+    /// * `uos` - The user operations to sort
+    fn sort(&self, uos: &mut [UserOperation]);
+
+    /// Sorts the user operations given the state of the chain and of the mempool (orderings
+    /// which don't depend on it ignore it)
+    ///
+    /// # Arguments
+    /// * `uos` - The user operations to sort
+    /// * `ctx` - The [OrderingContext](OrderingContext)
+    fn sort_with_context(&self, uos: &mut [UserOperation], _ctx: &OrderingContext) {
+        self.sort(uos);
+    }
+}
+
+/// State of the chain and of the mempool the user operations are sorted in
+#[derive(Clone, Debug, Default)]
+pub struct OrderingContext {
+    /// Base fee of the latest block (None if unknown)
+    pub base_fee: Option<U256>,
+    /// Time the user operations have been waiting in the mempool (untracked ones have just been
+    /// added)
+    pub waiting: HashMap<UserOperationHash, Duration>,
+}
+
+/// Default ordering: user operations with a higher effective priority fee
+/// (`min(maxPriorityFeePerGas, maxFeePerGas - baseFee)`, or the raw `maxPriorityFeePerGas` if the
+/// base fee is unknown) first, lower nonce first on ties
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FeeOrdering;
+
+impl BundleOrdering for FeeOrdering {
+    fn sort(&self, uos: &mut [UserOperation]) {
+        self.sort_with_context(uos, &OrderingContext::default());
+    }
+
+    fn sort_with_context(&self, uos: &mut [UserOperation], ctx: &OrderingContext) {
+        uos.sort_by(|a, b| {
+            b.effective_priority_fee(ctx.base_fee)
+                .cmp(&a.effective_priority_fee(ctx.base_fee))
+                .then_with(|| a.nonce.cmp(&b.nonce))
+        });
+    }
+}
+
+/// Fee ordering with aging: the effective priority fee of a user operation grows with the time
+/// it has been waiting in the mempool, so low-fee user operations aren't starved by a steady flow
+/// of higher-fee ones. The boost is capped, so fees still matter (a user operation never outranks
+/// one paying more than `max_boost` above it).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AgingFeeOrdering {
+    /// Boost of the priority fee per second of waiting
+    pub boost_per_second: U256,
+    /// Maximum boost of the priority fee
+    pub max_boost: U256,
+}
+
+impl AgingFeeOrdering {
+    /// Returns the effective priority fee of the user operation boosted by its waiting time
+    pub fn effective_priority_fee(
+        &self,
+        uo: &UserOperation,
+        base_fee: Option<U256>,
+        waiting: Duration,
+    ) -> U256 {
+        let boost = self.boost_per_second.saturating_mul(U256::from(waiting.as_secs()));
+        uo.effective_priority_fee(base_fee).saturating_add(boost.min(self.max_boost))
+    }
+}
+
+impl BundleOrdering for AgingFeeOrdering {
+    fn sort(&self, uos: &mut [UserOperation]) {
+        FeeOrdering.sort(uos);
+    }
+
+    fn sort_with_context(&self, uos: &mut [UserOperation], ctx: &OrderingContext) {
+        let fee = |uo: &UserOperation| {
+            let waiting = ctx.waiting.get(&uo.hash).copied().unwrap_or_default();
+            self.effective_priority_fee(uo, ctx.base_fee, waiting)
+        };
+        uos.sort_by(|a, b| fee(b).cmp(&fee(a)).then_with(|| a.nonce.cmp(&b.nonce)));
+    }
+}
+
+/// Discrepancies between the user operations and the secondary indexes (by sender, by entity and
+/// by entry point)
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MempoolConsistencyReport {
+    /// Number of user operations in the mempool
+    pub user_operations: usize,
+    /// Index entries by sender missing for user operations in the mempool
+    pub missing_by_sender: Vec<(Address, UserOperationHash)>,
+    /// Index entries by sender not matching any user operation in the mempool
+    pub stale_by_sender: Vec<(Address, UserOperationHash)>,
+    /// Index entries by entity missing for user operations in the mempool
+    pub missing_by_entity: Vec<(Address, UserOperationHash)>,
+    /// Index entries by entity not matching any user operation in the mempool
+    pub stale_by_entity: Vec<(Address, UserOperationHash)>,
+    /// Index entries by entry point missing for user operations in the mempool
+    pub missing_by_entry_point: Vec<(Address, UserOperationHash)>,
+    /// Index entries by entry point not matching any user operation in the mempool
+    pub stale_by_entry_point: Vec<(Address, UserOperationHash)>,
+    /// Whether the indexes were rebuilt
+    pub repaired: bool,
+}
+
+impl MempoolConsistencyReport {
+    /// Whether the indexes are consistent with the user operations
+    pub fn is_consistent(&self) -> bool {
+        self.missing_by_sender.is_empty() &&
+            self.stale_by_sender.is_empty() &&
+            self.missing_by_entity.is_empty() &&
+            self.stale_by_entity.is_empty() &&
+            self.missing_by_entry_point.is_empty() &&
+            self.stale_by_entry_point.is_empty()
+    }
+}
+
+/// Entries (address, user operation hash) of an index of the mempool
+type IndexEntries = Vec<(Address, UserOperationHash)>;
+
+/// Compares the expected index entries with the actual ones
+///
+/// # Returns
+/// The missing and the stale index entries, or an error if the index can't be read
+fn index_discrepancies(
+    expected: &HashSet<(Address, UserOperationHash)>,
+    index: &dyn UserOperationAddrAct,
+) -> Result<(IndexEntries, IndexEntries), MempoolErrorKind> {
+    let actual: HashSet<(Address, UserOperationHash)> = index
+        .get_all_entries()?
+        .into_iter()
+        .flat_map(|(addr, uo_hashes)| uo_hashes.into_iter().map(move |uo_hash| (addr, uo_hash)))
+        .collect();
+
+    let mut missing: Vec<_> = expected.difference(&actual).cloned().collect();
+    let mut stale: Vec<_> = actual.difference(expected).cloned().collect();
+    missing.sort();
+    stale.sort();
+    Ok((missing, stale))
+}
+
+/// User operation which isn't valid yet, held until its `validAfter` passes
+#[derive(Clone, Debug)]
+pub struct DelayedUserOperation {
+    pub user_operation: UserOperation,
+    pub source: UserOperationSource,
+    pub code_hashes: Option<Vec<CodeHash>>,
+    pub valid_after: U256,
+}
+
+/// User operation whose nonce is ahead of the next one expected for the sender, held until the
+/// nonce gap is filled
+#[derive(Clone, Debug)]
+pub struct GappedUserOperation {
+    pub user_operation: UserOperation,
+    pub source: UserOperationSource,
+    pub code_hashes: Option<Vec<CodeHash>>,
+    pub held_at: Instant,
+}
+
+/// User operation added to the mempool (sent to the subscribers of the mempool)
+#[derive(Clone, Debug)]
+pub struct NewUserOperation {
+    pub user_operation: UserOperation,
+    pub entry_point: Address,
+    /// Hash of the user operation cancelled by this one (if it's a cancellation)
+    pub cancels: Option<UserOperationHash>,
+}
+
 #[derive(Clone)]
 pub struct Mempool {
     user_operations: Box<dyn UserOperationAct>,
     user_operations_by_sender: Box<dyn UserOperationAddrAct>,
     user_operations_by_entity: Box<dyn UserOperationAddrAct>,
     user_operations_code_hashes: Box<dyn UserOperationCodeHashAct>,
+    // User operations forced into the next bundle regardless of fee ordering
+    pinned: Arc<RwLock<HashSet<UserOperationHash>>>,
+    // Serializes the additions of user operations, so the cap of the mempool is checked, the
+    // user operations are evicted and the new one is added at once (shared by the mempools of all
+    // entry points)
+    admission: Arc<Mutex<()>>,
+    // Metadata of the user operations, e.g. their sources (untracked ones are considered to be
+    // submitted via RPC)
+    user_operations_metadata: Box<dyn UserOperationMetadataAct>,
+    // Last processed blocks of the entry points (not cleared along with the user operations)
+    checkpoints: Box<dyn CheckpointAct>,
+    // Removal of user operations from all the tables at once (None if each table is updated on
+    // its own)
+    batch_remover: Option<Box<dyn RemoveBatchAct>>,
+    // Hashes of the user operations by the entry point they were added for (the storage may be
+    // shared by the mempools of several entry points)
+    user_operations_by_entry_point: Box<dyn UserOperationAddrAct>,
+    // Entry point of this mempool: only the user operations added for it are visible (all of
+    // them are if None)
+    entry_point: Option<Address>,
+    // User operations which aren't valid yet (not considered for bundles)
+    delayed: Arc<RwLock<HashMap<UserOperationHash, DelayedUserOperation>>>,
+    // User operations held until their nonce gap is filled (not considered for bundles)
+    gapped: Arc<RwLock<HashMap<UserOperationHash, GappedUserOperation>>>,
+    // Maximum number of user operations per sender for the given source
+    max_uos_per_sender: HashMap<UserOperationSource, usize>,
+    // Audit log of the admitted user operations (None if not enabled)
+    audit_log: Option<AuditLog>,
+    // Ordering of the user operations in bundles
+    ordering: Arc<dyn BundleOrdering>,
+    // Whether user operations not covering the base fee are still considered for bundles (the
+    // subsidized bundler pays the difference)
+    subsidized: bool,
+    // Notifies the subscribers of the user operations added to the mempool
+    new_uos: broadcast::Sender<NewUserOperation>,
 }
 
 impl Mempool {
@@ -367,10 +887,140 @@ impl Mempool {
             user_operations_by_sender,
             user_operations_by_entity,
             user_operations_code_hashes,
+            pinned: Default::default(),
+            admission: Default::default(),
+            user_operations_metadata: Box::new(Arc::new(RwLock::new(HashMap::<
+                UserOperationHash,
+                UserOperationMetadata,
+            >::new()))),
+            checkpoints: Box::new(Arc::new(RwLock::new(HashMap::<Address, u64>::new()))),
+            batch_remover: None,
+            user_operations_by_entry_point: Box::new(Arc::new(RwLock::new(HashMap::<
+                Address,
+                HashSet<UserOperationHash>,
+            >::new()))),
+            entry_point: None,
+            delayed: Default::default(),
+            gapped: Default::default(),
+            max_uos_per_sender: HashMap::new(),
+            audit_log: None,
+            ordering: Arc::new(FeeOrdering),
+            subsidized: false,
+            new_uos: broadcast::channel(NEW_USER_OPERATIONS_CAPACITY).0,
         }
     }
 
+    /// Scopes the mempool to the given entry point: the user operations are recorded as added for
+    /// it and the ones added for other entry points (sharing the storage) aren't visible
+    pub fn with_entry_point(mut self, entry_point: Address) -> Self {
+        self.entry_point = Some(entry_point);
+        self
+    }
+
+    /// Returns the entry point the mempool is scoped to (None if it isn't scoped)
+    pub fn entry_point(&self) -> Option<Address> {
+        self.entry_point
+    }
+
+    /// Replaces the default (in-memory) storage of the metadata of the user operations, e.g. with
+    /// a database table so it's kept along with the user operations across restarts
+    pub fn with_metadata(mut self, metadata: Box<dyn UserOperationMetadataAct>) -> Self {
+        self.user_operations_metadata = metadata;
+        self
+    }
+
+    /// Replaces the default (in-memory) index of the user operations by entry point, e.g. with a
+    /// database table so each user operation is still only visible to its entry point after a
+    /// restart
+    pub fn with_entry_point_index(
+        mut self,
+        user_operations_by_entry_point: Box<dyn UserOperationAddrAct>,
+    ) -> Self {
+        self.user_operations_by_entry_point = user_operations_by_entry_point;
+        self
+    }
+
+    /// Replaces the default (in-memory) storage of the last processed blocks, e.g. with a database
+    /// table so the blocks produced during downtime are scanned after a restart
+    pub fn with_checkpoints(mut self, checkpoints: Box<dyn CheckpointAct>) -> Self {
+        self.checkpoints = checkpoints;
+        self
+    }
+
+    /// Removes the user operations included on chain from all the tables at once (e.g., in a
+    /// single database transaction) instead of updating each table on its own
+    pub fn with_batch_remover(mut self, batch_remover: Box<dyn RemoveBatchAct>) -> Self {
+        self.batch_remover = Some(batch_remover);
+        self
+    }
+
+    /// Limits the number of user operations per sender submitted from the given source
+    pub fn with_max_uos_per_sender(mut self, source: UserOperationSource, max: usize) -> Self {
+        self.max_uos_per_sender.insert(source, max);
+        self
+    }
+
+    /// Records every user operation admitted via [add_with_source](Mempool::add_with_source) to
+    /// the audit log
+    pub fn with_audit_log(mut self, audit_log: AuditLog) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
+
+    /// Replaces the default [FeeOrdering](FeeOrdering) of the user operations in bundles
+    pub fn with_ordering(mut self, ordering: impl BundleOrdering + 'static) -> Self {
+        self.ordering = Arc::new(ordering);
+        self
+    }
+
+    /// Keeps the user operations whose max fee doesn't cover the base fee in bundles, as the
+    /// subsidized bundler pays the difference
+    pub fn with_subsidized(mut self, subsidized: bool) -> Self {
+        self.subsidized = subsidized;
+        self
+    }
+
+    /// Subscribes to the user operations added to the mempool (the subscription ends once the
+    /// receiver is dropped)
+    pub fn subscribe(&self) -> broadcast::Receiver<NewUserOperation> {
+        self.new_uos.subscribe()
+    }
+
+    /// Returns the number of the subscribers of the mempool
+    pub fn subscriber_count(&self) -> usize {
+        self.new_uos.receiver_count()
+    }
+
+    /// Returns the last processed block of the entry point (None if unknown)
+    pub fn get_checkpoint(&self, entry_point: &Address) -> Result<Option<u64>, MempoolErrorKind> {
+        self.checkpoints.get_checkpoint(entry_point)
+    }
+
+    /// Records the last processed block of the entry point
+    pub fn set_checkpoint(
+        &mut self,
+        entry_point: &Address,
+        block: u64,
+    ) -> Result<(), MempoolErrorKind> {
+        self.checkpoints.set_checkpoint(entry_point, block)
+    }
+
+    /// Notifies the subscribers of a user operation added to the mempool
+    pub fn notify(&self, new_uo: NewUserOperation) {
+        // there may be no subscribers
+        let _ = self.new_uos.send(new_uo);
+    }
+
     pub fn add(&mut self, uo: UserOperation) -> Result<UserOperationHash, MempoolErrorKind> {
+        self.add_with_metadata(uo, UserOperationMetadata::default())
+    }
+
+    // Adds the UserOperation along with its metadata (recording the entry point of the mempool)
+    fn add_with_metadata(
+        &mut self,
+        uo: UserOperation,
+        metadata: UserOperationMetadata,
+    ) -> Result<UserOperationHash, MempoolErrorKind> {
         let (sender, factory, paymaster) = uo.get_entities();
         let uo_hash = uo.hash;
         self.user_operations.add(uo)?;
@@ -381,9 +1031,174 @@ impl Mempool {
         if let Some(paymaster) = paymaster {
             self.user_operations_by_entity.add(&paymaster, uo_hash)?;
         }
+        if let Some(entry_point) = self.entry_point {
+            self.user_operations_by_entry_point.add(&entry_point, uo_hash)?;
+        }
+        // a user operation added again keeps its waiting time
+        let added_at = match self.user_operations_metadata.get_metadata(&uo_hash)? {
+            Some(prev) => prev.added_at,
+            None => SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+        };
+        self.user_operations_metadata.set_metadata(
+            &uo_hash,
+            UserOperationMetadata { entry_point: self.entry_point, added_at, ..metadata },
+        )?;
+        Ok(uo_hash)
+    }
+
+    /// Returns the entry point the user operation was added for (None if it isn't in the mempool
+    /// or wasn't added for an entry point)
+    pub fn get_entry_point(&self, uo_hash: &UserOperationHash) -> Option<Address> {
+        self.user_operations_metadata
+            .get_metadata(uo_hash)
+            .ok()
+            .flatten()
+            .and_then(|metadata| metadata.entry_point)
+    }
+
+    // Whether the UserOperation was added for the entry point of this mempool
+    fn is_visible(&self, uo_hash: &UserOperationHash) -> bool {
+        self.entry_point.is_none() || self.get_entry_point(uo_hash) == self.entry_point
+    }
+
+    /// Checks the limit of the user operations per sender submitted from the given source
+    /// (including the ones held until their validAfter passes). The user operations of the
+    /// sender with the same nonce don't count, as they are replaced.
+    pub fn check_max_uos_per_sender(
+        &self,
+        uo: &UserOperation,
+        source: UserOperationSource,
+    ) -> Result<(), MempoolErrorKind> {
+        if let Some(max) = self.max_uos_per_sender.get(&source) {
+            let count = self
+                .get_all_by_sender(&uo.sender)
+                .iter()
+                .chain(self.get_delayed_by_sender(&uo.sender).iter())
+                .filter(|uo_prev| uo_prev.nonce != uo.nonce)
+                .filter(|uo_prev| self.get_source(&uo_prev.hash) == source)
+                .count();
+            if count >= *max {
+                return Err(SanityError::Sender {
+                    inner: format!(
+                        "Sender {:?} has too many user operations ({source:?}) in the mempool",
+                        uo.sender
+                    ),
+                }
+                .into());
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn add_with_source(
+        &mut self,
+        uo: UserOperation,
+        source: UserOperationSource,
+    ) -> Result<UserOperationHash, MempoolErrorKind> {
+        let admission = self.admission.clone();
+        let _admission = admission.lock();
+        self.insert_with_source(uo, source)
+    }
+
+    /// Adds the user operation if there is room for it in the mempool of this entry point (at
+    /// most `max_user_operations`), evicting the user operations with the lowest
+    /// `max_priority_fee_per_gas` otherwise (see
+    /// [get_eviction_candidates](Mempool::get_eviction_candidates)). The user operations are
+    /// counted, evicted and added in one pass under the admission lock, so concurrent additions
+    /// can't exceed the cap.
+    ///
+    /// # Returns
+    /// The hash of the added user operation and the hashes of the evicted ones (an error if the
+    /// user operation doesn't pay more than the evicted ones)
+    pub fn add_with_cap(
+        &mut self,
+        uo: UserOperation,
+        source: UserOperationSource,
+        max_user_operations: usize,
+    ) -> Result<(UserOperationHash, Vec<UserOperationHash>), MempoolErrorKind> {
+        let admission = self.admission.clone();
+        let _admission = admission.lock();
+
+        // checked before anything is evicted
+        self.check_max_uos_per_sender(&uo, source)?;
+
+        let (count, candidates) = self.get_eviction_candidates(&uo.sender)?;
+        let excess = (count + 1).saturating_sub(max_user_operations);
+        let evicted = candidates.into_iter().take(excess).collect::<Vec<_>>();
+        if evicted.len() < excess ||
+            evicted.iter().any(|ev| ev.max_priority_fee_per_gas >= uo.max_priority_fee_per_gas)
+        {
+            return Err(MempoolErrorKind::MempoolFull {
+                max_priority_fee_per_gas: uo.max_priority_fee_per_gas,
+            });
+        }
+
+        let evicted = evicted.into_iter().map(|ev| ev.hash).collect::<Vec<_>>();
+        for uo_hash in evicted.iter() {
+            self.remove(uo_hash)?;
+        }
+
+        Ok((self.insert_with_source(uo, source)?, evicted))
+    }
+
+    fn insert_with_source(
+        &mut self,
+        uo: UserOperation,
+        source: UserOperationSource,
+    ) -> Result<UserOperationHash, MempoolErrorKind> {
+        self.check_max_uos_per_sender(&uo, source)?;
+
+        let sender = uo.sender;
+        let uo_hash =
+            self.add_with_metadata(uo, UserOperationMetadata { source, ..Default::default() })?;
+
+        if let Some(audit_log) = &self.audit_log {
+            if let Err(err) = audit_log.record(&uo_hash, &sender, source) {
+                error!("Failed to write user operation {uo_hash:?} to audit log: {err:?}");
+            }
+        }
+
         Ok(uo_hash)
     }
 
+    pub fn get_source(&self, uo_hash: &UserOperationHash) -> UserOperationSource {
+        self.user_operations_metadata
+            .get_metadata(uo_hash)
+            .ok()
+            .flatten()
+            .map(|metadata| metadata.source)
+            .or_else(|| self.gapped.read().get(uo_hash).map(|gapped| gapped.source))
+            .or_else(|| self.delayed.read().get(uo_hash).map(|delayed| delayed.source))
+            .unwrap_or_default()
+    }
+
+    pub fn get_number_by_sender_and_source(
+        &self,
+        addr: &Address,
+        source: UserOperationSource,
+    ) -> usize {
+        self.user_operations_by_sender
+            .get_all_by_address(addr)
+            .iter()
+            .filter(|uo_hash| self.get_source(uo_hash) == source)
+            .count()
+    }
+
+    pub fn get_stats(&self) -> Result<MempoolStats, MempoolErrorKind> {
+        let mut stats = MempoolStats::default();
+        for uo in self.get_all()? {
+            match self.get_source(&uo.hash) {
+                UserOperationSource::Rpc => stats.rpc += 1,
+                UserOperationSource::P2p => stats.p2p += 1,
+            }
+        }
+        Ok(stats)
+    }
+
     pub fn get(
         &self,
         uo_hash: &UserOperationHash,
@@ -391,15 +1206,79 @@ impl Mempool {
         self.user_operations.get_by_uo_hash(uo_hash)
     }
 
+    // Whether the UserOperation is in the mempool (or held in the delayed or gapped queue)
+    pub fn contains(&self, uo_hash: &UserOperationHash) -> Result<bool, MempoolErrorKind> {
+        Ok(self.delayed.read().contains_key(uo_hash) ||
+            self.gapped.read().contains_key(uo_hash) ||
+            self.get(uo_hash)?.is_some())
+    }
+
     pub fn get_all_by_sender(&self, addr: &Address) -> Vec<UserOperation> {
         let uos_by_sender = self.user_operations_by_sender.get_all_by_address(addr);
         uos_by_sender
             .iter()
+            .filter(|uo_hash| self.is_visible(uo_hash))
             .flat_map(|uo_hash| self.user_operations.get_by_uo_hash(uo_hash))
             .flatten()
             .collect()
     }
 
+    /// Returns a page of the user operations sorted by sender and nonce, starting after the
+    /// `(sender, nonce)` cursor, along with the cursor of the next page (None if this is the last
+    /// one) and the number of user operations in the mempool. As the cursor is the last user
+    /// operation of the previous page, the pages don't shift when user operations are added or
+    /// removed in between. Only the user operations of the page are read, the others are just
+    /// counted in the index of the senders.
+    pub fn get_page(
+        &self,
+        after: Option<(Address, U256)>,
+        limit: Option<usize>,
+    ) -> Result<(Vec<UserOperation>, Option<(Address, U256)>, usize), MempoolErrorKind> {
+        let mut senders = self.user_operations_by_sender.get_all_entries()?;
+        senders.sort_by_key(|(sender, _)| *sender);
+        if let Some(entry_point) = self.entry_point {
+            let visible = self
+                .user_operations_by_entry_point
+                .get_all_by_address(&entry_point)
+                .into_iter()
+                .collect::<HashSet<_>>();
+            for (_, uo_hashes) in senders.iter_mut() {
+                uo_hashes.retain(|uo_hash| visible.contains(uo_hash));
+            }
+        }
+        senders.retain(|(_, uo_hashes)| !uo_hashes.is_empty());
+        let total = senders.iter().map(|(_, uo_hashes)| uo_hashes.len()).sum();
+
+        let mut uos = vec![];
+        let mut more = false;
+        for (sender, uo_hashes) in senders {
+            if after.is_some_and(|(after, _)| sender < after) {
+                continue;
+            }
+            if limit.is_some_and(|limit| uos.len() >= limit) {
+                more = true;
+                break;
+            }
+
+            let mut uos_by_sender = vec![];
+            for uo_hash in uo_hashes.iter() {
+                uos_by_sender.extend(self.user_operations.get_by_uo_hash(uo_hash)?);
+            }
+            if let Some((_, nonce)) = after.filter(|(after, _)| *after == sender) {
+                uos_by_sender.retain(|uo| uo.nonce > nonce);
+            }
+            uos_by_sender.sort_by_key(|uo| uo.nonce);
+            uos.extend(uos_by_sender);
+        }
+        if let Some(limit) = limit {
+            more |= uos.len() > limit;
+            uos.truncate(limit);
+        }
+        let next = uos.last().filter(|_| more).map(|uo| (uo.sender, uo.nonce));
+
+        Ok((uos, next, total))
+    }
+
     pub fn get_number_by_sender(&self, addr: &Address) -> usize {
         self.user_operations_by_sender.get_number_by_address(addr)
     }
@@ -408,12 +1287,40 @@ impl Mempool {
         self.user_operations_by_entity.get_number_by_address(addr)
     }
 
+    /// Returns the number of user operations in the mempool (of this entry point) and the user
+    /// operations evicted first once it's full, ordered by the lowest `max_priority_fee_per_gas`.
+    /// Only the last (highest nonce) user operations of the senders are candidates, so the
+    /// eviction doesn't leave a nonce gap. Pinned user operations and the user operations of the
+    /// given sender (which can only be replaced) aren't evicted.
+    pub fn get_eviction_candidates(
+        &self,
+        sender: &Address,
+    ) -> Result<(usize, Vec<UserOperation>), MempoolErrorKind> {
+        let pinned = self.pinned.read();
+
+        let uos = self.get_all()?;
+        let count = uos.len();
+        let mut last_by_sender = HashMap::<Address, UserOperation>::new();
+        for uo in uos {
+            if uo.sender == *sender || pinned.contains(&uo.hash) {
+                continue;
+            }
+            match last_by_sender.get(&uo.sender) {
+                Some(last) if last.nonce >= uo.nonce => (),
+                _ => {
+                    last_by_sender.insert(uo.sender, uo);
+                }
+            }
+        }
+
+        let mut candidates = last_by_sender.into_values().collect::<Vec<_>>();
+        candidates.sort_by_key(|uo| uo.max_priority_fee_per_gas);
+        Ok((count, candidates))
+    }
+
     pub fn get_prev_by_sender(&self, uo: &UserOperation) -> Option<UserOperation> {
-        self.user_operations_by_sender
-            .get_all_by_address(&uo.sender)
-            .iter()
-            .flat_map(|uo_hash| self.get(uo_hash))
-            .flatten()
+        self.get_all_by_sender(&uo.sender)
+            .into_iter()
             .filter(|uo_prev| uo_prev.nonce == uo.nonce)
             .max_by_key(|uo_prev| uo_prev.max_priority_fee_per_gas)
     }
@@ -438,6 +1345,12 @@ impl Mempool {
     }
 
     pub fn remove(&mut self, uo_hash: &UserOperationHash) -> Result<bool, MempoolErrorKind> {
+        if self.delayed.write().remove(uo_hash).is_some() ||
+            self.gapped.write().remove(uo_hash).is_some()
+        {
+            return Ok(true);
+        }
+
         let uo = if let Some(user_op) = self.user_operations.get_by_uo_hash(uo_hash)? {
             user_op
         } else {
@@ -445,6 +1358,7 @@ impl Mempool {
         };
 
         let (sender, factory, paymaster) = uo.get_entities();
+        let entry_point = self.get_entry_point(uo_hash);
 
         self.user_operations.remove_by_uo_hash(uo_hash)?;
 
@@ -458,11 +1372,70 @@ impl Mempool {
             self.user_operations_by_entity.remove_uo_hash(&paymaster, uo_hash)?;
         }
 
+        if let Some(entry_point) = entry_point {
+            self.user_operations_by_entry_point.remove_uo_hash(&entry_point, uo_hash)?;
+        }
+
         self.user_operations_code_hashes.remove_code_hashes(uo_hash)?;
 
+        self.user_operations_metadata.remove_metadata(uo_hash)?;
+
+        self.pinned.write().remove(uo_hash);
+
         Ok(true)
     }
 
+    /// Removes the user operations included on chain (e.g. in a mined bundle) at once. With a
+    /// batch remover (database), the user operations are removed from all the tables in a single
+    /// write transaction (the code hashes, only needed during simulation, are removed after).
+    ///
+    /// # Returns
+    /// The number of user operations removed (hashes not found in the mempool are skipped)
+    pub fn remove_included_batch(
+        &mut self,
+        uo_hashes: &[UserOperationHash],
+    ) -> Result<usize, MempoolErrorKind> {
+        let mut hashes = Vec::with_capacity(uo_hashes.len());
+        let mut by_sender = Vec::with_capacity(uo_hashes.len());
+        let mut by_entity = Vec::new();
+        let mut by_entry_point = Vec::new();
+
+        for uo_hash in uo_hashes {
+            if let Some(uo) = self.user_operations.get_by_uo_hash(uo_hash)? {
+                let (sender, factory, paymaster) = uo.get_entities();
+                hashes.push(uo.hash);
+                by_sender.push((sender, uo.hash));
+                by_entity.extend(factory.into_iter().chain(paymaster).map(|addr| (addr, uo.hash)));
+                by_entry_point.extend(self.get_entry_point(&uo.hash).map(|ep| (ep, uo.hash)));
+            }
+        }
+
+        if hashes.is_empty() {
+            return Ok(0);
+        }
+
+        match self.batch_remover {
+            Some(ref mut batch_remover) => {
+                batch_remover.remove_batch(&hashes, &by_sender, &by_entity, &by_entry_point)?
+            }
+            None => {
+                self.user_operations.remove_by_uo_hashes(&hashes)?;
+                self.user_operations_by_sender.remove_uo_hashes(&by_sender)?;
+                self.user_operations_by_entity.remove_uo_hashes(&by_entity)?;
+                self.user_operations_by_entry_point.remove_uo_hashes(&by_entry_point)?;
+                self.user_operations_metadata.remove_metadata_batch(&hashes)?;
+            }
+        }
+        self.user_operations_code_hashes.remove_code_hashes_batch(&hashes)?;
+
+        let mut pinned = self.pinned.write();
+        for uo_hash in hashes.iter() {
+            pinned.remove(uo_hash);
+        }
+
+        Ok(hashes.len())
+    }
+
     pub fn remove_by_entity(&mut self, entity: &Address) -> Result<(), MempoolErrorKind> {
         let uos = self.user_operations_by_entity.get_all_by_address(entity);
 
@@ -473,13 +1446,204 @@ impl Mempool {
         Ok(())
     }
 
-    // Get UserOperations sorted by max_priority_fee_per_gas without dup sender
-    pub fn get_sorted(&self) -> Result<Vec<UserOperation>, MempoolErrorKind> {
-        self.user_operations.get_sorted()
+    // Get UserOperations sorted by the bundle ordering (effective priority fee given the base fee
+    // by default, aged by the time they have been waiting with AgingFeeOrdering)
+    // UserOperations whose max_fee_per_gas doesn't cover the base fee are skipped (unless the
+    // bundler is subsidized)
+    // Pinned UserOperations are put in front (keeping their relative order)
+    pub fn get_sorted(
+        &self,
+        base_fee: Option<U256>,
+    ) -> Result<Vec<UserOperation>, MempoolErrorKind> {
+        let mut uos = self.get_all()?;
+        if let Some(base_fee) = base_fee.filter(|_| !self.subsidized) {
+            uos.retain(|uo| uo.max_fee_per_gas >= base_fee);
+        }
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let waiting = uos
+            .iter()
+            .filter_map(|uo| {
+                let metadata = self.user_operations_metadata.get_metadata(&uo.hash).ok()??;
+                Some((uo.hash, now.saturating_sub(Duration::from_secs(metadata.added_at))))
+            })
+            .collect();
+        self.ordering.sort_with_context(&mut uos, &OrderingContext { base_fee, waiting });
+
+        // pinned user operations go first, keeping the order of the sort
+        let pinned = self.pinned.read();
+        if !pinned.is_empty() {
+            let (mut pinned_uos, rest): (Vec<_>, Vec<_>) =
+                uos.into_iter().partition(|uo| pinned.contains(&uo.hash));
+            pinned_uos.extend(rest);
+            return Ok(pinned_uos);
+        }
+
+        Ok(uos)
+    }
+
+    // Replaces the set of UserOperations forced into the next bundle
+    pub fn set_pinned(&mut self, uo_hashes: Vec<UserOperationHash>) {
+        *self.pinned.write() = uo_hashes.into_iter().collect();
+    }
+
+    pub fn get_pinned(&self) -> Vec<UserOperationHash> {
+        self.pinned.read().iter().cloned().collect()
     }
 
+    // Only the UserOperations of the entry point are read if the mempool is scoped to one
     pub fn get_all(&self) -> Result<Vec<UserOperation>, MempoolErrorKind> {
-        self.user_operations.get_all()
+        match self.entry_point {
+            Some(entry_point) => {
+                let mut uos = vec![];
+                for uo_hash in self.user_operations_by_entry_point.get_all_by_address(&entry_point)
+                {
+                    uos.extend(self.user_operations.get_by_uo_hash(&uo_hash)?);
+                }
+                Ok(uos)
+            }
+            None => self.user_operations.get_all(),
+        }
+    }
+
+    // Holds a UserOperation which isn't valid yet until its validAfter passes
+    pub fn add_delayed(&mut self, delayed: DelayedUserOperation) -> UserOperationHash {
+        let uo_hash = delayed.user_operation.hash;
+        self.delayed.write().insert(uo_hash, delayed);
+        uo_hash
+    }
+
+    pub fn get_delayed(&self) -> Vec<DelayedUserOperation> {
+        self.delayed.read().values().cloned().collect()
+    }
+
+    pub fn get_delayed_by_sender(&self, sender: &Address) -> Vec<UserOperation> {
+        self.delayed
+            .read()
+            .values()
+            .filter(|d| d.user_operation.sender == *sender)
+            .map(|d| d.user_operation.clone())
+            .collect()
+    }
+
+    pub fn get_delayed_by_nonce(&self, sender: &Address, nonce: U256) -> Option<UserOperation> {
+        self.get_delayed_by_sender(sender).into_iter().find(|uo| uo.nonce == nonce)
+    }
+
+    // Takes out the delayed UserOperations whose validAfter passed at the given timestamp
+    pub fn take_delayed(&mut self, timestamp: U256) -> Vec<DelayedUserOperation> {
+        let mut delayed = self.delayed.write();
+        let uo_hashes = delayed
+            .iter()
+            .filter(|(_, d)| d.valid_after <= timestamp)
+            .map(|(uo_hash, _)| *uo_hash)
+            .collect::<Vec<_>>();
+        uo_hashes.iter().filter_map(|uo_hash| delayed.remove(uo_hash)).collect()
+    }
+
+    // Holds a UserOperation with a nonce gap until the UserOperations filling the gap are added
+    pub fn add_gapped(&mut self, gapped: GappedUserOperation) -> UserOperationHash {
+        let uo_hash = gapped.user_operation.hash;
+        self.gapped.write().insert(uo_hash, gapped);
+        uo_hash
+    }
+
+    pub fn get_gapped(&self) -> Vec<GappedUserOperation> {
+        self.gapped.read().values().cloned().collect()
+    }
+
+    // Returns the held UserOperation with the given sender and nonce
+    pub fn get_gapped_by_nonce(&self, sender: &Address, nonce: U256) -> Option<UserOperation> {
+        self.gapped
+            .read()
+            .values()
+            .find(|g| g.user_operation.sender == *sender && g.user_operation.nonce == nonce)
+            .map(|g| g.user_operation.clone())
+    }
+
+    // Takes out the held UserOperation with the given sender and nonce (its gap was filled)
+    pub fn take_gapped(&mut self, sender: &Address, nonce: U256) -> Option<GappedUserOperation> {
+        let mut gapped = self.gapped.write();
+        let uo_hash = gapped
+            .iter()
+            .find(|(_, g)| g.user_operation.sender == *sender && g.user_operation.nonce == nonce)
+            .map(|(uo_hash, _)| *uo_hash)?;
+        gapped.remove(&uo_hash)
+    }
+
+    // Drops the UserOperations held for longer than the given time
+    pub fn remove_expired_gapped(&mut self, ttl: Duration) -> Vec<UserOperationHash> {
+        let mut gapped = self.gapped.write();
+        let uo_hashes = gapped
+            .iter()
+            .filter(|(_, g)| g.held_at.elapsed() >= ttl)
+            .map(|(uo_hash, _)| *uo_hash)
+            .collect::<Vec<_>>();
+        uo_hashes.into_iter().filter(|uo_hash| gapped.remove(uo_hash).is_some()).collect()
+    }
+
+    /// Checks that the secondary indexes (by sender, by entity and by entry point) are consistent
+    /// with the user operations and their metadata (they can drift apart e.g. after a crash).
+    ///
+    /// # Arguments
+    /// * `repair` - Whether to rebuild the inconsistent indexes from the user operations
+    ///
+    /// # Returns
+    /// The [MempoolConsistencyReport](MempoolConsistencyReport) (before the repair)
+    pub fn check_consistency(
+        &mut self,
+        repair: bool,
+    ) -> Result<MempoolConsistencyReport, MempoolErrorKind> {
+        let uos = self.user_operations.get_all()?;
+
+        let mut by_sender = HashSet::new();
+        let mut by_entity = HashSet::new();
+        let mut by_entry_point = HashSet::new();
+        for uo in uos.iter() {
+            let (sender, factory, paymaster) = uo.get_entities();
+            by_sender.insert((sender, uo.hash));
+            by_entity.extend(factory.into_iter().chain(paymaster).map(|addr| (addr, uo.hash)));
+            by_entry_point.extend(self.get_entry_point(&uo.hash).map(|ep| (ep, uo.hash)));
+        }
+
+        let (missing_by_sender, stale_by_sender) =
+            index_discrepancies(&by_sender, self.user_operations_by_sender.as_ref())?;
+        let (missing_by_entity, stale_by_entity) =
+            index_discrepancies(&by_entity, self.user_operations_by_entity.as_ref())?;
+        let (missing_by_entry_point, stale_by_entry_point) =
+            index_discrepancies(&by_entry_point, self.user_operations_by_entry_point.as_ref())?;
+
+        let mut report = MempoolConsistencyReport {
+            user_operations: uos.len(),
+            missing_by_sender,
+            stale_by_sender,
+            missing_by_entity,
+            stale_by_entity,
+            missing_by_entry_point,
+            stale_by_entry_point,
+            repaired: false,
+        };
+
+        if repair && !report.is_consistent() {
+            self.user_operations_by_sender.clear();
+            for (sender, uo_hash) in by_sender {
+                self.user_operations_by_sender.add(&sender, uo_hash)?;
+            }
+
+            self.user_operations_by_entity.clear();
+            for (entity, uo_hash) in by_entity {
+                self.user_operations_by_entity.add(&entity, uo_hash)?;
+            }
+
+            self.user_operations_by_entry_point.clear();
+            for (entry_point, uo_hash) in by_entry_point {
+                self.user_operations_by_entry_point.add(&entry_point, uo_hash)?;
+            }
+
+            report.repaired = true;
+        }
+
+        Ok(report)
     }
 
     pub fn clear(&mut self) {
@@ -487,5 +1651,138 @@ impl Mempool {
         self.user_operations_by_sender.clear();
         self.user_operations_by_entity.clear();
         self.user_operations_code_hashes.clear();
+        self.user_operations_metadata.clear();
+        self.user_operations_by_entry_point.clear();
+        self.pinned.write().clear();
+        self.delayed.write().clear();
+        self.gapped.write().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use silius_primitives::UserOperationSigned;
+    use std::str::FromStr;
+
+    const ENTRY_POINT_V06: &str = "0x5FF137D4b0FDCD49DcA30c7CF57E578a026d2789";
+    const ENTRY_POINT_V07: &str = "0x0000000071727De22E5E9d8BAf0edAc6f37da032";
+
+    #[test]
+    fn mempool_id_test_vectors() {
+        let vectors = [
+            (
+                ENTRY_POINT_V06,
+                1,
+                "0x64140087f03cb44502963a00aee9f17957cc99b13f6f933eee0583e006dd413e",
+            ),
+            (
+                ENTRY_POINT_V06,
+                11155111,
+                "0x8880b489034f61ec9b756b6115c8305ff63c684c5eb0f6b7243984478279f98d",
+            ),
+            (
+                ENTRY_POINT_V06,
+                1337,
+                "0x8b86cfbcab0d40566f90a346f75bf8394a74cf329c2ee3d89af507f34a984a37",
+            ),
+            (
+                ENTRY_POINT_V06,
+                137,
+                "0xbec82c7f7a3b7bfef1e440482046f76a07f26678f8c8eae3630995409155eda4",
+            ),
+            (
+                ENTRY_POINT_V07,
+                1,
+                "0xebf0d40c28a5b6b048c74ffd1884b8720846d40b93316c81d487a4fd8fe6f16b",
+            ),
+            (
+                ENTRY_POINT_V07,
+                11155111,
+                "0xef5682e0358e903a1dd9b4294c8632d452932dfbb1df88c2bdd4d9fbd41fe33e",
+            ),
+            (
+                ENTRY_POINT_V07,
+                1337,
+                "0x8badc03c86b0d6b637a7af4ac12614eb9430177195468bf3e90bc38223514e5b",
+            ),
+            (
+                ENTRY_POINT_V07,
+                137,
+                "0x74797c3e2487c390e91390a48355fd43358827daed641c446f95113936c0c479",
+            ),
+        ];
+
+        for (ep, chain_id, id) in vectors {
+            let ep = Address::from_str(ep).unwrap();
+            assert_eq!(mempool_id(&ep, chain_id), MempoolId::from_str(id).unwrap());
+        }
+    }
+
+    #[test]
+    fn mempool_id_checksum_encoding() {
+        let checksummed = Address::from_str(ENTRY_POINT_V06).unwrap();
+        let lowercase = Address::from_str(&ENTRY_POINT_V06.to_lowercase()).unwrap();
+        let uppercase =
+            Address::from_str(&format!("0x{}", &ENTRY_POINT_V06[2..].to_uppercase())).unwrap();
+
+        assert_eq!(mempool_id(&checksummed, 1), mempool_id(&lowercase, 1));
+        assert_eq!(mempool_id(&checksummed, 1), mempool_id(&uppercase, 1));
+        assert_ne!(mempool_id(&checksummed, 1), mempool_id(&checksummed, 5));
+    }
+
+    #[test]
+    fn aging_outranks_fresh_higher_fees() {
+        let mut mempool = Mempool::new(
+            Box::new(HashMap::<UserOperationHash, UserOperationSigned>::default()),
+            Box::new(HashMap::<Address, HashSet<UserOperationHash>>::default()),
+            Box::new(HashMap::<Address, HashSet<UserOperationHash>>::default()),
+            Box::new(HashMap::<UserOperationHash, Vec<CodeHash>>::default()),
+        )
+        .with_ordering(AgingFeeOrdering {
+            boost_per_second: U256::from(10),
+            max_boost: U256::from(1_500),
+        });
+
+        let mut add = |fee: u64| {
+            mempool
+                .add(UserOperation::from_user_operation_signed(
+                    H256::random().into(),
+                    UserOperationSigned::default()
+                        .sender(Address::random())
+                        .max_priority_fee_per_gas(U256::from(fee)),
+                ))
+                .unwrap()
+        };
+        let low = add(1_000);
+        add(2_000);
+        add(2_000);
+        add(5_000);
+
+        let fees = |mempool: &Mempool| {
+            mempool
+                .get_sorted(None)
+                .unwrap()
+                .iter()
+                .map(|uo| uo.max_priority_fee_per_gas.as_u64())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(fees(&mempool), vec![5_000, 2_000, 2_000, 1_000]);
+
+        let wait = |mempool: &mut Mempool, secs: u64| {
+            let mut metadata =
+                mempool.user_operations_metadata.get_metadata(&low).unwrap().unwrap();
+            metadata.added_at -= secs;
+            mempool.user_operations_metadata.set_metadata(&low, metadata).unwrap();
+        };
+
+        // a minute of waiting isn't enough to outrank the fresh user operations
+        wait(&mut mempool, 60);
+        assert_eq!(fees(&mempool), vec![5_000, 2_000, 2_000, 1_000]);
+
+        // after 10 minutes the boost is capped, so the low-fee user operation outranks the fresh
+        // mid-fee ones but not the high-fee one
+        wait(&mut mempool, 540);
+        assert_eq!(fees(&mempool), vec![5_000, 1_000, 2_000, 2_000]);
     }
 }
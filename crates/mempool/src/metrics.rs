@@ -1 +1,51 @@
+use metrics::{counter, describe_counter, describe_histogram, histogram, Unit};
+use std::time::Duration;
 
+pub const VALIDATION_PHASE_DURATION: &str = "silius_validation_phase_duration";
+pub const CHAIN_SPLITS: &str = "silius_chain_splits";
+
+/// Phases of the user operation validation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValidationPhase {
+    /// Sanity checks
+    Sanity,
+    /// `simulateValidation` call and simulation checks
+    Simulation,
+    /// `simulateValidation` call with the tracer and simulation trace checks
+    Trace,
+}
+
+impl ValidationPhase {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ValidationPhase::Sanity => "sanity",
+            ValidationPhase::Simulation => "simulation",
+            ValidationPhase::Trace => "trace",
+        }
+    }
+}
+
+/// Records the time spent in a phase of the user operation validation
+pub fn record_validation_phase(phase: ValidationPhase, elapsed: Duration) {
+    histogram!(VALIDATION_PHASE_DURATION, "phase" => phase.as_str()).record(elapsed);
+}
+
+pub fn describe_validation_metrics() {
+    describe_histogram!(
+        VALIDATION_PHASE_DURATION,
+        Unit::Seconds,
+        "The time spent in each phase (sanity, simulation, trace) of the user operation validation"
+    );
+}
+
+/// Records a chain split (a block at an already seen height with a different hash)
+pub fn record_chain_split() {
+    counter!(CHAIN_SPLITS).increment(1);
+}
+
+pub fn describe_chain_metrics() {
+    describe_counter!(
+        CHAIN_SPLITS,
+        "The number of chain splits (reorgs) detected in the stream of new blocks"
+    );
+}
@@ -6,7 +6,13 @@ use silius_primitives::{
     get_address,
     reputation::{ReputationEntry, ReputationStatus, StakeInfo, Status},
 };
-use std::{collections::HashSet, fmt::Debug, ops::Deref, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    ops::Deref,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 /// Trait representing operations on a HashSet.
 pub trait HashSetOp: Default + Sync + Send {
@@ -168,6 +174,15 @@ pub struct Reputation {
     blacklist: Arc<RwLock<HashSet<Address>>>,
     /// Entities' repuation registry
     entities: Box<dyn ReputationEntryOp>,
+    /// Number of seconds since an entity was first seen during which it isn't throttled or banned
+    grace_period: u64,
+    /// Timestamps (in seconds) at which the entities were first seen
+    first_seen: Arc<RwLock<HashMap<Address, u64>>>,
+    /// Number of seconds an entity recovering from throttling (or a ban) is on probation
+    probation_period: u64,
+    /// Entities which were throttled or banned, with the timestamp (in seconds) at which their
+    /// probation ends (`None` while they are still throttled or banned)
+    probation: Arc<RwLock<HashMap<Address, Option<u64>>>>,
 }
 
 impl Clone for Reputation {
@@ -181,6 +196,10 @@ impl Clone for Reputation {
             whitelist: self.whitelist.clone(),
             blacklist: self.blacklist.clone(),
             entities: self.entities.clone(),
+            grace_period: self.grace_period,
+            first_seen: self.first_seen.clone(),
+            probation_period: self.probation_period,
+            probation: self.probation.clone(),
         }
     }
 }
@@ -206,9 +225,41 @@ impl Reputation {
             whitelist,
             blacklist,
             entities,
+            grace_period: 0,
+            first_seen: Arc::new(RwLock::new(HashMap::default())),
+            probation_period: 0,
+            probation: Arc::new(RwLock::new(HashMap::default())),
         }
     }
 
+    /// Sets the grace period of new entities: an entity which was first seen less than
+    /// `grace_period` seconds ago isn't throttled or banned regardless of its inclusion ratio,
+    /// which gives it a chance to build inclusion history.
+    ///
+    /// # Arguments
+    /// * `grace_period` - The grace period in seconds (0 disables the grace period)
+    ///
+    /// # Returns
+    /// * The [Reputation] with the grace period set
+    pub fn with_grace_period(mut self, grace_period: u64) -> Self {
+        self.grace_period = grace_period;
+        self
+    }
+
+    /// Sets the probation period: an entity recovering from throttling (or a ban) is on probation
+    /// for `probation_period` seconds, during which it gets a smaller allowance than OK entities,
+    /// before it's OK again.
+    ///
+    /// # Arguments
+    /// * `probation_period` - The probation period in seconds (0 disables the probation status)
+    ///
+    /// # Returns
+    /// * The [Reputation] with the probation period set
+    pub fn with_probation_period(mut self, probation_period: u64) -> Self {
+        self.probation_period = probation_period;
+        self
+    }
+
     /// Set the default reputation entry for an address.
     /// It would do nothing if the address already exists.
     ///
@@ -223,11 +274,77 @@ impl Reputation {
             let ent = ReputationEntry::default_with_addr(*addr);
 
             self.entities.set_entry(ent)?;
+            self.first_seen.write().insert(*addr, Self::now());
         }
 
         Ok(())
     }
 
+    fn now() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+    }
+
+    /// Check if an entity is still within the grace period since it was first seen
+    ///
+    /// # Arguments
+    /// * `addr` - The address to check
+    ///
+    /// # Returns
+    /// * `true` if the entity was first seen less than `grace_period` seconds ago. Otherwise,
+    ///   `false`
+    fn in_grace_period(&self, addr: &Address) -> bool {
+        self.grace_period > 0 &&
+            self.first_seen
+                .read()
+                .get(addr)
+                .is_some_and(|first_seen| Self::now() < first_seen + self.grace_period)
+    }
+
+    /// Moves an entity which recovered from throttling (or a ban) to probation until the
+    /// probation period has passed
+    ///
+    /// # Arguments
+    /// * `addr` - The address of the entity
+    /// * `status` - The status of the entity according to its inclusion ratio
+    ///
+    /// # Returns
+    /// * The status of the entity, [Status::PROBATION](Status::PROBATION) if it's on probation
+    fn probation_status(&self, addr: &Address, status: Status) -> Status {
+        if self.probation_period == 0 {
+            return status;
+        }
+
+        let mut probation = self.probation.write();
+        match status {
+            Status::THROTTLED | Status::BANNED => {
+                probation.insert(*addr, None);
+                status
+            }
+            _ => match probation.get(addr) {
+                // recovered since the status was last checked
+                Some(None) => {
+                    probation.insert(*addr, Some(Self::now() + self.probation_period));
+                    Status::PROBATION
+                }
+                Some(Some(until)) if Self::now() < *until => Status::PROBATION,
+                Some(Some(_)) => {
+                    probation.remove(addr);
+                    status
+                }
+                None => status,
+            },
+        }
+    }
+
+    /// Backdates the end of the probation of an entity (the tests don't wait for the probation
+    /// period to pass)
+    #[cfg(test)]
+    pub(crate) fn set_probation_end(&self, addr: &Address, until: u64) {
+        if let Some(end) = self.probation.write().get_mut(addr) {
+            *end = Some(until);
+        }
+    }
+
     /// Get an entity's [ReputationEntry](ReputationEntry) by address
     ///
     /// # Arguments
@@ -377,15 +494,17 @@ impl Reputation {
         }
 
         Ok(match self.entities.get_entry(addr)? {
+            Some(_) if self.in_grace_period(addr) => Status::OK.into(),
             Some(ent) => {
                 let max_seen = ent.uo_seen / self.min_inclusion_denominator;
-                if max_seen > ent.uo_included + self.ban_slack {
-                    Status::BANNED.into()
+                let status = if max_seen > ent.uo_included + self.ban_slack {
+                    Status::BANNED
                 } else if max_seen > ent.uo_included + self.throttling_slack {
-                    Status::THROTTLED.into()
+                    Status::THROTTLED
                 } else {
-                    Status::OK.into()
-                }
+                    Status::OK
+                };
+                self.probation_status(addr, status).into()
             }
             _ => Status::OK.into(),
         })
@@ -473,6 +592,30 @@ impl Reputation {
 
         Ok(())
     }
+
+    /// Whether the entity is staked according to the canonical rules, i.e. whitelisted or with both
+    /// the stake and the unstake delay at least the required minimums
+    ///
+    /// # Arguments
+    /// * `info` - The entity's [stake information](StakeInfo)
+    /// * `min_stake` - Min stake required. If set, this value has higher priority than the struct's
+    ///   value.
+    /// * `min_unstake_delay` - Min unstake delay required. If set, this value has higher priority
+    ///   than the struct's value.
+    ///
+    /// # Returns
+    /// * `true` if the entity is staked, otherwise `false`
+    pub fn is_staked(
+        &self,
+        info: &StakeInfo,
+        min_stake: Option<U256>,
+        min_unstake_delay: Option<U256>,
+    ) -> bool {
+        self.whitelist.is_in_list(&info.address) ||
+            (info.stake >= min_stake.unwrap_or(self.min_stake) &&
+                info.unstake_delay >= min_unstake_delay.unwrap_or(self.min_unstake_delay))
+    }
+
     /// Set the [Reputation Entry](ReputationEntry) of an entity
     ///
     /// # Arguments
@@ -488,6 +631,31 @@ impl Reputation {
         Ok(())
     }
 
+    /// Merge the [Reputation Entries](ReputationEntry) (e.g., exported from another node) into the
+    /// existing ones. Entities which are not present in `entries` are kept, and for entities
+    /// present in both, the higher `opsSeen` and `opsIncluded` counters are kept.
+    ///
+    /// # Arguments
+    /// * `entries` - The [Reputation Entries](ReputationEntry) to merge
+    ///
+    /// # Returns
+    /// * `Ok(())` if the entries were merged successfully
+    pub fn merge_entities(&mut self, entries: Vec<ReputationEntry>) -> Result<(), ReputationError> {
+        for en in entries {
+            let en = match self.entities.get_entry(&en.address)? {
+                Some(prev) => ReputationEntry {
+                    uo_seen: prev.uo_seen.max(en.uo_seen),
+                    uo_included: prev.uo_included.max(en.uo_included),
+                    ..en
+                },
+                None => en,
+            };
+            self.entities.set_entry(en)?;
+        }
+
+        Ok(())
+    }
+
     /// Get all [Reputation Entries](ReputationEntry)
     ///
     /// # Returns
@@ -523,6 +691,8 @@ impl Reputation {
     /// Clear all [Reputation Entries](ReputationEntry)
     pub fn clear(&mut self) {
         self.entities.clear();
+        self.first_seen.write().clear();
+        self.probation.write().clear();
     }
 }
 
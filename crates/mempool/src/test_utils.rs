@@ -0,0 +1,167 @@
+//! Helpers setting up the mempool, the reputation and the user operation pool in tests
+
+use crate::{
+    validate::{
+        validator::{new_canonical, StandardValidator},
+        UserOperationValidator,
+    },
+    Mempool, Reputation, UoPool,
+};
+use alloy_chains::Chain;
+use ethers::{
+    providers::Middleware,
+    types::{Address, Bytes, U256},
+};
+use parking_lot::RwLock;
+use silius_contracts::{entry_point::SELECTORS_INDICES, EntryPoint};
+use silius_primitives::{
+    constants::validation::{
+        entities::LEVEL_TO_ENTITY,
+        reputation::{BAN_SLACK, MIN_INCLUSION_RATE_DENOMINATOR, THROTTLING_SLACK},
+    },
+    reputation::ReputationEntry,
+    simulation::CodeHash,
+    UoPoolMode, UserOperationHash, UserOperationSigned,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+/// Chain id of the user operation pools built for tests
+pub const TEST_CHAIN_ID: u64 = 1337;
+/// Max verification gas of the user operation pools built for tests
+pub const TEST_MAX_VERIFICATION_GAS: u64 = 5_000_000;
+/// Min priority fee per gas of the validators built for tests
+pub const TEST_MIN_PRIORITY_FEE_PER_GAS: u64 = 100;
+
+/// Creates an empty [Mempool] backed by the memory storage
+pub fn memory_mempool() -> Mempool {
+    Mempool::new(
+        Box::new(HashMap::<UserOperationHash, UserOperationSigned>::default()),
+        Box::new(HashMap::<Address, HashSet<UserOperationHash>>::default()),
+        Box::new(HashMap::<Address, HashSet<UserOperationHash>>::default()),
+        Box::new(HashMap::<UserOperationHash, Vec<CodeHash>>::default()),
+    )
+}
+
+/// Creates an empty [Reputation] backed by the memory storage (with the default slacks, a min
+/// stake of 1 wei and no min unstake delay)
+pub fn memory_reputation() -> Reputation {
+    memory_reputation_with_min_stake(U256::from(1), U256::from(0))
+}
+
+/// Creates an empty [Reputation] backed by the memory storage with the given min stake and min
+/// unstake delay (and the default slacks)
+pub fn memory_reputation_with_min_stake(min_stake: U256, min_unstake_delay: U256) -> Reputation {
+    Reputation::new(
+        MIN_INCLUSION_RATE_DENOMINATOR,
+        THROTTLING_SLACK,
+        BAN_SLACK,
+        min_stake,
+        min_unstake_delay,
+        Arc::new(RwLock::new(HashSet::<Address>::default())),
+        Arc::new(RwLock::new(HashSet::<Address>::default())),
+        Box::new(HashMap::<Address, ReputationEntry>::default()),
+    )
+}
+
+/// Selector of the top-level call of the entry point in the validation of the given entity (e.g.,
+/// `validateUserOp` for the account), as found in the validation traces
+pub fn entity_selector(entity: &str) -> Bytes {
+    SELECTORS_INDICES
+        .iter()
+        .find(|(_, l)| LEVEL_TO_ENTITY[**l] == entity)
+        .map(|(s, _)| Bytes::from(s.to_vec()))
+        .expect("selector of the entity exists")
+}
+
+/// Creates the canonical validator of the entry point on the test chain
+pub fn test_validator<M: Middleware + Clone + 'static>(
+    entry_point: EntryPoint<M>,
+) -> StandardValidator<M> {
+    new_canonical(
+        entry_point,
+        Chain::from(TEST_CHAIN_ID),
+        U256::from(TEST_MAX_VERIFICATION_GAS),
+        U256::from(TEST_MIN_PRIORITY_FEE_PER_GAS),
+    )
+}
+
+/// Builder of the [UoPools](UoPool) used in tests: a standard pool with an empty memory mempool and
+/// reputation on the test chain, validated by the canonical validator unless another one is set
+pub struct UoPoolTestBuilder<M: Middleware + 'static, V: UserOperationValidator> {
+    mode: UoPoolMode,
+    entry_point: EntryPoint<M>,
+    validator: V,
+    mempool: Mempool,
+    reputation: Reputation,
+    max_verification_gas: U256,
+}
+
+impl<M: Middleware + Clone + 'static> UoPoolTestBuilder<M, StandardValidator<M>> {
+    pub fn new(entry_point: EntryPoint<M>) -> Self {
+        Self {
+            mode: UoPoolMode::Standard,
+            validator: test_validator(entry_point.clone()),
+            entry_point,
+            mempool: memory_mempool(),
+            reputation: memory_reputation(),
+            max_verification_gas: U256::from(TEST_MAX_VERIFICATION_GAS),
+        }
+    }
+}
+
+impl<M: Middleware + 'static, V: UserOperationValidator> UoPoolTestBuilder<M, V> {
+    /// Sets the validator of the user operations
+    pub fn with_validator<W: UserOperationValidator>(
+        self,
+        validator: W,
+    ) -> UoPoolTestBuilder<M, W> {
+        UoPoolTestBuilder {
+            mode: self.mode,
+            entry_point: self.entry_point,
+            validator,
+            mempool: self.mempool,
+            reputation: self.reputation,
+            max_verification_gas: self.max_verification_gas,
+        }
+    }
+
+    /// Sets the mode of the pool
+    pub fn with_mode(mut self, mode: UoPoolMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Sets the mempool (e.g., shared by the pools of several entry points)
+    pub fn with_mempool(mut self, mempool: Mempool) -> Self {
+        self.mempool = mempool;
+        self
+    }
+
+    /// Sets the reputation of the entities
+    pub fn with_reputation(mut self, reputation: Reputation) -> Self {
+        self.reputation = reputation;
+        self
+    }
+
+    /// Sets the max verification gas of the pool (which also caps the gas of the bundles)
+    pub fn with_max_verification_gas(mut self, max_verification_gas: U256) -> Self {
+        self.max_verification_gas = max_verification_gas;
+        self
+    }
+
+    pub fn build(self) -> UoPool<M, V> {
+        UoPool::new(
+            self.mode,
+            self.entry_point,
+            self.validator,
+            self.mempool,
+            self.reputation,
+            self.max_verification_gas,
+            Chain::from(TEST_CHAIN_ID),
+            None,
+        )
+    }
+}
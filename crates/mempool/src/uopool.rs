@@ -1,38 +1,50 @@
 use crate::{
     estimate::estimate_user_op_gas,
-    mempool::Mempool,
+    head::HeadTracker,
+    mempool::{DelayedUserOperation, GappedUserOperation, Mempool, NewUserOperation},
     mempool_id,
-    utils::div_ceil,
+    utils::{
+        calculate_valid_gas, div_ceil, extract_user_operation_logs, extract_user_operation_outcome,
+        OverheadConfig, PaymasterDeposits,
+    },
     validate::{
-        utils::merge_storage_maps, UserOperationValidationOutcome, UserOperationValidator,
-        UserOperationValidatorMode,
+        utils::{is_eip7702_init_code, merge_storage_maps},
+        UserOperationValidationOutcome, UserOperationValidator, UserOperationValidatorMode,
+        ValidationTrace,
     },
-    InvalidMempoolUserOperationError, MempoolError, MempoolErrorKind, MempoolId, Overhead,
-    Reputation, ReputationError, SanityError, SimulationError,
+    InvalidMempoolUserOperationError, MempoolError, MempoolErrorKind, MempoolId, Reputation,
+    ReputationError, SanityError, SimulationError,
 };
 use alloy_chains::Chain;
 use ethers::{
     prelude::LogMeta,
     providers::Middleware,
-    types::{Address, BlockNumber, U256},
+    types::{spoof, Address, BlockNumber, H256, U256},
 };
 use eyre::format_err;
-use futures::channel::mpsc::UnboundedSender;
+use futures::{channel::mpsc::UnboundedSender, future::OptionFuture, stream, StreamExt};
 use silius_contracts::{
     entry_point::UserOperationEventFilter, utils::parse_from_input_data, EntryPoint,
     EntryPointError,
 };
 use silius_primitives::{
-    constants::validation::reputation::THROTTLED_ENTITY_BUNDLE_COUNT,
+    constants::mempool::{
+        GAS_INCREASE_PERC, MAX_DELAYED_USER_OPERATIONS, MAX_TRACKED_PAYMASTERS,
+        SIMULATION_CONCURRENCY, VERIFICATION_GAS_BUFFER_PERC,
+    },
     get_address,
     p2p::NetworkMessage,
     reputation::{ReputationEntry, StakeInfo, StakeInfoResponse, Status},
-    simulation::{StorageMap, ValidationConfig},
-    UoPoolMode, UserOperation, UserOperationByHash, UserOperationGasEstimation, UserOperationHash,
-    UserOperationReceipt,
+    simulation::{CodeHash, StateOverride, StorageMap, ValidationConfig},
+    ChainContext, MempoolStats, NonceGapConfig, ReplacementPolicy, UoPoolMode, UserOperation,
+    UserOperationByHash, UserOperationGasEstimation, UserOperationHash, UserOperationReceipt,
+    UserOperationSource, ValidAfterPolicy,
 };
-use std::collections::{HashMap, HashSet};
-use tracing::{debug, error, info, trace};
+use std::{
+    collections::{HashMap, HashSet},
+    time::Instant,
+};
+use tracing::{debug, error, info, trace, warn};
 
 const FILTER_MAX_DEPTH: u64 = 10;
 const PRE_VERIFICATION_SAFE_RESERVE_PERC: u64 = 10; // percentage how higher pre verification gas we return
@@ -58,6 +70,33 @@ pub struct UoPool<M: Middleware + 'static, V: UserOperationValidator> {
     pub max_verification_gas: U256,
     // The [EIP-155](https://eips.ethereum.org/EIPS/eip-155) chain ID
     pub chain: Chain,
+    // Handling of user operations which aren't valid yet
+    pub valid_after_policy: ValidAfterPolicy,
+    // The maximum number of user operations held until their validAfter passes
+    pub max_delayed: usize,
+    // The maximum number of paymaster deposits tracked while building a bundle
+    pub max_tracked_paymasters: usize,
+    // Whether the bundle is simulated as a whole (`handleOps` call) before it's returned
+    pub simulate_bundle: bool,
+    // The maximum number of user operations in the mempool (unbounded if None)
+    pub max_user_operations: Option<usize>,
+    // The minimum fee increase (percentage) of a replacement user operation (same sender and
+    // nonce)
+    pub replacement_fee_increase: u64,
+    // Precedence between the sources of replacement user operations
+    pub replacement_policy: ReplacementPolicy,
+    // Holding of user operations with a nonce gap (rejected by the entry point if None)
+    pub nonce_gap: Option<NonceGapConfig>,
+    // The percentage added to the estimated verification gas limit
+    pub verification_gas_buffer: u64,
+    // Configuration of the pre-verification gas calculation
+    pub overhead: OverheadConfig<M>,
+    // Tracker of the recent chain heads (bundling is paused after a chain split)
+    pub head_tracker: HeadTracker,
+    // Maximum number of user operations returned by a single dump (unlimited if None)
+    pub max_dump_size: Option<usize>,
+    // Number of user operations simulated concurrently while building a bundle
+    pub simulation_concurrency: usize,
     // Connection to the p2p network (None if not enabled)
     network: Option<UnboundedSender<NetworkMessage>>,
 }
@@ -69,7 +108,8 @@ impl<M: Middleware + 'static, V: UserOperationValidator> UoPool<M, V> {
     /// `mode` - The [UoPoolMode](UoPoolMode) object
     /// `entry_point` - The [EntryPoint](EntryPoint) contract object
     /// `validator` - The [UserOperationValidator](UserOperationValidator) object
-    /// `mempool` - The [Mempool](Mempool) object
+    /// `mempool` - The [Mempool](Mempool) object (scoped to the entry point, as the storage may be
+    /// shared by the mempools of several entry points)
     /// `reputation` - The [Reputation](Reputation) object
     /// `max_verification_gas` - The maximum gas limit for [UserOperation](UserOperation) gas
     /// verification.
@@ -92,16 +132,126 @@ impl<M: Middleware + 'static, V: UserOperationValidator> UoPool<M, V> {
         Self {
             id: mempool_id(&entry_point.address(), chain.id()),
             mode,
+            mempool: mempool.with_entry_point(entry_point.address()),
             entry_point,
             validator,
-            mempool,
             reputation,
             max_verification_gas,
             chain,
+            valid_after_policy: ValidAfterPolicy::default(),
+            max_delayed: MAX_DELAYED_USER_OPERATIONS,
+            max_tracked_paymasters: MAX_TRACKED_PAYMASTERS,
+            simulate_bundle: false,
+            max_user_operations: None,
+            replacement_fee_increase: GAS_INCREASE_PERC,
+            replacement_policy: ReplacementPolicy::default(),
+            nonce_gap: None,
+            verification_gas_buffer: VERIFICATION_GAS_BUFFER_PERC,
+            overhead: OverheadConfig::for_chain(&chain),
+            head_tracker: HeadTracker::default(),
+            max_dump_size: None,
+            simulation_concurrency: SIMULATION_CONCURRENCY,
             network,
         }
     }
 
+    /// Sets the handling of [UserOperations](UserOperation) which aren't valid yet (`validAfter`
+    /// in the future)
+    pub fn with_valid_after_policy(mut self, valid_after_policy: ValidAfterPolicy) -> Self {
+        self.valid_after_policy = valid_after_policy;
+        self
+    }
+
+    /// Sets the maximum number of [UserOperations](UserOperation) held until their `validAfter`
+    /// passes
+    pub fn with_max_delayed(mut self, max_delayed: usize) -> Self {
+        self.max_delayed = max_delayed;
+        self
+    }
+
+    /// Sets the maximum number of paymaster deposits tracked while building a bundle (evicted
+    /// deposits are re-fetched)
+    pub fn with_max_tracked_paymasters(mut self, max_tracked_paymasters: usize) -> Self {
+        self.max_tracked_paymasters = max_tracked_paymasters;
+        self
+    }
+
+    /// Enables the simulation of the whole bundle (`handleOps` call) as the final step of
+    /// bundling, which drops the [UserOperations](UserOperation) reverting because of the
+    /// preceding ones in the bundle
+    pub fn with_bundle_simulation(mut self, simulate_bundle: bool) -> Self {
+        self.simulate_bundle = simulate_bundle;
+        self
+    }
+
+    /// Sets the maximum number of [UserOperations](UserOperation) in the mempool of the entry
+    /// point (the storage may be shared with the mempools of other entry points). Once it's full,
+    /// the [UserOperation](UserOperation) with the lowest `max_priority_fee_per_gas` is evicted
+    /// to make room for a [UserOperation](UserOperation) paying more.
+    pub fn with_max_user_operations(mut self, max_user_operations: Option<usize>) -> Self {
+        self.max_user_operations = max_user_operations;
+        self
+    }
+
+    /// Sets the minimum increase (percentage) of both `max_fee_per_gas` and
+    /// `max_priority_fee_per_gas` required to replace a [UserOperation](UserOperation) with the
+    /// same sender and nonce
+    pub fn with_replacement_fee_increase(mut self, replacement_fee_increase: u64) -> Self {
+        self.replacement_fee_increase = replacement_fee_increase;
+        self
+    }
+
+    /// Sets the precedence between the sources of [UserOperations](UserOperation) replacing each
+    /// other (e.g., whether the ones received over the p2p network have to bump the fees more to
+    /// replace the ones submitted via the RPC API)
+    pub fn with_replacement_policy(mut self, replacement_policy: ReplacementPolicy) -> Self {
+        self.replacement_policy = replacement_policy;
+        self
+    }
+
+    /// Enables holding of [UserOperations](UserOperation) whose nonce is ahead of the next one
+    /// expected for the sender, until the [UserOperations](UserOperation) filling the nonce gap
+    /// are added
+    pub fn with_nonce_gap(mut self, nonce_gap: Option<NonceGapConfig>) -> Self {
+        self.nonce_gap = nonce_gap;
+        self
+    }
+
+    /// Sets the percentage added to the estimated `verification_gas_limit`, so the
+    /// [UserOperation](UserOperation) doesn't revert if the state changes slightly before it's
+    /// sent
+    pub fn with_verification_gas_buffer(mut self, verification_gas_buffer: u64) -> Self {
+        self.verification_gas_buffer = verification_gas_buffer;
+        self
+    }
+
+    /// Sets the configuration of the estimated pre-verification gas (e.g., the L1 data posting
+    /// cost on L2s)
+    pub fn with_overhead(mut self, overhead: OverheadConfig<M>) -> Self {
+        self.overhead = overhead;
+        self
+    }
+
+    /// Sets the tracker of the recent chain heads, which pauses bundling after a chain split
+    pub fn with_head_tracker(mut self, head_tracker: HeadTracker) -> Self {
+        self.head_tracker = head_tracker;
+        self
+    }
+
+    /// Sets the maximum number of [UserOperations](UserOperation) returned by a single dump
+    /// (unlimited if None)
+    pub fn with_max_dump_size(mut self, max_dump_size: Option<usize>) -> Self {
+        self.max_dump_size = max_dump_size;
+        self
+    }
+
+    /// Sets the number of [UserOperations](UserOperation) simulated concurrently while building a
+    /// bundle
+    pub fn with_simulation_concurrency(mut self, simulation_concurrency: usize) -> Self {
+        self.simulation_concurrency = simulation_concurrency.max(1);
+        self
+    }
+
     /// Returns all of the [UserOperations](UserOperation) in the mempool
     ///
     /// # Returns
@@ -112,6 +262,44 @@ impl<M: Middleware + 'static, V: UserOperationValidator> UoPool<M, V> {
         })
     }
 
+    /// Returns a page of the [UserOperations](UserOperation) in the mempool sorted by sender and
+    /// nonce, so clients can page through large mempools
+    ///
+    /// # Arguments
+    /// * `after` - The sender and nonce of the last [UserOperation](UserOperation) of the previous
+    ///   page (the first page if None)
+    /// * `limit` - The maximum number of [UserOperations](UserOperation) returned (capped at the
+    ///   maximum dump size if set, all of them if None)
+    ///
+    /// # Returns
+    /// `Result<(Vec<UserOperation>, Option<(Address, U256)>, usize), eyre::Error>` - An array of
+    /// [UserOperations](UserOperation), the cursor of the next page (None if this is the last
+    /// one) and the number of [UserOperations](UserOperation) in the mempool
+    pub fn get_page(
+        &self,
+        after: Option<(Address, U256)>,
+        limit: Option<usize>,
+    ) -> eyre::Result<(Vec<UserOperation>, Option<(Address, U256)>, usize)> {
+        let limit = match (limit, self.max_dump_size) {
+            (Some(limit), Some(max_dump_size)) => Some(limit.min(max_dump_size)),
+            (limit, max_dump_size) => limit.or(max_dump_size),
+        };
+        self.mempool.get_page(after, limit).map_err(|err| {
+            format_err!("Getting a page of user operations from mempool failed with error: {err:?}",)
+        })
+    }
+
+    /// Returns the source the [UserOperation](UserOperation) was submitted from
+    ///
+    /// # Arguments
+    /// * `uo_hash` - The hash of the [UserOperation](UserOperation)
+    ///
+    /// # Returns
+    /// `UserOperationSource` - The [UserOperationSource](UserOperationSource) (RPC if unknown)
+    pub fn get_source(&self, uo_hash: &UserOperationHash) -> UserOperationSource {
+        self.mempool.get_source(uo_hash)
+    }
+
     /// Returns an array of [ReputationEntry](ReputationEntry) for entities.
     ///
     /// # Returns
@@ -124,14 +312,55 @@ impl<M: Middleware + 'static, V: UserOperationValidator> UoPool<M, V> {
     ///
     /// # Arguments
     /// `reputation` - An array of [ReputationEntry](ReputationEntry)
+    /// `overwrite` - Whether to overwrite the existing entries (otherwise they are merged)
     ///
     /// # Returns
     /// `()` - Returns nothing
     pub fn set_reputation(
         &mut self,
         reputation: Vec<ReputationEntry>,
+        overwrite: bool,
     ) -> Result<(), ReputationError> {
-        self.reputation.set_entities(reputation)
+        if overwrite {
+            self.reputation.set_entities(reputation)
+        } else {
+            self.reputation.merge_entities(reputation)
+        }
+    }
+
+    /// Returns the number of [UserOperations](UserOperation) in the mempool by source.
+    ///
+    /// # Returns
+    /// `Result<MempoolStats, eyre::Error>` - The [MempoolStats](MempoolStats)
+    pub fn get_stats(&self) -> eyre::Result<MempoolStats> {
+        self.mempool
+            .get_stats()
+            .map_err(|err| format_err!("Getting mempool stats failed with error: {err:?}",))
+    }
+
+    /// Returns the chain and fee context as seen by the mempool (latest block, base fee and the
+    /// configured fee and gas limits).
+    ///
+    /// # Returns
+    /// `Result<ChainContext, eyre::Error>` - The [ChainContext](ChainContext)
+    pub async fn get_chain_context(&self) -> eyre::Result<ChainContext> {
+        let block = self
+            .entry_point
+            .eth_client()
+            .get_block(BlockNumber::Latest)
+            .await?
+            .ok_or(format_err!("No latest block found"))?;
+
+        Ok(ChainContext {
+            chain_id: self.chain.id(),
+            block_number: block.number.unwrap_or_default().as_u64(),
+            block_hash: block.hash.unwrap_or_default(),
+            base_fee_per_gas: block.base_fee_per_gas.unwrap_or_default(),
+            min_priority_fee_per_gas: self
+                .validator
+                .min_priority_fee_per_gas(&self.entry_point.address()),
+            max_verification_gas: self.max_verification_gas,
+        })
     }
 
     /// Batch clears the [Mempool](Mempool).
@@ -150,6 +379,15 @@ impl<M: Middleware + 'static, V: UserOperationValidator> UoPool<M, V> {
         self.reputation.clear();
     }
 
+    /// Runs the hourly update (decay) of the [Reputation](Reputation) immediately, bypassing the
+    /// timer.
+    ///
+    /// # Returns
+    /// `Result<(), ReputationError>` - Ok if the [Reputation](Reputation) was updated
+    pub fn update_reputation(&mut self) -> Result<(), ReputationError> {
+        self.reputation.update_hourly()
+    }
+
     /// Batch clears the [Mempool](Mempool) and [Reputation](Reputation).
     ///
     /// # Returns
@@ -159,6 +397,20 @@ impl<M: Middleware + 'static, V: UserOperationValidator> UoPool<M, V> {
         self.reputation.clear();
     }
 
+    /// Pins the [UserOperations](UserOperation) with the given hashes, so they are put in front
+    /// of the sorted [UserOperations](UserOperation) (regardless of fee ordering) and thus
+    /// considered first for the next bundle. They are still subject to the bundle gas limit and
+    /// the second validation. Replaces the previously pinned [UserOperations](UserOperation).
+    ///
+    /// # Arguments
+    /// `uo_hashes` - The hashes of the [UserOperations](UserOperation) to pin
+    ///
+    /// # Returns
+    /// `()` - Returns nothing
+    pub fn set_pinned_user_operations(&mut self, uo_hashes: Vec<UserOperationHash>) {
+        self.mempool.set_pinned(uo_hashes);
+    }
+
     /// Adds bulk of [UserOperations](UserOperation) into the mempool.
     /// The function first validates the [UserOperations](UserOperation).
     ///
@@ -175,8 +427,12 @@ impl<M: Middleware + 'static, V: UserOperationValidator> UoPool<M, V> {
         val_config: Option<ValidationConfig>,
     ) -> Result<(), MempoolError> {
         for uo in user_operations {
+            if self.check_duplicate(&uo)? {
+                continue;
+            }
+
             let res = self.validate_user_operation(&uo, val_config.clone()).await;
-            self.add_user_operation(uo, res).await?;
+            self.add_user_operation(uo, res, UserOperationSource::Rpc).await?;
         }
 
         Ok(())
@@ -210,17 +466,65 @@ impl<M: Middleware + 'static, V: UserOperationValidator> UoPool<M, V> {
             .await
     }
 
+    /// Simulates a single [UserOperation](UserOperation) by running the full validation pipeline
+    /// without adding the [UserOperation](UserOperation) into the mempool or updating the
+    /// reputation of its entities (e.g., for dry-runs)
+    ///
+    /// # Arguments
+    /// `uo` - The [UserOperation](UserOperation) to simulate
+    ///
+    /// # Returns
+    /// `Result<UserOperationValidationOutcome, InvalidMempoolUserOperationError>` - The validation
+    /// outcome
+    pub async fn simulate_user_operation(
+        &self,
+        uo: &UserOperation,
+    ) -> Result<UserOperationValidationOutcome, InvalidMempoolUserOperationError> {
+        self.validate_user_operation(uo, None).await
+    }
+
+    /// Traces the validation of a single [UserOperation](UserOperation) and runs the simulation
+    /// trace checks on it, without adding the [UserOperation](UserOperation) into the mempool or
+    /// updating the reputation of its entities (e.g., for debugging rejections)
+    ///
+    /// # Arguments
+    /// `uo` - The [UserOperation](UserOperation) to trace
+    /// `val_config` - The optional [ValidationConfig](ValidationConfig) object (the one the
+    /// [UserOperation](UserOperation) would be validated with when added)
+    ///
+    /// # Returns
+    /// `Result<ValidationTrace, InvalidMempoolUserOperationError>` - The validation trace (with
+    /// the failed simulation trace check if any)
+    pub async fn trace_user_operation_validation(
+        &self,
+        uo: &UserOperation,
+        val_config: Option<ValidationConfig>,
+    ) -> Result<ValidationTrace, InvalidMempoolUserOperationError> {
+        self.validator
+            .trace_user_operation_validation(uo, &self.mempool, &self.reputation, val_config)
+            .await
+    }
+
     /// Adds a single validated user operation into the pool
     /// Indirectly invoked by RPC API via gRPC sevice to add a [UserOperation](UserOperation) into
     /// the mempool The function first validates the [UserOperation](UserOperation) by calling
     /// [UoPool::validate_user_operation](UoPool::validate_user_operation). If
     /// [UserOperation](UserOperation) passes the validation, then adds it into the mempool by
-    /// calling [Mempool::add](Mempool::add).
+    /// calling [Mempool::add](Mempool::add). [UserOperations](UserOperation) which aren't valid
+    /// yet are either rejected or held in the delayed queue, depending on the
+    /// [ValidAfterPolicy](ValidAfterPolicy). [UserOperations](UserOperation) with a nonce gap
+    /// are held (if enabled) until the gap is filled. If the mempool is full, the
+    /// [UserOperation](UserOperation) has to pay more than the evicted one. The subscribers of
+    /// the mempool are notified of the added [UserOperation](UserOperation). A replacement with
+    /// an empty `callData` cancels the replaced [UserOperation](UserOperation) and is notified as a
+    /// cancellation.
     ///
     /// # Arguments
     /// `uo` - The [UserOperation](UserOperation) to add
     /// `res` - The [UserOperationValidationOutcome](UserOperationValidationOutcome) of the
     /// validation
+    /// `source` - The [UserOperationSource](UserOperationSource) the user operation was submitted
+    /// from
     ///
     /// # Returns
     /// `Result<UserOperationHash, MempoolError>` - The hash of the added
@@ -229,9 +533,17 @@ impl<M: Middleware + 'static, V: UserOperationValidator> UoPool<M, V> {
         &mut self,
         uo: UserOperation,
         res: Result<UserOperationValidationOutcome, InvalidMempoolUserOperationError>,
+        source: UserOperationSource,
     ) -> Result<UserOperationHash, MempoolError> {
-        let res = match res {
-            Ok(res) => res,
+        let (res, nonce_err) = match res {
+            Ok(res) => (res, None),
+            // the nonce check of the entry point fails for user operations with a nonce gap, so
+            // they are held (unvalidated) until the gap is filled
+            Err(
+                err @ InvalidMempoolUserOperationError::Simulation(SimulationError::Nonce {
+                    ..
+                }),
+            ) if self.nonce_gap.is_some() => (UserOperationValidationOutcome::default(), Some(err)),
             Err(err) => {
                 if let InvalidMempoolUserOperationError::Sanity(SanityError::Reputation(
                     ReputationError::BannedEntity { address, entity: _ },
@@ -243,32 +555,112 @@ impl<M: Middleware + 'static, V: UserOperationValidator> UoPool<M, V> {
             }
         };
 
+        // the user operation was already added (e.g., imported and then re-received over the p2p
+        // network), so it keeps its original source and isn't counted as seen again
+        if self.check_duplicate(&uo)? {
+            return Ok(uo.hash);
+        }
+
+        if let Some(valid_after) = res.valid_after {
+            if self.valid_after_policy == ValidAfterPolicy::Reject {
+                return Err(MempoolError {
+                    hash: uo.hash,
+                    kind: InvalidMempoolUserOperationError::Simulation(
+                        SimulationError::Timestamp {
+                            inner: format!("not valid until {valid_after}"),
+                        },
+                    )
+                    .into(),
+                });
+            }
+
+            // a delayed user operation with the same sender and nonce is replaced
+            if self.mempool.get_delayed_by_nonce(&uo.sender, uo.nonce).is_none() &&
+                self.mempool.get_delayed().len() >= self.max_delayed
+            {
+                return Err(MempoolError {
+                    hash: uo.hash,
+                    kind: MempoolErrorKind::DelayedQueueFull { valid_after },
+                });
+            }
+        }
+
+        // user operations re-validated for another canonical mempool replace the one with the same
+        // sender and nonce regardless of the fee increase
+        if !res.val_config.ignore_prev {
+            self.check_replacement_fee(&uo, source)?;
+        }
+
+        // checked before anything is evicted or replaced, so a rejected user operation doesn't
+        // drop others (the user operation it replaces doesn't count)
+        self.mempool
+            .check_max_uos_per_sender(&uo, source)
+            .map_err(|e| MempoolError { hash: uo.hash, kind: e })?;
+
+        let gapped = match self.nonce_gap {
+            Some(nonce_gap) if res.prev_hash.is_none() && res.valid_after.is_none() => {
+                self.check_nonce_gap(&uo, nonce_gap, nonce_err).await?
+            }
+            _ => false,
+        };
+
         if let Some(uo_hash) = res.prev_hash {
             self.remove_user_operation(&uo_hash);
         }
+        if let Some(uo_prev) = self.mempool.get_delayed_by_nonce(&uo.sender, uo.nonce) {
+            self.remove_user_operation(&uo_prev.hash);
+        }
+        let cancels = res.prev_hash.filter(|_| uo.is_cancellation());
 
-        if let Some(ref sender) = self.network {
-            sender
-                .unbounded_send(NetworkMessage::Publish {
+        let added = match res.valid_after {
+            None if gapped => {
+                let uo_hash = self.mempool.add_gapped(GappedUserOperation {
                     user_operation: uo.clone(),
-                    verified_at_block_hash: res.verified_block,
-                    validation_config: res.val_config,
-                })
-                .expect("Failed to send user operation to publish channel")
+                    source,
+                    code_hashes: res.code_hashes,
+                    held_at: Instant::now(),
+                });
+                info!(
+                    "{uo_hash:?} held until the nonce gap is filled in the mempool {:?}",
+                    self.id
+                );
+                Ok(uo_hash)
+            }
+            Some(valid_after) => {
+                let uo_hash = self.mempool.add_delayed(DelayedUserOperation {
+                    user_operation: uo.clone(),
+                    source,
+                    code_hashes: res.code_hashes,
+                    valid_after,
+                });
+                info!("{uo_hash:?} queued until {valid_after} in the mempool {:?}", self.id);
+                Ok(uo_hash)
+            }
+            // replacements don't grow the mempool
+            None => self.admit_user_operation(
+                uo.clone(),
+                source,
+                res.code_hashes,
+                cancels,
+                self.max_user_operations.filter(|_| res.prev_hash.is_none()),
+            ),
         };
 
-        match self.mempool.add(uo.clone()) {
+        match added {
             Ok(uo_hash) => {
-                // TODO: find better way to do it atomically
-                if let Some(code_hashes) = res.code_hashes {
-                    match self.mempool.set_code_hashes(&uo_hash, code_hashes){
-                        Ok(_) => (),
-                        Err(e) => error!("Failed to set code hashes for user operation {uo_hash:?} with error: {e:?}"),
-                    }
-                }
-                info!("{uo_hash:?} added to the mempool {:?}", self.id);
                 trace!("{uo:?} added to the mempool {:?}", self.id);
 
+                // held user operations aren't necessarily validated
+                if let (Some(sender), false) = (&self.network, gapped) {
+                    sender
+                        .unbounded_send(NetworkMessage::Publish {
+                            user_operation: uo.clone(),
+                            verified_at_block_hash: res.verified_block,
+                            validation_config: res.val_config,
+                        })
+                        .expect("Failed to send user operation to publish channel")
+                };
+
                 // update reputation
                 self.reputation
                     .increment_seen(&uo.sender)
@@ -284,461 +676,2776 @@ impl<M: Middleware + 'static, V: UserOperationValidator> UoPool<M, V> {
                         .map_err(|e| MempoolError { hash: uo_hash, kind: e.into() })?;
                 }
 
+                // the user operation might fill the nonce gap of the held ones
+                if !gapped && res.valid_after.is_none() {
+                    self.promote_gapped_user_operations(&uo.sender, uo.nonce).await;
+                }
+
+                if let Some(cancelled) = cancels {
+                    info!("{uo_hash:?} cancels {cancelled:?} in the mempool {:?}", self.id);
+                }
+
                 Ok(uo_hash)
             }
             Err(e) => Err(MempoolError { hash: uo.hash, kind: e }),
         }
     }
 
-    /// Sorts the [UserOperations](UserOperation) in the mempool by calling the
-    /// [Mempool::get_sorted](Mempool::get_sorted) function
+    /// Prepares a [UserOperation](UserOperation) received over RPC or from the p2p network for
+    /// the entry point: if EIP-7702 delegated senders are accepted, the authorization in the
+    /// initCode is dropped (the entry point would revert with AA10 - sender already constructed -
+    /// otherwise, as it can't apply the authorization). The user operation is hashed as by the
+    /// version of the entry point (the sender computes the hash without knowing it).
+    ///
+    /// # Arguments
+    /// `uo` - The [UserOperation](UserOperation) to prepare
     ///
     /// # Returns
-    /// `Result<Vec<UserOperation>, eyre::Error>` - The sorted [UserOperations](UserOperation)
-    pub fn get_sorted_user_operations(&self) -> eyre::Result<Vec<UserOperation>> {
-        self.mempool.get_sorted().map_err(|err| {
-            format_err!("Getting sorted user operations from mempool failed with error: {err:?}",)
-        })
+    /// `UserOperation` - The [UserOperation](UserOperation) as sent to the entry point
+    pub fn normalize_user_operation(&self, uo: UserOperation) -> UserOperation {
+        let mut uo = uo.user_operation;
+        if self.validator.eip7702_senders() && is_eip7702_init_code(&uo.init_code) {
+            uo = uo.init_code(Default::default());
+        }
+
+        // user operations which can't be packed for the entry point v0.7 keep the hash of v0.6
+        // (their simulation is rejected)
+        let hash = self
+            .entry_point
+            .version()
+            .user_operation_hash(&uo, &self.entry_point.address(), self.chain.id())
+            .unwrap_or_else(|_| uo.hash(&self.entry_point.address(), self.chain.id()));
+        UserOperation::from_user_operation_signed(hash, uo)
     }
 
-    /// Bundles an array of [UserOperations](UserOperation)
-    /// The function first checks the reputations of the entities, then validate each
-    /// [UserOperation](UserOperation) by calling
-    /// [UoPool::validate_user_operation](UoPool::validate_user_operation).
-    /// If the [UserOperations](UserOperation) passes the validation, push it into the `uos_valid`
-    /// array.
+    /// Checks whether the [UserOperation](UserOperation) is already in the mempool, so it isn't
+    /// validated (or added) again. The storage is keyed by the hash, so a
+    /// [UserOperation](UserOperation) already added for another entry point can't be added again.
     ///
     /// # Arguments
-    /// `uos` - An array of [UserOperations](UserOperation) to bundle
+    /// `uo` - The [UserOperation](UserOperation) to check
     ///
     /// # Returns
-    /// `Result<(Vec<UserOperation>, StorageMap), eyre::Error>` - The bundled
-    /// [UserOperations](UserOperation).
-    pub async fn bundle_user_operations(
-        &mut self,
-        uos: Vec<UserOperation>,
-    ) -> eyre::Result<(Vec<UserOperation>, StorageMap)> {
-        let mut uos_valid = vec![];
-        let mut senders = HashSet::new();
-        let mut gas_total = U256::zero();
-        let mut paymaster_dep = HashMap::new();
-        let mut staked_entity_c = HashMap::new();
-        let mut storage_maps: Vec<StorageMap> = Vec::new();
-
-        let senders_all = uos.iter().map(|uo| uo.sender).collect::<HashSet<_>>();
+    /// `Result<bool, MempoolError>` - Whether the [UserOperation](UserOperation) is already in the
+    /// mempool, or an error if it collides with one added for another entry point
+    pub fn check_duplicate(&self, uo: &UserOperation) -> Result<bool, MempoolError> {
+        if let Some(entry_point) = self
+            .mempool
+            .get_entry_point(&uo.hash)
+            .filter(|entry_point| *entry_point != self.entry_point.address())
+        {
+            return Err(MempoolError {
+                hash: uo.hash,
+                kind: MempoolErrorKind::HashCollision { entry_point },
+            });
+        }
 
-        'uos: for uo in uos {
-            if senders.contains(&uo.sender) {
-                continue;
-            }
+        if self.mempool.contains(&uo.hash).map_err(|e| MempoolError { hash: uo.hash, kind: e })? {
+            debug!("{:?} is already in the mempool {:?}", uo.hash, self.id);
+            return Ok(true);
+        }
 
-            let p_opt = get_address(&uo.paymaster_and_data.0);
-            let f_opt = get_address(&uo.init_code.0);
+        Ok(false)
+    }
 
-            let p_st = Status::from(
-                self.reputation.get_status_from_bytes(&uo.paymaster_and_data).map_err(|err| {
-                    format_err!("Error getting reputation status with error: {err:?}")
-                })?,
-            );
-            let f_st = Status::from(self.reputation.get_status_from_bytes(&uo.init_code).map_err(
-                |err| format_err!("Error getting reputation status with error: {err:?}"),
-            )?);
+    /// Checks whether the nonce of the [UserOperation](UserOperation) is ahead of the next one
+    /// expected for the sender: the on-chain nonce followed by the nonces of the sender's
+    /// [UserOperations](UserOperation) in the mempool. A held [UserOperation](UserOperation) with
+    /// the same nonce is replaced.
+    ///
+    /// # Arguments
+    /// `uo` - The new [UserOperation](UserOperation)
+    /// `nonce_gap` - The [NonceGapConfig](NonceGapConfig) with the limits of the held user
+    /// operations
+    /// `nonce_err` - The validation error of the [UserOperation](UserOperation) caused by the
+    /// nonce gap (if any)
+    ///
+    /// # Returns
+    /// `Result<bool, MempoolError>` - Whether the [UserOperation](UserOperation) has to be held,
+    /// or an error if the nonce gap is too large or there is no room to hold it
+    async fn check_nonce_gap(
+        &mut self,
+        uo: &UserOperation,
+        nonce_gap: NonceGapConfig,
+        nonce_err: Option<InvalidMempoolUserOperationError>,
+    ) -> Result<bool, MempoolError> {
+        let err = |kind| MempoolError { hash: uo.hash, kind };
 
-            let p_c = p_opt.map(|p| staked_entity_c.get(&p).cloned().unwrap_or(0)).unwrap_or(0);
-            let f_c = f_opt.map(|f| staked_entity_c.get(&f).cloned().unwrap_or(0)).unwrap_or(0);
+        let mut next_nonce = self
+            .entry_point
+            .get_nonce(&uo.sender, uo.nonce >> 64)
+            .await
+            .map_err(|e| err(MempoolErrorKind::Provider { inner: e.to_string() }))?;
+        let nonces = self
+            .mempool
+            .get_all_by_sender(&uo.sender)
+            .iter()
+            .map(|uo| uo.nonce)
+            .collect::<HashSet<_>>();
+        while nonces.contains(&next_nonce) {
+            next_nonce = next_nonce.saturating_add(U256::one());
+        }
 
-            match (p_st, f_st) {
-                (Status::BANNED, _) | (_, Status::BANNED) => {
-                    self.mempool.remove(&uo.hash).map_err(|err| {
-                        format_err!(
-                            "Removing a banned user operation {:?} failed with error: {err:?}",
-                            uo.hash,
-                        )
-                    })?;
-                    continue;
-                }
-                (Status::THROTTLED, _) if p_c > THROTTLED_ENTITY_BUNDLE_COUNT => {
-                    continue;
-                }
-                (_, Status::THROTTLED) if f_c > THROTTLED_ENTITY_BUNDLE_COUNT => {
-                    continue;
-                }
-                _ => (),
+        if uo.nonce <= next_nonce {
+            return match nonce_err {
+                Some(e) => Err(err(e.into())),
+                None => Ok(false),
             };
+        }
 
-            let val_out = self
-                .validator
-                .validate_user_operation(
-                    &uo,
-                    &self.mempool,
-                    &self.reputation,
-                    None,
-                    UserOperationValidatorMode::Simulation |
-                        UserOperationValidatorMode::SimulationTrace,
-                )
-                .await;
-
-            debug!("Second validation for userop {:?} result: {:?}", uo.hash, val_out);
-
-            match val_out {
-                Ok(val_out) => {
-                    if val_out.valid_after.is_some() {
-                        continue;
-                    }
-
-                    for addr in val_out.storage_map.root_hashes.keys() {
-                        if *addr != uo.sender && senders_all.contains(addr) {
-                            continue 'uos;
-                        }
-                    }
-
-                    for addr in val_out.storage_map.slots.keys() {
-                        if *addr != uo.sender && senders_all.contains(addr) {
-                            continue 'uos;
-                        }
-                    }
-
-                    storage_maps.push(val_out.storage_map);
+        if uo.nonce - next_nonce > U256::from(nonce_gap.max_gap) {
+            return Err(err(MempoolErrorKind::NonceGap {
+                inner: format!(
+                    "nonce {} is too far ahead of the next nonce {next_nonce} of the sender {:?}",
+                    uo.nonce, uo.sender
+                ),
+            }));
+        }
 
-                    // TODO
-                    // it would be better to use estimate_gas instead of call_gas_limit
-                    // The result of call_gas_limit is usesally higher and less user op would be
-                    // included
-                    let gas_cost = val_out.verification_gas_limit.saturating_add(uo.call_gas_limit);
-                    let gas_total_new = gas_total.saturating_add(gas_cost);
-                    if gas_total_new.gt(&self.max_verification_gas) {
-                        break;
-                    }
+        if let Some(uo_prev) = self.mempool.get_gapped_by_nonce(&uo.sender, uo.nonce) {
+            self.mempool.remove(&uo_prev.hash).map_err(err)?;
+        } else if self.mempool.get_gapped().len() >= nonce_gap.max_held {
+            return Err(err(MempoolErrorKind::NonceGap {
+                inner: format!(
+                    "no room to hold the user operation until the nonce {next_nonce} of the sender {:?} is added",
+                    uo.sender
+                ),
+            }));
+        }
 
-                    if let Some(p) = p_opt {
-                        let balance = match paymaster_dep.get(&p) {
-                            Some(n) => *n,
-                            None => self.entry_point.balance_of(&p).await.map_err(|err| {
-                                format_err!(
-                                    "Getting balance of paymaster {p:?} failed with error: {err:?}",
-                                )
-                            })?,
-                        };
-
-                        if balance.lt(&val_out.pre_fund) {
-                            continue;
-                        }
+        Ok(true)
+    }
 
-                        staked_entity_c.entry(p).and_modify(|c| *c += 1).or_insert(1);
-                        paymaster_dep.insert(p, balance.saturating_sub(val_out.pre_fund));
-                    }
+    /// Promotes the held [UserOperations](UserOperation) of the sender whose nonce gap was filled
+    /// by the [UserOperation](UserOperation) with the given nonce. They are validated again
+    /// first, as they were held without being fully validated: the entry point still rejects
+    /// their nonce while the preceding [UserOperations](UserOperation) are only in the mempool,
+    /// but any other validation error drops them. The ones not valid yet are queued until their
+    /// `validAfter` passes (the following ones stay held).
+    ///
+    /// # Arguments
+    /// `sender` - The sender of the [UserOperation](UserOperation)
+    /// `nonce` - The nonce of the [UserOperation](UserOperation) added to the mempool
+    ///
+    /// # Returns
+    /// `Vec<UserOperationHash>` - The hashes of the promoted [UserOperations](UserOperation)
+    pub async fn promote_gapped_user_operations(
+        &mut self,
+        sender: &Address,
+        nonce: U256,
+    ) -> Vec<UserOperationHash> {
+        let mut promoted = vec![];
+        let mut nonce = nonce;
 
-                    if let Some(f) = f_opt {
-                        staked_entity_c.entry(f).and_modify(|c| *c += 1).or_insert(1);
-                    }
+        while let Some(gapped) = self.mempool.take_gapped(sender, nonce.saturating_add(U256::one()))
+        {
+            let uo = gapped.user_operation;
+            let uo_hash = uo.hash;
+            nonce = uo.nonce;
 
-                    gas_total = gas_total_new;
+            let code_hashes = match self.validate_user_operation(&uo, None).await {
+                Ok(UserOperationValidationOutcome {
+                    valid_after: Some(valid_after),
+                    code_hashes,
+                    ..
+                }) => {
+                    self.mempool.add_delayed(DelayedUserOperation {
+                        user_operation: uo,
+                        source: gapped.source,
+                        code_hashes,
+                        valid_after,
+                    });
+                    break;
                 }
-                Err(_) => {
-                    self.mempool.remove(&uo.hash).map_err(|err| {
-                        format_err!(
-                            "Removing a user operation {:?} with 2nd failed simulation failed with error: {err:?}", uo.hash,
-                        )
-                    })?;
-                    continue;
+                Ok(res) => res.code_hashes,
+                Err(InvalidMempoolUserOperationError::Simulation(SimulationError::Nonce {
+                    ..
+                })) => gapped.code_hashes,
+                Err(e) => {
+                    warn!("{uo_hash:?} dropped after failing re-validation on promotion: {e:?}");
+                    break;
                 }
-            }
+            };
 
-            uos_valid.push(uo.clone());
-            senders.insert(uo.sender);
+            match self.admit_user_operation(uo, gapped.source, code_hashes, None, None) {
+                Ok(uo_hash) => promoted.push(uo_hash),
+                Err(e) => {
+                    warn!("Failed to promote held user operation {uo_hash:?} with error: {e:?}");
+                    break;
+                }
+            }
         }
 
-        Ok((uos_valid, merge_storage_maps(storage_maps)))
+        promoted
     }
 
-    /// Gets the block base fee per gas
+    /// Drops the [UserOperations](UserOperation) held longer than the TTL of the
+    /// [NonceGapConfig](NonceGapConfig) (their nonce gap wasn't filled in time)
     ///
     /// # Returns
-    /// `Result<U256, eyre::Error>` - The block base fee per gas.
-    pub async fn base_fee_per_gas(&self) -> eyre::Result<U256> {
-        let block = self
-            .entry_point
-            .eth_client()
-            .get_block(BlockNumber::Latest)
-            .await?
-            .ok_or(format_err!("No block found"))?;
-        block.base_fee_per_gas.ok_or(format_err!("No base fee found"))
+    /// `Vec<UserOperationHash>` - The hashes of the dropped [UserOperations](UserOperation)
+    pub fn remove_expired_gapped_user_operations(&mut self) -> Vec<UserOperationHash> {
+        let Some(nonce_gap) = self.nonce_gap else {
+            return vec![];
+        };
+
+        let expired = self.mempool.remove_expired_gapped(nonce_gap.ttl);
+        for uo_hash in expired.iter() {
+            debug!("{uo_hash:?} dropped, its nonce gap wasn't filled in the mempool {:?}", self.id);
+        }
+        expired
     }
 
-    /// Estimates the `verification_gas_limit`, `call_gas_limit` and `pre_verification_gas` for a
-    /// user operation. The function is indirectly invoked by the `estimate_user_operation_gas`
-    /// JSON RPC method.
+    /// Checks that a [UserOperation](UserOperation) replacing the one with the same sender and
+    /// nonce in the mempool bumps both `max_fee_per_gas` and `max_priority_fee_per_gas` by at
+    /// least the replacement fee increase (doubled for [UserOperations](UserOperation) received
+    /// over the p2p network replacing the ones submitted via the RPC API, if they are preferred by
+    /// the [ReplacementPolicy](ReplacementPolicy))
     ///
     /// # Arguments
-    /// * `uo` - The [UserOperation](UserOperation) to estimate the gas for.
+    /// `uo` - The new [UserOperation](UserOperation)
+    /// `source` - The [UserOperationSource](UserOperationSource) of the new
+    /// [UserOperation](UserOperation)
     ///
     /// # Returns
-    /// `Result<UserOperationGasEstimation, MempoolError>` - The gas estimation result,
-    /// which includes the `verification_gas_limit`, `call_gas_limit` and `pre_verification_gas`.
-    pub async fn estimate_user_operation_gas(
+    /// `Result<(), MempoolError>` - An error if the [UserOperation](UserOperation) is an
+    /// underpriced replacement
+    fn check_replacement_fee(
         &self,
         uo: &UserOperation,
-    ) -> Result<UserOperationGasEstimation, MempoolError> {
-        let pre_verification_gas = div_ceil(
-            Overhead::default().calculate_pre_verification_gas(uo).saturating_mul(
-                U256::from(100).saturating_add(PRE_VERIFICATION_SAFE_RESERVE_PERC.into()),
-            ),
-            U256::from(100),
-        );
+        source: UserOperationSource,
+    ) -> Result<(), MempoolError> {
+        let Some(uo_prev) = self
+            .mempool
+            .get_prev_by_sender(uo)
+            .or_else(|| self.mempool.get_gapped_by_nonce(&uo.sender, uo.nonce))
+            .or_else(|| self.mempool.get_delayed_by_nonce(&uo.sender, uo.nonce))
+        else {
+            return Ok(());
+        };
 
-        let (verification_gas_limit, call_gas_limit) = match self.mode {
-            UoPoolMode::Standard => estimate_user_op_gas(&uo.user_operation, &self.entry_point)
-                .await
-                .map_err(|e| match e {
-                    EntryPointError::FailedOp(op) => MempoolError {
-                        hash: uo.hash,
-                        kind: MempoolErrorKind::InvalidUserOperation(
-                            InvalidMempoolUserOperationError::Simulation(
-                                SimulationError::Validation { inner: op.reason },
-                            ),
-                        ),
-                    },
-                    EntryPointError::ExecutionReverted(e) => MempoolError {
-                        hash: uo.hash,
-                        kind: MempoolErrorKind::InvalidUserOperation(
-                            InvalidMempoolUserOperationError::Simulation(
-                                SimulationError::Execution { inner: e },
-                            ),
-                        ),
-                    },
-                    EntryPointError::Provider { inner } => {
-                        MempoolError { hash: uo.hash, kind: MempoolErrorKind::Provider { inner } }
-                    }
-                    _ => MempoolError {
-                        hash: uo.hash,
-                        kind: MempoolErrorKind::Other { inner: e.to_string() },
-                    },
-                })?,
-            UoPoolMode::Unsafe => {
-                let ret =
-                    self.entry_point.simulate_handle_op(uo.clone().user_operation).await.map_err(
-                        |e| match e {
-                            EntryPointError::FailedOp(op) => MempoolError {
-                                hash: uo.hash,
-                                kind: MempoolErrorKind::InvalidUserOperation(
-                                    InvalidMempoolUserOperationError::Simulation(
-                                        SimulationError::Validation { inner: op.reason },
-                                    ),
-                                ),
-                            },
-                            EntryPointError::ExecutionReverted(e) => MempoolError {
-                                hash: uo.hash,
-                                kind: MempoolErrorKind::InvalidUserOperation(
-                                    InvalidMempoolUserOperationError::Simulation(
-                                        SimulationError::Execution { inner: e },
-                                    ),
-                                ),
-                            },
-                            EntryPointError::Provider { inner } => MempoolError {
-                                hash: uo.hash,
-                                kind: MempoolErrorKind::Provider { inner },
-                            },
-                            _ => MempoolError {
-                                hash: uo.hash,
-                                kind: MempoolErrorKind::Other { inner: e.to_string() },
-                            },
-                        },
-                    )?;
+        let replacement_fee_increase =
+            match (self.replacement_policy, source, self.mempool.get_source(&uo_prev.hash)) {
+                (
+                    ReplacementPolicy::PreferLocal,
+                    UserOperationSource::P2p,
+                    UserOperationSource::Rpc,
+                ) => self.replacement_fee_increase.saturating_mul(2),
+                _ => self.replacement_fee_increase,
+            };
 
-                let verification_gas_limit = div_ceil(
-                    ret.pre_op_gas.saturating_sub(pre_verification_gas).saturating_mul(3.into()),
-                    2.into(),
-                );
-                let call_gas_limit = div_ceil(ret.paid, uo.user_operation.max_fee_per_gas)
-                    .saturating_sub(ret.pre_op_gas)
-                    .saturating_add(35000.into());
+        let max_fee_per_gas =
+            calculate_valid_gas(uo_prev.max_fee_per_gas, replacement_fee_increase.into());
+        let max_priority_fee_per_gas =
+            calculate_valid_gas(uo_prev.max_priority_fee_per_gas, replacement_fee_increase.into());
 
-                (verification_gas_limit, call_gas_limit)
-            }
-        };
+        if uo.max_fee_per_gas < max_fee_per_gas ||
+            uo.max_priority_fee_per_gas < max_priority_fee_per_gas
+        {
+            return Err(MempoolError {
+                hash: uo.hash,
+                kind: MempoolErrorKind::ReplacementUnderpriced {
+                    max_fee_per_gas,
+                    max_priority_fee_per_gas,
+                },
+            });
+        }
 
-        Ok(UserOperationGasEstimation {
-            pre_verification_gas,
-            verification_gas_limit,
-            call_gas_limit,
-        })
+        Ok(())
     }
 
-    /// Filters the events logged from the [EntryPoint](EntryPoint) contract for a given user
-    /// operation hash.
-    ///
-    /// # Arguments
-    /// * `uo_hash` - The [UserOperationHash](UserOperationHash) to filter the events for.
-    ///
-    /// # Returns
-    /// `Result<Option<(UserOperationEventFilter, LogMeta)>, eyre::Error>` - The filtered event, if
-    /// any.
-    pub async fn get_user_operation_event_meta(
-        &self,
-        uo_hash: &UserOperationHash,
-    ) -> eyre::Result<Option<(UserOperationEventFilter, LogMeta)>> {
-        let mut event: Option<(UserOperationEventFilter, LogMeta)> = None;
-        let latest_block = self.entry_point.eth_client().get_block_number().await?;
-        let filter = self
-            .entry_point
-            .entry_point_api()
-            .event::<UserOperationEventFilter>()
-            .from_block(latest_block - FILTER_MAX_DEPTH)
-            .topic1(uo_hash.0);
-        let res: Vec<(UserOperationEventFilter, LogMeta)> = filter.query_with_meta().await?;
-        // It is possible have two same user operatation in same bundle
-        // see https://twitter.com/leekt216/status/1636414866662785024
-        for log_meta in res.iter() {
-            event = Some(log_meta.clone());
+    /// Adds a validated [UserOperation](UserOperation) (and its code hashes) into the mempool and
+    /// notifies the subscribers of the mempool. If the mempool is capped at `max_user_operations`
+    /// and full, the [UserOperation](UserOperation) has to pay more than the evicted ones (see
+    /// [Mempool::add_with_cap](Mempool::add_with_cap)).
+    fn admit_user_operation(
+        &mut self,
+        uo: UserOperation,
+        source: UserOperationSource,
+        code_hashes: Option<Vec<CodeHash>>,
+        cancels: Option<UserOperationHash>,
+        max_user_operations: Option<usize>,
+    ) -> Result<UserOperationHash, MempoolErrorKind> {
+        let uo_hash = match max_user_operations {
+            Some(max_user_operations) => {
+                let (uo_hash, evicted) =
+                    self.mempool.add_with_cap(uo.clone(), source, max_user_operations)?;
+                for evicted in evicted {
+                    info!("{evicted:?} evicted from the full mempool {:?}", self.id);
+                }
+                uo_hash
+            }
+            None => self.mempool.add_with_source(uo.clone(), source)?,
+        };
+
+        // TODO: find better way to do it atomically
+        if let Some(code_hashes) = code_hashes {
+            match self.mempool.set_code_hashes(&uo_hash, code_hashes) {
+                Ok(_) => (),
+                Err(e) => error!(
+                    "Failed to set code hashes for user operation {uo_hash:?} with error: {e:?}"
+                ),
+            }
         }
-        Ok(event)
+        info!("{uo_hash:?} added to the mempool {:?}", self.id);
+
+        self.mempool.notify(NewUserOperation {
+            user_operation: uo,
+            entry_point: self.entry_point.address(),
+            cancels,
+        });
+
+        Ok(uo_hash)
     }
 
-    /// Gets the user operation by hash.
-    /// The function is indirectly invoked by the `get_user_operation_by_hash` JSON RPC method.
+    /// Promotes the delayed [UserOperations](UserOperation) whose `validAfter` passed at the
+    /// given timestamp (e.g., of the latest block) to the mempool, so they are considered for
+    /// bundles. They are re-validated first, as the state might have changed since they were
+    /// queued: the ones failing the validation are dropped and the ones still not valid are
+    /// queued again.
     ///
     /// # Arguments
-    /// * `uo_hash` - The [UserOperationHash](UserOperationHash) to get the user operation for.
+    /// `timestamp` - The current timestamp
     ///
     /// # Returns
-    /// `Result<UserOperationByHash, eyre::Error>` - The user operation, if any.
-    pub async fn get_user_operation_by_hash(
-        &self,
-        uo_hash: &UserOperationHash,
-    ) -> eyre::Result<UserOperationByHash> {
-        let event = self.get_user_operation_event_meta(uo_hash).await?;
+    /// `Vec<UserOperationHash>` - The hashes of the promoted [UserOperations](UserOperation)
+    pub async fn promote_delayed_user_operations(
+        &mut self,
+        timestamp: U256,
+    ) -> Vec<UserOperationHash> {
+        let mut promoted = vec![];
 
-        if let Some((event, log_meta)) = event {
-            if let Some((uo, ep)) = self
-                .entry_point
-                .eth_client()
-                .get_transaction(log_meta.transaction_hash)
-                .await?
-                .and_then(|tx| {
-                    let uos = parse_from_input_data(tx.input)?;
-                    let ep = tx.to?;
-                    uos.iter()
-                        .find(|uo| uo.sender == event.sender && uo.nonce == event.nonce)
-                        .map(|uo| (uo.clone(), ep))
-                })
-            {
-                return Ok(UserOperationByHash {
+        for delayed in self.mempool.take_delayed(timestamp) {
+            let uo = delayed.user_operation;
+            let (uo_hash, sender, nonce) = (uo.hash, uo.sender, uo.nonce);
+
+            let res = match self.validate_user_operation(&uo, None).await {
+                Ok(res) => res,
+                Err(e) => {
+                    warn!("{uo_hash:?} dropped after failing re-validation on promotion: {e:?}");
+                    continue;
+                }
+            };
+
+            if let Some(valid_after) = res.valid_after {
+                self.mempool.add_delayed(DelayedUserOperation {
                     user_operation: uo,
-                    entry_point: ep,
-                    transaction_hash: log_meta.transaction_hash,
-                    block_hash: log_meta.block_hash,
-                    block_number: log_meta.block_number,
+                    source: delayed.source,
+                    code_hashes: res.code_hashes,
+                    valid_after,
                 });
+                continue;
+            }
+
+            match self.admit_delayed_user_operation(uo, delayed.source, res) {
+                Ok(uo_hash) => {
+                    promoted.push(uo_hash);
+                    promoted.extend(self.promote_gapped_user_operations(&sender, nonce).await);
+                }
+                Err(e) => {
+                    warn!("Failed to promote delayed user operation {uo_hash:?} with error: {e:?}")
+                }
             }
         }
 
-        Err(format_err!("No user operation found"))
+        promoted
     }
 
-    /// Gets the [UserOperationReceipt](UserOperationReceipt) by hash.
-    /// The function is indirectly invoked by the `get_user_operation_receipt` JSON RPC method.
-    ///
-    /// # Arguments
-    /// * `uo_hash` - The [UserOperationHash](UserOperationHash) to get the user operation receipt
-    ///   for.
+    /// Adds a re-validated delayed [UserOperation](UserOperation) into the mempool, replacing the
+    /// one with the same sender and nonce (if it pays enough more) or evicting others if the
+    /// mempool is full
+    fn admit_delayed_user_operation(
+        &mut self,
+        uo: UserOperation,
+        source: UserOperationSource,
+        res: UserOperationValidationOutcome,
+    ) -> Result<UserOperationHash, MempoolError> {
+        if let Some(uo_hash) = res.prev_hash {
+            self.check_replacement_fee(&uo, source)?;
+            self.remove_user_operation(&uo_hash);
+        }
+
+        let uo_hash = uo.hash;
+        let cancels = res.prev_hash.filter(|_| uo.is_cancellation());
+        let max_user_operations = self.max_user_operations.filter(|_| res.prev_hash.is_none());
+        self.admit_user_operation(uo, source, res.code_hashes, cancels, max_user_operations)
+            .map_err(|kind| MempoolError { hash: uo_hash, kind })
+    }
+
+    /// Re-validates the [UserOperations](UserOperation) in the mempool against the state of the
+    /// latest block and removes the ones which aren't valid anymore (e.g., the sender's deposit
+    /// was spent in the meantime). Only the simulation is re-run, as the sanity checks depend on
+    /// when the [UserOperation](UserOperation) was received.
     ///
     /// # Returns
-    /// `Result<UserOperationReceipt, eyre::Error>` - The user operation receipt, if any.
-    pub async fn get_user_operation_receipt(
-        &self,
-        uo_hash: &UserOperationHash,
-    ) -> eyre::Result<UserOperationReceipt> {
-        let event = self.get_user_operation_event_meta(uo_hash).await?;
+    /// `Result<Vec<UserOperationHash>, eyre::Error>` - The hashes of the removed
+    /// [UserOperations](UserOperation)
+    pub async fn revalidate_user_operations(&mut self) -> eyre::Result<Vec<UserOperationHash>> {
+        let mut removed = vec![];
 
-        if let Some((event, log_meta)) = event {
-            if let Some(tx_receipt) = self
-                .entry_point
-                .eth_client()
-                .get_transaction_receipt(log_meta.transaction_hash)
-                .await?
+        for uo in self.get_all()? {
+            if let Err(err) = self
+                .validator
+                .validate_user_operation(
+                    &uo,
+                    &self.mempool,
+                    &self.reputation,
+                    None,
+                    UserOperationValidatorMode::Simulation.into(),
+                )
+                .await
             {
-                let uo = self.get_user_operation_by_hash(uo_hash).await?;
-                return Ok(UserOperationReceipt {
-                    user_operation_hash: *uo_hash,
-                    sender: event.sender,
-                    nonce: event.nonce,
-                    actual_gas_cost: event.actual_gas_cost,
-                    actual_gas_used: event.actual_gas_used,
-                    success: event.success,
-                    tx_receipt: tx_receipt.clone(),
-                    logs: tx_receipt.logs.into_iter().collect(),
-                    paymaster: get_address(&uo.user_operation.paymaster_and_data),
-                    reason: String::new(), // TODO: this must be set to revert reason
-                });
+                debug!("{:?} removed after failing re-validation: {:?}", uo.hash, err);
+                self.mempool.remove(&uo.hash)?;
+                removed.push(uo.hash);
             }
         }
 
-        Err(format_err!("No user operation found"))
+        Ok(removed)
     }
 
-    /// Removes the [UserOperation](UserOperation) from the user operation mempool
-    /// given the [UserOperationHash](UserOperationHash).
-    ///
-    /// # Arguments
-    /// * `uo_hash` - The [UserOperationHash](UserOperationHash) to remove the user operation for.
+    /// Sorts the [UserOperations](UserOperation) in the mempool by calling the
+    /// [Mempool::get_sorted](Mempool::get_sorted) function with the base fee of the latest block
+    /// (user operations which don't cover it are skipped). None are returned while bundling is
+    /// paused after a chain split.
     ///
     /// # Returns
-    /// `Option<()>` - None if the user operation was successfully removed.
-    pub fn remove_user_operation(&mut self, uo_hash: &UserOperationHash) -> Option<()> {
-        self.mempool.remove(uo_hash).ok();
-        None
+    /// `Result<Vec<UserOperation>, eyre::Error>` - The sorted [UserOperations](UserOperation)
+    pub async fn get_sorted_user_operations(&self) -> eyre::Result<Vec<UserOperation>> {
+        if self.head_tracker.is_paused() {
+            info!("Bundling paused until the chain head stabilizes");
+            return Ok(vec![]);
+        }
+
+        // the raw priority fees are used if the base fee is unavailable
+        let base_fee = self
+            .base_fee_per_gas()
+            .await
+            .map_err(|err| debug!("Sorting by the raw priority fees: {err:?}"))
+            .ok();
+
+        self.mempool.get_sorted(base_fee).map_err(|err| {
+            format_err!("Getting sorted user operations from mempool failed with error: {err:?}",)
+        })
     }
 
-    pub fn remove_user_operation_by_entity(&mut self, entity: &Address) -> Option<()> {
-        self.mempool.remove_by_entity(entity).ok();
-        None
+    /// Reputation statuses of the paymaster and the factory of the [UserOperation](UserOperation)
+    fn entity_statuses(&self, uo: &UserOperation) -> eyre::Result<(Status, Status)> {
+        let p_st =
+            Status::from(self.reputation.get_status_from_bytes(&uo.paymaster_and_data).map_err(
+                |err| format_err!("Error getting reputation status with error: {err:?}"),
+            )?);
+        let f_st =
+            Status::from(self.reputation.get_status_from_bytes(&uo.init_code).map_err(|err| {
+                format_err!("Error getting reputation status with error: {err:?}")
+            })?);
+        Ok((p_st, f_st))
     }
 
-    /// Removes multiple [UserOperations](UserOperation) from the
-    /// user operation mempool given an array of
+    /// Bundles an array of [UserOperations](UserOperation)
+    /// The [UserOperations](UserOperation) are processed in windows of at most
+    /// `simulation_concurrency` candidates. The candidates are first filtered (senders already
+    /// bundled, banned or throttled entities, gas budget), only the remaining ones are simulated
+    /// concurrently (second validation) and the outcomes are then processed in order (so the bundle
+    /// doesn't depend on the order in which the simulations complete): the function checks the
+    /// reputations of the entities and the outcome of the simulation of each
     /// [UserOperation](UserOperation).
+    /// If the [UserOperations](UserOperation) passes the validation, push it into the `uos_valid`
+    /// array. If enabled, the bundle is finally simulated as a whole by calling
+    /// [UoPool::simulate_bundle](UoPool::simulate_bundle).
     ///
     /// # Arguments
-    /// * `uos` - The array of [UserOperation](UserOperation).
+    /// `uos` - An array of [UserOperations](UserOperation) to bundle
     ///
     /// # Returns
-    /// `Option<()>` - None
-    pub fn remove_user_operations(&mut self, uos: Vec<UserOperation>) -> Option<()> {
-        for uo in uos {
-            self.remove_user_operation(&uo.hash);
+    /// `Result<(Vec<UserOperation>, StorageMap), eyre::Error>` - The bundled
+    /// [UserOperations](UserOperation).
+    pub async fn bundle_user_operations(
+        &mut self,
+        uos: Vec<UserOperation>,
+    ) -> eyre::Result<(Vec<UserOperation>, StorageMap)> {
+        let mut uos_valid = vec![];
+        let mut senders = HashSet::new();
+        let mut gas_total = U256::zero();
+        let mut paymaster_dep = PaymasterDeposits::new(self.max_tracked_paymasters);
+        let mut staked_entity_c = HashMap::new();
+        let mut storage_maps: Vec<StorageMap> = Vec::new();
 
-            // update reputations
-            self.reputation.increment_included(&uo.sender).ok();
+        let senders_all = uos.iter().map(|uo| uo.sender).collect::<HashSet<_>>();
+        let mut uos = uos.into_iter().peekable();
 
-            if let Some(addr) = get_address(&uo.paymaster_and_data) {
-                self.reputation.increment_included(&addr).ok();
+        'bundle: while uos.peek().is_some() {
+            // the next window of candidates, a sender waits for the outcome of its previous user
+            // operation (so a window has distinct senders)
+            let mut window = vec![];
+            let mut window_senders = HashSet::new();
+            let mut gas_exhausted = false;
+            while window.len() < self.simulation_concurrency {
+                let Some(uo) = uos.next_if(|uo| !window_senders.contains(&uo.sender)) else {
+                    break;
+                };
+                if senders.contains(&uo.sender) {
+                    continue;
+                }
+
+                // the gas of the bundle can't cover even the call gas limit
+                if gas_total.saturating_add(uo.call_gas_limit).gt(&self.max_verification_gas) {
+                    gas_exhausted = true;
+                    break;
+                }
+
+                // banned and throttled entities aren't simulated, they're handled below
+                let (p_st, f_st) = self.entity_statuses(&uo)?;
+                let p_c = get_address(&uo.paymaster_and_data.0)
+                    .map(|p| staked_entity_c.get(&p).cloned().unwrap_or(0))
+                    .unwrap_or(0);
+                let f_c = get_address(&uo.init_code.0)
+                    .map(|f| staked_entity_c.get(&f).cloned().unwrap_or(0))
+                    .unwrap_or(0);
+                let simulate = p_st != Status::BANNED &&
+                    f_st != Status::BANNED &&
+                    !p_st.bundle_allowance().is_some_and(|allowance| p_c > allowance) &&
+                    !f_st.bundle_allowance().is_some_and(|allowance| f_c > allowance);
+
+                window_senders.insert(uo.sender);
+                window.push((uo, simulate));
             }
 
-            if let Some(addr) = get_address(&uo.init_code) {
-                self.reputation.increment_included(&addr).ok();
+            // the simulations are independent of each other, only the results are processed in
+            // order
+            let val_outs = stream::iter(window.iter())
+                .map(|(uo, simulate)| {
+                    OptionFuture::from(simulate.then(|| {
+                        self.validator.validate_user_operation(
+                            uo,
+                            &self.mempool,
+                            &self.reputation,
+                            None,
+                            UserOperationValidatorMode::Simulation |
+                                UserOperationValidatorMode::SimulationTrace,
+                        )
+                    }))
+                })
+                .buffered(self.simulation_concurrency)
+                .collect::<Vec<_>>()
+                .await;
+
+            'uos: for ((uo, _), val_out) in window.into_iter().zip(val_outs) {
+                let p_opt = get_address(&uo.paymaster_and_data.0);
+                let f_opt = get_address(&uo.init_code.0);
+
+                let (p_st, f_st) = self.entity_statuses(&uo)?;
+
+                let p_c = p_opt.map(|p| staked_entity_c.get(&p).cloned().unwrap_or(0)).unwrap_or(0);
+                let f_c = f_opt.map(|f| staked_entity_c.get(&f).cloned().unwrap_or(0)).unwrap_or(0);
+
+                match (p_st, f_st) {
+                    (Status::BANNED, _) | (_, Status::BANNED) => {
+                        self.mempool.remove(&uo.hash).map_err(|err| {
+                            format_err!(
+                                "Removing a banned user operation {:?} failed with error: {err:?}",
+                                uo.hash,
+                            )
+                        })?;
+                        continue;
+                    }
+                    (p_st, _)
+                        if p_st.bundle_allowance().is_some_and(|allowance| p_c > allowance) =>
+                    {
+                        continue;
+                    }
+                    (_, f_st)
+                        if f_st.bundle_allowance().is_some_and(|allowance| f_c > allowance) =>
+                    {
+                        continue;
+                    }
+                    _ => (),
+                };
+
+                let Some(val_out) = val_out else {
+                    continue;
+                };
+
+                debug!("Second validation for userop {:?} result: {:?}", uo.hash, val_out);
+
+                match val_out {
+                    Ok(val_out) => {
+                        if val_out.valid_after.is_some() {
+                            continue;
+                        }
+
+                        for addr in val_out.storage_map.root_hashes.keys() {
+                            if *addr != uo.sender && senders_all.contains(addr) {
+                                continue 'uos;
+                            }
+                        }
+
+                        for addr in val_out.storage_map.slots.keys() {
+                            if *addr != uo.sender && senders_all.contains(addr) {
+                                continue 'uos;
+                            }
+                        }
+
+                        // TODO
+                        // it would be better to use estimate_gas instead of call_gas_limit
+                        // The result of call_gas_limit is usesally higher and less user op would
+                        // be included
+                        let gas_cost =
+                            val_out.verification_gas_limit.saturating_add(uo.call_gas_limit);
+                        let gas_total_new = gas_total.saturating_add(gas_cost);
+                        if gas_total_new.gt(&self.max_verification_gas) {
+                            break 'bundle;
+                        }
+
+                        if let Some(p) = p_opt {
+                            // left for the next bundle, so the spent pre-funds stay bounded
+                            if !paymaster_dep.can_spend(&p) {
+                                continue;
+                            }
+
+                            let balance = match paymaster_dep.remaining(&p) {
+                                Some(n) => n,
+                                None => {
+                                    let deposit =
+                                        self.entry_point.balance_of(&p).await.map_err(|err| {
+                                            format_err!(
+                                                "Getting balance of paymaster {p:?} failed with error: {err:?}",
+                                            )
+                                        })?;
+                                    paymaster_dep.insert(p, deposit)
+                                }
+                            };
+
+                            if balance.lt(&val_out.pre_fund) {
+                                continue;
+                            }
+
+                            staked_entity_c.entry(p).and_modify(|c| *c += 1).or_insert(1);
+                            paymaster_dep.spend(p, val_out.pre_fund);
+                        }
+
+                        if let Some(f) = f_opt {
+                            staked_entity_c.entry(f).and_modify(|c| *c += 1).or_insert(1);
+                        }
+
+                        gas_total = gas_total_new;
+                        storage_maps.push(val_out.storage_map);
+                    }
+                    Err(_) => {
+                        self.mempool.remove(&uo.hash).map_err(|err| {
+                            format_err!(
+                                "Removing a user operation {:?} with 2nd failed simulation failed with error: {err:?}", uo.hash,
+                            )
+                        })?;
+                        continue;
+                    }
+                }
+
+                uos_valid.push(uo.clone());
+                senders.insert(uo.sender);
+            }
+
+            if gas_exhausted {
+                break;
             }
         }
 
-        None
+        if self.simulate_bundle {
+            (uos_valid, storage_maps) = self.simulate_bundle(uos_valid, storage_maps).await?;
+        }
+
+        (uos_valid, storage_maps) =
+            self.drop_unprofitable_user_operations(uos_valid, storage_maps).await?;
+
+        Ok((uos_valid, merge_storage_maps(storage_maps)))
     }
 
-    /// Gets the [StakeInfoResponse](StakeInfoResponse) for entity
+    /// Simulates the bundle as a whole (`handleOps` call), since the
+    /// [UserOperations](UserOperation) are validated independently and a
+    /// [UserOperation](UserOperation) may revert only because of the state changed by the
+    /// preceding ones. The reverting [UserOperations](UserOperation) are dropped from the
+    /// bundle (but kept in the mempool) until the bundle doesn't revert.
     ///
     /// # Arguments
-    /// * `addr` - The address of the entity.
+    /// `uos` - The bundled [UserOperations](UserOperation)
+    /// `storage_maps` - The storage maps of the bundled [UserOperations](UserOperation)
     ///
     /// # Returns
-    /// `Result<StakeInfoResponse, eyre::Error>` - Stake info of the entity.
-    pub async fn get_stake_info(&self, addr: &Address) -> eyre::Result<StakeInfoResponse> {
-        let info = self.entry_point.get_deposit_info(addr).await?;
-        let stake_info = StakeInfo {
-            address: *addr,
-            stake: U256::from(info.stake),
-            unstake_delay: U256::from(info.unstake_delay_sec),
+    /// `Result<(Vec<UserOperation>, Vec<StorageMap>), eyre::Error>` - The
+    /// [UserOperations](UserOperation) (and their storage maps) which don't revert in the bundle.
+    async fn simulate_bundle(
+        &self,
+        mut uos: Vec<UserOperation>,
+        mut storage_maps: Vec<StorageMap>,
+    ) -> eyre::Result<(Vec<UserOperation>, Vec<StorageMap>)> {
+        while !uos.is_empty() {
+            // the beneficiary only has to be non-zero
+            let res = self
+                .entry_point
+                .handle_ops(
+                    uos.iter().map(|uo| uo.user_operation.clone()).collect::<Vec<_>>(),
+                    Address::repeat_byte(0xff),
+                )
+                .await;
+
+            match res {
+                Ok(()) => break,
+                Err(EntryPointError::FailedOp(op)) if op.op_index < U256::from(uos.len()) => {
+                    let uo = uos.remove(op.op_index.as_usize());
+                    storage_maps.remove(op.op_index.as_usize());
+                    debug!(
+                        "{:?} dropped from the bundle, reverted in the bundle simulation: {}",
+                        uo.hash, op.reason
+                    );
+                }
+                Err(err) => {
+                    return Err(format_err!("Simulating the bundle failed with error: {err:?}"))
+                }
+            }
+        }
+
+        Ok((uos, storage_maps))
+    }
+
+    /// Drops the [UserOperations](UserOperation) which don't pay for their share of the L1 data
+    /// posting cost of the bundle (only if the [OverheadConfig](OverheadConfig) has an L1 gas
+    /// oracle), so the bundler doesn't send bundles at a loss. The bundle is priced with a single
+    /// oracle query and the L1 fee is split proportionally to the calldata size of the
+    /// [UserOperations](UserOperation). A [UserOperation](UserOperation) pays for it with the part
+    /// of its pre-verification gas above the L2 overhead. The dropped
+    /// [UserOperations](UserOperation) are kept in the mempool.
+    ///
+    /// # Arguments
+    /// `uos` - The bundled [UserOperations](UserOperation)
+    /// `storage_maps` - The storage maps of the bundled [UserOperations](UserOperation)
+    ///
+    /// # Returns
+    /// `Result<(Vec<UserOperation>, Vec<StorageMap>), eyre::Error>` - The profitable
+    /// [UserOperations](UserOperation) (and their storage maps).
+    async fn drop_unprofitable_user_operations(
+        &self,
+        uos: Vec<UserOperation>,
+        storage_maps: Vec<StorageMap>,
+    ) -> eyre::Result<(Vec<UserOperation>, Vec<StorageMap>)> {
+        if self.overhead.l1_gas_oracle.is_none() || uos.is_empty() {
+            return Ok((uos, storage_maps));
+        }
+
+        let uos_signed = uos.iter().map(|uo| uo.user_operation.clone()).collect::<Vec<_>>();
+        let (l1_fee, base_fee) = futures::future::try_join(
+            async {
+                self.overhead.calculate_bundle_l1_fee(&self.entry_point, &uos_signed).await.map_err(
+                    |err| {
+                        format_err!("Pricing the L1 fee of the bundle failed with error: {err:?}")
+                    },
+                )
+            },
+            self.base_fee_per_gas(),
+        )
+        .await?;
+
+        // the share of the L1 fee is proportional to the size of the user operation packed for the
+        // version of the entry point
+        let version = self.entry_point.version();
+        let sizes = uos_signed
+            .iter()
+            .map(|uo| version.pack_user_operation(uo).map(|packed| U256::from(packed.len())))
+            .collect::<Result<Vec<_>, _>>()?;
+        let size_total = sizes.iter().fold(U256::zero(), |acc, size| acc.saturating_add(*size));
+        let overhead = self.overhead.overhead();
+
+        Ok(uos
+            .into_iter()
+            .zip(storage_maps)
+            .zip(sizes)
+            .filter_map(|((uo, storage_map), size)| {
+                let l1_fee_share = div_ceil(l1_fee.saturating_mul(size), size_total);
+                let gas_price =
+                    uo.max_fee_per_gas.min(base_fee.saturating_add(uo.max_priority_fee_per_gas));
+                let l1_paid = uo
+                    .pre_verification_gas
+                    .saturating_sub(overhead.calculate_pre_verification_gas(&uo.user_operation))
+                    .saturating_mul(gas_price);
+
+                if l1_paid < l1_fee_share {
+                    debug!(
+                        "{:?} dropped from the bundle, doesn't pay its share of the L1 fee: {l1_paid} < {l1_fee_share}",
+                        uo.hash
+                    );
+                    return None;
+                }
+
+                Some((uo, storage_map))
+            })
+            .unzip())
+    }
+
+    /// Gets the block base fee per gas
+    ///
+    /// # Returns
+    /// `Result<U256, eyre::Error>` - The block base fee per gas.
+    pub async fn base_fee_per_gas(&self) -> eyre::Result<U256> {
+        let block = self
+            .entry_point
+            .eth_client()
+            .get_block(BlockNumber::Latest)
+            .await?
+            .ok_or(format_err!("No block found"))?;
+        block.base_fee_per_gas.ok_or(format_err!("No base fee found"))
+    }
+
+    /// Estimates the `verification_gas_limit`, `call_gas_limit` and `pre_verification_gas` for a
+    /// user operation. The function is indirectly invoked by the `estimate_user_operation_gas`
+    /// JSON RPC method.
+    ///
+    /// # Arguments
+    /// * `uo` - The [UserOperation](UserOperation) to estimate the gas for.
+    /// * `state_override` - The [StateOverride](StateOverride) the user operation is simulated
+    ///   against (e.g., a pre-funded sender).
+    ///
+    /// # Returns
+    /// `Result<UserOperationGasEstimation, MempoolError>` - The gas estimation result,
+    /// which includes the `verification_gas_limit`, `call_gas_limit` and `pre_verification_gas`.
+    pub async fn estimate_user_operation_gas(
+        &self,
+        uo: &UserOperation,
+        state_override: Option<StateOverride>,
+    ) -> Result<UserOperationGasEstimation, MempoolError> {
+        let state_override = state_override.map(spoof::State::from);
+
+        let pre_verification_gas = div_ceil(
+            self.overhead.overhead().calculate_pre_verification_gas(uo).saturating_mul(
+                U256::from(100).saturating_add(PRE_VERIFICATION_SAFE_RESERVE_PERC.into()),
+            ),
+            U256::from(100),
+        );
+
+        // partial user operations (sent without gas fields) are simulated with the estimated
+        // pre-verification gas
+        let mut uo = uo.clone();
+        if uo.pre_verification_gas.is_zero() {
+            uo.user_operation.pre_verification_gas = pre_verification_gas;
+        }
+        let uo = &uo;
+
+        let (verification_gas_limit, call_gas_limit) = match self.mode {
+            UoPoolMode::Standard => {
+                estimate_user_op_gas(&uo.user_operation, &self.entry_point, state_override.as_ref())
+                    .await
+                    .map_err(|e| match e {
+                        EntryPointError::FailedOp(op) => MempoolError {
+                            hash: uo.hash,
+                            kind: MempoolErrorKind::InvalidUserOperation(
+                                InvalidMempoolUserOperationError::Simulation(op.into()),
+                            ),
+                        },
+                        EntryPointError::ExecutionReverted(e) => MempoolError {
+                            hash: uo.hash,
+                            kind: MempoolErrorKind::InvalidUserOperation(
+                                InvalidMempoolUserOperationError::Simulation(
+                                    SimulationError::Execution { inner: e },
+                                ),
+                            ),
+                        },
+                        EntryPointError::Provider { inner } => MempoolError {
+                            hash: uo.hash,
+                            kind: MempoolErrorKind::Provider { inner },
+                        },
+                        _ => MempoolError {
+                            hash: uo.hash,
+                            kind: MempoolErrorKind::Other { inner: e.to_string() },
+                        },
+                    })?
+            }
+            UoPoolMode::Unsafe => {
+                // the call gas is derived from the paid amount, which requires a non-zero fee
+                let mut uo = uo.clone();
+                if uo.max_fee_per_gas.is_zero() {
+                    uo.user_operation.max_fee_per_gas =
+                        self.base_fee_per_gas().await.map_err(|e| MempoolError {
+                            hash: uo.hash,
+                            kind: MempoolErrorKind::Provider { inner: e.to_string() },
+                        })?;
+                }
+                let ret = self
+                    .entry_point
+                    .simulate_handle_op(uo.clone().user_operation, state_override.as_ref())
+                    .await
+                    .map_err(|e| match e {
+                        EntryPointError::FailedOp(op) => MempoolError {
+                            hash: uo.hash,
+                            kind: MempoolErrorKind::InvalidUserOperation(
+                                InvalidMempoolUserOperationError::Simulation(op.into()),
+                            ),
+                        },
+                        EntryPointError::ExecutionReverted(e) => MempoolError {
+                            hash: uo.hash,
+                            kind: MempoolErrorKind::InvalidUserOperation(
+                                InvalidMempoolUserOperationError::Simulation(
+                                    SimulationError::Execution { inner: e },
+                                ),
+                            ),
+                        },
+                        EntryPointError::Provider { inner } => MempoolError {
+                            hash: uo.hash,
+                            kind: MempoolErrorKind::Provider { inner },
+                        },
+                        _ => MempoolError {
+                            hash: uo.hash,
+                            kind: MempoolErrorKind::Other { inner: e.to_string() },
+                        },
+                    })?;
+
+                let verification_gas_limit = div_ceil(
+                    ret.pre_op_gas.saturating_sub(pre_verification_gas).saturating_mul(3.into()),
+                    2.into(),
+                );
+                let call_gas_limit = div_ceil(ret.paid, uo.user_operation.max_fee_per_gas)
+                    .saturating_sub(ret.pre_op_gas)
+                    .saturating_add(35000.into());
+
+                (verification_gas_limit, call_gas_limit)
+            }
         };
-        Ok(StakeInfoResponse {
-            stake_info,
-            is_staked: self.reputation.verify_stake("", Some(stake_info), None, None).is_ok(),
+
+        let verification_gas_limit =
+            calculate_valid_gas(verification_gas_limit, self.verification_gas_buffer.into());
+
+        // on L2s (e.g., OP Stack chains) the user operation also has to cover its share of L1 data
+        // posting
+        let pre_verification_gas =
+            pre_verification_gas.saturating_add(self.estimate_l1_gas(uo).await?);
+
+        Ok(UserOperationGasEstimation {
+            pre_verification_gas,
+            verification_gas_limit,
+            call_gas_limit,
         })
     }
+
+    /// Estimates the L1 data posting cost of the user operation in L2 gas (only if the
+    /// [OverheadConfig](OverheadConfig) has an L1 gas oracle). If the user operation doesn't
+    /// specify `max_fee_per_gas`, the current base fee is used to convert the L1 fee into gas.
+    ///
+    /// # Arguments
+    /// * `uo` - The [UserOperation](UserOperation) to estimate the L1 gas for.
+    ///
+    /// # Returns
+    /// `Result<U256, MempoolError>` - The L1 gas of the user operation.
+    async fn estimate_l1_gas(&self, uo: &UserOperation) -> Result<U256, MempoolError> {
+        if self.overhead.l1_gas_oracle.is_none() {
+            return Ok(U256::zero());
+        }
+
+        let gas_price = if uo.max_fee_per_gas.is_zero() {
+            self.base_fee_per_gas().await.map_err(|e| MempoolError {
+                hash: uo.hash,
+                kind: MempoolErrorKind::Provider { inner: e.to_string() },
+            })?
+        } else {
+            uo.max_fee_per_gas
+        };
+
+        self.overhead
+            .calculate_l1_gas(&self.entry_point, &uo.user_operation, gas_price)
+            .await
+            .map_err(|e| match e {
+                EntryPointError::Provider { inner } => {
+                    MempoolError { hash: uo.hash, kind: MempoolErrorKind::Provider { inner } }
+                }
+                _ => MempoolError {
+                    hash: uo.hash,
+                    kind: MempoolErrorKind::Other { inner: e.to_string() },
+                },
+            })
+    }
+
+    /// Filters the events logged from the [EntryPoint](EntryPoint) contract for a given user
+    /// operation hash.
+    ///
+    /// # Arguments
+    /// * `uo_hash` - The [UserOperationHash](UserOperationHash) to filter the events for.
+    ///
+    /// # Returns
+    /// `Result<Option<(UserOperationEventFilter, LogMeta)>, eyre::Error>` - The filtered event, if
+    /// any.
+    pub async fn get_user_operation_event_meta(
+        &self,
+        uo_hash: &UserOperationHash,
+    ) -> eyre::Result<Option<(UserOperationEventFilter, LogMeta)>> {
+        let mut event: Option<(UserOperationEventFilter, LogMeta)> = None;
+        let latest_block = self.entry_point.eth_client().get_block_number().await?;
+        let filter = self
+            .entry_point
+            .entry_point_api()
+            .event::<UserOperationEventFilter>()
+            .from_block(latest_block - FILTER_MAX_DEPTH)
+            .topic1(uo_hash.0);
+        let res: Vec<(UserOperationEventFilter, LogMeta)> = filter.query_with_meta().await?;
+        // It is possible have two same user operatation in same bundle
+        // see https://twitter.com/leekt216/status/1636414866662785024
+        for log_meta in res.iter() {
+            event = Some(log_meta.clone());
+        }
+        Ok(event)
+    }
+
+    /// Handles the events logged from the [EntryPoint](EntryPoint) contract since the last
+    /// processed block (e.g., blocks produced while the bundler was down): user operations
+    /// included in these blocks are removed from the mempool. The blocks are scanned in pages of
+    /// `scan_depth` blocks, so no events are missed regardless of the length of the downtime.
+    ///
+    /// # Arguments
+    /// * `last_block` - The last processed block (if `None`, only the latest `scan_depth` blocks
+    ///   are scanned).
+    /// * `scan_depth` - The number of blocks scanned at once.
+    ///
+    /// # Returns
+    /// `Result<u64, eyre::Error>` - The latest scanned block.
+    pub async fn handle_past_events(
+        &mut self,
+        last_block: Option<u64>,
+        scan_depth: u64,
+    ) -> eyre::Result<u64> {
+        let scan_depth = scan_depth.max(1);
+        let latest_block = self.entry_point.eth_client().get_block_number().await?.as_u64();
+        let mut from_block = match last_block {
+            Some(last_block) => last_block + 1,
+            None => latest_block.saturating_sub(scan_depth - 1),
+        };
+
+        while from_block <= latest_block {
+            let to_block = latest_block.min(from_block + scan_depth - 1);
+            let events = self
+                .entry_point
+                .entry_point_api()
+                .event::<UserOperationEventFilter>()
+                .from_block(from_block)
+                .to_block(to_block)
+                .query()
+                .await?;
+
+            let uos = events
+                .iter()
+                .filter_map(|event| self.mempool.get(&event.user_op_hash.into()).ok().flatten())
+                .collect::<Vec<_>>();
+            if !uos.is_empty() {
+                debug!(
+                    "Removing {} user operations included in blocks {from_block}..={to_block}",
+                    uos.len()
+                );
+                self.remove_user_operations(uos);
+            }
+
+            from_block = to_block + 1;
+        }
+
+        Ok(latest_block)
+    }
+
+    /// Removes the [UserOperations](UserOperation) included in a block from the mempool. The ones
+    /// which were in the mempool are remembered, so they can be re-admitted if the block is
+    /// reorged.
+    ///
+    /// # Arguments
+    /// * `block_number` - The number of the block
+    /// * `uos` - The [UserOperations](UserOperation) included in the block
+    pub fn remove_included_user_operations(&mut self, block_number: u64, uos: Vec<UserOperation>) {
+        let included = uos
+            .iter()
+            .filter_map(|uo| self.mempool.get(&uo.hash).ok().flatten())
+            .map(|uo| {
+                let source = self.mempool.get_source(&uo.hash);
+                (uo, source)
+            })
+            .collect();
+        self.head_tracker.record_included(block_number, included);
+        self.remove_user_operations(uos);
+    }
+
+    /// Handles a chain reorg: if the new head replaces an already seen block or its parent isn't
+    /// the head seen at the previous height, the chain is walked back to the common ancestor and
+    /// the [UserOperations](UserOperation) included in the reorged blocks are re-admitted into the
+    /// mempool (with the source they were submitted from), unless they were included in the new
+    /// chain too or don't pass validation anymore.
+    ///
+    /// # Arguments
+    /// * `number` - The number of the new head
+    /// * `hash` - The hash of the new head
+    /// * `parent_hash` - The hash of the parent of the new head
+    ///
+    /// # Returns
+    /// `Result<usize, eyre::Error>` - The number of re-admitted user operations.
+    pub async fn handle_reorg(
+        &mut self,
+        number: u64,
+        hash: H256,
+        parent_hash: H256,
+    ) -> eyre::Result<usize> {
+        let seen_parent = number.checked_sub(1).and_then(|number| self.head_tracker.hash(number));
+        if self.head_tracker.hash(number).map_or(true, |seen| seen == hash) &&
+            seen_parent.map_or(true, |seen| seen == parent_hash)
+        {
+            return Ok(0);
+        }
+
+        // walk back until the parent of the first reorged block is a seen head
+        let mut from = number;
+        let mut parent_hash = parent_hash;
+        while let Some(seen) = from.checked_sub(1).and_then(|number| self.head_tracker.hash(number))
+        {
+            if seen == parent_hash {
+                break;
+            }
+
+            from -= 1;
+            match self.entry_point.eth_client().get_block(parent_hash).await? {
+                Some(block) => parent_hash = block.parent_hash,
+                None => break,
+            }
+        }
+
+        let uos = self.head_tracker.roll_back(from, number, hash);
+        if uos.is_empty() {
+            return Ok(0);
+        }
+
+        // user operations included in the new chain stay removed
+        let events = self
+            .entry_point
+            .entry_point_api()
+            .event::<UserOperationEventFilter>()
+            .from_block(from)
+            .to_block(number)
+            .query()
+            .await?;
+        let included = events
+            .iter()
+            .map(|event| event.user_op_hash.into())
+            .collect::<HashSet<UserOperationHash>>();
+
+        let mut readmitted = 0;
+        for (uo, source) in uos.into_iter().filter(|(uo, _)| !included.contains(&uo.hash)) {
+            if let Ok(true) = self.check_duplicate(&uo) {
+                continue;
+            }
+
+            let res = self.validate_user_operation(&uo, None).await;
+            match self.add_user_operation(uo, res, source).await {
+                Ok(uo_hash) => {
+                    info!(
+                        "{uo_hash:?} re-admitted after a chain reorg in the mempool {:?}",
+                        self.id
+                    );
+                    readmitted += 1;
+                }
+                Err(err) => {
+                    debug!("{:?} not re-admitted after a chain reorg: {:?}", err.hash, err.kind)
+                }
+            }
+        }
+
+        Ok(readmitted)
+    }
+
+    /// Gets the user operation by hash.
+    /// The function is indirectly invoked by the `get_user_operation_by_hash` JSON RPC method.
+    ///
+    /// # Arguments
+    /// * `uo_hash` - The [UserOperationHash](UserOperationHash) to get the user operation for.
+    ///
+    /// # Returns
+    /// `Result<UserOperationByHash, eyre::Error>` - The user operation, if any.
+    pub async fn get_user_operation_by_hash(
+        &self,
+        uo_hash: &UserOperationHash,
+    ) -> eyre::Result<UserOperationByHash> {
+        let event = self.get_user_operation_event_meta(uo_hash).await?;
+
+        if let Some((event, log_meta)) = event {
+            if let Some((uo, ep)) = self
+                .entry_point
+                .eth_client()
+                .get_transaction(log_meta.transaction_hash)
+                .await?
+                .and_then(|tx| {
+                    let uos = parse_from_input_data(tx.input)?;
+                    let ep = tx.to?;
+                    uos.iter()
+                        .find(|uo| uo.sender == event.sender && uo.nonce == event.nonce)
+                        .map(|uo| (uo.clone(), ep))
+                })
+            {
+                return Ok(UserOperationByHash {
+                    user_operation: uo,
+                    entry_point: ep,
+                    transaction_hash: log_meta.transaction_hash,
+                    block_hash: log_meta.block_hash,
+                    block_number: log_meta.block_number,
+                });
+            }
+        }
+
+        Err(format_err!("No user operation found"))
+    }
+
+    /// Gets the [UserOperationReceipt](UserOperationReceipt) by hash.
+    /// The function is indirectly invoked by the `get_user_operation_receipt` JSON RPC method.
+    ///
+    /// # Arguments
+    /// * `uo_hash` - The [UserOperationHash](UserOperationHash) to get the user operation receipt
+    ///   for.
+    ///
+    /// # Returns
+    /// `Result<UserOperationReceipt, eyre::Error>` - The user operation receipt, if any.
+    pub async fn get_user_operation_receipt(
+        &self,
+        uo_hash: &UserOperationHash,
+    ) -> eyre::Result<UserOperationReceipt> {
+        let event = self.get_user_operation_event_meta(uo_hash).await?;
+
+        if let Some((event, log_meta)) = event {
+            if let Some(tx_receipt) = self
+                .entry_point
+                .eth_client()
+                .get_transaction_receipt(log_meta.transaction_hash)
+                .await?
+            {
+                let uo = self.get_user_operation_by_hash(uo_hash).await?;
+                let (success, reason) = extract_user_operation_outcome(
+                    uo_hash,
+                    event.success,
+                    &tx_receipt.logs,
+                    self.entry_point.version(),
+                );
+                return Ok(UserOperationReceipt {
+                    user_operation_hash: *uo_hash,
+                    sender: event.sender,
+                    nonce: event.nonce,
+                    actual_gas_cost: event.actual_gas_cost,
+                    actual_gas_used: event.actual_gas_used,
+                    success,
+                    logs: extract_user_operation_logs(uo_hash, &tx_receipt.logs),
+                    tx_receipt,
+                    paymaster: get_address(&uo.user_operation.paymaster_and_data),
+                    reason,
+                });
+            }
+        }
+
+        Err(format_err!("No user operation found"))
+    }
+
+    /// Removes the [UserOperation](UserOperation) from the user operation mempool
+    /// given the [UserOperationHash](UserOperationHash).
+    ///
+    /// # Arguments
+    /// * `uo_hash` - The [UserOperationHash](UserOperationHash) to remove the user operation for.
+    ///
+    /// # Returns
+    /// `Option<()>` - None if the user operation was successfully removed.
+    pub fn remove_user_operation(&mut self, uo_hash: &UserOperationHash) -> Option<()> {
+        self.mempool.remove(uo_hash).ok();
+        None
+    }
+
+    pub fn remove_user_operation_by_entity(&mut self, entity: &Address) -> Option<()> {
+        self.mempool.remove_by_entity(entity).ok();
+        None
+    }
+
+    /// Removes multiple [UserOperations](UserOperation) from the
+    /// user operation mempool given an array of
+    /// [UserOperation](UserOperation).
+    ///
+    /// # Arguments
+    /// * `uos` - The array of [UserOperation](UserOperation).
+    ///
+    /// # Returns
+    /// `Option<()>` - None
+    pub fn remove_user_operations(&mut self, uos: Vec<UserOperation>) -> Option<()> {
+        let uo_hashes = uos.iter().map(|uo| uo.hash).collect::<Vec<_>>();
+        if let Err(err) = self.mempool.remove_included_batch(&uo_hashes) {
+            error!("Failed to remove included user operations with error: {err:?}");
+        }
+
+        for uo in uos {
+            // update reputations
+            self.reputation.increment_included(&uo.sender).ok();
+
+            if let Some(addr) = get_address(&uo.paymaster_and_data) {
+                self.reputation.increment_included(&addr).ok();
+            }
+
+            if let Some(addr) = get_address(&uo.init_code) {
+                self.reputation.increment_included(&addr).ok();
+            }
+        }
+
+        None
+    }
+
+    /// Gets the [StakeInfoResponse](StakeInfoResponse) for entity
+    ///
+    /// # Arguments
+    /// * `addr` - The address of the entity.
+    ///
+    /// # Returns
+    /// `Result<StakeInfoResponse, eyre::Error>` - Stake info of the entity.
+    pub async fn get_stake_info(&self, addr: &Address) -> eyre::Result<StakeInfoResponse> {
+        let info = self.entry_point.get_deposit_info(addr).await?;
+        let stake_info = StakeInfo {
+            address: *addr,
+            stake: U256::from(info.stake),
+            unstake_delay: U256::from(info.unstake_delay_sec),
+        };
+        Ok(StakeInfoResponse {
+            stake_info,
+            is_staked: self.reputation.verify_stake("", Some(stake_info), None, None).is_ok(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_utils::{
+            memory_mempool, memory_reputation, test_validator, UoPoolTestBuilder, TEST_CHAIN_ID,
+        },
+        utils::Overhead,
+        L1GasOracle,
+    };
+    use enumset::EnumSet;
+    use ethers::{
+        abi::{encode, AbiEncode, Token},
+        contract::{EthError, EthEvent},
+        providers::{JsonRpcClient, JsonRpcError, MockError, MockProvider, MockResponse, Provider},
+        types::{Block, Bytes, Log, H256, U64},
+    };
+    use parking_lot::RwLock;
+    use serde::{de::DeserializeOwned, Serialize};
+    use silius_contracts::{EntryPointVersion, ExecutionResult, FailedOp};
+    use silius_primitives::{
+        constants::validation::EIP7702_INIT_CODE_MARKER,
+        simulation::{AccountOverride, CodeHash},
+        PackedUserOperation, UserOperationSigned,
+    };
+    use std::{
+        fmt::Debug,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    #[tokio::test]
+    async fn chain_context() {
+        let (provider, mock) = Provider::mocked();
+        let entry_point = EntryPoint::new(Arc::new(provider), Address::random());
+        let uopool = UoPoolTestBuilder::new(entry_point).build();
+
+        let block = Block::<H256> {
+            number: Some(U64::from(42)),
+            hash: Some(H256::random()),
+            base_fee_per_gas: Some(U256::from(7)),
+            ..Default::default()
+        };
+        mock.push(block.clone()).unwrap();
+
+        assert_eq!(
+            uopool.get_chain_context().await.unwrap(),
+            ChainContext {
+                chain_id: 1337,
+                block_number: 42,
+                block_hash: block.hash.unwrap(),
+                base_fee_per_gas: U256::from(7),
+                min_priority_fee_per_gas: U256::from(100),
+                max_verification_gas: U256::from(5_000_000),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn handle_past_events_after_downtime() {
+        let (provider, mock) = Provider::mocked();
+        let entry_point = EntryPoint::new(Arc::new(provider), Address::random());
+        let mut uopool = UoPoolTestBuilder::new(entry_point).build();
+
+        let uos = (0..2)
+            .map(|_| {
+                UserOperation::from_user_operation_signed(
+                    H256::random().into(),
+                    UserOperationSigned::default().sender(Address::random()),
+                )
+            })
+            .collect::<Vec<_>>();
+        for uo in uos.iter() {
+            uopool.mempool.add(uo.clone()).unwrap();
+        }
+
+        let event = |uo: &UserOperation| Log {
+            topics: vec![
+                UserOperationEventFilter::signature(),
+                uo.hash.0,
+                H256::from(uo.sender),
+                H256::zero(),
+            ],
+            data: encode(&[
+                Token::Uint(U256::zero()),
+                Token::Bool(true),
+                Token::Uint(U256::zero()),
+                Token::Uint(U256::zero()),
+            ])
+            .into(),
+            ..Default::default()
+        };
+
+        // the bundler was down for 2500 blocks (longer than the scan depth), the first user
+        // operation was included right after the last processed block, the second one in the
+        // last page (responses are popped in the reverse order)
+        mock.push(vec![event(&uos[1])]).unwrap();
+        mock.push(Vec::<Log>::new()).unwrap();
+        mock.push(vec![event(&uos[0])]).unwrap();
+        mock.push(U64::from(2600)).unwrap();
+
+        assert_eq!(uopool.handle_past_events(Some(100), 1000).await.unwrap(), 2600);
+        for uo in uos.iter() {
+            assert!(uopool.mempool.get(&uo.hash).unwrap().is_none());
+            assert_eq!(uopool.reputation.get(&uo.sender).unwrap().uo_included, 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn readmit_user_operations_after_reorg() {
+        let (provider, mock) = Provider::mocked();
+        let entry_point = EntryPoint::new(Arc::new(provider), Address::random());
+        let mut uopool =
+            UoPoolTestBuilder::new(entry_point).with_validator(DelayedValidator::default()).build();
+
+        // the first user operation is received over the p2p network, the third one fails the
+        // validation (nonce 3)
+        let uos = [0, 0, 3]
+            .into_iter()
+            .map(|nonce| {
+                UserOperation::from_user_operation_signed(
+                    H256::random().into(),
+                    UserOperationSigned::default()
+                        .sender(Address::random())
+                        .nonce(U256::from(nonce)),
+                )
+            })
+            .collect::<Vec<_>>();
+        uopool.mempool.add_with_source(uos[0].clone(), UserOperationSource::P2p).unwrap();
+        for uo in uos[1..].iter() {
+            uopool.mempool.add(uo.clone()).unwrap();
+        }
+
+        let event = |uo: &UserOperation| Log {
+            topics: vec![
+                UserOperationEventFilter::signature(),
+                uo.hash.0,
+                H256::from(uo.sender),
+                H256::zero(),
+            ],
+            data: encode(&[
+                Token::Uint(U256::zero()),
+                Token::Bool(true),
+                Token::Uint(U256::zero()),
+                Token::Uint(U256::zero()),
+            ])
+            .into(),
+            ..Default::default()
+        };
+
+        // blocks 10, 11 and 12 are seen, the user operations are included in blocks 11 and 12
+        let hashes = (0..3).map(|_| H256::random()).collect::<Vec<_>>();
+        for (number, hash) in (10..).zip(hashes.iter()) {
+            uopool.head_tracker.observe(number, *hash);
+        }
+        uopool.remove_included_user_operations(11, vec![uos[0].clone(), uos[2].clone()]);
+        uopool.remove_included_user_operations(12, vec![uos[1].clone()]);
+        assert!(uopool.get_all().unwrap().is_empty());
+
+        // the same head received again isn't a reorg
+        assert_eq!(uopool.handle_reorg(12, hashes[2], hashes[1]).await.unwrap(), 0);
+
+        // a new head at height 12 whose parent replaces block 11 reorgs both blocks, and only the
+        // second user operation is included in the new chain (responses are popped in the reverse
+        // order)
+        mock.push(vec![event(&uos[1])]).unwrap();
+        mock.push(Block::<H256> { parent_hash: hashes[0], ..Default::default() }).unwrap();
+        let head = H256::random();
+        assert_eq!(uopool.handle_reorg(12, head, H256::random()).await.unwrap(), 1);
+        assert!(uopool.mempool.get(&uos[0].hash).unwrap().is_some());
+        assert_eq!(uopool.get_source(&uos[0].hash), UserOperationSource::P2p);
+        assert!(uopool.mempool.get(&uos[1].hash).unwrap().is_none());
+        assert!(uopool.mempool.get(&uos[2].hash).unwrap().is_none());
+        assert_eq!(uopool.head_tracker.hash(10), Some(hashes[0]));
+        assert!(uopool.head_tracker.hash(11).is_none());
+
+        // the chain split was recorded with the reorg, observing the new head doesn't count it
+        // again
+        assert!(!uopool.head_tracker.observe(12, head));
+    }
+
+    #[tokio::test]
+    async fn sort_by_effective_priority_fee() {
+        let (provider, mock) = Provider::mocked();
+        let entry_point = EntryPoint::new(Arc::new(provider), Address::random());
+        let mut uopool = UoPoolTestBuilder::new(entry_point).build();
+
+        // the highest raw priority fee is capped by a low max fee, the lowest one doesn't cover
+        // the base fee
+        for (max_priority_fee, max_fee) in [(2, 12), (3, 10), (1, 20), (5, 9)] {
+            uopool
+                .mempool
+                .add(UserOperation::from_user_operation_signed(
+                    H256::random().into(),
+                    UserOperationSigned::default()
+                        .sender(Address::random())
+                        .max_priority_fee_per_gas(U256::from(max_priority_fee))
+                        .max_fee_per_gas(U256::from(max_fee)),
+                ))
+                .unwrap();
+        }
+
+        let fees = |uos: Vec<UserOperation>| {
+            uos.iter().map(|uo| uo.max_priority_fee_per_gas.as_u64()).collect::<Vec<_>>()
+        };
+
+        // effective priority fees of 2, 0 and 1 with a base fee of 10
+        mock.push(Block::<H256> { base_fee_per_gas: Some(U256::from(10)), ..Default::default() })
+            .unwrap();
+        assert_eq!(fees(uopool.get_sorted_user_operations().await.unwrap()), vec![2, 1, 3]);
+
+        // the raw priority fees are used if the base fee is unavailable
+        assert_eq!(fees(uopool.get_sorted_user_operations().await.unwrap()), vec![5, 3, 2, 1]);
+
+        // a subsidized bundler keeps the user operation which doesn't cover the base fee (with
+        // zero effective priority fee, like the second one)
+        uopool.mempool = uopool.mempool.clone().with_subsidized(true);
+        mock.push(Block::<H256> { base_fee_per_gas: Some(U256::from(10)), ..Default::default() })
+            .unwrap();
+        let sorted = fees(uopool.get_sorted_user_operations().await.unwrap());
+        assert_eq!(sorted.len(), 4);
+        assert_eq!(sorted[..2], [2, 1]);
+    }
+
+    #[tokio::test]
+    async fn promote_delayed_user_operations() {
+        let (provider, _) = Provider::mocked();
+        let entry_point = EntryPoint::new(Arc::new(provider), Address::random());
+        let mut uopool = UoPoolTestBuilder::new(entry_point)
+            .with_validator(DelayedValidator::default())
+            .with_mempool(memory_mempool().with_max_uos_per_sender(UserOperationSource::Rpc, 2))
+            .build()
+            .with_max_delayed(2);
+
+        let valid_after = U256::from(1_700_000_000);
+        let sender = Address::random();
+        let new_uo = |sender: Address, nonce: u64| {
+            UserOperation::from_user_operation_signed(
+                H256::random().into(),
+                UserOperationSigned::default().sender(sender).nonce(U256::from(nonce)),
+            )
+        };
+        let uo = new_uo(sender, 0);
+        let outcome =
+            UserOperationValidationOutcome { valid_after: Some(valid_after), ..Default::default() };
+
+        // not valid yet, so held in the delayed queue instead of the mempool
+        let uo_hash = uopool
+            .add_user_operation(uo.clone(), Ok(outcome.clone()), UserOperationSource::Rpc)
+            .await
+            .unwrap();
+        assert!(uopool.mempool.get(&uo_hash).unwrap().is_none());
+        assert_eq!(uopool.mempool.get_delayed().len(), 1);
+        assert_eq!(uopool.reputation.get(&uo.sender).unwrap().uo_seen, 1);
+
+        assert!(uopool.promote_delayed_user_operations(valid_after - 1).await.is_empty());
+        assert!(uopool.mempool.get(&uo_hash).unwrap().is_none());
+
+        // fails the re-validation on promotion (nonce 3)
+        uopool
+            .add_user_operation(new_uo(sender, 3), Ok(outcome.clone()), UserOperationSource::Rpc)
+            .await
+            .unwrap();
+
+        // the delayed user operations count toward the limit per sender
+        let err = uopool
+            .add_user_operation(
+                new_uo(sender, 1),
+                Ok(UserOperationValidationOutcome::default()),
+                UserOperationSource::Rpc,
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.kind,
+            MempoolErrorKind::InvalidUserOperation(InvalidMempoolUserOperationError::Sanity(
+                SanityError::Sender { .. }
+            ))
+        ));
+
+        // no room left in the delayed queue
+        let err = uopool
+            .add_user_operation(
+                new_uo(Address::random(), 0),
+                Ok(outcome.clone()),
+                UserOperationSource::Rpc,
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err.kind, MempoolErrorKind::DelayedQueueFull { .. }));
+
+        // time advanced past validAfter (the user operation failing the re-validation is dropped)
+        assert_eq!(uopool.promote_delayed_user_operations(valid_after + 12).await, vec![uo_hash]);
+        assert!(uopool.mempool.get(&uo_hash).unwrap().is_some());
+        assert!(uopool.mempool.get_delayed().is_empty());
+        assert_eq!(uopool.get_sorted_user_operations().await.unwrap().len(), 1);
+
+        // rejected altogether with the reject policy
+        let mut uopool = uopool.with_valid_after_policy(ValidAfterPolicy::Reject);
+        uopool.clear();
+        let err =
+            uopool.add_user_operation(uo, Ok(outcome), UserOperationSource::Rpc).await.unwrap_err();
+        assert!(matches!(
+            err.kind,
+            MempoolErrorKind::InvalidUserOperation(InvalidMempoolUserOperationError::Simulation(
+                SimulationError::Timestamp { .. }
+            ))
+        ));
+        assert!(uopool.mempool.get_delayed().is_empty());
+        assert!(uopool.get_all().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn hold_gapped_user_operations() {
+        let (provider, mock) = Provider::mocked();
+        let entry_point = EntryPoint::new(Arc::new(provider), Address::random());
+        let nonce_gap = NonceGapConfig { max_gap: 2, max_held: 8, ttl: Duration::from_secs(600) };
+        let mut uopool = UoPoolTestBuilder::new(entry_point)
+            .with_validator(DelayedValidator::default())
+            .build()
+            .with_nonce_gap(Some(nonce_gap));
+
+        let sender = Address::random();
+        let new_uo_with_fee = |nonce: u64, fee: u64| {
+            UserOperation::from_user_operation_signed(
+                H256::random().into(),
+                UserOperationSigned::default()
+                    .sender(sender)
+                    .nonce(nonce.into())
+                    .max_fee_per_gas(fee.into())
+                    .max_priority_fee_per_gas(fee.into()),
+            )
+        };
+        let new_uo = |nonce: u64| new_uo_with_fee(nonce, 0);
+        // on-chain nonce of the sender
+        let on_chain_nonce = || Bytes::from(U256::zero().encode());
+        let nonce_err = || {
+            Err(InvalidMempoolUserOperationError::Simulation(SimulationError::Nonce {
+                inner: "AA25 invalid account nonce".into(),
+            }))
+        };
+        let mut new_uos = uopool.mempool.subscribe();
+
+        // the entry point rejects the user operation with a nonce gap, so it's held
+        mock.push(on_chain_nonce()).unwrap();
+        let gapped = new_uo(1);
+        uopool
+            .add_user_operation(gapped.clone(), nonce_err(), UserOperationSource::Rpc)
+            .await
+            .unwrap();
+        assert!(uopool.mempool.get(&gapped.hash).unwrap().is_none());
+        assert_eq!(uopool.mempool.get_gapped().len(), 1);
+        assert!(uopool.get_sorted_user_operations().await.unwrap().is_empty());
+        assert!(new_uos.try_recv().is_err());
+
+        // the nonce gap is too large
+        mock.push(on_chain_nonce()).unwrap();
+        assert!(matches!(
+            uopool
+                .add_user_operation(new_uo(5), Ok(Default::default()), UserOperationSource::Rpc)
+                .await,
+            Err(MempoolError { kind: MempoolErrorKind::NonceGap { .. }, .. })
+        ));
+
+        // the user operation filling the gap promotes the held one
+        mock.push(on_chain_nonce()).unwrap();
+        let filler = new_uo(0);
+        uopool
+            .add_user_operation(filler.clone(), Ok(Default::default()), UserOperationSource::Rpc)
+            .await
+            .unwrap();
+        assert!(uopool.mempool.get_gapped().is_empty());
+        let uos = uopool.get_sorted_user_operations().await.unwrap();
+        assert_eq!(uos.len(), 2);
+        assert!(uos.iter().any(|uo| uo.hash == filler.hash));
+        assert!(uos.iter().any(|uo| uo.hash == gapped.hash));
+
+        // the subscribers are notified of the promoted user operation too
+        assert_eq!(new_uos.try_recv().unwrap().user_operation.hash, filler.hash);
+        assert_eq!(new_uos.try_recv().unwrap().user_operation.hash, gapped.hash);
+
+        // a held user operation is only replaced with the fee increase
+        mock.push(on_chain_nonce()).unwrap();
+        let held = new_uo_with_fee(3, 100);
+        uopool
+            .add_user_operation(held.clone(), nonce_err(), UserOperationSource::Rpc)
+            .await
+            .unwrap();
+        assert!(matches!(
+            uopool
+                .add_user_operation(new_uo_with_fee(3, 105), nonce_err(), UserOperationSource::Rpc)
+                .await,
+            Err(MempoolError { kind: MempoolErrorKind::ReplacementUnderpriced { .. }, .. })
+        ));
+        assert_eq!(uopool.mempool.get_gapped().len(), 1);
+
+        // the held user operation is validated again on promotion and dropped as it fails (nonce 3)
+        mock.push(on_chain_nonce()).unwrap();
+        uopool
+            .add_user_operation(new_uo(2), Ok(Default::default()), UserOperationSource::Rpc)
+            .await
+            .unwrap();
+        assert!(uopool.mempool.get_gapped().is_empty());
+        assert!(uopool.mempool.get(&held.hash).unwrap().is_none());
+        assert_eq!(uopool.get_sorted_user_operations().await.unwrap().len(), 3);
+
+        // held user operations are dropped once the TTL passes
+        let mut uopool =
+            uopool.with_nonce_gap(Some(NonceGapConfig { ttl: Duration::ZERO, ..nonce_gap }));
+        mock.push(on_chain_nonce()).unwrap();
+        let expired = new_uo(4);
+        uopool
+            .add_user_operation(expired.clone(), Ok(Default::default()), UserOperationSource::Rpc)
+            .await
+            .unwrap();
+        assert_eq!(uopool.mempool.get_gapped().len(), 1);
+        assert_eq!(uopool.remove_expired_gapped_user_operations(), vec![expired.hash]);
+        assert!(uopool.mempool.get_gapped().is_empty());
+    }
+
+    #[tokio::test]
+    async fn eip7702_authorization_dropped_before_hashing() {
+        let (provider, _) = Provider::mocked();
+        let entry_point = EntryPoint::new(Arc::new(provider), Address::random());
+        let ep = entry_point.address();
+        let validator = test_validator(entry_point.clone()).with_eip7702_senders(true);
+        let uopool = UoPoolTestBuilder::new(entry_point.clone()).with_validator(validator).build();
+
+        let signed = UserOperationSigned::default().sender(Address::random());
+        let uo = UserOperation::from_user_operation_signed(
+            H256::random().into(),
+            signed.clone().init_code(
+                [Address::from_low_u64_be(EIP7702_INIT_CODE_MARKER).as_bytes(), &[0x01; 32]]
+                    .concat()
+                    .into(),
+            ),
+        );
+
+        // the entry point receives the user operation without the authorization
+        let normalized = uopool.normalize_user_operation(uo.clone());
+        assert!(normalized.init_code.is_empty());
+        assert_eq!(normalized.hash, signed.hash(&ep, TEST_CHAIN_ID));
+
+        // other initCode is kept
+        let factory_uo = UserOperation::from_user_operation_signed(
+            H256::random().into(),
+            signed.init_code(Address::random().as_bytes().to_vec().into()),
+        );
+        let normalized = uopool.normalize_user_operation(factory_uo.clone());
+        assert_eq!(normalized.user_operation, factory_uo.user_operation);
+        assert_eq!(normalized.hash, factory_uo.user_operation.hash(&ep, TEST_CHAIN_ID));
+
+        // without the EIP-7702 mode, the user operation is kept as is
+        let uopool = UoPoolTestBuilder::new(entry_point).build();
+        let normalized = uopool.normalize_user_operation(uo.clone());
+        assert_eq!(normalized.user_operation, uo.user_operation);
+        assert_eq!(normalized.hash, uo.user_operation.hash(&ep, TEST_CHAIN_ID));
+    }
+
+    #[tokio::test]
+    async fn user_operation_hashed_for_entry_point_version() {
+        let (provider, _) = Provider::mocked();
+        let entry_point = EntryPoint::new(Arc::new(provider), Address::random())
+            .with_version(EntryPointVersion::V0_7);
+        let ep = entry_point.address();
+        let uopool = UoPoolTestBuilder::new(entry_point).build();
+
+        // the hash sent over RPC (or p2p) is replaced with the one of the entry point v0.7
+        let signed = UserOperationSigned::default().sender(Address::random());
+        let uo = UserOperation::from_user_operation_signed(signed.hash(&ep, TEST_CHAIN_ID), signed);
+        let normalized = uopool.normalize_user_operation(uo.clone());
+        assert_eq!(
+            normalized.hash,
+            PackedUserOperation::try_from(uo.user_operation.clone())
+                .unwrap()
+                .hash(&ep, TEST_CHAIN_ID)
+        );
+        assert_ne!(normalized.hash, uo.hash);
+
+        // user operations which can't be packed keep the hash of the entry point v0.6
+        let unpackable = uo.user_operation.call_gas_limit(U256::MAX);
+        let normalized = uopool.normalize_user_operation(
+            UserOperation::from_user_operation_signed(H256::random().into(), unpackable.clone()),
+        );
+        assert_eq!(normalized.hash, unpackable.hash(&ep, TEST_CHAIN_ID));
+    }
+
+    #[tokio::test]
+    async fn re_received_user_operation_seen_once() {
+        let (provider, _) = Provider::mocked();
+        let entry_point = EntryPoint::new(Arc::new(provider), Address::random());
+        let mut uopool = UoPoolTestBuilder::new(entry_point).build();
+
+        let paymaster = Address::random();
+        let uo = UserOperation::from_user_operation_signed(
+            H256::random().into(),
+            UserOperationSigned {
+                paymaster_and_data: paymaster.as_bytes().to_vec().into(),
+                ..UserOperationSigned::default().sender(Address::random())
+            },
+        );
+
+        // imported via RPC
+        let uo_hash = uopool
+            .add_user_operation(uo.clone(), Ok(Default::default()), UserOperationSource::Rpc)
+            .await
+            .unwrap();
+        assert_eq!(uopool.reputation.get(&uo.sender).unwrap().uo_seen, 1);
+        assert_eq!(uopool.reputation.get(&paymaster).unwrap().uo_seen, 1);
+
+        // re-received over the p2p network (the validation outcome points to the same user
+        // operation as the previous one)
+        let outcome =
+            UserOperationValidationOutcome { prev_hash: Some(uo_hash), ..Default::default() };
+        assert_eq!(
+            uopool
+                .add_user_operation(uo.clone(), Ok(outcome), UserOperationSource::P2p)
+                .await
+                .unwrap(),
+            uo_hash
+        );
+        assert_eq!(uopool.reputation.get(&uo.sender).unwrap().uo_seen, 1);
+        assert_eq!(uopool.reputation.get(&paymaster).unwrap().uo_seen, 1);
+        assert_eq!(uopool.mempool.get_source(&uo_hash), UserOperationSource::Rpc);
+        assert_eq!(uopool.get_all().unwrap().len(), 1);
+
+        // found before the validation (which fails without the provider responses)
+        assert!(uopool.check_duplicate(&uo).unwrap());
+        uopool.add_user_operations(vec![uo.clone()], None).await.unwrap();
+        assert_eq!(uopool.reputation.get(&uo.sender).unwrap().uo_seen, 1);
+    }
+
+    #[tokio::test]
+    async fn replacement_fee_increase() {
+        let (provider, _) = Provider::mocked();
+        let entry_point = EntryPoint::new(Arc::new(provider), Address::random());
+        let mut uopool = UoPoolTestBuilder::new(entry_point).build();
+
+        let sender = Address::random();
+        let new_uo = |nonce: u64, max_fee_per_gas: u64, max_priority_fee_per_gas: u64| {
+            UserOperation::from_user_operation_signed(
+                H256::random().into(),
+                UserOperationSigned::default()
+                    .sender(sender)
+                    .nonce(nonce.into())
+                    .max_fee_per_gas(max_fee_per_gas.into())
+                    .max_priority_fee_per_gas(max_priority_fee_per_gas.into()),
+            )
+        };
+
+        let uo_hash = uopool
+            .add_user_operation(
+                new_uo(0, 100, 10),
+                Ok(Default::default()),
+                UserOperationSource::Rpc,
+            )
+            .await
+            .unwrap();
+        let outcome =
+            || UserOperationValidationOutcome { prev_hash: Some(uo_hash), ..Default::default() };
+
+        // just under the 10% increase of either of the fees
+        for uo in [new_uo(0, 110, 10), new_uo(0, 109, 11)] {
+            assert!(matches!(
+                uopool.add_user_operation(uo, Ok(outcome()), UserOperationSource::Rpc).await,
+                Err(MempoolError {
+                    kind: MempoolErrorKind::ReplacementUnderpriced {
+                        max_fee_per_gas,
+                        max_priority_fee_per_gas,
+                    },
+                    ..
+                }) if max_fee_per_gas == U256::from(110) && max_priority_fee_per_gas == U256::from(11)
+            ));
+        }
+        assert_eq!(uopool.get_all().unwrap().len(), 1);
+
+        // user operations with another nonce don't replace anything
+        uopool
+            .add_user_operation(
+                new_uo(1, 100, 10),
+                Ok(Default::default()),
+                UserOperationSource::Rpc,
+            )
+            .await
+            .unwrap();
+        assert_eq!(uopool.get_all().unwrap().len(), 2);
+
+        // exactly the 10% increase of both fees
+        let replacement = new_uo(0, 110, 11);
+        uopool
+            .add_user_operation(replacement.clone(), Ok(outcome()), UserOperationSource::Rpc)
+            .await
+            .unwrap();
+        let uos = uopool.get_all().unwrap();
+        assert_eq!(uos.len(), 2);
+        assert!(uos.iter().any(|uo| uo.hash == replacement.hash));
+        assert!(uos.iter().all(|uo| uo.hash != uo_hash));
+
+        // the required increase is configurable
+        let mut uopool = uopool.with_replacement_fee_increase(20);
+        assert!(matches!(
+            uopool
+                .add_user_operation(new_uo(0, 121, 13), Ok(outcome()), UserOperationSource::Rpc)
+                .await,
+            Err(MempoolError { kind: MempoolErrorKind::ReplacementUnderpriced { .. }, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn replacement_policy() {
+        let (provider, _) = Provider::mocked();
+        let entry_point = EntryPoint::new(Arc::new(provider), Address::random());
+        let mut uopool = UoPoolTestBuilder::new(entry_point).build();
+
+        let sender = Address::random();
+        let new_uo = |max_fee_per_gas: u64, max_priority_fee_per_gas: u64| {
+            UserOperation::from_user_operation_signed(
+                H256::random().into(),
+                UserOperationSigned::default()
+                    .sender(sender)
+                    .max_fee_per_gas(max_fee_per_gas.into())
+                    .max_priority_fee_per_gas(max_priority_fee_per_gas.into()),
+            )
+        };
+        let outcome = |prev_hash| UserOperationValidationOutcome {
+            prev_hash: Some(prev_hash),
+            ..Default::default()
+        };
+
+        let local_hash = uopool
+            .add_user_operation(new_uo(100, 10), Ok(Default::default()), UserOperationSource::Rpc)
+            .await
+            .unwrap();
+
+        // the 10% increase isn't enough for a p2p user operation to replace a local one
+        assert!(matches!(
+            uopool
+                .add_user_operation(
+                    new_uo(110, 11),
+                    Ok(outcome(local_hash)),
+                    UserOperationSource::P2p
+                )
+                .await,
+            Err(MempoolError {
+                kind: MempoolErrorKind::ReplacementUnderpriced {
+                    max_fee_per_gas,
+                    max_priority_fee_per_gas,
+                },
+                ..
+            }) if max_fee_per_gas == U256::from(120) && max_priority_fee_per_gas == U256::from(12)
+        ));
+        let uos = uopool.get_all().unwrap();
+        assert_eq!(uos.len(), 1);
+        assert_eq!(uos[0].hash, local_hash);
+
+        // twice the increase is
+        let p2p_hash = uopool
+            .add_user_operation(new_uo(120, 12), Ok(outcome(local_hash)), UserOperationSource::P2p)
+            .await
+            .unwrap();
+        let uos = uopool.get_all().unwrap();
+        assert_eq!(uos.len(), 1);
+        assert_eq!(uos[0].hash, p2p_hash);
+        assert_eq!(uopool.mempool.get_source(&p2p_hash), UserOperationSource::P2p);
+
+        // local user operations replace p2p ones with the regular increase
+        let local_hash = uopool
+            .add_user_operation(new_uo(132, 14), Ok(outcome(p2p_hash)), UserOperationSource::Rpc)
+            .await
+            .unwrap();
+        assert_eq!(uopool.get_all().unwrap()[0].hash, local_hash);
+
+        // sources are treated equally if local user operations aren't preferred
+        let mut uopool = uopool.with_replacement_policy(ReplacementPolicy::Equal);
+        let p2p_hash = uopool
+            .add_user_operation(new_uo(146, 16), Ok(outcome(local_hash)), UserOperationSource::P2p)
+            .await
+            .unwrap();
+        assert_eq!(uopool.get_all().unwrap()[0].hash, p2p_hash);
+
+        // replacements aren't rejected by the per-sender limit of the source
+        uopool.mempool =
+            uopool.mempool.clone().with_max_uos_per_sender(UserOperationSource::P2p, 1);
+        let p2p_hash = uopool
+            .add_user_operation(new_uo(161, 18), Ok(outcome(p2p_hash)), UserOperationSource::P2p)
+            .await
+            .unwrap();
+        assert_eq!(uopool.get_all().unwrap()[0].hash, p2p_hash);
+
+        let uo = new_uo(161, 18);
+        let uo = UserOperation::from_user_operation_signed(
+            uo.hash,
+            uo.user_operation.clone().nonce(1.into()),
+        );
+        assert!(uopool
+            .add_user_operation(uo, Ok(Default::default()), UserOperationSource::P2p)
+            .await
+            .is_err());
+        let uos = uopool.get_all().unwrap();
+        assert_eq!(uos.len(), 1);
+        assert_eq!(uos[0].hash, p2p_hash);
+    }
+
+    #[tokio::test]
+    async fn notify_added_user_operations() {
+        let (provider, _) = Provider::mocked();
+        let entry_point = EntryPoint::new(Arc::new(provider), Address::random());
+        let mut uopool = UoPoolTestBuilder::new(entry_point.clone()).build();
+
+        let mut new_uos = uopool.mempool.subscribe();
+
+        let uo = UserOperation::from_user_operation_signed(
+            H256::random().into(),
+            UserOperationSigned::default().sender(Address::random()),
+        );
+        uopool
+            .add_user_operation(uo.clone(), Ok(Default::default()), UserOperationSource::Rpc)
+            .await
+            .unwrap();
+
+        let new_uo = new_uos.try_recv().unwrap();
+        assert_eq!(new_uo.user_operation.hash, uo.hash);
+        assert_eq!(new_uo.entry_point, entry_point.address());
+
+        // rejected user operations aren't notified
+        let err = uopool
+            .add_user_operation(
+                uo.clone(),
+                Err(InvalidMempoolUserOperationError::Simulation(SimulationError::Timestamp {
+                    inner: String::new(),
+                })),
+                UserOperationSource::Rpc,
+            )
+            .await;
+        assert!(err.is_err());
+        assert!(new_uos.try_recv().is_err());
+
+        // the subscription is cleaned up once the receiver is dropped
+        assert_eq!(uopool.mempool.subscriber_count(), 1);
+        drop(new_uos);
+        assert_eq!(uopool.mempool.subscriber_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn cancel_user_operation() {
+        let (provider, _) = Provider::mocked();
+        let entry_point = EntryPoint::new(Arc::new(provider), Address::random());
+        let mut uopool = UoPoolTestBuilder::new(entry_point).build();
+
+        let sender = Address::random();
+        let new_uo = |call_data: Bytes, max_fee_per_gas: u64, max_priority_fee_per_gas: u64| {
+            UserOperation::from_user_operation_signed(
+                H256::random().into(),
+                UserOperationSigned::default()
+                    .sender(sender)
+                    .call_data(call_data)
+                    .max_fee_per_gas(max_fee_per_gas.into())
+                    .max_priority_fee_per_gas(max_priority_fee_per_gas.into()),
+            )
+        };
+        let outcome = |prev_hash| UserOperationValidationOutcome {
+            prev_hash: Some(prev_hash),
+            ..Default::default()
+        };
+
+        let mut new_uos = uopool.mempool.subscribe();
+
+        let pending_hash = uopool
+            .add_user_operation(
+                new_uo(Bytes::from(vec![1; 4]), 100, 10),
+                Ok(Default::default()),
+                UserOperationSource::Rpc,
+            )
+            .await
+            .unwrap();
+        assert_eq!(new_uos.try_recv().unwrap().cancels, None);
+
+        // a regular replacement doesn't cancel the pending user operation
+        let replaced_hash = uopool
+            .add_user_operation(
+                new_uo(Bytes::from(vec![2; 4]), 110, 11),
+                Ok(outcome(pending_hash)),
+                UserOperationSource::Rpc,
+            )
+            .await
+            .unwrap();
+        assert_eq!(new_uos.try_recv().unwrap().cancels, None);
+
+        // the cancellation still has to bump the fees
+        assert!(uopool
+            .add_user_operation(
+                new_uo(Bytes::default(), 110, 11),
+                Ok(outcome(replaced_hash)),
+                UserOperationSource::Rpc,
+            )
+            .await
+            .is_err());
+        assert!(new_uos.try_recv().is_err());
+
+        let cancel_hash = uopool
+            .add_user_operation(
+                new_uo(Bytes::default(), 121, 13),
+                Ok(outcome(replaced_hash)),
+                UserOperationSource::Rpc,
+            )
+            .await
+            .unwrap();
+        let uos = uopool.get_all().unwrap();
+        assert_eq!(uos.len(), 1);
+        assert_eq!(uos[0].hash, cancel_hash);
+
+        let new_uo = new_uos.try_recv().unwrap();
+        assert_eq!(new_uo.user_operation.hash, cancel_hash);
+        assert_eq!(new_uo.cancels, Some(replaced_hash));
+    }
+
+    #[tokio::test]
+    async fn verification_gas_buffer() {
+        let (provider, mock) = Provider::mocked();
+        let entry_point = EntryPoint::new(Arc::new(provider), Address::random());
+        let mut uopool = UoPoolTestBuilder::new(entry_point).with_mode(UoPoolMode::Unsafe).build();
+
+        let uo = UserOperation::from_user_operation_signed(
+            H256::random().into(),
+            UserOperationSigned::default()
+                .sender(Address::random())
+                .max_fee_per_gas(U256::from(1_000_000_000))
+                .max_priority_fee_per_gas(U256::from(1_000_000_000)),
+        );
+
+        let pre_op_gas = U256::from(1_000_000);
+        let execution_result = || {
+            MockResponse::Error(JsonRpcError {
+                code: -32000,
+                message: "execution reverted".into(),
+                data: Some(serde_json::Value::String(
+                    Bytes::from(
+                        [
+                            ExecutionResult::selector().as_slice(),
+                            &(
+                                pre_op_gas,
+                                U256::from(2_000_000_000_000_000_u64),
+                                U256::zero(),
+                                U256::zero(),
+                                true,
+                                Bytes::default(),
+                            )
+                                .encode(),
+                        ]
+                        .concat(),
+                    )
+                    .to_string(),
+                )),
+            })
+        };
+
+        // no buffer is added by default
+        mock.push_response(execution_result());
+        let raw = uopool.estimate_user_operation_gas(&uo, None).await.unwrap();
+        assert_eq!(
+            raw.verification_gas_limit,
+            div_ceil(
+                pre_op_gas.saturating_sub(raw.pre_verification_gas).saturating_mul(3.into()),
+                2.into()
+            )
+        );
+
+        uopool.verification_gas_buffer = 20;
+        mock.push_response(execution_result());
+        let buffered = uopool.estimate_user_operation_gas(&uo, None).await.unwrap();
+        assert_eq!(
+            buffered.verification_gas_limit,
+            calculate_valid_gas(raw.verification_gas_limit, U256::from(20))
+        );
+        assert!(buffered.verification_gas_limit > raw.verification_gas_limit);
+        assert_eq!(buffered.call_gas_limit, raw.call_gas_limit);
+        assert_eq!(buffered.pre_verification_gas, raw.pre_verification_gas);
+    }
+
+    /// Provider whose `simulateHandleOp` only succeeds if the balance of the sender is overridden
+    #[derive(Debug)]
+    struct PrefundProvider {
+        sender: Address,
+    }
+
+    #[async_trait::async_trait]
+    impl JsonRpcClient for PrefundProvider {
+        type Error = MockError;
+
+        async fn request<T, R>(&self, method: &str, params: T) -> Result<R, MockError>
+        where
+            T: Debug + Serialize + Send + Sync,
+            R: DeserializeOwned + Send,
+        {
+            assert_eq!(method, "eth_call");
+            let params = serde_json::to_value(params)?;
+            let data = if params[2][format!("{:?}", self.sender)]["balance"].is_string() {
+                [
+                    ExecutionResult::selector().as_slice(),
+                    &(
+                        U256::from(1_000_000),
+                        U256::from(2_000_000_000_000_000_u64),
+                        U256::zero(),
+                        U256::zero(),
+                        true,
+                        Bytes::default(),
+                    )
+                        .encode(),
+                ]
+                .concat()
+            } else {
+                [
+                    FailedOp::selector().as_slice(),
+                    &(U256::zero(), "AA21 didn't pay prefund".to_string()).encode(),
+                ]
+                .concat()
+            };
+            Err(MockError::JsonRpcError(JsonRpcError {
+                code: -32000,
+                message: "execution reverted".into(),
+                data: Some(serde_json::Value::String(Bytes::from(data).to_string())),
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn estimate_with_state_override() {
+        let sender = Address::random();
+        let entry_point =
+            EntryPoint::new(Arc::new(Provider::new(PrefundProvider { sender })), Address::random());
+        let uopool = UoPoolTestBuilder::new(entry_point).with_mode(UoPoolMode::Unsafe).build();
+
+        let uo = UserOperation::from_user_operation_signed(
+            H256::random().into(),
+            UserOperationSigned::default()
+                .sender(sender)
+                .max_fee_per_gas(U256::from(1_000_000_000))
+                .max_priority_fee_per_gas(U256::from(1_000_000_000)),
+        );
+
+        // the sender can't pay the prefund
+        let err = uopool.estimate_user_operation_gas(&uo, None).await.unwrap_err();
+        assert!(matches!(
+            err.kind,
+            MempoolErrorKind::InvalidUserOperation(InvalidMempoolUserOperationError::Simulation(
+                SimulationError::Validation { ref inner }
+            )) if inner.contains("AA21")
+        ));
+
+        // the sender is funded by the state override
+        let state_override = StateOverride(HashMap::from([(
+            sender,
+            AccountOverride { balance: Some(U256::exp10(18)), ..Default::default() },
+        )]));
+        let estimate = uopool.estimate_user_operation_gas(&uo, Some(state_override)).await.unwrap();
+        assert!(!estimate.verification_gas_limit.is_zero());
+        assert!(!estimate.call_gas_limit.is_zero());
+    }
+
+    #[tokio::test]
+    async fn simulate_bundle_drops_reverting_user_operations() {
+        let (provider, mock) = Provider::mocked();
+        let entry_point = EntryPoint::new(Arc::new(provider), Address::random());
+        let uopool = UoPoolTestBuilder::new(entry_point).build().with_bundle_simulation(true);
+
+        let uos = (0..2)
+            .map(|_| {
+                UserOperation::from_user_operation_signed(
+                    H256::random().into(),
+                    UserOperationSigned::default().sender(Address::random()),
+                )
+            })
+            .collect::<Vec<_>>();
+        let storage_maps = uos
+            .iter()
+            .map(|uo| StorageMap {
+                root_hashes: HashMap::from([(uo.sender, H256::random())]),
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+
+        // the second user operation only reverts after the first one (e.g., both spend the same
+        // paymaster deposit), so the bundle reverts and passes once the second one is dropped
+        // (responses are popped in the reverse order)
+        mock.push(Bytes::default()).unwrap();
+        mock.push_response(MockResponse::Error(JsonRpcError {
+            code: -32000,
+            message: "execution reverted".into(),
+            data: Some(serde_json::Value::String(
+                Bytes::from(
+                    [
+                        FailedOp::selector().as_slice(),
+                        &(U256::from(1), "AA31 paymaster deposit too low".to_string()).encode(),
+                    ]
+                    .concat(),
+                )
+                .to_string(),
+            )),
+        }));
+
+        let (uos_bundled, storage_maps_bundled) =
+            uopool.simulate_bundle(uos.clone(), storage_maps.clone()).await.unwrap();
+        assert_eq!(uos_bundled.iter().map(|uo| uo.hash).collect::<Vec<_>>(), vec![uos[0].hash]);
+        assert_eq!(storage_maps_bundled.len(), 1);
+        assert_eq!(storage_maps_bundled[0].root_hashes, storage_maps[0].root_hashes);
+    }
+
+    #[tokio::test]
+    async fn pause_bundling_on_chain_split() {
+        let (provider, _) = Provider::mocked();
+        let entry_point = EntryPoint::new(Arc::new(provider), Address::random());
+        let mut uopool = UoPoolTestBuilder::new(entry_point)
+            .build()
+            .with_head_tracker(HeadTracker::new(Some(2)));
+
+        uopool
+            .mempool
+            .add(UserOperation::from_user_operation_signed(
+                H256::random().into(),
+                UserOperationSigned::default().sender(Address::random()),
+            ))
+            .unwrap();
+
+        // the same head received twice isn't a split
+        let head_tracker = uopool.head_tracker.clone();
+        let hash = H256::random();
+        assert!(!head_tracker.observe(10, hash));
+        assert!(!head_tracker.observe(10, hash));
+        assert_eq!(uopool.get_sorted_user_operations().await.unwrap().len(), 1);
+
+        // conflicting head at the same height
+        assert!(head_tracker.observe(10, H256::random()));
+        assert!(uopool.get_sorted_user_operations().await.unwrap().is_empty());
+
+        // still paused until 2 blocks passed without a split
+        assert!(!head_tracker.observe(11, H256::random()));
+        assert!(uopool.get_sorted_user_operations().await.unwrap().is_empty());
+        assert!(!head_tracker.observe(12, H256::random()));
+        assert_eq!(uopool.get_sorted_user_operations().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn page_through_mempool() {
+        let (provider, _) = Provider::mocked();
+        let entry_point = EntryPoint::new(Arc::new(provider), Address::random());
+        let mut uopool = UoPoolTestBuilder::new(entry_point).build();
+
+        // 3 senders with 3 user operations each
+        for _ in 0..3 {
+            let sender = Address::random();
+            for nonce in 0..3 {
+                uopool
+                    .mempool
+                    .add(UserOperation::from_user_operation_signed(
+                        H256::random().into(),
+                        UserOperationSigned::default().sender(sender).nonce(U256::from(nonce)),
+                    ))
+                    .unwrap();
+            }
+        }
+
+        // the whole mempool is dumped unless the max dump size is set
+        let (page, next, _) = uopool.get_page(None, None).unwrap();
+        assert_eq!(page.len(), 9);
+        assert!(next.is_none());
+        let mut uopool = uopool.with_max_dump_size(Some(4));
+        assert_eq!(uopool.get_page(None, None).unwrap().0.len(), 4);
+        assert_eq!(uopool.get_page(None, Some(10)).unwrap().0.len(), 4);
+
+        let mut uos = vec![];
+        let mut after = None;
+        loop {
+            let (page, next, total) = uopool.get_page(after, Some(3)).unwrap();
+            assert_eq!(total, 9);
+            uos.extend(page);
+            if next.is_none() {
+                break;
+            }
+            after = next;
+        }
+
+        let mut all = uopool.get_all().unwrap();
+        all.sort_by(|a, b| (a.sender, a.nonce).cmp(&(b.sender, b.nonce)));
+        assert_eq!(uos.len(), 9);
+        assert_eq!(
+            uos.iter().map(|uo| uo.hash).collect::<Vec<_>>(),
+            all.iter().map(|uo| uo.hash).collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn same_user_operation_for_different_entry_points() {
+        let (provider, _) = Provider::mocked();
+        let provider = Arc::new(provider);
+        let chain = Chain::from(1337);
+        // the storage is shared by the mempools of the entry points
+        let mempool = Mempool::new(
+            Box::new(Arc::new(RwLock::new(
+                HashMap::<UserOperationHash, UserOperationSigned>::default(),
+            ))),
+            Box::new(Arc::new(RwLock::new(
+                HashMap::<Address, HashSet<UserOperationHash>>::default(),
+            ))),
+            Box::new(Arc::new(RwLock::new(
+                HashMap::<Address, HashSet<UserOperationHash>>::default(),
+            ))),
+            Box::new(Arc::new(RwLock::new(HashMap::<UserOperationHash, Vec<CodeHash>>::default()))),
+        );
+        let reputation = memory_reputation();
+        let uopool = |entry_point: EntryPoint<Provider<MockProvider>>| {
+            UoPoolTestBuilder::new(entry_point)
+                .with_mempool(mempool.clone())
+                .with_reputation(reputation.clone())
+                .build()
+        };
+        let mut uopool_a = uopool(EntryPoint::new(provider.clone(), Address::random()));
+        let mut uopool_b = uopool(EntryPoint::new(provider.clone(), Address::random()));
+
+        // structurally identical user operations submitted to both entry points
+        let uo = UserOperationSigned::default()
+            .sender(Address::random())
+            .max_fee_per_gas(U256::from(100))
+            .max_priority_fee_per_gas(U256::from(100));
+        let uo_a = UserOperation::from_user_operation_signed(
+            uo.hash(&uopool_a.entry_point.address(), chain.id()),
+            uo.clone(),
+        );
+        let uo_b = UserOperation::from_user_operation_signed(
+            uo.hash(&uopool_b.entry_point.address(), chain.id()),
+            uo.clone(),
+        );
+        assert_ne!(uo_a.hash, uo_b.hash);
+
+        let outcome = || Ok(UserOperationValidationOutcome::default());
+        uopool_a
+            .add_user_operation(uo_a.clone(), outcome(), UserOperationSource::Rpc)
+            .await
+            .unwrap();
+        // not a replacement of the one of the other entry point (same sender and nonce)
+        uopool_b
+            .add_user_operation(uo_b.clone(), outcome(), UserOperationSource::Rpc)
+            .await
+            .unwrap();
+
+        // both coexist in the shared storage, but each mempool only sees its own
+        assert_eq!(mempool.get_all().unwrap().len(), 2);
+        assert_eq!(
+            uopool_a.get_all().unwrap().iter().map(|uo| uo.hash).collect::<Vec<_>>(),
+            vec![uo_a.hash]
+        );
+        assert_eq!(
+            uopool_b
+                .get_sorted_user_operations()
+                .await
+                .unwrap()
+                .iter()
+                .map(|uo| uo.hash)
+                .collect::<Vec<_>>(),
+            vec![uo_b.hash]
+        );
+        assert_eq!(
+            uopool_a.mempool.get_entry_point(&uo_a.hash),
+            Some(uopool_a.entry_point.address())
+        );
+        assert_eq!(
+            uopool_b.mempool.get_entry_point(&uo_b.hash),
+            Some(uopool_b.entry_point.address())
+        );
+
+        // a misrouted user operation (hash of the other entry point) is rejected, keeping the
+        // user operation of the other entry point
+        assert!(matches!(
+            uopool_b.add_user_operation(uo_a.clone(), outcome(), UserOperationSource::Rpc).await,
+            Err(MempoolError { kind: MempoolErrorKind::HashCollision { entry_point }, .. })
+                if entry_point == uopool_a.entry_point.address()
+        ));
+        assert_eq!(mempool.get_all().unwrap().len(), 2);
+        assert_eq!(
+            uopool_a.mempool.get_entry_point(&uo_a.hash),
+            Some(uopool_a.entry_point.address())
+        );
+        assert_eq!(uopool_b.get_all().unwrap().len(), 1);
+
+        // removal of one of them doesn't affect the other
+        uopool_a.remove_user_operation(&uo_a.hash);
+        assert!(uopool_a.get_all().unwrap().is_empty());
+        assert_eq!(uopool_b.get_all().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn update_reputation_on_demand() {
+        let (provider, _) = Provider::mocked();
+        let entry_point = EntryPoint::new(Arc::new(provider), Address::random());
+        let mut uopool = UoPoolTestBuilder::new(entry_point).build();
+
+        let address = Address::random();
+        uopool
+            .set_reputation(
+                vec![ReputationEntry {
+                    uo_seen: 240,
+                    uo_included: 48,
+                    ..ReputationEntry::default_with_addr(address)
+                }],
+                true,
+            )
+            .unwrap();
+
+        // the counters decay by 1/24 without waiting for the hourly timer
+        uopool.update_reputation().unwrap();
+        let entry = uopool.get_reputation().into_iter().find(|ent| ent.address == address).unwrap();
+        assert_eq!((entry.uo_seen, entry.uo_included), (230, 46));
+
+        uopool.update_reputation().unwrap();
+        let entry = uopool.get_reputation().into_iter().find(|ent| ent.address == address).unwrap();
+        assert_eq!((entry.uo_seen, entry.uo_included), (220, 44));
+    }
+
+    // Validator which simulates a user operation in `pre_verification_gas` milliseconds (so the
+    // simulations complete in the reverse order) and fails every 7th one. It records the simulated
+    // user operations and the max number of simulations in flight.
+    #[derive(Default)]
+    struct DelayedValidator {
+        simulated: parking_lot::Mutex<Vec<UserOperationHash>>,
+        in_flight: AtomicUsize,
+        max_in_flight: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl UserOperationValidator for DelayedValidator {
+        async fn validate_user_operation(
+            &self,
+            uo: &UserOperation,
+            _mempool: &Mempool,
+            _reputation: &Reputation,
+            _val_config: Option<ValidationConfig>,
+            _mode: EnumSet<UserOperationValidatorMode>,
+        ) -> Result<UserOperationValidationOutcome, InvalidMempoolUserOperationError> {
+            self.simulated.lock().push(uo.hash);
+            let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(uo.pre_verification_gas.as_u64())).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+            if uo.nonce.as_u64() % 7 == 3 {
+                return Err(SimulationError::Validation { inner: "AA23 reverted".into() }.into());
+            }
+
+            Ok(UserOperationValidationOutcome {
+                verification_gas_limit: uo.verification_gas_limit,
+                ..Default::default()
+            })
+        }
+
+        async fn trace_user_operation_validation(
+            &self,
+            uo: &UserOperation,
+            mempool: &Mempool,
+            reputation: &Reputation,
+            val_config: Option<ValidationConfig>,
+        ) -> Result<ValidationTrace, InvalidMempoolUserOperationError> {
+            let val_out = self
+                .validate_user_operation(
+                    uo,
+                    mempool,
+                    reputation,
+                    val_config,
+                    UserOperationValidatorMode::Simulation.into(),
+                )
+                .await?;
+
+            Ok(ValidationTrace {
+                trace: Default::default(),
+                storage_map: val_out.storage_map,
+                banned_opcodes: vec![],
+                failed_check: None,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn concurrent_simulation_preserves_bundle() {
+        let senders = (0..20).map(|_| Address::random()).collect::<Vec<_>>();
+        let uos = (0..50u64)
+            .map(|i| {
+                UserOperation::from_user_operation_signed(
+                    H256::random().into(),
+                    UserOperationSigned::default()
+                        .sender(senders[i as usize % senders.len()])
+                        .nonce(U256::from(i))
+                        .call_gas_limit(U256::from(10_000 + i * 1_000))
+                        .verification_gas_limit(U256::from(5_000))
+                        .pre_verification_gas(U256::from(50 - i)),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let bundle = |simulation_concurrency: usize| {
+            let uos = uos.clone();
+            async move {
+                let (provider, _) = Provider::mocked();
+                let entry_point = EntryPoint::new(Arc::new(provider), Address::random());
+                let mut uopool = UoPoolTestBuilder::new(entry_point)
+                    .with_validator(DelayedValidator::default())
+                    .with_max_verification_gas(U256::from(300_000))
+                    .build()
+                    .with_simulation_concurrency(simulation_concurrency);
+
+                for uo in uos.iter() {
+                    uopool.mempool.add(uo.clone()).unwrap();
+                }
+
+                let (uos_bundled, _) = uopool.bundle_user_operations(uos).await.unwrap();
+
+                let mut uos_left =
+                    uopool.get_all().unwrap().into_iter().map(|uo| uo.hash).collect::<Vec<_>>();
+                uos_left.sort();
+
+                (
+                    uos_bundled.into_iter().map(|uo| uo.hash).collect::<Vec<_>>(),
+                    uos_left,
+                    uopool.validator.simulated.lock().clone(),
+                    uopool.validator.max_in_flight.load(Ordering::SeqCst),
+                )
+            }
+        };
+
+        let (uos_seq, uos_left_seq, simulated_seq, max_in_flight_seq) = bundle(1).await;
+        let (uos_conc, uos_left_conc, simulated_conc, max_in_flight_conc) = bundle(8).await;
+
+        // one user operation per sender, the failed ones are skipped (and removed from the
+        // mempool) and the bundle is capped by the gas limit
+        assert!(!uos_seq.is_empty() && uos_seq.len() < senders.len());
+        assert!(uos_left_seq.len() < uos.len());
+        assert_eq!(uos_conc, uos_seq);
+        assert_eq!(uos_left_conc, uos_left_seq);
+
+        // the simulations run in bounded windows
+        assert_eq!(max_in_flight_seq, 1);
+        assert!(max_in_flight_conc > 1 && max_in_flight_conc <= 8);
+
+        // the user operations are filtered before being simulated: the ones of senders already
+        // bundled aren't simulated and at most one window is simulated beyond the sequential run
+        let uos_by_hash = uos.iter().map(|uo| (uo.hash, uo)).collect::<HashMap<_, _>>();
+        let bundled_senders = uos_seq
+            .iter()
+            .map(|uo_hash| (uos_by_hash[uo_hash].sender, uos_by_hash[uo_hash].nonce))
+            .collect::<HashMap<_, _>>();
+        for simulated in [&simulated_seq, &simulated_conc] {
+            assert!(simulated.len() < uos.len());
+            assert!(simulated.iter().all(|uo_hash| {
+                let uo = uos_by_hash[uo_hash];
+                bundled_senders.get(&uo.sender).map_or(true, |nonce| uo.nonce <= *nonce)
+            }));
+        }
+        assert!(simulated_conc.len() <= simulated_seq.len() + 8);
+
+        // the simulations start in the order of the user operations
+        let position = |uo_hash: &UserOperationHash| uos.iter().position(|uo| uo.hash == *uo_hash);
+        let positions = simulated_conc.iter().map(position).collect::<Vec<_>>();
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[tokio::test]
+    async fn revalidate_user_operations() {
+        let (provider, _) = Provider::mocked();
+        let entry_point = EntryPoint::new(Arc::new(provider), Address::random());
+        let mut uopool = UoPoolTestBuilder::new(entry_point)
+            .with_validator(DelayedValidator::default())
+            .with_max_verification_gas(U256::from(300_000))
+            .build();
+
+        let uos = (0..8u64)
+            .map(|i| {
+                UserOperation::from_user_operation_signed(
+                    H256::random().into(),
+                    UserOperationSigned::default().sender(Address::random()).nonce(U256::from(i)),
+                )
+            })
+            .collect::<Vec<_>>();
+        for uo in uos.iter() {
+            uopool.mempool.add(uo.clone()).unwrap();
+        }
+
+        // the user operation with nonce 3 doesn't pass the simulation anymore
+        let removed = uopool.revalidate_user_operations().await.unwrap();
+        assert_eq!(removed, vec![uos[3].hash]);
+        assert_eq!(uopool.get_all().unwrap().len(), 7);
+        assert!(uopool.revalidate_user_operations().await.unwrap().is_empty());
+    }
+
+    // L1 gas oracle pricing every bundle at the same L1 fee
+    struct FixedL1GasOracle(U256);
+
+    #[async_trait::async_trait]
+    impl<M: Middleware + 'static> L1GasOracle<M> for FixedL1GasOracle {
+        async fn l1_fee(
+            &self,
+            _entry_point: &EntryPoint<M>,
+            _uos: &[UserOperationSigned],
+        ) -> Result<U256, EntryPointError> {
+            Ok(self.0)
+        }
+    }
+
+    #[tokio::test]
+    async fn drop_unprofitable_user_operations() {
+        let (provider, mock) = Provider::mocked();
+        let entry_point = EntryPoint::new(Arc::new(provider), Address::random());
+        let uopool = UoPoolTestBuilder::new(entry_point).build().with_overhead(
+            OverheadConfig::default()
+                .with_l1_gas_oracle(Arc::new(FixedL1GasOracle(U256::from(2_000_000)))),
+        );
+
+        // the bundle is priced at 2_000_000 wei, i.e., 1_000_000 wei per user operation at a gas
+        // price of 10 (base fee 1 + priority fee 9)
+        let uo = |l1_gas: u64| {
+            let uo = UserOperationSigned::default()
+                .sender(Address::random())
+                .max_fee_per_gas(U256::from(20))
+                .max_priority_fee_per_gas(U256::from(9));
+            let pre_verification_gas = Overhead::default()
+                .calculate_pre_verification_gas(&uo)
+                .saturating_add(U256::from(l1_gas));
+            UserOperation::from_user_operation_signed(
+                H256::random().into(),
+                uo.pre_verification_gas(pre_verification_gas),
+            )
+        };
+        let uos = vec![uo(200_000), uo(1_000)];
+
+        mock.push(Block::<H256> { base_fee_per_gas: Some(U256::one()), ..Default::default() })
+            .unwrap();
+        let (uos_profitable, storage_maps) = uopool
+            .drop_unprofitable_user_operations(uos.clone(), vec![StorageMap::default(); 2])
+            .await
+            .unwrap();
+        assert_eq!(uos_profitable.iter().map(|uo| uo.hash).collect::<Vec<_>>(), vec![uos[0].hash]);
+        assert_eq!(storage_maps.len(), 1);
+    }
 }
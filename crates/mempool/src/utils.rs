@@ -1,6 +1,20 @@
-use ethers::types::{Address, H256, U256};
-use silius_primitives::{simulation::CodeHash, UserOperationSigned};
-use std::{collections::HashMap, ops::Deref};
+use alloy_chains::Chain;
+use ethers::{
+    abi::RawLog,
+    contract::EthEvent,
+    providers::Middleware,
+    types::{Address, Bytes, Log, H256, U256},
+};
+use lru::LruCache;
+use silius_contracts::{
+    decode_revert_string, BeforeExecutionFilter, EntryPoint, EntryPointError, EntryPointVersion,
+    GasPriceOracle, PostOpRevertReasonFilter, UserOperationEventFilter,
+    UserOperationRevertReasonFilter,
+};
+use silius_primitives::{
+    chain::is_op_stack, simulation::CodeHash, UserOperationHash, UserOperationSigned,
+};
+use std::{collections::HashMap, num::NonZeroUsize, ops::Deref, sync::Arc};
 
 pub fn equal_code_hashes(hashes: &[CodeHash], hashes_prev: &Vec<CodeHash>) -> bool {
     if hashes_prev.len() != hashes.len() {
@@ -23,6 +37,85 @@ pub fn equal_code_hashes(hashes: &[CodeHash], hashes_prev: &Vec<CodeHash>) -> bo
     true
 }
 
+/// Formats the revert reason (decodes `Error(string)` if possible, otherwise hex encodes it)
+fn format_revert_reason(reason: Bytes) -> String {
+    decode_revert_string(reason.clone()).unwrap_or_else(|| reason.to_string())
+}
+
+/// Extracts the outcome of the user operation from the logs of the bundle transaction.
+/// If the paymaster's `postOp` reverted (`PostOpRevertReason` event, only emitted by the entry
+/// point v0.7), the user operation is marked as unsuccessful and the reason reflects the `postOp`
+/// revert.
+///
+/// # Arguments
+/// `uo_hash` - The hash of the user operation
+/// `success` - The success flag from the `UserOperationEvent`
+/// `logs` - The logs of the bundle transaction
+/// `version` - The version of the entry point which emitted the logs
+///
+/// # Returns
+/// `(bool, String)` - The success flag and the revert reason (empty if none)
+pub fn extract_user_operation_outcome(
+    uo_hash: &UserOperationHash,
+    success: bool,
+    logs: &[Log],
+    version: EntryPointVersion,
+) -> (bool, String) {
+    let mut reason = String::new();
+
+    for log in logs.iter().filter(|log| log.topics.get(1) == Some(&uo_hash.0)) {
+        let raw_log = RawLog::from(log.clone());
+
+        if version == EntryPointVersion::V0_7 &&
+            log.topics[0] == PostOpRevertReasonFilter::signature()
+        {
+            if let Ok(event) = <PostOpRevertReasonFilter as EthEvent>::decode_log(&raw_log) {
+                return (
+                    false,
+                    format!("postOp reverted: {}", format_revert_reason(event.revert_reason)),
+                );
+            }
+        } else if log.topics[0] == UserOperationRevertReasonFilter::signature() {
+            if let Ok(event) = <UserOperationRevertReasonFilter as EthEvent>::decode_log(&raw_log) {
+                reason = format_revert_reason(event.revert_reason);
+            }
+        }
+    }
+
+    (success, reason)
+}
+
+/// Extracts the logs emitted by the user operation from the logs of the bundle transaction.
+/// The logs of the user operation are the ones between the previous boundary (the
+/// `BeforeExecution` event or the `UserOperationEvent` of the preceding user operation) and the
+/// `UserOperationEvent` of the user operation, so logs of other user operations bundled in the
+/// same transaction aren't included.
+///
+/// # Arguments
+/// `uo_hash` - The hash of the user operation
+/// `logs` - The logs of the bundle transaction
+///
+/// # Returns
+/// `Vec<Log>` - The logs of the user operation (empty if its `UserOperationEvent` isn't found)
+pub fn extract_user_operation_logs(uo_hash: &UserOperationHash, logs: &[Log]) -> Vec<Log> {
+    let mut start = 0;
+
+    for (index, log) in logs.iter().enumerate() {
+        match log.topics.first() {
+            Some(topic) if *topic == BeforeExecutionFilter::signature() => start = index + 1,
+            Some(topic) if *topic == UserOperationEventFilter::signature() => {
+                if log.topics.get(1) == Some(&uo_hash.0) {
+                    return logs[start..index].to_vec();
+                }
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+
+    vec![]
+}
+
 /// Struct to calculate the pre-verification gas of a user operation
 // https://github.com/eth-infinitism/bundler/blob/main/packages/sdk/src/calcPreVerificationGas.ts#L44-L51
 pub struct Overhead {
@@ -86,6 +179,204 @@ impl Overhead {
     }
 }
 
+/// Oracle pricing the L1 data posting cost of a [UserOperation](UserOperationSigned) on L2s, where
+/// it's usually the dominant part of the pre-verification gas.
+#[async_trait::async_trait]
+pub trait L1GasOracle<M: Middleware + 'static>: Send + Sync {
+    /// Calculates the L1 data posting fee (in wei) of a bundle of
+    /// [UserOperations](UserOperationSigned). The bundle is priced as a whole with a single query.
+    ///
+    /// # Arguments
+    /// `entry_point` - The [EntryPoint](EntryPoint) the user operations are sent to
+    /// `uos` - The [UserOperations](UserOperationSigned) of the bundle
+    ///
+    /// # Returns
+    /// The L1 fee of the bundle
+    async fn l1_fee(
+        &self,
+        entry_point: &EntryPoint<M>,
+        uos: &[UserOperationSigned],
+    ) -> Result<U256, EntryPointError>;
+
+    /// Calculates the L1 data posting cost of a [UserOperation](UserOperationSigned), expressed
+    /// in L2 gas at the given gas price.
+    ///
+    /// # Arguments
+    /// `entry_point` - The [EntryPoint](EntryPoint) the user operation is sent to
+    /// `uo` - The [UserOperation](UserOperationSigned) to calculate the L1 gas for
+    /// `gas_price` - The L2 gas price used to convert the L1 fee into gas
+    ///
+    /// # Returns
+    /// The L1 gas of the [UserOperation](UserOperationSigned)
+    async fn l1_gas(
+        &self,
+        entry_point: &EntryPoint<M>,
+        uo: &UserOperationSigned,
+        gas_price: U256,
+    ) -> Result<U256, EntryPointError> {
+        Ok(div_ceil(self.l1_fee(entry_point, std::slice::from_ref(uo)).await?, gas_price))
+    }
+}
+
+/// [L1GasOracle] of OP Stack chains. The L1 fee is priced by the `GasPriceOracle` predeploy using
+/// the serialized `handleOps` calldata of the bundle.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OpStackL1GasOracle;
+
+#[async_trait::async_trait]
+impl<M: Middleware + 'static> L1GasOracle<M> for OpStackL1GasOracle {
+    async fn l1_fee(
+        &self,
+        entry_point: &EntryPoint<M>,
+        uos: &[UserOperationSigned],
+    ) -> Result<U256, EntryPointError> {
+        // non-zero beneficiary so the calldata isn't underpriced
+        GasPriceOracle::new(entry_point.eth_client())
+            .get_handle_ops_l1_fee(entry_point, uos.to_vec(), Address::repeat_byte(0xff))
+            .await
+    }
+}
+
+/// Configuration of the pre-verification gas calculation: the [Overhead] constants and an
+/// optional [L1GasOracle] adding the L1 data posting cost on L2s.
+pub struct OverheadConfig<M: Middleware + 'static> {
+    pub fixed: U256,
+    pub per_user_op: U256,
+    pub per_user_op_word: U256,
+    pub zero_byte: U256,
+    pub non_zero_byte: U256,
+    pub l1_gas_oracle: Option<Arc<dyn L1GasOracle<M>>>,
+}
+
+impl<M: Middleware + 'static> Clone for OverheadConfig<M> {
+    fn clone(&self) -> Self {
+        Self {
+            fixed: self.fixed,
+            per_user_op: self.per_user_op,
+            per_user_op_word: self.per_user_op_word,
+            zero_byte: self.zero_byte,
+            non_zero_byte: self.non_zero_byte,
+            l1_gas_oracle: self.l1_gas_oracle.clone(),
+        }
+    }
+}
+
+impl<M: Middleware + 'static> Default for OverheadConfig<M> {
+    /// Mainnet constants without L1 data posting cost
+    fn default() -> Self {
+        let overhead = Overhead::default();
+        Self {
+            fixed: overhead.fixed,
+            per_user_op: overhead.per_user_op,
+            per_user_op_word: overhead.per_user_op_word,
+            zero_byte: overhead.zero_byte,
+            non_zero_byte: overhead.non_zero_byte,
+            l1_gas_oracle: None,
+        }
+    }
+}
+
+impl<M: Middleware + 'static> OverheadConfig<M> {
+    /// Mainnet constants with the L1 data posting cost priced by the `GasPriceOracle` predeploy
+    pub fn op_stack() -> Self {
+        Self::default().with_l1_gas_oracle(Arc::new(OpStackL1GasOracle))
+    }
+
+    /// Default configuration of the chain ([OverheadConfig::op_stack] on OP Stack chains,
+    /// mainnet constants otherwise)
+    pub fn for_chain(chain: &Chain) -> Self {
+        if is_op_stack(chain) {
+            Self::op_stack()
+        } else {
+            Self::default()
+        }
+    }
+
+    /// Sets the [L1GasOracle] pricing the L1 data posting cost
+    pub fn with_l1_gas_oracle(mut self, l1_gas_oracle: Arc<dyn L1GasOracle<M>>) -> Self {
+        self.l1_gas_oracle = Some(l1_gas_oracle);
+        self
+    }
+
+    /// Returns the [Overhead] used to calculate the pre-verification gas (without the L1 gas)
+    pub fn overhead(&self) -> Overhead {
+        Overhead {
+            fixed: self.fixed,
+            per_user_op: self.per_user_op,
+            per_user_op_word: self.per_user_op_word,
+            zero_byte: self.zero_byte,
+            non_zero_byte: self.non_zero_byte,
+            ..Default::default()
+        }
+    }
+
+    /// Calculates the L1 data posting cost of a [UserOperation](UserOperationSigned) in L2 gas
+    /// (zero if there is no [L1GasOracle] or the gas price is zero).
+    ///
+    /// # Arguments
+    /// `entry_point` - The [EntryPoint](EntryPoint) the user operation is sent to
+    /// `uo` - The [UserOperation](UserOperationSigned) to calculate the L1 gas for
+    /// `gas_price` - The L2 gas price used to convert the L1 fee into gas
+    ///
+    /// # Returns
+    /// The L1 gas of the [UserOperation](UserOperationSigned)
+    pub async fn calculate_l1_gas(
+        &self,
+        entry_point: &EntryPoint<M>,
+        uo: &UserOperationSigned,
+        gas_price: U256,
+    ) -> Result<U256, EntryPointError> {
+        match self.l1_gas_oracle {
+            Some(ref oracle) if !gas_price.is_zero() => {
+                oracle.l1_gas(entry_point, uo, gas_price).await
+            }
+            _ => Ok(U256::zero()),
+        }
+    }
+
+    /// Calculates the L1 data posting fee (in wei) of a bundle with a single query (zero if there
+    /// is no [L1GasOracle]).
+    ///
+    /// # Arguments
+    /// `entry_point` - The [EntryPoint](EntryPoint) the user operations are sent to
+    /// `uos` - The [UserOperations](UserOperationSigned) of the bundle
+    ///
+    /// # Returns
+    /// The L1 fee of the bundle
+    pub async fn calculate_bundle_l1_fee(
+        &self,
+        entry_point: &EntryPoint<M>,
+        uos: &[UserOperationSigned],
+    ) -> Result<U256, EntryPointError> {
+        match self.l1_gas_oracle {
+            Some(ref oracle) if !uos.is_empty() => oracle.l1_fee(entry_point, uos).await,
+            _ => Ok(U256::zero()),
+        }
+    }
+
+    /// Calculates the pre-verification gas of a [UserOperation](UserOperationSigned), including
+    /// its L1 data posting cost.
+    ///
+    /// # Arguments
+    /// `entry_point` - The [EntryPoint](EntryPoint) the user operation is sent to
+    /// `uo` - The [UserOperation](UserOperationSigned) to calculate the pre-verification gas for
+    /// `gas_price` - The L2 gas price used to convert the L1 fee into gas
+    ///
+    /// # Returns
+    /// The pre-verification gas of the [UserOperation](UserOperationSigned)
+    pub async fn calculate_pre_verification_gas(
+        &self,
+        entry_point: &EntryPoint<M>,
+        uo: &UserOperationSigned,
+        gas_price: U256,
+    ) -> Result<U256, EntryPointError> {
+        Ok(self
+            .overhead()
+            .calculate_pre_verification_gas(uo)
+            .saturating_add(self.calculate_l1_gas(entry_point, uo, gas_price).await?))
+    }
+}
+
 /// Helper function to calculate the valid gas of a [UserOperation](UserOperation)
 /// The function is invoked by the
 /// [check_valid_gas](crates::uopool::validate::sanity::check_valid_gas) method.
@@ -146,15 +437,83 @@ pub fn div_ceil(numerator: U256, denominator: U256) -> U256 {
     numerator.checked_div(denominator).unwrap_or_default().saturating_add(rounding_const)
 }
 
+/// Deposits of the paymasters sponsoring the user operations of a bundle (dropped once the bundle
+/// is built). At most `cap` fetched deposits are tracked (least recently used ones are evicted and
+/// have to be re-fetched), while the pre-funds spent by the bundled user operations are kept for
+/// each paymaster, so the remaining deposit is correct even after an eviction. The pre-funds are
+/// spent from at most `cap` paymasters, the user operations of other paymasters are left for the
+/// next bundle.
+pub struct PaymasterDeposits {
+    cap: usize,
+    deposits: LruCache<Address, U256>,
+    spent: HashMap<Address, U256>,
+}
+
+impl PaymasterDeposits {
+    pub fn new(cap: usize) -> Self {
+        let cap = NonZeroUsize::new(cap).unwrap_or(NonZeroUsize::MIN);
+        Self { cap: cap.get(), deposits: LruCache::new(cap), spent: HashMap::new() }
+    }
+
+    /// Whether the pre-fund of a user operation can be spent from the deposit of the paymaster
+    /// (the paymaster already spent some or there is room for another one)
+    pub fn can_spend(&self, paymaster: &Address) -> bool {
+        self.spent.contains_key(paymaster) || self.spent.len() < self.cap
+    }
+
+    /// Returns the remaining deposit of the paymaster (None if its deposit isn't tracked)
+    pub fn remaining(&mut self, paymaster: &Address) -> Option<U256> {
+        let deposit = *self.deposits.get(paymaster)?;
+        Some(deposit.saturating_sub(self.spent.get(paymaster).cloned().unwrap_or_default()))
+    }
+
+    /// Tracks the fetched deposit of the paymaster and returns its remaining deposit
+    pub fn insert(&mut self, paymaster: Address, deposit: U256) -> U256 {
+        self.deposits.put(paymaster, deposit);
+        deposit.saturating_sub(self.spent.get(&paymaster).cloned().unwrap_or_default())
+    }
+
+    /// Spends the pre-fund of a bundled user operation from the deposit of the paymaster (see
+    /// [can_spend](PaymasterDeposits::can_spend))
+    pub fn spend(&mut self, paymaster: Address, pre_fund: U256) {
+        let spent = self.spent.entry(paymaster).or_default();
+        *spent = spent.saturating_add(pre_fund);
+    }
+
+    /// Returns the number of tracked deposits
+    pub fn len(&self) -> usize {
+        self.deposits.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.deposits.is_empty()
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
     use super::*;
-    use crate::{mempool::Mempool, Reputation};
-    use ethers::types::{Address, Bytes, H256, U256};
+    use crate::{
+        mempool::{BundleOrdering, Mempool},
+        test_utils::UoPoolTestBuilder,
+        validate::UserOperationValidationOutcome,
+        MempoolErrorKind, Reputation,
+    };
+    use alloy_chains::NamedChain;
+    use ethers::{
+        abi::{encode, AbiEncode, Token},
+        providers::Provider,
+        types::{Address, Bytes, Log, H256, U256},
+    };
     use silius_primitives::{
+        constants::validation::reputation::{
+            PROBATION_ENTITY_BUNDLE_COUNT, PROBATION_ENTITY_MEMPOOL_COUNT,
+            THROTTLED_ENTITY_BUNDLE_COUNT, THROTTLED_ENTITY_MEMPOOL_COUNT,
+        },
         reputation::{ReputationEntry, Status},
-        UserOperation, UserOperationHash, UserOperationSigned,
+        MempoolStats, UserOperation, UserOperationHash, UserOperationSigned, UserOperationSource,
     };
+    use std::sync::Arc;
 
     #[test]
     fn pre_verification_gas_calculation() {
@@ -176,6 +535,79 @@ pub mod tests {
         assert_eq!(gas_oh.calculate_pre_verification_gas(&uo), 45340.into());
     }
 
+    #[tokio::test]
+    async fn l1_gas_calculation() {
+        let (provider, mock) = Provider::mocked();
+        let ep = EntryPoint::new(Arc::new(provider), Address::random());
+        let uo = UserOperationSigned::default();
+        let gas_price = U256::from(1_000_000_u64);
+
+        // non OP Stack chains don't query the oracle
+        assert_eq!(
+            OverheadConfig::for_chain(&Chain::from(NamedChain::Mainnet))
+                .calculate_l1_gas(&ep, &uo, gas_price)
+                .await
+                .unwrap(),
+            U256::zero()
+        );
+
+        let l1_fee = U256::from(1_500_000_000_u64);
+        mock.push(Bytes::from(encode(&[Token::Uint(l1_fee)]))).unwrap();
+        assert_eq!(
+            OverheadConfig::for_chain(&Chain::from(NamedChain::Optimism))
+                .calculate_l1_gas(&ep, &uo, gas_price)
+                .await
+                .unwrap(),
+            U256::from(1500)
+        );
+    }
+
+    #[tokio::test]
+    async fn pre_verification_gas_calculation_with_overhead_config() {
+        let (provider, mock) = Provider::mocked();
+        let ep = EntryPoint::new(Arc::new(provider), Address::random());
+        let uo = UserOperationSigned {
+            sender: "0xAB7e2cbFcFb6A5F33A75aD745C3E5fB48d689B54".parse().unwrap(),
+            nonce: U256::zero(),
+            init_code: "0xe19e9755942bb0bd0cccce25b1742596b8a8250b3bf2c3e70000000000000000000000001d9a2cb3638c2fc8bf9c01d088b79e75cd188b17000000000000000000000000789d9058feecf1948af429793e7f1eb4a75db2220000000000000000000000000000000000000000000000000000000000000000".parse().unwrap(),
+            call_data: "0x80c5c7d0000000000000000000000000ab7e2cbfcfb6a5f33a75ad745c3e5fb48d689b5400000000000000000000000000000000000000000000000002c68af0bb14000000000000000000000000000000000000000000000000000000000000000000600000000000000000000000000000000000000000000000000000000000000000".parse().unwrap(),
+            call_gas_limit: 21900.into(),
+            verification_gas_limit: 1218343.into(),
+            pre_verification_gas: 50780.into(),
+            max_fee_per_gas: 10064120791_u64.into(),
+            max_priority_fee_per_gas: 1620899097.into(),
+            paymaster_and_data: Bytes::default(),
+            signature: "0x4e69eb5e02d47ba28878655d61c59c20c3e9a2e6905381305626f6a5a2892ec12bd8dd59179f0642731e0e853af54a71ce422a1a234548c9dd1c559bd07df4461c".parse().unwrap(),
+        };
+
+        // mainnet defaults
+        assert_eq!(
+            OverheadConfig::default()
+                .calculate_pre_verification_gas(&ep, &uo, uo.max_fee_per_gas)
+                .await
+                .unwrap(),
+            45340.into()
+        );
+
+        // OP Stack adds the L1 fee (converted to L2 gas at max fee per gas)
+        mock.push(Bytes::from(encode(&[Token::Uint(U256::from(1_500_000_000_000_000_u64))])))
+            .unwrap();
+        assert_eq!(
+            OverheadConfig::op_stack()
+                .calculate_pre_verification_gas(&ep, &uo, uo.max_fee_per_gas)
+                .await
+                .unwrap(),
+            (45340 + 149045).into()
+        );
+
+        // custom constants
+        let overhead = OverheadConfig { fixed: U256::zero(), ..Default::default() };
+        assert_eq!(
+            overhead.calculate_pre_verification_gas(&ep, &uo, uo.max_fee_per_gas).await.unwrap(),
+            (45340 - 21000).into()
+        );
+    }
+
     #[test]
     fn pre_verification_gas_calculation_with_large_user_operation() {
         let gas_oh = Overhead::default();
@@ -298,6 +730,125 @@ pub mod tests {
         assert_eq!(div_ceil(U256::from(10), U256::from(3)), 4.into());
     }
 
+    fn revert_event_log(signature: H256, uo_hash: &UserOperationHash, reason: &str) -> Log {
+        let sender = Address::random();
+        let mut revert_reason = vec![0x08, 0xc3, 0x79, 0xa0];
+        revert_reason.extend(reason.to_string().encode());
+        Log {
+            topics: vec![signature, uo_hash.0, H256::from(sender)],
+            data: encode(&[Token::Uint(U256::zero()), Token::Bytes(revert_reason)]).into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn user_operation_outcome_post_op_revert() {
+        let uo_hash = UserOperationHash(H256::random());
+
+        // postOp reverted
+        let logs = vec![revert_event_log(
+            PostOpRevertReasonFilter::signature(),
+            &uo_hash,
+            "paymaster postOp failed",
+        )];
+        assert_eq!(
+            extract_user_operation_outcome(&uo_hash, true, &logs, EntryPointVersion::V0_7),
+            (false, "postOp reverted: paymaster postOp failed".to_string())
+        );
+
+        // the entry point v0.6 doesn't emit the event
+        assert_eq!(
+            extract_user_operation_outcome(&uo_hash, true, &logs, EntryPointVersion::V0_6),
+            (true, String::new())
+        );
+
+        // execution reverted
+        let logs = vec![revert_event_log(
+            UserOperationRevertReasonFilter::signature(),
+            &uo_hash,
+            "execution failed",
+        )];
+        assert_eq!(
+            extract_user_operation_outcome(&uo_hash, false, &logs, EntryPointVersion::V0_7),
+            (false, "execution failed".to_string())
+        );
+
+        // events of other user operations are ignored
+        let logs = vec![revert_event_log(
+            PostOpRevertReasonFilter::signature(),
+            &UserOperationHash(H256::random()),
+            "paymaster postOp failed",
+        )];
+        assert_eq!(
+            extract_user_operation_outcome(&uo_hash, true, &logs, EntryPointVersion::V0_7),
+            (true, String::new())
+        );
+    }
+
+    #[test]
+    fn user_operation_logs_in_bundle() {
+        let uo_hashes = [H256::random(), H256::random(), H256::random()].map(UserOperationHash);
+        let uo_log = |address| Log { address, ..Default::default() };
+        let uo_event = |uo_hash: &UserOperationHash| Log {
+            topics: vec![UserOperationEventFilter::signature(), uo_hash.0],
+            ..Default::default()
+        };
+        let accounts = [Address::random(), Address::random(), Address::random()];
+
+        // each user operation emits a distinct log before its UserOperationEvent
+        let mut logs =
+            vec![Log { topics: vec![BeforeExecutionFilter::signature()], ..Default::default() }];
+        for (uo_hash, account) in uo_hashes.iter().zip(accounts) {
+            logs.push(uo_log(account));
+            logs.push(uo_event(uo_hash));
+        }
+
+        for (uo_hash, account) in uo_hashes.iter().zip(accounts) {
+            assert_eq!(extract_user_operation_logs(uo_hash, &logs), vec![uo_log(account)]);
+        }
+
+        // the first user operation starts at the beginning of the transaction if there is no
+        // BeforeExecution event
+        assert_eq!(
+            extract_user_operation_logs(&uo_hashes[0], &logs[1..]),
+            vec![uo_log(accounts[0])]
+        );
+
+        // user operations not included in the bundle don't have any logs
+        assert!(extract_user_operation_logs(&UserOperationHash(H256::random()), &logs).is_empty());
+    }
+
+    #[test]
+    fn paymaster_deposits_cap() {
+        let mut deposits = PaymasterDeposits::new(2);
+        let paymasters = [Address::random(), Address::random(), Address::random()];
+
+        assert_eq!(deposits.insert(paymasters[0], U256::from(100)), U256::from(100));
+        deposits.spend(paymasters[0], U256::from(30));
+        assert_eq!(deposits.remaining(&paymasters[0]), Some(U256::from(70)));
+
+        deposits.insert(paymasters[1], U256::from(100));
+        deposits.insert(paymasters[2], U256::from(100));
+        assert_eq!(deposits.len(), 2);
+
+        // the least recently used deposit is evicted, the re-fetched one accounts for the spent
+        // pre-funds
+        assert_eq!(deposits.remaining(&paymasters[0]), None);
+        assert_eq!(deposits.insert(paymasters[0], U256::from(100)), U256::from(70));
+        assert_eq!(deposits.len(), 2);
+        assert_eq!(deposits.remaining(&paymasters[1]), None);
+
+        deposits.spend(paymasters[0], U256::from(80));
+        assert_eq!(deposits.remaining(&paymasters[0]), Some(U256::zero()));
+        assert_eq!(deposits.remaining(&paymasters[2]), Some(U256::from(100)));
+
+        // the spent pre-funds are kept for at most 2 paymasters
+        assert!(deposits.can_spend(&paymasters[1]));
+        deposits.spend(paymasters[1], U256::from(10));
+        assert!(deposits.can_spend(&paymasters[0]));
+        assert!(!deposits.can_spend(&paymasters[2]));
+    }
+
     pub fn mempool_test_case(mut mempool: Mempool) {
         let ep = Address::random();
         let chain_id = 5_u64;
@@ -388,7 +939,7 @@ pub mod tests {
             );
         }
 
-        let sorted = mempool.get_sorted().unwrap();
+        let sorted = mempool.get_sorted(None).unwrap();
         assert_eq!(sorted[0].max_priority_fee_per_gas, U256::from(3));
         assert_eq!(sorted[1].max_priority_fee_per_gas, U256::from(2));
         assert_eq!(sorted[2].max_priority_fee_per_gas, U256::from(1));
@@ -413,6 +964,235 @@ pub mod tests {
 
         let code_hashes_get = mempool.get_code_hashes(&uo_hash).unwrap();
         assert_eq!(code_hashes, code_hashes_get);
+        assert_eq!(mempool.clear(), ());
+
+        let mut uo_hashes = vec![];
+        for i in 0..3 {
+            uo = UserOperationSigned {
+                sender: Address::random(),
+                nonce: U256::from(0),
+                max_priority_fee_per_gas: U256::from(i + 1),
+                ..UserOperationSigned::random()
+            };
+            uo_hash = uo.hash(&ep, chain_id);
+            mempool.add(UserOperation::from_user_operation_signed(uo_hash, uo.clone())).unwrap();
+            uo_hashes.push(uo_hash);
+        }
+
+        // pinned low-fee user operation is put ahead of the higher-fee ones
+        mempool.set_pinned(vec![uo_hashes[0]]);
+        let sorted = mempool.get_sorted(None).unwrap();
+        assert_eq!(sorted[0].hash, uo_hashes[0]);
+        assert_eq!(sorted[1].max_priority_fee_per_gas, U256::from(3));
+        assert_eq!(sorted[2].max_priority_fee_per_gas, U256::from(2));
+
+        // removed user operations are unpinned
+        assert!(mempool.remove(&uo_hashes[0]).unwrap());
+        assert!(mempool.get_pinned().is_empty());
+        let sorted = mempool.get_sorted(None).unwrap();
+        assert_eq!(sorted[0].max_priority_fee_per_gas, U256::from(3));
+        assert_eq!(sorted.len(), 2);
+
+        // user operations are tagged with their source and p2p ones are capped per sender
+        assert_eq!(mempool.clear(), ());
+        let mut mempool = mempool.with_max_uos_per_sender(UserOperationSource::P2p, 1);
+        let sender = Address::random();
+        let mut uo_hashes = vec![];
+        let sources = [
+            UserOperationSource::Rpc,
+            UserOperationSource::P2p,
+            UserOperationSource::P2p,
+            UserOperationSource::Rpc,
+        ];
+        for (i, source) in sources.into_iter().enumerate() {
+            uo = UserOperationSigned {
+                sender,
+                nonce: U256::from(i),
+                ..UserOperationSigned::random()
+            };
+            uo_hash = uo.hash(&ep, chain_id);
+            let res = mempool
+                .add_with_source(UserOperation::from_user_operation_signed(uo_hash, uo), source);
+            if i == 2 {
+                // second p2p user operation of the sender exceeds the cap
+                assert!(res.is_err());
+            } else {
+                // rpc user operations are not affected by the p2p cap
+                assert_eq!(res.unwrap(), uo_hash);
+                assert_eq!(mempool.get_source(&uo_hash), source);
+            }
+            uo_hashes.push(uo_hash);
+        }
+        assert_eq!(mempool.get_number_by_sender_and_source(&sender, UserOperationSource::P2p), 1);
+        assert_eq!(mempool.get_stats().unwrap(), MempoolStats { rpc: 2, p2p: 1 });
+
+        // removed user operations are no longer counted
+        assert!(mempool.remove(&uo_hashes[1]).unwrap());
+        assert_eq!(mempool.get_stats().unwrap(), MempoolStats { rpc: 2, p2p: 0 });
+
+        // included user operations are removed at once together with their indexes
+        assert_eq!(mempool.clear(), ());
+        let sender = Address::random();
+        let paymaster = Address::random();
+        let mut uo_hashes = vec![];
+        for i in 0..4 {
+            uo = UserOperationSigned {
+                sender,
+                nonce: U256::from(i),
+                paymaster_and_data: paymaster.as_bytes().to_vec().into(),
+                ..UserOperationSigned::random()
+            };
+            uo_hash = uo.hash(&ep, chain_id);
+            mempool.add(UserOperation::from_user_operation_signed(uo_hash, uo)).unwrap();
+            let code_hashes = vec![CodeHash { address: Address::random(), hash: H256::random() }];
+            mempool.set_code_hashes(&uo_hash, code_hashes).unwrap();
+            uo_hashes.push(uo_hash);
+        }
+        mempool.set_pinned(vec![uo_hashes[0]]);
+
+        // unknown hashes are skipped
+        let included =
+            vec![uo_hashes[0], uo_hashes[1], uo_hashes[2], UserOperationHash(H256::random())];
+        assert_eq!(mempool.remove_included_batch(&included).unwrap(), 3);
+        assert_eq!(mempool.get_all().unwrap().len(), 1);
+        assert_eq!(mempool.get_all_by_sender(&sender)[0].hash, uo_hashes[3]);
+        assert_eq!(mempool.get_number_by_sender(&sender), 1);
+        assert_eq!(mempool.get_number_by_entity(&paymaster), 1);
+        assert!(mempool.get_pinned().is_empty());
+        for uo_hash in included.iter() {
+            assert!(mempool.get(uo_hash).unwrap().is_none());
+            assert!(!mempool.has_code_hashes(uo_hash).unwrap());
+        }
+        assert!(mempool.has_code_hashes(&uo_hashes[3]).unwrap());
+
+        assert_eq!(mempool.remove_included_batch(&uo_hashes).unwrap(), 1);
+        assert_eq!(mempool.get_number_by_sender(&sender), 0);
+        assert_eq!(mempool.get_number_by_entity(&paymaster), 0);
+
+        // custom ordering reverses the fee priority
+        struct ReverseFeeOrdering;
+        impl BundleOrdering for ReverseFeeOrdering {
+            fn sort(&self, uos: &mut [UserOperation]) {
+                uos.sort_by_key(|uo| uo.max_priority_fee_per_gas);
+            }
+        }
+
+        assert_eq!(mempool.clear(), ());
+        let mut mempool = mempool.with_ordering(ReverseFeeOrdering);
+        for i in 0..3 {
+            uo = UserOperationSigned {
+                sender: Address::random(),
+                nonce: U256::from(0),
+                max_priority_fee_per_gas: U256::from(i + 1),
+                ..UserOperationSigned::random()
+            };
+            uo_hash = uo.hash(&ep, chain_id);
+            mempool.add(UserOperation::from_user_operation_signed(uo_hash, uo)).unwrap();
+        }
+        let sorted = mempool.get_sorted(None).unwrap();
+        assert_eq!(
+            sorted.iter().map(|uo| uo.max_priority_fee_per_gas).collect::<Vec<_>>(),
+            vec![U256::from(1), U256::from(2), U256::from(3)]
+        );
+    }
+
+    pub async fn mempool_eviction_test_case(mempool: Mempool) {
+        let (provider, _) = Provider::mocked();
+        let provider = Arc::new(provider);
+        let entry_point = EntryPoint::new(provider.clone(), Address::random());
+        let mut uopool = UoPoolTestBuilder::new(entry_point)
+            .with_mempool(mempool.clone())
+            .build()
+            .with_max_user_operations(Some(3));
+
+        let senders = [Address::random(), Address::random(), Address::random()];
+        let paymaster = Address::random();
+        let new_uo = |sender: Address, nonce: u64, fee: u64| {
+            UserOperation::from_user_operation_signed(
+                H256::random().into(),
+                UserOperationSigned {
+                    sender,
+                    nonce: U256::from(nonce),
+                    max_priority_fee_per_gas: U256::from(fee),
+                    paymaster_and_data: if sender == senders[1] {
+                        paymaster.as_bytes().to_vec().into()
+                    } else {
+                        Bytes::default()
+                    },
+                    ..UserOperationSigned::random()
+                },
+            )
+        };
+        let outcome = UserOperationValidationOutcome {
+            code_hashes: Some(vec![CodeHash { address: paymaster, hash: H256::random() }]),
+            ..Default::default()
+        };
+
+        // the first user operation of the first sender pays the least, but evicting it would
+        // leave a nonce gap
+        let uos = [new_uo(senders[0], 0, 1), new_uo(senders[0], 1, 5), new_uo(senders[1], 0, 3)];
+        for uo in uos.iter() {
+            uopool
+                .add_user_operation(uo.clone(), Ok(outcome.clone()), UserOperationSource::Rpc)
+                .await
+                .unwrap();
+        }
+
+        // the mempool is full and the user operation doesn't pay more than the evicted one
+        let err = uopool
+            .add_user_operation(
+                new_uo(senders[2], 0, 3),
+                Ok(outcome.clone()),
+                UserOperationSource::Rpc,
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err.kind, MempoolErrorKind::MempoolFull { .. }));
+        assert_eq!(uopool.mempool.get_all().unwrap().len(), 3);
+
+        // the user operation paying more evicts the last user operation with the lowest fee
+        let uo = new_uo(senders[2], 0, 4);
+        uopool
+            .add_user_operation(uo.clone(), Ok(outcome.clone()), UserOperationSource::Rpc)
+            .await
+            .unwrap();
+        assert_eq!(uopool.mempool.get_all().unwrap().len(), 3);
+        assert!(uopool.mempool.get(&uos[2].hash).unwrap().is_none());
+        assert!(uopool.mempool.get(&uos[0].hash).unwrap().is_some());
+        assert!(uopool.mempool.get(&uo.hash).unwrap().is_some());
+
+        // the indexes of the evicted user operation are cleaned up
+        assert_eq!(uopool.mempool.get_number_by_sender(&senders[1]), 0);
+        assert_eq!(uopool.mempool.get_number_by_entity(&paymaster), 0);
+        assert!(!uopool.mempool.has_code_hashes(&uos[2].hash).unwrap());
+        assert!(uopool.mempool.has_code_hashes(&uo.hash).unwrap());
+        assert!(uopool.mempool.check_consistency(false).unwrap().is_consistent());
+
+        // user operations of the same sender aren't evicted
+        uopool
+            .add_user_operation(new_uo(senders[2], 1, 10), Ok(outcome), UserOperationSource::Rpc)
+            .await
+            .unwrap();
+        assert!(uopool.mempool.get(&uo.hash).unwrap().is_some());
+        assert!(uopool.mempool.get(&uos[1].hash).unwrap().is_none());
+        assert!(uopool.mempool.check_consistency(false).unwrap().is_consistent());
+
+        // the cap is per entry point, so the mempool of another entry point sharing the storage
+        // isn't full
+        let mut other_uopool = UoPoolTestBuilder::new(EntryPoint::new(provider, Address::random()))
+            .with_mempool(mempool)
+            .build()
+            .with_max_user_operations(Some(3));
+        let other_uo = new_uo(Address::random(), 0, 1);
+        other_uopool
+            .add_user_operation(other_uo.clone(), Ok(Default::default()), UserOperationSource::Rpc)
+            .await
+            .unwrap();
+        assert_eq!(
+            other_uopool.mempool.get_all().unwrap().iter().map(|uo| uo.hash).collect::<Vec<_>>(),
+            vec![other_uo.hash]
+        );
+        assert_eq!(uopool.mempool.get_all().unwrap().len(), 3);
     }
 
     pub fn reputation_test_case(mut reputation: Reputation) {
@@ -473,4 +1253,110 @@ pub mod tests {
         }
         assert_eq!(Status::from(reputation.get_status(&addrs[3]).unwrap()), Status::BANNED);
     }
+
+    pub fn reputation_grace_period_test_case(reputation: Reputation) {
+        let mut reputation = reputation.with_grace_period(3600);
+        let addr = Address::random();
+
+        // a new entity without inclusions isn't throttled within the grace period
+        for _ in 0..1000 {
+            assert_eq!(reputation.increment_seen(&addr).unwrap(), ());
+        }
+        assert_eq!(Status::from(reputation.get_status(&addr).unwrap()), Status::OK);
+
+        // once the grace period is over, the inclusion ratio applies
+        let reputation = reputation.with_grace_period(0);
+        assert_eq!(Status::from(reputation.get_status(&addr).unwrap()), Status::BANNED);
+
+        // blacklisted entities are banned regardless of the grace period
+        let mut reputation = reputation.with_grace_period(3600);
+        let addr = Address::random();
+        assert_eq!(reputation.increment_seen(&addr).unwrap(), ());
+        assert_eq!(reputation.add_blacklist(&addr), true);
+        assert_eq!(Status::from(reputation.get_status(&addr).unwrap()), Status::BANNED);
+    }
+
+    pub fn reputation_probation_test_case(reputation: Reputation) {
+        let mut reputation = reputation.with_probation_period(3600);
+        let addr = Address::random();
+        let status = |reputation: &Reputation| Status::from(reputation.get_status(&addr).unwrap());
+
+        // OK
+        for _ in 0..100 {
+            assert_eq!(reputation.increment_seen(&addr).unwrap(), ());
+        }
+        for _ in 0..10 {
+            assert_eq!(reputation.increment_included(&addr).unwrap(), ());
+        }
+        assert_eq!(status(&reputation), Status::OK);
+        assert_eq!(status(&reputation).mempool_allowance(), None);
+        assert_eq!(status(&reputation).bundle_allowance(), None);
+
+        // throttled
+        for _ in 0..150 {
+            assert_eq!(reputation.increment_seen(&addr).unwrap(), ());
+        }
+        assert_eq!(status(&reputation), Status::THROTTLED);
+        assert_eq!(status(&reputation).mempool_allowance(), Some(THROTTLED_ENTITY_MEMPOOL_COUNT));
+        assert_eq!(status(&reputation).bundle_allowance(), Some(THROTTLED_ENTITY_BUNDLE_COUNT));
+
+        // probation (recovered from throttling)
+        for _ in 0..5 {
+            assert_eq!(reputation.increment_included(&addr).unwrap(), ());
+        }
+        assert_eq!(status(&reputation), Status::PROBATION);
+        assert_eq!(status(&reputation).mempool_allowance(), Some(PROBATION_ENTITY_MEMPOOL_COUNT));
+        assert_eq!(status(&reputation).bundle_allowance(), Some(PROBATION_ENTITY_BUNDLE_COUNT));
+        assert!(PROBATION_ENTITY_MEMPOOL_COUNT > THROTTLED_ENTITY_MEMPOOL_COUNT);
+
+        // still on probation with more inclusions
+        for _ in 0..5 {
+            assert_eq!(reputation.increment_included(&addr).unwrap(), ());
+        }
+        assert_eq!(status(&reputation), Status::PROBATION);
+
+        // OK after the probation period
+        reputation.set_probation_end(&addr, 0);
+        assert_eq!(status(&reputation), Status::OK);
+        assert_eq!(status(&reputation).mempool_allowance(), None);
+        assert_eq!(status(&reputation).bundle_allowance(), None);
+    }
+
+    pub fn reputation_export_import_test_case(mut from: Reputation, mut to: Reputation) {
+        let addrs: Vec<Address> = (0..3).map(|_| Address::random()).collect();
+
+        for _ in 0..10 {
+            from.increment_seen(&addrs[0]).unwrap();
+        }
+        from.increment_included(&addrs[0]).unwrap();
+        from.increment_seen(&addrs[1]).unwrap();
+
+        for _ in 0..20 {
+            to.increment_seen(&addrs[1]).unwrap();
+        }
+        to.increment_seen(&addrs[2]).unwrap();
+
+        // import merges with the existing entries by default
+        to.merge_entities(from.get_all().unwrap()).unwrap();
+
+        let entry = to.get(&addrs[0]).unwrap();
+        assert_eq!((entry.uo_seen, entry.uo_included), (10, 1));
+        let entry = to.get(&addrs[1]).unwrap();
+        assert_eq!((entry.uo_seen, entry.uo_included), (20, 0));
+        let entry = to.get(&addrs[2]).unwrap();
+        assert_eq!((entry.uo_seen, entry.uo_included), (1, 0));
+
+        // round-trip back to the first pool
+        from.merge_entities(to.get_all().unwrap()).unwrap();
+
+        let mut exported = from.get_all().unwrap();
+        let mut imported = to.get_all().unwrap();
+        exported.sort();
+        imported.sort();
+        assert_eq!(exported, imported);
+
+        // overwrite replaces the existing entries
+        to.set_entities(vec![ReputationEntry::default_with_addr(addrs[1])]).unwrap();
+        assert_eq!(to.get(&addrs[1]).unwrap().uo_seen, 0);
+    }
 }
@@ -1,10 +1,15 @@
 //! User operation validator module provides all the necessary traits and types for validations.
 use crate::{
-    mempool::Mempool, InvalidMempoolUserOperationError, Reputation, SanityError, SimulationError,
+    mempool::Mempool, InvalidMempoolUserOperationError, OverheadConfig, Reputation, SanityError,
+    SimulationError,
 };
 use alloy_chains::Chain;
 use enumset::{EnumSet, EnumSetType};
-use ethers::{providers::Middleware, types::U256};
+use ethers::{
+    providers::Middleware,
+    types::{Address, U256},
+};
+use serde::{Deserialize, Serialize};
 use silius_contracts::{entry_point::SimulateValidationResult, tracer::JsTracerFrame, EntryPoint};
 use silius_primitives::{
     constants::validation::entities::NUMBER_OF_LEVELS,
@@ -12,6 +17,11 @@ use silius_primitives::{
     simulation::{CodeHash, StorageMap, ValidationConfig},
     UserOperation, UserOperationHash,
 };
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
+use tracing::{debug, warn};
 
 pub mod sanity;
 pub mod simulation;
@@ -27,6 +37,9 @@ pub struct UserOperationValidationOutcome {
     pub prev_hash: Option<UserOperationHash>,
     pub pre_fund: U256,
     pub verification_gas_limit: U256,
+    // gas limits of the validated user operation
+    pub call_gas_limit: U256,
+    pub pre_verification_gas: U256,
     // Simulation
     pub valid_after: Option<U256>,
     // Simulation trace
@@ -36,6 +49,36 @@ pub struct UserOperationValidationOutcome {
     pub verified_block: U256,
 }
 
+/// Banned opcode used by an entity during the validation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BannedOpcode {
+    pub entity: String,
+    pub opcode: String,
+    pub count: u64,
+}
+
+/// The simulation trace check which rejected the user operation.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FailedTraceCheck {
+    // name of the [SimulationTraceCheck](SimulationTraceCheck), e.g., `StorageAccess`
+    pub check: String,
+    pub error: SimulationError,
+}
+
+/// The validation trace of a user operation (dry-run of the simulation trace checks which doesn't
+/// touch the mempool or the reputation).
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationTrace {
+    pub trace: JsTracerFrame,
+    pub storage_map: StorageMap,
+    pub banned_opcodes: Vec<BannedOpcode>,
+    // None if all of the simulation trace checks passed
+    pub failed_check: Option<FailedTraceCheck>,
+}
+
 /// The mode in which the user operation validator is running.
 /// The validator has three modes: sanity, simulation, and simulation trace.
 #[derive(EnumSetType, Debug)]
@@ -45,6 +88,54 @@ pub enum UserOperationValidatorMode {
     SimulationTrace,
 }
 
+/// Simulation trace rule violations which can be downgraded to warnings (e.g., in private
+/// mempools). Storage access and entity association violations are always hard rejections.
+#[derive(EnumSetType, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TraceViolation {
+    /// Banned opcodes (OP-011, OP-012, OP-031, ...)
+    Opcode,
+    /// Out of gas (OP-020)
+    OutOfGas,
+    /// Call stack rules (OP-052, OP-053, OP-054, OP-061, ...)
+    CallStack,
+    /// Code hashes changed between validations (COD-010)
+    CodeHashes,
+}
+
+impl TraceViolation {
+    /// Returns the [TraceViolation](TraceViolation) which caused the simulation error (if any).
+    ///
+    /// # Arguments
+    /// `err` - The [SimulationError](SimulationError) returned by the simulation trace check.
+    ///
+    /// # Returns
+    /// `Option<TraceViolation>` - The trace violation or `None` if the error can't be downgraded.
+    pub fn from_error(err: &SimulationError) -> Option<Self> {
+        match err {
+            SimulationError::Opcode { .. } => Some(Self::Opcode),
+            SimulationError::OutOfGas => Some(Self::OutOfGas),
+            SimulationError::CallStack { .. } => Some(Self::CallStack),
+            SimulationError::CodeHashes { .. } => Some(Self::CodeHashes),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for TraceViolation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "opcode" => Ok(Self::Opcode),
+            "out-of-gas" => Ok(Self::OutOfGas),
+            "call-stack" => Ok(Self::CallStack),
+            "code-hashes" => Ok(Self::CodeHashes),
+            _ => Err(format!("{s} is not a valid trace violation")),
+        }
+    }
+}
+
 /// The [UserOperation](UserOperation) validator trait.
 /// The [UserOperationValidator](UserOperationValidator) is a composable trait that allows bundler
 /// to choose validation rules(sanity, simultation, simulation trace) to apply.
@@ -58,13 +149,55 @@ pub trait UserOperationValidator: Send + Sync {
         val_config: Option<ValidationConfig>,
         mode: EnumSet<UserOperationValidatorMode>,
     ) -> Result<UserOperationValidationOutcome, InvalidMempoolUserOperationError>;
+
+    async fn trace_user_operation_validation(
+        &self,
+        uo: &UserOperation,
+        mempool: &Mempool,
+        reputation: &Reputation,
+        val_config: Option<ValidationConfig>,
+    ) -> Result<ValidationTrace, InvalidMempoolUserOperationError>;
+
+    /// Returns the min priority fee per gas the user operations of the entry point are required
+    /// to pay (zero if the validator doesn't enforce one).
+    fn min_priority_fee_per_gas(&self, _entry_point: &Address) -> U256 {
+        U256::zero()
+    }
+
+    /// Returns whether EOAs delegated to a contract (EIP-7702) are accepted as deployed senders
+    /// with the authorization in the initCode.
+    fn eip7702_senders(&self) -> bool {
+        false
+    }
 }
 
 /// The [UserOperation] sanity check helper trait.
 pub struct SanityHelper<'a, M: Middleware + 'static> {
     entry_point: &'a EntryPoint<M>,
-    chain: Chain,
     val_config: ValidationConfig,
+    acceptance_window: Option<u64>,
+    subsidized: bool,
+    eip7702_senders: bool,
+    overhead: &'a OverheadConfig<M>,
+}
+
+#[cfg(test)]
+impl<'a, M: Middleware + 'static> SanityHelper<'a, M> {
+    /// Creates a sanity check helper with the default configuration (the tests override the
+    /// fields they need)
+    pub(crate) fn new_test(
+        entry_point: &'a EntryPoint<M>,
+        overhead: &'a OverheadConfig<M>,
+    ) -> Self {
+        Self {
+            entry_point,
+            val_config: Default::default(),
+            acceptance_window: None,
+            subsidized: false,
+            eip7702_senders: false,
+            overhead,
+        }
+    }
 }
 
 #[async_trait::async_trait]
@@ -101,6 +234,12 @@ pub trait SanityCheck<M: Middleware>: Send + Sync {
         reputation: &Reputation,
         helper: &SanityHelper<M>,
     ) -> Result<(), SanityError>;
+
+    /// Returns the min priority fee per gas enforced by the check for the user operations of the
+    /// given entry point (None if the check doesn't enforce one).
+    fn min_priority_fee_per_gas(&self, _entry_point: &Address) -> Option<U256> {
+        None
+    }
 }
 
 macro_rules! sanity_check_impls {
@@ -121,6 +260,11 @@ macro_rules! sanity_check_impls {
                     ($($name.check_user_operation(uo, mempool, reputation, helper).await?,)+);
                     Ok(())
                 }
+
+            fn min_priority_fee_per_gas(&self, entry_point: &Address) -> Option<U256> {
+                let ($($name,)+) = self;
+                None$(.or_else(|| $name.min_priority_fee_per_gas(entry_point)))+
+            }
         }
     };
 }
@@ -151,6 +295,8 @@ sanity_check_impls! { A B C D F G I }
 sanity_check_impls! { A B C D F G I J }
 sanity_check_impls! { A B C D F G I J K }
 sanity_check_impls! { A B C D F G I J K L }
+sanity_check_impls! { A B C D F G I J K L N }
+sanity_check_impls! { A B C D F G I J K L N O }
 
 /// The [UserOperation] simulation check helper trait.
 pub struct SimulationHelper<'a> {
@@ -222,10 +368,55 @@ pub struct SimulationTraceHelper<'a, M: Middleware + Send + Sync + 'static> {
     val_config: ValidationConfig,
     stake_info: Option<[StakeInfo; NUMBER_OF_LEVELS]>,
     code_hashes: Option<Vec<CodeHash>>,
+    downgraded_violations: EnumSet<TraceViolation>,
+    verify_signature_hash: bool,
+    strict_opcodes: bool,
+    // opcodes allowed in the frames of the trusted factories (by factory address)
+    trusted_factory_opcodes: &'a HashMap<Address, HashSet<String>>,
+    // name of the check which rejected the user operation
+    failed_check: Option<&'static str>,
+}
+
+#[cfg(test)]
+impl<'a, M: Middleware + Send + Sync + 'static> SimulationTraceHelper<'a, M> {
+    /// Creates a simulation trace helper with the default configuration (the tests override the
+    /// fields they need)
+    pub(crate) fn new_test(
+        entry_point: &'a EntryPoint<M>,
+        simulate_validation_result: &'a SimulateValidationResult,
+        js_trace: &'a JsTracerFrame,
+        trusted_factory_opcodes: &'a HashMap<Address, HashSet<String>>,
+    ) -> Self {
+        Self {
+            entry_point,
+            chain: Chain::from(1337),
+            simulate_validation_result,
+            js_trace,
+            val_config: Default::default(),
+            stake_info: None,
+            code_hashes: None,
+            downgraded_violations: EnumSet::empty(),
+            verify_signature_hash: false,
+            strict_opcodes: false,
+            trusted_factory_opcodes,
+            failed_check: None,
+        }
+    }
+}
+
+impl<'a, M: Middleware + Send + Sync + 'static> SimulationTraceHelper<'a, M> {
+    /// Checks whether the simulation error should be downgraded to a warning.
+    fn is_downgraded(&self, err: &SimulationError) -> bool {
+        TraceViolation::from_error(err).is_some_and(|v| self.downgraded_violations.contains(v))
+    }
 }
 
 #[async_trait::async_trait]
 pub trait SimulationTraceCheck<M: Middleware>: Send + Sync {
+    /// Name of the check (reported as the failed check of a validation trace), e.g.,
+    /// `StorageAccess`
+    fn name(&self) -> &'static str;
+
     /// Asynchronously checks a user operation against the mempool, reputation, and simulation
     /// trace.
     ///
@@ -264,6 +455,11 @@ macro_rules! simulation_trace_check_impls {
         #[async_trait::async_trait]
         impl<M: Middleware, $($name : SimulationTraceCheck<M>,)+> SimulationTraceCheck<M> for ($($name,)+)
         {
+            // the failing check of the tuple is reported by its own name
+            fn name(&self) -> &'static str {
+                "SimulationTraceChecks"
+            }
+
             async fn check_user_operation(
                 &self,
                 uo: &UserOperation,
@@ -273,7 +469,19 @@ macro_rules! simulation_trace_check_impls {
             ) -> Result<(), SimulationError>
                 {
                     let ($($name,)+) = self;
-                    ($($name.check_user_operation(uo, mempool, reputation, helper).await?,)+);
+                    $(
+                        match $name.check_user_operation(uo, mempool, reputation, helper).await {
+                            Err(err) if helper.is_downgraded(&err) => {
+                                warn!("Downgraded simulation trace violation of user operation {:?}: {err}", uo.hash);
+                            }
+                            Err(err) => {
+                                debug!(user_operation = ?uo.hash, sender = ?uo.sender, error = %err, "Simulation trace check failed");
+                                helper.failed_check.get_or_insert($name.name());
+                                return Err(err);
+                            }
+                            Ok(()) => {}
+                        }
+                    )+
                     Ok(())
                 }
         }
@@ -282,6 +490,10 @@ macro_rules! simulation_trace_check_impls {
 
 #[async_trait::async_trait]
 impl<M: Middleware> SimulationTraceCheck<M> for () {
+    fn name(&self) -> &'static str {
+        "SimulationTraceChecks"
+    }
+
     async fn check_user_operation(
         &self,
         _uo: &UserOperation,
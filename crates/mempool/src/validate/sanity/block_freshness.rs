@@ -0,0 +1,107 @@
+use crate::{
+    mempool::Mempool,
+    validate::{SanityCheck, SanityHelper},
+    Reputation, SanityError,
+};
+use ethers::{providers::Middleware, types::BlockNumber};
+use silius_primitives::UserOperation;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Clone)]
+pub struct BlockFreshness;
+
+#[async_trait::async_trait]
+impl<M: Middleware> SanityCheck<M> for BlockFreshness {
+    /// The method implementation that checks that the user operation is received within the
+    /// acceptance window after the latest block (so it isn't validated against stale state).
+    ///
+    /// # Arguments
+    /// `uo` - The user operation to check
+    /// `helper` - The helper struct that contains the middleware and the acceptance window
+    ///
+    /// # Returns
+    /// None if the check passes, otherwise a [SanityError]
+    async fn check_user_operation(
+        &self,
+        _uo: &UserOperation,
+        _mempool: &Mempool,
+        _reputation: &Reputation,
+        helper: &SanityHelper<M>,
+    ) -> Result<(), SanityError> {
+        let acceptance_window = match helper.acceptance_window {
+            Some(acceptance_window) => acceptance_window,
+            None => return Ok(()),
+        };
+
+        let block = helper
+            .entry_point
+            .eth_client()
+            .get_block(BlockNumber::Latest)
+            .await
+            .map_err(|err| SanityError::Provider { inner: err.to_string() })?
+            .ok_or(SanityError::Other { inner: "No block found".into() })?;
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|err| SanityError::Other { inner: err.to_string() })?
+            .as_secs();
+        let age = now.saturating_sub(block.timestamp.as_u64());
+
+        if age > acceptance_window {
+            return Err(SanityError::StaleBlock { age, acceptance_window });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_utils::{memory_mempool, memory_reputation},
+        OverheadConfig,
+    };
+    use ethers::{
+        providers::Provider,
+        types::{Address, Block, TxHash, U256},
+    };
+    use silius_contracts::EntryPoint;
+    use silius_primitives::{UserOperationHash, UserOperationSigned};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn reject_outside_acceptance_window() {
+        let (provider, mock) = Provider::mocked();
+        let entry_point = EntryPoint::new(Arc::new(provider), Address::random());
+        let mempool = memory_mempool();
+        let reputation = memory_reputation();
+        let overhead = OverheadConfig::default();
+        let mut helper = SanityHelper::new_test(&entry_point, &overhead);
+        helper.acceptance_window = Some(12);
+
+        let uo = UserOperation::from_user_operation_signed(
+            UserOperationHash::default(),
+            UserOperationSigned::default(),
+        );
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let block = |timestamp: u64| Block::<TxHash> {
+            timestamp: U256::from(timestamp),
+            ..Default::default()
+        };
+
+        // the latest block is within the acceptance window
+        mock.push(block(now)).unwrap();
+        assert!(BlockFreshness
+            .check_user_operation(&uo, &mempool, &reputation, &helper)
+            .await
+            .is_ok());
+
+        // no block was observed for a minute
+        mock.push(block(now - 60)).unwrap();
+        assert!(matches!(
+            BlockFreshness.check_user_operation(&uo, &mempool, &reputation, &helper).await,
+            Err(SanityError::StaleBlock { age, acceptance_window: 12 }) if age >= 60
+        ));
+    }
+}
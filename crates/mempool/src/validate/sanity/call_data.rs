@@ -0,0 +1,103 @@
+use crate::{
+    mempool::Mempool,
+    validate::{SanityCheck, SanityHelper},
+    Reputation, SanityError,
+};
+use ethers::{
+    abi::Selector,
+    providers::Middleware,
+    types::{Address, Bytes},
+};
+use silius_primitives::{decode_inner_calls, UserOperation};
+use std::collections::HashSet;
+
+/// Allowlist and denylist of the function selectors called by user operations
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SelectorFilter {
+    /// If not empty, only these selectors are accepted
+    pub allowlist: HashSet<Selector>,
+    /// Selectors which are never accepted
+    pub denylist: HashSet<Selector>,
+}
+
+impl SelectorFilter {
+    pub fn new(allowlist: Vec<Selector>, denylist: Vec<Selector>) -> Self {
+        Self {
+            allowlist: allowlist.into_iter().collect(),
+            denylist: denylist.into_iter().collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.allowlist.is_empty() && self.denylist.is_empty()
+    }
+
+    pub fn is_allowed(&self, selector: &Selector) -> bool {
+        (self.allowlist.is_empty() || self.allowlist.contains(selector)) &&
+            !self.denylist.contains(selector)
+    }
+}
+
+/// Returns the selectors called by the account with their targets: the selectors of the inner
+/// calls if the call data is an `execute`/`executeBatch` call, otherwise the selector of the call
+/// data itself (without a known target). Inner calls without call data (plain transfers) don't
+/// have a selector.
+fn called_selectors(call_data: &Bytes) -> Vec<(Option<Address>, Selector)> {
+    match decode_inner_calls(call_data) {
+        Some(inner_calls) => inner_calls
+            .iter()
+            .filter_map(|call| call.selector().map(|selector| (Some(call.target), selector)))
+            .collect(),
+        None => call_data
+            .get(..4)
+            .map(|s| vec![(None, s.try_into().expect("selector is 4 bytes long"))])
+            .unwrap_or_default(),
+    }
+}
+
+#[derive(Clone)]
+pub struct CallData {
+    /// Function selectors which user operations are allowed (or not allowed) to call
+    pub selector_filter: SelectorFilter,
+}
+
+#[async_trait::async_trait]
+impl<M: Middleware> SanityCheck<M> for CallData {
+    /// The method implementation that checks that the function selectors called by the user
+    /// operation (the inner calls of `execute`/`executeBatch`) match the selector allowlist and
+    /// denylist of the bundler.
+    ///
+    /// # Arguments
+    /// `uo` - The user operation to check
+    ///
+    /// # Returns
+    /// None if the check passes, otherwise a [SanityError]
+    async fn check_user_operation(
+        &self,
+        uo: &UserOperation,
+        _mempool: &Mempool,
+        _reputation: &Reputation,
+        _helper: &SanityHelper<M>,
+    ) -> Result<(), SanityError> {
+        if self.selector_filter.is_empty() {
+            return Ok(());
+        }
+
+        if let Some((target, selector)) = called_selectors(&uo.call_data)
+            .into_iter()
+            .find(|(_, selector)| !self.selector_filter.is_allowed(selector))
+        {
+            let selector = Bytes::from(selector.to_vec());
+            return Err(SanityError::CallData {
+                inner: match target {
+                    Some(target) => format!(
+                        "function selector {selector} called on {target:?} is not accepted by the bundler"
+                    ),
+                    None => format!("function selector {selector} is not accepted by the bundler"),
+                },
+            });
+        }
+
+        Ok(())
+    }
+}
@@ -7,15 +7,19 @@ use ethers::{providers::Middleware, types::U256};
 use silius_primitives::UserOperation;
 
 #[derive(Clone)]
-pub struct CallGas;
+pub struct CallGas {
+    /// Min call gas limit of user operations (lower call gas limits revert in execution)
+    pub min_call_gas: U256,
+}
 
 #[async_trait::async_trait]
 impl<M: Middleware> SanityCheck<M> for CallGas {
-    /// The `check_user_operation` method implementation for the `CallGas` sanity check.
+    /// The `check_user_operation` method implementation for the `CallGas` sanity check: the call
+    /// gas limit is at least the min call gas limit of the bundler.
     ///
     /// # Arguments
     /// `uo` - The user operation to check.
-    /// `helper` - The helper struct that contains the entry point and the Ethereum client.
+    /// `helper` - The helper struct (the min call gas limit is set on the check).
     ///
     /// # Returns
     /// None if the sanity check passes, otherwise [SanityError].
@@ -26,18 +30,13 @@ impl<M: Middleware> SanityCheck<M> for CallGas {
         _reputation: &Reputation,
         _helper: &SanityHelper<M>,
     ) -> Result<(), SanityError> {
-        // call gas limit is at least the cost of a CALL with non-zero value
-        // https://github.com/wolflo/evm-opcodes/blob/main/gas.md#aa-1-call
-        // gas_cost = 100 + 9000
-        let call_gas_limit = U256::from(9100);
-
-        if uo.call_gas_limit >= call_gas_limit {
+        if uo.call_gas_limit >= self.min_call_gas {
             return Ok(());
         }
 
         Err(SanityError::CallGasLimitTooLow {
             call_gas_limit: uo.call_gas_limit,
-            call_gas_limit_expected: call_gas_limit,
+            call_gas_limit_expected: self.min_call_gas,
         })
     }
 }
@@ -6,10 +6,7 @@ use crate::{
 };
 use ethers::{providers::Middleware, types::Address};
 use silius_primitives::{
-    constants::validation::{
-        entities::{FACTORY, PAYMASTER, SENDER},
-        reputation::THROTTLED_ENTITY_MEMPOOL_COUNT,
-    },
+    constants::validation::entities::{FACTORY, PAYMASTER, SENDER},
     reputation::Status,
     UserOperation,
 };
@@ -45,7 +42,7 @@ impl Entities {
     }
 
     /// [SREP-030] - THROTTLED address is limited to THROTTLED_ENTITY_MEMPOOL_COUNT entries in the
-    /// mempool
+    /// mempool (and an address on PROBATION to PROBATION_ENTITY_MEMPOOL_COUNT entries)
     fn check_throttled<M: Middleware>(
         &self,
         entity: &str,
@@ -55,10 +52,9 @@ impl Entities {
         mempool: &Mempool,
         _reputation: &Reputation,
     ) -> Result<(), SanityError> {
-        if *status == Status::THROTTLED &&
-            (mempool.get_number_by_sender(addr) + mempool.get_number_by_entity(addr)) >=
-                THROTTLED_ENTITY_MEMPOOL_COUNT
-        {
+        if status.mempool_allowance().is_some_and(|allowance| {
+            (mempool.get_number_by_sender(addr) + mempool.get_number_by_entity(addr)) >= allowance
+        }) {
             return Err(
                 ReputationError::ThrottledEntity { entity: entity.into(), address: *addr }.into()
             );
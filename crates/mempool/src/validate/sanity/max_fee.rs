@@ -5,21 +5,37 @@ use crate::{
 };
 use ethers::{
     providers::Middleware,
-    types::{BlockNumber, U256},
+    types::{Address, BlockNumber, U256},
 };
 use silius_primitives::UserOperation;
+use std::collections::HashMap;
 
 #[derive(Clone)]
 pub struct MaxFee {
     pub min_priority_fee_per_gas: U256,
+    /// Min priority fee per gas of specific entry points (overriding the default one)
+    pub ep_min_priority_fees: HashMap<Address, U256>,
+}
+
+impl MaxFee {
+    /// Returns the min priority fee per gas of the entry point (the default one if it isn't set
+    /// for the entry point or the entry point is unknown)
+    fn min_priority_fee_per_gas_of(&self, entry_point: Option<&Address>) -> U256 {
+        entry_point
+            .and_then(|ep| self.ep_min_priority_fees.get(ep))
+            .copied()
+            .unwrap_or(self.min_priority_fee_per_gas)
+    }
 }
 
 #[async_trait::async_trait]
 impl<M: Middleware> SanityCheck<M> for MaxFee {
-    /// The method implementation that checks the max fee.
+    /// The method implementation that checks the max fee (any fee is accepted if the bundler is
+    /// subsidized).
     ///
     /// # Arguments
     /// `uo` - The user operation to check
+    /// `mempool` - The mempool of the entry point (which determines the min priority fee per gas)
     /// `helper` - The helper struct that contains the middleware
     ///
     /// # Returns
@@ -27,17 +43,10 @@ impl<M: Middleware> SanityCheck<M> for MaxFee {
     async fn check_user_operation(
         &self,
         uo: &UserOperation,
-        _mempool: &Mempool,
+        mempool: &Mempool,
         _reputation: &Reputation,
         helper: &SanityHelper<M>,
     ) -> Result<(), SanityError> {
-        if uo.max_priority_fee_per_gas > uo.max_fee_per_gas {
-            return Err(SanityError::MaxPriorityFeePerGasTooHigh {
-                max_priority_fee_per_gas: uo.max_priority_fee_per_gas,
-                max_fee_per_gas: uo.max_fee_per_gas,
-            });
-        }
-
         let block = helper
             .entry_point
             .eth_client()
@@ -48,20 +57,28 @@ impl<M: Middleware> SanityCheck<M> for MaxFee {
         let base_fee_per_gas =
             block.base_fee_per_gas.ok_or(SanityError::Other { inner: "No base fee".into() })?;
 
-        if base_fee_per_gas > uo.max_fee_per_gas {
+        // subsidized bundler pays the part of the base fee not covered by the max fee
+        if !helper.subsidized && base_fee_per_gas > uo.max_fee_per_gas {
             return Err(SanityError::MaxFeePerGasTooLow {
                 max_fee_per_gas: uo.max_fee_per_gas,
                 base_fee_per_gas,
             });
         }
 
-        if uo.max_priority_fee_per_gas < self.min_priority_fee_per_gas {
+        // subsidized bundler accepts any priority fee
+        let min_priority_fee_per_gas =
+            self.min_priority_fee_per_gas_of(mempool.entry_point().as_ref());
+        if !helper.subsidized && uo.max_priority_fee_per_gas < min_priority_fee_per_gas {
             return Err(SanityError::MaxPriorityFeePerGasTooLow {
                 max_priority_fee_per_gas: uo.max_priority_fee_per_gas,
-                max_priority_fee_per_gas_expected: self.min_priority_fee_per_gas,
+                max_priority_fee_per_gas_expected: min_priority_fee_per_gas,
             });
         }
 
         Ok(())
     }
+
+    fn min_priority_fee_per_gas(&self, entry_point: &Address) -> Option<U256> {
+        Some(self.min_priority_fee_per_gas_of(Some(entry_point)))
+    }
 }
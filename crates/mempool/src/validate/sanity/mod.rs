@@ -1,9 +1,14 @@
 //! Sanity module performs call gas limit, verification gas limit, max priority fee, paymaster
 //! verification, sender vericiation, and UserOperation type checks
+pub mod block_freshness;
+pub mod call_data;
 pub mod call_gas;
 pub mod entities;
 pub mod max_fee;
 pub mod paymaster;
+pub mod prefund;
+pub mod priority_fee_cap;
 pub mod sender;
+pub mod simulation_gas;
 pub mod unstaked_entities;
 pub mod verification_gas;
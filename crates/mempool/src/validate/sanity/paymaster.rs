@@ -4,14 +4,20 @@ use crate::{
     Reputation, SanityError,
 };
 use ethers::{providers::Middleware, types::U256};
-use silius_primitives::{get_address, UserOperation};
+use silius_primitives::{get_address, reputation::StakeInfo, UserOperation};
 
 #[derive(Clone)]
-pub struct Paymaster;
+pub struct Paymaster {
+    /// Whether paymasters have to be either staked or whitelisted
+    pub require_staked_paymaster: bool,
+}
 
 #[async_trait::async_trait]
 impl<M: Middleware> SanityCheck<M> for Paymaster {
-    /// The method implementation that performs the sanity check on the paymaster.
+    /// The method implementation that performs the sanity check on the paymaster. If required by
+    /// the bundler, the paymaster also has to be either staked or whitelisted. Otherwise paymasters
+    /// with a too low stake or unstake delay count as unstaked and are left to the
+    /// [unstaked entities](crate::validate::sanity::unstaked_entities) check.
     ///
     /// # Arguments
     /// `uo` - The user operation to be checked.
@@ -24,7 +30,7 @@ impl<M: Middleware> SanityCheck<M> for Paymaster {
         &self,
         uo: &UserOperation,
         _mempool: &Mempool,
-        _reputation: &Reputation,
+        reputation: &Reputation,
         helper: &SanityHelper<M>,
     ) -> Result<(), SanityError> {
         if !uo.paymaster_and_data.is_empty() {
@@ -40,6 +46,26 @@ impl<M: Middleware> SanityCheck<M> for Paymaster {
                     let deposit_info = helper.entry_point.get_deposit_info(&addr).await?;
 
                     if U256::from(deposit_info.deposit) >= uo.max_fee_per_gas {
+                        let stake_info = StakeInfo {
+                            address: addr,
+                            stake: U256::from(deposit_info.stake),
+                            unstake_delay: U256::from(deposit_info.unstake_delay_sec),
+                        };
+
+                        if self.require_staked_paymaster &&
+                            !reputation.is_staked(
+                                &stake_info,
+                                helper.val_config.min_stake,
+                                helper.val_config.min_unstake_delay,
+                            )
+                        {
+                            return Err(SanityError::Paymaster {
+                                inner: format!(
+                                    "Paymaster {addr:?} is neither staked nor whitelisted"
+                                ),
+                            });
+                        }
+
                         return Ok(());
                     }
                 }
@@ -51,3 +77,97 @@ impl<M: Middleware> SanityCheck<M> for Paymaster {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_utils::{memory_mempool, memory_reputation_with_min_stake},
+        OverheadConfig,
+    };
+    use ethers::{
+        abi::{encode, Token},
+        providers::Provider,
+        types::{Address, Bytes},
+    };
+    use silius_contracts::EntryPoint;
+    use silius_primitives::{UserOperationHash, UserOperationSigned};
+    use std::sync::Arc;
+
+    fn deposit_info(stake: u64, unstake_delay_sec: u64) -> Bytes {
+        encode(&[Token::Tuple(vec![
+            Token::Uint(U256::from(1_000_000)),
+            Token::Bool(stake > 0),
+            Token::Uint(U256::from(stake)),
+            Token::Uint(U256::from(unstake_delay_sec)),
+            Token::Uint(U256::zero()),
+        ])])
+        .into()
+    }
+
+    #[tokio::test]
+    async fn paymaster_min_unstake_delay() {
+        let (provider, mock) = Provider::mocked();
+        let entry_point = EntryPoint::new(Arc::new(provider), Address::random());
+        let mempool = memory_mempool();
+        let mut reputation = memory_reputation_with_min_stake(U256::from(1), U256::from(86400));
+        let overhead = OverheadConfig::default();
+        let helper = SanityHelper::new_test(&entry_point, &overhead);
+
+        let check = Paymaster { require_staked_paymaster: false };
+        let check_staked = Paymaster { require_staked_paymaster: true };
+        let paymaster = Address::random();
+        let uo = UserOperation::from_user_operation_signed(
+            UserOperationHash::default(),
+            UserOperationSigned {
+                sender: Address::random(),
+                paymaster_and_data: paymaster.as_bytes().to_vec().into(),
+                max_fee_per_gas: U256::from(100),
+                ..Default::default()
+            },
+        );
+        // mocked responses are returned in reverse order (code of the paymaster, deposit info)
+        let mock_paymaster = |stake, unstake_delay_sec| {
+            mock.push(deposit_info(stake, unstake_delay_sec)).unwrap();
+            mock.push(Bytes::from(vec![0x60, 0x80])).unwrap();
+        };
+
+        // staked with a long enough unstake delay
+        mock_paymaster(100, 86400);
+        assert!(check.check_user_operation(&uo, &mempool, &reputation, &helper).await.is_ok());
+
+        mock_paymaster(100, 86400);
+        assert!(check_staked
+            .check_user_operation(&uo, &mempool, &reputation, &helper)
+            .await
+            .is_ok());
+
+        // a too short unstake delay counts as unstaked, so it's left to the unstaked entities check
+        // unless staked paymasters are required
+        mock_paymaster(100, 3600);
+        assert!(check.check_user_operation(&uo, &mempool, &reputation, &helper).await.is_ok());
+        mock_paymaster(100, 3600);
+        assert!(matches!(
+            check_staked.check_user_operation(&uo, &mempool, &reputation, &helper).await,
+            Err(SanityError::Paymaster { .. })
+        ));
+
+        // whitelisted paymasters count as staked
+        reputation.add_whitelist(&paymaster);
+        mock_paymaster(100, 3600);
+        assert!(check_staked
+            .check_user_operation(&uo, &mempool, &reputation, &helper)
+            .await
+            .is_ok());
+        reputation.remove_whitelist(&paymaster);
+
+        // unstaked paymasters are handled by the unstaked entities check
+        mock_paymaster(0, 0);
+        assert!(check.check_user_operation(&uo, &mempool, &reputation, &helper).await.is_ok());
+        mock_paymaster(0, 0);
+        assert!(matches!(
+            check_staked.check_user_operation(&uo, &mempool, &reputation, &helper).await,
+            Err(SanityError::Paymaster { .. })
+        ));
+    }
+}
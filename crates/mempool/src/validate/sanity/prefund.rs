@@ -0,0 +1,141 @@
+use crate::{
+    mempool::Mempool,
+    validate::{SanityCheck, SanityHelper},
+    Reputation, SanityError,
+};
+use ethers::providers::Middleware;
+use silius_primitives::{PrefundPolicy, UserOperation};
+
+#[derive(Clone)]
+pub struct Prefund {
+    /// Funds of the sender required to cover the prefund of user operations without a paymaster
+    pub policy: PrefundPolicy,
+}
+
+#[async_trait::async_trait]
+impl<M: Middleware> SanityCheck<M> for Prefund {
+    /// The method implementation that checks that the sender of a user operation without a
+    /// paymaster can cover the required prefund, either with its deposit in the entry point or
+    /// (if allowed by the [PrefundPolicy]) with its deposit plus its balance.
+    ///
+    /// # Arguments
+    /// `uo` - The user operation to check
+    /// `helper` - The helper struct that contains the middleware
+    ///
+    /// # Returns
+    /// None if the check passes, otherwise a [SanityError]
+    async fn check_user_operation(
+        &self,
+        uo: &UserOperation,
+        _mempool: &Mempool,
+        _reputation: &Reputation,
+        helper: &SanityHelper<M>,
+    ) -> Result<(), SanityError> {
+        // sponsored user operations are prefunded by the paymaster
+        if self.policy == PrefundPolicy::Disabled || !uo.paymaster_and_data.is_empty() {
+            return Ok(());
+        }
+
+        let required_prefund = uo
+            .call_gas_limit
+            .saturating_add(uo.verification_gas_limit)
+            .saturating_add(uo.pre_verification_gas)
+            .saturating_mul(uo.max_fee_per_gas);
+
+        let mut funds = helper.entry_point.balance_of(&uo.sender).await?;
+
+        if funds < required_prefund && self.policy == PrefundPolicy::DepositAndBalance {
+            let balance = helper
+                .entry_point
+                .eth_client()
+                .get_balance(uo.sender, None)
+                .await
+                .map_err(|err| SanityError::Provider { inner: err.to_string() })?;
+            funds = funds.saturating_add(balance);
+        }
+
+        if funds < required_prefund {
+            return Err(SanityError::InsufficientPrefund { funds, required_prefund });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_utils::{memory_mempool, memory_reputation},
+        OverheadConfig,
+    };
+    use ethers::{
+        abi::{encode, Token},
+        providers::Provider,
+        types::{Address, Bytes, U256},
+    };
+    use silius_contracts::EntryPoint;
+    use silius_primitives::{UserOperationHash, UserOperationSigned};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn underfunded_sender_without_paymaster() {
+        let (provider, mock) = Provider::mocked();
+        let entry_point = EntryPoint::new(Arc::new(provider), Address::random());
+        let mempool = memory_mempool();
+        let reputation = memory_reputation();
+        let overhead = OverheadConfig::default();
+        let helper = SanityHelper::new_test(&entry_point, &overhead);
+        let check = Prefund { policy: PrefundPolicy::Deposit };
+
+        // required prefund of (100_000 + 100_000 + 50_000) * 10 = 2_500_000
+        let uo = UserOperation::from_user_operation_signed(
+            UserOperationHash::default(),
+            UserOperationSigned {
+                sender: Address::random(),
+                call_gas_limit: U256::from(100_000),
+                verification_gas_limit: U256::from(100_000),
+                pre_verification_gas: U256::from(50_000),
+                max_fee_per_gas: U256::from(10),
+                ..Default::default()
+            },
+        );
+        let deposit = |amount: u64| -> Bytes { encode(&[Token::Uint(U256::from(amount))]).into() };
+
+        // the deposit covers the prefund
+        mock.push(deposit(2_500_000)).unwrap();
+        assert!(check.check_user_operation(&uo, &mempool, &reputation, &helper).await.is_ok());
+
+        // underfunded sender
+        mock.push(deposit(1_000_000)).unwrap();
+        assert!(matches!(
+            check.check_user_operation(&uo, &mempool, &reputation, &helper).await,
+            Err(SanityError::InsufficientPrefund { funds, required_prefund })
+                if funds == U256::from(1_000_000) && required_prefund == U256::from(2_500_000)
+        ));
+
+        // the balance of the sender makes up for the missing deposit (responses are popped in
+        // the reverse order)
+        let check = Prefund { policy: PrefundPolicy::DepositAndBalance };
+        mock.push(U256::from(1_500_000)).unwrap();
+        mock.push(deposit(1_000_000)).unwrap();
+        assert!(check.check_user_operation(&uo, &mempool, &reputation, &helper).await.is_ok());
+
+        mock.push(U256::from(1_000_000)).unwrap();
+        mock.push(deposit(1_000_000)).unwrap();
+        assert!(matches!(
+            check.check_user_operation(&uo, &mempool, &reputation, &helper).await,
+            Err(SanityError::InsufficientPrefund { .. })
+        ));
+
+        // user operations with a paymaster aren't checked
+        let uo = UserOperation::from_user_operation_signed(
+            UserOperationHash::default(),
+            UserOperationSigned {
+                paymaster_and_data: Address::random().as_bytes().to_vec().into(),
+                ..uo.user_operation.clone()
+            },
+        );
+        assert!(check.check_user_operation(&uo, &mempool, &reputation, &helper).await.is_ok());
+    }
+}
@@ -0,0 +1,94 @@
+use crate::{
+    mempool::Mempool,
+    validate::{SanityCheck, SanityHelper},
+    Reputation, SanityError,
+};
+use ethers::providers::Middleware;
+use silius_primitives::UserOperation;
+
+#[derive(Clone)]
+pub struct PriorityFeeCap {
+    /// Whether user operations whose max priority fee per gas exceeds the max fee per gas are
+    /// rejected
+    pub enabled: bool,
+}
+
+#[async_trait::async_trait]
+impl<M: Middleware> SanityCheck<M> for PriorityFeeCap {
+    /// The method implementation that checks that the max priority fee per gas doesn't exceed the
+    /// max fee per gas (as required by [EIP-1559](https://eips.ethereum.org/EIPS/eip-1559)).
+    ///
+    /// # Arguments
+    /// `uo` - The user operation to check
+    ///
+    /// # Returns
+    /// None if the check passes, otherwise a [SanityError]
+    async fn check_user_operation(
+        &self,
+        uo: &UserOperation,
+        _mempool: &Mempool,
+        _reputation: &Reputation,
+        _helper: &SanityHelper<M>,
+    ) -> Result<(), SanityError> {
+        if self.enabled && uo.max_priority_fee_per_gas > uo.max_fee_per_gas {
+            return Err(SanityError::MaxPriorityFeePerGasTooHigh {
+                max_priority_fee_per_gas: uo.max_priority_fee_per_gas,
+                max_fee_per_gas: uo.max_fee_per_gas,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_utils::{memory_mempool, memory_reputation},
+        OverheadConfig,
+    };
+    use ethers::{
+        providers::Provider,
+        types::{Address, U256},
+    };
+    use silius_contracts::EntryPoint;
+    use silius_primitives::{UserOperationHash, UserOperationSigned};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn priority_fee_above_max_fee() {
+        let (provider, _) = Provider::mocked();
+        let entry_point = EntryPoint::new(Arc::new(provider), Address::random());
+        let mempool = memory_mempool();
+        let reputation = memory_reputation();
+        let overhead = OverheadConfig::default();
+        let helper = SanityHelper::new_test(&entry_point, &overhead);
+
+        let uo = |max_fee_per_gas: u64, max_priority_fee_per_gas: u64| {
+            UserOperation::from_user_operation_signed(
+                UserOperationHash::default(),
+                UserOperationSigned::default()
+                    .max_fee_per_gas(U256::from(max_fee_per_gas))
+                    .max_priority_fee_per_gas(U256::from(max_priority_fee_per_gas)),
+            )
+        };
+        let check = PriorityFeeCap { enabled: true };
+
+        assert!(check
+            .check_user_operation(&uo(10, 10), &mempool, &reputation, &helper)
+            .await
+            .is_ok());
+        assert!(matches!(
+            check.check_user_operation(&uo(10, 11), &mempool, &reputation, &helper).await,
+            Err(SanityError::MaxPriorityFeePerGasTooHigh { .. })
+        ));
+
+        // inverted fees are accepted if the check is disabled
+        let check = PriorityFeeCap { enabled: false };
+        assert!(check
+            .check_user_operation(&uo(10, 11), &mempool, &reputation, &helper)
+            .await
+            .is_ok());
+    }
+}
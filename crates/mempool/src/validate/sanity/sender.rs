@@ -1,19 +1,27 @@
 use crate::{
     mempool::Mempool,
     utils::calculate_valid_gas,
-    validate::{SanityCheck, SanityHelper},
+    validate::{
+        utils::{extract_eip7702_delegate, is_eip7702_init_code},
+        SanityCheck, SanityHelper,
+    },
     Reputation, SanityError,
 };
 use ethers::providers::Middleware;
-use silius_primitives::{constants::mempool::GAS_INCREASE_PERC, UserOperation};
+use silius_primitives::UserOperation;
 
 #[derive(Clone)]
-pub struct Sender;
+pub struct Sender {
+    /// Min increase (percentage) of the fees of a user operation replacing the one with the same
+    /// sender and nonce
+    pub replacement_fee_increase: u64,
+}
 
 #[async_trait::async_trait]
 impl<M: Middleware> SanityCheck<M> for Sender {
     /// The method implementation that performs the check for the sender of
-    /// the [UserOperation](UserOperation).
+    /// the [UserOperation](UserOperation). If enabled, an EOA delegated to a contract (EIP-7702)
+    /// is a deployed sender even if the initCode carries the authorization.
     ///
     /// # Arguments
     /// `uo` - The [UserOperation](UserOperation) to be checked.
@@ -37,12 +45,28 @@ impl<M: Middleware> SanityCheck<M> for Sender {
             .await
             .map_err(|e| SanityError::Provider { inner: e.to_string() })?;
 
-        // check if sender or init code
-        if (code.is_empty() && uo.init_code.is_empty()) ||
-            (!code.is_empty() && !uo.init_code.is_empty())
-        {
+        // EOA delegated to a contract (EIP-7702) with the authorization in the initCode
+        let eip7702_authorization = helper.eip7702_senders &&
+            extract_eip7702_delegate(&code).is_some() &&
+            is_eip7702_init_code(&uo.init_code);
+
+        // sender without code (nor delegation) can't validate the user operation unless it is
+        // deployed
+        if code.is_empty() && uo.init_code.is_empty() {
+            return Err(SanityError::Sender {
+                inner: format!(
+                    "sender {0:?} is not an account (no code or EIP-7702 delegation) and initCode is empty",
+                    uo.sender
+                ),
+            });
+        }
+
+        if !code.is_empty() && !uo.init_code.is_empty() && !eip7702_authorization {
             return Err(SanityError::Sender {
-                inner: format!("sender {0:?} is an existing contract, or the initCode {1} is not empty (but not both)", uo.sender, uo.init_code),
+                inner: format!(
+                    "sender {0:?} is an existing contract, but the initCode {1} is not empty",
+                    uo.sender, uo.init_code
+                ),
             });
         }
 
@@ -62,13 +86,10 @@ impl<M: Middleware> SanityCheck<M> for Sender {
         }
 
         if let Some(uo_prev) = uo_prev {
-            if uo.max_fee_per_gas <
-                calculate_valid_gas(uo_prev.max_fee_per_gas, GAS_INCREASE_PERC.into()) ||
+            let increase = self.replacement_fee_increase.into();
+            if uo.max_fee_per_gas < calculate_valid_gas(uo_prev.max_fee_per_gas, increase) ||
                 uo.max_priority_fee_per_gas <
-                    calculate_valid_gas(
-                        uo_prev.max_priority_fee_per_gas,
-                        GAS_INCREASE_PERC.into(),
-                    )
+                    calculate_valid_gas(uo_prev.max_priority_fee_per_gas, increase)
             {
                 return Err(SanityError::Sender {
                     inner: format!(
@@ -82,3 +103,109 @@ impl<M: Middleware> SanityCheck<M> for Sender {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_utils::{memory_mempool, memory_reputation},
+        OverheadConfig,
+    };
+    use ethers::{
+        providers::Provider,
+        types::{Address, Bytes, H256},
+    };
+    use silius_contracts::EntryPoint;
+    use silius_primitives::{
+        constants::{
+            mempool::GAS_INCREASE_PERC,
+            validation::{EIP7702_DELEGATION_PREFIX, EIP7702_INIT_CODE_MARKER},
+        },
+        UserOperationHash, UserOperationSigned,
+    };
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn eip7702_delegated_sender() {
+        let (provider, mock) = Provider::mocked();
+        let entry_point = EntryPoint::new(Arc::new(provider), Address::random());
+        let mempool = memory_mempool();
+        let reputation = memory_reputation();
+        let overhead = OverheadConfig::default();
+        let mut helper = SanityHelper::new_test(&entry_point, &overhead);
+        helper.eip7702_senders = true;
+        let sender = Sender { replacement_fee_increase: GAS_INCREASE_PERC };
+
+        // initCode carrying the authorization of the delegation
+        let uo = UserOperation::from_user_operation_signed(
+            UserOperationHash::default(),
+            UserOperationSigned {
+                sender: Address::random(),
+                init_code: [
+                    Address::from_low_u64_be(EIP7702_INIT_CODE_MARKER).as_bytes(),
+                    &[0x01; 32],
+                ]
+                .concat()
+                .into(),
+                ..Default::default()
+            },
+        );
+        let delegated_code: Bytes =
+            [EIP7702_DELEGATION_PREFIX.as_slice(), Address::random().as_bytes()].concat().into();
+
+        mock.push(delegated_code.clone()).unwrap();
+        assert!(sender.check_user_operation(&uo, &mempool, &reputation, &helper).await.is_ok());
+
+        // without the EIP-7702 mode, the sender is an existing contract with non-empty initCode
+        helper.eip7702_senders = false;
+        mock.push(delegated_code).unwrap();
+        assert!(matches!(
+            sender.check_user_operation(&uo, &mempool, &reputation, &helper).await,
+            Err(SanityError::Sender { .. })
+        ));
+
+        // senders with deployed code (not delegated) still can't have initCode
+        helper.eip7702_senders = true;
+        mock.push(Bytes::from(vec![0x60, 0x80])).unwrap();
+        assert!(matches!(
+            sender.check_user_operation(&uo, &mempool, &reputation, &helper).await,
+            Err(SanityError::Sender { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn replacement_fee_increase() {
+        let (provider, mock) = Provider::mocked();
+        let entry_point = EntryPoint::new(Arc::new(provider), Address::random());
+        let mut mempool = memory_mempool();
+        let reputation = memory_reputation();
+        let overhead = OverheadConfig::default();
+        let helper = SanityHelper::new_test(&entry_point, &overhead);
+
+        let new_uo = |sender: Address, fee: u64| {
+            UserOperation::from_user_operation_signed(
+                H256::random().into(),
+                UserOperationSigned::default()
+                    .sender(sender)
+                    .max_fee_per_gas(fee.into())
+                    .max_priority_fee_per_gas(fee.into()),
+            )
+        };
+        let uo_prev = new_uo(Address::random(), 100);
+        mempool.add(uo_prev.clone()).unwrap();
+        let uo = new_uo(uo_prev.sender, 115);
+        let code = Bytes::from(vec![0x60, 0x80]);
+
+        mock.push(code.clone()).unwrap();
+        let sender = Sender { replacement_fee_increase: GAS_INCREASE_PERC };
+        assert!(sender.check_user_operation(&uo, &mempool, &reputation, &helper).await.is_ok());
+
+        // the configured increase is required
+        mock.push(code).unwrap();
+        let sender = Sender { replacement_fee_increase: 20 };
+        assert!(matches!(
+            sender.check_user_operation(&uo, &mempool, &reputation, &helper).await,
+            Err(SanityError::Sender { .. })
+        ));
+    }
+}
@@ -0,0 +1,49 @@
+use crate::{
+    mempool::Mempool,
+    validate::{SanityCheck, SanityHelper},
+    Reputation, SanityError,
+};
+use ethers::{providers::Middleware, types::U256};
+use silius_primitives::UserOperation;
+
+#[derive(Clone)]
+pub struct SimulationGas {
+    /// Max gas of the validation simulation (no cap if None)
+    pub max_simulation_gas: Option<U256>,
+}
+
+#[async_trait::async_trait]
+impl<M: Middleware> SanityCheck<M> for SimulationGas {
+    /// The method implementation that checks that the gas required by the user operation
+    /// (pre-verification, verification and call gas) doesn't exceed the max simulation gas, so
+    /// expensive user operations are rejected before being simulated.
+    ///
+    /// # Arguments
+    /// `uo` - The user operation to check
+    ///
+    /// # Returns
+    /// None if the check passes, otherwise a [SanityError]
+    async fn check_user_operation(
+        &self,
+        uo: &UserOperation,
+        _mempool: &Mempool,
+        _reputation: &Reputation,
+        _helper: &SanityHelper<M>,
+    ) -> Result<(), SanityError> {
+        if let Some(max_simulation_gas) = self.max_simulation_gas {
+            let simulation_gas = uo
+                .pre_verification_gas
+                .saturating_add(uo.verification_gas_limit)
+                .saturating_add(uo.call_gas_limit);
+
+            if simulation_gas > max_simulation_gas {
+                return Err(SanityError::SimulationGasTooHigh {
+                    simulation_gas,
+                    max_simulation_gas,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -165,16 +165,13 @@ impl<M: Middleware> SanityCheck<M> for UnstakedEntities {
                 });
             }
 
+            // paymasters with a too low stake or unstake delay are treated as unstaked
             let paymaster_stake = self.get_stake(&paymaster, helper).await?;
-            if reputation
-                .verify_stake(
-                    PAYMASTER,
-                    Some(paymaster_stake),
-                    helper.val_config.min_stake,
-                    helper.val_config.min_unstake_delay,
-                )
-                .is_err()
-            {
+            if !reputation.is_staked(
+                &paymaster_stake,
+                helper.val_config.min_stake,
+                helper.val_config.min_unstake_delay,
+            ) {
                 // [UREP-020] - for other entities
                 let entity = self.get_entity(&paymaster, helper, reputation)?;
                 let uos_allowed = Self::calculate_allowed_user_operations(entity);
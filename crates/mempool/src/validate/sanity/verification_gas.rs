@@ -2,7 +2,7 @@ use crate::{
     mempool::Mempool,
     utils::div_ceil,
     validate::{SanityCheck, SanityHelper},
-    Overhead, Reputation, SanityError,
+    Reputation, SanityError,
 };
 use ethers::{providers::Middleware, types::U256};
 use silius_primitives::UserOperation;
@@ -29,7 +29,7 @@ impl<M: Middleware> SanityCheck<M> for VerificationGas {
         uo: &UserOperation,
         _mempool: &Mempool,
         _reputation: &Reputation,
-        _helper: &SanityHelper<M>,
+        helper: &SanityHelper<M>,
     ) -> Result<(), SanityError> {
         if uo.verification_gas_limit > self.max_verification_gas {
             return Err(SanityError::VerificationGasLimitTooHigh {
@@ -40,9 +40,18 @@ impl<M: Middleware> SanityCheck<M> for VerificationGas {
 
         // calculate the pvg and allow 10 % deviation
         let pre_gas = div_ceil(
-            Overhead::default().calculate_pre_verification_gas(uo).saturating_mul(U256::from(90)),
+            helper
+                .overhead
+                .overhead()
+                .calculate_pre_verification_gas(uo)
+                .saturating_mul(U256::from(90)),
             U256::from(100),
         );
+
+        // on L2s (e.g., OP Stack chains), the pvg also has to cover the L1 data posting cost
+        let pre_gas = pre_gas.saturating_add(
+            helper.overhead.calculate_l1_gas(helper.entry_point, uo, uo.max_fee_per_gas).await?,
+        );
         if uo.pre_verification_gas < pre_gas {
             return Err(SanityError::PreVerificationGasTooLow {
                 pre_verification_gas: uo.pre_verification_gas,
@@ -93,6 +93,10 @@ impl CallStack {
 
 #[async_trait::async_trait]
 impl<M: Middleware> SimulationTraceCheck<M> for CallStack {
+    fn name(&self) -> &'static str {
+        "CallStack"
+    }
+
     /// The method implementation that performs the call stack trace check.
     ///
     /// # Arguments
@@ -63,6 +63,10 @@ impl CodeHashes {
 
 #[async_trait::async_trait]
 impl<M: Middleware> SimulationTraceCheck<M> for CodeHashes {
+    fn name(&self) -> &'static str {
+        "CodeHashes"
+    }
+
     /// The method implementation that checks the code hashes.
     ///
     /// # Arguments
@@ -1,6 +1,6 @@
 use crate::{
     mempool::Mempool,
-    validate::{SimulationTraceCheck, SimulationTraceHelper},
+    validate::{utils::extract_stake_info, SimulationTraceCheck, SimulationTraceHelper},
     Reputation, SimulationError,
 };
 use ethers::providers::Middleware;
@@ -14,6 +14,10 @@ pub struct ExternalContracts;
 
 #[async_trait::async_trait]
 impl<M: Middleware> SimulationTraceCheck<M> for ExternalContracts {
+    fn name(&self) -> &'static str {
+        "ExternalContracts"
+    }
+
     async fn check_user_operation(
         &self,
         uo: &UserOperation,
@@ -21,6 +25,11 @@ impl<M: Middleware> SimulationTraceCheck<M> for ExternalContracts {
         _reputation: &Reputation,
         helper: &mut SimulationTraceHelper<M>,
     ) -> Result<(), SimulationError> {
+        if helper.stake_info.is_none() {
+            helper.stake_info = Some(extract_stake_info(uo, helper.simulate_validation_result));
+        }
+        let stake_info = helper.stake_info.unwrap_or_default();
+
         for call_info in helper.js_trace.calls_from_entry_point.iter() {
             let level = SELECTORS_INDICES.get(call_info.top_level_method_sig.as_ref()).cloned();
 
@@ -33,16 +42,21 @@ impl<M: Middleware> SimulationTraceCheck<M> for ExternalContracts {
                         && size.opcode != CREATE2_OPCODE.to_string()
                     {
                         return Err(SimulationError::Opcode {
+                            rule: "OP-041".into(),
                             entity: LEVEL_TO_ENTITY[l].into(),
+                            address: stake_info[l].address,
                             opcode: size.opcode.clone(),
                         });
                     }
                 }
 
+                // [OP-054] - EXTCODE* access to the entry point is forbidden
                 for (addr, info) in call_info.ext_code_access_info.iter() {
                     if *addr == helper.entry_point.address() {
                         return Err(SimulationError::Opcode {
+                            rule: "OP-054".into(),
                             entity: LEVEL_TO_ENTITY[l].into(),
+                            address: stake_info[l].address,
                             opcode: info.clone(),
                         });
                     }
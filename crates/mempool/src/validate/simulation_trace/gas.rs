@@ -11,6 +11,10 @@ pub struct Gas;
 
 #[async_trait::async_trait]
 impl<M: Middleware> SimulationTraceCheck<M> for Gas {
+    fn name(&self) -> &'static str {
+        "Gas"
+    }
+
     /// The method implementation that checks if the user operation runs out
     /// of gas
     ///
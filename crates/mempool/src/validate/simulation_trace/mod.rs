@@ -6,4 +6,5 @@ pub mod code_hashes;
 pub mod external_contracts;
 pub mod gas;
 pub mod opcodes;
+pub mod signature_hash;
 pub mod storage_access;
@@ -1,44 +1,79 @@
 use crate::{
-    validate::{SimulationTraceCheck, SimulationTraceHelper},
+    validate::{
+        utils::{extract_stake_info, is_trusted_factory_opcode},
+        SimulationTraceCheck, SimulationTraceHelper,
+    },
     Mempool, Reputation, SimulationError,
 };
 use ethers::providers::Middleware;
 use silius_contracts::entry_point::SELECTORS_INDICES;
 use silius_primitives::{
     constants::validation::entities::{FACTORY, LEVEL_TO_ENTITY},
-    simulation::{CREATE2_OPCODE, FORBIDDEN_OPCODES},
+    simulation::{CREATE2_OPCODE, FORBIDDEN_OPCODES, STRICT_FORBIDDEN_OPCODES},
     UserOperation,
 };
+use tracing::warn;
 
 #[derive(Clone)]
 pub struct Opcodes;
 
 #[async_trait::async_trait]
 impl<M: Middleware> SimulationTraceCheck<M> for Opcodes {
-    /// The method implementation that checks the use of forbidden opcodes
+    fn name(&self) -> &'static str {
+        "Opcodes"
+    }
+
+    /// The method implementation that checks the use of forbidden opcodes (in the strict mode,
+    /// also the opcodes forbidden only by the latest ERC-7562 rules). The opcodes allowed for a
+    /// trusted factory are only accepted in the frames of that factory (not in the contracts it
+    /// calls).
     ///
     /// # Arguments
-    /// `_uo` - Not used
+    /// `uo` - The [UserOperation](UserOperation) to check
     /// `helper` - The [SimulationTraceHelper]
     ///
     /// # Returns
     /// None if the check passes, otherwise a [SimulationError] error.
     async fn check_user_operation(
         &self,
-        _uo: &UserOperation,
+        uo: &UserOperation,
         _mempool: &Mempool,
         _reputation: &Reputation,
         helper: &mut SimulationTraceHelper<M>,
     ) -> Result<(), SimulationError> {
+        if helper.stake_info.is_none() {
+            helper.stake_info = Some(extract_stake_info(uo, helper.simulate_validation_result));
+        }
+        let stake_info = helper.stake_info.unwrap_or_default();
+
         for call_info in helper.js_trace.calls_from_entry_point.iter() {
             let level = SELECTORS_INDICES.get(call_info.top_level_method_sig.as_ref()).cloned();
 
             if let Some(l) = level {
                 // [OP-011] - block opcodes
                 for op in call_info.opcodes.keys() {
-                    if FORBIDDEN_OPCODES.contains(op) {
+                    if FORBIDDEN_OPCODES.contains(op) ||
+                        (helper.strict_opcodes && STRICT_FORBIDDEN_OPCODES.contains(op))
+                    {
+                        if LEVEL_TO_ENTITY[l] == FACTORY &&
+                            is_trusted_factory_opcode(
+                                call_info,
+                                &stake_info[l].address,
+                                op,
+                                helper.trusted_factory_opcodes,
+                            )
+                        {
+                            warn!(
+                                "Trusted factory {:?} of user operation {:?} uses banned opcode: {op}",
+                                stake_info[l].address, uo.hash
+                            );
+                            continue;
+                        }
+
                         return Err(SimulationError::Opcode {
+                            rule: "OP-011".into(),
                             entity: LEVEL_TO_ENTITY[l].to_string(),
+                            address: stake_info[l].address,
                             opcode: op.clone(),
                         });
                     }
@@ -51,7 +86,9 @@ impl<M: Middleware> SimulationTraceCheck<M> for Opcodes {
                         continue;
                     }
                     return Err(SimulationError::Opcode {
+                        rule: "OP-031".into(),
                         entity: LEVEL_TO_ENTITY[l].to_string(),
+                        address: stake_info[l].address,
                         opcode: CREATE2_OPCODE.to_string(),
                     });
                 }
@@ -61,3 +98,185 @@ impl<M: Middleware> SimulationTraceCheck<M> for Opcodes {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{entity_selector, memory_mempool, memory_reputation};
+    use ethers::{
+        providers::Provider,
+        types::{Address, H256},
+    };
+    use silius_contracts::{
+        entry_point::SimulateValidationResult,
+        tracer::{JsTracerFrame, TopLevelCallInfo},
+        EntryPoint,
+    };
+    use silius_primitives::{constants::validation::entities::SENDER, UserOperationSigned};
+    use std::{
+        collections::{HashMap, HashSet},
+        sync::Arc,
+    };
+
+    #[tokio::test]
+    async fn banned_opcode_context() {
+        let (provider, _) = Provider::mocked();
+        let entry_point = EntryPoint::new(Arc::new(provider), Address::random());
+        let mempool = memory_mempool();
+        let reputation = memory_reputation();
+
+        let sender = Address::random();
+        let uo = UserOperation::from_user_operation_signed(
+            H256::random().into(),
+            UserOperationSigned::default().sender(sender),
+        );
+        let sim_res = SimulateValidationResult::ValidationResult(Default::default());
+
+        // GAS opcode used in the account validation
+        let js_trace = JsTracerFrame {
+            calls_from_entry_point: vec![TopLevelCallInfo {
+                top_level_method_sig: entity_selector(SENDER),
+                opcodes: HashMap::from([("GAS".to_string(), 1)]),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let trusted_factory_opcodes = HashMap::new();
+        let mut helper = SimulationTraceHelper::new_test(
+            &entry_point,
+            &sim_res,
+            &js_trace,
+            &trusted_factory_opcodes,
+        );
+
+        let err = Opcodes
+            .check_user_operation(&uo, &mempool, &reputation, &mut helper)
+            .await
+            .expect_err("GAS opcode is banned");
+        assert!(matches!(
+            &err,
+            SimulationError::Opcode { rule, entity, address, opcode }
+                if rule == "OP-011" && entity == SENDER && *address == sender && opcode == "GAS"
+        ));
+        assert_eq!(
+            err.to_string(),
+            format!("[OP-011] account at {sender:?} uses banned opcode: GAS")
+        );
+    }
+
+    #[tokio::test]
+    async fn strict_opcodes() {
+        let (provider, _) = Provider::mocked();
+        let entry_point = EntryPoint::new(Arc::new(provider), Address::random());
+        let mempool = memory_mempool();
+        let reputation = memory_reputation();
+
+        let sender = Address::random();
+        let uo = UserOperation::from_user_operation_signed(
+            H256::random().into(),
+            UserOperationSigned::default().sender(sender),
+        );
+        let sim_res = SimulateValidationResult::ValidationResult(Default::default());
+        let account_selector = entity_selector(SENDER);
+        let trusted_factory_opcodes = HashMap::new();
+
+        for opcode in ["BLOBHASH", "BLOBBASEFEE", "INVALID"] {
+            let js_trace = JsTracerFrame {
+                calls_from_entry_point: vec![TopLevelCallInfo {
+                    top_level_method_sig: account_selector.clone(),
+                    opcodes: HashMap::from([(opcode.to_string(), 1)]),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            };
+            let mut helper = SimulationTraceHelper::new_test(
+                &entry_point,
+                &sim_res,
+                &js_trace,
+                &trusted_factory_opcodes,
+            );
+
+            // allowed by the canonical rules
+            assert!(Opcodes
+                .check_user_operation(&uo, &mempool, &reputation, &mut helper)
+                .await
+                .is_ok());
+
+            // rejected in the strict mode
+            helper.strict_opcodes = true;
+            let err = Opcodes
+                .check_user_operation(&uo, &mempool, &reputation, &mut helper)
+                .await
+                .expect_err("opcode is banned in the strict mode");
+            assert_eq!(
+                err.to_string(),
+                format!("[OP-011] account at {sender:?} uses banned opcode: {opcode}")
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn trusted_factory_opcodes() {
+        let (provider, _) = Provider::mocked();
+        let entry_point = EntryPoint::new(Arc::new(provider), Address::random());
+        let mempool = memory_mempool();
+        let reputation = memory_reputation();
+
+        let trusted_factory = Address::random();
+        let other_factory = Address::random();
+        let trusted_factory_opcodes =
+            HashMap::from([(trusted_factory, HashSet::from(["GAS".to_string()]))]);
+        let sim_res = SimulateValidationResult::ValidationResult(Default::default());
+        let user_operation = |factory: Address| {
+            UserOperation::from_user_operation_signed(
+                H256::random().into(),
+                UserOperationSigned::default()
+                    .sender(Address::random())
+                    .init_code([factory.as_bytes(), &[1, 2, 3][..]].concat().into()),
+            )
+        };
+        // GAS opcode used in the level of the given entity by the given contract
+        let trace = |entity: &str, contract: Address| JsTracerFrame {
+            calls_from_entry_point: vec![TopLevelCallInfo {
+                top_level_method_sig: entity_selector(entity),
+                opcodes: HashMap::from([("GAS".to_string(), 1)]),
+                contract_opcodes: HashMap::from([(
+                    contract,
+                    HashMap::from([("GAS".to_string(), 1)]),
+                )]),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        for (factory, entity, contract, allowed) in [
+            (trusted_factory, FACTORY, trusted_factory, true),
+            (other_factory, FACTORY, other_factory, false),
+            // only allowed in the level of the trusted factory
+            (trusted_factory, SENDER, trusted_factory, false),
+            // only allowed in the frames of the trusted factory, not of the contracts it calls
+            (trusted_factory, FACTORY, Address::random(), false),
+        ] {
+            let uo = user_operation(factory);
+            let js_trace = trace(entity, contract);
+            let mut helper = SimulationTraceHelper::new_test(
+                &entry_point,
+                &sim_res,
+                &js_trace,
+                &trusted_factory_opcodes,
+            );
+
+            let res = Opcodes.check_user_operation(&uo, &mempool, &reputation, &mut helper).await;
+            if allowed {
+                assert!(res.is_ok());
+            } else {
+                assert!(matches!(
+                    res,
+                    Err(SimulationError::Opcode { rule, entity: e, opcode, .. })
+                        if rule == "OP-011" && e == entity && opcode == "GAS"
+                ));
+            }
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use crate::{
+    mempool::Mempool,
+    validate::{SimulationTraceCheck, SimulationTraceHelper},
+    Reputation, SimulationError,
+};
+use ethers::{contract::EthCall, providers::Middleware, types::H256};
+use silius_contracts::entry_point::{ValidateUserOpCall, ValidateUserOpV07Call};
+use silius_primitives::UserOperation;
+
+#[derive(Clone)]
+pub struct SignatureHash;
+
+#[async_trait::async_trait]
+impl<M: Middleware> SimulationTraceCheck<M> for SignatureHash {
+    fn name(&self) -> &'static str {
+        "SignatureHash"
+    }
+
+    /// The method implementation that checks (if enabled) that the account's `validateUserOp` was
+    /// called with the user operation hash derived by the bundler, so the signature covers the
+    /// full user operation.
+    ///
+    /// # Arguments
+    /// `uo` - The user operation to check
+    /// `helper` - The [SimulationTraceHelper](crate::validate::SimulationTraceHelper)
+    ///
+    /// # Returns
+    /// None if the check passes, otherwise a [SimulationError] error.
+    async fn check_user_operation(
+        &self,
+        uo: &UserOperation,
+        _mempool: &Mempool,
+        _reputation: &Reputation,
+        helper: &mut SimulationTraceHelper<M>,
+    ) -> Result<(), SimulationError> {
+        if !helper.verify_signature_hash {
+            return Ok(());
+        }
+
+        let selectors = [ValidateUserOpCall::selector(), ValidateUserOpV07Call::selector()];
+        let mut validated = false;
+
+        for call in helper.js_trace.calls.iter() {
+            if call.to != Some(uo.sender) ||
+                !call
+                    .method
+                    .as_ref()
+                    .is_some_and(|m| selectors.iter().any(|s| s.as_slice() == m.as_ref()))
+            {
+                continue;
+            }
+
+            // validateUserOp(UserOperation userOp, bytes32 userOpHash, uint256 missingAccountFunds)
+            // (the packed user operation for the entry point v0.7)
+            let uo_hash = call
+                .data
+                .as_ref()
+                .filter(|data| data.len() >= 68)
+                .map(|data| H256::from_slice(&data[36..68]));
+            if uo_hash != Some(uo.hash.0) {
+                return Err(SimulationError::Signature);
+            }
+
+            validated = true;
+        }
+
+        if !validated {
+            return Err(SimulationError::Signature);
+        }
+
+        Ok(())
+    }
+}
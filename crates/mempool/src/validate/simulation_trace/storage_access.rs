@@ -75,8 +75,9 @@ impl StorageAccess {
             return Ok(false);
         }
 
-        let slot_num = U256::from_str_radix(slot, 16)
-            .map_err(|_| SimulationError::StorageAccess { slot: slot.clone() })?;
+        let slot_num = U256::from_str_radix(slot, 16).map_err(|_| SimulationError::Validation {
+            inner: format!("invalid storage slot {slot}"),
+        })?;
 
         if let Some(slots) = slots.get(addr) {
             for slot in slots {
@@ -94,6 +95,10 @@ impl StorageAccess {
 
 #[async_trait::async_trait]
 impl<M: Middleware> SimulationTraceCheck<M> for StorageAccess {
+    fn name(&self) -> &'static str {
+        "StorageAccess"
+    }
+
     /// The method implementation that checks if the user operation access
     /// storage other than the one associated with itself.
     ///
@@ -163,7 +168,12 @@ impl<M: Middleware> SimulationTraceCheck<M> for StorageAccess {
                         {
                             slot_staked.clone_from(&slot);
                         } else {
-                            return Err(SimulationError::StorageAccess { slot });
+                            return Err(SimulationError::StorageAccess {
+                                rule: "STO-033".into(),
+                                entity: LEVEL_TO_ENTITY[l].into(),
+                                address: *addr,
+                                slot,
+                            });
                         }
                     }
 
@@ -1,9 +1,48 @@
-use ethers::types::{Address, U256};
-use silius_contracts::{entry_point::SimulateValidationResult, tracer::JsTracerFrame};
+use super::BannedOpcode;
+use ethers::types::{Address, Bytes, U256};
+use silius_contracts::{
+    entry_point::{SimulateValidationResult, SELECTORS_INDICES},
+    tracer::{JsTracerFrame, TopLevelCallInfo},
+};
 use silius_primitives::{
-    constants::validation::entities::NUMBER_OF_LEVELS, get_address, reputation::StakeInfo,
-    simulation::StorageMap, UserOperation,
+    constants::validation::{
+        entities::{FACTORY, LEVEL_TO_ENTITY, NUMBER_OF_LEVELS},
+        EIP7702_DELEGATION_PREFIX, EIP7702_INIT_CODE_MARKER,
+    },
+    get_address,
+    reputation::StakeInfo,
+    simulation::{StorageMap, CREATE2_OPCODE, FORBIDDEN_OPCODES},
+    UserOperation,
 };
+use std::collections::{HashMap, HashSet};
+
+/// Helper function to extract the delegate of an EOA from its code (EIP-7702)
+///
+/// # Arguments
+/// `code` - The code of the account
+///
+/// # Returns
+/// The address of the delegate if the code is an EIP-7702 delegation designator
+pub fn extract_eip7702_delegate(code: &Bytes) -> Option<Address> {
+    match code.strip_prefix(EIP7702_DELEGATION_PREFIX.as_slice()) {
+        Some(address) if address.len() == Address::len_bytes() => {
+            Some(Address::from_slice(address))
+        }
+        _ => None,
+    }
+}
+
+/// Helper function to check whether the initCode carries an EIP-7702 authorization (instead of
+/// deploying the sender)
+///
+/// # Arguments
+/// `init_code` - The initCode of the user operation
+///
+/// # Returns
+/// True if the factory of the initCode is the EIP-7702 marker
+pub fn is_eip7702_init_code(init_code: &Bytes) -> bool {
+    get_address(init_code) == Some(Address::from_low_u64_be(EIP7702_INIT_CODE_MARKER))
+}
 
 /// Helper function to extract the gas limit for verification from the simulation result
 ///
@@ -90,6 +129,96 @@ pub fn extract_stake_info(
     ]
 }
 
+/// Helper function to extract the stake info of the aggregator from the simulation result
+///
+/// # Arguments
+/// `sim_res` - The [simulation result](SimulateValidationResult) from the simulation
+///
+/// # Returns
+/// The stake info for the aggregator (None if the user operation isn't aggregated)
+pub fn extract_aggregator_stake_info(sim_res: &SimulateValidationResult) -> Option<StakeInfo> {
+    match sim_res {
+        SimulateValidationResult::ValidationResult(_) => None,
+        SimulateValidationResult::ValidationResultWithAggregation(res) => Some(StakeInfo {
+            address: res.aggregator_info.0,
+            stake: res.aggregator_info.1 .0,
+            unstake_delay: res.aggregator_info.1 .1,
+        }),
+    }
+}
+
+/// Helper function to extract the banned opcodes used by the entities from the simulation result
+/// (CREATE2 is only allowed once in the factory)
+///
+/// # Arguments
+/// `js_trace` - The [js tracer frame](JsTracerFrame) to extract the banned opcodes from
+/// `factory` - The address of the factory of the user operation
+/// `trusted_factory_opcodes` - The allowed opcodes by factory address
+///
+/// # Returns
+/// The [banned opcodes](BannedOpcode) sorted by entity and opcode
+pub fn extract_banned_opcodes(
+    js_trace: &JsTracerFrame,
+    factory: &Address,
+    trusted_factory_opcodes: &HashMap<Address, HashSet<String>>,
+) -> Vec<BannedOpcode> {
+    let mut banned_opcodes = vec![];
+
+    for call_info in js_trace.calls_from_entry_point.iter() {
+        if let Some(l) = SELECTORS_INDICES.get(call_info.top_level_method_sig.as_ref()).cloned() {
+            for (opcode, count) in call_info.opcodes.iter() {
+                if LEVEL_TO_ENTITY[l] == FACTORY &&
+                    is_trusted_factory_opcode(
+                        call_info,
+                        factory,
+                        opcode,
+                        trusted_factory_opcodes,
+                    )
+                {
+                    continue;
+                }
+
+                if FORBIDDEN_OPCODES.contains(opcode) ||
+                    (*opcode == *CREATE2_OPCODE &&
+                        !(LEVEL_TO_ENTITY[l] == FACTORY && *count == 1))
+                {
+                    banned_opcodes.push(BannedOpcode {
+                        entity: LEVEL_TO_ENTITY[l].into(),
+                        opcode: opcode.clone(),
+                        count: *count,
+                    });
+                }
+            }
+        }
+    }
+
+    banned_opcodes.sort_by(|a, b| (&a.entity, &a.opcode).cmp(&(&b.entity, &b.opcode)));
+    banned_opcodes
+}
+
+/// Helper function to check whether an opcode used in the factory level of the validation is
+/// allowed for the factory, i.e., the factory is trusted with the opcode and all of its uses come
+/// from the frames of the factory itself (not from the contracts it calls)
+///
+/// # Arguments
+/// `call_info` - The [top level call info](TopLevelCallInfo) of the factory level
+/// `factory` - The address of the factory
+/// `opcode` - The opcode to check
+/// `trusted_factory_opcodes` - The allowed opcodes by factory address
+///
+/// # Returns
+/// true if the opcode is allowed for the factory, otherwise false
+pub fn is_trusted_factory_opcode(
+    call_info: &TopLevelCallInfo,
+    factory: &Address,
+    opcode: &str,
+    trusted_factory_opcodes: &HashMap<Address, HashSet<String>>,
+) -> bool {
+    trusted_factory_opcodes.get(factory).is_some_and(|ops| ops.contains(opcode)) &&
+        call_info.contract_opcodes.get(factory).and_then(|ops| ops.get(opcode)) ==
+            call_info.opcodes.get(opcode)
+}
+
 /// Helper function to extract the storage map from the simulation result
 ///
 /// # Arguments
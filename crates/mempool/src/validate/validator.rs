@@ -1,47 +1,90 @@
 use super::{
     sanity::{
-        call_gas::CallGas, entities::Entities, max_fee::MaxFee, paymaster::Paymaster,
-        sender::Sender, unstaked_entities::UnstakedEntities, verification_gas::VerificationGas,
+        block_freshness::BlockFreshness,
+        call_data::{CallData, SelectorFilter},
+        call_gas::CallGas,
+        entities::Entities,
+        max_fee::MaxFee,
+        paymaster::Paymaster,
+        prefund::Prefund,
+        priority_fee_cap::PriorityFeeCap,
+        sender::Sender,
+        simulation_gas::SimulationGas,
+        unstaked_entities::UnstakedEntities,
+        verification_gas::VerificationGas,
     },
     simulation::{
         signature::Signature, timestamp::Timestamp, verification_extra_gas::VerificationExtraGas,
     },
     simulation_trace::{
         call_stack::CallStack, code_hashes::CodeHashes, external_contracts::ExternalContracts,
-        gas::Gas, opcodes::Opcodes, storage_access::StorageAccess,
+        gas::Gas, opcodes::Opcodes, signature_hash::SignatureHash, storage_access::StorageAccess,
     },
-    utils::{extract_pre_fund, extract_storage_map, extract_verification_gas_limit},
-    SanityCheck, SanityHelper, SimulationCheck, SimulationHelper, SimulationTraceCheck,
-    SimulationTraceHelper, UserOperationValidationOutcome, UserOperationValidator,
-    UserOperationValidatorMode,
+    utils::{
+        extract_aggregator_stake_info, extract_banned_opcodes, extract_pre_fund,
+        extract_storage_map, extract_verification_gas_limit,
+    },
+    FailedTraceCheck, SanityCheck, SanityHelper, SimulationCheck, SimulationHelper,
+    SimulationTraceCheck, SimulationTraceHelper, TraceViolation, UserOperationValidationOutcome,
+    UserOperationValidator, UserOperationValidatorMode, ValidationTrace,
 };
 use crate::{
-    mempool::Mempool, InvalidMempoolUserOperationError, Reputation, SanityError, SimulationError,
+    mempool::Mempool,
+    metrics::{record_validation_phase, ValidationPhase},
+    InvalidMempoolUserOperationError, OverheadConfig, Reputation, SanityError, SimulationError,
 };
 use alloy_chains::Chain;
 use enumset::EnumSet;
 use ethers::{
     providers::Middleware,
-    types::{BlockNumber, GethTrace, U256},
+    types::{Address, BlockNumber, Bytes, GethTrace, U256},
 };
 use silius_contracts::{
     entry_point::{EntryPointError, SimulateValidationResult},
     tracer::JsTracerFrame,
     EntryPoint,
 };
-use silius_primitives::{simulation::ValidationConfig, UserOperation};
+use silius_primitives::{
+    constants::{
+        mempool::GAS_INCREASE_PERC,
+        validation::{entities::AGGREGATOR, MIN_CALL_GAS_LIMIT},
+    },
+    get_address,
+    simulation::ValidationConfig,
+    PrefundPolicy, UserOperation,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    time::Instant,
+};
 use tracing::debug;
 
+/// The [SanityChecks](SanityCheck) of the canonical mempool.
+pub type CanonicalSanityChecks = (
+    BlockFreshness,
+    CallData,
+    Sender,
+    VerificationGas,
+    SimulationGas,
+    CallGas,
+    PriorityFeeCap,
+    MaxFee,
+    Paymaster,
+    Prefund,
+    Entities,
+    UnstakedEntities,
+);
+
 pub type StandardValidator<M> = StandardUserOperationValidator<
     M,
-    (Sender, VerificationGas, CallGas, MaxFee, Paymaster, Entities, UnstakedEntities),
+    CanonicalSanityChecks,
     (Signature, Timestamp, VerificationExtraGas),
-    (Gas, Opcodes, ExternalContracts, StorageAccess, CallStack, CodeHashes),
+    (Gas, Opcodes, ExternalContracts, StorageAccess, CallStack, CodeHashes, SignatureHash),
 >;
 
 type UnsafeValidator<M> = StandardUserOperationValidator<
     M,
-    (Sender, VerificationGas, CallGas, MaxFee, Paymaster, Entities, UnstakedEntities),
+    CanonicalSanityChecks,
     (Signature, Timestamp, VerificationExtraGas),
     (),
 >;
@@ -63,6 +106,25 @@ where
     simulation_checks: SimCk,
     /// An array of [SimulationTraceChecks](SimulationTraceCheck).
     simulation_trace_checks: SimTrCk,
+    /// [TraceViolations](TraceViolation) which are downgraded to warnings.
+    downgraded_violations: EnumSet<TraceViolation>,
+    /// Whether to verify that `validateUserOp` was called with the user operation hash.
+    verify_signature_hash: bool,
+    /// Whether the opcodes forbidden only by the latest ERC-7562 rules are rejected as well.
+    strict_opcodes: bool,
+    /// Opcodes (otherwise banned) allowed in the frames of trusted factories.
+    trusted_factory_opcodes: HashMap<Address, HashSet<String>>,
+    /// Max number of seconds after the latest block within which user operations are accepted.
+    acceptance_window: Option<u64>,
+    /// Whether the bundler is subsidized (accepts priority fees below the minimum).
+    subsidized: bool,
+    /// Whether aggregators have to be either staked or whitelisted.
+    require_staked_aggregator: bool,
+    /// Whether EOAs delegated to a contract (EIP-7702) are accepted as deployed senders even if
+    /// the initCode carries the authorization.
+    eip7702_senders: bool,
+    /// Configuration of the pre-verification gas calculation.
+    overhead: OverheadConfig<M>,
 }
 
 impl<M: Middleware + Clone + 'static, SanCk, SimCk, SimTrCk> Clone
@@ -79,6 +141,15 @@ where
             sanity_checks: self.sanity_checks.clone(),
             simulation_checks: self.simulation_checks.clone(),
             simulation_trace_checks: self.simulation_trace_checks.clone(),
+            downgraded_violations: self.downgraded_violations,
+            verify_signature_hash: self.verify_signature_hash,
+            strict_opcodes: self.strict_opcodes,
+            trusted_factory_opcodes: self.trusted_factory_opcodes.clone(),
+            acceptance_window: self.acceptance_window,
+            subsidized: self.subsidized,
+            require_staked_aggregator: self.require_staked_aggregator,
+            eip7702_senders: self.eip7702_senders,
+            overhead: self.overhead.clone(),
         }
     }
 }
@@ -107,16 +178,21 @@ pub fn new_canonical<M: Middleware + 'static>(
         entry_point,
         chain,
         (
-            Sender,
+            BlockFreshness,
+            CallData { selector_filter: SelectorFilter::default() },
+            Sender { replacement_fee_increase: GAS_INCREASE_PERC },
             VerificationGas { max_verification_gas },
-            CallGas,
-            MaxFee { min_priority_fee_per_gas },
-            Paymaster,
+            SimulationGas { max_simulation_gas: None },
+            CallGas { min_call_gas: U256::from(MIN_CALL_GAS_LIMIT) },
+            PriorityFeeCap { enabled: true },
+            MaxFee { min_priority_fee_per_gas, ep_min_priority_fees: HashMap::new() },
+            Paymaster { require_staked_paymaster: false },
+            Prefund { policy: PrefundPolicy::Disabled },
             Entities,
             UnstakedEntities,
         ),
         (Signature, Timestamp, VerificationExtraGas),
-        (Gas, Opcodes, ExternalContracts, StorageAccess, CallStack, CodeHashes),
+        (Gas, Opcodes, ExternalContracts, StorageAccess, CallStack, CodeHashes, SignatureHash),
     )
 }
 
@@ -130,11 +206,16 @@ pub fn new_canonical_unsafe<M: Middleware + Clone + 'static>(
         entry_point.clone(),
         chain,
         (
-            Sender,
+            BlockFreshness,
+            CallData { selector_filter: SelectorFilter::default() },
+            Sender { replacement_fee_increase: GAS_INCREASE_PERC },
             VerificationGas { max_verification_gas },
-            CallGas,
-            MaxFee { min_priority_fee_per_gas },
-            Paymaster,
+            SimulationGas { max_simulation_gas: None },
+            CallGas { min_call_gas: U256::from(MIN_CALL_GAS_LIMIT) },
+            PriorityFeeCap { enabled: true },
+            MaxFee { min_priority_fee_per_gas, ep_min_priority_fees: HashMap::new() },
+            Paymaster { require_staked_paymaster: false },
+            Prefund { policy: PrefundPolicy::Disabled },
             Entities,
             UnstakedEntities,
         ),
@@ -143,6 +224,162 @@ pub fn new_canonical_unsafe<M: Middleware + Clone + 'static>(
     )
 }
 
+impl<M: Middleware + 'static, SimCk, SimTrCk>
+    StandardUserOperationValidator<M, CanonicalSanityChecks, SimCk, SimTrCk>
+where
+    SimCk: SimulationCheck,
+    SimTrCk: SimulationTraceCheck<M>,
+{
+    /// Sets whether user operations whose max priority fee per gas exceeds the max fee per gas
+    /// (invalid per [EIP-1559](https://eips.ethereum.org/EIPS/eip-1559)) are rejected by the
+    /// [PriorityFeeCap] sanity check (enabled by default).
+    ///
+    /// # Arguments
+    /// `enabled` - Whether the [PriorityFeeCap] sanity check is enabled.
+    ///
+    /// # Returns
+    /// The [StandardUserOperationValidator] with the priority fee cap set.
+    pub fn with_priority_fee_cap(mut self, enabled: bool) -> Self {
+        self.sanity_checks.6 = PriorityFeeCap { enabled };
+        self
+    }
+
+    /// Sets the function selectors which user operations are allowed (or not allowed) to call,
+    /// checked by the [CallData] sanity check. The inner calls of the account's
+    /// `execute`/`executeBatch` are inspected.
+    ///
+    /// # Arguments
+    /// `selector_filter` - The [SelectorFilter] (an empty filter accepts all selectors).
+    ///
+    /// # Returns
+    /// The [StandardUserOperationValidator] with the selector filter set.
+    pub fn with_selector_filter(mut self, selector_filter: SelectorFilter) -> Self {
+        self.sanity_checks.1 = CallData { selector_filter };
+        self
+    }
+
+    /// Requires paymasters to be either staked or whitelisted (stricter than the reputation
+    /// throttling of unstaked paymasters), checked by the [Paymaster] sanity check.
+    ///
+    /// # Arguments
+    /// `require_staked_paymaster` - Whether unstaked paymasters which aren't whitelisted are
+    /// rejected.
+    ///
+    /// # Returns
+    /// The [StandardUserOperationValidator] with the paymaster policy set.
+    pub fn with_staked_paymaster_required(mut self, require_staked_paymaster: bool) -> Self {
+        self.sanity_checks.8 = Paymaster { require_staked_paymaster };
+        self
+    }
+
+    /// Sets the funds of the sender required to cover the prefund of user operations without a
+    /// paymaster, checked by the [Prefund] sanity check before the simulation.
+    ///
+    /// # Arguments
+    /// `policy` - The [PrefundPolicy] (not checked by default).
+    ///
+    /// # Returns
+    /// The [StandardUserOperationValidator] with the prefund policy set.
+    pub fn with_prefund_policy(mut self, policy: PrefundPolicy) -> Self {
+        self.sanity_checks.9 = Prefund { policy };
+        self
+    }
+
+    /// Sets the min increase (percentage) of the fees required to replace a user operation with
+    /// the same sender and nonce, checked by the [Sender] sanity check. It should match the one
+    /// of the mempool (see
+    /// [UoPool::with_replacement_fee_increase](crate::UoPool::with_replacement_fee_increase)).
+    ///
+    /// # Arguments
+    /// `replacement_fee_increase` - The min fee increase (percentage).
+    ///
+    /// # Returns
+    /// The [StandardUserOperationValidator] with the replacement fee increase set.
+    pub fn with_replacement_fee_increase(mut self, replacement_fee_increase: u64) -> Self {
+        self.sanity_checks.2 = Sender { replacement_fee_increase };
+        self
+    }
+
+    /// Sets the min call gas limit: user operations with a lower call gas limit are rejected by
+    /// the [CallGas] sanity check, as their execution would revert.
+    ///
+    /// # Arguments
+    /// `min_call_gas` - The min call gas limit.
+    ///
+    /// # Returns
+    /// The [StandardUserOperationValidator] with the min call gas limit set.
+    pub fn with_min_call_gas(mut self, min_call_gas: U256) -> Self {
+        self.sanity_checks.5 = CallGas { min_call_gas };
+        self
+    }
+
+    /// Sets the max gas of the validation simulation: the simulation calls are capped at this gas
+    /// and user operations whose gas fields add up to more are rejected by the [SimulationGas]
+    /// sanity check before being simulated (bounds the cost of simulating expensive user
+    /// operations).
+    ///
+    /// # Arguments
+    /// `max_simulation_gas` - The max simulation gas (`None` disables the cap).
+    ///
+    /// # Returns
+    /// The [StandardUserOperationValidator] with the max simulation gas set.
+    pub fn with_max_simulation_gas(mut self, max_simulation_gas: Option<U256>) -> Self {
+        self.sanity_checks.4 = SimulationGas { max_simulation_gas };
+        self.entry_point = self.entry_point.with_simulation_gas(max_simulation_gas);
+        self
+    }
+
+    /// Sets the deployed code of `EntryPointSimulations`, which the validations of user operations
+    /// of the entry point v0.7 are simulated with.
+    ///
+    /// # Arguments
+    /// `simulations_code` - The code of `EntryPointSimulations` (v0.7 user operations can't be
+    /// simulated if not set).
+    ///
+    /// # Returns
+    /// The [StandardUserOperationValidator] with the code of the entry point simulations set.
+    pub fn with_entry_point_simulations_code(mut self, simulations_code: Option<Bytes>) -> Self {
+        self.entry_point = self.entry_point.with_simulations_code(simulations_code);
+        self
+    }
+
+    /// Sets the entry point the user operations are simulated against (in its version), keeping
+    /// the simulation settings of the validator.
+    ///
+    /// # Arguments
+    /// `entry_point` - The [EntryPoint] of the mempool.
+    ///
+    /// # Returns
+    /// The [StandardUserOperationValidator] of the entry point.
+    pub fn with_entry_point(mut self, entry_point: EntryPoint<M>) -> Self {
+        self.entry_point = entry_point
+            .with_simulation_gas(self.entry_point.simulation_gas())
+            .with_simulations_code(self.entry_point.simulations_code());
+        self
+    }
+
+    /// Returns the [EntryPoint] the user operations are simulated against.
+    pub fn entry_point(&self) -> &EntryPoint<M> {
+        &self.entry_point
+    }
+
+    /// Sets the min priority fees per gas of specific entry points (mempools), overriding the
+    /// default one of the [MaxFee] sanity check.
+    ///
+    /// # Arguments
+    /// `ep_min_priority_fees` - The min priority fee per gas of each entry point.
+    ///
+    /// # Returns
+    /// The [StandardUserOperationValidator] with the min priority fees per gas set.
+    pub fn with_ep_min_priority_fees(
+        mut self,
+        ep_min_priority_fees: HashMap<Address, U256>,
+    ) -> Self {
+        self.sanity_checks.7.ep_min_priority_fees = ep_min_priority_fees;
+        self
+    }
+}
+
 impl<M: Middleware + 'static, SanCk, SimCk, SimTrCk>
     StandardUserOperationValidator<M, SanCk, SimCk, SimTrCk>
 where
@@ -157,7 +394,222 @@ where
         simulation_checks: SimCk,
         simulation_trace_checks: SimTrCk,
     ) -> Self {
-        Self { entry_point, chain, sanity_checks, simulation_checks, simulation_trace_checks }
+        Self {
+            entry_point,
+            chain,
+            sanity_checks,
+            simulation_checks,
+            simulation_trace_checks,
+            downgraded_violations: EnumSet::empty(),
+            verify_signature_hash: false,
+            strict_opcodes: false,
+            trusted_factory_opcodes: HashMap::new(),
+            acceptance_window: None,
+            subsidized: false,
+            require_staked_aggregator: false,
+            eip7702_senders: false,
+            overhead: OverheadConfig::for_chain(&chain),
+        }
+    }
+
+    /// Downgrades the given [TraceViolations](TraceViolation) to warnings, so user operations
+    /// violating only these rules are still accepted (and bundled).
+    ///
+    /// # Arguments
+    /// `downgraded_violations` - The [TraceViolations](TraceViolation) to downgrade.
+    ///
+    /// # Returns
+    /// The [StandardUserOperationValidator] with downgraded violations.
+    pub fn with_downgraded_violations(
+        mut self,
+        downgraded_violations: EnumSet<TraceViolation>,
+    ) -> Self {
+        self.downgraded_violations = downgraded_violations;
+        self
+    }
+
+    /// Enables verification that the account's `validateUserOp` was called with the user
+    /// operation hash derived by the bundler (guards against accounts accepting signatures over
+    /// a partial hash).
+    ///
+    /// # Arguments
+    /// `verify_signature_hash` - Whether to verify the user operation hash.
+    ///
+    /// # Returns
+    /// The [StandardUserOperationValidator] with signature hash verification set.
+    pub fn with_signature_hash_verification(mut self, verify_signature_hash: bool) -> Self {
+        self.verify_signature_hash = verify_signature_hash;
+        self
+    }
+
+    /// Enables the strict validation mode: the opcodes forbidden only by the latest ERC-7562 rules
+    /// (e.g., `BLOBHASH`, `BLOBBASEFEE`) are rejected as well.
+    ///
+    /// # Arguments
+    /// `strict_opcodes` - Whether to reject the opcodes of the strict mode.
+    ///
+    /// # Returns
+    /// The [StandardUserOperationValidator] with the strict mode set.
+    pub fn with_strict_opcodes(mut self, strict_opcodes: bool) -> Self {
+        self.strict_opcodes = strict_opcodes;
+        self
+    }
+
+    /// Allows otherwise banned opcodes (e.g., `GAS`, `TIMESTAMP`) in the frames of trusted
+    /// factories (for private mempools). The violations are logged as warnings, and the same
+    /// opcodes are still rejected in the frames of the other entities.
+    ///
+    /// # Arguments
+    /// `trusted_factory_opcodes` - The allowed opcodes by factory address.
+    ///
+    /// # Returns
+    /// The [StandardUserOperationValidator] with the trusted factories set.
+    pub fn with_trusted_factory_opcodes(
+        mut self,
+        trusted_factory_opcodes: HashMap<Address, HashSet<String>>,
+    ) -> Self {
+        self.trusted_factory_opcodes = trusted_factory_opcodes;
+        self
+    }
+
+    /// Sets the acceptance window: user operations are only accepted if they are received within
+    /// the given number of seconds after the latest block (reduces the chance of validating
+    /// against stale state).
+    ///
+    /// # Arguments
+    /// `acceptance_window` - The acceptance window in seconds (`None` disables the check).
+    ///
+    /// # Returns
+    /// The [StandardUserOperationValidator] with the acceptance window set.
+    pub fn with_acceptance_window(mut self, acceptance_window: Option<u64>) -> Self {
+        self.acceptance_window = acceptance_window;
+        self
+    }
+
+    /// Enables subsidization: user operations with zero priority fee (or lower than the minimum
+    /// priority fee) are accepted, as the bundler intentionally runs at a loss (e.g., on
+    /// subsidized chains).
+    ///
+    /// # Arguments
+    /// `subsidized` - Whether the minimum priority fee check is bypassed.
+    ///
+    /// # Returns
+    /// The [StandardUserOperationValidator] with subsidization set.
+    pub fn with_subsidized(mut self, subsidized: bool) -> Self {
+        self.subsidized = subsidized;
+        self
+    }
+
+    /// Requires aggregators of aggregated user operations to be either staked or whitelisted (as
+    /// required by the spec).
+    ///
+    /// # Arguments
+    /// `require_staked_aggregator` - Whether user operations with an unstaked aggregator which
+    /// isn't whitelisted are rejected.
+    ///
+    /// # Returns
+    /// The [StandardUserOperationValidator] with the aggregator policy set.
+    pub fn with_staked_aggregator_required(mut self, require_staked_aggregator: bool) -> Self {
+        self.require_staked_aggregator = require_staked_aggregator;
+        self
+    }
+
+    /// Accepts EOAs delegated to a contract (EIP-7702) as deployed senders even if the initCode
+    /// carries the authorization (the [Sender] sanity check rejects non-empty initCode of senders
+    /// with code otherwise). The entry point can't apply the authorization, so it's dropped from
+    /// the user operations received over RPC before they are hashed (see
+    /// [UoPool::normalize_user_operation](crate::UoPool::normalize_user_operation)).
+    ///
+    /// # Arguments
+    /// `eip7702_senders` - Whether EIP-7702 delegated senders with the authorization in the
+    /// initCode are accepted.
+    ///
+    /// # Returns
+    /// The [StandardUserOperationValidator] with the EIP-7702 sender mode set.
+    pub fn with_eip7702_senders(mut self, eip7702_senders: bool) -> Self {
+        self.eip7702_senders = eip7702_senders;
+        self
+    }
+
+    /// Sets the configuration of the pre-verification gas calculation (the [VerificationGas]
+    /// sanity check requires the pre-verification gas to cover it).
+    ///
+    /// # Arguments
+    /// `overhead` - The [OverheadConfig] (the default one of the chain if not set).
+    ///
+    /// # Returns
+    /// The [StandardUserOperationValidator] with the overhead configuration set.
+    pub fn with_overhead(mut self, overhead: OverheadConfig<M>) -> Self {
+        self.overhead = overhead;
+        self
+    }
+
+    /// Checks that the aggregator of an aggregated [UserOperation](UserOperation) meets the stake
+    /// requirement (if required by the bundler).
+    ///
+    /// # Arguments
+    /// `sim_res` - The [simulation result](SimulateValidationResult) of the user operation.
+    /// `reputation` - [Reputation](Reputation) object.
+    /// `val_config` - [ValidationConfig](ValidationConfig) with the stake requirement.
+    ///
+    /// # Returns
+    /// None if the check passes, otherwise a [SimulationError].
+    fn check_aggregator_stake(
+        &self,
+        sim_res: &SimulateValidationResult,
+        reputation: &Reputation,
+        val_config: &ValidationConfig,
+    ) -> Result<(), SimulationError> {
+        if !self.require_staked_aggregator {
+            return Ok(());
+        }
+
+        if let Some(info) = extract_aggregator_stake_info(sim_res) {
+            reputation.verify_stake(
+                AGGREGATOR,
+                Some(info),
+                val_config.min_stake,
+                val_config.min_unstake_delay,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Creates the [SanityHelper] of the sanity checks with the settings of the validator.
+    fn sanity_helper(&self, val_config: ValidationConfig) -> SanityHelper<'_, M> {
+        SanityHelper {
+            entry_point: &self.entry_point,
+            val_config,
+            acceptance_window: self.acceptance_window,
+            subsidized: self.subsidized,
+            eip7702_senders: self.eip7702_senders,
+            overhead: &self.overhead,
+        }
+    }
+
+    /// Creates the [SimulationTraceHelper] of the simulation trace checks with the settings of
+    /// the validator.
+    fn simulation_trace_helper<'a>(
+        &'a self,
+        sim_res: &'a SimulateValidationResult,
+        js_trace: &'a JsTracerFrame,
+        val_config: ValidationConfig,
+    ) -> SimulationTraceHelper<'a, M> {
+        SimulationTraceHelper {
+            entry_point: &self.entry_point,
+            chain: self.chain,
+            simulate_validation_result: sim_res,
+            js_trace,
+            val_config,
+            stake_info: None,
+            code_hashes: None,
+            downgraded_violations: self.downgraded_violations,
+            verify_signature_hash: self.verify_signature_hash,
+            strict_opcodes: self.strict_opcodes,
+            trusted_factory_opcodes: &self.trusted_factory_opcodes,
+            failed_check: None,
+        }
     }
 
     /// Simulates validation of a [UserOperation](UserOperation) via the
@@ -177,7 +629,7 @@ where
         match self.entry_point.simulate_validation(uo.user_operation.clone()).await {
             Ok(res) => Ok(res),
             Err(err) => Err(match err {
-                EntryPointError::FailedOp(op) => SimulationError::Validation { inner: op.reason },
+                EntryPointError::FailedOp(op) => op.into(),
                 EntryPointError::Provider { inner } => SimulationError::Provider { inner },
                 _ => SimulationError::Other { inner: err.to_string() },
             }),
@@ -201,12 +653,59 @@ where
         match self.entry_point.simulate_validation_trace(uo.user_operation.clone()).await {
             Ok(trace) => Ok(trace),
             Err(err) => Err(match err {
-                EntryPointError::FailedOp(op) => SimulationError::Validation { inner: op.reason },
+                EntryPointError::FailedOp(op) => op.into(),
                 EntryPointError::Provider { inner } => SimulationError::Provider { inner },
                 _ => SimulationError::Other { inner: err.to_string() },
             }),
         }
     }
+
+    /// Runs the simulation trace checks on the validation trace of a
+    /// [UserOperation](UserOperation) and collects what they inspect (instead of only returning
+    /// the first error).
+    ///
+    /// # Arguments
+    /// `uo` - [UserOperation](UserOperation) to check.
+    /// `mempool` - [Mempool](Mempool) object.
+    /// `reputation` - [Reputation](Reputation) object.
+    /// `sim_res` - The [simulation result](SimulateValidationResult) of the user operation.
+    /// `js_trace` - The [JsTracerFrame] of the validation.
+    /// `val_config` - [ValidationConfig](ValidationConfig) the user operation is validated with.
+    ///
+    /// # Returns
+    /// The [ValidationTrace] with the check which rejected the user operation (if any).
+    async fn check_validation_trace(
+        &self,
+        uo: &UserOperation,
+        mempool: &Mempool,
+        reputation: &Reputation,
+        sim_res: &SimulateValidationResult,
+        js_trace: JsTracerFrame,
+        val_config: ValidationConfig,
+    ) -> ValidationTrace {
+        let mut sim_helper = self.simulation_trace_helper(sim_res, &js_trace, val_config);
+
+        let failed_check = self
+            .simulation_trace_checks
+            .check_user_operation(uo, mempool, reputation, &mut sim_helper)
+            .await
+            .err()
+            .map(|error| FailedTraceCheck {
+                check: sim_helper.failed_check.unwrap_or_default().into(),
+                error,
+            });
+
+        ValidationTrace {
+            storage_map: extract_storage_map(&js_trace),
+            banned_opcodes: extract_banned_opcodes(
+                &js_trace,
+                &get_address(&uo.init_code).unwrap_or_default(),
+                &self.trusted_factory_opcodes,
+            ),
+            failed_check,
+            trace: js_trace,
+        }
+    }
 }
 
 #[async_trait::async_trait]
@@ -247,47 +746,62 @@ where
         if let Some(val_config) = val_config.clone() {
             out.val_config = val_config;
         } else {
-            out.val_config = ValidationConfig {
-                min_stake: Some(reputation.min_stake()),
-                min_unstake_delay: Some(reputation.min_unstake_delay()),
-                topic: None,
-                ignore_prev: false,
-            };
+            out.val_config = ValidationConfig::builder()
+                .min_stake(reputation.min_stake())
+                .min_unstake_delay(reputation.min_unstake_delay())
+                .build()
+                .expect("validation config with stake values is valid");
         }
 
+        uo.validate_byte_fields().map_err(|inner| SanityError::MalformedFields { inner })?;
+
         if mode.contains(UserOperationValidatorMode::Sanity) {
-            let sanity_helper = SanityHelper {
-                entry_point: &self.entry_point,
-                chain: self.chain,
-                val_config: val_config.clone().unwrap_or_default(),
-            };
+            let sanity_helper = self.sanity_helper(val_config.clone().unwrap_or_default());
 
-            self.sanity_checks
+            // failed phases are timed too
+            let start = Instant::now();
+            let res = self
+                .sanity_checks
                 .check_user_operation(uo, mempool, reputation, &sanity_helper)
-                .await?;
+                .await;
+            record_validation_phase(ValidationPhase::Sanity, start.elapsed());
+            res?;
         }
 
+        // user operations re-validated for another canonical mempool (without the gas increase
+        // check) still replace the user operation with the same sender and nonce
         if let Some(uo) = mempool.get_prev_by_sender(uo) {
             out.prev_hash = Some(uo.hash);
         }
 
         debug!("Simulate user operation from {:?}", uo.sender);
-        let sim_res = self.simulate_validation(uo).await?;
+        let start = Instant::now();
+        let res = async {
+            let sim_res = self.simulate_validation(uo).await?;
+
+            if mode.contains(UserOperationValidatorMode::Simulation) {
+                let mut sim_helper = SimulationHelper {
+                    simulate_validation_result: &sim_res,
+                    val_config: val_config.clone().unwrap_or_default(),
+                    valid_after: None,
+                };
 
-        if mode.contains(UserOperationValidatorMode::Simulation) {
-            let mut sim_helper = SimulationHelper {
-                simulate_validation_result: &sim_res,
-                val_config: val_config.clone().unwrap_or_default(),
-                valid_after: None,
-            };
+                self.simulation_checks.check_user_operation(uo, &mut sim_helper)?;
+                self.check_aggregator_stake(&sim_res, reputation, &sim_helper.val_config)?;
 
-            self.simulation_checks.check_user_operation(uo, &mut sim_helper)?;
+                out.valid_after = sim_helper.valid_after;
+            }
 
-            out.valid_after = sim_helper.valid_after;
+            Ok::<_, InvalidMempoolUserOperationError>(sim_res)
         }
+        .await;
+        record_validation_phase(ValidationPhase::Simulation, start.elapsed());
+        let sim_res = res?;
 
         out.pre_fund = extract_pre_fund(&sim_res);
         out.verification_gas_limit = extract_verification_gas_limit(&sim_res);
+        out.call_gas_limit = uo.call_gas_limit;
+        out.pre_verification_gas = uo.pre_verification_gas;
 
         let block_number = self
             .entry_point
@@ -300,28 +814,273 @@ where
 
         if mode.contains(UserOperationValidatorMode::SimulationTrace) {
             debug!("Simulate user operation with trace from {:?}", uo.sender);
-            let geth_trace = self.simulate_validation_trace(uo).await?;
-            let js_trace: JsTracerFrame = JsTracerFrame::try_from(geth_trace)
-                .map_err(|error| SimulationError::Validation { inner: error.to_string() })?;
-
-            let mut sim_helper = SimulationTraceHelper {
-                entry_point: &self.entry_point,
-                chain: self.chain,
-                simulate_validation_result: &sim_res,
-                js_trace: &js_trace,
-                val_config: val_config.unwrap_or_default(),
-                stake_info: None,
-                code_hashes: None,
-            };
-
-            self.simulation_trace_checks
-                .check_user_operation(uo, mempool, reputation, &mut sim_helper)
-                .await?;
-
-            out.code_hashes = sim_helper.code_hashes;
-            out.storage_map = extract_storage_map(&js_trace);
+            let start = Instant::now();
+            let res = async {
+                let geth_trace = self.simulate_validation_trace(uo).await?;
+                let js_trace: JsTracerFrame = JsTracerFrame::try_from(geth_trace)
+                    .map_err(|error| SimulationError::Validation { inner: error.to_string() })?;
+
+                let mut sim_helper = self.simulation_trace_helper(
+                    &sim_res,
+                    &js_trace,
+                    val_config.unwrap_or_default(),
+                );
+
+                self.simulation_trace_checks
+                    .check_user_operation(uo, mempool, reputation, &mut sim_helper)
+                    .await?;
+
+                Ok::<_, InvalidMempoolUserOperationError>((
+                    sim_helper.code_hashes,
+                    extract_storage_map(&js_trace),
+                ))
+            }
+            .await;
+            record_validation_phase(ValidationPhase::Trace, start.elapsed());
+            let (code_hashes, storage_map) = res?;
+
+            out.code_hashes = code_hashes;
+            out.storage_map = storage_map;
         }
 
         Ok(out)
     }
+
+    /// Traces the validation of a [UserOperation](UserOperation) (dry-run) and runs the
+    /// simulation trace checks on it, without adding it to the mempool or updating the
+    /// reputation.
+    ///
+    /// # Arguments
+    /// `uo` - [UserOperation](UserOperation) to trace.
+    /// `mempool` - [Mempool](Mempool) object.
+    /// `reputation` - [Reputation](Reputation) object.
+    /// `val_config` - Optional [ValidationConfig](ValidationConfig) object (as passed to
+    /// [validate_user_operation](UserOperationValidator::validate_user_operation)).
+    ///
+    /// # Returns
+    /// A [ValidationTrace] (with the failed simulation trace check if any) if the validation
+    /// could be traced, otherwise a
+    /// [InvalidMempoolUserOperationError](InvalidMempoolUserOperationError) (e.g., of a failed
+    /// sanity check).
+    async fn trace_user_operation_validation(
+        &self,
+        uo: &UserOperation,
+        mempool: &Mempool,
+        reputation: &Reputation,
+        val_config: Option<ValidationConfig>,
+    ) -> Result<ValidationTrace, InvalidMempoolUserOperationError> {
+        let val_config = val_config.unwrap_or_default();
+
+        uo.validate_byte_fields().map_err(|inner| SanityError::MalformedFields { inner })?;
+        self.sanity_checks
+            .check_user_operation(uo, mempool, reputation, &self.sanity_helper(val_config.clone()))
+            .await?;
+
+        let sim_res = self.simulate_validation(uo).await?;
+        let geth_trace = self.simulate_validation_trace(uo).await?;
+        let js_trace: JsTracerFrame = JsTracerFrame::try_from(geth_trace)
+            .map_err(|error| SimulationError::Validation { inner: error.to_string() })?;
+
+        Ok(self
+            .check_validation_trace(uo, mempool, reputation, &sim_res, js_trace, val_config)
+            .await)
+    }
+
+    fn min_priority_fee_per_gas(&self, entry_point: &Address) -> U256 {
+        self.sanity_checks.min_priority_fee_per_gas(entry_point).unwrap_or_default()
+    }
+
+    fn eip7702_senders(&self) -> bool {
+        self.eip7702_senders
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_utils::{entity_selector, memory_mempool, memory_reputation},
+        validate::BannedOpcode,
+        ReputationError,
+    };
+    use ethers::{
+        providers::Provider,
+        types::{Address, H256},
+    };
+    use silius_contracts::{
+        entry_point::ValidationResultWithAggregation,
+        tracer::{ReadsAndWrites, TopLevelCallInfo},
+    };
+    use silius_primitives::{
+        constants::validation::entities::{FACTORY, SENDER},
+        UserOperationSigned,
+    };
+    use std::{collections::HashMap, sync::Arc};
+
+    #[test]
+    fn aggregator_stake() {
+        let (provider, _) = Provider::mocked();
+        let entry_point = EntryPoint::new(Arc::new(provider), Address::random());
+        let validator =
+            new_canonical(entry_point, Chain::from(1337), U256::from(5_000_000), U256::from(100));
+        let reputation = memory_reputation();
+        let val_config = ValidationConfig::default();
+
+        let aggregated = |stake: u64| {
+            SimulateValidationResult::ValidationResultWithAggregation(
+                ValidationResultWithAggregation {
+                    aggregator_info: (Address::random(), (U256::from(stake), U256::from(2))),
+                    ..Default::default()
+                },
+            )
+        };
+
+        // the stake of the aggregator isn't checked unless required
+        assert!(validator.check_aggregator_stake(&aggregated(0), &reputation, &val_config).is_ok());
+
+        let validator = validator.with_staked_aggregator_required(true);
+        assert!(matches!(
+            validator.check_aggregator_stake(&aggregated(0), &reputation, &val_config),
+            Err(SimulationError::Reputation(ReputationError::StakeTooLow { .. }))
+        ));
+        assert!(validator.check_aggregator_stake(&aggregated(1), &reputation, &val_config).is_ok());
+
+        // user operations without an aggregator aren't affected
+        let not_aggregated = SimulateValidationResult::ValidationResult(Default::default());
+        assert!(validator
+            .check_aggregator_stake(&not_aggregated, &reputation, &val_config)
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn trace_storage_access_violation() {
+        let (provider, _) = Provider::mocked();
+        let entry_point = EntryPoint::new(Arc::new(provider), Address::random());
+        let validator =
+            new_canonical(entry_point, Chain::from(1337), U256::from(5_000_000), U256::from(100));
+        let mempool = memory_mempool();
+        let reputation = memory_reputation();
+
+        let uo = UserOperation::from_user_operation_signed(
+            H256::random().into(),
+            UserOperationSigned::default().sender(Address::random()),
+        );
+        let sim_res = SimulateValidationResult::ValidationResult(Default::default());
+
+        // the account writes a slot (not associated with it) of an external contract
+        let contract = Address::random();
+        let slot = "0x00000000000000000000000000000000000000000000000000000000000000aa".to_string();
+        let js_trace = JsTracerFrame {
+            calls_from_entry_point: vec![TopLevelCallInfo {
+                top_level_method_sig: entity_selector(SENDER),
+                access: HashMap::from([(
+                    contract,
+                    ReadsAndWrites {
+                        reads: HashMap::from([(slot.clone(), "0x01".to_string())]),
+                        writes: HashMap::from([(slot.clone(), 1)]),
+                    },
+                )]),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let trace = validator
+            .check_validation_trace(
+                &uo,
+                &mempool,
+                &reputation,
+                &sim_res,
+                js_trace.clone(),
+                ValidationConfig::default(),
+            )
+            .await;
+
+        let failed_check = trace.failed_check.as_ref().expect("storage access is violated");
+        assert_eq!(failed_check.check, "StorageAccess");
+        assert!(matches!(
+            &failed_check.error,
+            SimulationError::StorageAccess { rule, entity, address, slot: s }
+                if rule == "STO-033" && entity == SENDER && *address == contract && *s == slot
+        ));
+        assert_eq!(trace.storage_map.slots[&contract][&slot], "0x01");
+        assert!(trace.banned_opcodes.is_empty());
+        assert_eq!(trace.trace, js_trace);
+
+        // the mempool and the reputation are untouched
+        assert!(mempool.get_all().unwrap().is_empty());
+        assert!(reputation.get_all().unwrap().is_empty());
+
+        let json = serde_json::to_value(&trace).unwrap();
+        assert_eq!(json["failedCheck"]["check"], "StorageAccess");
+        assert!(json["failedCheck"]["error"].to_string().contains(&slot));
+    }
+
+    #[tokio::test]
+    async fn trace_trusted_factory_opcodes() {
+        let (provider, _) = Provider::mocked();
+        let entry_point = EntryPoint::new(Arc::new(provider), Address::random());
+        let factory = Address::random();
+        let validator =
+            new_canonical(entry_point, Chain::from(1337), U256::from(5_000_000), U256::from(100))
+                .with_trusted_factory_opcodes(HashMap::from([(
+                    factory,
+                    HashSet::from(["GAS".to_string()]),
+                )]));
+        let mempool = memory_mempool();
+        let reputation = memory_reputation();
+
+        let uo = UserOperation::from_user_operation_signed(
+            H256::random().into(),
+            UserOperationSigned::default()
+                .sender(Address::random())
+                .init_code([factory.as_bytes(), &[1, 2, 3][..]].concat().into()),
+        );
+        let sim_res = SimulateValidationResult::ValidationResult(Default::default());
+
+        // GAS opcode used in the factory level by the given contract
+        let js_trace = |contract: Address| JsTracerFrame {
+            calls_from_entry_point: vec![TopLevelCallInfo {
+                top_level_method_sig: entity_selector(FACTORY),
+                opcodes: HashMap::from([("GAS".to_string(), 1)]),
+                contract_opcodes: HashMap::from([(
+                    contract,
+                    HashMap::from([("GAS".to_string(), 1)]),
+                )]),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        // used by the trusted factory itself
+        let trace = validator
+            .check_validation_trace(
+                &uo,
+                &mempool,
+                &reputation,
+                &sim_res,
+                js_trace(factory),
+                ValidationConfig::default(),
+            )
+            .await;
+        assert!(trace.failed_check.is_none());
+        assert!(trace.banned_opcodes.is_empty());
+
+        // used by a contract called by the trusted factory
+        let trace = validator
+            .check_validation_trace(
+                &uo,
+                &mempool,
+                &reputation,
+                &sim_res,
+                js_trace(Address::random()),
+                ValidationConfig::default(),
+            )
+            .await;
+        assert_eq!(trace.failed_check.expect("GAS opcode is banned").check, "Opcodes");
+        assert_eq!(
+            trace.banned_opcodes,
+            vec![BannedOpcode { entity: FACTORY.into(), opcode: "GAS".into(), count: 1 }]
+        );
+    }
 }
@@ -0,0 +1,28 @@
+use ethers::types::{Address, U256};
+use metrics::{counter, describe_counter, describe_gauge, gauge};
+
+const BUNDLER_BALANCE: &str = "silius_bundler_balance";
+const BUNDLER_BALANCE_LOW: &str = "silius_bundler_balance_low";
+
+/// Records the balance of the bundler's wallet (in wei) and whether it's below the warning
+/// threshold (both labeled with the address of the wallet)
+pub fn record_bundler_balance(address: Address, balance: U256, threshold: U256) {
+    let address = format!("{address:?}");
+    gauge!(BUNDLER_BALANCE, "address" => address.clone())
+        .set(balance.min(U256::from(u128::MAX)).as_u128() as f64);
+    // the labeled counter is registered on the first check, even if the balance isn't low
+    let balance_low = counter!(BUNDLER_BALANCE_LOW, "address" => address);
+    if balance < threshold {
+        balance_low.increment(1);
+    } else {
+        balance_low.increment(0);
+    }
+}
+
+pub fn describe_bundler_metrics() {
+    describe_gauge!(BUNDLER_BALANCE, "The balance of the bundler's wallet (in wei)");
+    describe_counter!(
+        BUNDLER_BALANCE_LOW,
+        "The number of balance checks where the bundler's balance was below the warning threshold"
+    );
+}
@@ -1,6 +1,8 @@
+use serde::Serialize;
+
 /// Represents a label-value pair.
 /// Mainly used for metrics system.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct LabelValue {
     pub label: String,
     pub value: String,
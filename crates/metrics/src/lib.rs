@@ -1,12 +1,15 @@
 use crate::{
-    grpc::describe_grpc_metrics, mempool::describe_mempool_metrics, rpc::describe_json_rpc_metrics,
+    bundler::describe_bundler_metrics, grpc::describe_grpc_metrics,
+    mempool::describe_mempool_metrics, rpc::describe_json_rpc_metrics,
 };
 use label::LabelValue;
 use metrics_exporter_prometheus::PrometheusBuilder;
 use metrics_util::MetricKindMask;
+use silius_p2p::metrics::describe_p2p_metrics;
 use std::{net::SocketAddr, time::Duration};
 use tracing::info;
 
+pub mod bundler;
 pub mod ethers;
 pub mod grpc;
 pub mod label;
@@ -33,4 +36,6 @@ pub fn launch_metrics_exporter(listen_addr: SocketAddr, label_value_opt: Option<
     describe_json_rpc_metrics();
     describe_mempool_metrics();
     describe_grpc_metrics();
+    describe_bundler_metrics();
+    describe_p2p_metrics();
 }
@@ -1,6 +1,8 @@
 use metrics::{counter, describe_counter, describe_gauge, gauge};
 use silius_mempool::{
-    AddRemoveUserOp, ClearOp, MempoolErrorKind, ReputationEntryOp, ReputationError, UserOperationOp,
+    metrics::{describe_chain_metrics, describe_validation_metrics},
+    AddRemoveUserOp, ClearOp, MempoolErrorKind, RemoveBatchOp, ReputationEntryOp, ReputationError,
+    UserOperationOp,
 };
 use silius_primitives::{UserOperation, UserOperationHash};
 
@@ -54,6 +56,27 @@ impl<S: AddRemoveUserOp + Clone> AddRemoveUserOp for MetricsHandler<S> {
     }
 }
 
+impl<S: RemoveBatchOp + Clone> RemoveBatchOp for MetricsHandler<S> {
+    fn remove_batch(
+        &mut self,
+        uo_hashes: &[UserOperationHash],
+        by_sender: &[(ethers::types::Address, UserOperationHash)],
+        by_entity: &[(ethers::types::Address, UserOperationHash)],
+        by_entry_point: &[(ethers::types::Address, UserOperationHash)],
+    ) -> Result<(), MempoolErrorKind> {
+        match self.inner.remove_batch(uo_hashes, by_sender, by_entity, by_entry_point) {
+            Ok(()) => {
+                gauge!(MEMPOOL_SIZE).decrement(uo_hashes.len() as f64);
+                Ok(())
+            }
+            Err(e) => {
+                counter!(MEMPOOL_REMOVE_ERROR, "error" => format!("{:?}", e)).increment(1);
+                Err(e)
+            }
+        }
+    }
+}
+
 impl<S: UserOperationOp + Clone> UserOperationOp for MetricsHandler<S> {
     fn get_by_uo_hash(
         &self,
@@ -117,6 +140,8 @@ impl<S: ReputationEntryOp + Clone> ReputationEntryOp for MetricsHandler<S> {
 }
 
 pub fn describe_mempool_metrics() {
+    describe_validation_metrics();
+    describe_chain_metrics();
     describe_gauge!(MEMPOOL_SIZE, "The number of user operations in the mempool");
     describe_counter!(MEMPOOL_ADD_ERROR, "The number of errors when adding to the mempool");
     describe_counter!(MEMPOOL_REMOVE_ERROR, "The number of errors when removing from the mempool");
@@ -1,17 +1,20 @@
-use crate::listen_addr::{ListenAddr, ListenAddress};
+use crate::{
+    listen_addr::{ListenAddr, ListenAddress},
+    types::pubsub::message_id,
+};
 use discv5::{Enr, ListenConfig};
 use libp2p::gossipsub;
-use sha2::{Digest, Sha256};
 use silius_primitives::{
     chain::ChainSpec,
     constants::p2p::{
-        IPV4_ADDRESS, MESSAGE_DOMAIN_VALID_SNAPPY, NODE_ENR_FILE_NAME, NODE_KEY_FILE_NAME,
-        TARGET_PEERS, TCP_PORT, UDP_PORT,
+        GOSSIP_LAZY, GOSSIP_MESH_N, GOSSIP_MESH_N_HIGH, GOSSIP_MESH_N_LOW, IPV4_ADDRESS, MIN_PEERS,
+        NODE_ENR_FILE_NAME, NODE_KEY_FILE_NAME, SEEN_CACHE_SIZE, TARGET_PEERS, TCP_PORT, UDP_PORT,
     },
 };
 use std::{
     net::{IpAddr, Ipv4Addr, Ipv6Addr},
     path::PathBuf,
+    time::Duration,
 };
 
 #[derive(Clone, Debug)]
@@ -55,6 +58,21 @@ pub struct Config {
     /// Target number of peers.
     pub target_peers: usize,
 
+    /// Minimum number of connected peers before user operations received over gossip are pooled.
+    pub min_peers: usize,
+
+    /// Number of recently seen user operation hashes used to drop gossip duplicates (0 disables
+    /// the cache).
+    pub seen_cache_size: usize,
+
+    /// Canonical mempool (id) used to validate user operations which weren't validated against a
+    /// specific canonical mempool (the first canonical mempool if not set).
+    pub default_mempool: Option<String>,
+
+    /// Time without useful gossip after which a peer which isn't whitelisted is disconnected (idle
+    /// peers aren't pruned if not set).
+    pub idle_peer_timeout: Option<Duration>,
+
     /// List of bootnodes.
     pub bootnodes: Vec<Enr>,
 
@@ -67,7 +85,7 @@ pub struct Config {
 
 impl Default for Config {
     fn default() -> Self {
-        let gs_config = gossipsub::ConfigBuilder::default().build().expect("valid config");
+        let gs_config = gossipsub_config();
         let discv5_config =
             discv5::ConfigBuilder::new(ListenConfig::Ipv4 { ip: IPV4_ADDRESS, port: UDP_PORT })
                 .build();
@@ -90,6 +108,10 @@ impl Default for Config {
             discv5_config,
             chain_spec: ChainSpec::dev(),
             target_peers: TARGET_PEERS,
+            min_peers: MIN_PEERS,
+            seen_cache_size: SEEN_CACHE_SIZE,
+            default_mempool: None,
+            idle_peer_timeout: None,
             bootnodes: vec![],
             peers_whitelist: vec![],
             ips_whitelist: vec![],
@@ -197,6 +219,30 @@ impl ConfigBuilder {
         self
     }
 
+    /// Set the minimum number of connected peers before user operations from p2p are pooled.
+    pub fn min_peers(mut self, min_peers: usize) -> Self {
+        self.config.min_peers = min_peers;
+        self
+    }
+
+    /// Set the number of recently seen user operation hashes used to drop gossip duplicates.
+    pub fn seen_cache_size(mut self, seen_cache_size: usize) -> Self {
+        self.config.seen_cache_size = seen_cache_size;
+        self
+    }
+
+    /// Set the canonical mempool used to validate user operations without a canonical mempool.
+    pub fn default_mempool(mut self, default_mempool: Option<String>) -> Self {
+        self.config.default_mempool = default_mempool;
+        self
+    }
+
+    /// Set the time without useful gossip after which a peer is disconnected.
+    pub fn idle_peer_timeout(mut self, idle_peer_timeout: Option<Duration>) -> Self {
+        self.config.idle_peer_timeout = idle_peer_timeout;
+        self
+    }
+
     /// Set the bootnodes.
     pub fn bootnodes(mut self, bootnodes: Vec<Enr>) -> Self {
         self.config.bootnodes = bootnodes;
@@ -216,30 +262,73 @@ impl ConfigBuilder {
     }
 }
 
-/// Create a `GossipsubConfig`.
+/// Gossipsub mesh parameters (control to how many peers messages are propagated).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GossipsubMeshParams {
+    /// Target number of peers in the mesh (D). Also the number of fanout peers used when
+    /// publishing to a topic we are not subscribed to.
+    pub mesh_n: usize,
+
+    /// Minimum number of peers in the mesh before more are grafted (D_low).
+    pub mesh_n_low: usize,
+
+    /// Maximum number of peers in the mesh before some are pruned (D_high).
+    pub mesh_n_high: usize,
+
+    /// Number of peers outside the mesh to emit gossip to (D_lazy).
+    pub gossip_lazy: usize,
+}
+
+impl Default for GossipsubMeshParams {
+    fn default() -> Self {
+        Self {
+            mesh_n: GOSSIP_MESH_N,
+            mesh_n_low: GOSSIP_MESH_N_LOW,
+            mesh_n_high: GOSSIP_MESH_N_HIGH,
+            gossip_lazy: GOSSIP_LAZY,
+        }
+    }
+}
+
+impl GossipsubMeshParams {
+    /// Validates that `0 < mesh_n_low <= mesh_n <= mesh_n_high`.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.mesh_n_low == 0 {
+            return Err("mesh_n_low must be greater than 0".into());
+        }
+
+        if !(self.mesh_n_low <= self.mesh_n && self.mesh_n <= self.mesh_n_high) {
+            return Err(format!(
+                "mesh params must satisfy mesh_n_low ({}) <= mesh_n ({}) <= mesh_n_high ({})",
+                self.mesh_n_low, self.mesh_n, self.mesh_n_high
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Create a `GossipsubConfig` with the default mesh parameters.
 pub fn gossipsub_config() -> gossipsub::Config {
-    let message_id_fn = |message: &gossipsub::Message| {
-        let topic_bytes = message.topic.as_str().as_bytes();
-        let topic_len_bytes = topic_bytes.len().to_le_bytes();
-
-        let mut vec: Vec<u8> = Vec::with_capacity(
-            MESSAGE_DOMAIN_VALID_SNAPPY.len() +
-                topic_len_bytes.len() +
-                topic_bytes.len() +
-                message.data.len(),
-        );
-        vec.extend_from_slice(&MESSAGE_DOMAIN_VALID_SNAPPY);
-        vec.extend_from_slice(&topic_len_bytes);
-        vec.extend_from_slice(topic_bytes);
-        vec.extend_from_slice(&message.data);
-
-        Sha256::digest(vec)[..20].into()
-    };
+    gossipsub_config_with_mesh_params(&GossipsubMeshParams::default()).expect("valid config")
+}
+
+/// Create a `GossipsubConfig` with the given mesh parameters.
+pub fn gossipsub_config_with_mesh_params(
+    mesh_params: &GossipsubMeshParams,
+) -> Result<gossipsub::Config, String> {
+    mesh_params.validate()?;
 
     gossipsub::ConfigBuilder::default()
         .validate_messages()
         .validation_mode(gossipsub::ValidationMode::Anonymous)
-        .message_id_fn(message_id_fn)
+        .message_id_fn(message_id)
+        .mesh_n(mesh_params.mesh_n)
+        .mesh_n_low(mesh_params.mesh_n_low)
+        .mesh_n_high(mesh_params.mesh_n_high)
+        .gossip_lazy(mesh_params.gossip_lazy)
+        // gossipsub requires mesh_outbound_min <= mesh_n_low and mesh_outbound_min <= mesh_n / 2
+        .mesh_outbound_min(2.min(mesh_params.mesh_n_low).min(mesh_params.mesh_n / 2))
         .build()
-        .expect("valid config")
+        .map_err(|e| e.to_string())
 }
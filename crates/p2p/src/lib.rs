@@ -3,6 +3,7 @@
 pub mod config;
 pub mod discovery;
 pub mod listen_addr;
+pub mod metrics;
 pub mod peer_manager;
 pub mod rpc;
 pub mod service;
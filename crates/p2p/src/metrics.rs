@@ -0,0 +1,17 @@
+use metrics::{counter, describe_counter};
+
+pub const P2P_OVERSIZED_USER_OPERATIONS: &str = "silius_p2p_oversized_user_operations";
+
+/// Records a user operation which wasn't gossiped since its byte field exceeds the maximum length
+/// of the SSZ encoding
+pub fn record_oversized_user_operation(field: &'static str) {
+    counter!(P2P_OVERSIZED_USER_OPERATIONS, "field" => field).increment(1);
+}
+
+pub fn describe_p2p_metrics() {
+    describe_counter!(
+        P2P_OVERSIZED_USER_OPERATIONS,
+        "The number of user operations not gossiped since a byte field exceeds the SSZ length limit"
+    );
+    counter!(P2P_OVERSIZED_USER_OPERATIONS).absolute(0);
+}
@@ -10,11 +10,17 @@ use crate::{
 };
 use delay_map::HashSetDelay;
 use discv5::Enr;
-use libp2p::{Multiaddr, PeerId};
+use libp2p::{multiaddr::Protocol, Multiaddr, PeerId};
 use silius_primitives::constants::p2p::{
-    HEARTBEAT_INTERVAL, PING_INTERVAL_INBOUND, PING_INTERVAL_OUTBOUND, TARGET_PEERS,
+    BAD_GOSSIP_PENALTY, HEARTBEAT_INTERVAL, MIN_PEER_SCORE, PEER_SCORE_RECOVERY,
+    PING_INTERVAL_INBOUND, PING_INTERVAL_OUTBOUND, TARGET_PEERS,
+};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    net::IpAddr,
+    sync::Arc,
+    time::{Duration, Instant},
 };
-use std::{collections::VecDeque, net::IpAddr, sync::Arc, time::Duration};
 use tracing::debug;
 
 /// The events that the `PeerManager` outputs (requests).
@@ -61,6 +67,16 @@ pub struct PeerManager {
     ips_whitelist: Vec<IpAddr>,
     /// The heartbeat interval for peer management.
     heartbeat: tokio::time::Interval,
+    /// Scores of the peers penalized for bad gossip (recovering toward zero every heartbeat).
+    scores: HashMap<PeerId, f64>,
+    /// Peers whose score dropped below the threshold, banned until their score has fully
+    /// recovered.
+    banned: HashSet<PeerId>,
+    /// Time without useful activity after which a (non-whitelisted) peer is disconnected (never
+    /// if None).
+    idle_timeout: Option<Duration>,
+    /// Last useful activity of the connected peers (or the time they connected).
+    last_activity: HashMap<PeerId, Instant>,
 }
 
 impl PeerManager {
@@ -68,6 +84,7 @@ impl PeerManager {
         network_globals: Arc<NetworkGlobals>,
         peers_whitelist: Vec<Enr>,
         ips_whitelist: Vec<IpAddr>,
+        idle_timeout: Option<Duration>,
     ) -> Self {
         Self {
             network_globals,
@@ -79,6 +96,10 @@ impl PeerManager {
             peers_whitelist,
             ips_whitelist,
             heartbeat: tokio::time::interval(Duration::from_secs(HEARTBEAT_INTERVAL)),
+            scores: HashMap::new(),
+            banned: HashSet::new(),
+            idle_timeout,
+            last_activity: HashMap::new(),
         }
     }
 
@@ -86,8 +107,66 @@ impl PeerManager {
         self.network_globals.peers.read().is_connected(peer_id)
     }
 
+    /// Score of the peer (zero unless it was penalized for bad gossip).
+    pub fn score(&self, peer_id: &PeerId) -> f64 {
+        self.scores.get(peer_id).copied().unwrap_or_default()
+    }
+
+    /// Whether the peer is banned (its score dropped below the threshold and hasn't fully recovered
+    /// yet).
+    pub fn is_banned(&self, peer_id: &PeerId) -> bool {
+        self.banned.contains(peer_id)
+    }
+
+    /// The peer has sent gossip which is malformed or for an unsupported entry point. Its score is
+    /// decremented and it's disconnected once the score drops below the threshold.
+    pub fn report_bad_gossip(&mut self, peer_id: &PeerId) {
+        let score = self.scores.entry(*peer_id).or_default();
+        *score -= BAD_GOSSIP_PENALTY;
+        let score = *score;
+
+        if let Some(peer_info) = self.network_globals.peers.write().peer_info_mut(peer_id) {
+            peer_info.set_score(score);
+        }
+
+        if score < MIN_PEER_SCORE {
+            self.banned.insert(*peer_id);
+            if self.is_connected(peer_id) {
+                debug!("Disconnecting peer {peer_id} with score {score} for bad gossip");
+                self.disconnect_peer(*peer_id, GoodbyeReason::Error);
+            }
+        }
+    }
+
+    /// The peer has sent useful gossip (a user operation accepted by the mempool), so it
+    /// isn't pruned as idle.
+    pub fn report_useful_gossip(&mut self, peer_id: &PeerId) {
+        if let Some(last_activity) = self.last_activity.get_mut(peer_id) {
+            *last_activity = Instant::now();
+        }
+    }
+
+    /// Whether the peer (or its IP address) is whitelisted.
+    fn is_whitelisted(&self, peer_id: &PeerId) -> bool {
+        if self.peers_whitelist.iter().any(|enr| enr.peer_id() == *peer_id) {
+            return true;
+        }
+
+        let peer_db = self.network_globals.peers.read();
+        let ip = peer_db.peer_info(peer_id).and_then(|peer_info| {
+            peer_info.seen_address().as_ref().and_then(|addr| match addr.iter().next() {
+                Some(Protocol::Ip4(ip)) => Some(IpAddr::V4(ip)),
+                Some(Protocol::Ip6(ip)) => Some(IpAddr::V6(ip)),
+                _ => None,
+            })
+        });
+        ip.is_some_and(|ip| self.ips_whitelist.contains(&ip))
+    }
+
     pub fn dial_peer(&mut self, enr: Enr) -> bool {
-        if self.network_globals.peers.read().should_dial(&enr.peer_id()) {
+        if !self.is_banned(&enr.peer_id()) &&
+            self.network_globals.peers.read().should_dial(&enr.peer_id())
+        {
             self.peers_to_dial.push(enr);
             true
         } else {
@@ -186,6 +265,7 @@ impl PeerManager {
 
         self.inbound_ping_peers.remove(peer_id);
         self.outbound_ping_peers.remove(peer_id);
+        self.last_activity.remove(peer_id);
     }
 
     fn inject_peer_connection(
@@ -210,16 +290,175 @@ impl PeerManager {
                 self.outbound_ping_peers.insert(*peer_id);
             }
         }
+        self.last_activity.entry(*peer_id).or_insert_with(Instant::now);
 
         true
     }
 
-    fn _disconnect_peer(&mut self, peer_id: PeerId, reason: GoodbyeReason) {
+    fn disconnect_peer(&mut self, peer_id: PeerId, reason: GoodbyeReason) {
         self.events.push_back(PeerManagerEvent::DisconnectPeer(peer_id, reason));
         self.network_globals.peers.write().notify_disconnecting(&peer_id);
     }
 
     fn heartbeat(&mut self) {
         // TODO: optionally run discovery
+
+        // scores of penalized peers decay back toward zero, bans are lifted once fully recovered
+        let mut peer_db = self.network_globals.peers.write();
+        let banned = &mut self.banned;
+        self.scores.retain(|peer_id, score| {
+            *score = (*score + PEER_SCORE_RECOVERY).min(0.0);
+            if let Some(peer_info) = peer_db.peer_info_mut(peer_id) {
+                peer_info.set_score(*score);
+            }
+            if *score < 0.0 {
+                true
+            } else {
+                banned.remove(peer_id);
+                false
+            }
+        });
+        drop(peer_db);
+
+        self.prune_idle_peers();
+    }
+
+    /// Disconnects the peers without useful activity within the idle timeout, freeing their
+    /// connection slots (whitelisted peers are kept).
+    fn prune_idle_peers(&mut self) {
+        let Some(idle_timeout) = self.idle_timeout else {
+            return;
+        };
+
+        let idle_peers = self
+            .last_activity
+            .iter()
+            .filter(|(_, last_activity)| last_activity.elapsed() >= idle_timeout)
+            .map(|(peer_id, _)| *peer_id)
+            .collect::<Vec<_>>();
+
+        for peer_id in idle_peers {
+            if !self.is_connected(&peer_id) || self.is_whitelisted(&peer_id) {
+                continue;
+            }
+
+            debug!("Disconnecting peer {peer_id} idle for more than {idle_timeout:?}");
+            self.last_activity.remove(&peer_id);
+            self.disconnect_peer(peer_id, GoodbyeReason::IrrelevantNetwork);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use discv5::enr::CombinedKey;
+    use ethers::types::H256;
+    use silius_primitives::chain::ChainSpec;
+
+    fn peer_manager() -> PeerManager {
+        let enr = Enr::builder().build(&CombinedKey::generate_secp256k1()).unwrap();
+        let network_globals = Arc::new(NetworkGlobals::new(
+            enr,
+            MetaData::default(),
+            vec![],
+            ChainSpec::dev(),
+            H256::zero(),
+            0,
+        ));
+        PeerManager::new(network_globals, vec![], vec![], None)
+    }
+
+    #[tokio::test]
+    async fn bad_gossip_disconnects_peer() {
+        let mut peer_manager = peer_manager();
+        let peer_id = PeerId::random();
+        peer_manager.inject_connect_ingoing(
+            &peer_id,
+            "/ip4/127.0.0.1/tcp/4337".parse().unwrap(),
+            None,
+        );
+        peer_manager.events.clear();
+
+        // the peer stays connected until its score drops below the threshold
+        let reports = (-MIN_PEER_SCORE / BAD_GOSSIP_PENALTY) as usize;
+        for _ in 0..reports {
+            peer_manager.report_bad_gossip(&peer_id);
+        }
+        assert!(peer_manager.events.is_empty());
+        assert!(!peer_manager.is_banned(&peer_id));
+
+        peer_manager.report_bad_gossip(&peer_id);
+        assert!(peer_manager.is_banned(&peer_id));
+        assert!(matches!(
+            peer_manager.events.pop_front(),
+            Some(PeerManagerEvent::DisconnectPeer(id, GoodbyeReason::Error)) if id == peer_id
+        ));
+        assert_eq!(
+            peer_manager.network_globals.peers.read().peer_info(&peer_id).unwrap().score(),
+            peer_manager.score(&peer_id)
+        );
+
+        // the ban holds while the score decays back toward zero (even above the threshold)
+        let heartbeats = (-peer_manager.score(&peer_id) / PEER_SCORE_RECOVERY).ceil() as usize;
+        assert!(heartbeats * HEARTBEAT_INTERVAL as usize >= 30 * 60);
+        for _ in 1..heartbeats {
+            peer_manager.heartbeat();
+            assert!(peer_manager.is_banned(&peer_id));
+        }
+        assert!(peer_manager.score(&peer_id) > MIN_PEER_SCORE);
+
+        // and is lifted once the score has fully recovered
+        peer_manager.heartbeat();
+        assert!(!peer_manager.is_banned(&peer_id));
+        assert!(peer_manager.scores.is_empty());
+        assert_eq!(
+            peer_manager.network_globals.peers.read().peer_info(&peer_id).unwrap().score(),
+            0.0
+        );
+    }
+
+    #[tokio::test]
+    async fn idle_peers_pruned() {
+        let whitelisted_enr = Enr::builder().build(&CombinedKey::generate_secp256k1()).unwrap();
+        let mut peer_manager = peer_manager();
+        peer_manager.idle_timeout = Some(Duration::from_millis(50));
+        peer_manager.peers_whitelist = vec![whitelisted_enr.clone()];
+        peer_manager.ips_whitelist = vec!["10.0.0.1".parse().unwrap()];
+
+        let idle = PeerId::random();
+        let useful = PeerId::random();
+        let whitelisted = whitelisted_enr.peer_id();
+        let whitelisted_ip = PeerId::random();
+        for (peer_id, addr) in [
+            (idle, "/ip4/127.0.0.1/tcp/4337"),
+            (useful, "/ip4/127.0.0.1/tcp/4338"),
+            (whitelisted, "/ip4/127.0.0.1/tcp/4339"),
+            (whitelisted_ip, "/ip4/10.0.0.1/tcp/4337"),
+        ] {
+            peer_manager.inject_connect_ingoing(&peer_id, addr.parse().unwrap(), None);
+        }
+        peer_manager.events.clear();
+
+        // no peer is pruned within the window
+        peer_manager.heartbeat();
+        assert!(peer_manager.events.is_empty());
+
+        // only the idle peer which isn't whitelisted is pruned after the window
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        peer_manager.report_useful_gossip(&useful);
+        peer_manager.heartbeat();
+        assert!(matches!(
+            peer_manager.events.pop_front(),
+            Some(PeerManagerEvent::DisconnectPeer(id, GoodbyeReason::IrrelevantNetwork))
+                if id == idle
+        ));
+        assert!(peer_manager.events.is_empty());
+
+        // pruning is disabled without an idle timeout
+        peer_manager.idle_timeout = None;
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        peer_manager.heartbeat();
+        assert!(peer_manager.events.is_empty());
     }
 }
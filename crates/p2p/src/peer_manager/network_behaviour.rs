@@ -54,6 +54,10 @@ impl NetworkBehaviour for PeerManager {
         _local_addr: &libp2p::Multiaddr,
         _remote_addr: &libp2p::Multiaddr,
     ) -> Result<libp2p::swarm::THandler<Self>, libp2p::swarm::ConnectionDenied> {
+        if self.is_banned(&peer_id) {
+            return Err(libp2p::swarm::ConnectionDenied::new("Peer is banned"));
+        }
+
         // check if whitelist exists and if the peer is in the whitelist
         if !self.peers_whitelist.is_empty() &&
             self.peers_whitelist.iter().filter(|enr| enr.peer_id() == peer_id).count() == 0
@@ -71,6 +75,10 @@ impl NetworkBehaviour for PeerManager {
         _addr: &libp2p::Multiaddr,
         _role_override: libp2p::core::Endpoint,
     ) -> Result<libp2p::swarm::THandler<Self>, libp2p::swarm::ConnectionDenied> {
+        if self.is_banned(&peer_id) {
+            return Err(libp2p::swarm::ConnectionDenied::new("Peer is banned"));
+        }
+
         // check if whitelist exists and if the peer is in the whitelist
         if !self.peers_whitelist.is_empty() &&
             self.peers_whitelist.iter().filter(|enr| enr.peer_id() == peer_id).count() == 0
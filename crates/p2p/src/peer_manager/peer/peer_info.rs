@@ -14,6 +14,10 @@ pub struct PeerInfo {
     metadata: Option<MetaData>,
     /// Connection direction (ingoing or outgoing).
     connection_direction: Option<ConnectionDirection>,
+    /// Address the peer was last seen at.
+    seen_address: Option<Multiaddr>,
+    /// Score of the peer.
+    score: f64,
 }
 
 impl PeerInfo {
@@ -37,6 +41,18 @@ impl PeerInfo {
         &self.connection_direction
     }
 
+    pub fn seen_address(&self) -> &Option<Multiaddr> {
+        &self.seen_address
+    }
+
+    pub fn score(&self) -> f64 {
+        self.score
+    }
+
+    pub fn set_score(&mut self, score: f64) {
+        self.score = score;
+    }
+
     pub fn is_connected(&self) -> bool {
         matches!(self.connection_status, PeerConnectionStatus::Connected)
     }
@@ -79,7 +95,7 @@ impl PeerInfo {
         Ok(())
     }
 
-    pub fn connect_ingoing(&mut self, _multiaddr: Multiaddr) {
+    pub fn connect_ingoing(&mut self, multiaddr: Multiaddr) {
         match &mut self.connection_status {
             PeerConnectionStatus::Connected |
             PeerConnectionStatus::Disconnected |
@@ -88,11 +104,12 @@ impl PeerInfo {
             PeerConnectionStatus::Unknown => {
                 self.connection_status = PeerConnectionStatus::Connected;
                 self.connection_direction = Some(ConnectionDirection::Incoming);
+                self.seen_address = Some(multiaddr);
             }
         }
     }
 
-    pub fn connect_outgoing(&mut self, _multiaddr: Multiaddr) {
+    pub fn connect_outgoing(&mut self, multiaddr: Multiaddr) {
         match &mut self.connection_status {
             PeerConnectionStatus::Connected |
             PeerConnectionStatus::Disconnected |
@@ -101,17 +118,27 @@ impl PeerInfo {
             PeerConnectionStatus::Unknown => {
                 self.connection_status = PeerConnectionStatus::Connected;
                 self.connection_direction = Some(ConnectionDirection::Outgoing);
+                self.seen_address = Some(multiaddr);
             }
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ConnectionDirection {
     Incoming,
     Outgoing,
 }
 
+impl std::fmt::Display for ConnectionDirection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConnectionDirection::Incoming => write!(f, "incoming"),
+            ConnectionDirection::Outgoing => write!(f, "outgoing"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub enum PeerConnectionStatus {
     Connected,
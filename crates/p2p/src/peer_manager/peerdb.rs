@@ -1,6 +1,7 @@
 use super::peer::peer_info::{ConnectionDirection, PeerConnectionStatus, PeerInfo};
 use discv5::Enr;
 use libp2p::{Multiaddr, PeerId};
+use silius_primitives::p2p::PeerDetails;
 use std::collections::HashMap;
 use tracing::error;
 
@@ -48,6 +49,28 @@ impl PeerDB {
         )
     }
 
+    pub fn connected_peers(&self) -> impl Iterator<Item = &PeerId> {
+        self.peers.iter().filter(|(_, info)| info.is_connected()).map(|(peer_id, _)| peer_id)
+    }
+
+    pub fn connected_peers_details(&self) -> Vec<PeerDetails> {
+        self.peers
+            .iter()
+            .filter(|(_, info)| info.is_connected())
+            .map(|(peer_id, info)| PeerDetails {
+                peer_id: peer_id.to_string(),
+                multiaddr: info.seen_address().as_ref().map(|addr| addr.to_string()),
+                supported_mempools: info
+                    .metadata()
+                    .as_ref()
+                    .map(|metadata| metadata.mempool_ids())
+                    .unwrap_or_default(),
+                score: info.score(),
+                direction: info.connection_direction().as_ref().map(|dir| dir.to_string()),
+            })
+            .collect()
+    }
+
     pub fn connected_or_dialing_peers(&self) -> impl Iterator<Item = &PeerId> {
         self.peers
             .iter()
@@ -137,3 +160,47 @@ pub enum NewConnectionState {
     Disconnecting,
     Dialing { enr: Option<Enr> },
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::methods::MetaData;
+    use silius_primitives::constants::p2p::MAX_IPFS_CID_LENGTH;
+    use ssz_rs::{List, Vector};
+
+    #[test]
+    fn connected_peers_details() {
+        let mut peer_db = PeerDB::default();
+
+        let peer_id = PeerId::random();
+        let seen_address: Multiaddr = "/ip4/127.0.0.1/tcp/4337".parse().unwrap();
+        peer_db.connect_outgoing(&peer_id, seen_address.clone(), None);
+
+        let mempool_id = "QmdDwVFoEEcgv5qnaTB8ncnXGMnqrhnA5nYpRr4ouWe4AT";
+        let mut id = mempool_id.as_bytes().to_vec();
+        id.resize(MAX_IPFS_CID_LENGTH, 0);
+        let mut supported_mempools = List::default();
+        supported_mempools.push(Vector::try_from(id).unwrap());
+        peer_db
+            .peer_info_mut(&peer_id)
+            .unwrap()
+            .set_metadata(MetaData { seq_number: 1, supported_mempools });
+
+        // peers which are not connected are not reported
+        peer_db.dialing_peer(&PeerId::random(), None);
+        let disconnected = PeerId::random();
+        peer_db.connect_ingoing(&disconnected, seen_address.clone(), None);
+        peer_db.inject_disconnect(&disconnected);
+
+        assert_eq!(
+            peer_db.connected_peers_details(),
+            vec![PeerDetails {
+                peer_id: peer_id.to_string(),
+                multiaddr: Some(seen_address.to_string()),
+                supported_mempools: vec![mempool_id.into()],
+                score: 0.0,
+                direction: Some("outgoing".into()),
+            }]
+        );
+    }
+}
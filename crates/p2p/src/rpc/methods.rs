@@ -13,6 +13,19 @@ pub struct MetaData {
     pub supported_mempools: List<Vector<u8, MAX_IPFS_CID_LENGTH>, MAX_SUPPORTED_MEMPOOLS>,
 }
 
+impl MetaData {
+    /// Ids of the supported mempools (without the zero padding).
+    pub fn mempool_ids(&self) -> Vec<String> {
+        self.supported_mempools
+            .iter()
+            .map(|id| {
+                let len = id.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1);
+                String::from_utf8_lossy(&id[..len]).into_owned()
+            })
+            .collect()
+    }
+}
+
 #[derive(ssz_rs_derive::Serializable, Clone, Debug, PartialEq, Default)]
 pub struct Status {
     pub chain_id: u64,
@@ -13,6 +13,7 @@ use crate::{
         enr_ext::{CombinedPublicKeyExt, EnrExt},
         DiscoveredPeers, Discovery,
     },
+    metrics::record_oversized_user_operation,
     peer_manager::{PeerManager, PeerManagerEvent},
     rpc::{
         methods::{MetaData, MetaDataRequest, Ping, RPCResponse, RequestId, Status},
@@ -47,15 +48,21 @@ use libp2p::{
     Multiaddr, PeerId, Swarm, SwarmBuilder,
 };
 use libp2p_mplex::{MaxBufferBehaviour, MplexConfig};
+use lru::LruCache;
 use silius_primitives::{
-    constants::p2p::{FIND_NODE_QUERY_CLOSEST_PEERS, MAX_IPFS_CID_LENGTH, MAX_SUPPORTED_MEMPOOLS},
+    constants::p2p::{
+        FIND_NODE_QUERY_CLOSEST_PEERS, MAX_IPFS_CID_LENGTH, MAX_SSZ_BYTES_LENGTH,
+        MAX_SUPPORTED_MEMPOOLS, PENDING_GOSSIP_CACHE_SIZE,
+    },
     p2p::NetworkMessage,
     simulation::ValidationConfig,
-    MempoolConfig, UserOperation, VerifiedUserOperation,
+    MempoolConfig, UserOperation, UserOperationHash, VerifiedUserOperation,
 };
 use ssz_rs::{Deserialize, List, Serialize, Vector};
 use std::{
-    env,
+    collections::HashMap,
+    env, io,
+    num::NonZeroUsize,
     sync::Arc,
     task::{Context, Poll},
 };
@@ -119,7 +126,100 @@ pub struct Network {
     network_globals: Arc<NetworkGlobals>,
     // Each entry point address has its own mempool channel.
     mempool_channels: Vec<MempoolChannel>,
-    mempool_configs: Vec<(TopicHash, MempoolConfig)>,
+    mempool_configs: MempoolConfigs,
+    // Minimum number of connected peers before user operations from gossip are pooled.
+    min_peers: usize,
+    // Recently published or received user operations (per canonical mempool), so that duplicates
+    // coming back over gossip don't re-enter validation. Disabled if the cache size is 0.
+    seen_uos: Option<LruCache<(UserOperationHash, TopicHash), ()>>,
+    // Propagation sources of the user operations from gossip awaiting validation, credited with
+    // useful gossip once the mempool accepts (and publishes) the user operation.
+    pending_gossip: LruCache<UserOperationHash, PeerId>,
+    // Goodbye requests in flight, the peer is disconnected once the goodbye has been delivered (or
+    // has failed).
+    pending_goodbyes: HashMap<RequestId, PeerId>,
+}
+
+/// Builds the [ValidationConfig](ValidationConfig) for user operations of the canonical mempool
+fn gossip_validation_config(
+    topic: &TopicHash,
+    mempool_config: &MempoolConfig,
+    ignore_prev: bool,
+) -> Option<ValidationConfig> {
+    ValidationConfig::builder()
+        .gossip()
+        .min_stake(mempool_config.min_stake)
+        .topic(topic.to_string())
+        .ignore_prev(ignore_prev)
+        .build()
+        .map_err(|err| warn!("Invalid validation config for canonical mempool {topic}: {err}"))
+        .ok()
+}
+
+/// Configs of the canonical mempools the node is subscribed to, each mapped to its gossipsub topic
+#[derive(Clone, Debug, Default)]
+pub struct MempoolConfigs {
+    configs: Vec<(TopicHash, MempoolConfig)>,
+    /// Topic of the canonical mempool used for user operations without a canonical mempool
+    default_topic: Option<TopicHash>,
+}
+
+impl MempoolConfigs {
+    /// Creates the mempool configs
+    ///
+    /// # Arguments
+    /// * `configs` - Configs of the canonical mempools with their topics
+    /// * `default_mempool` - Id of the canonical mempool used for user operations without a
+    ///   canonical mempool (the first one if not set)
+    ///
+    /// # Returns
+    /// * `MempoolConfigs` - Error if the default mempool isn't one of the canonical mempools
+    pub fn new(
+        configs: Vec<(TopicHash, MempoolConfig)>,
+        default_mempool: Option<&str>,
+    ) -> Result<Self, String> {
+        let default_topic = match default_mempool {
+            Some(mempool_id) => {
+                let default_topic: TopicHash = topic(mempool_id).into();
+                if !configs.iter().any(|(topic, _)| topic == &default_topic) {
+                    return Err(format!(
+                        "Default mempool {mempool_id} is not one of the canonical mempools"
+                    ));
+                }
+                Some(default_topic)
+            }
+            None => configs.first().map(|(topic, _)| topic.clone()),
+        };
+
+        Ok(Self { configs, default_topic })
+    }
+
+    /// Returns the [ValidationConfig](ValidationConfig) of the canonical mempool of the topic
+    pub fn validation_config(
+        &self,
+        topic: &TopicHash,
+        ignore_prev: bool,
+    ) -> Option<ValidationConfig> {
+        self.configs
+            .iter()
+            .find(|(t, _)| t == topic)
+            .and_then(|(topic, config)| gossip_validation_config(topic, config, ignore_prev))
+    }
+
+    /// Returns the [ValidationConfig](ValidationConfig) of the default canonical mempool
+    pub fn default_validation_config(&self, ignore_prev: bool) -> Option<ValidationConfig> {
+        self.default_topic.as_ref().and_then(|topic| self.validation_config(topic, ignore_prev))
+    }
+
+    /// Returns the [ValidationConfig](ValidationConfig) of the canonical mempool following the one
+    /// of the topic (the next candidate after the validation failed)
+    pub fn next_validation_config(&self, topic: &str) -> Option<ValidationConfig> {
+        self.configs
+            .iter()
+            .skip_while(|(t, _)| t.as_str() != topic)
+            .nth(1)
+            .and_then(|(topic, config)| gossip_validation_config(topic, config, true))
+    }
 }
 
 impl From<Network> for Swarm<Behaviour> {
@@ -181,7 +281,7 @@ impl Network {
                 ));
             }
 
-            m
+            MempoolConfigs::new(m, config.default_mempool.as_deref()).map_err(|e| eyre::eyre!(e))?
         };
         let trusted_peers = config.bootnodes.iter().map(|x| x.public_key().as_peer_id()).collect();
 
@@ -214,7 +314,8 @@ impl Network {
             ))
         };
 
-        let mut gossipsub = create_gossipsub(canonical_mempools).map_err(|e| eyre::anyhow!(e))?;
+        let mut gossipsub = create_gossipsub(canonical_mempools, config.gs_config.clone())
+            .map_err(|e| eyre::anyhow!(e))?;
         for bootnode in &config.bootnodes {
             gossipsub.add_explicit_peer(&bootnode.peer_id());
         }
@@ -225,6 +326,7 @@ impl Network {
             network_globals.clone(),
             config.clone().peers_whitelist,
             config.clone().ips_whitelist,
+            config.idle_peer_timeout,
         );
 
         let mut discovery =
@@ -250,7 +352,19 @@ impl Network {
             .expect("building p2p behaviour failed")
             .build();
 
-        let mut network = Network { swarm, network_globals, mempool_channels, mempool_configs };
+        let mut network = Network {
+            swarm,
+            network_globals,
+            mempool_channels,
+            mempool_configs,
+            min_peers: config.min_peers,
+            seen_uos: NonZeroUsize::new(config.seen_cache_size).map(LruCache::new),
+            pending_gossip: LruCache::new(
+                NonZeroUsize::new(PENDING_GOSSIP_CACHE_SIZE)
+                    .expect("pending gossip cache size is not zero"),
+            ),
+            pending_goodbyes: HashMap::new(),
+        };
 
         network.start(&config).await?;
 
@@ -292,52 +406,75 @@ impl Network {
         }
     }
 
+    /// Marks the user operation as seen on the topic and returns whether it was already seen.
+    pub fn check_and_mark_seen(&mut self, uo: &VerifiedUserOperation, topic: &TopicHash) -> bool {
+        let chain_id = self.network_globals.chain_spec().chain.id();
+        match self.seen_uos.as_mut() {
+            Some(seen_uos) => {
+                let uo_hash = uo.clone().user_operation().hash(&uo.entry_point(), chain_id);
+                seen_uos.put((uo_hash, topic.clone()), ()).is_some()
+            }
+            None => false,
+        }
+    }
+
     /// handle gossipsub event
-    fn handle_gossipsub_event(&self, event: Box<gossipsub::Event>) -> Option<NetworkEvent> {
+    fn handle_gossipsub_event(&mut self, event: Box<gossipsub::Event>) -> Option<NetworkEvent> {
         match *event {
             gossipsub::Event::Message { propagation_source, message_id, message } => {
                 let uo = match VerifiedUserOperation::deserialize(message.data.as_ref()) {
                     Ok(uo) => uo,
                     Err(e) => {
                         debug!("Failed to deserialize user operations: {:?}", e);
+                        self.swarm
+                            .behaviour_mut()
+                            .peer_manager
+                            .report_bad_gossip(&propagation_source);
                         return None;
                     }
                 };
 
-                self.mempool_channels.iter().find_map(|(ep, mempool_sender, _)| {
-                    if *ep == uo.entry_point() {
-                        self.mempool_configs.iter().find_map(|(topic, canonical_mempool_config)| {
-                            if topic == &message.topic {
-                                let uo = uo.clone().user_operation();
-
-                                mempool_sender
-                                    .unbounded_send(NetworkMessage::Validate {
-                                        user_operation: UserOperation::from_user_operation_signed(
-                                            uo.hash(ep, self.network_globals.chain_spec().chain.id()),
-                                            uo,
-                                        ),
-                                        validation_config: ValidationConfig {
-                                            min_stake: Some(canonical_mempool_config.min_stake),
-                                            min_unstake_delay: None,
-                                            topic: Some(message.topic.to_string()),
-                                            ignore_prev: false,
-                                        }
-                                    })
-                                    .expect("mempool channel should be open all the time");
-
-                                Some(())
-                            } else {
-                                warn!("User operation from p2p is using unsupported canonical mempool {}" , message.topic);
-                                None
-                            }
-                        });
-
-                        Some(())
-                    } else {
-                        warn!("User operation from p2p is using unsupported entry point {ep:?}");
-                        None
+                let connected_peers = self.network_globals.connected_peers();
+                if connected_peers < self.min_peers {
+                    debug!(
+                        "Dropping user operation from p2p: {connected_peers} connected peers, at least {} required",
+                        self.min_peers
+                    );
+                } else if self.check_and_mark_seen(&uo, &message.topic) {
+                    debug!(
+                        "Dropping user operation from p2p already seen on {}: {uo:?}",
+                        message.topic
+                    );
+                } else if let Some((ep, mempool_sender, _)) =
+                    self.mempool_channels.iter().find(|(ep, _, _)| *ep == uo.entry_point())
+                {
+                    match self.mempool_configs.validation_config(&message.topic, false) {
+                        Some(validation_config) => {
+                            let uo = uo.clone().user_operation();
+                            let uo_hash = uo.hash(ep, self.network_globals.chain_spec().chain.id());
+                            self.pending_gossip.put(uo_hash, propagation_source);
+
+                            mempool_sender
+                                .unbounded_send(NetworkMessage::Validate {
+                                    user_operation: UserOperation::from_user_operation_signed(
+                                        uo_hash, uo,
+                                    ),
+                                    validation_config,
+                                })
+                                .expect("mempool channel should be open all the time");
+                        }
+                        None => warn!(
+                            "User operation from p2p is using unsupported canonical mempool {}",
+                            message.topic
+                        ),
                     }
-                });
+                } else {
+                    warn!(
+                        "User operation from p2p is using unsupported entry point {:?}",
+                        uo.entry_point()
+                    );
+                    self.swarm.behaviour_mut().peer_manager.report_bad_gossip(&propagation_source);
+                }
 
                 let message = PubsubMessage::UserOperation(uo);
 
@@ -360,6 +497,14 @@ impl Network {
 
     /// handle reqrep event
     fn handle_rpc_event(&mut self, event: RPCEvent) -> Option<NetworkEvent> {
+        if let RPCEvent::Response { req_id, .. } | RPCEvent::OutboundFailure { req_id, .. } = &event
+        {
+            if let Some(peer_id) = self.pending_goodbyes.remove(req_id) {
+                let _ = self.swarm.disconnect_peer_id(peer_id);
+                return None;
+            }
+        }
+
         match event {
             RPCEvent::Request { peer_id, request, sender, .. } => match request {
                 InboundRequest::Ping(ping) => {
@@ -431,7 +576,12 @@ impl Network {
                 self.send_request(&peer_id, OutboundRequest::MetaData(MetaDataRequest));
                 None
             }
-            _ => None,
+            PeerManagerEvent::DisconnectPeer(peer_id, reason) => {
+                debug!("Disconnecting peer {peer_id} with reason {reason:?}");
+                let req_id = self.send_request(&peer_id, OutboundRequest::Goodbye(reason));
+                self.pending_goodbyes.insert(req_id, peer_id);
+                None
+            }
         }
     }
 
@@ -448,6 +598,12 @@ impl Network {
                     } => {
                         info!("Received user operation (verified at {verified_at_block_hash:?}) to gossip over p2p: {user_operation:?}");
 
+                        // the peer which sent the user operation over gossip is credited now that
+                        // the mempool accepted it
+                        if let Some(peer_id) = self.pending_gossip.pop(&user_operation.hash) {
+                            self.swarm.behaviour_mut().peer_manager.report_useful_gossip(&peer_id);
+                        }
+
                         let user_op = VerifiedUserOperation::new(
                             user_operation.user_operation.clone(),
                             *ep,
@@ -456,46 +612,27 @@ impl Network {
 
                         if let Some(topic) = validation_config.topic {
                             uos_received.push((user_op, TopicHash::from_raw(topic)));
-                        } else if let Some((first_mempool_topic, first_mempool_config)) =
-                            self.mempool_configs.first()
+                        } else if let Some(validation_config) =
+                            self.mempool_configs.default_validation_config(true)
                         {
                             mempool_sender
                                 .unbounded_send(NetworkMessage::Validate {
                                     user_operation,
-                                    validation_config: ValidationConfig {
-                                        min_stake: Some(first_mempool_config.min_stake),
-                                        min_unstake_delay: None,
-                                        topic: Some(first_mempool_topic.to_string()),
-                                        ignore_prev: true,
-                                    },
+                                    validation_config,
                                 })
                                 .expect("mempool channel should be open all the time");
                         }
                     }
                     NetworkMessage::FindNewMempool { user_operation, topic } => {
-                        let mut next = false;
-
-                        for (canonical_mempool_topic, canonical_mempool_config) in
-                            self.mempool_configs.iter()
+                        if let Some(validation_config) =
+                            self.mempool_configs.next_validation_config(&topic)
                         {
-                            if next {
-                                mempool_sender
-                                    .unbounded_send(NetworkMessage::Validate {
-                                        user_operation,
-                                        validation_config: ValidationConfig {
-                                            min_stake: Some(canonical_mempool_config.min_stake),
-                                            min_unstake_delay: None,
-                                            topic: Some(canonical_mempool_topic.to_string()),
-                                            ignore_prev: true,
-                                        },
-                                    })
-                                    .expect("mempool channel should be open all the time");
-                                break;
-                            }
-
-                            if topic == canonical_mempool_topic.to_string() {
-                                next = true;
-                            }
+                            mempool_sender
+                                .unbounded_send(NetworkMessage::Validate {
+                                    user_operation,
+                                    validation_config,
+                                })
+                                .expect("mempool channel should be open all the time");
                         }
                     }
                     NetworkMessage::NewBlock { block_hash, block_number } => {
@@ -511,6 +648,7 @@ impl Network {
         }
 
         for (uo, topic) in uos_received {
+            self.check_and_mark_seen(&uo, &topic);
             match self.publish(uo.clone(), topic) {
                 Ok(_) => {}
                 Err(err) => match err {
@@ -557,18 +695,37 @@ impl Network {
         futures::future::poll_fn(|cx| self.poll_network(cx)).await
     }
 
+    /// Return the shared network state.
+    pub fn network_globals(&self) -> Arc<NetworkGlobals> {
+        self.network_globals.clone()
+    }
+
     pub fn local_peer_id(&self) -> &PeerId {
         self.swarm.local_peer_id()
     }
 
-    /// Publish a gossipsub message.
+    /// Publish a gossipsub message. User operations with a byte field exceeding the maximum length
+    /// of the SSZ encoding are rejected (`MessageTooLarge`).
     pub fn publish(
         &mut self,
         user_op: VerifiedUserOperation,
         topic_hash: TopicHash,
     ) -> Result<MessageId, PublishError> {
+        if let Some((field, len)) = user_op.clone().user_operation().ssz_oversized_field() {
+            warn!(
+                "Not gossiping user operation with {field} of {len} bytes (SSZ limit {MAX_SSZ_BYTES_LENGTH})"
+            );
+            record_oversized_user_operation(field);
+            return Err(PublishError::MessageTooLarge);
+        }
+
         let mut buf = Vec::new();
-        let _ = user_op.serialize(&mut buf).expect("ssz of user ops serialization failed");
+        user_op.serialize(&mut buf).map_err(|err| {
+            PublishError::TransformFailed(io::Error::new(
+                io::ErrorKind::InvalidData,
+                err.to_string(),
+            ))
+        })?;
         self.swarm.behaviour_mut().gossipsub.publish(topic_hash, buf)
     }
 
@@ -601,3 +758,50 @@ impl Network {
         self.swarm.behaviour_mut().rpc.send_response(response_channel, response)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::U256;
+
+    #[test]
+    fn mempool_configs_per_topic() {
+        let mempool_a: TopicHash = topic("mempool_a").into();
+        let mempool_b: TopicHash = topic("mempool_b").into();
+        let configs = vec![
+            (mempool_a.clone(), MempoolConfig::dev().with_id("mempool_a".into())),
+            (
+                mempool_b.clone(),
+                MempoolConfig { min_stake: U256::from(100), ..MempoolConfig::dev() }
+                    .with_id("mempool_b".into()),
+            ),
+        ];
+
+        let mempool_configs = MempoolConfigs::new(configs.clone(), None).unwrap();
+
+        // user operations are routed to the config of their topic
+        let config = mempool_configs.validation_config(&mempool_a, false).unwrap();
+        assert_eq!(config.topic, Some(mempool_a.to_string()));
+        assert_eq!(config.min_stake, Some(U256::zero()));
+        let config = mempool_configs.validation_config(&mempool_b, false).unwrap();
+        assert_eq!(config.topic, Some(mempool_b.to_string()));
+        assert_eq!(config.min_stake, Some(U256::from(100)));
+        assert!(mempool_configs.validation_config(&topic("mempool_c").into(), false).is_none());
+
+        // the first canonical mempool is the default one
+        let config = mempool_configs.default_validation_config(true).unwrap();
+        assert_eq!(config.topic, Some(mempool_a.to_string()));
+
+        // the next canonical mempool is used after the validation failed
+        let config = mempool_configs.next_validation_config(mempool_a.as_str()).unwrap();
+        assert_eq!(config.topic, Some(mempool_b.to_string()));
+        assert!(mempool_configs.next_validation_config(mempool_b.as_str()).is_none());
+
+        // the default canonical mempool is configurable
+        let mempool_configs = MempoolConfigs::new(configs.clone(), Some("mempool_b")).unwrap();
+        let config = mempool_configs.default_validation_config(true).unwrap();
+        assert_eq!(config.topic, Some(mempool_b.to_string()));
+        assert_eq!(config.min_stake, Some(U256::from(100)));
+        assert!(MempoolConfigs::new(configs, Some("mempool_c")).is_err());
+    }
+}
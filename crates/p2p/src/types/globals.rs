@@ -3,7 +3,7 @@ use discv5::Enr;
 use ethers::types::H256;
 use libp2p::{Multiaddr, PeerId};
 use parking_lot::RwLock;
-use silius_primitives::chain::ChainSpec;
+use silius_primitives::{chain::ChainSpec, p2p::PeerDetails};
 
 pub struct NetworkGlobals {
     /// The local ENR of the node.
@@ -68,6 +68,14 @@ impl NetworkGlobals {
         self.chain_spec.read().clone()
     }
 
+    pub fn connected_peers(&self) -> usize {
+        self.peers.read().connected_peers().count()
+    }
+
+    pub fn connected_peers_details(&self) -> Vec<PeerDetails> {
+        self.peers.read().connected_peers_details()
+    }
+
     pub fn connected_or_dialing_peers(&self) -> usize {
         self.peers.read().connected_or_dialing_peers().count()
     }
@@ -1,8 +1,15 @@
 use super::topics::{create_whitelist_filter, topic};
-use crate::{config::gossipsub_config, service::behaviour::Gossipsub};
-use libp2p::gossipsub::{DataTransform, Message, MessageAuthenticity, RawMessage, TopicHash};
-use silius_primitives::{constants::p2p::GOSSIP_MAX_SIZE, VerifiedUserOperation};
+use crate::service::behaviour::Gossipsub;
+use libp2p::gossipsub::{
+    self, DataTransform, Message, MessageAuthenticity, MessageId, RawMessage, TopicHash,
+};
+use sha2::{Digest, Sha256};
+use silius_primitives::{
+    constants::p2p::{GOSSIP_MAX_SIZE, MESSAGE_DOMAIN_INVALID_SNAPPY, MESSAGE_DOMAIN_VALID_SNAPPY},
+    VerifiedUserOperation,
+};
 use snap::raw::{decompress_len, Decoder, Encoder};
+use ssz_rs::Deserialize;
 use std::io::{Error, ErrorKind};
 
 #[derive(Debug, PartialEq)]
@@ -71,10 +78,43 @@ impl DataTransform for SnappyTransform {
     }
 }
 
-/// Creates a gossipsub instance with the given mempool ids
-pub fn create_gossipsub(mempool_ids: Vec<String>) -> Result<Gossipsub, String> {
+/// Computes the id of a gossipsub message. The id is content-addressed by the user operation and
+/// its entry point (the chain is given by the topic), so identical user operations received from
+/// different peers (e.g., verified at different blocks) share the id and are deduplicated.
+/// Messages which aren't valid user operations are addressed by their whole content.
+pub fn message_id(message: &Message) -> MessageId {
+    let (domain, content) = match VerifiedUserOperation::deserialize(message.data.as_ref()) {
+        Ok(uo) => {
+            let entry_point = uo.entry_point();
+            (
+                MESSAGE_DOMAIN_VALID_SNAPPY,
+                [uo.user_operation().pack().as_ref(), entry_point.as_bytes()].concat(),
+            )
+        }
+        Err(_) => (MESSAGE_DOMAIN_INVALID_SNAPPY, message.data.clone()),
+    };
+
+    let topic_bytes = message.topic.as_str().as_bytes();
+    let topic_len_bytes = topic_bytes.len().to_le_bytes();
+
+    let mut vec: Vec<u8> = Vec::with_capacity(
+        domain.len() + topic_len_bytes.len() + topic_bytes.len() + content.len(),
+    );
+    vec.extend_from_slice(&domain);
+    vec.extend_from_slice(&topic_len_bytes);
+    vec.extend_from_slice(topic_bytes);
+    vec.extend_from_slice(&content);
+
+    Sha256::digest(vec)[..20].into()
+}
+
+/// Creates a gossipsub instance with the given mempool ids and gossipsub config (mesh parameters
+/// are taken from the config)
+pub fn create_gossipsub(
+    mempool_ids: Vec<String>,
+    config: gossipsub::Config,
+) -> Result<Gossipsub, String> {
     let filter = create_whitelist_filter(mempool_ids.clone());
-    let config = gossipsub_config();
     let snappy_transform = SnappyTransform::new(GOSSIP_MAX_SIZE);
     let mut gossipsub = Gossipsub::new_with_subscription_filter_and_transform(
         MessageAuthenticity::Anonymous,
@@ -90,3 +130,69 @@ pub fn create_gossipsub(mempool_ids: Vec<String>) -> Result<Gossipsub, String> {
 
     Ok(gossipsub)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::{Address, U256};
+    use silius_primitives::UserOperationSigned;
+    use ssz_rs::Serialize;
+
+    fn message(uo: &VerifiedUserOperation, mempool_id: &str) -> Message {
+        let mut data = Vec::new();
+        uo.serialize(&mut data).unwrap();
+        Message { source: None, data, sequence_number: None, topic: topic(mempool_id).into() }
+    }
+
+    #[test]
+    fn topic_format() {
+        assert_eq!(
+            topic("QmdDwVFoEEcgv5qnaTB8ncnXGMnqrhnA5nYpRr4ouWe4AT").to_string(),
+            "/account_abstraction/QmdDwVFoEEcgv5qnaTB8ncnXGMnqrhnA5nYpRr4ouWe4AT/user_operations/ssz_snappy"
+        );
+    }
+
+    #[test]
+    fn message_id_of_identical_user_operations() {
+        let entry_point = Address::random();
+        let uo = UserOperationSigned { sender: Address::random(), ..Default::default() };
+        let verified = VerifiedUserOperation::new(uo.clone(), entry_point, U256::from(1));
+
+        // identical user operations verified at different blocks
+        assert_eq!(
+            message_id(&message(&verified, "mempool")),
+            message_id(&message(&verified.clone(), "mempool"))
+        );
+        assert_eq!(
+            message_id(&message(&verified, "mempool")),
+            message_id(&message(
+                &VerifiedUserOperation::new(uo.clone(), entry_point, U256::from(2)),
+                "mempool"
+            ))
+        );
+
+        // different user operation, entry point or topic
+        assert_ne!(
+            message_id(&message(&verified, "mempool")),
+            message_id(&message(
+                &VerifiedUserOperation::new(
+                    UserOperationSigned { nonce: U256::from(1), ..uo.clone() },
+                    entry_point,
+                    U256::from(1)
+                ),
+                "mempool"
+            ))
+        );
+        assert_ne!(
+            message_id(&message(&verified, "mempool")),
+            message_id(&message(
+                &VerifiedUserOperation::new(uo, Address::random(), U256::from(1)),
+                "mempool"
+            ))
+        );
+        assert_ne!(
+            message_id(&message(&verified, "mempool")),
+            message_id(&message(&verified, "other_mempool"))
+        );
+    }
+}
@@ -3,9 +3,12 @@ use futures::channel::mpsc::unbounded;
 use silius_p2p::{
     config::{gossipsub_config, Config},
     listen_addr::{ListenAddr, ListenAddress},
-    service::{Network, NetworkEvent},
+    service::{MempoolChannel, Network, NetworkEvent},
+};
+use silius_primitives::{
+    chain::ChainSpec,
+    constants::p2p::{SEEN_CACHE_SIZE, TARGET_PEERS},
 };
-use silius_primitives::{chain::ChainSpec, constants::p2p::TARGET_PEERS};
 use std::{
     net::{Ipv4Addr, TcpListener},
     time::Duration,
@@ -30,7 +33,11 @@ pub fn get_available_port() -> Option<u16> {
     Some(unused_port)
 }
 
-async fn build_p2p_instance(bootnode: Option<Enr>) -> eyre::Result<Network> {
+async fn build_p2p_instance(
+    bootnode: Option<Enr>,
+    mempool_channels: Vec<MempoolChannel>,
+    min_peers: usize,
+) -> eyre::Result<Network> {
     let dir = TempDir::new().unwrap();
     let node_key_file = dir.path().join("node_key");
     let node_enr_file = dir.path().join("node_enr");
@@ -58,27 +65,41 @@ async fn build_p2p_instance(bootnode: Option<Enr>) -> eyre::Result<Network> {
         discv5_config: discv5::ConfigBuilder::new(listen_addr.to_listen_config()).build(),
         chain_spec: chain_spec.clone(),
         target_peers: TARGET_PEERS,
+        min_peers,
+        seen_cache_size: SEEN_CACHE_SIZE,
+        default_mempool: None,
+        idle_peer_timeout: None,
         bootnodes: if let Some(bootnode) = bootnode { vec![bootnode] } else { vec![] },
         peers_whitelist: vec![],
         ips_whitelist: vec![],
     };
 
-    let (_, receiver) = unbounded();
-    let (sender, _) = unbounded();
-
-    let network = Network::new(
-        config,
-        (Default::default(), Default::default()),
-        vec![(Default::default(), sender, receiver)],
-    )
-    .await?;
+    let network =
+        Network::new(config, (Default::default(), Default::default()), mempool_channels).await?;
 
     Ok(network)
 }
 
+fn dummy_mempool_channels() -> Vec<MempoolChannel> {
+    let (_, receiver) = unbounded();
+    let (sender, _) = unbounded();
+    vec![(Default::default(), sender, receiver)]
+}
+
 pub async fn build_connnected_p2p_pair() -> eyre::Result<(Network, Network)> {
-    let mut peer1 = build_p2p_instance(None).await?;
-    let mut peer2 = build_p2p_instance(Some(peer1.local_enr())).await?;
+    build_connnected_p2p_pair_with_mempool_channels(dummy_mempool_channels(), 0).await
+}
+
+/// Builds two connected p2p instances, the second one using the given mempool channels and
+/// minimum peer count.
+#[allow(dead_code)]
+pub async fn build_connnected_p2p_pair_with_mempool_channels(
+    mempool_channels: Vec<MempoolChannel>,
+    min_peers: usize,
+) -> eyre::Result<(Network, Network)> {
+    let mut peer1 = build_p2p_instance(None, dummy_mempool_channels(), 0).await?;
+    let mut peer2 =
+        build_p2p_instance(Some(peer1.local_enr()), mempool_channels, min_peers).await?;
 
     // let the two nodes set up listeners
     let peer1_fut = async {
@@ -1,12 +1,27 @@
 mod common;
 
-use crate::common::build_connnected_p2p_pair;
-use ethers::types::H160;
+use crate::common::{build_connnected_p2p_pair, build_connnected_p2p_pair_with_mempool_channels};
+use ethers::types::{H160, U256};
+use futures::channel::mpsc::unbounded;
+use libp2p::gossipsub::{PublishError, TopicHash};
 use silius_p2p::{
+    config::{gossipsub_config_with_mesh_params, GossipsubMeshParams},
     service::NetworkEvent,
-    types::{pubsub::PubsubMessage, topics::topic},
+    types::{
+        pubsub::{create_gossipsub, PubsubMessage},
+        topics::topic,
+    },
+};
+use silius_primitives::{
+    chain::ChainSpec,
+    constants::{
+        entry_point::ADDRESS,
+        p2p::{BAD_GOSSIP_PENALTY, MAX_SSZ_BYTES_LENGTH, MIN_PEER_SCORE},
+    },
+    p2p::NetworkMessage,
+    simulation::ValidationConfig,
+    UserOperation, UserOperationSigned, VerifiedUserOperation,
 };
-use silius_primitives::{chain::ChainSpec, constants::entry_point::ADDRESS, VerifiedUserOperation};
 use std::{str::FromStr, time::Duration};
 
 #[tokio::test]
@@ -61,3 +76,342 @@ async fn pubsub_msg() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn pubsub_msg_ssz_limit() -> eyre::Result<()> {
+    let chain_spec = ChainSpec::dev();
+    let (mut peer1, mut peer2) = build_connnected_p2p_pair().await?;
+
+    let mempool_id = chain_spec.canonical_mempools.first().unwrap();
+    peer1.subscribe(&mempool_id)?;
+    peer2.subscribe(&mempool_id)?;
+
+    // byte fields at the limit are gossiped, the ones above it are rejected explicitly
+    let user_op = |len: usize| {
+        VerifiedUserOperation::new(
+            UserOperationSigned::default().call_data(vec![1; len].into()),
+            H160::from_str(ADDRESS).unwrap(),
+            Default::default(),
+        )
+    };
+    let user_op_at_limit = user_op(MAX_SSZ_BYTES_LENGTH);
+
+    let sender_fut = async {
+        loop {
+            match peer1.next_event().await {
+                NetworkEvent::Subscribe { .. } => {
+                    let topic_hash: TopicHash = topic(&mempool_id).into();
+                    assert!(matches!(
+                        peer1.publish(user_op(MAX_SSZ_BYTES_LENGTH + 1), topic_hash.clone()),
+                        Err(PublishError::MessageTooLarge)
+                    ));
+                    peer1.publish(user_op_at_limit.clone(), topic_hash).unwrap();
+                }
+                _ => {}
+            }
+        }
+    };
+
+    let receiver_fut = async {
+        loop {
+            match peer2.next_event().await {
+                NetworkEvent::PubsubMessage { message, .. } => {
+                    assert_eq!(message, PubsubMessage::UserOperation(user_op_at_limit.clone()));
+                    return;
+                }
+                _ => {}
+            }
+        }
+    };
+
+    tokio::select! {
+        _ = sender_fut => {}
+        _ = receiver_fut => {}
+        _ = tokio::time::sleep(Duration::from_secs(30)) => {
+            panic!("Future timed out");
+        }
+    }
+
+    Ok(())
+}
+
+/// Gossips `count` distinct user operations from the first peer to the second one (requiring
+/// `min_peers` connected peers) and returns the number of validation requests that reached the
+/// second peer's mempool channel.
+async fn gossip_user_operations(count: u64, min_peers: usize) -> eyre::Result<u64> {
+    let chain_spec = ChainSpec::dev();
+    let ep = H160::from_str(ADDRESS)?;
+
+    // nobody consumes the validation requests, mimicking a saturated validation pool
+    let (network_sender, mut network_receiver) = unbounded();
+    let (_mempool_sender, mempool_receiver) = unbounded();
+    let (mut peer1, mut peer2) = build_connnected_p2p_pair_with_mempool_channels(
+        vec![(ep, network_sender, mempool_receiver)],
+        min_peers,
+    )
+    .await?;
+
+    let mempool_id = chain_spec.canonical_mempools.first().unwrap();
+    peer1.subscribe(&mempool_id)?;
+    peer2.subscribe(&mempool_id)?;
+
+    let sender_fut = async {
+        let mut published = false;
+        loop {
+            match peer1.next_event().await {
+                NetworkEvent::Subscribe { .. } if !published => {
+                    let topic_hash = topic(&mempool_id).into();
+                    for nonce in 0..count {
+                        let user_op = VerifiedUserOperation::new(
+                            UserOperationSigned { nonce: U256::from(nonce), ..Default::default() },
+                            ep,
+                            Default::default(),
+                        );
+                        peer1.publish(user_op, topic_hash.clone()).unwrap();
+                    }
+                    published = true;
+                }
+                _ => {}
+            }
+        }
+    };
+
+    let receiver_fut = async {
+        let mut received = 0;
+        loop {
+            if let NetworkEvent::PubsubMessage { .. } = peer2.next_event().await {
+                received += 1;
+                if received == count {
+                    return;
+                }
+            }
+        }
+    };
+
+    tokio::select! {
+        _ = sender_fut => {}
+        _ = receiver_fut => {}
+        _ = tokio::time::sleep(Duration::from_secs(30)) => {
+            panic!("Swarm stopped processing events while receiving gossip");
+        }
+    }
+
+    let mut validate_requests = 0;
+    while let Ok(Some(msg)) = network_receiver.try_next() {
+        assert!(matches!(msg, NetworkMessage::Validate { .. }));
+        validate_requests += 1;
+    }
+
+    Ok(validate_requests)
+}
+
+#[tokio::test]
+async fn pubsub_flood() -> eyre::Result<()> {
+    assert_eq!(gossip_user_operations(500, 0).await?, 500);
+    Ok(())
+}
+
+#[tokio::test]
+async fn pubsub_min_peers() -> eyre::Result<()> {
+    // only one peer is connected, so nothing gets pooled
+    assert_eq!(gossip_user_operations(5, 2).await?, 0);
+    assert_eq!(gossip_user_operations(5, 1).await?, 5);
+    Ok(())
+}
+
+#[tokio::test]
+async fn pubsub_seen_cache() -> eyre::Result<()> {
+    let chain_spec = ChainSpec::dev();
+    let ep = H160::from_str(ADDRESS)?;
+
+    let (network_sender, mut network_receiver) = unbounded();
+    let (mempool_sender, mempool_receiver) = unbounded();
+    let (mut peer1, mut peer2) = build_connnected_p2p_pair_with_mempool_channels(
+        vec![(ep, network_sender, mempool_receiver)],
+        0,
+    )
+    .await?;
+
+    let mempool_id = chain_spec.canonical_mempools.first().unwrap();
+    peer1.subscribe(&mempool_id)?;
+    peer2.subscribe(&mempool_id)?;
+    let topic_hash: TopicHash = topic(&mempool_id).into();
+
+    let uo = UserOperationSigned { nonce: U256::from(1), ..Default::default() };
+    let fresh_uo = UserOperationSigned { nonce: U256::from(2), ..Default::default() };
+
+    // the second peer publishes the user operation, the first peer can't gossip it back (the
+    // gossipsub message id doesn't depend on the block it was verified at), only a fresh one
+    let sender_fut = async {
+        let mut published = false;
+        loop {
+            match peer1.next_event().await {
+                NetworkEvent::PubsubMessage { .. } if !published => {
+                    let user_op = VerifiedUserOperation::new(uo.clone(), ep, U256::from(1));
+                    assert!(matches!(
+                        peer1.publish(user_op, topic_hash.clone()),
+                        Err(PublishError::Duplicate)
+                    ));
+                    let user_op = VerifiedUserOperation::new(fresh_uo.clone(), ep, U256::from(1));
+                    peer1.publish(user_op, topic_hash.clone()).unwrap();
+                    published = true;
+                }
+                _ => {}
+            }
+        }
+    };
+
+    let receiver_fut = async {
+        let mut published = false;
+        loop {
+            match peer2.next_event().await {
+                NetworkEvent::Subscribe { .. } if !published => {
+                    mempool_sender
+                        .unbounded_send(NetworkMessage::Publish {
+                            user_operation: UserOperation::from_user_operation_signed(
+                                uo.hash(&ep, chain_spec.chain.id()),
+                                uo.clone(),
+                            ),
+                            verified_at_block_hash: Default::default(),
+                            validation_config: ValidationConfig {
+                                topic: Some(topic_hash.to_string()),
+                                ..Default::default()
+                            },
+                        })
+                        .unwrap();
+                    published = true;
+                }
+                NetworkEvent::PubsubMessage { .. } => return,
+                _ => {}
+            }
+        }
+    };
+
+    tokio::select! {
+        _ = sender_fut => {}
+        _ = receiver_fut => {}
+        _ = tokio::time::sleep(Duration::from_secs(30)) => {
+            panic!("Future timed out");
+        }
+    }
+
+    // the published user operation is already seen by the second peer (e.g., gossiped back by a
+    // peer once it left the gossipsub duplicate cache), the fresh one was marked on receipt
+    for uo in [uo, fresh_uo.clone()] {
+        let user_op = VerifiedUserOperation::new(uo, ep, U256::from(2));
+        assert!(peer2.check_and_mark_seen(&user_op, &topic_hash));
+    }
+
+    // only the fresh user operation is validated
+    let mut validated = vec![];
+    while let Ok(Some(msg)) = network_receiver.try_next() {
+        if let NetworkMessage::Validate { user_operation, .. } = msg {
+            validated.push(user_operation.user_operation);
+        }
+    }
+    assert_eq!(validated, vec![fresh_uo]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn pubsub_bad_gossip_disconnects_peer() -> eyre::Result<()> {
+    let chain_spec = ChainSpec::dev();
+    let ep = H160::from_str(ADDRESS)?;
+
+    // the second peer only supports the default entry point
+    let (network_sender, _network_receiver) = unbounded();
+    let (_mempool_sender, mempool_receiver) = unbounded();
+    let (mut peer1, mut peer2) = build_connnected_p2p_pair_with_mempool_channels(
+        vec![(ep, network_sender, mempool_receiver)],
+        0,
+    )
+    .await?;
+
+    let mempool_id = chain_spec.canonical_mempools.first().unwrap();
+    peer1.subscribe(&mempool_id)?;
+    peer2.subscribe(&mempool_id)?;
+
+    let peer1_id = peer1.local_peer_id().clone();
+    let unsupported_ep = H160::random();
+    let count = (-MIN_PEER_SCORE / BAD_GOSSIP_PENALTY) as u64 + 1;
+
+    let sender_fut = async {
+        let mut published = false;
+        loop {
+            match peer1.next_event().await {
+                NetworkEvent::Subscribe { .. } if !published => {
+                    let topic_hash: TopicHash = topic(&mempool_id).into();
+                    for nonce in 0..count {
+                        let user_op = VerifiedUserOperation::new(
+                            UserOperationSigned { nonce: U256::from(nonce), ..Default::default() },
+                            unsupported_ep,
+                            Default::default(),
+                        );
+                        peer1.publish(user_op, topic_hash.clone()).unwrap();
+                    }
+                    published = true;
+                }
+                _ => {}
+            }
+        }
+    };
+
+    // the gossip for the unsupported entry point gets the first peer disconnected
+    let receiver_fut = async {
+        loop {
+            match peer2.next_event().await {
+                NetworkEvent::PeerDisconnected(peer_id) if peer_id == peer1_id => return,
+                _ => {}
+            }
+        }
+    };
+
+    tokio::select! {
+        _ = sender_fut => {}
+        _ = receiver_fut => {}
+        _ = tokio::time::sleep(Duration::from_secs(30)) => {
+            panic!("Peer sending bad gossip wasn't disconnected");
+        }
+    }
+
+    let score = peer2
+        .network_globals()
+        .peers
+        .read()
+        .peer_info(&peer1_id)
+        .map(|peer_info| peer_info.score());
+    assert!(score.is_some_and(|score| score < MIN_PEER_SCORE));
+
+    Ok(())
+}
+
+#[test]
+fn gossipsub_custom_mesh_params() -> eyre::Result<()> {
+    let mesh_params =
+        GossipsubMeshParams { mesh_n: 4, mesh_n_low: 2, mesh_n_high: 6, gossip_lazy: 3 };
+    let config = gossipsub_config_with_mesh_params(&mesh_params).map_err(|e| eyre::anyhow!(e))?;
+
+    assert_eq!(config.mesh_n(), 4);
+    assert_eq!(config.mesh_n_low(), 2);
+    assert_eq!(config.mesh_n_high(), 6);
+    assert_eq!(config.gossip_lazy(), 3);
+
+    let mempool_ids = ChainSpec::dev().canonical_mempools;
+    let gossipsub = create_gossipsub(mempool_ids, config).map_err(|e| eyre::anyhow!(e))?;
+    assert_eq!(gossipsub.topics().count(), 1);
+
+    // mesh_n outside of [mesh_n_low, mesh_n_high]
+    assert!(gossipsub_config_with_mesh_params(&GossipsubMeshParams {
+        mesh_n: 10,
+        ..mesh_params.clone()
+    })
+    .is_err());
+    assert!(gossipsub_config_with_mesh_params(&GossipsubMeshParams {
+        mesh_n_low: 0,
+        ..mesh_params
+    })
+    .is_err());
+
+    Ok(())
+}
@@ -1,6 +1,8 @@
 //! Bundler-related primitives
 
-use serde::Deserialize;
+use ethers::types::{Address, U256};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 use strum_macros::{EnumString, EnumVariantNames};
 
 /// Bundle modes
@@ -15,8 +17,9 @@ pub enum BundleMode {
 }
 
 /// Determines the mode how bundler sends the bundle
-#[derive(Clone, Copy, Debug, EnumString, EnumVariantNames, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, EnumString, EnumVariantNames, PartialEq, Eq, Serialize)]
 #[strum(serialize_all = "kebab_case")]
+#[serde(rename_all = "kebab-case")]
 pub enum BundleStrategy {
     /// Sends the bundle to the Ethereum execution client
     EthereumClient,
@@ -26,4 +29,69 @@ pub enum BundleStrategy {
     Conditional,
     /// Sends the bundle to the Fastlane relay
     Fastlane,
+    /// Sends the bundle to a generic relay over `eth_sendBundle` RPC method
+    Relay,
+}
+
+/// Position of the bundler among the instances sharing a mempool (e.g., behind a load balancer).
+/// User operations are assigned to the instances by their sender, so each of them is bundled by
+/// exactly one instance (and the nonces of a sender aren't split across the instances).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct BundlerInstance {
+    /// Index of the instance (lower than the number of instances)
+    pub index: u64,
+    /// Number of instances
+    pub count: u64,
+}
+
+impl BundlerInstance {
+    pub fn new(index: u64, count: u64) -> Result<Self, String> {
+        if index >= count {
+            return Err(format!("Bundler instance index {index} must be lower than {count}"));
+        }
+
+        Ok(Self { index, count })
+    }
+
+    /// Whether the user operations of the sender are assigned to this instance (sender modulo
+    /// number of instances)
+    pub fn is_assigned(&self, sender: &Address) -> bool {
+        U256::from_big_endian(sender.as_bytes()) % self.count == U256::from(self.index)
+    }
+}
+
+impl FromStr for BundlerInstance {
+    type Err = String;
+
+    /// Parses the instance in the format `<index>/<count>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (index, count) = s
+            .split_once('/')
+            .ok_or_else(|| format!("String {s} is not in the format <index>/<count>"))?;
+        let index = index.trim().parse::<u64>().map_err(|e| format!("Invalid index: {e}"))?;
+        let count = count.trim().parse::<u64>().map_err(|e| format!("Invalid count: {e}"))?;
+        Self::new(index, count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn instance_assignment_by_sender() {
+        let instances = [BundlerInstance::new(0, 2).unwrap(), BundlerInstance::new(1, 2).unwrap()];
+
+        // senders with even addresses are assigned to the first instance, odd ones to the second
+        // instance
+        let senders = [Address::from_low_u64_be(4), Address::from_low_u64_be(7)];
+        for (i, sender) in senders.iter().enumerate() {
+            assert_eq!(
+                instances.iter().map(|instance| instance.is_assigned(sender)).collect::<Vec<_>>(),
+                (0..2).map(|j| i == j).collect::<Vec<_>>()
+            );
+        }
+
+        assert!(BundlerInstance::new(2, 2).is_err());
+    }
 }
@@ -15,6 +15,12 @@ pub struct ChainSpec {
 }
 
 impl ChainSpec {
+    /// Returns whether the chain is built on the OP Stack (charges L1 data fees via the
+    /// `GasPriceOracle` predeploy)
+    pub fn is_op_stack(&self) -> bool {
+        is_op_stack(&self.chain)
+    }
+
     /// Constructs a 'ChainSpec' from chain id
     pub fn from_chain_id(chain_id: u64) -> Self {
         match chain_id {
@@ -114,3 +120,18 @@ impl ChainSpec {
         }
     }
 }
+
+/// Returns whether the chain is built on the OP Stack
+pub fn is_op_stack(chain: &Chain) -> bool {
+    matches!(
+        chain.named(),
+        Some(
+            NamedChain::Optimism |
+                NamedChain::OptimismGoerli |
+                NamedChain::OptimismSepolia |
+                NamedChain::Base |
+                NamedChain::BaseGoerli |
+                NamedChain::BaseSepolia
+        )
+    )
+}
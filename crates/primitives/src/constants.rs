@@ -6,24 +6,76 @@ pub mod entry_point {
     pub const ADDRESS: &str = "0x5FF137D4b0FDCD49DcA30c7CF57E578a026d2789";
     /// Version of the entry point smart contract
     pub const VERSION: &str = "0.6.0";
+    /// Address of the entry point smart contract v0.7
+    pub const ADDRESS_V07: &str = "0x0000000071727De22E5E9d8BAf0edAc6f37da032";
+    /// Version of the entry point smart contract v0.7
+    pub const VERSION_V07: &str = "0.7.0";
 }
 
 /// Bundler
 pub mod bundler {
     /// Default time interval for auto bundling mode (in seconds)
     pub const BUNDLE_INTERVAL: u64 = 10;
+    /// Default maximum number of bundle rebuilds after dropping user operations with invalid nonce
+    pub const MAX_BUNDLE_REBUILDS: usize = 3;
+    /// Default time interval for checking the balance of the bundler's wallet (in seconds)
+    pub const BALANCE_CHECK_INTERVAL: u64 = 60;
+    /// Default header carrying the signature of the `eth_sendBundle` requests sent to relays
+    pub const RELAY_SIGNATURE_HEADER: &str = "X-Flashbots-Signature";
+    /// Default percentage added to the estimated gas of the bundle transaction
+    pub const BUNDLE_GAS_LIMIT_BUFFER: u64 = 0;
+    /// Default maximum number of submissions of a bundle not included in the target block (e.g.,
+    /// by the Flashbots relay)
+    pub const MAX_BUNDLE_SUBMISSIONS: usize = 3;
+    /// Default percentage by which the priority fee of a resubmitted bundle is increased
+    pub const BUNDLE_TIP_ESCALATION: u64 = 20;
 }
 
 /// User operation mempool
 pub mod mempool {
     /// Percentage increase of gas price to replace a user operation in the mempool
     pub const GAS_INCREASE_PERC: u64 = 10;
-    /// Depth scan when searching for previous user operations
+    /// Number of blocks scanned at once for past entry point events (and scanned on startup if
+    /// the last processed block is unknown)
     pub const LATEST_SCAN_DEPTH: u64 = 1000;
+    /// Maximum number of paymaster deposits tracked while building a bundle
+    pub const MAX_TRACKED_PAYMASTERS: usize = 1024;
+    /// Placeholder signature of user operations estimated without a signature (a well-formed ECDSA
+    /// signature which doesn't revert on recovery)
+    pub const DUMMY_SIGNATURE: &str = "0xfffffffffffffffffffffffffffffff0000000000000000000000000000000007aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa1c";
+    /// Capacity of the channel notifying subscribers of the user operations added to the mempool
+    /// (subscribers lagging behind miss the oldest notifications)
+    pub const NEW_USER_OPERATIONS_CAPACITY: usize = 1024;
+    /// Maximum number of user operations held until their validAfter passes
+    pub const MAX_DELAYED_USER_OPERATIONS: usize = 1024;
+    /// Maximum number of user operations held until their nonce gap is filled
+    pub const MAX_GAPPED_USER_OPERATIONS: usize = 1024;
+    /// Time (in seconds) after which a user operation held because of a nonce gap is dropped
+    pub const GAPPED_USER_OPERATION_TTL: u64 = 600;
+    /// Percentage added to the estimated verification gas limit of a user operation (none by
+    /// default, so the estimates stay unchanged)
+    pub const VERIFICATION_GAS_BUFFER_PERC: u64 = 0;
+    /// Number of recent chain heads kept to detect chain splits (same height, different hash)
+    pub const CHAIN_HEAD_HISTORY: u64 = 64;
+    /// Number of user operations simulated concurrently while building a bundle
+    pub const SIMULATION_CONCURRENCY: usize = 8;
+    /// Default maximum priority fee boost (in wei) of the user operations waiting in the mempool
+    pub const PRIORITY_AGING_MAX_BOOST: u64 = 1_000_000_000;
 }
 
 /// User operation validation
 pub mod validation {
+    /// Prefix of the code of EOAs delegated to a contract (EIP-7702 delegation designator)
+    pub const EIP7702_DELEGATION_PREFIX: [u8; 3] = [0xef, 0x01, 0x00];
+    /// Factory address marking the initCode of an EOA delegated to a contract (EIP-7702): the
+    /// initCode carries the authorization instead of deploying the sender
+    pub const EIP7702_INIT_CODE_MARKER: u64 = 0x7702;
+    /// Default min call gas limit: the cost of a CALL with non-zero value (100 + 9000)
+    /// <https://github.com/wolflo/evm-opcodes/blob/main/gas.md#aa-1-call>
+    pub const MIN_CALL_GAS_LIMIT: u64 = 9100;
+    /// Prefix of the `FailedOp` revert reason when the nonce of the sender is invalid
+    pub const INVALID_NONCE_REASON: &str = "AA25";
+
     /// Entities (factory, sender/account, paymaster, aggregator)
     pub mod entities {
         // 0 - factory, 1 - sender/account, 2 - paymaster
@@ -32,6 +84,7 @@ pub mod validation {
         pub const FACTORY: &str = "factory";
         pub const SENDER: &str = "account";
         pub const PAYMASTER: &str = "paymaster";
+        pub const AGGREGATOR: &str = "aggregator";
 
         pub const FACTORY_LEVEL: usize = 0;
         pub const SENDER_LEVEL: usize = 1;
@@ -51,6 +104,10 @@ pub mod validation {
         pub const THROTTLED_ENTITY_MEMPOOL_COUNT: usize = 4;
         pub const THROTTLED_ENTITY_LIVE_BLOCKS: usize = 4;
         pub const THROTTLED_ENTITY_BUNDLE_COUNT: usize = 4;
+        // entities on probation (recovering from throttling) have a smaller allowance than OK
+        // entities, but aren't throttled
+        pub const PROBATION_ENTITY_MEMPOOL_COUNT: usize = 8;
+        pub const PROBATION_ENTITY_BUNDLE_COUNT: usize = 8;
         pub const MIN_INCLUSION_RATE_DENOMINATOR: u64 = 10;
         pub const INCLUSION_RATE_FACTOR: u64 = 10;
         pub const THROTTLING_SLACK: u64 = 10;
@@ -114,6 +171,20 @@ pub mod rpc {
     pub const HTTP_PORT: u16 = 3000;
     /// The default port for WS
     pub const WS_PORT: u16 = 3001;
+    /// The default maximum number of WS subscriptions per connection
+    pub const MAX_SUBSCRIPTIONS_PER_CONNECTION: u32 = 1024;
+    /// The HTTP header carrying the API key
+    pub const API_KEY_HEADER: &str = "x-api-key";
+    /// The default period (in seconds) of the API key rate limits
+    pub const API_KEY_RATE_PERIOD: u64 = 60;
+    /// The methods sending user operations, which are rate limited per API key
+    pub const API_KEY_METHODS: &[&str] = &["eth_sendUserOperation", "eth_sendUserOperations"];
+    /// The maximum number of user operations sent in one `eth_sendUserOperations` call
+    pub const MAX_BATCH_USER_OPERATIONS: usize = 100;
+    /// The admin methods, which require the admin token (in the `Authorization: Bearer` header)
+    pub const ADMIN_METHODS: &[&str] = &["debug_bundler_setPinnedUserOperations"];
+    /// The WS subscription to the user operations added to the mempool
+    pub const NEW_USER_OPERATIONS_SUBSCRIPTION: &str = "newUserOperations";
 }
 
 /// gRPC
@@ -122,12 +193,21 @@ pub mod grpc {
     pub const MEMPOOL_PORT: u16 = 3002;
     /// The default port for bundler
     pub const BUNDLER_PORT: u16 = 3003;
+    /// Interval (in seconds) between the probes of the execution client before the gRPC services
+    /// are reported as serving
+    pub const HEALTH_PROBE_INTERVAL: u64 = 1;
 }
 
 /// Storage
 pub mod storage {
     /// The default path for database
     pub const DATABASE_FOLDER_NAME: &str = "db";
+    /// The default initial size of the database (in bytes)
+    pub const DATABASE_INITIAL_SIZE: usize = 0;
+    /// The default maximum size of the database (in bytes, 4 TB)
+    pub const DATABASE_MAX_SIZE: usize = 1024 * 1024 * 1024 * 1024 * 4;
+    /// The default growth step of the database (in bytes, 256 MB)
+    pub const DATABASE_GROWTH_STEP: usize = 1024 * 1024 * 256;
 }
 
 /// P2P
@@ -148,8 +228,23 @@ pub mod p2p {
     pub const FIND_NODE_QUERY_CLOSEST_PEERS: usize = 16;
     /// Default target peers.
     pub const TARGET_PEERS: usize = 50;
+    /// Default minimum number of connected peers before user operations from p2p are pooled.
+    pub const MIN_PEERS: usize = 0;
+    /// Default number of recently seen user operation hashes remembered to drop gossip duplicates.
+    pub const SEEN_CACHE_SIZE: usize = 1024;
+    /// Number of user operations from gossip awaiting validation whose propagation source is
+    /// remembered, so the peer is credited once the mempool accepts them.
+    pub const PENDING_GOSSIP_CACHE_SIZE: usize = 1024;
     /// Default heartbeat interval (how often we perform discovery and peer management).
     pub const HEARTBEAT_INTERVAL: u64 = 30;
+    /// Score decrement of a peer which sent malformed gossip or gossip for an unsupported entry
+    /// point.
+    pub const BAD_GOSSIP_PENALTY: f64 = 10.0;
+    /// Score below which a peer is disconnected (and banned until its score has fully recovered).
+    pub const MIN_PEER_SCORE: f64 = -50.0;
+    /// Score a penalized peer recovers per heartbeat (decaying back toward zero), so that a banned
+    /// peer stays banned for about half an hour.
+    pub const PEER_SCORE_RECOVERY: f64 = 1.0;
     /// Default outbound ping interval.
     pub const PING_INTERVAL_OUTBOUND: u64 = 15;
     /// Default inbound ping interval.
@@ -168,6 +263,17 @@ pub mod p2p {
     pub const SSZ_SNAPPY_ENCODING: &str = "ssz_snappy";
     /// The maximum size of a uncompressed gossipsub message
     pub const GOSSIP_MAX_SIZE: usize = 1048576; // bytes
+    /// Default target number of peers in the gossipsub mesh (D)
+    pub const GOSSIP_MESH_N: usize = 8;
+    /// Default minimum number of peers in the gossipsub mesh (D_low)
+    pub const GOSSIP_MESH_N_LOW: usize = 6;
+    /// Default maximum number of peers in the gossipsub mesh (D_high)
+    pub const GOSSIP_MESH_N_HIGH: usize = 12;
+    /// Default number of peers to emit gossip to outside the mesh (D_lazy)
+    pub const GOSSIP_LAZY: usize = 6;
+    /// The maximum length of the byte fields of a user operation in the SSZ encoding (user
+    /// operations with longer byte fields aren't gossiped)
+    pub const MAX_SSZ_BYTES_LENGTH: usize = 1024;
     /// The maximum number of user operations in a single request
     pub const MAX_OPS_PER_REQUEST: usize = 4096;
     /// The maximum time for complete response transfer
@@ -186,4 +292,6 @@ pub mod p2p {
     pub const MAX_IPFS_CID_LENGTH: usize = 256;
     /// Public IPFS gateway.
     pub const IPFS_GATEWAY: &str = "https://ipfs.io/ipfs";
+    /// The maximum number of user operations received over p2p validated concurrently.
+    pub const VALIDATION_WORKERS: usize = 16;
 }
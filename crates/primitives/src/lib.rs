@@ -15,11 +15,16 @@ mod utils;
 mod wallet;
 
 pub use bundler::BundleMode;
-pub use mempool::Mode as UoPoolMode;
+pub use mempool::{
+    ChainContext, MempoolStats, Mode as UoPoolMode, NonceGapConfig, PrefundPolicy,
+    ReplacementPolicy, SimulationOutcome, UserOperationSource, ValidAfterPolicy,
+};
 pub use p2p::{MempoolConfig, VerifiedUserOperation};
 pub use user_operation::{
-    UserOperation, UserOperationByHash, UserOperationGasEstimation, UserOperationHash,
-    UserOperationReceipt, UserOperationRequest, UserOperationSigned,
+    decode_inner_calls, encode_execute_batch, pack_paymaster_and_data, InnerCall,
+    NewUserOperationNotification, PackedUserOperation, PackingError, UserOperation,
+    UserOperationByHash, UserOperationGasEstimation, UserOperationHash, UserOperationReceipt,
+    UserOperationRequest, UserOperationSigned,
 };
 pub use utils::get_address;
 pub use wallet::Wallet;
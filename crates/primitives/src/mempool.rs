@@ -1,11 +1,141 @@
 //! Mempool/related primitives
 
+use crate::UserOperationHash;
+use ethers::types::{H256, U256};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
 use strum_macros::{EnumString, EnumVariantNames};
 
 /// Verification modes for user operation mempool
-#[derive(Clone, Copy, Debug, EnumString, EnumVariantNames, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, EnumString, EnumVariantNames, PartialEq, Eq, Serialize)]
 #[strum(serialize_all = "kebab_case")]
+#[serde(rename_all = "kebab-case")]
 pub enum Mode {
     Standard,
     Unsafe,
 }
+
+/// Source from which a user operation was submitted to the mempool
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    EnumString,
+    EnumVariantNames,
+    PartialEq,
+    Eq,
+    Hash,
+    Serialize,
+    Deserialize,
+)]
+#[strum(serialize_all = "kebab_case")]
+#[serde(rename_all = "lowercase")]
+pub enum UserOperationSource {
+    /// Submitted via the RPC API
+    #[default]
+    Rpc,
+    /// Received over the p2p network
+    P2p,
+}
+
+/// Handling of user operations which aren't valid yet (`validAfter` in the future)
+#[derive(Clone, Copy, Debug, Default, EnumString, EnumVariantNames, PartialEq, Eq, Serialize)]
+#[strum(serialize_all = "kebab_case")]
+#[serde(rename_all = "kebab-case")]
+pub enum ValidAfterPolicy {
+    /// Held in a delayed queue and promoted to the mempool once `validAfter` passes
+    #[default]
+    Queue,
+    /// Rejected
+    Reject,
+}
+
+/// Funds of the sender required to cover the prefund of user operations without a paymaster
+#[derive(Clone, Copy, Debug, Default, EnumString, EnumVariantNames, PartialEq, Eq, Serialize)]
+#[strum(serialize_all = "kebab_case")]
+#[serde(rename_all = "kebab-case")]
+pub enum PrefundPolicy {
+    /// Not checked before the simulation
+    #[default]
+    Disabled,
+    /// Deposit of the sender in the entry point
+    Deposit,
+    /// Deposit of the sender in the entry point plus its balance (paid to the entry point during
+    /// the validation)
+    DepositAndBalance,
+}
+
+/// Precedence between the sources of user operations replacing each other (same sender and
+/// nonce)
+#[derive(Clone, Copy, Debug, Default, EnumString, EnumVariantNames, PartialEq, Eq, Serialize)]
+#[strum(serialize_all = "kebab_case")]
+#[serde(rename_all = "kebab-case")]
+pub enum ReplacementPolicy {
+    /// User operations submitted via the RPC API are preferred: the ones received over the p2p
+    /// network have to bump the fees by twice the replacement fee increase to replace them
+    #[default]
+    PreferLocal,
+    /// Sources are treated equally (the replacement fee increase applies to all replacements)
+    Equal,
+}
+
+/// Holding of user operations whose nonce is ahead of the next one expected for the sender (nonce
+/// gap). They are promoted to the mempool once the user operations filling the gap are added.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NonceGapConfig {
+    /// Maximum distance of the nonce from the next expected one (further ones are rejected)
+    pub max_gap: u64,
+    /// Maximum number of held user operations
+    pub max_held: usize,
+    /// Time after which a held user operation is dropped
+    pub ttl: Duration,
+}
+
+/// Number of user operations in the mempool by source
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MempoolStats {
+    /// User operations submitted via the RPC API
+    pub rpc: u64,
+    /// User operations received over the p2p network
+    pub p2p: u64,
+}
+
+/// Chain and fee context as seen by the mempool (useful for debugging fee rejections)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainContext {
+    /// Chain id
+    pub chain_id: u64,
+    /// Number of the latest block
+    pub block_number: u64,
+    /// Hash of the latest block
+    pub block_hash: H256,
+    /// Base fee per gas of the latest block
+    pub base_fee_per_gas: U256,
+    /// Minimum priority fee per gas accepted by the mempool
+    pub min_priority_fee_per_gas: U256,
+    /// Maximum verification gas accepted by the mempool
+    pub max_verification_gas: U256,
+}
+
+/// Outcome of a simulate-only validation of a user operation (the user operation isn't added to
+/// the mempool and the reputation isn't updated)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulationOutcome {
+    /// Required prefund of the user operation
+    pub pre_fund: U256,
+    /// Verification gas limit used by the validation
+    pub verification_gas_limit: U256,
+    /// Call gas limit of the user operation
+    pub call_gas_limit: U256,
+    /// Pre-verification gas of the user operation
+    pub pre_verification_gas: U256,
+    /// Timestamp after which the user operation is valid
+    pub valid_after: Option<U256>,
+    /// Block the user operation was validated on
+    pub verified_block: U256,
+    /// Hash of the user operation (same sender and nonce) in the mempool it would replace
+    pub prev_hash: Option<UserOperationHash>,
+}
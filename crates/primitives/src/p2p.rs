@@ -100,3 +100,19 @@ impl VerifiedUserOperation {
         Address::from_slice(&self.entry_point)
     }
 }
+
+/// Connected peer as seen by the local p2p node
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerDetails {
+    /// Peer id of the peer
+    pub peer_id: String,
+    /// Multiaddr the peer was seen at
+    pub multiaddr: Option<String>,
+    /// Mempools the peer advertised in its metadata
+    pub supported_mempools: Vec<String>,
+    /// Score of the peer
+    pub score: f64,
+    /// Connection direction (incoming or outgoing)
+    pub direction: Option<String>,
+}
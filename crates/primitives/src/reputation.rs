@@ -1,6 +1,12 @@
 //! Primitives for reputation
 
-use super::utils::{as_checksum_addr, as_hex_string, as_u64};
+use super::{
+    constants::validation::reputation::{
+        PROBATION_ENTITY_BUNDLE_COUNT, PROBATION_ENTITY_MEMPOOL_COUNT,
+        THROTTLED_ENTITY_BUNDLE_COUNT, THROTTLED_ENTITY_MEMPOOL_COUNT,
+    },
+    utils::{as_checksum_addr, as_hex_string, as_u64},
+};
 use ethers::{
     prelude::{EthAbiCodec, EthAbiType},
     types::{Address, U256},
@@ -15,16 +21,42 @@ pub type ReputationStatus = u64;
 pub enum Status {
     #[default]
     OK,
+    /// Recovering from throttling: the entity has a smaller allowance than OK entities, but isn't
+    /// throttled
+    PROBATION,
     THROTTLED,
     BANNED,
 }
 
+impl Status {
+    /// Max number of user operations of the entity in the mempool (`None` if unlimited)
+    pub fn mempool_allowance(&self) -> Option<usize> {
+        match self {
+            Status::OK => None,
+            Status::PROBATION => Some(PROBATION_ENTITY_MEMPOOL_COUNT),
+            Status::THROTTLED => Some(THROTTLED_ENTITY_MEMPOOL_COUNT),
+            Status::BANNED => Some(0),
+        }
+    }
+
+    /// Max number of user operations of the entity in a bundle (`None` if unlimited)
+    pub fn bundle_allowance(&self) -> Option<usize> {
+        match self {
+            Status::OK => None,
+            Status::PROBATION => Some(PROBATION_ENTITY_BUNDLE_COUNT),
+            Status::THROTTLED => Some(THROTTLED_ENTITY_BUNDLE_COUNT),
+            Status::BANNED => Some(0),
+        }
+    }
+}
+
 impl From<Status> for ReputationStatus {
     fn from(status: Status) -> Self {
         match status {
             Status::OK => 0,
             Status::THROTTLED => 1,
             Status::BANNED => 2,
+            Status::PROBATION => 3,
         }
     }
 }
@@ -35,6 +67,7 @@ impl From<ReputationStatus> for Status {
             0 => Status::OK,
             1 => Status::THROTTLED,
             2 => Status::BANNED,
+            3 => Status::PROBATION,
             _ => Status::OK,
         }
     }
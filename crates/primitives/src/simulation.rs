@@ -2,11 +2,12 @@
 
 use ethers::{
     prelude::{EthAbiCodec, EthAbiType},
-    types::{Address, H256, U256},
+    types::{spoof, Address, Bytes, H256, U256, U64},
 };
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
+use thiserror::Error;
 
 /// Time ineterval before user operation expires (in seconds)
 pub const EXPIRATION_TIMESTAMP_DIFF: u64 = 30;
@@ -37,6 +38,14 @@ lazy_static! {
         set.insert("PREVRANDAO".into());
         set
     };
+    /// Opcodes additionally forbidden in the strict validation mode (the latest ERC-7562 rules)
+    pub static ref STRICT_FORBIDDEN_OPCODES: HashSet<String> = {
+        let mut set = HashSet::new();
+        set.insert("BLOBHASH".into());
+        set.insert("BLOBBASEFEE".into());
+        set.insert("INVALID".into());
+        set
+    };
 }
 
 /// Validaton config (you can override some validation values).
@@ -45,9 +54,113 @@ pub struct ValidationConfig {
     pub min_stake: Option<U256>,
     pub min_unstake_delay: Option<U256>,
     pub topic: Option<String>,
+    /// Whether the user operation is re-validated for another canonical mempool while it's
+    /// already known (e.g., received over p2p). If set, the replacement checks against the user
+    /// operation with the same sender and nonce in the mempool are skipped: the gas increase isn't
+    /// enforced, but the previous user operation is still replaced (both are never kept).
     pub ignore_prev: bool,
 }
 
+impl ValidationConfig {
+    /// Returns a [ValidationConfigBuilder](ValidationConfigBuilder)
+    pub fn builder() -> ValidationConfigBuilder {
+        ValidationConfigBuilder::default()
+    }
+}
+
+/// Invalid combination of values of a [ValidationConfig](ValidationConfig)
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum ValidationConfigError {
+    /// The topic is empty
+    #[error("topic can't be empty")]
+    EmptyTopic,
+    /// The previous validation is ignored without a topic
+    #[error("ignore_prev requires a topic")]
+    IgnorePrevWithoutTopic,
+    /// The minimum unstake delay is set without the minimum stake
+    #[error("min_unstake_delay requires min_stake")]
+    UnstakeDelayWithoutStake,
+    /// The minimum stake is set without a topic for gossip
+    #[error("topic is required when min_stake is set for gossip")]
+    GossipStakeWithoutTopic,
+    /// The topic is set without the minimum stake for gossip
+    #[error("min_stake is required when topic is set for gossip")]
+    GossipTopicWithoutStake,
+}
+
+/// Builder of [ValidationConfig](ValidationConfig) that rejects invalid combinations of values
+#[derive(Debug, Clone, Default)]
+pub struct ValidationConfigBuilder {
+    min_stake: Option<U256>,
+    min_unstake_delay: Option<U256>,
+    topic: Option<String>,
+    ignore_prev: bool,
+    gossip: bool,
+}
+
+impl ValidationConfigBuilder {
+    /// Sets the minimum stake of the entities
+    pub fn min_stake(mut self, min_stake: U256) -> Self {
+        self.min_stake = Some(min_stake);
+        self
+    }
+
+    /// Sets the minimum unstake delay of the entities
+    pub fn min_unstake_delay(mut self, min_unstake_delay: U256) -> Self {
+        self.min_unstake_delay = Some(min_unstake_delay);
+        self
+    }
+
+    /// Sets the topic of the canonical mempool
+    pub fn topic(mut self, topic: impl Into<String>) -> Self {
+        self.topic = Some(topic.into());
+        self
+    }
+
+    /// Sets whether the replacement checks against the user operation with the same sender and
+    /// nonce in the mempool are skipped (see [ValidationConfig::ignore_prev])
+    pub fn ignore_prev(mut self, ignore_prev: bool) -> Self {
+        self.ignore_prev = ignore_prev;
+        self
+    }
+
+    /// Marks the config as used for the validation of user operations of canonical mempools (p2p)
+    pub fn gossip(mut self) -> Self {
+        self.gossip = true;
+        self
+    }
+
+    /// Builds the [ValidationConfig](ValidationConfig)
+    ///
+    /// # Returns
+    /// * `Result<ValidationConfig, ValidationConfigError>` - Error if the combination of values is
+    ///   invalid
+    pub fn build(self) -> Result<ValidationConfig, ValidationConfigError> {
+        if self.topic.as_ref().is_some_and(|topic| topic.is_empty()) {
+            return Err(ValidationConfigError::EmptyTopic);
+        }
+        if self.ignore_prev && self.topic.is_none() {
+            return Err(ValidationConfigError::IgnorePrevWithoutTopic);
+        }
+        if self.min_unstake_delay.is_some() && self.min_stake.is_none() {
+            return Err(ValidationConfigError::UnstakeDelayWithoutStake);
+        }
+        if self.gossip && self.min_stake.is_some() && self.topic.is_none() {
+            return Err(ValidationConfigError::GossipStakeWithoutTopic);
+        }
+        if self.gossip && self.topic.is_some() && self.min_stake.is_none() {
+            return Err(ValidationConfigError::GossipTopicWithoutStake);
+        }
+
+        Ok(ValidationConfig {
+            min_stake: self.min_stake,
+            min_unstake_delay: self.min_unstake_delay,
+            topic: self.topic,
+            ignore_prev: self.ignore_prev,
+        })
+    }
+}
+
 /// Code hash - hash of the code of the contract
 #[derive(
     Debug,
@@ -68,8 +181,147 @@ pub struct CodeHash {
 }
 
 /// Storage map
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct StorageMap {
     pub root_hashes: HashMap<Address, H256>,
     pub slots: HashMap<Address, HashMap<String, String>>,
 }
+
+/// Override of the state of an account (an entry of the `eth_call` state override set)
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct AccountOverride {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub balance: Option<U256>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<U64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<Bytes>,
+    /// Storage replacing the whole storage of the account
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<HashMap<H256, H256>>,
+    /// Storage slots overriding the ones of the account
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_diff: Option<HashMap<H256, H256>>,
+}
+
+/// State override set (`eth_call`-style) applied to the simulation of a user operation, e.g., to
+/// estimate the gas of a user operation against a pre-funded sender
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct StateOverride(pub HashMap<Address, AccountOverride>);
+
+impl StateOverride {
+    /// Checks that the storage of each account is either replaced or patched (not both)
+    pub fn validate(&self) -> Result<(), String> {
+        match self.0.iter().find(|(_, acc)| acc.state.is_some() && acc.state_diff.is_some()) {
+            Some((addr, _)) => {
+                Err(format!("State override of {addr:?} has both state and stateDiff"))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl From<StateOverride> for spoof::State {
+    fn from(state_override: StateOverride) -> Self {
+        let mut state = spoof::State::default();
+        for (addr, acc) in state_override.0 {
+            let account = state.account(addr);
+            account.balance = acc.balance;
+            account.nonce = acc.nonce;
+            account.code = acc.code;
+            // the replaced storage takes precedence (both aren't allowed by the validation)
+            account.storage =
+                acc.state.map(spoof::Storage::Replace).or(acc.state_diff.map(spoof::Storage::Diff));
+        }
+        state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn state_override() {
+        let state_override: StateOverride = serde_json::from_str(
+            r#"{
+                "0x0000000000000000000000000000000000000001": {
+                    "balance": "0xde0b6b3a7640000",
+                    "stateDiff": {
+                        "0x0000000000000000000000000000000000000000000000000000000000000000": "0x0000000000000000000000000000000000000000000000000000000000000001"
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        assert!(state_override.validate().is_ok());
+
+        let addr = Address::from_low_u64_be(1);
+        let mut state = spoof::State::default();
+        state.account(addr).balance(U256::exp10(18)).store(H256::zero(), H256::from_low_u64_be(1));
+        assert_eq!(spoof::State::from(state_override), state);
+
+        // unknown keys are rejected
+        assert!(serde_json::from_str::<StateOverride>(
+            r#"{"0x0000000000000000000000000000000000000001": {"balanse": "0x1"}}"#
+        )
+        .is_err());
+
+        // the storage is either replaced or patched
+        let state_override: StateOverride = serde_json::from_str(
+            r#"{"0x0000000000000000000000000000000000000001": {"state": {}, "stateDiff": {}}}"#,
+        )
+        .unwrap();
+        assert!(state_override.validate().is_err());
+    }
+
+    #[test]
+    fn validation_config_builder() {
+        let config = ValidationConfig::builder()
+            .min_stake(U256::from(1))
+            .min_unstake_delay(U256::from(2))
+            .build()
+            .unwrap();
+        assert_eq!(config.min_stake, Some(U256::from(1)));
+        assert_eq!(config.min_unstake_delay, Some(U256::from(2)));
+        assert!(config.topic.is_none());
+        assert!(!config.ignore_prev);
+
+        let config = ValidationConfig::builder()
+            .gossip()
+            .min_stake(U256::from(1))
+            .topic("topic")
+            .ignore_prev(true)
+            .build()
+            .unwrap();
+        assert_eq!(config.topic, Some("topic".into()));
+        assert!(config.ignore_prev);
+
+        assert!(ValidationConfig::builder().build().is_ok());
+
+        // invalid combinations
+        assert_eq!(
+            ValidationConfig::builder().topic("").build().unwrap_err(),
+            ValidationConfigError::EmptyTopic
+        );
+        assert_eq!(
+            ValidationConfig::builder().ignore_prev(true).build().unwrap_err(),
+            ValidationConfigError::IgnorePrevWithoutTopic
+        );
+        assert_eq!(
+            ValidationConfig::builder().min_unstake_delay(U256::from(2)).build().unwrap_err(),
+            ValidationConfigError::UnstakeDelayWithoutStake
+        );
+        assert_eq!(
+            ValidationConfig::builder().gossip().min_stake(U256::from(1)).build().unwrap_err(),
+            ValidationConfigError::GossipStakeWithoutTopic
+        );
+        assert_eq!(
+            ValidationConfig::builder().gossip().topic("topic").build().unwrap_err(),
+            ValidationConfigError::GossipTopicWithoutStake
+        );
+    }
+}
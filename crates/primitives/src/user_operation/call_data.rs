@@ -0,0 +1,163 @@
+//! Decoding of the common account calls (`execute`/`executeBatch`) of the user operation call data
+
+use ethers::{
+    abi::{decode, encode, ParamType, Selector, Token},
+    types::{Address, Bytes, U256},
+    utils::id,
+};
+
+/// Call made by the account on behalf of the user operation
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InnerCall {
+    /// Target of the call
+    pub target: Address,
+    /// Value sent with the call
+    pub value: U256,
+    /// Call data of the call
+    pub data: Bytes,
+}
+
+impl InnerCall {
+    /// Returns the selector of the call (None for plain transfers)
+    pub fn selector(&self) -> Option<Selector> {
+        selector(&self.data)
+    }
+}
+
+/// Returns the selector of the call data (if the call data isn't shorter than 4 bytes)
+pub fn selector(call_data: &[u8]) -> Option<Selector> {
+    call_data.get(..4).map(|s| s.try_into().expect("selector is 4 bytes long"))
+}
+
+/// Decodes the inner calls of an `execute(address,uint256,bytes)`,
+/// `executeBatch(address[],bytes[])` or `executeBatch(address[],uint256[],bytes[])` call data
+///
+/// # Arguments
+/// * `call_data` - The call data of the user operation
+///
+/// # Returns
+/// * The inner calls, or None if the call data isn't one of these calls (or is malformed)
+pub fn decode_inner_calls(call_data: &[u8]) -> Option<Vec<InnerCall>> {
+    let outer = selector(call_data)?;
+    let args = &call_data[4..];
+
+    let address = || ParamType::Address;
+    let uint = || ParamType::Uint(256);
+    let array = |kind: ParamType| ParamType::Array(Box::new(kind));
+
+    if outer == id("execute(address,uint256,bytes)") {
+        let mut tokens = decode(&[address(), uint(), ParamType::Bytes], args).ok()?.into_iter();
+        Some(vec![InnerCall {
+            target: tokens.next()?.into_address()?,
+            value: tokens.next()?.into_uint()?,
+            data: tokens.next()?.into_bytes()?.into(),
+        }])
+    } else if outer == id("executeBatch(address[],bytes[])") {
+        let mut tokens =
+            decode(&[array(address()), array(ParamType::Bytes)], args).ok()?.into_iter();
+        let targets = tokens.next()?.into_array()?;
+        let data = tokens.next()?.into_array()?;
+        if targets.len() != data.len() {
+            return None;
+        }
+
+        targets
+            .into_iter()
+            .zip(data)
+            .map(|(target, data)| {
+                Some(InnerCall {
+                    target: target.into_address()?,
+                    value: U256::zero(),
+                    data: data.into_bytes()?.into(),
+                })
+            })
+            .collect()
+    } else if outer == id("executeBatch(address[],uint256[],bytes[])") {
+        let mut tokens = decode(&[array(address()), array(uint()), array(ParamType::Bytes)], args)
+            .ok()?
+            .into_iter();
+        let targets = tokens.next()?.into_array()?;
+        let values = tokens.next()?.into_array()?;
+        let data = tokens.next()?.into_array()?;
+        if targets.len() != values.len() || targets.len() != data.len() {
+            return None;
+        }
+
+        targets
+            .into_iter()
+            .zip(values)
+            .zip(data)
+            .map(|((target, value), data)| {
+                Some(InnerCall {
+                    target: target.into_address()?,
+                    value: value.into_uint()?,
+                    data: data.into_bytes()?.into(),
+                })
+            })
+            .collect()
+    } else {
+        None
+    }
+}
+
+/// Encodes an `executeBatch(address[],uint256[],bytes[])` call data
+pub fn encode_execute_batch(calls: &[InnerCall]) -> Bytes {
+    let args = encode(&[
+        Token::Array(calls.iter().map(|call| Token::Address(call.target)).collect()),
+        Token::Array(calls.iter().map(|call| Token::Uint(call.value)).collect()),
+        Token::Array(calls.iter().map(|call| Token::Bytes(call.data.to_vec())).collect()),
+    ]);
+    [id("executeBatch(address[],uint256[],bytes[])").to_vec(), args].concat().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_execute_batch() {
+        let calls = vec![
+            InnerCall {
+                target: Address::random(),
+                value: U256::from(1),
+                data: [id("transfer(address,uint256)").to_vec(), vec![0; 64]].concat().into(),
+            },
+            InnerCall { target: Address::random(), value: U256::from(2), data: Bytes::default() },
+            InnerCall {
+                target: Address::random(),
+                value: U256::zero(),
+                data: id("approve(address,uint256)").to_vec().into(),
+            },
+        ];
+
+        let decoded = decode_inner_calls(&encode_execute_batch(&calls)).unwrap();
+        assert_eq!(decoded, calls);
+        assert_eq!(
+            decoded.iter().map(|call| call.target).collect::<Vec<_>>(),
+            calls.iter().map(|call| call.target).collect::<Vec<_>>()
+        );
+        assert_eq!(decoded[0].selector(), Some(id("transfer(address,uint256)")));
+        assert_eq!(decoded[1].selector(), None);
+
+        // batch without values
+        let call_data: Bytes = [
+            id("executeBatch(address[],bytes[])").to_vec(),
+            encode(&[
+                Token::Array(calls.iter().map(|call| Token::Address(call.target)).collect()),
+                Token::Array(calls.iter().map(|call| Token::Bytes(call.data.to_vec())).collect()),
+            ]),
+        ]
+        .concat()
+        .into();
+        let decoded = decode_inner_calls(&call_data).unwrap();
+        assert_eq!(
+            decoded.iter().map(|call| call.target).collect::<Vec<_>>(),
+            calls.iter().map(|call| call.target).collect::<Vec<_>>()
+        );
+        assert!(decoded.iter().all(|call| call.value.is_zero()));
+
+        // other calls aren't decoded
+        assert!(decode_inner_calls(&id("transfer(address,uint256)")).is_none());
+        assert!(decode_inner_calls(&[0x12, 0x34]).is_none());
+    }
+}
@@ -1,9 +1,16 @@
 //! Basic transaction type for account abstraction (ERC-4337)
 
+mod call_data;
 mod hash;
+mod packed;
 mod request;
 
-use crate::{get_address, utils::as_checksum_addr};
+use crate::{
+    constants::p2p::MAX_SSZ_BYTES_LENGTH,
+    get_address,
+    utils::{as_checksum_addr, ADDRESS_LENGTH},
+};
+pub use call_data::{decode_inner_calls, encode_execute_batch, InnerCall};
 use derive_more::{AsRef, Deref};
 use ethers::{
     abi::AbiEncode,
@@ -12,6 +19,7 @@ use ethers::{
     utils::keccak256,
 };
 pub use hash::UserOperationHash;
+pub use packed::{pack_paymaster_and_data, PackedUserOperation, PackingError};
 pub use request::UserOperationRequest;
 use serde::{Deserialize, Serialize};
 use ssz_rs::List;
@@ -157,6 +165,24 @@ impl UserOperationSigned {
         .into()
     }
 
+    /// Whether the user operation is a cancellation (doesn't execute anything, i.e., has an empty
+    /// `callData`). Replacing a pending user operation with a cancellation only bumps the nonce.
+    pub fn is_cancellation(&self) -> bool {
+        self.call_data.is_empty()
+    }
+
+    /// The priority fee the user operation effectively pays given the base fee:
+    /// `min(maxPriorityFeePerGas, maxFeePerGas - baseFee)` (the raw `maxPriorityFeePerGas` if the
+    /// base fee is unknown)
+    pub fn effective_priority_fee(&self, base_fee: Option<U256>) -> U256 {
+        match base_fee {
+            Some(base_fee) => {
+                self.max_priority_fee_per_gas.min(self.max_fee_per_gas.saturating_sub(base_fee))
+            }
+            None => self.max_priority_fee_per_gas,
+        }
+    }
+
     // Builder pattern helpers
 
     /// Sets the sender of the user operation
@@ -233,6 +259,40 @@ impl UserOperationSigned {
         (sender, factory, paymaster)
     }
 
+    /// Validates the structure of the byte fields of the user operation: `initCode` and
+    /// `paymasterAndData` have to be either empty or start with an address (20 bytes)
+    ///
+    /// # Returns
+    /// * `Result<(), String>` - Error message describing the malformed field
+    pub fn validate_byte_fields(&self) -> Result<(), String> {
+        for (name, field) in
+            [("initCode", &self.init_code), ("paymasterAndData", &self.paymaster_and_data)]
+        {
+            if !field.is_empty() && get_address(field).is_none() {
+                return Err(format!(
+                    "{name} must be either empty or at least {ADDRESS_LENGTH} bytes long, got {} bytes",
+                    field.len()
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the first byte field (name and length) longer than the maximum length of the SSZ
+    /// encoding, in which case the user operation can't be gossiped over the p2p network
+    pub fn ssz_oversized_field(&self) -> Option<(&'static str, usize)> {
+        [
+            ("initCode", &self.init_code),
+            ("callData", &self.call_data),
+            ("paymasterAndData", &self.paymaster_and_data),
+            ("signature", &self.signature),
+        ]
+        .into_iter()
+        .find(|(_, field)| field.len() > MAX_SSZ_BYTES_LENGTH)
+        .map(|(name, field)| (name, field.len()))
+    }
+
     /// Creates random user operation (for testing purposes)
     #[cfg(feature = "test-utils")]
     pub fn random() -> Self {
@@ -244,14 +304,9 @@ impl UserOperationSigned {
     }
 }
 
-/// This could be increased if we found bigger bytes, not sure about the proper value right now.
-const MAXIMUM_SSZ_BYTES_LENGTH: usize = 1024;
-
-fn btyes_to_list(
-    value: &Bytes,
-) -> Result<List<u8, MAXIMUM_SSZ_BYTES_LENGTH>, ssz_rs::SerializeError> {
+fn btyes_to_list(value: &Bytes) -> Result<List<u8, MAX_SSZ_BYTES_LENGTH>, ssz_rs::SerializeError> {
     let data = value.to_vec();
-    List::<u8, MAXIMUM_SSZ_BYTES_LENGTH>::try_from(data)
+    List::<u8, MAX_SSZ_BYTES_LENGTH>::try_from(data)
         .map_err(|(data, _)| ssz_rs::SerializeError::MaximumEncodedLengthReached(data.len()))
 }
 
@@ -452,6 +507,23 @@ pub struct UserOperationByHash {
     pub block_number: U64,
 }
 
+/// Notification of the user operation added to the mempool (pushed by the RPC subscription
+/// eth_subscribe("newUserOperations"))
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewUserOperationNotification {
+    pub user_operation_hash: UserOperationHash,
+    #[serde(serialize_with = "as_checksum_addr")]
+    pub entry_point: Address,
+    /// The full user operation (only if requested by the subscriber)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_operation: Option<UserOperationSigned>,
+    /// Hash of the pending user operation cancelled (replaced with an empty `callData`) by this
+    /// one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cancels: Option<UserOperationHash>,
+}
+
 /// Gas estimations for user operation (returned from the RPC endpoint eth_estimateUserOperationGas)
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -568,4 +640,113 @@ mod tests {
         assert_eq!(uo_decode.paymaster_and_data, uo.paymaster_and_data);
         assert_eq!(uo_decode.signature, uo.signature);
     }
+
+    #[test]
+    fn user_operation_signed_ssz_limit() {
+        // byte fields at the limit are encoded
+        let uo = UserOperationSigned::default()
+            .call_data(vec![1; MAX_SSZ_BYTES_LENGTH].into())
+            .signature(vec![2; MAX_SSZ_BYTES_LENGTH].into());
+        assert_eq!(uo.ssz_oversized_field(), None);
+        let mut encoded = Vec::new();
+        ssz_rs::Serialize::serialize(&uo, &mut encoded).unwrap();
+        assert_eq!(
+            <UserOperationSigned as ssz_rs::Deserialize>::deserialize(&encoded).unwrap(),
+            uo
+        );
+
+        // byte fields above the limit are reported
+        let uo = uo.signature(vec![2; MAX_SSZ_BYTES_LENGTH + 1].into());
+        assert_eq!(uo.ssz_oversized_field(), Some(("signature", MAX_SSZ_BYTES_LENGTH + 1)));
+        assert!(ssz_rs::Serialize::serialize(&uo, &mut Vec::new()).is_err());
+
+        let uo = uo.init_code(vec![3; MAX_SSZ_BYTES_LENGTH + 2].into());
+        assert_eq!(uo.ssz_oversized_field(), Some(("initCode", MAX_SSZ_BYTES_LENGTH + 2)));
+    }
+
+    #[test]
+    fn user_operation_request_unknown_fields() {
+        let uo = r#"{
+            "sender": "0x9c5754De1443984659E1b3a8d1931D83475ba29C",
+            "nonce": "0x1",
+            "initCode": "0x",
+            "callData": "0x",
+            "callGasLimit": "0x814c",
+            "verificationGasLimit": "0xecd0",
+            "preVerificationGas": "0xac18",
+            "maxFeePerGas": "0x6507a5de",
+            "maxPriorityFeePerGas": "0x6507a5c0",
+            "paymasterAndData": "0x",
+            "signature": "0x",
+            "extraField": "0x1234"
+        }"#;
+
+        let uo: UserOperationRequest = serde_json::from_str(uo).unwrap();
+        assert_eq!(uo.unknown_fields.keys().collect::<Vec<_>>(), vec!["extraField"]);
+        assert_eq!(uo.nonce, 1.into());
+        assert_eq!(uo.call_gas_limit, Some(33_100.into()));
+
+        // lenient mode ignores unknown fields
+        assert!(uo.check_unknown_fields(false).is_ok());
+        // strict mode rejects them
+        assert_eq!(
+            uo.check_unknown_fields(true).unwrap_err(),
+            "User operation contains unknown fields: extraField"
+        );
+
+        // unknown fields are never serialized back
+        let uo_json = serde_json::to_value(&uo).unwrap();
+        assert!(uo_json.get("extraField").is_none());
+
+        let uo: UserOperationRequest = serde_json::from_value(uo_json).unwrap();
+        assert!(uo.unknown_fields.is_empty());
+        assert!(uo.check_unknown_fields(true).is_ok());
+    }
+
+    #[test]
+    fn user_operation_request_estimation() {
+        let uo: UserOperationRequest = serde_json::from_str(
+            r#"{
+            "sender": "0x9c5754De1443984659E1b3a8d1931D83475ba29C",
+            "nonce": "0x1",
+            "callData": "0x1234"
+        }"#,
+        )
+        .unwrap();
+
+        let uo = uo.into_estimation_user_operation().unwrap();
+        assert_eq!(uo.call_data, "0x1234".parse::<Bytes>().unwrap());
+        assert_eq!(uo.call_gas_limit, U256::zero());
+        assert_eq!(uo.verification_gas_limit, U256::zero());
+        assert_eq!(uo.pre_verification_gas, U256::zero());
+        assert_eq!(uo.signature.len(), 65);
+
+        let uo: UserOperationRequest = serde_json::from_str(r#"{ "nonce": "0x1" }"#).unwrap();
+        assert_eq!(
+            uo.into_estimation_user_operation().unwrap_err(),
+            "User operation is missing required fields: sender, callData"
+        );
+    }
+
+    #[test]
+    fn user_operation_signed_validate_byte_fields() {
+        let address = Address::random();
+        let uo = UserOperationSigned::default();
+        assert!(uo.validate_byte_fields().is_ok());
+
+        let uo = uo
+            .init_code([address.as_bytes(), &[1, 2, 3]].concat().into())
+            .paymaster_and_data(address.as_bytes().to_vec().into());
+        assert!(uo.validate_byte_fields().is_ok());
+
+        assert_eq!(
+            uo.clone().init_code(address.as_bytes()[..19].to_vec().into()).validate_byte_fields(),
+            Err("initCode must be either empty or at least 20 bytes long, got 19 bytes".into())
+        );
+        assert_eq!(
+            uo.clone().paymaster_and_data(vec![1].into()).validate_byte_fields(),
+            Err("paymasterAndData must be either empty or at least 20 bytes long, got 1 bytes"
+                .into())
+        );
+    }
 }
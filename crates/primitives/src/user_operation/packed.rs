@@ -0,0 +1,378 @@
+use super::{UserOperationHash, UserOperationSigned};
+use crate::{
+    get_address,
+    utils::{as_checksum_addr, ADDRESS_LENGTH},
+};
+use ethers::{
+    abi::AbiEncode,
+    contract::{EthAbiCodec, EthAbiType},
+    types::{Address, Bytes, H256, U256},
+    utils::keccak256,
+};
+use serde::{Deserialize, Serialize};
+use std::ops::Deref;
+use thiserror::Error;
+
+/// Offset of the paymaster verification gas limit in `paymasterAndData`
+const PAYMASTER_VALIDATION_GAS_OFFSET: usize = ADDRESS_LENGTH;
+/// Offset of the paymaster postOp gas limit in `paymasterAndData`
+const PAYMASTER_POSTOP_GAS_OFFSET: usize = 36;
+/// Offset of the paymaster data in `paymasterAndData`
+const PAYMASTER_DATA_OFFSET: usize = 52;
+
+/// Error of packing a user operation into the layout of the entry point v0.7
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{field} {value} doesn't fit into 128 bits")]
+pub struct PackingError {
+    /// Name of the field that doesn't fit
+    pub field: &'static str,
+    /// Value of the field
+    pub value: U256,
+}
+
+/// Packs two values into 32 bytes, the first one into the high 128 bits and the second one into
+/// the low 128 bits (values that don't fit into 128 bits are rejected instead of truncated)
+fn pack_uints(high: (&'static str, U256), low: (&'static str, U256)) -> Result<H256, PackingError> {
+    let max = U256::from(u128::MAX);
+    if let Some((field, value)) = [high, low].into_iter().find(|(_, value)| *value > max) {
+        return Err(PackingError { field, value });
+    }
+
+    let mut packed = [0u8; 32];
+    ((high.1 << 128) | low.1).to_big_endian(&mut packed);
+    Ok(H256(packed))
+}
+
+/// Unpacks the high and low 128 bits of the packed value
+fn unpack_uints(packed: &H256) -> (U256, U256) {
+    let value = U256::from_big_endian(packed.as_bytes());
+    (value >> 128, value & U256::from(u128::MAX))
+}
+
+/// Packs the paymaster, its verification and postOp gas limits and the paymaster data into
+/// `paymasterAndData` of the entry point v0.7
+pub fn pack_paymaster_and_data(
+    paymaster: Address,
+    verification_gas_limit: u128,
+    post_op_gas_limit: u128,
+    data: &[u8],
+) -> Bytes {
+    [
+        paymaster.as_bytes(),
+        &verification_gas_limit.to_be_bytes(),
+        &post_op_gas_limit.to_be_bytes(),
+        data,
+    ]
+    .concat()
+    .into()
+}
+
+/// User operation of the entry point v0.7 (gas limits and gas fees are packed in pairs)
+#[derive(Default, Clone, Debug, PartialEq, Eq, EthAbiCodec, EthAbiType, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackedUserOperation {
+    /// Sender of the user operation
+    #[serde(serialize_with = "as_checksum_addr")]
+    pub sender: Address,
+
+    /// Nonce (anti replay protection)
+    pub nonce: U256,
+
+    /// Init code for the account (needed if account not yet deployed and needs to be created)
+    pub init_code: Bytes,
+
+    /// The data that is passed to the sender during the main execution call
+    pub call_data: Bytes,
+
+    /// The verification gas limit (high 128 bits) and the call gas limit (low 128 bits)
+    pub account_gas_limits: H256,
+
+    /// The amount of gas to pay bundler to compensate for the pre-verification execution and
+    /// calldata
+    pub pre_verification_gas: U256,
+
+    /// The max priority fee per gas (high 128 bits) and the max fee per gas (low 128 bits)
+    pub gas_fees: H256,
+
+    /// Address of paymaster sponsoring the user operation, its verification and postOp gas
+    /// limits (16 bytes each), followed by extra data to send to the paymaster (can be empty)
+    pub paymaster_and_data: Bytes,
+
+    /// Data passed to the account along with the nonce during the verification step
+    pub signature: Bytes,
+}
+
+/// Packed user operation without signature (helper for calculating the hash)
+#[derive(EthAbiCodec, EthAbiType)]
+struct PackedUserOperationNoSignature {
+    pub sender: Address,
+    pub nonce: U256,
+    pub init_code: H256,
+    pub call_data: H256,
+    pub account_gas_limits: H256,
+    pub pre_verification_gas: U256,
+    pub gas_fees: H256,
+    pub paymaster_and_data: H256,
+}
+
+impl From<PackedUserOperation> for PackedUserOperationNoSignature {
+    fn from(value: PackedUserOperation) -> Self {
+        Self {
+            sender: value.sender,
+            nonce: value.nonce,
+            init_code: keccak256(value.init_code.deref()).into(),
+            call_data: keccak256(value.call_data.deref()).into(),
+            account_gas_limits: value.account_gas_limits,
+            pre_verification_gas: value.pre_verification_gas,
+            gas_fees: value.gas_fees,
+            paymaster_and_data: keccak256(value.paymaster_and_data.deref()).into(),
+        }
+    }
+}
+
+/// The `paymasterAndData` is passed as is, so it has to be in the layout of the entry point v0.7
+/// (see [pack_paymaster_and_data](pack_paymaster_and_data))
+impl TryFrom<UserOperationSigned> for PackedUserOperation {
+    type Error = PackingError;
+
+    fn try_from(value: UserOperationSigned) -> Result<Self, Self::Error> {
+        Ok(Self {
+            sender: value.sender,
+            nonce: value.nonce,
+            init_code: value.init_code,
+            call_data: value.call_data,
+            account_gas_limits: pack_uints(
+                ("verification gas limit", value.verification_gas_limit),
+                ("call gas limit", value.call_gas_limit),
+            )?,
+            pre_verification_gas: value.pre_verification_gas,
+            gas_fees: pack_uints(
+                ("max priority fee per gas", value.max_priority_fee_per_gas),
+                ("max fee per gas", value.max_fee_per_gas),
+            )?,
+            paymaster_and_data: value.paymaster_and_data,
+            signature: value.signature,
+        })
+    }
+}
+
+impl From<PackedUserOperation> for UserOperationSigned {
+    fn from(value: PackedUserOperation) -> Self {
+        let (verification_gas_limit, call_gas_limit) = unpack_uints(&value.account_gas_limits);
+        let (max_priority_fee_per_gas, max_fee_per_gas) = unpack_uints(&value.gas_fees);
+        Self {
+            sender: value.sender,
+            nonce: value.nonce,
+            init_code: value.init_code,
+            call_data: value.call_data,
+            call_gas_limit,
+            verification_gas_limit,
+            pre_verification_gas: value.pre_verification_gas,
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+            paymaster_and_data: value.paymaster_and_data,
+            signature: value.signature,
+        }
+    }
+}
+
+impl PackedUserOperation {
+    /// Packs the user operation into bytes
+    pub fn pack(&self) -> Bytes {
+        self.clone().encode().into()
+    }
+
+    /// Packs the user operation without signature to bytes (used for calculating the hash)
+    pub fn pack_without_signature(&self) -> Bytes {
+        PackedUserOperationNoSignature::from(self.clone()).encode().into()
+    }
+
+    /// Calculates the hash of the user operation (as `getUserOpHash` of the entry point v0.7)
+    pub fn hash(&self, entry_point: &Address, chain_id: u64) -> UserOperationHash {
+        H256::from_slice(
+            keccak256(
+                [
+                    keccak256(self.pack_without_signature().deref()).to_vec(),
+                    entry_point.encode(),
+                    U256::from(chain_id).encode(),
+                ]
+                .concat(),
+            )
+            .as_slice(),
+        )
+        .into()
+    }
+
+    pub fn verification_gas_limit(&self) -> U256 {
+        unpack_uints(&self.account_gas_limits).0
+    }
+
+    pub fn call_gas_limit(&self) -> U256 {
+        unpack_uints(&self.account_gas_limits).1
+    }
+
+    pub fn max_priority_fee_per_gas(&self) -> U256 {
+        unpack_uints(&self.gas_fees).0
+    }
+
+    pub fn max_fee_per_gas(&self) -> U256 {
+        unpack_uints(&self.gas_fees).1
+    }
+
+    pub fn paymaster(&self) -> Option<Address> {
+        get_address(&self.paymaster_and_data)
+    }
+
+    pub fn paymaster_verification_gas_limit(&self) -> U256 {
+        self.paymaster_and_data
+            .get(PAYMASTER_VALIDATION_GAS_OFFSET..PAYMASTER_POSTOP_GAS_OFFSET)
+            .map(U256::from_big_endian)
+            .unwrap_or_default()
+    }
+
+    pub fn paymaster_post_op_gas_limit(&self) -> U256 {
+        self.paymaster_and_data
+            .get(PAYMASTER_POSTOP_GAS_OFFSET..PAYMASTER_DATA_OFFSET)
+            .map(U256::from_big_endian)
+            .unwrap_or_default()
+    }
+
+    pub fn paymaster_data(&self) -> Bytes {
+        self.paymaster_and_data.get(PAYMASTER_DATA_OFFSET..).unwrap_or_default().to_vec().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENTRY_POINT_V07: &str = "0x0000000071727De22E5E9d8BAf0edAc6f37da032";
+
+    fn user_operations() -> Vec<UserOperationSigned> {
+        vec![
+            UserOperationSigned {
+                sender: "0x9c5754De1443984659E1b3a8d1931D83475ba29C".parse().unwrap(),
+                nonce: U256::zero(),
+                init_code: Bytes::default(),
+                call_data: "0xb61d27f60000000000000000000000009c5754de1443984659e1b3a8d1931d83475ba29c00000000000000000000000000000000000000000000000000005af3107a400000000000000000000000000000000000000000000000000000000000000000600000000000000000000000000000000000000000000000000000000000000000".parse().unwrap(),
+                call_gas_limit: 33_100.into(),
+                verification_gas_limit: 60_624.into(),
+                pre_verification_gas: 44_056.into(),
+                max_fee_per_gas: 1_695_000_030_u64.into(),
+                max_priority_fee_per_gas: 1_695_000_000.into(),
+                paymaster_and_data: Bytes::default(),
+                signature: "0x37540ca4f91a9f08993ba4ebd4b7473902f69864c98951f9db8cb47b78764c1a13ad46894a96dc0cad68f9207e49b4dbb897f25f47f040cec2a636a8201c1cd71b".parse().unwrap(),
+            },
+            UserOperationSigned {
+                sender: "0x9c5754De1443984659E1b3a8d1931D83475ba29C".parse().unwrap(),
+                nonce: 1.into(),
+                init_code: "0x9406cc6185a346906296840746125a0e449764545fbfb9cf000000000000000000000000ce0fefa6f7979c4c9b5373e0f5105b7259092c6d0000000000000000000000000000000000000000000000000000000000000000".parse().unwrap(),
+                call_data: "0xb61d27f60000000000000000000000009c5754de1443984659e1b3a8d1931d83475ba29c00000000000000000000000000000000000000000000000000005af3107a400000000000000000000000000000000000000000000000000000000000000000600000000000000000000000000000000000000000000000000000000000000000".parse().unwrap(),
+                call_gas_limit: 33_100.into(),
+                verification_gas_limit: 361_460.into(),
+                pre_verification_gas: 44_980.into(),
+                max_fee_per_gas: 1_695_000_030_u64.into(),
+                max_priority_fee_per_gas: 1_695_000_000.into(),
+                paymaster_and_data: pack_paymaster_and_data(
+                    "0x1234567890123456789012345678901234567890".parse().unwrap(),
+                    100_000,
+                    50_000,
+                    &[0xde, 0xad, 0xbe, 0xef],
+                ),
+                signature: Bytes::default(),
+            },
+        ]
+    }
+
+    #[test]
+    fn packed_user_operation_conversion() {
+        let uos = user_operations();
+        let packed = PackedUserOperation::try_from(uos[1].clone()).unwrap();
+
+        assert_eq!(
+            packed.account_gas_limits,
+            "0x000000000000000000000000000583f40000000000000000000000000000814c"
+                .parse::<H256>()
+                .unwrap()
+        );
+        assert_eq!(
+            packed.gas_fees,
+            "0x0000000000000000000000006507a5c00000000000000000000000006507a5de"
+                .parse::<H256>()
+                .unwrap()
+        );
+        assert_eq!(packed.verification_gas_limit(), 361_460.into());
+        assert_eq!(packed.call_gas_limit(), 33_100.into());
+        assert_eq!(packed.max_priority_fee_per_gas(), 1_695_000_000.into());
+        assert_eq!(packed.max_fee_per_gas(), 1_695_000_030_u64.into());
+        assert_eq!(
+            packed.paymaster(),
+            Some("0x1234567890123456789012345678901234567890".parse().unwrap())
+        );
+        assert_eq!(packed.paymaster_verification_gas_limit(), 100_000.into());
+        assert_eq!(packed.paymaster_post_op_gas_limit(), 50_000.into());
+        assert_eq!(packed.paymaster_data(), "0xdeadbeef".parse::<Bytes>().unwrap());
+
+        // without paymaster
+        let packed = PackedUserOperation::try_from(uos[0].clone()).unwrap();
+        assert_eq!(packed.paymaster(), None);
+        assert_eq!(packed.paymaster_verification_gas_limit(), U256::zero());
+        assert_eq!(packed.paymaster_data(), Bytes::default());
+
+        for uo in uos {
+            assert_eq!(
+                UserOperationSigned::from(PackedUserOperation::try_from(uo.clone()).unwrap()),
+                uo
+            );
+        }
+    }
+
+    #[test]
+    fn packed_user_operation_overflow() {
+        let uo = user_operations()[0].clone();
+        let max = U256::from(u128::MAX);
+
+        let packed = PackedUserOperation::try_from(
+            uo.clone().verification_gas_limit(max).max_fee_per_gas(max),
+        )
+        .unwrap();
+        assert_eq!(packed.verification_gas_limit(), max);
+        assert_eq!(packed.max_fee_per_gas(), max);
+
+        assert_eq!(
+            PackedUserOperation::try_from(uo.clone().call_gas_limit(max + 1)),
+            Err(PackingError { field: "call gas limit", value: max + 1 })
+        );
+        assert_eq!(
+            PackedUserOperation::try_from(uo.max_priority_fee_per_gas(U256::MAX)),
+            Err(PackingError { field: "max priority fee per gas", value: U256::MAX })
+        );
+    }
+
+    #[test]
+    fn packed_user_operation_pack_without_signature() {
+        let uos = user_operations();
+        assert_eq!(PackedUserOperation::try_from(uos[0].clone()).unwrap().pack_without_signature(), "0x0000000000000000000000009c5754de1443984659e1b3a8d1931d83475ba29c0000000000000000000000000000000000000000000000000000000000000000c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470f7def7aeb687d6992b466243b713223689982cefca0f91a1f5c5f60adb532b930000000000000000000000000000ecd00000000000000000000000000000814c000000000000000000000000000000000000000000000000000000000000ac180000000000000000000000006507a5c00000000000000000000000006507a5dec5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470".parse::<Bytes>().unwrap());
+        assert_eq!(PackedUserOperation::try_from(uos[1].clone()).unwrap().pack_without_signature(), "0x0000000000000000000000009c5754de1443984659e1b3a8d1931d83475ba29c00000000000000000000000000000000000000000000000000000000000000017c9a3ecef0c0e50c8a3d223f45d1f69e7436194eb9c7f223db0c633a7a7d7d4df7def7aeb687d6992b466243b713223689982cefca0f91a1f5c5f60adb532b93000000000000000000000000000583f40000000000000000000000000000814c000000000000000000000000000000000000000000000000000000000000afb40000000000000000000000006507a5c00000000000000000000000006507a5de1fe8bf63e69e99be7262c8860ea58f84dd43164ad280c495d7097bda7b63a988".parse::<Bytes>().unwrap());
+    }
+
+    #[test]
+    fn packed_user_operation_hash() {
+        let uos = user_operations();
+        let entry_point = ENTRY_POINT_V07.parse().unwrap();
+        assert_eq!(
+            PackedUserOperation::try_from(uos[0].clone()).unwrap().hash(&entry_point, 11_155_111),
+            "0xfaeb7b63e77d1258012b76ac984fdfb5b3ab69e1083b582171591a90305a6138"
+                .parse::<H256>()
+                .unwrap()
+                .into()
+        );
+        assert_eq!(
+            PackedUserOperation::try_from(uos[1].clone()).unwrap().hash(&entry_point, 1),
+            "0x3874f2378a1c3e403869ccb889e3eb8c5e30f5086efe7ca834ef27d2b2b73519"
+                .parse::<H256>()
+                .unwrap()
+                .into()
+        );
+    }
+}
@@ -1,9 +1,13 @@
 //! User operation request (optional fields)
 
 use super::UserOperationSigned;
-use crate::utils::{as_checksum_addr, as_checksum_bytes};
+use crate::{
+    constants::mempool::DUMMY_SIGNATURE,
+    utils::{as_checksum_addr, as_checksum_bytes},
+};
 use ethers::types::{Address, Bytes, U256};
-use serde::{Deserialize, Serialize};
+use serde::{de::IgnoredAny, Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// User operation with all fields being optional
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -16,7 +20,7 @@ pub struct UserOperationRequest {
     #[serde(default, serialize_with = "as_checksum_bytes")]
     pub init_code: Bytes,
     #[serde(default)]
-    pub call_data: Bytes,
+    pub call_data: Option<Bytes>,
     #[serde(default)]
     pub call_gas_limit: Option<U256>,
     #[serde(default)]
@@ -31,6 +35,58 @@ pub struct UserOperationRequest {
     pub paymaster_and_data: Bytes,
     #[serde(default)]
     pub signature: Option<Bytes>,
+    /// Fields sent by the client which are not part of the user operation
+    #[serde(flatten, skip_serializing)]
+    pub unknown_fields: BTreeMap<String, IgnoredAny>,
+}
+
+impl UserOperationRequest {
+    /// Checks whether the request carries fields which are not part of the user operation
+    ///
+    /// # Arguments
+    /// `deny_unknown_fields` - Whether unknown fields should be rejected instead of ignored
+    ///
+    /// # Returns
+    /// `Result<(), String>` - Error message listing the unknown fields if they are rejected
+    pub fn check_unknown_fields(&self, deny_unknown_fields: bool) -> Result<(), String> {
+        if deny_unknown_fields && !self.unknown_fields.is_empty() {
+            return Err(format!(
+                "User operation contains unknown fields: {}",
+                self.unknown_fields.keys().cloned().collect::<Vec<_>>().join(", ")
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Completes a (partial) user operation sent for the gas estimation. The sender and the call
+    /// data are required, the missing gas and fee fields are left at zero (estimated by the
+    /// bundler) and the missing signature is replaced by a dummy signature.
+    ///
+    /// # Returns
+    /// `Result<UserOperationSigned, String>` - The completed user operation or the error message
+    /// listing the missing required fields
+    pub fn into_estimation_user_operation(self) -> Result<UserOperationSigned, String> {
+        let mut missing = vec![];
+        if self.sender.is_zero() {
+            missing.push("sender");
+        }
+        if self.call_data.is_none() {
+            missing.push("callData");
+        }
+        if !missing.is_empty() {
+            return Err(format!(
+                "User operation is missing required fields: {}",
+                missing.join(", ")
+            ));
+        }
+
+        let signature = self
+            .signature
+            .clone()
+            .unwrap_or_else(|| DUMMY_SIGNATURE.parse().expect("dummy signature is valid"));
+        Ok(UserOperationSigned { signature, ..self.into() })
+    }
 }
 
 impl From<UserOperationRequest> for UserOperationSigned {
@@ -39,7 +95,7 @@ impl From<UserOperationRequest> for UserOperationSigned {
             sender: user_operation.sender,
             nonce: user_operation.nonce,
             init_code: user_operation.init_code,
-            call_data: user_operation.call_data,
+            call_data: user_operation.call_data.unwrap_or_default(),
             call_gas_limit: {
                 if let Some(call_gas_limit) = user_operation.call_gas_limit {
                     call_gas_limit
@@ -87,7 +143,7 @@ impl From<UserOperationSigned> for UserOperationRequest {
             sender: user_operation.sender,
             nonce: user_operation.nonce,
             init_code: user_operation.init_code,
-            call_data: user_operation.call_data,
+            call_data: Some(user_operation.call_data),
             call_gas_limit: Some(user_operation.call_gas_limit),
             verification_gas_limit: Some(user_operation.verification_gas_limit),
             pre_verification_gas: Some(user_operation.pre_verification_gas),
@@ -95,6 +151,7 @@ impl From<UserOperationSigned> for UserOperationRequest {
             max_priority_fee_per_gas: Some(user_operation.max_priority_fee_per_gas),
             paymaster_and_data: user_operation.paymaster_and_data,
             signature: Some(user_operation.signature),
+            unknown_fields: BTreeMap::new(),
         }
     }
 }
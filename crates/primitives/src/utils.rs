@@ -14,15 +14,17 @@ where
     s.serialize_str(&to_checksum(val, None))
 }
 
+/// Length of an address (in bytes)
+pub const ADDRESS_LENGTH: usize = 20;
+
 /// Converts bytes to checksum (first 20 bytes are address)
 pub fn as_checksum_bytes<S>(val: &Bytes, s: S) -> Result<S::Ok, S::Error>
 where
     S: serde::Serializer,
 {
     let mut str = hex::encode_prefixed(val);
-    s.serialize_str(if val.len() >= 20 {
-        let addr = Address::from_slice(&val[0..20]);
-        str.replace_range(0..42, &to_checksum(&addr, None));
+    s.serialize_str(if let Some(addr) = get_address(val) {
+        str.replace_range(0..2 + 2 * ADDRESS_LENGTH, &to_checksum(&addr, None));
         &str
     } else {
         &str
@@ -58,8 +60,8 @@ where
 
 /// If possible, parses address from the first 20 bytes
 pub fn get_address(buf: &[u8]) -> Option<Address> {
-    if buf.len() >= 20 {
-        Some(Address::from_slice(&buf[0..20]))
+    if buf.len() >= ADDRESS_LENGTH {
+        Some(Address::from_slice(&buf[0..ADDRESS_LENGTH]))
     } else {
         None
     }
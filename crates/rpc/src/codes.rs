@@ -9,3 +9,5 @@ pub const SIGNATURE: i32 = -32507;
 pub const EXECUTION: i32 = -32521;
 pub const USER_OPERATION_HASH: i32 = -32601;
 pub const SANITY: i32 = -32602;
+pub const UNAUTHORIZED: i32 = -32001;
+pub const LIMIT_EXCEEDED: i32 = -32005;
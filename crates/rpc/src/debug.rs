@@ -1,5 +1,5 @@
 use crate::{
-    debug_api::{DebugApiServer, ResponseSuccess},
+    debug_api::{DebugApiServer, MempoolCursor, MempoolPage, ResponseSuccess},
     error::JsonRpcError,
 };
 use async_trait::async_trait;
@@ -9,14 +9,18 @@ use jsonrpsee::{
     types::{error::INTERNAL_ERROR_CODE, ErrorObjectOwned},
 };
 use silius_grpc::{
-    bundler_client::BundlerClient, uo_pool_client::UoPoolClient, AddMempoolRequest,
+    bundler_client::BundlerClient, uo_pool_client::UoPoolClient, AddMempoolRequest, AddRequest,
     GetAllReputationRequest, GetAllRequest, GetStakeInfoRequest, Mode as GrpcMode,
-    SetBundleModeRequest, SetReputationRequest, SetReputationResult,
+    SetBeneficiaryRequest, SetBundleModeRequest, SetPinnedRequest, SetReputationRequest,
+    SetReputationResult,
 };
+use silius_mempool::{validate::ValidationTrace, MempoolError};
 use silius_primitives::{
     constants::bundler::BUNDLE_INTERVAL,
+    p2p::PeerDetails,
     reputation::{ReputationEntry, StakeInfoResponse},
-    BundleMode, UserOperation, UserOperationRequest, UserOperationSigned,
+    BundleMode, ChainContext, MempoolStats, SimulationOutcome, UserOperation, UserOperationHash,
+    UserOperationRequest, UserOperationSigned,
 };
 use tonic::Request;
 
@@ -25,6 +29,8 @@ use tonic::Request;
 pub struct DebugApiServerImpl {
     pub uopool_grpc_client: UoPoolClient<tonic::transport::Channel>,
     pub bundler_grpc_client: BundlerClient<tonic::transport::Channel>,
+    /// Effective configuration of the node (secrets redacted)
+    pub config: Option<serde_json::Value>,
 }
 
 #[async_trait]
@@ -63,6 +69,23 @@ impl DebugApiServer for DebugApiServerImpl {
         Ok(ResponseSuccess::Ok)
     }
 
+    /// Runs the hourly update (decay) of the reputation immediately
+    ///
+    ///
+    /// # Returns
+    /// * `RpcResult<ResponseSuccess>` - Ok
+    async fn update_reputation(&self) -> RpcResult<ResponseSuccess> {
+        let mut uopool_grpc_client = self.uopool_grpc_client.clone();
+
+        uopool_grpc_client
+            .update_reputation(Request::new(()))
+            .await
+            .map_err(JsonRpcError::from)?
+            .into_inner();
+
+        Ok(ResponseSuccess::Ok)
+    }
+
     /// Clears the bundler mempool and reputation
     ///
     ///
@@ -122,28 +145,156 @@ impl DebugApiServer for DebugApiServerImpl {
         Ok(ResponseSuccess::Ok)
     }
 
+    /// Simulate the [UserOperation](UserOperationRequest) via the [AddRequest](AddRequest) without
+    /// adding it to the mempool or updating the reputation.
+    ///
+    /// # Arguments
+    /// * `user_operation: UserOperationRequest` - The [UserOperation](UserOperationRequest) to be
+    ///   simulated.
+    /// * `entry_point: Address` - The address of the entry point.
+    ///
+    /// # Returns
+    /// * `RpcResult<SimulationOutcome>` - The outcome of the validation.
+    async fn simulate_user_operation(
+        &self,
+        uo: UserOperationRequest,
+        ep: Address,
+    ) -> RpcResult<SimulationOutcome> {
+        let mut uopool_grpc_client = self.uopool_grpc_client.clone();
+
+        let res = uopool_grpc_client
+            .get_chain_id(Request::new(()))
+            .await
+            .map_err(JsonRpcError::from)?
+            .into_inner();
+
+        let uo: UserOperationSigned = uo.into();
+
+        let req = Request::new(AddRequest {
+            uo: Some(
+                UserOperation::from_user_operation_signed(uo.hash(&ep, res.chain_id), uo).into(),
+            ),
+            ep: Some(ep.into()),
+        });
+
+        let res = uopool_grpc_client.simulate(req).await.map_err(JsonRpcError::from)?.into_inner();
+
+        if let Some(outcome) = res.outcome {
+            return Ok(outcome.into());
+        }
+
+        Err(JsonRpcError::from(
+            serde_json::from_str::<MempoolError>(&res.data).map_err(JsonRpcError::from)?,
+        )
+        .0)
+    }
+
+    /// Trace the validation of the [UserOperation](UserOperationRequest) via the
+    /// [AddRequest](AddRequest) without adding it to the mempool or updating the reputation.
+    ///
+    /// # Arguments
+    /// * `user_operation: UserOperationRequest` - The [UserOperation](UserOperationRequest) to be
+    ///   traced.
+    /// * `entry_point: Address` - The address of the entry point.
+    ///
+    /// # Returns
+    /// * `RpcResult<ValidationTrace>` - The validation trace.
+    async fn trace_user_operation_validation(
+        &self,
+        uo: UserOperationRequest,
+        ep: Address,
+    ) -> RpcResult<ValidationTrace> {
+        let mut uopool_grpc_client = self.uopool_grpc_client.clone();
+
+        let res = uopool_grpc_client
+            .get_chain_id(Request::new(()))
+            .await
+            .map_err(JsonRpcError::from)?
+            .into_inner();
+
+        let uo: UserOperationSigned = uo.into();
+
+        let req = Request::new(AddRequest {
+            uo: Some(
+                UserOperation::from_user_operation_signed(uo.hash(&ep, res.chain_id), uo).into(),
+            ),
+            ep: Some(ep.into()),
+        });
+
+        let res = uopool_grpc_client
+            .trace_validation(req)
+            .await
+            .map_err(JsonRpcError::from)?
+            .into_inner();
+
+        if !res.trace.is_empty() {
+            return serde_json::from_str::<ValidationTrace>(&res.trace)
+                .map_err(|err| JsonRpcError::from(err).0);
+        }
+
+        Err(JsonRpcError::from(
+            serde_json::from_str::<MempoolError>(&res.data).map_err(JsonRpcError::from)?,
+        )
+        .0)
+    }
+
     /// Sending an [GetAllRequest](GetAllRequest) to the UoPool gRPC server
-    /// to get all of the [UserOperation](UserOperationRequest) in the mempool.
+    /// to get the [UserOperations](UserOperationRequest) in the mempool.
     ///
     /// # Arguments
     /// * `entry_point: Address` - The address of the entry point.
     ///
     /// # Returns
     /// * `RpcResult<Vec<UserOperationRequest>>` - An array of [UserOperation](UserOperationRequest)
+    ///   sorted by sender and nonce (the first page if the bundler sets a maximum dump size)
     async fn dump_mempool(&self, ep: Address) -> RpcResult<Vec<UserOperationRequest>> {
+        Ok(self.dump_mempool_page(ep, None, None).await?.user_operations)
+    }
+
+    /// Sending an [GetAllRequest](GetAllRequest) to the UoPool gRPC server
+    /// to get a page of the [UserOperations](UserOperationRequest) in the mempool along with the
+    /// cursor of the next page.
+    ///
+    /// # Arguments
+    /// * `entry_point: Address` - The address of the entry point.
+    /// * `cursor: Option<MempoolCursor>` - The cursor returned with the previous page (the first
+    ///   page if None).
+    /// * `page_size: Option<u64>` - The maximum number of user operations returned (capped at the
+    ///   maximum dump size of the bundler).
+    ///
+    /// # Returns
+    /// * `RpcResult<MempoolPage>` - The [MempoolPage](MempoolPage)
+    async fn dump_mempool_page(
+        &self,
+        ep: Address,
+        cursor: Option<MempoolCursor>,
+        page_size: Option<u64>,
+    ) -> RpcResult<MempoolPage> {
         let mut uopool_grpc_client = self.uopool_grpc_client.clone();
 
-        let req = Request::new(GetAllRequest { ep: Some(ep.into()) });
+        let req = Request::new(GetAllRequest {
+            ep: Some(ep.into()),
+            after_sender: cursor.map(|cursor| cursor.sender.into()),
+            limit: page_size.unwrap_or_default(),
+            after_nonce: cursor.map(|cursor| cursor.nonce.into()),
+        });
 
         let res = uopool_grpc_client.get_all(req).await.map_err(JsonRpcError::from)?.into_inner();
 
-        let mut uos: Vec<UserOperationRequest> = res
-            .uos
-            .iter()
-            .map(|uo| UserOperation::from(uo.clone()).user_operation.into())
-            .collect();
-        uos.sort_by(|a, b| a.nonce.cmp(&b.nonce));
-        Ok(uos)
+        let next_cursor = res.next_sender.clone().map(|sender| MempoolCursor {
+            sender: sender.into(),
+            nonce: res.next_nonce.clone().map(Into::into).unwrap_or_default(),
+        });
+        Ok(MempoolPage {
+            sources: res.sources().map(Into::into).collect(),
+            user_operations: res
+                .uos
+                .into_iter()
+                .map(|uo| UserOperation::from(uo).user_operation.into())
+                .collect(),
+            next_cursor,
+            total: res.total,
+        })
     }
 
     /// Set the reputations for the given array of [ReputationEntry](ReputationEntry)
@@ -154,6 +305,8 @@ impl DebugApiServer for DebugApiServerImpl {
     /// * `reputation_entries: Vec<ReputationEntry>` - The [ReputationEntry](ReputationEntry) to be
     ///   set.
     /// * `entry_point: Address` - The address of the entry point.
+    /// * `overwrite: Option<bool>` - Whether to overwrite the existing entries instead of merging
+    ///   them (default).
     ///
     /// # Returns
     /// * `RpcResult<ResponseSuccess>` - Ok
@@ -161,12 +314,14 @@ impl DebugApiServer for DebugApiServerImpl {
         &self,
         entries: Vec<ReputationEntry>,
         ep: Address,
+        overwrite: Option<bool>,
     ) -> RpcResult<ResponseSuccess> {
         let mut uopool_grpc_client = self.uopool_grpc_client.clone();
 
         let req = Request::new(SetReputationRequest {
             rep: entries.iter().map(|re| re.clone().into()).collect(),
             ep: Some(ep.into()),
+            overwrite: overwrite.unwrap_or(false),
         });
 
         let res =
@@ -243,6 +398,41 @@ impl DebugApiServer for DebugApiServerImpl {
         }
     }
 
+    /// Returns the bundler beneficiary addresses.
+    ///
+    /// # Returns
+    /// * `RpcResult<Vec<Address>>` - The beneficiary addresses the bundles are paid to in
+    ///   round-robin order.
+    async fn get_beneficiary(&self) -> RpcResult<Vec<Address>> {
+        let mut bundler_grpc_client = self.bundler_grpc_client.clone();
+
+        let req = Request::new(());
+
+        match bundler_grpc_client.get_beneficiary(req).await {
+            Ok(res) => Ok(res.into_inner().beneficiaries.into_iter().map(Into::into).collect()),
+            Err(s) => Err(JsonRpcError::from(s).into()),
+        }
+    }
+
+    /// Sets the bundler beneficiary address through the
+    /// [SetBeneficiaryRequest](SetBeneficiaryRequest).
+    ///
+    /// # Arguments
+    /// * `beneficiary: Address` - The new beneficiary address.
+    ///
+    /// # Returns
+    /// * `RpcResult<ResponseSuccess>` - Ok
+    async fn set_beneficiary(&self, beneficiary: Address) -> RpcResult<ResponseSuccess> {
+        let mut bundler_grpc_client = self.bundler_grpc_client.clone();
+
+        let req = Request::new(SetBeneficiaryRequest { beneficiary: Some(beneficiary.into()) });
+
+        match bundler_grpc_client.set_beneficiary(req).await {
+            Ok(_) => Ok(ResponseSuccess::Ok),
+            Err(s) => Err(JsonRpcError::from(s).into()),
+        }
+    }
+
     /// Returns the stake info of the given address.
     ///
     /// # Arguments
@@ -268,4 +458,122 @@ impl DebugApiServer for DebugApiServerImpl {
             Err(s) => Err(JsonRpcError::from(s).into()),
         }
     }
+
+    /// Pin the user operations with the given hashes for the next bundle through the
+    /// [SetPinnedRequest](SetPinnedRequest).
+    ///
+    /// # Arguments
+    /// * `user_operation_hashes: Vec<UserOperationHash>` - The hashes of the user operations to
+    ///   pin.
+    /// * `entry_point: Address` - The address of the entry point.
+    ///
+    /// # Returns
+    /// * `RpcResult<ResponseSuccess>` - Ok
+    async fn set_pinned_user_operations(
+        &self,
+        uo_hashes: Vec<UserOperationHash>,
+        ep: Address,
+    ) -> RpcResult<ResponseSuccess> {
+        let mut uopool_grpc_client = self.uopool_grpc_client.clone();
+
+        let req = Request::new(SetPinnedRequest {
+            hashes: uo_hashes.into_iter().map(Into::into).collect(),
+            ep: Some(ep.into()),
+        });
+
+        uopool_grpc_client.set_pinned(req).await.map_err(JsonRpcError::from)?;
+
+        Ok(ResponseSuccess::Ok)
+    }
+
+    /// Get the number of user operations in the mempool by submission source through the
+    /// [GetAllRequest](GetAllRequest).
+    ///
+    /// # Arguments
+    /// * `entry_point: Address` - The address of the entry point.
+    ///
+    /// # Returns
+    /// * `RpcResult<MempoolStats>` - The number of user operations by source.
+    async fn get_mempool_stats(&self, ep: Address) -> RpcResult<MempoolStats> {
+        let mut uopool_grpc_client = self.uopool_grpc_client.clone();
+
+        let req = Request::new(GetAllRequest { ep: Some(ep.into()), ..Default::default() });
+
+        let res = uopool_grpc_client
+            .get_mempool_stats(req)
+            .await
+            .map_err(JsonRpcError::from)?
+            .into_inner();
+
+        Ok(MempoolStats { rpc: res.rpc, p2p: res.p2p })
+    }
+
+    /// Get the chain and fee context as seen by the bundler through the
+    /// [GetAllRequest](GetAllRequest).
+    ///
+    /// # Arguments
+    /// * `entry_point: Address` - The address of the entry point.
+    ///
+    /// # Returns
+    /// * `RpcResult<ChainContext>` - The chain and fee context.
+    async fn get_chain_context(&self, ep: Address) -> RpcResult<ChainContext> {
+        let mut uopool_grpc_client = self.uopool_grpc_client.clone();
+
+        let req = Request::new(GetAllRequest { ep: Some(ep.into()), ..Default::default() });
+
+        let res = uopool_grpc_client
+            .get_chain_context(req)
+            .await
+            .map_err(JsonRpcError::from)?
+            .into_inner();
+
+        Ok(ChainContext {
+            chain_id: res.chain_id,
+            block_number: res.block_number,
+            block_hash: res.block_hash.unwrap_or_default().into(),
+            base_fee_per_gas: res.base_fee_per_gas.unwrap_or_default().into(),
+            min_priority_fee_per_gas: res.min_priority_fee_per_gas.unwrap_or_default().into(),
+            max_verification_gas: res.max_verification_gas.unwrap_or_default().into(),
+        })
+    }
+
+    /// Get the effective configuration of the node (secrets redacted).
+    ///
+    /// # Returns
+    /// * `RpcResult<serde_json::Value>` - The configuration.
+    async fn get_config(&self) -> RpcResult<serde_json::Value> {
+        self.config.clone().ok_or_else(|| {
+            ErrorObjectOwned::owned(
+                INTERNAL_ERROR_CODE,
+                "Configuration is not available".to_string(),
+                None::<bool>,
+            )
+        })
+    }
+
+    /// Get the peers the p2p node is connected to.
+    ///
+    /// # Returns
+    /// * `RpcResult<Vec<PeerDetails>>` - The connected peers with their metadata.
+    async fn get_peers(&self) -> RpcResult<Vec<PeerDetails>> {
+        let mut uopool_grpc_client = self.uopool_grpc_client.clone();
+
+        let res = uopool_grpc_client
+            .get_peers(Request::new(()))
+            .await
+            .map_err(JsonRpcError::from)?
+            .into_inner();
+
+        Ok(res
+            .peers
+            .into_iter()
+            .map(|peer| PeerDetails {
+                peer_id: peer.peer_id,
+                multiaddr: (!peer.multiaddr.is_empty()).then_some(peer.multiaddr),
+                supported_mempools: peer.supported_mempools,
+                score: peer.score,
+                direction: (!peer.direction.is_empty()).then_some(peer.direction),
+            })
+            .collect())
+    }
 }
@@ -1,10 +1,13 @@
 pub use crate::debug::DebugApiServerImpl;
-use ethers::types::{Address, H256};
+use ethers::types::{Address, H256, U256};
 use jsonrpsee::{core::RpcResult, proc_macros::rpc};
 use serde::{Deserialize, Serialize};
+use silius_mempool::validate::ValidationTrace;
 use silius_primitives::{
+    p2p::PeerDetails,
     reputation::{ReputationEntry, StakeInfoResponse},
-    BundleMode, UserOperationRequest,
+    BundleMode, ChainContext, MempoolStats, SimulationOutcome, UserOperationHash,
+    UserOperationRequest, UserOperationSource,
 };
 
 #[derive(Clone, Copy, Serialize, Deserialize)]
@@ -13,6 +16,26 @@ pub enum ResponseSuccess {
     Ok,
 }
 
+/// The sender and nonce of the last user operation of a page, from which the next page starts
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MempoolCursor {
+    pub sender: Address,
+    pub nonce: U256,
+}
+
+/// A page of the user operations in the mempool (see `debug_dumpMempool`)
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MempoolPage {
+    pub user_operations: Vec<UserOperationRequest>,
+    /// Sources of the user operations (in the same order)
+    pub sources: Vec<UserOperationSource>,
+    /// Cursor of the next page (None if this is the last one)
+    pub next_cursor: Option<MempoolCursor>,
+    /// Number of user operations in the mempool
+    pub total: u64,
+}
+
 /// The ERC-4337 `debug` namespace RPC methods trait
 #[rpc(server, namespace = "debug_bundler")]
 pub trait DebugApi {
@@ -32,6 +55,14 @@ pub trait DebugApi {
     #[method(name = "clearReputation")]
     async fn clear_reputation(&self) -> RpcResult<ResponseSuccess>;
 
+    /// Runs the hourly update (decay) of the reputation immediately
+    ///
+    ///
+    /// # Returns
+    /// * `RpcResult<ResponseSuccess>` - Ok
+    #[method(name = "updateReputation")]
+    async fn update_reputation(&self) -> RpcResult<ResponseSuccess>;
+
     /// Clears the bundler mempool and reputation
     ///
     ///
@@ -56,23 +87,87 @@ pub trait DebugApi {
         entry_point: Address,
     ) -> RpcResult<ResponseSuccess>;
 
-    /// Get all [UserOperations](UserOperationRequest) of the mempool
+    /// Simulate the [UserOperation](UserOperationRequest) by running the full validation without
+    /// adding it to the mempool or updating the reputation (e.g., for dry-runs).
+    ///
+    /// # Arguments
+    /// * `user_operation: UserOperationRequest` - The [UserOperation](UserOperationRequest) to be
+    ///   simulated.
+    /// * `entry_point: Address` - The address of the entry point.
+    ///
+    /// # Returns
+    /// * `RpcResult<SimulationOutcome>` - The outcome of the validation.
+    #[method(name = "simulateUserOperation")]
+    async fn simulate_user_operation(
+        &self,
+        user_operation: UserOperationRequest,
+        entry_point: Address,
+    ) -> RpcResult<SimulationOutcome>;
+
+    /// Trace the validation of the [UserOperation](UserOperationRequest) without adding it to the
+    /// mempool or updating the reputation (e.g., to debug why it's rejected).
+    ///
+    /// # Arguments
+    /// * `user_operation: UserOperationRequest` - The [UserOperation](UserOperationRequest) to be
+    ///   traced.
+    /// * `entry_point: Address` - The address of the entry point.
+    ///
+    /// # Returns
+    /// * `RpcResult<ValidationTrace>` - The validation trace, the accessed storage, the banned
+    ///   opcodes and the simulation trace check which failed (if any).
+    #[method(name = "traceUserOperationValidation")]
+    async fn trace_user_operation_validation(
+        &self,
+        user_operation: UserOperationRequest,
+        entry_point: Address,
+    ) -> RpcResult<ValidationTrace>;
+
+    /// Get the [UserOperations](UserOperationRequest) of the mempool (all of them, unless the
+    /// bundler sets a maximum dump size, in which case this is the first page of
+    /// `dumpMempoolPage`)
     ///
     /// # Arguments
     /// * `entry_point: Address` - The address of the entry point.
     ///
     /// # Returns
     /// * `RpcResult<Vec<UserOperationRequest>>` - A vector of
-    ///   [UserOperations](UserOperationRequest) returned
+    ///   [UserOperations](UserOperationRequest) returned, sorted by sender and nonce
     #[method(name = "dumpMempool")]
     async fn dump_mempool(&self, entry_point: Address) -> RpcResult<Vec<UserOperationRequest>>;
 
-    /// Set the reputations for the given array of [ReputationEntry](ReputationEntry)
+    /// Get a page of the [UserOperations](UserOperationRequest) of the mempool along with the
+    /// cursor of the next page, so operators can list large mempools. The user operations are
+    /// sorted by sender and nonce and the cursor is the last one of the page, so the pages don't
+    /// shift when user operations are added or removed in between. Also available as
+    /// `debug_dumpMempool`.
+    ///
+    /// # Arguments
+    /// * `entry_point: Address` - The address of the entry point.
+    /// * `cursor: Option<MempoolCursor>` - The cursor returned with the previous page (the first
+    ///   page if None).
+    /// * `page_size: Option<u64>` - The maximum number of user operations returned (capped at the
+    ///   maximum dump size of the bundler).
+    ///
+    /// # Returns
+    /// * `RpcResult<MempoolPage>` - The [MempoolPage](MempoolPage)
+    #[method(name = "dumpMempoolPage", aliases = ["debug_dumpMempool"])]
+    async fn dump_mempool_page(
+        &self,
+        entry_point: Address,
+        cursor: Option<MempoolCursor>,
+        page_size: Option<u64>,
+    ) -> RpcResult<MempoolPage>;
+
+    /// Set the reputations for the given array of [ReputationEntry](ReputationEntry). By default,
+    /// the entries are merged with the existing ones (e.g., when importing reputation exported
+    /// from another bundler via `dumpReputation`).
     ///
     /// # Arguments
     /// * `reputation_entries: Vec<ReputationEntry>` - The [ReputationEntry](ReputationEntry) to be
     ///   set.
     /// * `entry_point: Address` - The address of the entry point.
+    /// * `overwrite: Option<bool>` - Whether to overwrite the existing entries instead of merging
+    ///   them (default).
     ///
     /// # Returns
     /// * `RpcResult<ResponseSuccess>` - Ok
@@ -81,6 +176,7 @@ pub trait DebugApi {
         &self,
         reputation_entries: Vec<ReputationEntry>,
         entry_point: Address,
+        overwrite: Option<bool>,
     ) -> RpcResult<ResponseSuccess>;
 
     /// Return the all of [ReputationEntries](ReputationEntry) in the mempool.
@@ -113,6 +209,25 @@ pub trait DebugApi {
     #[method(name = "sendBundleNow")]
     async fn send_bundle_now(&self) -> RpcResult<H256>;
 
+    /// Returns the bundler beneficiary addresses.
+    ///
+    ///
+    /// # Returns
+    /// * `RpcResult<Vec<Address>>` - The beneficiary addresses the bundles are paid to in
+    ///   round-robin order.
+    #[method(name = "getBeneficiary")]
+    async fn get_beneficiary(&self) -> RpcResult<Vec<Address>>;
+
+    /// Sets the bundler beneficiary address (used for the subsequent bundles).
+    ///
+    /// # Arguments
+    /// * `beneficiary: Address` - The new beneficiary address (must be non-zero).
+    ///
+    /// # Returns
+    /// * `RpcResult<ResponseSuccess>` - Ok
+    #[method(name = "setBeneficiary")]
+    async fn set_beneficiary(&self, beneficiary: Address) -> RpcResult<ResponseSuccess>;
+
     /// Returns the stake info of the given address.
     ///
     /// # Arguments
@@ -127,4 +242,61 @@ pub trait DebugApi {
         address: Address,
         entry_point: Address,
     ) -> RpcResult<StakeInfoResponse>;
+
+    /// Pin the [UserOperations](UserOperationRequest) with the given hashes, so they are included
+    /// in the next bundle regardless of fee ordering (still subject to the bundle gas limit and
+    /// validity). Replaces the previously pinned user operations (an empty array unpins all).
+    ///
+    /// # Arguments
+    /// * `user_operation_hashes: Vec<UserOperationHash>` - The hashes of the user operations to
+    ///   pin.
+    /// * `entry_point: Address` - The address of the entry point.
+    ///
+    /// # Returns
+    /// * `RpcResult<ResponseSuccess>` - Ok
+    #[method(name = "setPinnedUserOperations")]
+    async fn set_pinned_user_operations(
+        &self,
+        user_operation_hashes: Vec<UserOperationHash>,
+        entry_point: Address,
+    ) -> RpcResult<ResponseSuccess>;
+
+    /// Get the number of user operations in the mempool by submission source (RPC or P2P).
+    ///
+    /// # Arguments
+    /// * `entry_point: Address` - The address of the entry point.
+    ///
+    /// # Returns
+    /// * `RpcResult<MempoolStats>` - The number of user operations by source.
+    #[method(name = "getMempoolStats")]
+    async fn get_mempool_stats(&self, entry_point: Address) -> RpcResult<MempoolStats>;
+
+    /// Get the chain and fee context as seen by the bundler (useful for debugging fee
+    /// rejections).
+    ///
+    /// # Arguments
+    /// * `entry_point: Address` - The address of the entry point.
+    ///
+    /// # Returns
+    /// * `RpcResult<ChainContext>` - Chain id, latest block, base fee, min priority fee and max
+    ///   verification gas.
+    #[method(name = "getChainContext")]
+    async fn get_chain_context(&self, entry_point: Address) -> RpcResult<ChainContext>;
+
+    /// Get the effective configuration of the node (CLI args merged with the defaults), with
+    /// secrets (e.g., the wallet key) redacted.
+    ///
+    /// # Returns
+    /// * `RpcResult<serde_json::Value>` - The configuration.
+    #[method(name = "getConfig")]
+    async fn get_config(&self) -> RpcResult<serde_json::Value>;
+
+    /// Get the peers the p2p node is connected to, with their metadata (empty when p2p is
+    /// disabled).
+    ///
+    /// # Returns
+    /// * `RpcResult<Vec<PeerDetails>>` - Peer id, multiaddr, supported mempools, score and
+    ///   connection direction of the connected peers.
+    #[method(name = "getPeers")]
+    async fn get_peers(&self) -> RpcResult<Vec<PeerDetails>>;
 }
@@ -37,6 +37,13 @@ impl From<MempoolError> for JsonRpcError {
                 InvalidMempoolUserOperationError::Simulation(err) => err.into(),
                 InvalidMempoolUserOperationError::Reputation(err) => err.into(),
             },
+            MempoolErrorKind::MempoolFull { .. } |
+            MempoolErrorKind::DelayedQueueFull { .. } |
+            MempoolErrorKind::ReplacementUnderpriced { .. } |
+            MempoolErrorKind::HashCollision { .. } |
+            MempoolErrorKind::NonceGap { .. } => {
+                ErrorObject::owned(SANITY, err.to_string(), None::<bool>).into()
+            }
             _ => ErrorObject::owned(INTERNAL_ERROR_CODE, err.to_string(), None::<bool>).into(),
         }
     }
@@ -98,9 +105,24 @@ impl From<SanityError> for JsonRpcError {
             SanityError::Paymaster { inner: _ } => {
                 ErrorObject::owned(SANITY, err.to_string(), None::<bool>)
             }
+            SanityError::CallData { inner: _ } => {
+                ErrorObject::owned(SANITY, err.to_string(), None::<bool>)
+            }
             SanityError::Sender { inner: _ } => {
                 ErrorObject::owned(SANITY, err.to_string(), None::<bool>)
             }
+            SanityError::MalformedFields { inner: _ } => {
+                ErrorObject::owned(SANITY, err.to_string(), None::<bool>)
+            }
+            SanityError::SimulationGasTooHigh { simulation_gas: _, max_simulation_gas: _ } => {
+                ErrorObject::owned(SANITY, err.to_string(), None::<bool>)
+            }
+            SanityError::InsufficientPrefund { funds: _, required_prefund: _ } => {
+                ErrorObject::owned(SANITY, err.to_string(), None::<bool>)
+            }
+            SanityError::StaleBlock { age: _, acceptance_window: _ } => {
+                ErrorObject::owned(SANITY, err.to_string(), None::<bool>)
+            }
             SanityError::EntityRoles { entity: _, address: _, entity_other: _ } => {
                 ErrorObject::owned(OPCODE, err.to_string(), None::<bool>)
             }
@@ -120,16 +142,16 @@ impl From<SimulationError> for JsonRpcError {
             SimulationError::Timestamp { inner: _ } => {
                 ErrorObject::owned(TIMESTAMP, err.to_string(), None::<bool>)
             }
-            SimulationError::Validation { inner: _ } => {
+            SimulationError::Validation { inner: _ } | SimulationError::Nonce { inner: _ } => {
                 ErrorObject::owned(VALIDATION, err.to_string(), None::<bool>)
             }
             SimulationError::Execution { inner: _ } => {
                 ErrorObject::owned(EXECUTION, err.to_string(), None::<bool>)
             }
-            SimulationError::Opcode { entity: _, opcode: _ } => {
+            SimulationError::Opcode { .. } => {
                 ErrorObject::owned(OPCODE, err.to_string(), None::<bool>)
             }
-            SimulationError::StorageAccess { slot: _ } => {
+            SimulationError::StorageAccess { .. } => {
                 ErrorObject::owned(OPCODE, err.to_string(), None::<bool>)
             }
             SimulationError::Unstaked { entity: _, address: _, inner: _ } => {
@@ -1,18 +1,28 @@
-use crate::{codes::USER_OPERATION_HASH, error::JsonRpcError, eth_api::EthApiServer};
+use crate::{
+    codes::USER_OPERATION_HASH,
+    error::JsonRpcError,
+    eth_api::{EthApiServer, SendUserOperationResult},
+};
 use async_trait::async_trait;
 use ethers::{
     types::{Address, U64},
     utils::to_checksum,
 };
-use jsonrpsee::{core::RpcResult, types::ErrorObjectOwned};
+use jsonrpsee::{
+    core::{RpcResult, SubscriptionResult},
+    types::{error::INVALID_PARAMS_CODE, ErrorObjectOwned},
+    PendingSubscriptionSink, SubscriptionMessage,
+};
 use silius_grpc::{
     uo_pool_client::UoPoolClient, AddRequest, AddResult, EstimateUserOperationGasRequest,
     EstimateUserOperationGasResult, UserOperationHashRequest,
 };
 use silius_mempool::MempoolError;
 use silius_primitives::{
-    UserOperation, UserOperationByHash, UserOperationGasEstimation, UserOperationHash,
-    UserOperationReceipt, UserOperationRequest, UserOperationSigned,
+    constants::rpc::{MAX_BATCH_USER_OPERATIONS, NEW_USER_OPERATIONS_SUBSCRIPTION},
+    simulation::StateOverride,
+    NewUserOperationNotification, UserOperation, UserOperationByHash, UserOperationGasEstimation,
+    UserOperationHash, UserOperationReceipt, UserOperationRequest, UserOperationSigned,
 };
 use std::str::FromStr;
 use tonic::Request;
@@ -22,6 +32,70 @@ use tonic::Request;
 pub struct EthApiServerImpl {
     /// The [UoPool gRPC client](UoPoolClient).
     pub uopool_grpc_client: UoPoolClient<tonic::transport::Channel>,
+    /// Whether user operations with unknown fields are rejected (otherwise the fields are
+    /// ignored).
+    pub deny_unknown_fields: bool,
+}
+
+impl EthApiServerImpl {
+    /// Retrieve the chain ID from the uopool gRPC service.
+    async fn grpc_chain_id(&self) -> RpcResult<u64> {
+        let mut uopool_grpc_client = self.uopool_grpc_client.clone();
+
+        let res = uopool_grpc_client
+            .get_chain_id(Request::new(()))
+            .await
+            .map_err(JsonRpcError::from)?
+            .into_inner();
+
+        Ok(res.chain_id)
+    }
+
+    /// Add a user operation to the mempool via the [AddRequest](AddRequest).
+    ///
+    /// # Arguments
+    /// * `uo: UserOperationRequest` - The user operation to be added.
+    /// * `ep: Address` - The address of the entry point.
+    /// * `chain_id: u64` - The chain ID (part of the user operation hash).
+    ///
+    /// # Returns
+    /// * `RpcResult<UserOperationHash>` - The hash of the added user operation.
+    async fn add_user_operation(
+        &self,
+        uo: UserOperationRequest,
+        ep: Address,
+        chain_id: u64,
+    ) -> RpcResult<UserOperationHash> {
+        uo.check_unknown_fields(self.deny_unknown_fields)
+            .map_err(|err| ErrorObjectOwned::owned(INVALID_PARAMS_CODE, err, None::<bool>))?;
+
+        let mut uopool_grpc_client = self.uopool_grpc_client.clone();
+
+        let uo: UserOperationSigned = uo.into();
+
+        // the mempool hashes the user operation again for the version of the entry point (the
+        // returned hash is the one of the mempool)
+        let req = Request::new(AddRequest {
+            uo: Some(
+                UserOperation::from_user_operation_signed(uo.hash(&ep, chain_id), uo.clone())
+                    .into(),
+            ),
+            ep: Some(ep.into()),
+        });
+
+        let res = uopool_grpc_client.add(req).await.map_err(JsonRpcError::from)?.into_inner();
+
+        if res.res == AddResult::Added as i32 {
+            let uo_hash =
+                serde_json::from_str::<UserOperationHash>(&res.data).map_err(JsonRpcError::from)?;
+            return Ok(uo_hash);
+        }
+
+        Err(JsonRpcError::from(
+            serde_json::from_str::<MempoolError>(&res.data).map_err(JsonRpcError::from)?,
+        )
+        .0)
+    }
 }
 
 #[async_trait]
@@ -71,46 +145,60 @@ impl EthApiServer for EthApiServerImpl {
         uo: UserOperationRequest,
         ep: Address,
     ) -> RpcResult<UserOperationHash> {
-        let mut uopool_grpc_client = self.uopool_grpc_client.clone();
-
-        let res = uopool_grpc_client
-            .get_chain_id(Request::new(()))
-            .await
-            .map_err(JsonRpcError::from)?
-            .into_inner();
-
-        let uo: UserOperationSigned = uo.into();
+        let chain_id = self.grpc_chain_id().await?;
+        self.add_user_operation(uo, ep, chain_id).await
+    }
 
-        let req = Request::new(AddRequest {
-            uo: Some(
-                UserOperation::from_user_operation_signed(uo.hash(&ep, res.chain_id), uo.clone())
-                    .into(),
-            ),
-            ep: Some(ep.into()),
-        });
+    /// Send multiple user operations via the [AddRequest](AddRequest), one after another (in the
+    /// order they were sent).
+    ///
+    /// # Arguments
+    /// * `uos: Vec<(UserOperationRequest, Address)>` - The user operations to be sent, each with
+    ///   the address of its entry point.
+    ///
+    /// # Returns
+    /// * `RpcResult<Vec<SendUserOperationResult>>` - The hash of each sent user operation or the
+    ///   error it was rejected with (the whole batch is rejected if it has more than
+    ///   [MAX_BATCH_USER_OPERATIONS](MAX_BATCH_USER_OPERATIONS) user operations).
+    async fn send_user_operations(
+        &self,
+        uos: Vec<(UserOperationRequest, Address)>,
+    ) -> RpcResult<Vec<SendUserOperationResult>> {
+        if uos.len() > MAX_BATCH_USER_OPERATIONS {
+            return Err(ErrorObjectOwned::owned(
+                INVALID_PARAMS_CODE,
+                format!(
+                    "Batch of {} user operations exceeds the limit of {MAX_BATCH_USER_OPERATIONS}",
+                    uos.len()
+                ),
+                None::<bool>,
+            ));
+        }
 
-        let res = uopool_grpc_client.add(req).await.map_err(JsonRpcError::from)?.into_inner();
+        let chain_id = self.grpc_chain_id().await?;
 
-        if res.res == AddResult::Added as i32 {
-            let uo_hash =
-                serde_json::from_str::<UserOperationHash>(&res.data).map_err(JsonRpcError::from)?;
-            return Ok(uo_hash);
+        let mut res = Vec::with_capacity(uos.len());
+        for (uo, ep) in uos {
+            res.push(match self.add_user_operation(uo, ep, chain_id).await {
+                Ok(uo_hash) => SendUserOperationResult::Hash(uo_hash),
+                Err(err) => SendUserOperationResult::Error(err),
+            });
         }
 
-        Err(JsonRpcError::from(
-            serde_json::from_str::<MempoolError>(&res.data).map_err(JsonRpcError::from)?,
-        )
-        .0)
+        Ok(res)
     }
 
     /// Estimate the gas required for a [UserOperation](UserOperationRequest) via the
     /// [EstimateUserOperationGasRequest](EstimateUserOperationGasRequest). This allows you to
     /// gauge the computational cost of the operation. See [How ERC-4337 Gas Estimation Works](https://www.alchemy.com/blog/erc-4337-gas-estimation).
+    /// The user operation may be partial (only the sender and the call data are required).
     ///
     /// # Arguments
     /// * `user_operation: [UserOperation](UserOperationRequest)` - User operation for which to
     ///   estimate the gas.
     /// * `entry_point: Address` - The address of the entry point.
+    /// * `state_override: Option<StateOverride>` - The [StateOverride](StateOverride) the user
+    ///   operation is simulated against (optional).
     ///
     /// # Returns
     /// * `RpcResult<UserOperationGasEstimation>` - The
@@ -119,7 +207,22 @@ impl EthApiServer for EthApiServerImpl {
         &self,
         uo: UserOperationRequest,
         ep: Address,
+        state_override: Option<StateOverride>,
     ) -> RpcResult<UserOperationGasEstimation> {
+        let uo = uo
+            .into_estimation_user_operation()
+            .map_err(|err| ErrorObjectOwned::owned(INVALID_PARAMS_CODE, err, None::<bool>))?;
+
+        let state_override = match state_override {
+            Some(state_override) => {
+                state_override.validate().map_err(|err| {
+                    ErrorObjectOwned::owned(INVALID_PARAMS_CODE, err, None::<bool>)
+                })?;
+                serde_json::to_string(&state_override).map_err(JsonRpcError::from)?
+            }
+            None => String::new(),
+        };
+
         let mut uopool_grpc_client = self.uopool_grpc_client.clone();
 
         let res = uopool_grpc_client
@@ -128,8 +231,6 @@ impl EthApiServer for EthApiServerImpl {
             .map_err(JsonRpcError::from)?
             .into_inner();
 
-        let uo: UserOperationSigned = uo.into();
-
         let req: Request<EstimateUserOperationGasRequest> =
             Request::new(EstimateUserOperationGasRequest {
                 uo: Some(
@@ -140,6 +241,7 @@ impl EthApiServer for EthApiServerImpl {
                     .into(),
                 ),
                 ep: Some(ep.into()),
+                state_override,
             });
 
         let res = uopool_grpc_client
@@ -189,7 +291,7 @@ impl EthApiServer for EthApiServerImpl {
                                 actual_gas_cost: res.actual_gas_cost?.into(),
                                 actual_gas_used: res.actual_gas_used?.into(),
                                 success: res.success,
-                                reason: String::new(),
+                                reason: res.reason,
                                 logs: res.logs.into_iter().map(|l| l.into()).collect(),
                                 tx_receipt: res.tx_receipt?.into(),
                             })
@@ -267,4 +369,72 @@ impl EthApiServer for EthApiServerImpl {
             )),
         }
     }
+
+    /// Subscribe to the [UserOperations](UserOperation) added to the mempool via the
+    /// [UoPool gRPC](UoPoolClient) stream. The gRPC stream is closed (and the mempool receiver
+    /// dropped) once the client unsubscribes or disconnects.
+    ///
+    /// # Arguments
+    /// * `pending: PendingSubscriptionSink` - The pending subscription.
+    /// * `kind: String` - The kind of the subscription (only `newUserOperations` is supported).
+    /// * `full: Option<bool>` - Whether the notifications include the full user operations.
+    ///
+    /// # Returns
+    /// * `SubscriptionResult` - The result of the subscription.
+    async fn subscribe(
+        &self,
+        pending: PendingSubscriptionSink,
+        kind: String,
+        full: Option<bool>,
+    ) -> SubscriptionResult {
+        if kind != NEW_USER_OPERATIONS_SUBSCRIPTION {
+            pending
+                .reject(ErrorObjectOwned::owned(
+                    INVALID_PARAMS_CODE,
+                    format!("Unsupported subscription: {kind}"),
+                    None::<bool>,
+                ))
+                .await;
+            return Ok(());
+        }
+
+        let mut uopool_grpc_client = self.uopool_grpc_client.clone();
+
+        let mut new_uos = match uopool_grpc_client.subscribe_user_operations(Request::new(())).await
+        {
+            Ok(res) => res.into_inner(),
+            Err(err) => {
+                pending.reject(JsonRpcError::from(err).0).await;
+                return Ok(());
+            }
+        };
+
+        let sink = pending.accept().await?;
+        let full = full.unwrap_or(false);
+
+        loop {
+            let new_uo = tokio::select! {
+                _ = sink.closed() => break,
+                new_uo = new_uos.message() => match new_uo {
+                    Ok(Some(new_uo)) => new_uo,
+                    _ => break,
+                },
+            };
+
+            let (Some(uo), Some(ep)) = (new_uo.uo, new_uo.ep) else {
+                continue;
+            };
+            let uo: UserOperation = uo.into();
+
+            let notification = NewUserOperationNotification {
+                user_operation_hash: uo.hash,
+                entry_point: ep.into(),
+                user_operation: full.then_some(uo.user_operation),
+                cancels: new_uo.cancels.map(UserOperationHash::from),
+            };
+            sink.send(SubscriptionMessage::from_json(&notification)?).await?;
+        }
+
+        Ok(())
+    }
 }
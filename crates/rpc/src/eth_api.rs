@@ -1,11 +1,24 @@
 pub use crate::eth::EthApiServerImpl;
 use ethers::types::{Address, U64};
-use jsonrpsee::{core::RpcResult, proc_macros::rpc};
+use jsonrpsee::{
+    core::{RpcResult, SubscriptionResult},
+    proc_macros::rpc,
+    types::ErrorObjectOwned,
+};
+use serde::Serialize;
 use silius_primitives::{
-    UserOperationByHash, UserOperationGasEstimation, UserOperationHash, UserOperationReceipt,
-    UserOperationRequest,
+    simulation::StateOverride, NewUserOperationNotification, UserOperationByHash,
+    UserOperationGasEstimation, UserOperationHash, UserOperationReceipt, UserOperationRequest,
 };
 
+/// Result of a user operation sent in a batch: either its hash or the error it was rejected with
+#[derive(Clone, Debug, Serialize)]
+#[serde(untagged)]
+pub enum SendUserOperationResult {
+    Hash(UserOperationHash),
+    Error(ErrorObjectOwned),
+}
+
 /// The ERC-4337 `eth` namespace RPC methods trait
 #[rpc(server, namespace = "eth")]
 pub trait EthApi {
@@ -39,6 +52,24 @@ pub trait EthApi {
         entry_point: Address,
     ) -> RpcResult<UserOperationHash>;
 
+    /// Send multiple user operations in one call (at most
+    /// [MAX_BATCH_USER_OPERATIONS](silius_primitives::constants::rpc::MAX_BATCH_USER_OPERATIONS)).
+    /// A rejected user operation doesn't fail the whole batch.
+    ///
+    /// # Arguments
+    /// * `user_operations: Vec<(UserOperation, Address)>` - The
+    ///   [UserOperations](UserOperationRequest) to be sent, each with the address of its entry
+    ///   point.
+    ///
+    /// # Returns
+    /// * `RpcResult<Vec<SendUserOperationResult>>` - The hash of each sent user operation or the
+    ///   error it was rejected with (in the order of the user operations).
+    #[method(name = "sendUserOperations")]
+    async fn send_user_operations(
+        &self,
+        user_operations: Vec<(UserOperationRequest, Address)>,
+    ) -> RpcResult<Vec<SendUserOperationResult>>;
+
     /// Estimate the gas required for a user operation.
     /// This allows you to gauge the computational cost of the operation.
     /// See [How ERC-4337 Gas Estimation Works](https://www.alchemy.com/blog/erc-4337-gas-estimation).
@@ -47,6 +78,8 @@ pub trait EthApi {
     /// * `user_operation: [UserOperation](UserOperationRequest)` - User operation for which to
     ///   estimate the gas.
     /// * `entry_point: Address` - The address of the entry point.
+    /// * `state_override: Option<StateOverride>` - The `eth_call`-style state override set the user
+    ///   operation is simulated against (optional).
     ///
     /// # Returns
     /// * `RpcResult<UserOperationGasEstimation>` - The estimated gas for the user operation.
@@ -55,6 +88,7 @@ pub trait EthApi {
         &self,
         user_operation: UserOperationRequest,
         entry_point: Address,
+        state_override: Option<StateOverride>,
     ) -> RpcResult<UserOperationGasEstimation>;
 
     /// Retrieve the receipt of a user operation.
@@ -86,4 +120,20 @@ pub trait EthApi {
         &self,
         user_operation_hash: String,
     ) -> RpcResult<Option<UserOperationByHash>>;
+
+    /// Subscribe to the user operations added to the mempool (only available over WS).
+    ///
+    /// # Arguments
+    /// * `kind: String` - The kind of the subscription (only `newUserOperations` is supported).
+    /// * `full: Option<bool>` - Whether the notifications include the full user operations.
+    ///
+    /// # Returns
+    /// * `SubscriptionResult` - The result of the subscription, the notifications are
+    ///   [NewUserOperationNotifications](NewUserOperationNotification).
+    #[subscription(
+        name = "subscribe" => "subscription",
+        unsubscribe = "unsubscribe",
+        item = NewUserOperationNotification
+    )]
+    async fn subscribe(&self, kind: String, full: Option<bool>) -> SubscriptionResult;
 }
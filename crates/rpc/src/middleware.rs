@@ -1,18 +1,23 @@
+use crate::codes::{LIMIT_EXCEEDED, UNAUTHORIZED};
 use hyper::{Body, Request, Response};
 use hyper_tls::HttpsConnector;
 use jsonrpsee::{
     core::ClientError as JsonRpcError,
     types::{
         error::{ErrorCode, METHOD_NOT_FOUND_MSG},
-        ErrorObjectOwned,
+        ErrorObject, ErrorObjectOwned,
     },
 };
+use serde_json::{json, Value};
+use silius_primitives::constants::rpc::{ADMIN_METHODS, API_KEY_HEADER};
 use std::{
+    collections::HashMap,
     error::Error,
     future::Future,
     pin::Pin,
-    sync::Arc,
+    sync::{Arc, Mutex},
     task::{Context, Poll},
+    time::{Duration, Instant},
 };
 use tower::{Layer, Service};
 
@@ -131,3 +136,420 @@ where
         Box::pin(res_fut)
     }
 }
+
+/// Rate limiter of the user operations sent over `eth_sendUserOperation` per API key.
+#[derive(Debug)]
+pub struct ApiKeyRateLimiter {
+    /// Max number of user operations per period for each API key
+    limits: HashMap<String, u64>,
+    /// Period of the rate limits
+    period: Duration,
+    /// Start of the current period and the number of user operations sent within it for each API
+    /// key
+    usage: Mutex<HashMap<String, (Instant, u64)>>,
+}
+
+impl ApiKeyRateLimiter {
+    /// Create a new API key rate limiter
+    ///
+    /// # Arguments
+    /// * `limits: HashMap<String, u64>` - Max number of user operations per period for each API key
+    /// * `period: Duration` - Period of the rate limits
+    ///
+    /// # Returns
+    /// * `Self` - An ApiKeyRateLimiter instance
+    pub fn new(limits: HashMap<String, u64>, period: Duration) -> Self {
+        Self { limits, period, usage: Mutex::new(HashMap::new()) }
+    }
+
+    /// Counts the user operations sent with the API key against its rate limit.
+    ///
+    /// # Arguments
+    /// * `key: Option<&str>` - The API key of the request
+    /// * `uos: u64` - Number of user operations sent in the request
+    ///
+    /// # Returns
+    /// * `Result<(), ErrorObjectOwned>` - None if the user operations are accepted, otherwise the
+    ///   JSON-RPC error
+    pub fn check(&self, key: Option<&str>, uos: u64) -> Result<(), ErrorObjectOwned> {
+        let Some(key) = key else {
+            return Err(ErrorObject::owned(UNAUTHORIZED, "Missing API key", None::<bool>));
+        };
+        let Some(limit) = self.limits.get(key) else {
+            return Err(ErrorObject::owned(UNAUTHORIZED, "Invalid API key", None::<bool>));
+        };
+
+        let now = Instant::now();
+        let mut usage = self.usage.lock().expect("API key usage lock should not be poisoned");
+        let (start, count) = usage.entry(key.to_string()).or_insert((now, 0));
+        if now.duration_since(*start) >= self.period {
+            *start = now;
+            *count = 0;
+        }
+
+        if *count + uos > *limit {
+            return Err(ErrorObject::owned(
+                LIMIT_EXCEEDED,
+                format!(
+                    "API key exceeded its limit of {limit} user operations per {} seconds",
+                    self.period.as_secs()
+                ),
+                None::<bool>,
+            ));
+        }
+        *count += uos;
+
+        Ok(())
+    }
+}
+
+/// Returns the number of user operations sent over `eth_sendUserOperation` and
+/// `eth_sendUserOperations` in the JSON-RPC request (or batch)
+fn count_user_operations(body: &[u8]) -> u64 {
+    let count = |req: &Value| match req["method"].as_str() {
+        Some("eth_sendUserOperation") => 1,
+        Some("eth_sendUserOperations") => {
+            let uos = match &req["params"] {
+                Value::Object(params) => params.get("user_operations"),
+                params => params.get(0),
+            };
+            uos.and_then(Value::as_array).map_or(0, |uos| uos.len() as u64)
+        }
+        _ => 0,
+    };
+    match serde_json::from_slice::<Value>(body) {
+        Ok(Value::Array(reqs)) => reqs.iter().map(count).sum(),
+        Ok(req) => count(&req),
+        Err(_) => 0,
+    }
+}
+
+/// Returns the JSON-RPC error response to the request: an error object for a single request, or
+/// an array with an error object for each request of a batch
+fn error_response(body: &[u8], err: ErrorObjectOwned) -> Value {
+    let error = |req: &Value| {
+        let id = req.get("id").cloned().unwrap_or(Value::Null);
+        json!({ "jsonrpc": "2.0", "id": id, "error": err })
+    };
+    match serde_json::from_slice::<Value>(body) {
+        Ok(Value::Array(reqs)) => Value::Array(reqs.iter().map(error).collect()),
+        Ok(req) => error(&req),
+        Err(_) => error(&Value::Null),
+    }
+}
+
+/// The API key layer for the JSON-RPC server: user operations sent over `eth_sendUserOperation`
+/// have to carry an API key (in the `x-api-key` header) and are rate limited per API key.
+#[derive(Clone, Debug)]
+pub struct ApiKeyLayer {
+    /// The rate limiter of the API keys
+    pub limiter: Arc<ApiKeyRateLimiter>,
+}
+
+impl ApiKeyLayer {
+    /// Create a new API key layer
+    ///
+    /// # Arguments
+    /// * `limiter: ApiKeyRateLimiter` - The rate limiter of the API keys
+    ///
+    /// # Returns
+    /// * `Self` - An ApiKeyLayer instance
+    pub fn new(limiter: ApiKeyRateLimiter) -> Self {
+        Self { limiter: Arc::new(limiter) }
+    }
+}
+
+impl<S> Layer<S> for ApiKeyLayer {
+    type Service = ApiKeyRequest<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ApiKeyRequest { inner, limiter: self.limiter.clone() }
+    }
+}
+
+/// The RPC request checked against the API key rate limits.
+#[derive(Debug, Clone)]
+pub struct ApiKeyRequest<S> {
+    /// The inner service
+    inner: S,
+    /// The rate limiter of the API keys
+    limiter: Arc<ApiKeyRateLimiter>,
+}
+
+impl<S> Service<Request<Body>> for ApiKeyRequest<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+    S::Response: 'static,
+    S::Error: Into<Box<dyn Error + Send + Sync>> + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = Box<dyn Error + Send + Sync + 'static>;
+    type Future =
+        Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send + 'static>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let limiter = self.limiter.clone();
+        let clone = self.inner.clone();
+        // take the service that was ready
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let res_fut = async move {
+            let (req_h, req_b) = req.into_parts();
+            let req_bb = hyper::body::to_bytes(req_b).await?;
+
+            let uos = count_user_operations(&req_bb);
+            if uos > 0 {
+                let key = req_h.headers.get(API_KEY_HEADER).and_then(|key| key.to_str().ok());
+                if let Err(err) = limiter.check(key, uos) {
+                    let body = error_response(&req_bb, err);
+                    return Ok(Response::builder()
+                        .header(hyper::header::CONTENT_TYPE, "application/json")
+                        .body(Body::from(body.to_string()))?);
+                }
+            }
+
+            inner
+                .call(Request::from_parts(req_h, Body::from(req_bb)))
+                .await
+                .map_err(|err| err.into())
+        };
+
+        Box::pin(res_fut)
+    }
+}
+
+/// Returns whether the JSON-RPC request (or batch) calls one of the admin methods
+fn calls_admin_method(body: &[u8]) -> bool {
+    let admin = |req: &Value| req["method"].as_str().is_some_and(|m| ADMIN_METHODS.contains(&m));
+    match serde_json::from_slice::<Value>(body) {
+        Ok(Value::Array(reqs)) => reqs.iter().any(admin),
+        Ok(req) => admin(&req),
+        Err(_) => false,
+    }
+}
+
+/// The admin authentication layer for the JSON-RPC server: the admin methods (e.g.,
+/// `debug_bundler_setPinnedUserOperations`) have to carry the admin token (in the
+/// `Authorization: Bearer` header). If no admin token is configured, the admin methods are
+/// rejected.
+#[derive(Clone, Debug, Default)]
+pub struct AdminAuthLayer {
+    /// The admin token
+    pub token: Option<Arc<str>>,
+}
+
+impl AdminAuthLayer {
+    /// Create a new admin authentication layer
+    ///
+    /// # Arguments
+    /// * `token: Option<String>` - The admin token (None disables the admin methods)
+    ///
+    /// # Returns
+    /// * `Self` - An AdminAuthLayer instance
+    pub fn new(token: Option<String>) -> Self {
+        Self { token: token.map(Arc::from) }
+    }
+
+    /// Checks the `Authorization` header of a request calling an admin method.
+    ///
+    /// # Arguments
+    /// * `authorization: Option<&str>` - The `Authorization` header of the request
+    ///
+    /// # Returns
+    /// * `Result<(), ErrorObjectOwned>` - None if the request is authorized, otherwise the JSON-RPC
+    ///   error
+    pub fn check(&self, authorization: Option<&str>) -> Result<(), ErrorObjectOwned> {
+        let Some(token) = self.token.as_deref() else {
+            return Err(ErrorObject::owned(
+                UNAUTHORIZED,
+                "Admin methods are disabled (no admin token is configured)",
+                None::<bool>,
+            ));
+        };
+
+        match authorization.and_then(|auth| auth.strip_prefix("Bearer ")) {
+            Some(auth) if auth == token => Ok(()),
+            Some(_) => Err(ErrorObject::owned(UNAUTHORIZED, "Invalid admin token", None::<bool>)),
+            None => Err(ErrorObject::owned(UNAUTHORIZED, "Missing admin token", None::<bool>)),
+        }
+    }
+}
+
+impl<S> Layer<S> for AdminAuthLayer {
+    type Service = AdminAuthRequest<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AdminAuthRequest { inner, auth: self.clone() }
+    }
+}
+
+/// The RPC request checked for the admin token.
+#[derive(Debug, Clone)]
+pub struct AdminAuthRequest<S> {
+    /// The inner service
+    inner: S,
+    /// The admin authentication
+    auth: AdminAuthLayer,
+}
+
+impl<S> Service<Request<Body>> for AdminAuthRequest<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+    S::Response: 'static,
+    S::Error: Into<Box<dyn Error + Send + Sync>> + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = Box<dyn Error + Send + Sync + 'static>;
+    type Future =
+        Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send + 'static>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let auth = self.auth.clone();
+        let clone = self.inner.clone();
+        // take the service that was ready
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let res_fut = async move {
+            let (req_h, req_b) = req.into_parts();
+            let req_bb = hyper::body::to_bytes(req_b).await?;
+
+            if calls_admin_method(&req_bb) {
+                let authorization = req_h
+                    .headers
+                    .get(hyper::header::AUTHORIZATION)
+                    .and_then(|auth| auth.to_str().ok());
+                if let Err(err) = auth.check(authorization) {
+                    let id = serde_json::from_slice::<Value>(&req_bb)
+                        .ok()
+                        .and_then(|req| req.get("id").cloned())
+                        .unwrap_or(Value::Null);
+                    let body = json!({ "jsonrpc": "2.0", "id": id, "error": err });
+                    return Ok(Response::builder()
+                        .header(hyper::header::CONTENT_TYPE, "application/json")
+                        .body(Body::from(body.to_string()))?);
+                }
+            }
+
+            inner
+                .call(Request::from_parts(req_h, Body::from(req_bb)))
+                .await
+                .map_err(|err| err.into())
+        };
+
+        Box::pin(res_fut)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn api_key_rate_limit() {
+        let limiter = ApiKeyRateLimiter::new(
+            HashMap::from([("a".to_string(), 2), ("b".to_string(), 1)]),
+            Duration::from_secs(60),
+        );
+
+        assert_eq!(limiter.check(None, 1).unwrap_err().code(), UNAUTHORIZED);
+        assert_eq!(limiter.check(Some("c"), 1).unwrap_err().code(), UNAUTHORIZED);
+
+        assert!(limiter.check(Some("a"), 1).is_ok());
+        assert!(limiter.check(Some("a"), 1).is_ok());
+        assert_eq!(limiter.check(Some("a"), 1).unwrap_err().code(), LIMIT_EXCEEDED);
+
+        // other API keys aren't affected
+        assert!(limiter.check(Some("b"), 1).is_ok());
+        assert_eq!(limiter.check(Some("b"), 1).unwrap_err().code(), LIMIT_EXCEEDED);
+
+        // the limits are reset after the period
+        let limiter = ApiKeyRateLimiter::new(
+            HashMap::from([("a".to_string(), 1)]),
+            Duration::from_millis(10),
+        );
+        assert!(limiter.check(Some("a"), 1).is_ok());
+        assert!(limiter.check(Some("a"), 1).is_err());
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(limiter.check(Some("a"), 1).is_ok());
+    }
+
+    #[test]
+    fn count_sent_user_operations() {
+        assert_eq!(
+            count_user_operations(br#"{"jsonrpc":"2.0","id":1,"method":"eth_sendUserOperation"}"#),
+            1
+        );
+        assert_eq!(
+            count_user_operations(
+                br#"[{"method":"eth_sendUserOperation"},{"method":"eth_chainId"},{"method":"eth_sendUserOperation"}]"#
+            ),
+            2
+        );
+        assert_eq!(
+            count_user_operations(
+                br#"[{"method":"eth_sendUserOperations","params":[[[{},"0x"],[{},"0x"]]]},{"method":"eth_sendUserOperation"}]"#
+            ),
+            3
+        );
+        assert_eq!(
+            count_user_operations(
+                br#"{"method":"eth_sendUserOperations","params":{"user_operations":[[{},"0x"]]}}"#
+            ),
+            1
+        );
+        assert_eq!(count_user_operations(br#"{"method":"eth_chainId"}"#), 0);
+        assert_eq!(count_user_operations(b""), 0);
+    }
+
+    #[test]
+    fn error_response_per_request() {
+        let err = ErrorObject::owned(LIMIT_EXCEEDED, "limit", None::<bool>);
+
+        let res = error_response(br#"{"id":1,"method":"eth_sendUserOperation"}"#, err.clone());
+        assert_eq!(res["id"], 1);
+        assert_eq!(res["error"]["code"], LIMIT_EXCEEDED);
+
+        // each request of a batch gets its own error
+        let res = error_response(
+            br#"[{"id":1,"method":"eth_sendUserOperation"},{"id":2,"method":"eth_chainId"}]"#,
+            err.clone(),
+        );
+        let res = res.as_array().unwrap();
+        assert_eq!(res.len(), 2);
+        assert_eq!(res[0]["id"], 1);
+        assert_eq!(res[1]["id"], 2);
+        assert!(res.iter().all(|res| res["error"]["code"] == LIMIT_EXCEEDED));
+
+        assert_eq!(error_response(b"", err)["id"], Value::Null);
+    }
+
+    #[test]
+    fn admin_auth() {
+        let auth = AdminAuthLayer::new(Some("secret".into()));
+        assert!(auth.check(Some("Bearer secret")).is_ok());
+        assert_eq!(auth.check(None).unwrap_err().code(), UNAUTHORIZED);
+        assert_eq!(auth.check(Some("Bearer other")).unwrap_err().code(), UNAUTHORIZED);
+        assert_eq!(auth.check(Some("secret")).unwrap_err().code(), UNAUTHORIZED);
+
+        // admin methods are disabled without an admin token
+        let auth = AdminAuthLayer::new(None);
+        assert_eq!(auth.check(Some("Bearer secret")).unwrap_err().code(), UNAUTHORIZED);
+
+        assert!(calls_admin_method(br#"{"method":"debug_bundler_setPinnedUserOperations"}"#));
+        assert!(calls_admin_method(
+            br#"[{"method":"eth_chainId"},{"method":"debug_bundler_setPinnedUserOperations"}]"#
+        ));
+        assert!(!calls_admin_method(br#"{"method":"debug_bundler_dumpMempool"}"#));
+        assert!(!calls_admin_method(b""));
+    }
+}
@@ -1,14 +1,28 @@
-use super::middleware::ProxyJsonRpcLayer;
+use super::{
+    codes::UNAUTHORIZED,
+    middleware::{AdminAuthLayer, ApiKeyLayer, ApiKeyRateLimiter, ProxyJsonRpcLayer},
+};
 use eyre::Error;
 use hyper::{http::HeaderValue, Method};
 use jsonrpsee::{
     server::{RpcServiceBuilder, ServerBuilder, ServerHandle},
-    Methods,
+    types::{ErrorObject, ErrorObjectOwned},
+    Methods, RpcModule,
 };
 use silius_metrics::rpc::MetricsLayer;
-use std::net::{IpAddr, SocketAddr};
+use silius_primitives::constants::rpc::{
+    ADMIN_METHODS, API_KEY_METHODS, MAX_SUBSCRIPTIONS_PER_CONNECTION,
+};
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
 use tower::ServiceBuilder;
-use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+use tower_http::{
+    compression::CompressionLayer,
+    cors::{AllowOrigin, Any, CorsLayer},
+};
 
 /// JsonRpcServer is a wrapper around the `jsonrpsee` [ServerBuilder](https://docs.rs/jsonrpsee/3.0.0-beta.1/jsonrpsee/server/struct.ServerBuilder.html).
 pub struct JsonRpcServer {
@@ -22,6 +36,8 @@ pub struct JsonRpcServer {
     http_methods: Methods,
     /// The [cors layer](CorsLayer) for HTTP server to filter requests.
     http_cors_layer: Option<CorsLayer>,
+    /// Whether to compress HTTP responses (negotiated via the `Accept-Encoding` header).
+    http_compression: bool,
     /// Whether to start a WS server.
     ws: bool,
     /// WS address to listen on.
@@ -30,10 +46,17 @@ pub struct JsonRpcServer {
     ws_port: u16,
     /// The WS RPC methods to be exposed.
     ws_methods: Methods,
+    /// Maximum number of WS subscriptions per connection.
+    ws_max_subscriptions: u32,
     /// The [cors layer](CorsLayer) for WS server to filter requests.   
     ws_cors_layer: Option<CorsLayer>,
     /// The [proxy layer](ProxyJsonRpcLayer) to forward requests.
     proxy_layer: Option<ProxyJsonRpcLayer>,
+    /// The [API key layer](ApiKeyLayer) to rate limit user operations sent over HTTP (sending
+    /// user operations over WS is rejected if set).
+    api_key_layer: Option<ApiKeyLayer>,
+    /// The [admin authentication layer](AdminAuthLayer) guarding the admin methods over HTTP.
+    admin_auth_layer: AdminAuthLayer,
     /// This [metric layer](MetricsLayer) is used for collecting and reporting metrics related to
     /// RPC operations.
     metric_layer: Option<MetricsLayer>,
@@ -75,12 +98,16 @@ impl JsonRpcServer {
             http_port,
             http_methods: Methods::new(),
             http_cors_layer: None,
+            http_compression: false,
             ws,
             ws_addr,
             ws_port,
             ws_methods: Methods::new(),
+            ws_max_subscriptions: MAX_SUBSCRIPTIONS_PER_CONNECTION,
             ws_cors_layer: None,
             proxy_layer: None,
+            api_key_layer: None,
+            admin_auth_layer: AdminAuthLayer::default(),
             metric_layer: None,
         }
     }
@@ -135,11 +162,65 @@ impl JsonRpcServer {
         self
     }
 
+    /// Require an API key (in the `x-api-key` header) on `eth_sendUserOperation` requests sent
+    /// over HTTP and rate limit the user operations per API key. Requests over the limit are
+    /// rejected. The API key can't be checked per request over WS, so sending user operations over
+    /// WS is rejected.
+    ///
+    /// # Arguments
+    /// * `limits: HashMap<String, u64>` - Max number of user operations per period for each API
+    ///   key.
+    /// * `period: Duration` - Period of the rate limits.
+    ///
+    /// # Returns
+    /// * `Self` - The JsonRpcServer instance.
+    pub fn with_api_keys(mut self, limits: HashMap<String, u64>, period: Duration) -> Self {
+        self.api_key_layer = Some(ApiKeyLayer::new(ApiKeyRateLimiter::new(limits, period)));
+        self
+    }
+
+    /// Require the admin token (in the `Authorization: Bearer` header) on the admin methods (e.g.,
+    /// `debug_bundler_setPinnedUserOperations`). Without an admin token, the admin methods are
+    /// rejected. The admin methods are only served over HTTP.
+    ///
+    /// # Arguments
+    /// * `token: String` - The admin token.
+    ///
+    /// # Returns
+    /// * `Self` - The JsonRpcServer instance.
+    pub fn with_admin_token(mut self, token: String) -> Self {
+        self.admin_auth_layer = AdminAuthLayer::new(Some(token));
+        self
+    }
+
     pub fn with_metrics(mut self) -> Self {
         self.metric_layer = Some(MetricsLayer::new());
         self
     }
 
+    /// Enable gzip compression of HTTP responses. Responses are only compressed if the client
+    /// accepts it via the `Accept-Encoding` header.
+    ///
+    /// # Returns
+    /// * `Self` - The JsonRpcServer instance.
+    pub fn with_compression(mut self) -> Self {
+        self.http_compression = true;
+        self
+    }
+
+    /// Set the maximum number of subscriptions a single WS connection can open (new
+    /// subscriptions beyond it are rejected).
+    ///
+    /// # Arguments
+    /// * `max_subscriptions: u32` - The maximum number of subscriptions per connection.
+    ///
+    /// # Returns
+    /// * `Self` - The JsonRpcServer instance.
+    pub fn with_max_subscriptions_per_connection(mut self, max_subscriptions: u32) -> Self {
+        self.ws_max_subscriptions = max_subscriptions;
+        self
+    }
+
     /// Add methods to the RPC server.
     ///
     /// # Arguments
@@ -174,7 +255,10 @@ impl JsonRpcServer {
     pub async fn start(&self) -> eyre::Result<(Option<ServerHandle>, Option<ServerHandle>)> {
         let http_handle = if self.http {
             let service = ServiceBuilder::new()
+                .layer(CompressionLayer::new().gzip(self.http_compression))
                 .option_layer(self.http_cors_layer.clone())
+                .option_layer(self.api_key_layer.clone())
+                .layer(self.admin_auth_layer.clone())
                 .option_layer(self.proxy_layer.clone());
             let rpc_service = RpcServiceBuilder::new().option_layer(self.metric_layer.clone());
 
@@ -196,12 +280,35 @@ impl JsonRpcServer {
             let rpc_service = RpcServiceBuilder::new().option_layer(self.metric_layer.clone());
             let server = ServerBuilder::new()
                 .ws_only()
+                .max_subscriptions_per_connection(self.ws_max_subscriptions)
                 .set_rpc_middleware(rpc_service)
                 .set_http_middleware(service)
                 .build(SocketAddr::new(self.ws_addr, self.ws_port))
                 .await?;
 
-            Some(server.start(self.ws_methods.clone()))
+            // the admin token can't be checked per request over WS
+            let mut ws_methods = self.ws_methods.clone();
+            for method in ADMIN_METHODS {
+                ws_methods.remove(method);
+            }
+            // neither can the API key
+            if self.api_key_layer.is_some() {
+                let mut rejected = RpcModule::new(());
+                for method in API_KEY_METHODS {
+                    if ws_methods.remove(method).is_some() {
+                        rejected.register_method(method, |_, _| {
+                            Err::<(), ErrorObjectOwned>(ErrorObject::owned(
+                                UNAUTHORIZED,
+                                "User operations can only be sent over HTTP with an API key",
+                                None::<bool>,
+                            ))
+                        })?;
+                    }
+                }
+                ws_methods.merge(rejected)?;
+            }
+
+            Some(server.start(ws_methods))
         } else {
             None
         };
@@ -1,10 +1,11 @@
 use async_trait::async_trait;
 use ethers::types::U64;
 use jsonrpsee::{
-    core::{ClientError as RpcError, RpcResult},
+    core::{ClientError as RpcError, RpcResult, SubscriptionResult},
     http_client::{HttpClient, HttpClientBuilder},
     proc_macros::rpc,
     ws_client::{WsClient, WsClientBuilder},
+    PendingSubscriptionSink,
 };
 use std::{
     net::{IpAddr, Ipv4Addr},
@@ -28,6 +29,12 @@ pub fn test_port() -> u16 {
 pub trait DummyEthApi {
     #[method(name = "chainId")]
     async fn chain_id(&self) -> RpcResult<U64>;
+
+    #[method(name = "supportedEntryPoints")]
+    async fn supported_entry_points(&self) -> RpcResult<Vec<String>>;
+
+    #[subscription(name = "subscribe" => "subscription", unsubscribe = "unsubscribe", item = U64)]
+    async fn subscribe(&self) -> SubscriptionResult;
 }
 
 pub struct DummyEthApiServerImpl {
@@ -40,6 +47,18 @@ impl DummyEthApiServer for DummyEthApiServerImpl {
         let chain_id = self.chain_id;
         return Ok(chain_id);
     }
+
+    async fn supported_entry_points(&self) -> RpcResult<Vec<String>> {
+        // large enough response to be worth compressing
+        return Ok((0..1000).map(|i| format!("{i:#042x}")).collect());
+    }
+
+    async fn subscribe(&self, pending: PendingSubscriptionSink) -> SubscriptionResult {
+        // keep the subscription open until the client unsubscribes
+        let sink = pending.accept().await?;
+        sink.closed().await;
+        Ok(())
+    }
 }
 
 pub fn build_http_client(addr: IpAddr, port: u16) -> Result<HttpClient, RpcError> {
@@ -1,12 +1,56 @@
 mod common;
 
 use crate::common::{test_port, ADDRESS};
+use alloy_chains::Chain;
 use common::{
     build_http_client, build_ws_client, DummyEthApiClient, DummyEthApiServer, DummyEthApiServerImpl,
 };
-use ethers::types::U64;
-use silius_rpc::{JsonRpcServer, JsonRpcServerType};
-use std::net::IpAddr;
+use ethers::{
+    providers::Provider,
+    types::{Address, H256, U256, U64},
+};
+use hyper::{
+    header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_TYPE},
+    Body, Client, Method, Request,
+};
+use jsonrpsee::{
+    core::{
+        client::{ClientT, SubscriptionClientT},
+        ClientError,
+    },
+    rpc_params,
+};
+use parking_lot::RwLock;
+use serde_json::{json, Value};
+use silius_contracts::EntryPoint;
+use silius_grpc::{
+    bundler_client::BundlerClient, uo_pool_client::UoPoolClient, uo_pool_server::UoPoolServer,
+    UoPoolService,
+};
+use silius_mempool::{
+    mempool_id,
+    test_utils::{
+        memory_mempool, memory_reputation, test_validator, TEST_CHAIN_ID, TEST_MAX_VERIFICATION_GAS,
+    },
+    UoPoolBuilder,
+};
+use silius_primitives::{
+    constants::rpc::{API_KEY_HEADER, MAX_BATCH_USER_OPERATIONS},
+    NewUserOperationNotification, UoPoolMode, UserOperation, UserOperationHash,
+    UserOperationRequest, UserOperationSigned, UserOperationSource,
+};
+use silius_rpc::{
+    codes::{LIMIT_EXCEEDED, SANITY, UNAUTHORIZED},
+    debug_api::{DebugApiServer, DebugApiServerImpl, MempoolCursor, MempoolPage},
+    eth_api::{EthApiServer, EthApiServerImpl},
+    JsonRpcServer, JsonRpcServerType,
+};
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
 use tokio;
 
 #[tokio::test]
@@ -91,3 +135,439 @@ async fn http_and_ws_rpc_server() {
     let ws_response = DummyEthApiClient::chain_id(&ws_client).await.unwrap();
     assert_eq!(ws_response, chain_id);
 }
+
+#[tokio::test]
+async fn http_rpc_server_with_compression() {
+    let addr = IpAddr::from(ADDRESS);
+    let port = test_port();
+    let http = true;
+    let ws = false;
+    let mut server =
+        JsonRpcServer::new(http, addr.clone(), port, ws, addr.clone(), port).with_compression();
+
+    let chain_id: U64 = U64::from(0x7a69);
+    server
+        .add_methods(DummyEthApiServerImpl { chain_id }.into_rpc(), JsonRpcServerType::Http)
+        .unwrap();
+
+    let (http_handle, _ws_handle) = server.start().await.unwrap();
+    tokio::spawn(http_handle.unwrap().stopped());
+
+    let client = Client::new();
+    let body = r#"{"jsonrpc":"2.0","id":1,"method":"eth_supportedEntryPoints","params":[]}"#;
+
+    // response is gzipped when requested
+    let req = Request::builder()
+        .method(Method::POST)
+        .uri(format!("http://{addr}:{port}"))
+        .header(CONTENT_TYPE, "application/json")
+        .header(ACCEPT_ENCODING, "gzip")
+        .body(Body::from(body))
+        .unwrap();
+    let res = client.request(req).await.unwrap();
+    assert_eq!(res.headers().get(CONTENT_ENCODING).unwrap(), "gzip");
+
+    // response is not compressed when not requested
+    let req = Request::builder()
+        .method(Method::POST)
+        .uri(format!("http://{addr}:{port}"))
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(body))
+        .unwrap();
+    let res = client.request(req).await.unwrap();
+    assert!(res.headers().get(CONTENT_ENCODING).is_none());
+}
+
+#[tokio::test]
+async fn rpc_server_with_api_keys() {
+    let addr = IpAddr::from(ADDRESS);
+    let http_port = test_port();
+    let ws_port = test_port();
+    let mut server = JsonRpcServer::new(true, addr.clone(), http_port, true, addr.clone(), ws_port)
+        .with_api_keys(HashMap::from([("a".to_string(), 1)]), Duration::from_secs(60));
+
+    // the uopool service isn't needed, the requests are rejected before reaching it
+    let uopool_grpc_client = UoPoolClient::new(
+        tonic::transport::Channel::from_static("http://127.0.0.1:1").connect_lazy(),
+    );
+    server
+        .add_methods(
+            EthApiServerImpl { uopool_grpc_client, deny_unknown_fields: false }.into_rpc(),
+            JsonRpcServerType::Both,
+        )
+        .unwrap();
+
+    let (http_handle, ws_handle) = server.start().await.unwrap();
+    tokio::spawn(http_handle.unwrap().stopped());
+    tokio::spawn(ws_handle.unwrap().stopped());
+
+    // each request of a batch over the limit gets its own error
+    let body = json!([
+        { "jsonrpc": "2.0", "id": 1, "method": "eth_sendUserOperation", "params": [{}, Address::random()] },
+        { "jsonrpc": "2.0", "id": 2, "method": "eth_sendUserOperation", "params": [{}, Address::random()] },
+    ]);
+    let req = Request::builder()
+        .method(Method::POST)
+        .uri(format!("http://{addr}:{http_port}"))
+        .header(CONTENT_TYPE, "application/json")
+        .header(API_KEY_HEADER, "a")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let res = Client::new().request(req).await.unwrap();
+    let res: Value =
+        serde_json::from_slice(&hyper::body::to_bytes(res.into_body()).await.unwrap()).unwrap();
+    let res = res.as_array().unwrap();
+    assert_eq!(res.len(), 2);
+    assert_eq!(res[0]["id"], 1);
+    assert_eq!(res[1]["id"], 2);
+    assert!(res.iter().all(|res| res["error"]["code"] == LIMIT_EXCEEDED));
+
+    // the API key can't be checked over WS, so user operations can't be sent over it
+    let ws_client = build_ws_client(addr, ws_port).await.unwrap();
+    let err = ws_client
+        .request::<Value, _>("eth_sendUserOperation", rpc_params![json!({}), Address::random()])
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ClientError::Call(err) if err.code() == UNAUTHORIZED));
+}
+
+#[tokio::test]
+async fn ws_rpc_server_with_max_subscriptions() {
+    let addr = IpAddr::from(ADDRESS);
+    let port = test_port();
+    let mut server = JsonRpcServer::new(false, addr.clone(), port, true, addr.clone(), port)
+        .with_max_subscriptions_per_connection(2);
+
+    let chain_id: U64 = U64::from(0x7a69);
+    server
+        .add_methods(DummyEthApiServerImpl { chain_id }.into_rpc(), JsonRpcServerType::Ws)
+        .unwrap();
+
+    let (_http_handle, ws_handle) = server.start().await.unwrap();
+    tokio::spawn(ws_handle.unwrap().stopped());
+
+    let ws_client = build_ws_client(addr.clone(), port).await.unwrap();
+    let sub_1 = DummyEthApiClient::subscribe(&ws_client).await.unwrap();
+    let _sub_2 = DummyEthApiClient::subscribe(&ws_client).await.unwrap();
+
+    // subscriptions beyond the cap are rejected
+    assert!(DummyEthApiClient::subscribe(&ws_client).await.is_err());
+
+    // other connections have their own cap
+    let other_ws_client = build_ws_client(addr, port).await.unwrap();
+    assert!(DummyEthApiClient::subscribe(&other_ws_client).await.is_ok());
+
+    // unsubscribing frees a slot
+    sub_1.unsubscribe().await.unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    assert!(DummyEthApiClient::subscribe(&ws_client).await.is_ok());
+}
+
+#[tokio::test]
+async fn ws_subscribe_new_user_operations() {
+    let addr = IpAddr::from(ADDRESS);
+    let grpc_port = test_port();
+    let port = test_port();
+
+    // uopool gRPC service
+    let (provider, _) = Provider::mocked();
+    let provider = Arc::new(provider);
+    let chain = Chain::from(TEST_CHAIN_ID);
+    let ep = Address::random();
+    let validator = test_validator(EntryPoint::new(provider.clone(), ep));
+    let uopool_builder = UoPoolBuilder::new(
+        UoPoolMode::Standard,
+        provider,
+        ep,
+        chain,
+        U256::from(TEST_MAX_VERIFICATION_GAS),
+        memory_mempool(),
+        memory_reputation(),
+        validator,
+        None,
+    );
+    let mut uopool = uopool_builder.uopool();
+    let uopools =
+        Arc::new(RwLock::new(HashMap::from([(mempool_id(&ep, chain.id()), uopool_builder)])));
+    tokio::spawn(
+        tonic::transport::Server::builder()
+            .add_service(UoPoolServer::new(UoPoolService::new(uopools, chain, None)))
+            .serve(SocketAddr::new(addr, grpc_port)),
+    );
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // eth namespace over WS
+    let uopool_grpc_client =
+        UoPoolClient::connect(format!("http://127.0.0.1:{grpc_port}")).await.unwrap();
+    let mut server = JsonRpcServer::new(false, addr.clone(), port, true, addr.clone(), port);
+    server
+        .add_methods(
+            EthApiServerImpl { uopool_grpc_client, deny_unknown_fields: false }.into_rpc(),
+            JsonRpcServerType::Ws,
+        )
+        .unwrap();
+
+    let (_http_handle, ws_handle) = server.start().await.unwrap();
+    tokio::spawn(ws_handle.unwrap().stopped());
+
+    let ws_client = build_ws_client(addr, port).await.unwrap();
+
+    // unknown subscriptions are rejected
+    assert!(ws_client
+        .subscribe::<NewUserOperationNotification, _>(
+            "eth_subscribe",
+            rpc_params!["newBlocks"],
+            "eth_unsubscribe",
+        )
+        .await
+        .is_err());
+
+    let mut sub = ws_client
+        .subscribe::<NewUserOperationNotification, _>(
+            "eth_subscribe",
+            rpc_params!["newUserOperations", true],
+            "eth_unsubscribe",
+        )
+        .await
+        .unwrap();
+    assert_eq!(uopool.mempool.subscriber_count(), 1);
+
+    let uo = UserOperation::from_user_operation_signed(
+        H256::random().into(),
+        UserOperationSigned::default().sender(Address::random()),
+    );
+    uopool
+        .add_user_operation(uo.clone(), Ok(Default::default()), UserOperationSource::Rpc)
+        .await
+        .unwrap();
+
+    let notification =
+        tokio::time::timeout(Duration::from_secs(5), sub.next()).await.unwrap().unwrap().unwrap();
+    assert_eq!(
+        notification,
+        NewUserOperationNotification {
+            user_operation_hash: uo.hash,
+            entry_point: ep,
+            user_operation: Some(uo.user_operation),
+            cancels: None,
+        }
+    );
+
+    // unsubscribing drops the mempool receiver
+    sub.unsubscribe().await.unwrap();
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while uopool.mempool.subscriber_count() > 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn send_user_operations_batch() {
+    let addr = IpAddr::from(ADDRESS);
+    let grpc_port = test_port();
+    let port = test_port();
+
+    // uopool gRPC service
+    let (provider, _) = Provider::mocked();
+    let provider = Arc::new(provider);
+    let chain = Chain::from(TEST_CHAIN_ID);
+    let ep = Address::random();
+    let validator = test_validator(EntryPoint::new(provider.clone(), ep));
+    let uopool_builder = UoPoolBuilder::new(
+        UoPoolMode::Standard,
+        provider,
+        ep,
+        chain,
+        U256::from(TEST_MAX_VERIFICATION_GAS),
+        memory_mempool(),
+        memory_reputation(),
+        validator,
+        None,
+    );
+    let uopool = uopool_builder.uopool();
+    let uopools =
+        Arc::new(RwLock::new(HashMap::from([(mempool_id(&ep, chain.id()), uopool_builder)])));
+    tokio::spawn(
+        tonic::transport::Server::builder()
+            .add_service(UoPoolServer::new(UoPoolService::new(uopools, chain, None)))
+            .serve(SocketAddr::new(addr, grpc_port)),
+    );
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // eth namespace over HTTP
+    let uopool_grpc_client =
+        UoPoolClient::connect(format!("http://127.0.0.1:{grpc_port}")).await.unwrap();
+    let mut server = JsonRpcServer::new(true, addr.clone(), port, false, addr.clone(), port);
+    server
+        .add_methods(
+            EthApiServerImpl { uopool_grpc_client, deny_unknown_fields: true }.into_rpc(),
+            JsonRpcServerType::Http,
+        )
+        .unwrap();
+
+    let (http_handle, _ws_handle) = server.start().await.unwrap();
+    tokio::spawn(http_handle.unwrap().stopped());
+
+    let http_client = build_http_client(addr, port).unwrap();
+
+    let res: Vec<Value> = http_client
+        .request("eth_sendUserOperations", rpc_params![Vec::<(Value, Address)>::new()])
+        .await
+        .unwrap();
+    assert!(res.is_empty());
+
+    // user operations already in the mempool are accepted without being validated again (the
+    // mocked provider can't simulate the validation)
+    let uo = UserOperationSigned::default().sender(Address::random());
+    let uo_hash = uo.hash(&ep, chain.id());
+    uopool
+        .add_user_operation(
+            UserOperation::from_user_operation_signed(uo_hash, uo.clone()),
+            Ok(Default::default()),
+            UserOperationSource::Rpc,
+        )
+        .await
+        .unwrap();
+
+    // each user operation gets its own result (in order) and a rejected one doesn't fail the
+    // whole batch
+    let res: Vec<Value> = http_client
+        .request(
+            "eth_sendUserOperations",
+            rpc_params![vec![
+                (json!({ "sender": Address::random(), "paymasterAndData": "0x01" }), ep),
+                (serde_json::to_value(UserOperationRequest::from(uo)).unwrap(), ep),
+                (json!({ "sender": Address::random(), "unknownField": true }), ep),
+                (json!({ "sender": Address::random() }), Address::random()),
+            ]],
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.len(), 4);
+    assert_eq!(res[0]["code"], SANITY);
+    assert_eq!(serde_json::from_value::<UserOperationHash>(res[1].clone()).unwrap(), uo_hash);
+    assert!(res[2]["message"].as_str().unwrap().contains("unknownField"));
+    assert!(res[3]["code"].is_i64());
+    let uos = uopool.get_all().unwrap();
+    assert_eq!(uos.len(), 1);
+    assert_eq!(uos[0].hash, uo_hash);
+
+    // too large batches are rejected as a whole
+    let batch = vec![(json!({ "sender": Address::random() }), ep); MAX_BATCH_USER_OPERATIONS + 1];
+    assert!(http_client
+        .request::<Vec<Value>, _>("eth_sendUserOperations", rpc_params![batch])
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn dump_mempool_pages() {
+    let addr = IpAddr::from(ADDRESS);
+    let grpc_port = test_port();
+    let port = test_port();
+
+    // uopool gRPC service
+    let (provider, _) = Provider::mocked();
+    let provider = Arc::new(provider);
+    let chain = Chain::from(TEST_CHAIN_ID);
+    let ep = Address::random();
+    let validator = test_validator(EntryPoint::new(provider.clone(), ep));
+    let uopool_builder = UoPoolBuilder::new(
+        UoPoolMode::Standard,
+        provider,
+        ep,
+        chain,
+        U256::from(TEST_MAX_VERIFICATION_GAS),
+        memory_mempool(),
+        memory_reputation(),
+        validator,
+        None,
+    );
+    let mut uopool = uopool_builder.uopool();
+    // the user operations of the first sender are received over the p2p network
+    let p2p_sender = Address::from_low_u64_be(1);
+    for i in 0..25 {
+        let sender = Address::from_low_u64_be(i % 5 + 1);
+        let source =
+            if sender == p2p_sender { UserOperationSource::P2p } else { UserOperationSource::Rpc };
+        uopool
+            .mempool
+            .add_with_source(
+                UserOperation::from_user_operation_signed(
+                    H256::random().into(),
+                    UserOperationSigned::default().sender(sender).nonce(U256::from(i / 5)),
+                ),
+                source,
+            )
+            .unwrap();
+    }
+    let uopools =
+        Arc::new(RwLock::new(HashMap::from([(mempool_id(&ep, chain.id()), uopool_builder)])));
+    tokio::spawn(
+        tonic::transport::Server::builder()
+            .add_service(UoPoolServer::new(UoPoolService::new(uopools, chain, None)))
+            .serve(SocketAddr::new(addr, grpc_port)),
+    );
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // debug namespace over HTTP (the bundler service isn't needed for dumping the mempool)
+    let uopool_grpc_client =
+        UoPoolClient::connect(format!("http://127.0.0.1:{grpc_port}")).await.unwrap();
+    let bundler_grpc_client = BundlerClient::new(
+        tonic::transport::Channel::from_static("http://127.0.0.1:1").connect_lazy(),
+    );
+    let mut server = JsonRpcServer::new(true, addr.clone(), port, false, addr.clone(), port);
+    server
+        .add_methods(
+            DebugApiServerImpl { uopool_grpc_client, bundler_grpc_client, config: None }.into_rpc(),
+            JsonRpcServerType::Http,
+        )
+        .unwrap();
+
+    let (http_handle, _ws_handle) = server.start().await.unwrap();
+    tokio::spawn(http_handle.unwrap().stopped());
+
+    let http_client = build_http_client(addr, port).unwrap();
+
+    let mut cursor = None;
+    let mut uos = vec![];
+    for (len, total) in [(10, 25), (10, 26), (5, 26)] {
+        let page: MempoolPage =
+            http_client.request("debug_dumpMempool", rpc_params![ep, cursor, 10]).await.unwrap();
+        assert_eq!(page.user_operations.len(), len);
+        assert_eq!(page.sources.len(), len);
+        for (uo, source) in page.user_operations.iter().zip(page.sources.iter()) {
+            assert_eq!(*source == UserOperationSource::P2p, uo.sender == p2p_sender);
+        }
+        assert_eq!(page.total, total);
+        let last = page.user_operations.last().unwrap();
+        assert_eq!(
+            page.next_cursor,
+            (total - uos.len() as u64 > 10)
+                .then_some(MempoolCursor { sender: last.sender, nonce: last.nonce })
+        );
+        uos.extend(page.user_operations.into_iter().map(|uo| (uo.sender, uo.nonce)));
+        cursor = page.next_cursor;
+
+        // a sender sorting before the cursor doesn't shift the next pages
+        if total == 25 {
+            uopool
+                .mempool
+                .add(UserOperation::from_user_operation_signed(
+                    H256::random().into(),
+                    UserOperationSigned::default().sender(Address::zero()),
+                ))
+                .unwrap();
+        }
+    }
+
+    // the pages don't overlap and follow the (sender, nonce) order
+    let mut sorted = uos.clone();
+    sorted.sort();
+    sorted.dedup();
+    assert_eq!(uos, sorted);
+    assert_eq!(uos.len(), 25);
+}
@@ -3,8 +3,9 @@ use ethers::types::{Address, U256};
 use parking_lot::RwLock;
 use silius_contracts::EntryPoint;
 use silius_mempool::{
-    init_env, validate::validator::new_canonical, CodeHashes, DatabaseTable, Mempool, Reputation,
-    UoPoolBuilder, UserOperations, UserOperationsByEntity, UserOperationsBySender, WriteMap,
+    init_env, validate::validator::new_canonical, CodeHashes, DatabaseArguments, DatabaseTable,
+    Mempool, Reputation, UoPoolBuilder, UserOperations, UserOperationsByEntity,
+    UserOperationsBySender, WriteMap,
 };
 use silius_primitives::{
     constants::{
@@ -32,7 +33,10 @@ async fn main() -> eyre::Result<()> {
     if let Ok(provider_url) = env::var("PROVIDER_URL") {
         // initialize database env
         let dir = TempDir::new().unwrap();
-        let env = Arc::new(init_env::<WriteMap>(dir.into_path()).expect("Init mdbx failed"));
+        let env = Arc::new(
+            init_env::<WriteMap>(dir.into_path(), DatabaseArguments::default())
+                .expect("Init mdbx failed"),
+        );
         env.create_tables().expect("Create mdbx database tables failed");
         println!("Database uopool created!");
 
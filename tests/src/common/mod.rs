@@ -7,13 +7,13 @@ use ethers::{
     prelude::{MiddlewareBuilder, NonceManagerMiddleware, SignerMiddleware},
     providers::{Http, Middleware, Provider},
     signers::{coins_bip39::English, LocalWallet, MnemonicBuilder, Signer},
-    types::{Address, TransactionRequest, U256},
-    utils::{Geth, GethInstance},
+    types::{Address, TransactionRequest, H256, U256},
+    utils::{keccak256, rlp::RlpStream, Geth, GethInstance},
 };
 use parking_lot::RwLock;
 use silius_mempool::{
-    init_env, CodeHashes, DatabaseTable, EntitiesReputation, Mempool, Reputation, UserOperations,
-    UserOperationsByEntity, UserOperationsBySender, WriteMap,
+    init_env, CodeHashes, DatabaseArguments, DatabaseTable, EntitiesReputation, Mempool,
+    Reputation, UserOperations, UserOperationsByEntity, UserOperationsBySender, WriteMap,
 };
 use silius_primitives::{
     reputation::ReputationEntry, simulation::CodeHash, UserOperationHash, UserOperationSigned,
@@ -155,10 +155,66 @@ pub async fn setup_geth() -> eyre::Result<(GethInstance, ClientType, Provider<Ht
     Ok((geth, client, provider))
 }
 
+/// Delegates the EOA of the `authority` to the `delegate` contract (EIP-7702) by sending a
+/// set code transaction, so the EOA can be the sender of user operations
+pub async fn delegate_eoa(
+    client: &ClientType,
+    authority: &LocalWallet,
+    delegate: Address,
+    chain_id: u64,
+) -> eyre::Result<()> {
+    // authorization: keccak256(0x05 || rlp([chain_id, address, nonce]))
+    let auth_nonce = client.get_transaction_count(authority.address(), None).await?;
+    let mut auth = RlpStream::new_list(3);
+    auth.append(&chain_id).append(&delegate).append(&auth_nonce);
+    let auth_sig = authority.sign_hash(H256::from(keccak256([&[0x05], auth.as_raw()].concat())))?;
+
+    let signer = client.inner().signer();
+    client.initialize_nonce(None).await?;
+    let nonce = client.next();
+    let gas_price = client.get_gas_price().await?.mul(2);
+
+    let append_fields = |s: &mut RlpStream| {
+        s.append(&chain_id)
+            .append(&nonce)
+            .append(&gas_price)
+            .append(&gas_price)
+            .append(&U256::from(100_000))
+            .append(&authority.address())
+            .append(&U256::zero())
+            .append_empty_data();
+        s.begin_list(0);
+        s.begin_list(1);
+        s.begin_list(6)
+            .append(&chain_id)
+            .append(&delegate)
+            .append(&auth_nonce)
+            .append(&(auth_sig.v - 27))
+            .append(&auth_sig.r)
+            .append(&auth_sig.s);
+    };
+
+    // set code transaction: 0x04 || rlp([chain_id, nonce, ..., authorization_list, y, r, s])
+    let mut unsigned = RlpStream::new_list(10);
+    append_fields(&mut unsigned);
+    let sig = signer.sign_hash(H256::from(keccak256([&[0x04], unsigned.as_raw()].concat())))?;
+
+    let mut signed = RlpStream::new_list(13);
+    append_fields(&mut signed);
+    signed.append(&(sig.v - 27)).append(&sig.r).append(&sig.s);
+
+    client.send_raw_transaction([&[0x04], signed.as_raw()].concat().into()).await?.await?;
+
+    Ok(())
+}
+
 #[allow(clippy::type_complexity)]
 pub fn setup_database_mempool_reputation() -> (Mempool, Reputation) {
     let dir = TempDir::new().expect("create tmp");
-    let env = Arc::new(init_env::<WriteMap>(dir.into_path()).expect("Init mdbx failed"));
+    let env = Arc::new(
+        init_env::<WriteMap>(dir.into_path(), DatabaseArguments::default())
+            .expect("Init mdbx failed"),
+    );
     env.create_tables().expect("Create mdbx database tables failed");
     let mempool = Mempool::new(
         Box::new(DatabaseTable::<WriteMap, UserOperations>::new(env.clone())),
@@ -12,7 +12,9 @@ use ethers::{
 };
 use silius_contracts::EntryPoint;
 use silius_mempool::{validate::validator::new_canonical, UoPool};
-use silius_primitives::{UoPoolMode, UserOperationSigned, Wallet as UoWallet};
+use silius_primitives::{
+    UoPoolMode, UserOperation, UserOperationRequest, UserOperationSigned, Wallet as UoWallet,
+};
 use std::sync::Arc;
 
 async fn setup_basic() -> eyre::Result<(
@@ -98,7 +100,7 @@ async fn estimate_with_zero() -> eyre::Result<()> {
     let uo_wallet = UoWallet::from_phrase(SEED_PHRASE, chain_id, false)?;
     let user_op = uo_wallet.sign_user_operation(&user_op, &entry_point.address, chain_id).await?;
 
-    let estimate = uopool.estimate_user_operation_gas(&user_op).await.expect("estimate done");
+    let estimate = uopool.estimate_user_operation_gas(&user_op, None).await.expect("estimate done");
     let user_op = UserOperationSigned {
         verification_gas_limit: estimate.verification_gas_limit,
         call_gas_limit: estimate.call_gas_limit,
@@ -111,3 +113,94 @@ async fn estimate_with_zero() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn estimate_partial_user_operation() -> eyre::Result<()> {
+    let (client, entry_point, chain_id, _geth, simple_account_factory) = setup_basic().await?;
+    let (mempool, reputation) = setup_memory_mempool_reputation();
+    let max_verification_gas = 5000000.into();
+    let chain = Chain::from_id(chain_id);
+    let entry = EntryPoint::new(client.clone(), entry_point.address);
+    let entry_for_uopool = EntryPoint::new(client.clone(), entry_point.address);
+    let min_priority_fee_per_gas = 0.into();
+    let validator = new_canonical(entry, chain, max_verification_gas, min_priority_fee_per_gas);
+    let mut uopool = UoPool::new(
+        UoPoolMode::Standard,
+        entry_for_uopool,
+        validator,
+        mempool,
+        reputation,
+        max_verification_gas,
+        chain,
+        None,
+    );
+
+    let wallet = MnemonicBuilder::<English>::default().phrase(SEED_PHRASE).build()?;
+    let owner_address = wallet.address();
+    let address: H160 =
+        simple_account_factory.contract().get_address(owner_address, U256::from(1)).call().await?;
+    let nonce = client.get_transaction_count(owner_address, None).await?;
+    let mut initial_fund = TypedTransaction::default();
+    initial_fund.set_from(owner_address).set_to(address).set_value(u64::MAX).set_nonce(nonce);
+    let _receipt = client.send_transaction(initial_fund, None).await?.await?;
+
+    let call = simple_account_factory.contract().create_account(owner_address, U256::from(1));
+    let tx: TypedTransaction = call.tx;
+    let mut init_code = Vec::new();
+    init_code.extend_from_slice(simple_account_factory.address.as_bytes());
+    init_code.extend_from_slice(tx.data().unwrap().to_vec().as_slice());
+
+    // partial user operation without gas fields, fees and signature
+    let user_op = UserOperationRequest {
+        sender: address,
+        nonce: U256::zero(),
+        init_code: Bytes::from(init_code),
+        // `execute(address dest, uint256 value, bytes calldata func)` with all empty values
+        call_data: Some(
+            format!("0xb61d27f6{}{:064x}{}", "0".repeat(128), 96, "0".repeat(64)).parse()?,
+        ),
+        call_gas_limit: None,
+        verification_gas_limit: None,
+        pre_verification_gas: None,
+        max_fee_per_gas: None,
+        max_priority_fee_per_gas: None,
+        paymaster_and_data: Bytes::new(),
+        signature: None,
+        unknown_fields: Default::default(),
+    };
+    let user_op = user_op.into_estimation_user_operation().expect("required fields present");
+    assert!(user_op.call_gas_limit.is_zero());
+    assert!(user_op.verification_gas_limit.is_zero());
+    assert!(user_op.pre_verification_gas.is_zero());
+
+    let estimate = uopool
+        .estimate_user_operation_gas(
+            &UserOperation::from_user_operation_signed(
+                user_op.hash(&entry_point.address, chain_id),
+                user_op.clone(),
+            ),
+            None,
+        )
+        .await
+        .expect("estimate done");
+    assert!(!estimate.verification_gas_limit.is_zero());
+    assert!(!estimate.call_gas_limit.is_zero());
+    assert!(!estimate.pre_verification_gas.is_zero());
+
+    // the completed user operation is accepted
+    let (gas_price, priority_fee) = client.estimate_eip1559_fees(None).await?;
+    let user_op = UserOperationSigned {
+        verification_gas_limit: estimate.verification_gas_limit,
+        call_gas_limit: estimate.call_gas_limit,
+        pre_verification_gas: estimate.pre_verification_gas,
+        max_fee_per_gas: gas_price,
+        max_priority_fee_per_gas: priority_fee,
+        ..user_op
+    };
+
+    let uo_wallet = UoWallet::from_phrase(SEED_PHRASE, chain_id, false)?;
+    let user_op = uo_wallet.sign_user_operation(&user_op, &entry_point.address, chain_id).await?;
+    uopool.add_user_operations(vec![user_op], None).await.expect("handle done");
+
+    Ok(())
+}
@@ -1,5 +1,5 @@
 use crate::common::{
-    deploy_entry_point, deploy_test_coin, deploy_test_opcode_account,
+    delegate_eoa, deploy_entry_point, deploy_test_coin, deploy_test_opcode_account,
     deploy_test_opcode_account_factory, deploy_test_recursion_account,
     deploy_test_rules_account_factory, deploy_test_storage_account,
     deploy_test_storage_account_factory,
@@ -12,25 +12,35 @@ use crate::common::{
 };
 use alloy_chains::Chain;
 use ethers::{
-    abi::Token,
+    abi::{encode, Token},
+    core::rand::thread_rng,
     prelude::BaseContract,
     providers::Middleware,
+    signers::{LocalWallet, Signer},
     types::{transaction::eip2718::TypedTransaction, Address, Bytes, U256},
-    utils::{parse_units, GethInstance},
+    utils::{id, parse_units, GethInstance},
 };
+use metrics_util::debugging::{DebugValue, DebuggingRecorder};
 use silius_contracts::EntryPoint;
 use silius_mempool::{
+    metrics::{ValidationPhase, VALIDATION_PHASE_DURATION},
     validate::{
+        sanity::call_data::SelectorFilter,
         validator::{new_canonical, StandardValidator},
-        UserOperationValidationOutcome, UserOperationValidator, UserOperationValidatorMode,
+        TraceViolation, UserOperationValidationOutcome, UserOperationValidator,
+        UserOperationValidatorMode,
     },
-    InvalidMempoolUserOperationError, Mempool, Reputation, SimulationError,
+    InvalidMempoolUserOperationError, Mempool, Reputation, SanityError, SimulationError, UoPool,
 };
 use silius_primitives::{
-    constants::validation::entities::{FACTORY, PAYMASTER, SENDER},
-    UserOperation, UserOperationSigned,
+    constants::validation::{
+        entities::{FACTORY, PAYMASTER, SENDER},
+        EIP7702_INIT_CODE_MARKER,
+    },
+    simulation::ValidationConfig,
+    UoPoolMode, UserOperation, UserOperationSigned,
 };
-use std::{ops::Deref, sync::Arc, time::Duration};
+use std::{collections::HashMap, ops::Deref, sync::Arc, time::Duration};
 
 struct TestContext<M>
 where
@@ -304,6 +314,28 @@ where
         .await
 }
 
+async fn validate_with<M>(
+    context: &TestContext<M>,
+    validator: &StandardValidator<M>,
+    uo: UserOperationSigned,
+) -> Result<UserOperationValidationOutcome, InvalidMempoolUserOperationError>
+where
+    M: Middleware + 'static,
+{
+    validator
+        .validate_user_operation(
+            &UserOperation::from_user_operation_signed(
+                uo.hash(&context.entry_point.address, context.chain_id),
+                uo.clone(),
+            ),
+            &context.mempool,
+            &context.reputation,
+            None,
+            UserOperationValidatorMode::Simulation | UserOperationValidatorMode::SimulationTrace,
+        )
+        .await
+}
+
 async fn test_user_operation<M>(
     context: &TestContext<M>,
     validate_rule: String,
@@ -337,6 +369,25 @@ async fn test_existing_user_operation(
     validate(&c, uo).await
 }
 
+/// Generates the tests running the scenario against both the database and the memory storage
+macro_rules! storage_tests {
+    ($($scenario:ident),* $(,)?) => {
+        $(
+            mod $scenario {
+                #[tokio::test]
+                async fn database() -> eyre::Result<()> {
+                    super::$scenario(super::setup_database().await?).await
+                }
+
+                #[tokio::test]
+                async fn memory() -> eyre::Result<()> {
+                    super::$scenario(super::setup_memory().await?).await
+                }
+            }
+        )*
+    };
+}
+
 macro_rules! accept_plain_request {
     ($setup:expr, $name: ident) => {
         #[tokio::test]
@@ -362,6 +413,130 @@ macro_rules! accept_plain_request {
 accept_plain_request!(setup_database().await?, accept_plain_request_database);
 accept_plain_request!(setup_memory().await?, accept_plain_request_memory);
 
+async fn simulate_only_keeps_reputation(c: TestContext<ClientType>) -> eyre::Result<()> {
+    let (init_code, init_func) = create_opcode_factory_init_code("".into()).await.unwrap();
+    let uo = create_test_user_operation(
+        &c,
+        "".into(),
+        None,
+        init_code,
+        init_func,
+        c.opcodes_factory.address,
+    )
+    .await
+    .expect("Create test user operation failed.");
+    let uo =
+        UserOperation::from_user_operation_signed(uo.hash(&c.entry_point.address, c.chain_id), uo);
+
+    let uopool = UoPool::new(
+        UoPoolMode::Standard,
+        EntryPoint::new(c.client.clone(), c.entry_point.address),
+        c.validator,
+        c.mempool,
+        c.reputation,
+        U256::from(3000000_u64),
+        Chain::from(c.chain_id),
+        None,
+    );
+    let reputation = uopool.get_reputation();
+
+    uopool.simulate_user_operation(&uo).await.expect("succeed");
+
+    assert_eq!(uopool.get_reputation(), reputation);
+    assert_eq!(uopool.reputation.get(&uo.sender)?.uo_seen, 0);
+    assert!(uopool.get_all()?.is_empty());
+
+    Ok(())
+}
+
+async fn record_validation_phase_durations(c: TestContext<ClientType>) -> eyre::Result<()> {
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+    let _guard = metrics::set_default_local_recorder(&recorder);
+
+    let (init_code, init_func) = create_opcode_factory_init_code("".into()).await.unwrap();
+    let validator = new_canonical(
+        EntryPoint::new(c.client.clone(), c.entry_point.address),
+        Chain::from(c.chain_id),
+        U256::from(3000000_u64),
+        U256::from(1u64),
+    )
+    .with_subsidized(true);
+
+    let uo = create_test_user_operation(
+        &c,
+        "".into(),
+        None,
+        init_code,
+        init_func,
+        c.opcodes_factory.address,
+    )
+    .await
+    .expect("Create test user operation failed.");
+    let mode = UserOperationValidatorMode::Sanity |
+        UserOperationValidatorMode::Simulation |
+        UserOperationValidatorMode::SimulationTrace;
+    validator
+        .validate_user_operation(
+            &UserOperation::from_user_operation_signed(
+                uo.hash(&c.entry_point.address, c.chain_id),
+                uo.clone(),
+            ),
+            &c.mempool,
+            &c.reputation,
+            None,
+            mode,
+        )
+        .await
+        .expect("succeed");
+
+    // number of durations recorded for each phase since the last snapshot
+    let recorded = || {
+        let snapshot = snapshotter.snapshot().into_vec();
+        [ValidationPhase::Sanity, ValidationPhase::Simulation, ValidationPhase::Trace].map(
+            |phase| {
+                snapshot
+                    .iter()
+                    .find_map(|(key, _, _, value)| {
+                        let key = key.key();
+                        (key.name() == VALIDATION_PHASE_DURATION &&
+                            key.labels().any(|label| label.value() == phase.as_str()))
+                        .then_some(value)
+                    })
+                    .map_or(0, |value| match value {
+                        DebugValue::Histogram(values) => values.len(),
+                        _ => 0,
+                    })
+            },
+        )
+    };
+    assert_eq!(recorded(), [1, 1, 1]);
+
+    // failed phases are timed too
+    let uo = UserOperationSigned { verification_gas_limit: U256::from(10000000_u64), ..uo };
+    let res = validator
+        .validate_user_operation(
+            &UserOperation::from_user_operation_signed(
+                uo.hash(&c.entry_point.address, c.chain_id),
+                uo,
+            ),
+            &c.mempool,
+            &c.reputation,
+            None,
+            mode,
+        )
+        .await;
+    assert!(matches!(
+        res,
+        Err(InvalidMempoolUserOperationError::Sanity(
+            SanityError::VerificationGasLimitTooHigh { .. }
+        ))
+    ));
+    assert_eq!(recorded(), [1, 0, 0]);
+
+    Ok(())
+}
+
 macro_rules! reject_unkown_rule {
     ($setup:expr, $name: ident) => {
         #[tokio::test]
@@ -411,7 +586,7 @@ macro_rules! fail_with_bad_opcode_in_ctr {
             .await;
             assert!(matches!(
                 res,
-                Err(InvalidMempoolUserOperationError::Simulation(SimulationError::Opcode { entity, opcode })) if entity==FACTORY && opcode == "COINBASE"
+                Err(InvalidMempoolUserOperationError::Simulation(SimulationError::Opcode { entity, opcode, .. })) if entity==FACTORY && opcode == "COINBASE"
             ));
 
             Ok(())
@@ -441,7 +616,7 @@ macro_rules! fail_with_bad_opcode_in_paymaster {
             .await;
             assert!(matches!(
                 res,
-                Err(InvalidMempoolUserOperationError::Simulation(SimulationError::Opcode { entity, opcode })) if entity==PAYMASTER && opcode == "COINBASE"
+                Err(InvalidMempoolUserOperationError::Simulation(SimulationError::Opcode { entity, opcode, .. })) if entity==PAYMASTER && opcode == "COINBASE"
             ));
 
             Ok(())
@@ -449,6 +624,686 @@ macro_rules! fail_with_bad_opcode_in_paymaster {
     };
 }
 
+async fn downgraded_bad_opcode(mut c: TestContext<ClientType>) -> eyre::Result<()> {
+    let (init_code, init_func) = create_opcode_factory_init_code("coinbase".into()).await.unwrap();
+    let validator = new_canonical(
+        EntryPoint::new(c.client.clone(), c.entry_point.address),
+        Chain::from(c.chain_id),
+        U256::from(3000000_u64),
+        U256::from(1u64),
+    )
+    .with_downgraded_violations(TraceViolation::Opcode.into());
+
+    // banned opcode is only a warning, so the user operation is pooled
+    let uo = create_test_user_operation(
+        &c,
+        "".into(),
+        None,
+        init_code,
+        init_func,
+        c.opcodes_factory.address,
+    )
+    .await
+    .expect("Create test user operation failed.");
+    let uo_hash = uo.hash(&c.entry_point.address, c.chain_id);
+    validate_with(&c, &validator, uo.clone()).await.expect("succeed");
+    c.mempool.add(UserOperation::from_user_operation_signed(uo_hash, uo))?;
+    assert!(c.mempool.get(&uo_hash)?.is_some());
+
+    // storage/association violations are still hard rejections
+    let (init_code, init_func) = create_storage_factory_init_code(0, "".into()).await.unwrap();
+    let uo = create_test_user_operation(
+        &c,
+        "balance-self".into(),
+        None,
+        init_code,
+        init_func,
+        c.storage_factory.address,
+    )
+    .await
+    .expect("Create test user operation failed.");
+    let res = validate_with(&c, &validator, uo).await;
+    assert!(matches!(
+        res,
+        Err(InvalidMempoolUserOperationError::Simulation(SimulationError::Unstaked { .. }))
+    ));
+
+    Ok(())
+}
+
+async fn accept_full_signature_hash(c: TestContext<ClientType>) -> eyre::Result<()> {
+    let (init_code, init_func) = create_opcode_factory_init_code("".into()).await.unwrap();
+    let validator = new_canonical(
+        EntryPoint::new(c.client.clone(), c.entry_point.address),
+        Chain::from(c.chain_id),
+        U256::from(3000000_u64),
+        U256::from(1u64),
+    )
+    .with_signature_hash_verification(true);
+
+    let uo = create_test_user_operation(
+        &c,
+        "".into(),
+        None,
+        init_code,
+        init_func,
+        c.opcodes_factory.address,
+    )
+    .await
+    .expect("Create test user operation failed.");
+    validate_with(&c, &validator, uo).await.expect("succeed");
+
+    Ok(())
+}
+
+async fn accept_eip7702_delegated_sender(c: TestContext<ClientType>) -> eyre::Result<()> {
+    let eip7702_validator = || {
+        new_canonical(
+            EntryPoint::new(c.client.clone(), c.entry_point.address),
+            Chain::from(c.chain_id),
+            U256::from(3000000_u64),
+            U256::from(1u64),
+        )
+        .with_eip7702_senders(true)
+    };
+    let validator = eip7702_validator();
+
+    // EOA delegated to the opcodes account
+    let authority = LocalWallet::new(&mut thread_rng()).with_chain_id(c.chain_id);
+    delegate_eoa(&c.client, &authority, c.paymaster.address, c.chain_id).await?;
+
+    let uo = UserOperationSigned {
+        sender: authority.address(),
+        nonce: U256::zero(),
+        init_code: [
+            Address::from_low_u64_be(EIP7702_INIT_CODE_MARKER).as_bytes(),
+            c.paymaster.address.as_bytes(),
+        ]
+        .concat()
+        .into(),
+        call_data: Bytes::default(),
+        call_gas_limit: 1000000.into(),
+        verification_gas_limit: 1000000.into(),
+        pre_verification_gas: 50000.into(),
+        max_fee_per_gas: U256::zero(),
+        max_priority_fee_per_gas: U256::zero(),
+        paymaster_and_data: Bytes::default(),
+        signature: Bytes::default(),
+    };
+
+    // the entry point can't apply the authorization
+    let res = validate_with(&c, &validator, uo.clone()).await;
+    assert!(matches!(
+        res,
+        Err(InvalidMempoolUserOperationError::Simulation(SimulationError::Validation { inner }))
+            if inner.contains("AA10")
+    ));
+
+    // the authorization is dropped before hashing (as on RPC ingress)
+    let (mempool, reputation) = setup_memory_mempool_reputation();
+    let uopool = UoPool::new(
+        UoPoolMode::Standard,
+        EntryPoint::new(c.client.clone(), c.entry_point.address),
+        eip7702_validator(),
+        mempool,
+        reputation,
+        U256::from(3000000_u64),
+        Chain::from(c.chain_id),
+        None,
+    );
+    let uo = uopool.normalize_user_operation(UserOperation::from_user_operation_signed(
+        uo.hash(&c.entry_point.address, c.chain_id),
+        uo,
+    ));
+    assert!(uo.init_code.is_empty());
+    assert_eq!(uo.hash, uo.user_operation.hash(&c.entry_point.address, c.chain_id));
+
+    let res = validate_with(&c, &validator, uo.user_operation).await;
+    assert!(res.is_ok(), "{res:?}");
+
+    Ok(())
+}
+
+async fn accept_zero_priority_fee_if_subsidized(c: TestContext<ClientType>) -> eyre::Result<()> {
+    let (init_code, init_func) = create_opcode_factory_init_code("".into()).await.unwrap();
+    let validator = new_canonical(
+        EntryPoint::new(c.client.clone(), c.entry_point.address),
+        Chain::from(c.chain_id),
+        U256::from(3000000_u64),
+        U256::from(1u64),
+    );
+
+    let mut uo = create_test_user_operation(
+        &c,
+        "".into(),
+        None,
+        init_code,
+        init_func,
+        c.opcodes_factory.address,
+    )
+    .await
+    .expect("Create test user operation failed.");
+    uo.max_priority_fee_per_gas = U256::zero();
+    let uo =
+        UserOperation::from_user_operation_signed(uo.hash(&c.entry_point.address, c.chain_id), uo);
+
+    let res = validator
+        .validate_user_operation(
+            &uo,
+            &c.mempool,
+            &c.reputation,
+            None,
+            UserOperationValidatorMode::Sanity.into(),
+        )
+        .await;
+    assert!(matches!(
+        res,
+        Err(InvalidMempoolUserOperationError::Sanity(
+            SanityError::MaxPriorityFeePerGasTooLow { .. }
+        ))
+    ));
+
+    let validator = validator.with_subsidized(true);
+    let res = validator
+        .validate_user_operation(
+            &uo,
+            &c.mempool,
+            &c.reputation,
+            None,
+            UserOperationValidatorMode::Sanity.into(),
+        )
+        .await;
+    assert!(res.is_ok());
+
+    // max fee below the base fee is accepted as well
+    let mut uo = uo.user_operation.clone();
+    uo.max_fee_per_gas = U256::zero();
+    let uo =
+        UserOperation::from_user_operation_signed(uo.hash(&c.entry_point.address, c.chain_id), uo);
+    let res = validator
+        .validate_user_operation(
+            &uo,
+            &c.mempool,
+            &c.reputation,
+            None,
+            UserOperationValidatorMode::Sanity.into(),
+        )
+        .await;
+    assert!(res.is_ok());
+
+    Ok(())
+}
+
+async fn enforce_min_priority_fee_per_entry_point(c: TestContext<ClientType>) -> eyre::Result<()> {
+    let (init_code, init_func) = create_opcode_factory_init_code("".into()).await.unwrap();
+    let validator = new_canonical(
+        EntryPoint::new(c.client.clone(), c.entry_point.address),
+        Chain::from(c.chain_id),
+        U256::from(3000000_u64),
+        U256::from(100u64),
+    );
+    // mempools of two entry points with different floors
+    let (ep_low, ep_high) = (c.entry_point.address, Address::random());
+    let validator = validator
+        .with_ep_min_priority_fees(HashMap::from([(ep_low, 1.into()), (ep_high, 10.into())]));
+    let mempool_low = c.mempool.clone().with_entry_point(ep_low);
+    let mempool_high = c.mempool.clone().with_entry_point(ep_high);
+
+    let mut uo = create_test_user_operation(
+        &c,
+        "".into(),
+        None,
+        init_code,
+        init_func,
+        c.opcodes_factory.address,
+    )
+    .await
+    .expect("Create test user operation failed.");
+    uo.max_fee_per_gas = U256::from(5);
+    uo.max_priority_fee_per_gas = U256::from(5);
+    let uo =
+        UserOperation::from_user_operation_signed(uo.hash(&c.entry_point.address, c.chain_id), uo);
+
+    let res = validator
+        .validate_user_operation(
+            &uo,
+            &mempool_low,
+            &c.reputation,
+            None,
+            UserOperationValidatorMode::Sanity.into(),
+        )
+        .await;
+    assert!(res.is_ok());
+
+    let res = validator
+        .validate_user_operation(
+            &uo,
+            &mempool_high,
+            &c.reputation,
+            None,
+            UserOperationValidatorMode::Sanity.into(),
+        )
+        .await;
+    assert!(matches!(
+        res,
+        Err(InvalidMempoolUserOperationError::Sanity(
+            SanityError::MaxPriorityFeePerGasTooLow {
+                max_priority_fee_per_gas_expected,
+                ..
+            }
+        )) if max_priority_fee_per_gas_expected == U256::from(10)
+    ));
+
+    // without an override, the default floor of the sanity check applies
+    let res = validator
+        .validate_user_operation(
+            &uo,
+            &c.mempool,
+            &c.reputation,
+            None,
+            UserOperationValidatorMode::Sanity.into(),
+        )
+        .await;
+    assert!(matches!(
+        res,
+        Err(InvalidMempoolUserOperationError::Sanity(
+            SanityError::MaxPriorityFeePerGasTooLow {
+                max_priority_fee_per_gas_expected,
+                ..
+            }
+        )) if max_priority_fee_per_gas_expected == U256::from(100)
+    ));
+
+    Ok(())
+}
+
+async fn reject_above_max_simulation_gas(c: TestContext<ClientType>) -> eyre::Result<()> {
+    let (init_code, init_func) = create_opcode_factory_init_code("".into()).await.unwrap();
+    let validator = new_canonical(
+        EntryPoint::new(c.client.clone(), c.entry_point.address),
+        Chain::from(c.chain_id),
+        U256::from(3000000_u64),
+        U256::from(1u64),
+    )
+    .with_max_simulation_gas(Some(U256::from(3000000_u64)));
+
+    let uo = create_test_user_operation(
+        &c,
+        "".into(),
+        None,
+        init_code,
+        init_func,
+        c.opcodes_factory.address,
+    )
+    .await
+    .expect("Create test user operation failed.");
+    validate_with(&c, &validator, uo.clone()).await.expect("succeed");
+
+    // gas fields add up to more than the simulation cap
+    let uo = UserOperationSigned { call_gas_limit: 5000000.into(), ..uo };
+    let res = validate_with(&c, &validator, uo).await;
+    assert!(matches!(
+        res,
+        Err(InvalidMempoolUserOperationError::Sanity(SanityError::SimulationGasTooHigh { .. }))
+    ));
+
+    Ok(())
+}
+
+async fn reject_call_data_selector_not_allowed(c: TestContext<ClientType>) -> eyre::Result<()> {
+    let (init_code, init_func) = create_opcode_factory_init_code("".into()).await.unwrap();
+    let transfer = id("transfer(address,uint256)");
+    let approve = id("approve(address,uint256)");
+    let validator = new_canonical(
+        EntryPoint::new(c.client.clone(), c.entry_point.address),
+        Chain::from(c.chain_id),
+        U256::from(3000000_u64),
+        U256::from(1u64),
+    )
+    .with_subsidized(true)
+    .with_selector_filter(SelectorFilter::new(vec![transfer, approve], vec![approve]));
+
+    let uo = create_test_user_operation(
+        &c,
+        "".into(),
+        None,
+        init_code,
+        init_func,
+        c.opcodes_factory.address,
+    )
+    .await
+    .expect("Create test user operation failed.");
+    let inner_call = |selector: [u8; 4]| {
+        Token::Bytes([selector.as_slice(), &encode(&[Token::Uint(1.into())])].concat())
+    };
+    let execute = |call: Token| -> Bytes {
+        [
+            id("execute(address,uint256,bytes)").as_slice(),
+            &encode(&[Token::Address(Address::random()), Token::Uint(0.into()), call]),
+        ]
+        .concat()
+        .into()
+    };
+    let execute_batch = |calls: Vec<Token>| -> Bytes {
+        [
+            id("executeBatch(address[],bytes[])").as_slice(),
+            &encode(&[
+                Token::Array(calls.iter().map(|_| Token::Address(Address::random())).collect()),
+                Token::Array(calls),
+            ]),
+        ]
+        .concat()
+        .into()
+    };
+
+    for (call_data, allowed) in [
+        (execute(inner_call(transfer)), true),
+        (execute_batch(vec![inner_call(transfer), Token::Bytes(vec![])]), true),
+        // not in the allowlist
+        (execute(inner_call(id("mint(uint256)"))), false),
+        // in the denylist
+        (execute_batch(vec![inner_call(transfer), inner_call(approve)]), false),
+    ] {
+        let uo =
+            UserOperationSigned { call_data, pre_verification_gas: 100000.into(), ..uo.clone() };
+        let res = validator
+            .validate_user_operation(
+                &UserOperation::from_user_operation_signed(
+                    uo.hash(&c.entry_point.address, c.chain_id),
+                    uo,
+                ),
+                &c.mempool,
+                &c.reputation,
+                None,
+                UserOperationValidatorMode::Sanity.into(),
+            )
+            .await;
+        if allowed {
+            assert!(res.is_ok());
+        } else {
+            assert!(matches!(
+                res,
+                Err(InvalidMempoolUserOperationError::Sanity(SanityError::CallData { .. }))
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+async fn reject_unstaked_paymaster_if_not_whitelisted(
+    c: TestContext<ClientType>,
+) -> eyre::Result<()> {
+    let (init_code, init_func) = create_opcode_factory_init_code("".into()).await.unwrap();
+    let validator = new_canonical(
+        EntryPoint::new(c.client.clone(), c.entry_point.address),
+        Chain::from(c.chain_id),
+        U256::from(3000000_u64),
+        U256::from(1u64),
+    )
+    .with_subsidized(true)
+    .with_staked_paymaster_required(true);
+
+    // paymaster with a deposit, but without a stake
+    let paymaster = deploy_test_opcode_account(c.client.clone()).await?;
+    c.entry_point
+        .contract()
+        .deposit_to(paymaster.address)
+        .value(parse_units("0.1", "ether").unwrap())
+        .send()
+        .await?
+        .await?;
+
+    let uo = create_test_user_operation(
+        &c,
+        "".into(),
+        None,
+        init_code,
+        init_func,
+        c.opcodes_factory.address,
+    )
+    .await
+    .expect("Create test user operation failed.");
+    let uo = UserOperationSigned {
+        paymaster_and_data: paymaster.address.as_bytes().to_vec().into(),
+        ..uo
+    };
+    let uo =
+        UserOperation::from_user_operation_signed(uo.hash(&c.entry_point.address, c.chain_id), uo);
+
+    let res = validator
+        .validate_user_operation(
+            &uo,
+            &c.mempool,
+            &c.reputation,
+            None,
+            UserOperationValidatorMode::Sanity.into(),
+        )
+        .await;
+    assert!(matches!(
+        res,
+        Err(InvalidMempoolUserOperationError::Sanity(SanityError::Paymaster { .. }))
+    ));
+
+    // whitelisted paymasters don't have to be staked
+    let mut reputation = c.reputation.clone();
+    reputation.add_whitelist(&paymaster.address);
+    let res = validator
+        .validate_user_operation(
+            &uo,
+            &c.mempool,
+            &reputation,
+            None,
+            UserOperationValidatorMode::Sanity.into(),
+        )
+        .await;
+    assert!(res.is_ok());
+
+    Ok(())
+}
+
+async fn skip_prev_checks_if_ignore_prev(mut c: TestContext<ClientType>) -> eyre::Result<()> {
+    let (init_code, init_func) = create_opcode_factory_init_code("".into()).await.unwrap();
+    let validator = new_canonical(
+        EntryPoint::new(c.client.clone(), c.entry_point.address),
+        Chain::from(c.chain_id),
+        U256::from(3000000_u64),
+        U256::from(1u64),
+    )
+    .with_subsidized(true);
+
+    let uo = create_test_user_operation(
+        &c,
+        "".into(),
+        None,
+        init_code,
+        init_func,
+        c.opcodes_factory.address,
+    )
+    .await
+    .expect("Create test user operation failed.");
+    let prev_hash = c.mempool.add(UserOperation::from_user_operation_signed(
+        uo.hash(&c.entry_point.address, c.chain_id),
+        uo.clone(),
+    ))?;
+
+    // same sender and nonce without a gas increase
+    let uo = UserOperationSigned { call_gas_limit: uo.call_gas_limit + 1, ..uo };
+    let uo =
+        UserOperation::from_user_operation_signed(uo.hash(&c.entry_point.address, c.chain_id), uo);
+
+    let res = validator
+        .validate_user_operation(
+            &uo,
+            &c.mempool,
+            &c.reputation,
+            None,
+            UserOperationValidatorMode::Sanity.into(),
+        )
+        .await;
+    assert!(matches!(
+        res,
+        Err(InvalidMempoolUserOperationError::Sanity(SanityError::Sender { .. }))
+    ));
+
+    // re-validation for another canonical mempool skips the gas increase check, but still
+    // replaces the user operation with the same sender and nonce (both aren't kept)
+    let val_config = ValidationConfig::builder().topic("topic").ignore_prev(true).build().unwrap();
+    let res = validator
+        .validate_user_operation(
+            &uo,
+            &c.mempool,
+            &c.reputation,
+            Some(val_config),
+            UserOperationValidatorMode::Sanity.into(),
+        )
+        .await
+        .expect("succeed");
+    assert_eq!(res.prev_hash, Some(prev_hash));
+
+    Ok(())
+}
+
+async fn reject_call_gas_below_min(c: TestContext<ClientType>) -> eyre::Result<()> {
+    let (init_code, init_func) = create_opcode_factory_init_code("".into()).await.unwrap();
+    let validator = new_canonical(
+        EntryPoint::new(c.client.clone(), c.entry_point.address),
+        Chain::from(c.chain_id),
+        U256::from(3000000_u64),
+        U256::from(1u64),
+    )
+    .with_subsidized(true)
+    .with_min_call_gas(U256::from(21000));
+
+    let uo = create_test_user_operation(
+        &c,
+        "".into(),
+        None,
+        init_code,
+        init_func,
+        c.opcodes_factory.address,
+    )
+    .await
+    .expect("Create test user operation failed.");
+
+    let uo = UserOperationSigned { call_gas_limit: U256::from(20999), ..uo };
+    let res = validator
+        .validate_user_operation(
+            &UserOperation::from_user_operation_signed(
+                uo.hash(&c.entry_point.address, c.chain_id),
+                uo.clone(),
+            ),
+            &c.mempool,
+            &c.reputation,
+            None,
+            UserOperationValidatorMode::Sanity.into(),
+        )
+        .await;
+    assert!(matches!(
+        res,
+        Err(InvalidMempoolUserOperationError::Sanity(SanityError::CallGasLimitTooLow {
+            call_gas_limit,
+            call_gas_limit_expected,
+        })) if call_gas_limit == U256::from(20999) &&
+            call_gas_limit_expected == U256::from(21000)
+    ));
+
+    let uo = UserOperationSigned { call_gas_limit: U256::from(21000), ..uo };
+    validator
+        .validate_user_operation(
+            &UserOperation::from_user_operation_signed(
+                uo.hash(&c.entry_point.address, c.chain_id),
+                uo,
+            ),
+            &c.mempool,
+            &c.reputation,
+            None,
+            UserOperationValidatorMode::Sanity.into(),
+        )
+        .await
+        .expect("succeed");
+
+    Ok(())
+}
+
+async fn reject_priority_fee_above_max_fee(c: TestContext<ClientType>) -> eyre::Result<()> {
+    let (init_code, init_func) = create_opcode_factory_init_code("".into()).await.unwrap();
+
+    let mut uo = create_test_user_operation(
+        &c,
+        "".into(),
+        None,
+        init_code,
+        init_func,
+        c.opcodes_factory.address,
+    )
+    .await
+    .expect("Create test user operation failed.");
+    uo.max_priority_fee_per_gas = uo.max_fee_per_gas + 1;
+
+    let res = c
+        .validator
+        .validate_user_operation(
+            &UserOperation::from_user_operation_signed(
+                uo.hash(&c.entry_point.address, c.chain_id),
+                uo,
+            ),
+            &c.mempool,
+            &c.reputation,
+            None,
+            UserOperationValidatorMode::Sanity.into(),
+        )
+        .await;
+    assert!(matches!(
+        res,
+        Err(InvalidMempoolUserOperationError::Sanity(
+            SanityError::MaxPriorityFeePerGasTooHigh { .. }
+        ))
+    ));
+
+    Ok(())
+}
+
+async fn reject_non_account_sender(c: TestContext<ClientType>) -> eyre::Result<()> {
+    let (init_code, init_func) = create_opcode_factory_init_code("".into()).await.unwrap();
+
+    let mut uo = create_test_user_operation(
+        &c,
+        "".into(),
+        None,
+        init_code,
+        init_func,
+        c.opcodes_factory.address,
+    )
+    .await
+    .expect("Create test user operation failed.");
+
+    // bare EOA without code and without initCode
+    uo.sender = Address::random();
+    uo.init_code = Bytes::default();
+
+    let res = c
+        .validator
+        .validate_user_operation(
+            &UserOperation::from_user_operation_signed(
+                uo.hash(&c.entry_point.address, c.chain_id),
+                uo,
+            ),
+            &c.mempool,
+            &c.reputation,
+            None,
+            UserOperationValidatorMode::Sanity.into(),
+        )
+        .await;
+    assert!(matches!(
+        res,
+        Err(InvalidMempoolUserOperationError::Sanity(SanityError::Sender { inner }))
+            if inner.contains("is not an account")
+    ));
+
+    Ok(())
+}
+
 fail_with_bad_opcode_in_paymaster!(
     setup_database().await?,
     fail_with_bad_opcode_in_paymaster_database
@@ -475,7 +1330,7 @@ macro_rules! fail_with_bad_opcode_in_validation {
             .await;
             assert!(matches!(
                 res,
-                Err(InvalidMempoolUserOperationError::Simulation(SimulationError::Opcode { entity, opcode })) if entity==SENDER && opcode == "BLOCKHASH"
+                Err(InvalidMempoolUserOperationError::Simulation(SimulationError::Opcode { entity, opcode, .. })) if entity==SENDER && opcode == "BLOCKHASH"
             ));
 
             Ok(())
@@ -512,7 +1367,7 @@ macro_rules!fail_if_create_too_many {
             .await;
             assert!(matches!(
                 res,
-                Err(InvalidMempoolUserOperationError::Simulation(SimulationError::Opcode { entity, opcode })) if entity==SENDER && opcode == "CREATE2"
+                Err(InvalidMempoolUserOperationError::Simulation(SimulationError::Opcode { entity, opcode, .. })) if entity==SENDER && opcode == "CREATE2"
             ));
 
             Ok(())
@@ -769,3 +1624,20 @@ macro_rules! fail_with_inner_oog_revert {
 
 fail_with_inner_oog_revert!(setup_database().await?, fail_with_inner_oog_revert_database);
 fail_with_inner_oog_revert!(setup_memory().await?, fail_with_inner_oog_revert_memory);
+
+storage_tests!(
+    simulate_only_keeps_reputation,
+    record_validation_phase_durations,
+    downgraded_bad_opcode,
+    accept_full_signature_hash,
+    accept_zero_priority_fee_if_subsidized,
+    accept_eip7702_delegated_sender,
+    enforce_min_priority_fee_per_entry_point,
+    reject_above_max_simulation_gas,
+    reject_call_data_selector_not_allowed,
+    reject_unstaked_paymaster_if_not_whitelisted,
+    skip_prev_checks_if_ignore_prev,
+    reject_call_gas_below_min,
+    reject_priority_fee_above_max_fee,
+    reject_non_account_sender,
+);